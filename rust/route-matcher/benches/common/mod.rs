@@ -0,0 +1,86 @@
+//! Synthetic track generators shared by the benchmarks.
+//!
+//! Everything is deterministic (a small LCG instead of a `rand` dependency),
+//! so runs are comparable across machines and commits.
+
+#![allow(dead_code)]
+
+use route_matcher::{GpsPoint, MatchConfig, RouteSignature};
+
+/// Minimal linear congruential generator for reproducible noise
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407))
+    }
+
+    /// Uniform value in [-1, 1)
+    pub fn next_signed(&mut self) -> f64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 11) as f64 / (1u64 << 53) as f64) * 2.0 - 1.0
+    }
+}
+
+/// A wandering route of `point_count` points (~10m apart) starting at `origin`.
+pub fn synthetic_route(origin: GpsPoint, point_count: usize, seed: u64) -> Vec<GpsPoint> {
+    let mut rng = Lcg::new(seed);
+    let mut heading: f64 = rng.next_signed() * std::f64::consts::PI;
+    let mut point = origin;
+    let step_deg = 10.0 / 111_320.0;
+    let cos_lat = origin.latitude.to_radians().cos();
+
+    (0..point_count)
+        .map(|_| {
+            let current = point;
+            heading += rng.next_signed() * 0.2;
+            point = GpsPoint::new(
+                point.latitude + heading.cos() * step_deg,
+                point.longitude + heading.sin() * step_deg / cos_lat,
+            );
+            current
+        })
+        .collect()
+}
+
+/// Add up to `meters` of GPS jitter to every point.
+pub fn with_noise(route: &[GpsPoint], meters: f64, seed: u64) -> Vec<GpsPoint> {
+    let mut rng = Lcg::new(seed);
+    let deg = meters / 111_320.0;
+    route
+        .iter()
+        .map(|p| GpsPoint::new(p.latitude + rng.next_signed() * deg, p.longitude + rng.next_signed() * deg))
+        .collect()
+}
+
+/// Origin of base route `index`, spread over a ~50km x 50km area so that
+/// only repeats of the same base route overlap.
+fn base_origin(index: usize) -> GpsPoint {
+    let row = (index / 40) as f64;
+    let col = (index % 40) as f64;
+    GpsPoint::new(47.0 + row * 0.012, 8.0 + col * 0.018)
+}
+
+/// `route_count` full-resolution tracks: repeats (with noise) of
+/// `route_count / repeats` distinct base routes of `point_count` points each.
+pub fn synthetic_tracks(route_count: usize, repeats: usize, point_count: usize) -> Vec<(String, Vec<GpsPoint>)> {
+    let base_count = (route_count / repeats.max(1)).max(1);
+    let bases: Vec<Vec<GpsPoint>> = (0..base_count)
+        .map(|i| synthetic_route(base_origin(i), point_count, i as u64))
+        .collect();
+
+    (0..route_count)
+        .map(|i| {
+            let base = &bases[i % base_count];
+            (format!("activity-{}", i), with_noise(base, 5.0, 10_000 + i as u64))
+        })
+        .collect()
+}
+
+/// Signatures for [`synthetic_tracks`].
+pub fn synthetic_signatures(route_count: usize, repeats: usize, config: &MatchConfig) -> Vec<RouteSignature> {
+    synthetic_tracks(route_count, repeats, 300)
+        .iter()
+        .filter_map(|(id, points)| RouteSignature::from_points(id, points, config))
+        .collect()
+}
@@ -0,0 +1,25 @@
+//! Benchmarks for the distance models: cost of a 10k-point polyline length
+//! with each formula. Accuracy is documented on `geo_utils::DistanceModel`.
+//!
+//! Run with: cargo bench --bench distance
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use route_matcher::{DistanceModel, GpsPoint};
+
+mod common;
+
+fn bench_polyline_length(c: &mut Criterion) {
+    let points = common::synthetic_route(GpsPoint::new(47.37, 8.54), 10_000, 1);
+    let mut group = c.benchmark_group("polyline_length");
+
+    for model in [DistanceModel::Equirectangular, DistanceModel::Haversine, DistanceModel::Geodesic] {
+        group.bench_with_input(BenchmarkId::new(format!("{:?}", model), points.len()), &points, |b, points| {
+            b.iter(|| model.polyline_length(black_box(points)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_polyline_length);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! Benchmarks for the route matching hot paths: signature creation,
+//! pairwise comparison, and grouping.
+//!
+//! Run with: cargo bench --bench matching
+//! (add `--features parallel` to include parallel grouping)
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use route_matcher::{compare_routes, group_signatures, GpsPoint, MatchConfig, RouteSignature};
+
+mod common;
+
+fn bench_signature_creation(c: &mut Criterion) {
+    let config = MatchConfig::default();
+    let mut group = c.benchmark_group("signature_creation");
+
+    for &point_count in &[1_000usize, 10_000, 100_000] {
+        let points = common::synthetic_route(GpsPoint::new(47.37, 8.54), point_count, 1);
+
+        group.bench_with_input(BenchmarkId::new("from_points", point_count), &points, |b, points| {
+            b.iter(|| RouteSignature::from_points("bench", black_box(points), &config))
+        });
+        group.bench_with_input(BenchmarkId::new("from_point_iter", point_count), &points, |b, points| {
+            b.iter(|| RouteSignature::from_point_iter("bench", black_box(points).iter().copied(), &config))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_compare_routes(c: &mut Criterion) {
+    let base = common::synthetic_route(GpsPoint::new(47.37, 8.54), 2_000, 2);
+    let mut group = c.benchmark_group("compare_routes");
+
+    for &resample_count in &[50u32, 200, 500] {
+        let config = MatchConfig { resample_count, ..MatchConfig::default() };
+        let sig1 = RouteSignature::from_points("a", &base, &config).unwrap();
+        let sig2 = RouteSignature::from_points("b", &common::with_noise(&base, 8.0, 3), &config).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("resample", resample_count), &config, |b, config| {
+            b.iter(|| compare_routes(black_box(&sig1), black_box(&sig2), config))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_grouping(c: &mut Criterion) {
+    let config = MatchConfig::default();
+    let mut group = c.benchmark_group("grouping");
+    group.sample_size(10);
+
+    for &route_count in &[1_000usize, 10_000] {
+        let signatures = common::synthetic_signatures(route_count, 10, &config);
+
+        group.bench_with_input(BenchmarkId::new("sequential", route_count), &signatures, |b, sigs| {
+            b.iter(|| group_signatures(black_box(sigs), &config))
+        });
+
+        #[cfg(feature = "parallel")]
+        group.bench_with_input(BenchmarkId::new("parallel", route_count), &signatures, |b, sigs| {
+            b.iter(|| route_matcher::group_signatures_parallel(black_box(sigs), &config))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_signature_creation, bench_compare_routes, bench_grouping);
+criterion_main!(benches);
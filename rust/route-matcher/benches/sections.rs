@@ -0,0 +1,35 @@
+//! Benchmarks for frequent section detection on full-resolution tracks.
+//!
+//! Run with: cargo bench --bench sections
+
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use route_matcher::{detect_sections_from_tracks, RouteGroup, SectionConfig};
+
+mod common;
+
+fn bench_section_detection(c: &mut Criterion) {
+    let config = SectionConfig::default();
+    let mut group = c.benchmark_group("section_detection");
+    group.sample_size(10);
+
+    for &track_count in &[20usize, 100] {
+        // 5 repeats of each base route, 1000 points (~10km) per track
+        let tracks = common::synthetic_tracks(track_count, 5, 1_000);
+        let sport_types: HashMap<String, String> = tracks
+            .iter()
+            .map(|(id, _)| (id.clone(), "Run".to_string()))
+            .collect();
+        let groups: Vec<RouteGroup> = vec![];
+
+        group.bench_with_input(BenchmarkId::new("from_tracks", track_count), &tracks, |b, tracks| {
+            b.iter(|| detect_sections_from_tracks(black_box(tracks), &sport_types, &groups, &config))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_section_detection);
+criterion_main!(benches);
@@ -0,0 +1,5 @@
+fn main() {
+    // Node.js addons resolve N-API symbols from the host process at load time
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
@@ -22,16 +22,9 @@ const DISPATCH_INTERVAL_MS: u64 = 80;  // Same as v6-sustained
 
 #[derive(Debug, serde::Deserialize)]
 struct MapApiResponse {
-    bounds: Option<ApiBounds>,
     latlngs: Option<Vec<Option<[f64; 2]>>>,
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct ApiBounds {
-    ne: [f64; 2],
-    sw: [f64; 2],
-}
-
 #[derive(Debug)]
 struct TimingResult {
     activity_id: String,
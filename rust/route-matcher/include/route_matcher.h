@@ -0,0 +1,101 @@
+#ifndef ROUTE_MATCHER_H
+#define ROUTE_MATCHER_H
+
+/* Generated by cbindgen from src/capi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A null pointer or malformed track buffer was passed.
+#define RM_ERROR_INVALID_ARGUMENT -1
+
+// The call panicked; no output was written.
+#define RM_ERROR_INTERNAL -2
+
+// Group assigned to tracks too short to produce a signature.
+#define RM_NO_GROUP UINT32_MAX
+
+// Route matching parameters (see `MatchConfig` for meanings and defaults).
+typedef struct RmMatchConfig {
+  double perfect_threshold;
+  double zero_threshold;
+  double min_match_percentage;
+  double min_route_distance;
+  double max_distance_diff_ratio;
+  double endpoint_threshold;
+  uint32_t resample_count;
+  double simplification_tolerance;
+  uint32_t max_simplified_points;
+} RmMatchConfig;
+
+// A non-empty heatmap cell.
+typedef struct RmHeatmapCell {
+  double center_lat;
+  double center_lng;
+  // Normalized density (0.0-1.0)
+  float density;
+  uint32_t visit_count;
+  uint32_t unique_route_count;
+} RmHeatmapCell;
+
+// Heatmap owned by the library; release with `rm_heatmap_free`.
+typedef struct RmHeatmap {
+  struct RmHeatmapCell *cells;
+  size_t cell_count;
+  double cell_size_meters;
+  float max_density;
+  uint32_t total_routes;
+  uint32_t total_activities;
+} RmHeatmap;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Default route matching parameters.
+struct RmMatchConfig rm_default_match_config(void);
+
+// Group tracks that follow the same route.
+//
+// Writes each track's group number (`0..group_count`) to `out_groups`, or
+// `RM_NO_GROUP` for tracks too short to match. `config` may be null for
+// defaults. Returns the number of groups, or a negative `RM_ERROR_*` code.
+//
+// # Safety
+// `offsets` must point to `track_count + 1` values, `coords` to
+// `2 * offsets[track_count]` values and `out_groups` to `track_count` values.
+int32_t rm_group_flat_tracks(const double *coords,
+                             const uint32_t *offsets,
+                             size_t track_count,
+                             const struct RmMatchConfig *config,
+                             uint32_t *out_groups);
+
+// Generate a heatmap from full-resolution tracks.
+//
+// `groups` (nullable) holds each track's group from `rm_group_flat_tracks`,
+// used to count routes per cell. Returns null on error.
+//
+// # Safety
+// `offsets` must point to `track_count + 1` values, `coords` to
+// `2 * offsets[track_count]` values and `groups`, if not null, to
+// `track_count` values. Free the result with `rm_heatmap_free`.
+struct RmHeatmap *rm_heatmap_from_flat_tracks(const double *coords,
+                                              const uint32_t *offsets,
+                                              size_t track_count,
+                                              const uint32_t *groups,
+                                              double cell_size_meters);
+
+// Free a heatmap returned by `rm_heatmap_from_flat_tracks`. Null is ignored.
+//
+// # Safety
+// `heatmap` must come from `rm_heatmap_from_flat_tracks` and not be freed twice.
+void rm_heatmap_free(struct RmHeatmap *heatmap);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ROUTE_MATCHER_H */
@@ -0,0 +1,17 @@
+#!/bin/bash
+set -e
+
+# Generate the C header for the `capi` feature
+# Prerequisites:
+#   - cbindgen: cargo install cbindgen
+
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+PROJECT_DIR="$(dirname "$SCRIPT_DIR")"
+HEADER="${PROJECT_DIR}/include/route_matcher.h"
+
+cd "$PROJECT_DIR"
+
+mkdir -p "$(dirname "$HEADER")"
+cbindgen --config cbindgen.toml --output "$HEADER" src/capi.rs
+
+echo "Generated $HEADER"
@@ -0,0 +1,366 @@
+//! # Activity Analytics
+//!
+//! Common cycling/running statistics computed from activity distances and
+//! dates, so apps don't need to re-implement them on each platform:
+//!
+//! - **Eddington number**: the largest `E` such that on `E` different days
+//!   the user covered at least `E` kilometers (or miles)
+//! - **Distance per route group**: cumulative distance, count and date range
+//!   for each [`RouteGroup`]
+//! - **Section traversals over time**: how often each [`FrequentSection`] was
+//!   ridden per day/week/month/year
+//!
+//! Dates are Unix timestamps (seconds). Days are calendar days after applying
+//! `utc_offset_seconds`, so an evening ride isn't counted on the next UTC day.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::analytics::{ActivitySummary, DistanceUnit, eddington_number};
+//!
+//! let day = 86_400;
+//! let activities: Vec<ActivitySummary> = (0..5)
+//!     .map(|i| ActivitySummary::new(&format!("a{}", i), 4_500.0, i * day))
+//!     .collect();
+//!
+//! // Five days of 4.5km: E = 4 (four days of at least 4km)
+//! let result = eddington_number(&activities, DistanceUnit::Kilometers, 0);
+//! assert_eq!(result.number, 4);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use crate::{FrequentSection, RouteGroup};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Distance and date of one activity.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ActivitySummary {
+    pub activity_id: String,
+    /// Activity distance in meters
+    pub distance_meters: f64,
+    /// Start time (Unix timestamp, seconds)
+    pub timestamp: i64,
+}
+
+impl ActivitySummary {
+    pub fn new(activity_id: &str, distance_meters: f64, timestamp: i64) -> Self {
+        Self {
+            activity_id: activity_id.to_string(),
+            distance_meters,
+            timestamp,
+        }
+    }
+}
+
+/// Unit for the Eddington number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum DistanceUnit {
+    Kilometers,
+    Miles,
+}
+
+impl DistanceUnit {
+    fn meters(self) -> f64 {
+        match self {
+            DistanceUnit::Kilometers => 1_000.0,
+            DistanceUnit::Miles => 1_609.344,
+        }
+    }
+}
+
+/// Eddington number with progress towards the next one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct EddingtonResult {
+    /// Largest E with E days of at least E units
+    pub number: u32,
+    pub unit: DistanceUnit,
+    /// Days already at or above E + 1 units
+    pub days_at_next: u32,
+    /// Further days of E + 1 units needed to reach E + 1
+    pub days_needed_for_next: u32,
+}
+
+/// Time bucket size for traversal histories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum TimeBucket {
+    Day,
+    /// ISO weeks (starting Monday)
+    Week,
+    Month,
+    Year,
+}
+
+/// Cumulative statistics for one route group.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct GroupDistanceStats {
+    pub group_id: String,
+    /// Activities of the group with a known summary
+    pub activity_count: u32,
+    /// Sum of activity distances in meters
+    pub total_distance_meters: f64,
+    /// Earliest and latest activity (Unix timestamps)
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+}
+
+/// Traversal count in one time bucket.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct TraversalBucket {
+    /// Start of the bucket (Unix timestamp, local midnight)
+    pub start: i64,
+    pub count: u32,
+}
+
+/// Traversals of one section over time.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SectionTraversalHistory {
+    pub section_id: String,
+    /// Non-empty buckets in chronological order
+    pub buckets: Vec<TraversalBucket>,
+    /// Total traversals with a known date
+    pub total: u32,
+}
+
+/// Compute the Eddington number from activities.
+///
+/// Distances of activities on the same local calendar day are summed.
+pub fn eddington_number(activities: &[ActivitySummary], unit: DistanceUnit, utc_offset_seconds: i64) -> EddingtonResult {
+    let mut per_day: HashMap<i64, f64> = HashMap::new();
+    for activity in activities {
+        *per_day.entry(local_day(activity.timestamp, utc_offset_seconds)).or_insert(0.0) += activity.distance_meters;
+    }
+
+    // Distances in units, longest first
+    let mut days: Vec<f64> = per_day.values().map(|d| d / unit.meters()).collect();
+    days.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+    // E is the number of leading days whose (1-based) rank is <= their distance
+    let number = days
+        .iter()
+        .enumerate()
+        .take_while(|(i, &d)| d >= (*i + 1) as f64)
+        .count() as u32;
+
+    let next = (number + 1) as f64;
+    let days_at_next = days.iter().filter(|&&d| d >= next).count() as u32;
+
+    EddingtonResult {
+        number,
+        unit,
+        days_at_next,
+        days_needed_for_next: (number + 1).saturating_sub(days_at_next),
+    }
+}
+
+/// Cumulative distance, count and date range per route group.
+///
+/// Activities without a summary are skipped. Output order follows `groups`.
+pub fn distance_by_group(groups: &[RouteGroup], activities: &[ActivitySummary]) -> Vec<GroupDistanceStats> {
+    let by_id: HashMap<&str, &ActivitySummary> = activities.iter().map(|a| (a.activity_id.as_str(), a)).collect();
+
+    groups
+        .iter()
+        .map(|group| {
+            let members: Vec<&ActivitySummary> = group
+                .activity_ids
+                .iter()
+                .filter_map(|id| by_id.get(id.as_str()).copied())
+                .collect();
+
+            GroupDistanceStats {
+                group_id: group.group_id.clone(),
+                activity_count: members.len() as u32,
+                total_distance_meters: members.iter().map(|a| a.distance_meters).sum(),
+                first_timestamp: members.iter().map(|a| a.timestamp).min(),
+                last_timestamp: members.iter().map(|a| a.timestamp).max(),
+            }
+        })
+        .collect()
+}
+
+/// Per-section traversal counts bucketed over time.
+///
+/// Each activity portion of a section is one traversal; portions whose
+/// activity has no summary (no date) are skipped.
+pub fn section_traversals_over_time(
+    sections: &[FrequentSection],
+    activities: &[ActivitySummary],
+    bucket: TimeBucket,
+    utc_offset_seconds: i64,
+) -> Vec<SectionTraversalHistory> {
+    let timestamps: HashMap<&str, i64> = activities.iter().map(|a| (a.activity_id.as_str(), a.timestamp)).collect();
+
+    sections
+        .iter()
+        .map(|section| {
+            let mut counts: BTreeMap<i64, u32> = BTreeMap::new();
+            for portion in &section.activity_portions {
+                if let Some(&ts) = timestamps.get(portion.activity_id.as_str()) {
+                    *counts.entry(bucket_start(ts, bucket, utc_offset_seconds)).or_insert(0) += 1;
+                }
+            }
+
+            SectionTraversalHistory {
+                section_id: section.id.clone(),
+                total: counts.values().sum(),
+                buckets: counts.into_iter().map(|(start, count)| TraversalBucket { start, count }).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Days since the epoch in local time.
+fn local_day(timestamp: i64, utc_offset_seconds: i64) -> i64 {
+    (timestamp + utc_offset_seconds).div_euclid(SECONDS_PER_DAY)
+}
+
+/// Start of the bucket containing `timestamp` (Unix timestamp of local midnight).
+fn bucket_start(timestamp: i64, bucket: TimeBucket, utc_offset_seconds: i64) -> i64 {
+    let day = local_day(timestamp, utc_offset_seconds);
+    let start_day = match bucket {
+        TimeBucket::Day => day,
+        // 1970-01-01 was a Thursday (3 days after Monday)
+        TimeBucket::Week => day - (day + 3).rem_euclid(7),
+        TimeBucket::Month => {
+            let (year, month, _) = civil_from_days(day);
+            days_from_civil(year, month, 1)
+        }
+        TimeBucket::Year => {
+            let (year, _, _) = civil_from_days(day);
+            days_from_civil(year, 1, 1)
+        }
+    };
+    start_day * SECONDS_PER_DAY - utc_offset_seconds
+}
+
+/// Gregorian (year, month, day) from days since 1970-01-01 (H. Hinnant's algorithm).
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 from a Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SectionPortion;
+
+    const DAY: i64 = SECONDS_PER_DAY;
+
+    #[test]
+    fn test_eddington_number() {
+        // Days of 10, 8, 5, 5, 3, 1 km -> E = 4
+        let activities: Vec<ActivitySummary> = [10.0, 8.0, 5.0, 5.0, 3.0, 1.0]
+            .iter()
+            .enumerate()
+            .map(|(i, km)| ActivitySummary::new(&format!("a{}", i), km * 1000.0, i as i64 * DAY))
+            .collect();
+
+        let result = eddington_number(&activities, DistanceUnit::Kilometers, 0);
+        assert_eq!(result.number, 4);
+        assert_eq!(result.days_at_next, 4); // 10, 8, 5, 5 are >= 5
+        assert_eq!(result.days_needed_for_next, 1);
+
+        // Same-day activities are summed: two 3km rides on one day count as 6km
+        let same_day = vec![
+            ActivitySummary::new("m", 3000.0, 8 * 3600),
+            ActivitySummary::new("e", 3000.0, 18 * 3600),
+        ];
+        assert_eq!(eddington_number(&same_day, DistanceUnit::Kilometers, 0).number, 1);
+        assert_eq!(eddington_number(&[], DistanceUnit::Miles, 0).number, 0);
+    }
+
+    #[test]
+    fn test_calendar_buckets() {
+        // 2024-02-29 12:00 UTC (Thursday)
+        let ts = 1_709_208_000;
+        assert_eq!(civil_from_days(local_day(ts, 0)), (2024, 2, 29));
+        assert_eq!(bucket_start(ts, TimeBucket::Month, 0), 1_706_745_600); // 2024-02-01
+        assert_eq!(bucket_start(ts, TimeBucket::Year, 0), 1_704_067_200); // 2024-01-01
+        assert_eq!(bucket_start(ts, TimeBucket::Week, 0), 1_708_905_600); // Monday 2024-02-26
+
+        // 23:30 UTC is already the next day at UTC+2
+        let late = 1_709_249_400; // 2024-02-29 23:30 UTC
+        assert_eq!(civil_from_days(local_day(late, 7200)), (2024, 3, 1));
+        assert_eq!(bucket_start(late, TimeBucket::Month, 7200), 1_709_244_000); // 2024-03-01 00:00 local
+    }
+
+    #[test]
+    fn test_group_distance_and_section_history() {
+        let activities = vec![
+            ActivitySummary::new("a", 5000.0, 1_704_153_600), // 2024-01-02
+            ActivitySummary::new("b", 7000.0, 1_706_831_999), // 2024-02-01 23:59:59
+            ActivitySummary::new("c", 9000.0, 1_706_832_000), // 2024-02-02
+        ];
+        let groups = vec![RouteGroup { group_id: "g".to_string(), activity_ids: vec!["a".to_string(), "b".to_string(), "unknown".to_string()] }];
+
+        let stats = distance_by_group(&groups, &activities);
+        assert_eq!(stats[0].activity_count, 2);
+        assert_eq!(stats[0].total_distance_meters, 12_000.0);
+        assert_eq!(stats[0].first_timestamp, Some(1_704_153_600));
+
+        let portion = |id: &str| SectionPortion {
+            activity_id: id.to_string(),
+            start_index: 0,
+            end_index: 10,
+            distance_meters: 500.0,
+            direction: "same".to_string(),
+        };
+        let section = FrequentSection {
+            id: "s".to_string(),
+            sport_type: "Ride".to_string(),
+            polyline: Vec::new(),
+            representative_activity_id: "a".to_string(),
+            activity_ids: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            activity_portions: vec![portion("a"), portion("b"), portion("c"), portion("unknown")],
+            route_ids: Vec::new(),
+            visit_count: 4,
+            distance_meters: 500.0,
+            activity_traces: HashMap::new(),
+            confidence: 1.0,
+            observation_count: 4,
+            average_spread: 0.0,
+            point_density: Vec::new(),
+            same_direction: None,
+            reverse_direction: None,
+            suggested_name: None,
+            matching_threshold: None,
+        };
+
+        let history = section_traversals_over_time(&[section], &activities, TimeBucket::Month, 0);
+        assert_eq!(history[0].total, 3);
+        assert_eq!(
+            history[0].buckets,
+            vec![
+                TraversalBucket { start: 1_704_067_200, count: 1 },
+                TraversalBucket { start: 1_706_745_600, count: 2 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! # Batch Comparison
+//!
+//! One-vs-many and many-vs-many route comparison without writing the pair
+//! generation and threading around [`crate::compare_routes`] by hand.
+//!
+//! All functions only compare pairs whose bounding boxes lie within
+//! [`crate::MatchConfig::search_tolerance_degrees`] of each other (the same
+//! pre-filter grouping uses) and run the comparisons on all cores with the
+//! `parallel` feature. Results are sparse: only pairs that match are returned.
+//!
+//! | Function | Compares | Pre-filter |
+//! |----------|----------|------------|
+//! | [`compare_one_to_many`] | One route against each candidate | Bounding box check per candidate |
+//! | [`similarity_matrix`] | Every pair of routes, once | R-tree over all routes |
+//! | [`top_k_similar`] | Every pair, keeping each route's closest matches | R-tree over all routes |
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, RouteSignature, MatchConfig, similarity_matrix};
+//!
+//! let config = MatchConfig::default();
+//! let route: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1)).collect();
+//! let elsewhere: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(48.8 + i as f64 * 0.001, 2.3)).collect();
+//!
+//! let signatures = vec![
+//!     RouteSignature::from_points("a", &route, &config).unwrap(),
+//!     RouteSignature::from_points("b", &route, &config).unwrap(),
+//!     RouteSignature::from_points("paris", &elsewhere, &config).unwrap(),
+//! ];
+//!
+//! let matches = similarity_matrix(&signatures, &config);
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!((matches[0].activity_id_1.as_str(), matches[0].activity_id_2.as_str()), ("a", "b"));
+//! ```
+
+use std::collections::HashMap;
+use rstar::{Envelope, RTree, RTreeObject};
+use crate::{compare_routes, indexable_bounds, search_envelope, MatchConfig, MatchResult, RouteSignature};
+
+/// Compare one route against each candidate.
+///
+/// Returns a result for every candidate that matches, in candidate order,
+/// with `signature` as `activity_id_1`. Candidates with the same activity ID
+/// as `signature` are skipped.
+pub fn compare_one_to_many(
+    signature: &RouteSignature,
+    candidates: &[RouteSignature],
+    config: &MatchConfig,
+) -> Vec<MatchResult> {
+    let search = search_envelope(&signature.bounds, config.search_tolerance_degrees);
+    let pairs: Vec<(&RouteSignature, &RouteSignature)> = candidates
+        .iter()
+        .filter(|c| search.intersects(&c.route_bounds().envelope()))
+        .map(|c| (signature, c))
+        .collect();
+    compare_pairs(&pairs, config)
+}
+
+/// Compare every pair of routes once.
+///
+/// Returns a result for every matching pair, with `activity_id_1` sorting
+/// before `activity_id_2`. Pairs never listed didn't match (or were too far
+/// apart to be compared).
+pub fn similarity_matrix(signatures: &[RouteSignature], config: &MatchConfig) -> Vec<MatchResult> {
+    let rtree = RTree::bulk_load(indexable_bounds(signatures));
+    let sig_map: HashMap<&str, &RouteSignature> = signatures
+        .iter()
+        .map(|s| (s.activity_id.as_str(), s))
+        .collect();
+
+    let pairs: Vec<(&RouteSignature, &RouteSignature)> = signatures
+        .iter()
+        .flat_map(|sig1| {
+            rtree
+                .locate_in_envelope_intersecting(&search_envelope(&sig1.bounds, config.search_tolerance_degrees))
+                .filter(|b| sig1.activity_id < b.activity_id)
+                .filter_map(|b| sig_map.get(b.activity_id.as_str()))
+                .map(move |sig2| (sig1, *sig2))
+        })
+        .collect();
+    compare_pairs(&pairs, config)
+}
+
+/// A route's most similar other routes, best match first.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SimilarRoutes {
+    pub activity_id: String,
+    /// Matches with this route as `activity_id_1`
+    pub neighbours: Vec<MatchResult>,
+}
+
+/// For each route, its `k` most similar other routes by match percentage.
+///
+/// Unlike grouping this ignores [`MatchConfig::min_match_percentage`], so
+/// routes that only partly overlap still count as similar ("similar rides
+/// you've done"); routes scoring 0% are left out. Returns one entry per
+/// signature, in input order, with ties broken by lower AMD.
+///
+/// # Example
+/// ```
+/// use route_matcher::{GpsPoint, RouteSignature, MatchConfig, top_k_similar};
+///
+/// let config = MatchConfig::default();
+/// let route: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1)).collect();
+/// let nearby: Vec<GpsPoint> = route.iter().map(|p| GpsPoint::new(p.latitude, p.longitude + 0.0015)).collect();
+///
+/// let signatures = vec![
+///     RouteSignature::from_points("a", &route, &config).unwrap(),
+///     RouteSignature::from_points("b", &route, &config).unwrap(),
+///     RouteSignature::from_points("nearby", &nearby, &config).unwrap(),
+/// ];
+///
+/// let similar = top_k_similar(&signatures, 1, &config);
+/// assert_eq!(similar[0].neighbours[0].activity_id_2, "b");
+/// assert_eq!(similar[2].neighbours.len(), 1);
+/// ```
+pub fn top_k_similar(signatures: &[RouteSignature], k: u32, config: &MatchConfig) -> Vec<SimilarRoutes> {
+    let relaxed = MatchConfig { min_match_percentage: 0.0, ..config.clone() };
+
+    let mut neighbours: HashMap<String, Vec<MatchResult>> = HashMap::new();
+    for result in similarity_matrix(signatures, &relaxed) {
+        if result.match_percentage <= 0.0 {
+            continue;
+        }
+        let reversed = MatchResult {
+            activity_id_1: result.activity_id_2.clone(),
+            activity_id_2: result.activity_id_1.clone(),
+            ..result.clone()
+        };
+        neighbours.entry(result.activity_id_1.clone()).or_default().push(result);
+        neighbours.entry(reversed.activity_id_1.clone()).or_default().push(reversed);
+    }
+
+    signatures
+        .iter()
+        .map(|sig| {
+            let mut list = neighbours.remove(&sig.activity_id).unwrap_or_default();
+            list.sort_by(|a, b| {
+                b.match_percentage
+                    .total_cmp(&a.match_percentage)
+                    .then(a.amd.total_cmp(&b.amd))
+                    .then_with(|| a.activity_id_2.cmp(&b.activity_id_2))
+            });
+            list.truncate(k as usize);
+            SimilarRoutes { activity_id: sig.activity_id.clone(), neighbours: list }
+        })
+        .collect()
+}
+
+/// Run [`compare_routes`] on each pair, keeping the matches in pair order.
+fn compare_pairs(pairs: &[(&RouteSignature, &RouteSignature)], config: &MatchConfig) -> Vec<MatchResult> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        pairs.par_iter().filter_map(|(a, b)| compare_routes(a, b, config)).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        pairs.iter().filter_map(|(a, b)| compare_routes(a, b, config)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GpsPoint;
+
+    fn signature(id: &str, lat: f64, lng: f64) -> RouteSignature {
+        let points: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(lat + i as f64 * 0.001, lng)).collect();
+        RouteSignature::from_points(id, &points, &MatchConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_compare_one_to_many() {
+        let config = MatchConfig::default();
+        let query = signature("q", 51.5, -0.1);
+        let candidates = [
+            signature("near", 51.5, -0.1),
+            signature("q", 51.5, -0.1),
+            signature("far", 40.7, -74.0),
+            signature("also", 51.5, -0.1),
+        ];
+
+        let results = compare_one_to_many(&query, &candidates, &config);
+        let ids: Vec<&str> = results.iter().map(|r| r.activity_id_2.as_str()).collect();
+        assert_eq!(ids, vec!["near", "also"]);
+        assert!(results.iter().all(|r| r.activity_id_1 == "q"));
+    }
+
+    #[test]
+    fn test_similarity_matrix_matches_pairwise() {
+        let config = MatchConfig::default();
+        let signatures = [
+            signature("c", 51.5, -0.1),
+            signature("a", 51.5, -0.1),
+            signature("b", 51.5, -0.1),
+            signature("x", 40.7, -74.0),
+        ];
+
+        let mut pairs: Vec<(String, String)> = similarity_matrix(&signatures, &config)
+            .into_iter()
+            .map(|r| (r.activity_id_1, r.activity_id_2))
+            .collect();
+        pairs.sort();
+        let expected: Vec<(String, String)> = [("a", "b"), ("a", "c"), ("b", "c")]
+            .iter()
+            .map(|(x, y)| (x.to_string(), y.to_string()))
+            .collect();
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn test_top_k_includes_partial_matches() {
+        let config = MatchConfig::default();
+        let shifted: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1 + 0.002)).collect();
+        let signatures = [
+            signature("a", 51.5, -0.1),
+            signature("b", 51.5, -0.1),
+            RouteSignature::from_points("shifted", &shifted, &config).unwrap(),
+            signature("far", 40.7, -74.0),
+        ];
+        // ~140m apart: similar, but below the grouping threshold
+        assert!(compare_routes(&signatures[0], &signatures[2], &config).is_none());
+
+        let similar = top_k_similar(&signatures, 5, &config);
+        assert_eq!(similar.len(), 4);
+        let ids: Vec<&str> = similar[0].neighbours.iter().map(|m| m.activity_id_2.as_str()).collect();
+        assert_eq!(ids, vec!["b", "shifted"]);
+        assert!(similar[2].neighbours.iter().all(|m| m.activity_id_1 == "shifted"));
+        assert!(similar[3].neighbours.is_empty());
+
+        assert_eq!(top_k_similar(&signatures, 1, &config)[0].neighbours.len(), 1);
+    }
+}
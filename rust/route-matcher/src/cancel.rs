@@ -0,0 +1,69 @@
+//! # Cancellation
+//!
+//! Cooperative cancellation for long-running operations (grouping, section
+//! detection, heatmap generation, HTTP fetching). The caller keeps a clone of
+//! the token and calls [`CancellationToken::cancel`], e.g. when the user
+//! navigates away; the operation checks the token between work units and
+//! returns early.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::CancellationToken;
+//!
+//! let token = CancellationToken::new();
+//! let worker = token.clone();
+//!
+//! token.cancel();
+//! assert!(worker.is_cancelled());
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared cancellation flag. Clones observe the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation. Cannot be undone.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_state() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_independent_tokens() {
+        let a = CancellationToken::new();
+        let b = CancellationToken::new();
+        a.cancel();
+        assert!(!b.is_cancelled());
+    }
+}
@@ -0,0 +1,333 @@
+//! # C API
+//!
+//! Plain `extern "C"` functions over the flat-buffer batch APIs, for desktop
+//! or game-engine apps that embed the matcher without UniFFI tooling. Enabled
+//! with the `capi` feature; the header is `include/route_matcher.h`, generated
+//! by `scripts/generate-c-header.sh` (cbindgen).
+//!
+//! Tracks are passed as one `[lat, lng, lat, lng, ...]` buffer plus
+//! `track_count + 1` point offsets: track `i` is points
+//! `offsets[i]..offsets[i + 1]`. Tracks are identified by their index.
+//!
+//! ```c
+//! #include "route_matcher.h"
+//!
+//! double coords[] = {51.50, -0.10, 51.51, -0.10, 51.50, -0.10, 51.51, -0.10};
+//! uint32_t offsets[] = {0, 2, 4};
+//! uint32_t groups[2];
+//!
+//! RmMatchConfig config = rm_default_match_config();
+//! int32_t group_count = rm_group_flat_tracks(coords, offsets, 2, &config, groups);
+//!
+//! RmHeatmap *heatmap = rm_heatmap_from_flat_tracks(coords, offsets, 2, groups, 50.0);
+//! for (size_t i = 0; i < heatmap->cell_count; i++) { /* heatmap->cells[i] */ }
+//! rm_heatmap_free(heatmap);
+//! ```
+
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use log::info;
+use crate::preprocess::split_flat_tracks;
+use crate::{ActivityHeatmapData, GpsPoint, HeatmapConfig, MatchConfig, RouteSignature};
+
+/// A null pointer or malformed track buffer was passed.
+pub const RM_ERROR_INVALID_ARGUMENT: i32 = -1;
+/// The call panicked; no output was written.
+pub const RM_ERROR_INTERNAL: i32 = -2;
+/// Group assigned to tracks too short to produce a signature.
+pub const RM_NO_GROUP: u32 = u32::MAX;
+
+/// Route matching parameters (see `MatchConfig` for meanings and defaults).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RmMatchConfig {
+    pub perfect_threshold: f64,
+    pub zero_threshold: f64,
+    pub min_match_percentage: f64,
+    pub min_route_distance: f64,
+    pub max_distance_diff_ratio: f64,
+    pub endpoint_threshold: f64,
+    pub resample_count: u32,
+    pub simplification_tolerance: f64,
+    pub max_simplified_points: u32,
+}
+
+impl From<&RmMatchConfig> for MatchConfig {
+    fn from(config: &RmMatchConfig) -> Self {
+        MatchConfig {
+            perfect_threshold: config.perfect_threshold,
+            zero_threshold: config.zero_threshold,
+            min_match_percentage: config.min_match_percentage,
+            min_route_distance: config.min_route_distance,
+            max_distance_diff_ratio: config.max_distance_diff_ratio,
+            endpoint_threshold: config.endpoint_threshold,
+            resample_count: config.resample_count,
+            simplification_tolerance: config.simplification_tolerance,
+            max_simplified_points: config.max_simplified_points,
+            ..MatchConfig::default()
+        }
+    }
+}
+
+/// A non-empty heatmap cell.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RmHeatmapCell {
+    pub center_lat: f64,
+    pub center_lng: f64,
+    /// Normalized density (0.0-1.0)
+    pub density: f32,
+    pub visit_count: u32,
+    pub unique_route_count: u32,
+}
+
+/// Heatmap owned by the library; release with `rm_heatmap_free`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RmHeatmap {
+    pub cells: *mut RmHeatmapCell,
+    pub cell_count: usize,
+    pub cell_size_meters: f64,
+    pub max_density: f32,
+    pub total_routes: u32,
+    pub total_activities: u32,
+}
+
+/// Default route matching parameters.
+#[no_mangle]
+pub extern "C" fn rm_default_match_config() -> RmMatchConfig {
+    let defaults = MatchConfig::default();
+    RmMatchConfig {
+        perfect_threshold: defaults.perfect_threshold,
+        zero_threshold: defaults.zero_threshold,
+        min_match_percentage: defaults.min_match_percentage,
+        min_route_distance: defaults.min_route_distance,
+        max_distance_diff_ratio: defaults.max_distance_diff_ratio,
+        endpoint_threshold: defaults.endpoint_threshold,
+        resample_count: defaults.resample_count,
+        simplification_tolerance: defaults.simplification_tolerance,
+        max_simplified_points: defaults.max_simplified_points,
+    }
+}
+
+/// Read `track_count` tracks from raw flat buffers.
+///
+/// # Safety
+/// `offsets` must point to `track_count + 1` values and `coords` to
+/// `2 * offsets[track_count]` values.
+unsafe fn read_tracks(coords: *const f64, offsets: *const u32, track_count: usize) -> Result<Vec<Vec<GpsPoint>>, String> {
+    if offsets.is_null() {
+        return Err("offsets is null".to_string());
+    }
+    let offsets = std::slice::from_raw_parts(offsets, track_count + 1);
+    let coord_count = offsets[track_count] as usize * 2;
+    if coords.is_null() && coord_count > 0 {
+        return Err("coords is null".to_string());
+    }
+    let coords = if coord_count == 0 { &[][..] } else { std::slice::from_raw_parts(coords, coord_count) };
+    split_flat_tracks(coords, offsets)
+}
+
+/// Group tracks that follow the same route.
+///
+/// Writes each track's group number (`0..group_count`) to `out_groups`, or
+/// `RM_NO_GROUP` for tracks too short to match. `config` may be null for
+/// defaults. Returns the number of groups, or a negative `RM_ERROR_*` code.
+///
+/// # Safety
+/// `offsets` must point to `track_count + 1` values, `coords` to
+/// `2 * offsets[track_count]` values and `out_groups` to `track_count` values.
+#[no_mangle]
+pub unsafe extern "C" fn rm_group_flat_tracks(
+    coords: *const f64,
+    offsets: *const u32,
+    track_count: usize,
+    config: *const RmMatchConfig,
+    out_groups: *mut u32,
+) -> i32 {
+    if out_groups.is_null() {
+        return RM_ERROR_INVALID_ARGUMENT;
+    }
+    let tracks = match read_tracks(coords, offsets, track_count) {
+        Ok(tracks) => tracks,
+        Err(e) => {
+            info!("[RouteMatcherRust] rm_group_flat_tracks: {}", e);
+            return RM_ERROR_INVALID_ARGUMENT;
+        }
+    };
+    let config = config.as_ref().map(MatchConfig::from).unwrap_or_default();
+    let out_groups = std::slice::from_raw_parts_mut(out_groups, track_count);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let signatures: Vec<RouteSignature> = tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, points)| RouteSignature::from_points(&i.to_string(), points, &config))
+            .collect();
+
+        #[cfg(feature = "parallel")]
+        let groups = crate::group_signatures_parallel(&signatures, &config);
+        #[cfg(not(feature = "parallel"))]
+        let groups = crate::group_signatures(&signatures, &config);
+
+        out_groups.fill(RM_NO_GROUP);
+        for (group, route) in groups.iter().enumerate() {
+            for id in &route.activity_ids {
+                if let Ok(i) = id.parse::<usize>() {
+                    out_groups[i] = group as u32;
+                }
+            }
+        }
+        groups.len() as i32
+    }));
+    result.unwrap_or(RM_ERROR_INTERNAL)
+}
+
+/// Generate a heatmap from full-resolution tracks.
+///
+/// `groups` (nullable) holds each track's group from `rm_group_flat_tracks`,
+/// used to count routes per cell. Returns null on error.
+///
+/// # Safety
+/// `offsets` must point to `track_count + 1` values, `coords` to
+/// `2 * offsets[track_count]` values and `groups`, if not null, to
+/// `track_count` values. Free the result with `rm_heatmap_free`.
+#[no_mangle]
+pub unsafe extern "C" fn rm_heatmap_from_flat_tracks(
+    coords: *const f64,
+    offsets: *const u32,
+    track_count: usize,
+    groups: *const u32,
+    cell_size_meters: f64,
+) -> *mut RmHeatmap {
+    let tracks = match read_tracks(coords, offsets, track_count) {
+        Ok(tracks) => tracks,
+        Err(e) => {
+            info!("[RouteMatcherRust] rm_heatmap_from_flat_tracks: {}", e);
+            return std::ptr::null_mut();
+        }
+    };
+    let groups = if groups.is_null() { None } else { Some(std::slice::from_raw_parts(groups, track_count)) };
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let tracks: Vec<(String, Vec<GpsPoint>)> =
+            tracks.into_iter().enumerate().map(|(i, points)| (i.to_string(), points)).collect();
+        let activity_data: HashMap<String, ActivityHeatmapData> = tracks
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| {
+                let route_id = groups.map(|g| g[i]).filter(|&g| g != RM_NO_GROUP).map(|g| g.to_string());
+                let data = ActivityHeatmapData {
+                    activity_id: id.clone(),
+                    route_id,
+                    route_name: None,
+                    timestamp: None,
+                    sport_type: None,
+                };
+                (id.clone(), data)
+            })
+            .collect();
+        let config = HeatmapConfig { cell_size_meters, ..HeatmapConfig::default() };
+        let heatmap = crate::generate_heatmap_from_tracks(&tracks, &activity_data, &config);
+
+        let cells: Box<[RmHeatmapCell]> = heatmap
+            .cells
+            .iter()
+            .map(|c| RmHeatmapCell {
+                center_lat: c.center_lat,
+                center_lng: c.center_lng,
+                density: c.density,
+                visit_count: c.visit_count,
+                unique_route_count: c.unique_route_count,
+            })
+            .collect();
+        let cell_count = cells.len();
+        Box::into_raw(Box::new(RmHeatmap {
+            cells: Box::into_raw(cells) as *mut RmHeatmapCell,
+            cell_count,
+            cell_size_meters: heatmap.cell_size_meters,
+            max_density: heatmap.max_density,
+            total_routes: heatmap.total_routes,
+            total_activities: heatmap.total_activities,
+        }))
+    }));
+    result.unwrap_or(std::ptr::null_mut())
+}
+
+/// Free a heatmap returned by `rm_heatmap_from_flat_tracks`. Null is ignored.
+///
+/// # Safety
+/// `heatmap` must come from `rm_heatmap_from_flat_tracks` and not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn rm_heatmap_free(heatmap: *mut RmHeatmap) {
+    if heatmap.is_null() {
+        return;
+    }
+    let heatmap = Box::from_raw(heatmap);
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(heatmap.cells, heatmap.cell_count)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two copies of one 1km line and a 1km line 5km away, flattened.
+    fn flat_tracks() -> (Vec<f64>, Vec<u32>) {
+        let line = |lng: f64| -> Vec<f64> { (0..=50).flat_map(|i| [51.5 + i as f64 * 0.0002, lng]).collect() };
+        let coords: Vec<f64> = [line(-0.1), line(-0.1), line(-0.03)].concat();
+        (coords, vec![0, 51, 102, 153])
+    }
+
+    #[test]
+    fn test_group_flat_tracks() {
+        let (coords, offsets) = flat_tracks();
+        let config = rm_default_match_config();
+        let mut groups = [0u32; 3];
+        let count = unsafe { rm_group_flat_tracks(coords.as_ptr(), offsets.as_ptr(), 3, &config, groups.as_mut_ptr()) };
+        assert_eq!(count, 2);
+        assert_eq!(groups[0], groups[1]);
+        assert_ne!(groups[0], groups[2]);
+
+        // Defaults when config is null; a one-point track has no group
+        let offsets = [0u32, 1, 51];
+        let mut groups = [0u32; 2];
+        let count = unsafe { rm_group_flat_tracks(coords.as_ptr(), offsets.as_ptr(), 2, std::ptr::null(), groups.as_mut_ptr()) };
+        assert_eq!(count, 1);
+        assert_eq!(groups[0], RM_NO_GROUP);
+    }
+
+    #[test]
+    fn test_heatmap_roundtrip() {
+        let (coords, offsets) = flat_tracks();
+        let groups = [0u32, 0, 1];
+        unsafe {
+            let heatmap = rm_heatmap_from_flat_tracks(coords.as_ptr(), offsets.as_ptr(), 3, groups.as_ptr(), 100.0);
+            assert!(!heatmap.is_null());
+            let cells = std::slice::from_raw_parts((*heatmap).cells, (*heatmap).cell_count);
+            assert!(!cells.is_empty());
+            assert_eq!((*heatmap).total_routes, 2);
+            assert!(cells.iter().all(|c| c.unique_route_count == 1));
+            rm_heatmap_free(heatmap);
+            rm_heatmap_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let (coords, _) = flat_tracks();
+        let mut groups = [0u32; 1];
+        // Decreasing offsets
+        let bad_offsets = [2u32, 1];
+        unsafe {
+            assert_eq!(
+                rm_group_flat_tracks(coords.as_ptr(), bad_offsets.as_ptr(), 1, std::ptr::null(), groups.as_mut_ptr()),
+                RM_ERROR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                rm_group_flat_tracks(coords.as_ptr(), std::ptr::null(), 1, std::ptr::null(), groups.as_mut_ptr()),
+                RM_ERROR_INVALID_ARGUMENT
+            );
+            assert!(rm_heatmap_from_flat_tracks(std::ptr::null(), bad_offsets.as_ptr(), 1, std::ptr::null(), 100.0).is_null());
+        }
+    }
+}
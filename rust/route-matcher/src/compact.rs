@@ -0,0 +1,259 @@
+//! # Compact Coordinates
+//!
+//! Fixed-point representations of signatures, sections and heatmap cells for
+//! memory-constrained targets. Coordinates are stored as `i32` in units of
+//! 1e-7 degrees (about 1.1 cm at the equator), halving the size of every point
+//! in memory and over FFI. That is far below the tens-of-meters thresholds used
+//! for matching, so compact signatures match exactly like the originals.
+//!
+//! Compact types keep what apps store and draw; per-activity section portions,
+//! consensus statistics and heatmap drill-down lists are dropped.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, MatchConfig, RouteSignature};
+//! use route_matcher::compact::CompactSignature;
+//!
+//! let points: Vec<GpsPoint> = (0..50)
+//!     .map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1278))
+//!     .collect();
+//! let signature = RouteSignature::from_points("run-1", &points, &MatchConfig::default()).unwrap();
+//!
+//! let compact = CompactSignature::from(&signature);
+//! let restored = compact.to_signature();
+//! assert!((restored.start_point.latitude - signature.start_point.latitude).abs() < 1e-7);
+//! ```
+
+use std::collections::HashMap;
+use crate::{Bounds, FrequentSection, GpsPoint, HeatmapCell, HeatmapResult, RouteSignature};
+
+/// Fixed-point units per degree.
+const E7: f64 = 1e7;
+
+/// A GPS point in 1e-7 degree units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactPoint {
+    pub lat_e7: i32,
+    pub lng_e7: i32,
+}
+
+impl From<GpsPoint> for CompactPoint {
+    fn from(point: GpsPoint) -> Self {
+        Self {
+            lat_e7: (point.latitude * E7).round() as i32,
+            lng_e7: (point.longitude * E7).round() as i32,
+        }
+    }
+}
+
+impl From<CompactPoint> for GpsPoint {
+    fn from(point: CompactPoint) -> Self {
+        GpsPoint::new(point.lat_e7 as f64 / E7, point.lng_e7 as f64 / E7)
+    }
+}
+
+fn compact_points(points: &[GpsPoint]) -> Vec<CompactPoint> {
+    points.iter().copied().map(CompactPoint::from).collect()
+}
+
+/// A route signature with fixed-point coordinates. Start, end, bounds and
+/// center are derived from the points when restored.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactSignature {
+    pub activity_id: String,
+    /// Simplified GPS points
+    pub points: Vec<CompactPoint>,
+    /// Total route distance in meters
+    pub total_distance: f32,
+    /// Legs bridging a recording gap (see [`RouteSignature::gap_legs`])
+    #[cfg_attr(feature = "ffi", uniffi(default = []))]
+    #[cfg_attr(feature = "persist", serde(default))]
+    pub gap_legs: Vec<u32>,
+}
+
+impl From<&RouteSignature> for CompactSignature {
+    fn from(signature: &RouteSignature) -> Self {
+        Self {
+            activity_id: signature.activity_id.clone(),
+            points: compact_points(&signature.points),
+            total_distance: signature.total_distance as f32,
+            gap_legs: signature.gap_legs.clone(),
+        }
+    }
+}
+
+impl CompactSignature {
+    /// Restore a full signature for matching and grouping.
+    pub fn to_signature(&self) -> RouteSignature {
+        let points: Vec<GpsPoint> = self.points.iter().copied().map(GpsPoint::from).collect();
+        let bounds = Bounds::from_points(&points)
+            .unwrap_or(Bounds { min_lat: 0.0, max_lat: 0.0, min_lng: 0.0, max_lng: 0.0 });
+        RouteSignature {
+            activity_id: self.activity_id.clone(),
+            start_point: points.first().copied().unwrap_or_else(|| bounds.center()),
+            end_point: points.last().copied().unwrap_or_else(|| bounds.center()),
+            points,
+            total_distance: self.total_distance as f64,
+            center: bounds.center(),
+            bounds,
+            gap_legs: self.gap_legs.clone(),
+        }
+    }
+}
+
+/// A frequent section with fixed-point polyline and traces.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactSection {
+    pub id: String,
+    pub sport_type: String,
+    /// Consensus polyline
+    pub polyline: Vec<CompactPoint>,
+    pub representative_activity_id: String,
+    pub activity_ids: Vec<String>,
+    pub visit_count: u32,
+    /// Section length in meters
+    pub distance_meters: f32,
+    /// Each activity's GPS points within proximity of the section
+    pub activity_traces: HashMap<String, Vec<CompactPoint>>,
+}
+
+impl From<&FrequentSection> for CompactSection {
+    fn from(section: &FrequentSection) -> Self {
+        Self {
+            id: section.id.clone(),
+            sport_type: section.sport_type.clone(),
+            polyline: compact_points(&section.polyline),
+            representative_activity_id: section.representative_activity_id.clone(),
+            activity_ids: section.activity_ids.clone(),
+            visit_count: section.visit_count,
+            distance_meters: section.distance_meters as f32,
+            activity_traces: section
+                .activity_traces
+                .iter()
+                .map(|(id, trace)| (id.clone(), compact_points(trace)))
+                .collect(),
+        }
+    }
+}
+
+impl CompactSection {
+    /// The consensus polyline as GPS points, for drawing.
+    pub fn polyline_points(&self) -> Vec<GpsPoint> {
+        self.polyline.iter().copied().map(GpsPoint::from).collect()
+    }
+}
+
+/// A heatmap cell for rendering, without route and activity drill-down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactHeatmapCell {
+    pub row: i32,
+    pub col: i32,
+    /// Cell center
+    pub center: CompactPoint,
+    /// Normalized density (0.0-1.0) for color mapping
+    pub density: f32,
+    pub visit_count: u32,
+    pub unique_route_count: u32,
+    pub is_common_path: bool,
+}
+
+impl From<&HeatmapCell> for CompactHeatmapCell {
+    fn from(cell: &HeatmapCell) -> Self {
+        Self {
+            row: cell.row,
+            col: cell.col,
+            center: GpsPoint::new(cell.center_lat, cell.center_lng).into(),
+            density: cell.density,
+            visit_count: cell.visit_count,
+            unique_route_count: cell.unique_route_count,
+            is_common_path: cell.is_common_path,
+        }
+    }
+}
+
+/// Compact cells of a heatmap, in the same order.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+/// use route_matcher::compact::compact_heatmap_cells;
+///
+/// let track: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1)).collect();
+/// let heatmap = generate_heatmap_from_tracks(
+///     &[("a".to_string(), track)],
+///     &HashMap::new(),
+///     &HeatmapConfig::default(),
+/// );
+///
+/// let cells = compact_heatmap_cells(&heatmap);
+/// assert_eq!(cells.len(), heatmap.cells.len());
+/// ```
+pub fn compact_heatmap_cells(heatmap: &HeatmapResult) -> Vec<CompactHeatmapCell> {
+    heatmap.cells.iter().map(CompactHeatmapCell::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compare_routes, MatchConfig};
+
+    #[test]
+    fn test_point_round_trip_precision() {
+        for point in [
+            GpsPoint::new(51.507_412_345, -0.127_812_345),
+            GpsPoint::new(-33.868_8, 151.209_3),
+            GpsPoint::new(89.999_999_9, -179.999_999_9),
+            GpsPoint::new(-90.0, 180.0),
+        ] {
+            let restored = GpsPoint::from(CompactPoint::from(point));
+            assert!((restored.latitude - point.latitude).abs() <= 0.5e-7);
+            assert!((restored.longitude - point.longitude).abs() <= 0.5e-7);
+        }
+    }
+
+    #[test]
+    fn test_compact_signature_still_matches() {
+        let config = MatchConfig::default();
+        let points: Vec<GpsPoint> = (0..100)
+            .map(|i| GpsPoint::new(51.5 + i as f64 * 0.0005, -0.1 + (i % 7) as f64 * 0.0002))
+            .collect();
+        let signature = RouteSignature::from_points("a", &points, &config).unwrap();
+
+        let mut restored = CompactSignature::from(&signature).to_signature();
+        restored.activity_id = "b".to_string();
+        assert_eq!(restored.points.len(), signature.points.len());
+        assert!((restored.total_distance - signature.total_distance).abs() < 0.01);
+        assert!((restored.center.latitude - signature.center.latitude).abs() < 1e-6);
+
+        let result = compare_routes(&signature, &restored, &config).unwrap();
+        assert!(result.match_percentage > 99.0);
+    }
+
+    #[test]
+    fn test_compact_section_keeps_traces() {
+        let polyline = vec![GpsPoint::new(51.5, -0.1), GpsPoint::new(51.51, -0.1)];
+        let section = FrequentSection {
+            id: "sec_1".to_string(),
+            polyline: polyline.clone(),
+            visit_count: 3,
+            distance_meters: 1112.0,
+            activity_traces: HashMap::from([("a".to_string(), polyline.clone())]),
+            ..Default::default()
+        };
+
+        let compact = CompactSection::from(&section);
+        assert_eq!(compact.visit_count, 3);
+        assert_eq!(compact.activity_traces["a"].len(), 2);
+        assert_eq!(compact.polyline_points(), polyline);
+    }
+}
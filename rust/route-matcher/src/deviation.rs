@@ -0,0 +1,576 @@
+//! # Off-Route Detection
+//!
+//! Finds where an activity left a planned route, how far and how long it was
+//! away, and where it rejoined — live, point by point, or after the fact for
+//! a whole activity.
+//!
+//! ## Algorithm
+//! A [`DeviationTracker`] follows progress along the planned route:
+//! 1. While on route, each point is projected onto the part of the route just
+//!    around the progress so far (prefix matching, as in [`crate::live`]), so
+//!    out-and-back courses are followed in order
+//! 2. After `min_points` consecutive points further than
+//!    `off_route_distance`, a deviation opens at the first of them
+//! 3. While off route, points are projected onto the whole route, since a
+//!    detour or shortcut may rejoin anywhere. After `min_points` consecutive
+//!    points back within `off_route_distance`, the deviation closes at the
+//!    first of them, and progress continues from there
+//!
+//! Requiring several points either way keeps single GPS glitches from opening
+//! or closing deviations.
+//!
+//! ## Coverage
+//! [`coverage`] checks a finished activity against a course regardless of
+//! order or direction, e.g. to validate an audax or brevet: both tracks are
+//! sampled every 10m, and a sample is covered when the other track passes
+//! within the tolerance. Uncovered runs of the course are missed portions;
+//! uncovered runs of the activity are extra portions.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::deviation::{DeviationConfig, detect_deviations};
+//!
+//! // A straight planned road north; the ride detours 200m east around
+//! // its middle
+//! let planned: Vec<GpsPoint> = (0..=50).map(|i| GpsPoint::new(51.50 + i as f64 * 0.0002, -0.10)).collect();
+//! let actual: Vec<GpsPoint> = planned
+//!     .iter()
+//!     .enumerate()
+//!     .map(|(i, p)| {
+//!         let east = if (20..30).contains(&i) { 0.003 } else { 0.0 };
+//!         GpsPoint::new(p.latitude, p.longitude + east)
+//!     })
+//!     .collect();
+//!
+//! let report = detect_deviations(&planned, &actual, &[], &DeviationConfig::default());
+//! assert_eq!(report.deviations.len(), 1);
+//! assert_eq!(report.deviations[0].start_index, 20);
+//! assert_eq!(report.deviations[0].rejoin_index, Some(30));
+//! ```
+
+use rstar::primitives::Line;
+use rstar::{PointDistance, RTree};
+use crate::geo_utils::{haversine_distance, interpolate_point, polyline_length, project_point_onto_route};
+use crate::projection::{LocalProjection, PlanarPoint};
+use crate::GpsPoint;
+
+/// Spacing of coverage samples (meters)
+const COVERAGE_SAMPLE_SPACING: f64 = 10.0;
+
+/// Default distance within which a course counts as covered (meters)
+const DEFAULT_COVERAGE_TOLERANCE: f64 = 50.0;
+
+/// Configuration for off-route detection
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct DeviationConfig {
+    /// Points further than this from the planned route are off it (meters). Default: 50
+    pub off_route_distance: f64,
+    /// Consecutive points needed to leave or rejoin the route. Default: 3
+    pub min_points: u32,
+}
+
+impl Default for DeviationConfig {
+    fn default() -> Self {
+        Self {
+            off_route_distance: 50.0,
+            min_points: 3,
+        }
+    }
+}
+
+/// A stretch of an activity away from the planned route
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct Deviation {
+    /// Index of the first off-route point
+    pub start_index: u32,
+    /// Index of the first point back on the route; None if the activity
+    /// hasn't rejoined (yet)
+    pub rejoin_index: Option<u32>,
+    /// Where the route was left: the last on-route progress
+    pub leave_point: GpsPoint,
+    /// Where the route was rejoined
+    pub rejoin_point: Option<GpsPoint>,
+    /// Distance along the planned route where it was left (meters)
+    pub leave_distance_along: f64,
+    /// Distance along the planned route where it was rejoined (meters).
+    /// Beyond `leave_distance_along` for detours and shortcuts, before it when
+    /// the activity rejoined further back
+    pub rejoin_distance_along: Option<f64>,
+    /// Furthest the activity got from the route (meters)
+    pub max_offset_meters: f64,
+    /// Distance ridden while off route (meters)
+    pub distance_meters: f64,
+    /// Time off route (seconds), for timed points
+    pub duration_seconds: Option<f64>,
+}
+
+/// All deviations of an activity from a planned route
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct DeviationReport {
+    pub deviations: Vec<Deviation>,
+    /// Total distance ridden off route (meters)
+    pub off_route_meters: f64,
+    /// Total time off route (seconds), for timed points
+    pub off_route_seconds: Option<f64>,
+    /// Share of the distance ridden that was off route (0-1)
+    pub off_route_fraction: f64,
+}
+
+/// A stretch of one track not covered by the other
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct CoveragePortion {
+    /// Distance along its own track where the portion starts (meters)
+    pub start_distance: f64,
+    /// Distance along its own track where the portion ends (meters)
+    pub end_distance: f64,
+    pub polyline: Vec<GpsPoint>,
+}
+
+/// How much of a planned course an activity covered
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct CoverageReport {
+    /// Share of the course ridden (0-1)
+    pub covered_fraction: f64,
+    /// Course length (meters)
+    pub planned_meters: f64,
+    /// Course length ridden (meters)
+    pub covered_meters: f64,
+    /// Parts of the course not ridden, in course order
+    pub missed: Vec<CoveragePortion>,
+    /// Parts of the activity away from the course, in activity order
+    pub extra: Vec<CoveragePortion>,
+}
+
+/// Whether a deviation opened or closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum DeviationEventKind {
+    OffRoute,
+    Rejoined,
+}
+
+/// A deviation opening or closing
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct DeviationEvent {
+    pub kind: DeviationEventKind,
+    pub deviation: Deviation,
+}
+
+/// The first point of a run of off-route (or back-on-route) points
+#[derive(Clone, Copy)]
+struct RunStart {
+    index: u32,
+    point: GpsPoint,
+    /// Distance along the planned route of its projection (meters)
+    along: f64,
+    /// Distance ridden and time at the point
+    ridden: f64,
+    time: Option<i64>,
+    /// Distance ridden and time at the point before it
+    ridden_before: f64,
+    time_before: Option<i64>,
+}
+
+/// Streaming off-route detection against a planned route.
+///
+/// Feed points with [`DeviationTracker::push_point`]; [`DeviationTracker::report`]
+/// summarises the deviations so far.
+pub struct DeviationTracker {
+    planned: Vec<GpsPoint>,
+    /// Distance from the start to each planned point (meters)
+    cumulative: Vec<f64>,
+    config: DeviationConfig,
+    progress: f64,
+    last_point: Option<GpsPoint>,
+    last_timestamp: Option<i64>,
+    /// Points pushed so far
+    count: u32,
+    /// Distance ridden so far (meters)
+    ridden: f64,
+    /// Deviations closed so far
+    closed: Vec<Deviation>,
+    /// The open deviation, and the start of the run that opened it
+    open: Option<(Deviation, RunStart)>,
+    /// The current run of points on the other side of the threshold: its
+    /// start, length and largest offset
+    run: Option<(RunStart, u32, f64)>,
+}
+
+impl DeviationTracker {
+    /// Tracker against `planned`, which needs at least 2 points to detect
+    /// anything.
+    pub fn new(planned: &[GpsPoint], config: DeviationConfig) -> Self {
+        let planned: Vec<GpsPoint> = planned.iter().copied().filter(|p| p.is_valid()).collect();
+        let mut cumulative = Vec::with_capacity(planned.len());
+        let mut along = 0.0;
+        for (i, p) in planned.iter().enumerate() {
+            if i > 0 {
+                along += haversine_distance(&planned[i - 1], p);
+            }
+            cumulative.push(along);
+        }
+        Self {
+            planned,
+            cumulative,
+            config,
+            progress: 0.0,
+            last_point: None,
+            last_timestamp: None,
+            count: 0,
+            ridden: 0.0,
+            closed: Vec::new(),
+            open: None,
+            run: None,
+        }
+    }
+
+    /// Add the next point, with its time (Unix seconds) if known. Returns an
+    /// event when it confirms leaving or rejoining the route.
+    pub fn push_point(&mut self, point: GpsPoint, timestamp: Option<i64>) -> Option<DeviationEvent> {
+        if !point.is_valid() || self.planned.len() < 2 {
+            return None;
+        }
+        let index = self.count;
+        let (ridden_before, time_before) = (self.ridden, self.last_timestamp);
+        let step = self.last_point.map_or(0.0, |last| haversine_distance(&last, &point));
+        self.count += 1;
+        self.ridden += step;
+        self.last_point = Some(point);
+        self.last_timestamp = timestamp;
+
+        let threshold = self.config.off_route_distance;
+        let window = if self.open.is_some() {
+            None
+        } else {
+            self.project_between(&point, self.progress - threshold, self.progress + step + threshold)
+        };
+        let (along, offset) = window.or_else(|| self.project_between(&point, f64::NEG_INFINITY, f64::INFINITY))?;
+
+        if let Some((deviation, _)) = &mut self.open {
+            deviation.max_offset_meters = deviation.max_offset_meters.max(offset);
+        }
+        // Points on the side of the threshold the tracker is on end any run
+        let crossing = (offset > threshold) == self.open.is_none();
+        if !crossing {
+            self.run = None;
+            if self.open.is_none() {
+                self.progress = self.progress.max(along);
+            }
+            return None;
+        }
+
+        let (start, length, run_offset) = match self.run {
+            Some((start, length, run_offset)) => (start, length + 1, run_offset.max(offset)),
+            None => {
+                let ridden = self.ridden;
+                (RunStart { index, point, along, ridden, time: timestamp, ridden_before, time_before }, 1, offset)
+            }
+        };
+        self.run = Some((start, length, run_offset));
+        if length < self.config.min_points.max(1) {
+            return None;
+        }
+        self.run = None;
+
+        match self.open.take() {
+            None => {
+                let deviation = Deviation {
+                    start_index: start.index,
+                    rejoin_index: None,
+                    leave_point: self.point_at(self.progress),
+                    rejoin_point: None,
+                    leave_distance_along: self.progress,
+                    rejoin_distance_along: None,
+                    max_offset_meters: run_offset,
+                    distance_meters: 0.0,
+                    duration_seconds: None,
+                };
+                self.open = Some((deviation.clone(), start));
+                let deviation = self.up_to_now(deviation, &start);
+                Some(DeviationEvent { kind: DeviationEventKind::OffRoute, deviation })
+            }
+            Some((deviation, from)) => {
+                // The deviation ends at the first point back on the route
+                let mut deviation = with_span(deviation, &from, start.ridden, start.time);
+                deviation.rejoin_index = Some(start.index);
+                deviation.rejoin_point = Some(start.point);
+                deviation.rejoin_distance_along = Some(start.along);
+                self.progress = along;
+                self.closed.push(deviation.clone());
+                Some(DeviationEvent { kind: DeviationEventKind::Rejoined, deviation })
+            }
+        }
+    }
+
+    /// Deviations so far, including one still open.
+    pub fn report(&self) -> DeviationReport {
+        let mut deviations = self.closed.clone();
+        if let Some((deviation, from)) = &self.open {
+            deviations.push(self.up_to_now(deviation.clone(), from));
+        }
+
+        let off_route_meters: f64 = deviations.iter().map(|d| d.distance_meters).sum();
+        let off_route_seconds = deviations
+            .iter()
+            .map(|d| d.duration_seconds)
+            .try_fold(0.0, |total, seconds| seconds.map(|s| total + s))
+            .filter(|_| !deviations.is_empty());
+        DeviationReport {
+            off_route_fraction: if self.ridden > 0.0 { (off_route_meters / self.ridden).min(1.0) } else { 0.0 },
+            deviations,
+            off_route_meters,
+            off_route_seconds,
+        }
+    }
+
+    /// An open `deviation` with its distance and duration up to the last point.
+    fn up_to_now(&self, deviation: Deviation, from: &RunStart) -> Deviation {
+        with_span(deviation, from, self.ridden, self.last_timestamp)
+    }
+
+    /// Projection of `point` onto the planned route between `from` and `to`
+    /// meters along: (distance along, offset).
+    fn project_between(&self, point: &GpsPoint, from: f64, to: f64) -> Option<(f64, f64)> {
+        let first = self.cumulative.partition_point(|&d| d < from).saturating_sub(1);
+        let last = self.cumulative.partition_point(|&d| d <= to).min(self.planned.len() - 1);
+        if last <= first {
+            return None;
+        }
+        project_point_onto_route(point, &self.planned[first..=last])
+            .map(|proj| (self.cumulative[first] + proj.distance_along, proj.offset))
+    }
+
+    /// The planned route's point `along` meters from its start.
+    fn point_at(&self, along: f64) -> GpsPoint {
+        let i = self.cumulative.partition_point(|&d| d <= along).clamp(1, self.planned.len() - 1);
+        let (d0, d1) = (self.cumulative[i - 1], self.cumulative[i]);
+        let t = if d1 > d0 { ((along - d0) / (d1 - d0)).clamp(0.0, 1.0) } else { 0.0 };
+        interpolate_point(&self.planned[i - 1], &self.planned[i], t)
+    }
+}
+
+/// Coverage of `planned` by `actual`, counting the course as ridden within
+/// 50m. See [`coverage_within`].
+pub fn coverage(planned: &[GpsPoint], actual: &[GpsPoint]) -> CoverageReport {
+    coverage_within(planned, actual, DEFAULT_COVERAGE_TOLERANCE)
+}
+
+/// Coverage of `planned` by `actual`, counting the course as ridden where the
+/// activity passes within `tolerance_meters`, in any order or direction.
+pub fn coverage_within(planned: &[GpsPoint], actual: &[GpsPoint], tolerance_meters: f64) -> CoverageReport {
+    let planned: Vec<GpsPoint> = planned.iter().copied().filter(|p| p.is_valid()).collect();
+    let actual: Vec<GpsPoint> = actual.iter().copied().filter(|p| p.is_valid()).collect();
+    if planned.len() < 2 || polyline_length(&planned) <= 0.0 {
+        return CoverageReport::default();
+    }
+
+    let projection = LocalProjection::for_points(&[planned.as_slice(), actual.as_slice()].concat());
+    let planned_samples = coverage_samples(&planned);
+    let planned_meters = planned_samples[planned_samples.len() - 1].1;
+    let actual_samples = coverage_samples(&actual);
+    let planned_covered = covered_by(&planned_samples, &actual, &projection, tolerance_meters);
+    let actual_covered = covered_by(&actual_samples, &planned, &projection, tolerance_meters);
+
+    // Each gap between samples counts when both ends are covered
+    let covered_meters: f64 = planned_samples
+        .windows(2)
+        .zip(planned_covered.windows(2))
+        .filter(|(_, covered)| covered[0] && covered[1])
+        .map(|(w, _)| w[1].1 - w[0].1)
+        .sum();
+
+    CoverageReport {
+        covered_fraction: (covered_meters / planned_meters).clamp(0.0, 1.0),
+        planned_meters,
+        covered_meters,
+        missed: uncovered_portions(&planned_samples, &planned_covered),
+        extra: uncovered_portions(&actual_samples, &actual_covered),
+    }
+}
+
+/// Points every [`COVERAGE_SAMPLE_SPACING`] along `points`, with their
+/// distance along.
+fn coverage_samples(points: &[GpsPoint]) -> Vec<(GpsPoint, f64)> {
+    if points.len() < 2 {
+        return points.iter().map(|&p| (p, 0.0)).collect();
+    }
+    let count = (polyline_length(points) / COVERAGE_SAMPLE_SPACING).ceil() as usize + 1;
+    let samples = crate::geo_utils::resample_polyline(points, count.max(2));
+    let mut along = 0.0;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| {
+            if i > 0 {
+                along += haversine_distance(&samples[i - 1], &p);
+            }
+            (p, along)
+        })
+        .collect()
+}
+
+/// Whether each sample is within `tolerance` of the polyline through `track`.
+fn covered_by(samples: &[(GpsPoint, f64)], track: &[GpsPoint], projection: &LocalProjection, tolerance: f64) -> Vec<bool> {
+    if track.len() < 2 {
+        return vec![false; samples.len()];
+    }
+    let planar = projection.project_all(track);
+    let tree: RTree<Line<PlanarPoint>> = RTree::bulk_load(planar.windows(2).map(|w| Line::new(w[0], w[1])).collect());
+    samples
+        .iter()
+        .map(|(p, _)| {
+            let point = projection.project(p);
+            tree.nearest_neighbor(&point).is_some_and(|line| line.distance_2(&point) <= tolerance * tolerance)
+        })
+        .collect()
+}
+
+/// Runs of uncovered samples as portions, extended to the covered samples
+/// either side.
+fn uncovered_portions(samples: &[(GpsPoint, f64)], covered: &[bool]) -> Vec<CoveragePortion> {
+    let mut portions = Vec::new();
+    let mut i = 0;
+    while i < samples.len() {
+        if covered[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < samples.len() && !covered[i] {
+            i += 1;
+        }
+        let (from, to) = (start.saturating_sub(1), i.min(samples.len() - 1));
+        if to > from {
+            portions.push(CoveragePortion {
+                start_distance: samples[from].1,
+                end_distance: samples[to].1,
+                polyline: samples[from..=to].iter().map(|&(p, _)| p).collect(),
+            });
+        }
+    }
+    portions
+}
+
+/// `deviation` with its distance and duration from the last point before it
+/// (`from`) to a point `ridden` meters into the activity at `time`.
+fn with_span(mut deviation: Deviation, from: &RunStart, ridden: f64, time: Option<i64>) -> Deviation {
+    deviation.distance_meters = (ridden - from.ridden_before).max(0.0);
+    deviation.duration_seconds = from.time_before.zip(time).map(|(a, b)| (b - a).max(0) as f64);
+    deviation
+}
+
+/// Deviations of a whole activity from `planned`. `timestamps` (Unix seconds,
+/// one per point) give durations; pass an empty slice without them.
+pub fn detect_deviations(
+    planned: &[GpsPoint],
+    actual: &[GpsPoint],
+    timestamps: &[i64],
+    config: &DeviationConfig,
+) -> DeviationReport {
+    let mut tracker = DeviationTracker::new(planned, config.clone());
+    for (i, &point) in actual.iter().enumerate() {
+        tracker.push_point(point, timestamps.get(i).copied());
+    }
+    tracker.report()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points ~22m apart heading north from 51.50, shifted `east` degrees
+    fn line(from: usize, to: usize, east: f64) -> Vec<GpsPoint> {
+        (from..=to).map(|i| GpsPoint::new(51.50 + i as f64 * 0.0002, -0.10 + east)).collect()
+    }
+
+    #[test]
+    fn test_detour_distance_duration_and_rejoin() {
+        let planned = line(0, 50, 0.0);
+        // ~200m east for points 20-29
+        let mut actual = line(0, 19, 0.0);
+        actual.extend(line(20, 29, 0.003));
+        actual.extend(line(30, 50, 0.0));
+        let timestamps: Vec<i64> = (0..actual.len() as i64).map(|i| 1_700_000_000 + 5 * i).collect();
+
+        let report = detect_deviations(&planned, &actual, &timestamps, &DeviationConfig::default());
+        assert_eq!(report.deviations.len(), 1);
+        let deviation = &report.deviations[0];
+        assert_eq!((deviation.start_index, deviation.rejoin_index), (20, Some(30)));
+        assert!((deviation.max_offset_meters - 208.0).abs() < 5.0);
+        assert!((deviation.leave_distance_along - 19.0 * 22.24).abs() < 2.0);
+        assert!((deviation.rejoin_distance_along.unwrap() - 30.0 * 22.24).abs() < 2.0);
+        // Out and back on ~209m diagonals, plus 9 legs of ~22m
+        assert!((deviation.distance_meters - (2.0 * 209.0 + 9.0 * 22.24)).abs() < 10.0, "{}", deviation.distance_meters);
+        assert_eq!(deviation.duration_seconds, Some(55.0));
+        assert_eq!(report.off_route_seconds, Some(55.0));
+        assert!(report.off_route_fraction > 0.4 && report.off_route_fraction < 0.6);
+    }
+
+    #[test]
+    fn test_glitches_and_out_and_back_courses() {
+        // Out and back along the same road
+        let mut planned = line(0, 30, 0.0);
+        planned.extend(line(0, 29, 0.0).into_iter().rev());
+        let mut actual = planned.clone();
+        // A single wild point
+        actual[10] = GpsPoint::new(51.502, -0.095);
+
+        let mut tracker = DeviationTracker::new(&planned, DeviationConfig::default());
+        assert!(actual.iter().all(|&p| tracker.push_point(p, None).is_none()));
+        let report = tracker.report();
+        assert!(report.deviations.is_empty());
+        assert_eq!(report.off_route_seconds, None);
+    }
+
+    #[test]
+    fn test_coverage_finds_missed_and_extra_portions() {
+        let planned = line(0, 50, 0.0);
+        // Rides the course backwards, skipping points 20-29 for a road 200m east
+        let mut actual = line(30, 50, 0.0);
+        actual.extend(line(20, 29, 0.003));
+        actual.extend(line(0, 19, 0.0));
+        actual.reverse();
+
+        let report = coverage(&planned, &actual);
+        assert_eq!(report.missed.len(), 1);
+        let missed = &report.missed[0];
+        // The course between ~19.5 and ~29.5 steps is missed (50m tolerance
+        // covers the diagonal legs' first samples)
+        assert!(missed.start_distance > 19.0 * 22.24 && missed.start_distance < 22.0 * 22.24, "{:?}", missed);
+        assert!(missed.end_distance > 28.0 * 22.24 && missed.end_distance < 31.0 * 22.24, "{:?}", missed);
+        assert!(report.covered_fraction > 0.78 && report.covered_fraction < 0.88, "{}", report.covered_fraction);
+        assert_eq!(report.extra.len(), 1);
+        assert!(report.extra[0].end_distance - report.extra[0].start_distance > 500.0);
+
+        // Ridden exactly: nothing missed or extra
+        let full = coverage(&planned, &planned);
+        assert_eq!(full.covered_fraction, 1.0);
+        assert!(full.missed.is_empty() && full.extra.is_empty());
+        assert_eq!(coverage(&planned, &[]).covered_fraction, 0.0);
+    }
+
+    #[test]
+    fn test_open_deviation_at_end() {
+        let planned = line(0, 50, 0.0);
+        let mut actual = line(0, 19, 0.0);
+        actual.extend(line(20, 29, 0.003));
+
+        let mut tracker = DeviationTracker::new(&planned, DeviationConfig::default());
+        let events: Vec<DeviationEvent> = actual.iter().filter_map(|&p| tracker.push_point(p, None)).collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, DeviationEventKind::OffRoute);
+        assert_eq!(events[0].deviation.start_index, 20);
+
+        let report = tracker.report();
+        assert_eq!(report.deviations[0].rejoin_index, None);
+        assert!(report.deviations[0].distance_meters > 400.0);
+    }
+}
@@ -0,0 +1,397 @@
+//! # Section Efforts
+//!
+//! Compares every activity's effort over a section: how long it took, how
+//! fast it was and where it ranks, so apps can answer "how did today's
+//! traversal compare?".
+//!
+//! Efforts come from the section's [`SectionPortion`] index ranges, applied to
+//! each activity's time (and optionally distance) streams. Those streams must
+//! be aligned with the full GPS track the section was detected from.
+//!
+//! ## Grade adjustment
+//!
+//! With distance and altitude streams, efforts also get an adjusted speed so
+//! hilly efforts compare with flat ones:
+//!
+//! - **Runs** (and walks/hikes): grade-adjusted pace, scaling each stretch by
+//!   the metabolic cost of running at its gradient (Minetti et al., 2002)
+//! - **Rides**: the flat-road speed needing the same average power, from a
+//!   standard gravity, rolling resistance and drag model
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{FrequentSection, SectionPortion};
+//! use route_matcher::efforts::{EffortStreams, compare_section_efforts};
+//!
+//! let portion = |id: &str| SectionPortion {
+//!     activity_id: id.to_string(),
+//!     start_index: 10,
+//!     end_index: 20,
+//!     distance_meters: 450.0,
+//!     direction: "same".to_string(),
+//! };
+//! let section = FrequentSection {
+//!     id: "climb".to_string(),
+//!     activity_portions: vec![portion("slow"), portion("fast")],
+//!     ..Default::default()
+//! };
+//!
+//! // One sample every 10s for "slow", every 6s for "fast"
+//! let streams = vec![
+//!     EffortStreams::new("slow", (0..30).map(|i| i * 10).collect()),
+//!     EffortStreams::new("fast", (0..30).map(|i| i * 6).collect()),
+//! ];
+//!
+//! let efforts = compare_section_efforts(&section, &streams);
+//! assert_eq!(efforts[0].activity_id, "fast");
+//! assert_eq!(efforts[0].elapsed_seconds, 54);
+//! assert_eq!(efforts[1].rank, 2);
+//! ```
+
+use std::collections::HashMap;
+use crate::{FrequentSection, SectionPortion};
+
+/// Stretches shorter than this are merged before taking a gradient, so
+/// altitude noise between close samples doesn't produce extreme grades
+const MIN_GRADE_DISTANCE: f64 = 10.0;
+
+/// Gradients beyond this are clamped (outside Minetti's measured range)
+const MAX_GRADE: f64 = 0.45;
+
+/// Rider + bike mass (kg), rolling resistance and drag area (m²) for the ride model
+const RIDE_MASS: f64 = 85.0;
+const RIDE_CRR: f64 = 0.005;
+const RIDE_CDA: f64 = 0.32;
+const AIR_DENSITY: f64 = 1.225;
+const GRAVITY: f64 = 9.81;
+
+/// Time and distance streams of one activity, aligned with its full track.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct EffortStreams {
+    pub activity_id: String,
+    /// Seconds since the activity started, one per track point
+    pub time: Vec<i64>,
+    /// Cumulative distance in meters, one per track point. If empty, the
+    /// portion's GPS distance is used instead.
+    pub distance: Vec<f64>,
+    /// Altitude in meters, one per track point. Needed (with `distance`)
+    /// for adjusted speeds.
+    pub altitude: Vec<f64>,
+}
+
+impl EffortStreams {
+    /// Streams with only timestamps; distances come from the section portions.
+    pub fn new(activity_id: &str, time: Vec<i64>) -> Self {
+        Self {
+            activity_id: activity_id.to_string(),
+            time,
+            distance: Vec::new(),
+            altitude: Vec::new(),
+        }
+    }
+}
+
+/// One activity's effort over a section
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SectionEffort {
+    pub activity_id: String,
+    /// Start index into the activity's full track
+    pub start_index: u32,
+    /// End index into the activity's full track (exclusive)
+    pub end_index: u32,
+    /// Direction relative to the section polyline: "same" or "reverse"
+    pub direction: String,
+    /// Time spent on the section in seconds
+    pub elapsed_seconds: i64,
+    /// Distance covered in meters
+    pub distance_meters: f64,
+    /// Average speed in m/s
+    pub average_speed: f64,
+    /// Grade-adjusted (runs) or normalized (rides) speed in m/s, when the
+    /// streams carry distance and altitude and the sport is supported
+    pub adjusted_speed: Option<f64>,
+    /// 1 for the fastest effort; ties share a rank
+    pub rank: u32,
+}
+
+/// Compare the efforts of every activity in `section.activity_portions`,
+/// fastest first. Adjusted speeds use the section's sport type.
+///
+/// Activities without streams, or whose streams don't cover the portion,
+/// are skipped, as are efforts with no elapsed time.
+pub fn compare_section_efforts(section: &FrequentSection, streams: &[EffortStreams]) -> Vec<SectionEffort> {
+    let by_id: HashMap<&str, &EffortStreams> = streams
+        .iter()
+        .map(|s| (s.activity_id.as_str(), s))
+        .collect();
+
+    let mut efforts: Vec<SectionEffort> = section
+        .activity_portions
+        .iter()
+        .filter_map(|portion| {
+            let streams = by_id.get(portion.activity_id.as_str())?;
+            effort(portion, streams, &section.sport_type)
+        })
+        .collect();
+
+    efforts.sort_by(|a, b| {
+        a.elapsed_seconds
+            .cmp(&b.elapsed_seconds)
+            .then_with(|| a.activity_id.cmp(&b.activity_id))
+    });
+
+    let mut previous: Option<(i64, u32)> = None;
+    for (i, effort) in efforts.iter_mut().enumerate() {
+        effort.rank = match previous {
+            Some((elapsed, rank)) if elapsed == effort.elapsed_seconds => rank,
+            _ => i as u32 + 1,
+        };
+        previous = Some((effort.elapsed_seconds, effort.rank));
+    }
+
+    efforts
+}
+
+/// Grade-adjusted (runs) or normalized (rides) speed in m/s over a whole
+/// activity, e.g. to compare efforts on a route group.
+///
+/// Returns `None` for other sports, or if the distance or altitude stream is
+/// missing or not aligned with `time`.
+pub fn adjusted_speed(streams: &EffortStreams, sport_type: &str) -> Option<f64> {
+    adjusted_speed_between(streams, sport_type, 0, streams.time.len())
+}
+
+/// How gradient is accounted for in a sport
+#[derive(Clone, Copy)]
+enum EffortModel {
+    Run,
+    Ride,
+}
+
+impl EffortModel {
+    fn for_sport(sport_type: &str) -> Option<Self> {
+        if ["Run", "Walk", "Hike"].iter().any(|s| sport_type.contains(s)) {
+            Some(EffortModel::Run)
+        } else if sport_type.contains("Ride") {
+            Some(EffortModel::Ride)
+        } else {
+            None
+        }
+    }
+}
+
+/// Adjusted speed over `start..end` of the streams.
+fn adjusted_speed_between(streams: &EffortStreams, sport_type: &str, start: usize, end: usize) -> Option<f64> {
+    let model = EffortModel::for_sport(sport_type)?;
+    let len = streams.time.len();
+    if streams.distance.len() != len || streams.altitude.len() != len || start >= end || end > len {
+        return None;
+    }
+
+    let elapsed = (streams.time[end - 1] - streams.time[start]) as f64;
+    if elapsed <= 0.0 {
+        return None;
+    }
+
+    // (distance, grade, seconds) of each stretch of at least MIN_GRADE_DISTANCE
+    let mut stretches = Vec::new();
+    let mut from = start;
+    for i in start + 1..end {
+        let distance = streams.distance[i] - streams.distance[from];
+        if distance >= MIN_GRADE_DISTANCE || i == end - 1 {
+            if distance > 0.0 {
+                let grade = ((streams.altitude[i] - streams.altitude[from]) / distance).clamp(-MAX_GRADE, MAX_GRADE);
+                let seconds = (streams.time[i] - streams.time[from]) as f64;
+                stretches.push((distance, grade, seconds));
+            }
+            from = i;
+        }
+    }
+    if stretches.is_empty() {
+        return None;
+    }
+
+    match model {
+        EffortModel::Run => {
+            // Flat distance with the same energy cost
+            let flat_cost = running_cost(0.0);
+            let equivalent: f64 = stretches
+                .iter()
+                .map(|&(distance, grade, _)| distance * running_cost(grade) / flat_cost)
+                .sum();
+            Some(equivalent / elapsed)
+        }
+        EffortModel::Ride => {
+            // Time-weighted average power, then the flat speed that needs it.
+            // Descents can't produce negative power: the rider coasts.
+            let energy: f64 = stretches
+                .iter()
+                .filter(|&&(_, _, seconds)| seconds > 0.0)
+                .map(|&(distance, grade, seconds)| riding_power(distance / seconds, grade).max(0.0) * seconds)
+                .sum();
+            Some(flat_riding_speed(energy / elapsed))
+        }
+    }
+}
+
+/// Energy cost of running (J/kg/m) at a gradient (Minetti et al., 2002).
+fn running_cost(grade: f64) -> f64 {
+    let i = grade;
+    155.4 * i.powi(5) - 30.4 * i.powi(4) - 43.3 * i.powi(3) + 46.3 * i.powi(2) + 19.5 * i + 3.6
+}
+
+/// Power (W) to ride at `speed` (m/s) up `grade`.
+fn riding_power(speed: f64, grade: f64) -> f64 {
+    let angle = grade.atan();
+    let gravity = RIDE_MASS * GRAVITY * angle.sin() * speed;
+    let rolling = RIDE_CRR * RIDE_MASS * GRAVITY * angle.cos() * speed;
+    let drag = 0.5 * AIR_DENSITY * RIDE_CDA * speed.powi(3);
+    gravity + rolling + drag
+}
+
+/// Flat-road speed (m/s) sustained by `power` watts.
+fn flat_riding_speed(power: f64) -> f64 {
+    if power <= 0.0 {
+        return 0.0;
+    }
+    // Power increases monotonically with speed, so bisect
+    let (mut low, mut high) = (0.0, 50.0);
+    for _ in 0..60 {
+        let mid = (low + high) / 2.0;
+        if riding_power(mid, 0.0) < power {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Timing of one portion from the activity's streams.
+fn effort(portion: &SectionPortion, streams: &EffortStreams, sport_type: &str) -> Option<SectionEffort> {
+    let start = portion.start_index as usize;
+    let end = portion.end_index as usize;
+    if start >= end || end > streams.time.len() {
+        return None;
+    }
+
+    let elapsed_seconds = streams.time[end - 1] - streams.time[start];
+    if elapsed_seconds <= 0 {
+        return None;
+    }
+
+    let distance_meters = if streams.distance.len() == streams.time.len() {
+        streams.distance[end - 1] - streams.distance[start]
+    } else {
+        portion.distance_meters
+    };
+
+    Some(SectionEffort {
+        activity_id: portion.activity_id.clone(),
+        start_index: portion.start_index,
+        end_index: portion.end_index,
+        direction: portion.direction.clone(),
+        elapsed_seconds,
+        distance_meters,
+        average_speed: distance_meters / elapsed_seconds as f64,
+        adjusted_speed: adjusted_speed_between(streams, sport_type, start, end),
+        rank: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn portion(id: &str, start: u32, end: u32) -> SectionPortion {
+        SectionPortion {
+            activity_id: id.to_string(),
+            start_index: start,
+            end_index: end,
+            distance_meters: 500.0,
+            direction: "same".to_string(),
+        }
+    }
+
+    fn section(portions: Vec<SectionPortion>) -> FrequentSection {
+        FrequentSection {
+            id: "s".to_string(),
+            activity_portions: portions,
+            ..Default::default()
+        }
+    }
+
+    fn every(activity_id: &str, seconds: i64) -> EffortStreams {
+        EffortStreams::new(activity_id, (0..20).map(|i| i * seconds).collect())
+    }
+
+    #[test]
+    fn test_ties_share_rank() {
+        let section = section(vec![portion("a", 0, 11), portion("b", 5, 16), portion("c", 0, 11)]);
+        let streams = vec![every("a", 10), every("b", 10), every("c", 5)];
+
+        let efforts = compare_section_efforts(&section, &streams);
+        let ranks: Vec<(&str, u32)> = efforts.iter().map(|e| (e.activity_id.as_str(), e.rank)).collect();
+        assert_eq!(ranks, vec![("c", 1), ("a", 2), ("b", 2)]);
+        assert_eq!(efforts[0].elapsed_seconds, 50);
+        assert!((efforts[0].average_speed - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_distance_stream_overrides_portion_distance() {
+        let section = section(vec![portion("a", 2, 12)]);
+        let mut streams = every("a", 1);
+        streams.distance = (0..20).map(|i| i as f64 * 4.0).collect();
+
+        let efforts = compare_section_efforts(&section, &[streams]);
+        assert_eq!(efforts.len(), 1);
+        assert!((efforts[0].distance_meters - 36.0).abs() < 1e-9);
+        assert!((efforts[0].average_speed - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_skips_missing_and_short_streams() {
+        let section = section(vec![portion("missing", 0, 5), portion("short", 10, 30), portion("ok", 0, 5)]);
+        let streams = vec![every("short", 1), every("ok", 1)];
+
+        let efforts = compare_section_efforts(&section, &streams);
+        assert_eq!(efforts.len(), 1);
+        assert_eq!(efforts[0].activity_id, "ok");
+        assert_eq!(efforts[0].rank, 1);
+        // No altitude stream
+        assert_eq!(efforts[0].adjusted_speed, None);
+    }
+
+    /// 1.2km at 4 m/s with a constant gradient, one sample every 12m
+    fn steady(grade: f64) -> EffortStreams {
+        EffortStreams {
+            activity_id: "a".to_string(),
+            time: (0..=100).map(|i| i * 3).collect(),
+            distance: (0..=100).map(|i| i as f64 * 12.0).collect(),
+            altitude: (0..=100).map(|i| i as f64 * 12.0 * grade).collect(),
+        }
+    }
+
+    #[test]
+    fn test_grade_adjusted_pace_for_runs() {
+        let flat = adjusted_speed(&steady(0.0), "Run").unwrap();
+        assert!((flat - 4.0).abs() < 1e-9);
+
+        // Climbing at the same speed is a harder effort, descending gently an easier one
+        assert!(adjusted_speed(&steady(0.08), "TrailRun").unwrap() > 6.0);
+        assert!(adjusted_speed(&steady(-0.05), "Run").unwrap() < 4.0);
+        assert_eq!(adjusted_speed(&steady(0.08), "Swim"), None);
+    }
+
+    #[test]
+    fn test_normalized_speed_for_rides() {
+        let flat = adjusted_speed(&steady(0.0), "Ride").unwrap();
+        assert!((flat - 4.0).abs() < 1e-6);
+        assert!(adjusted_speed(&steady(0.06), "GravelRide").unwrap() > 8.0);
+        // Coasting downhill needs no power
+        assert_eq!(adjusted_speed(&steady(-0.2), "Ride"), Some(0.0));
+    }
+}
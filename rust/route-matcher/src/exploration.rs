@@ -0,0 +1,271 @@
+//! # Explorer Tiles
+//!
+//! VeloViewer-style exploration statistics on a fixed slippy-map tile grid
+//! (zoom 14 by default, ~2.4 km tiles at the equator, ~1.5 km in Europe).
+//!
+//! ## Statistics
+//!
+//! | Statistic | Definition |
+//! |-----------|------------|
+//! | Total tiles | Tiles any activity passed through |
+//! | Max cluster | Largest 4-connected group of *cluster tiles* (visited tiles whose four neighbours are all visited) |
+//! | Max square | Side length of the largest fully visited square of tiles |
+//!
+//! ## Algorithm
+//! 1. Project each track to fractional Web Mercator tile coordinates
+//! 2. Walk every line between consecutive points in quarter-tile steps, so sparse
+//!    points (e.g. simplified signatures) still mark every tile they cross
+//! 3. Max square: dynamic programming over tiles sorted by `(x, y)`
+//!    (`side(x, y) = 1 + min(left, above, diagonal)`)
+//! 4. Max cluster: flood fill over cluster tiles
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::exploration::{exploration_from_tracks, EXPLORER_ZOOM};
+//!
+//! // ~5km east-west ride crosses several z14 tiles
+//! let track = vec![GpsPoint::new(47.37, 8.50), GpsPoint::new(47.37, 8.57)];
+//! let stats = exploration_from_tracks(&[("ride".to_string(), track)], EXPLORER_ZOOM);
+//!
+//! assert!(stats.total_tiles >= 4);
+//! assert_eq!(stats.max_square_size, 1);
+//! assert_eq!(stats.max_cluster_size, 0);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use crate::heatmap::tiles::{MAX_TILE_ZOOM, TileCoord, world_tile_position};
+use crate::{GpsPoint, RouteSignature};
+
+/// Zoom level used by VeloViewer / StatsHunters explorer tiles
+pub const EXPLORER_ZOOM: u32 = 14;
+
+/// Steps per tile when walking lines between points
+const STEPS_PER_TILE: f64 = 4.0;
+
+/// Longest line (in tiles) that is walked between two consecutive points.
+/// Longer jumps are GPS glitches or recording pauses and only mark their endpoints.
+const MAX_WALK_TILES: f64 = 1_000.0;
+
+/// Exploration statistics for a set of activities.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ExplorationStats {
+    /// Zoom level of the tile grid (at most [`MAX_TILE_ZOOM`])
+    pub zoom: u32,
+    /// All visited tiles, sorted by `x` then `y`
+    pub tiles: Vec<TileCoord>,
+    /// Number of visited tiles
+    pub total_tiles: u32,
+    /// Number of cluster tiles (visited with all four neighbours visited)
+    pub cluster_tiles: u32,
+    /// Size of the largest connected group of cluster tiles
+    pub max_cluster_size: u32,
+    /// Side length (in tiles) of the largest fully visited square
+    pub max_square_size: u32,
+    /// Top-left tile of the largest square
+    pub max_square_origin: Option<TileCoord>,
+}
+
+/// Tiles at `zoom` that a track passes through, including tiles crossed
+/// between consecutive points. Zooms above [`MAX_TILE_ZOOM`] are clamped.
+pub fn visited_tiles(points: &[GpsPoint], zoom: u32) -> HashSet<(u32, u32)> {
+    let zoom = zoom.min(MAX_TILE_ZOOM);
+    let mut tiles = HashSet::new();
+    let n = (1u64 << zoom) as f64;
+    let max_index = n - 1.0;
+    let mut mark = |x: f64, y: f64| {
+        tiles.insert((x.floor().clamp(0.0, max_index) as u32, y.floor().clamp(0.0, max_index) as u32));
+    };
+
+    let mut prev: Option<(f64, f64)> = None;
+    for point in points.iter().filter(|p| p.is_valid()) {
+        let (x, y) = world_tile_position(point.latitude, point.longitude, zoom);
+
+        if let Some((px, py)) = prev {
+            let (dx, dy) = (x - px, y - py);
+            let span = dx.abs().max(dy.abs());
+            // Don't walk glitches or the long way round across the antimeridian
+            if span <= MAX_WALK_TILES && dx.abs() < n / 2.0 {
+                let steps = (span * STEPS_PER_TILE).ceil() as usize;
+                for i in 1..steps {
+                    let t = i as f64 / steps as f64;
+                    mark(px + dx * t, py + dy * t);
+                }
+            }
+        }
+
+        mark(x, y);
+        prev = Some((x, y));
+    }
+
+    tiles
+}
+
+/// Compute exploration statistics from a set of visited tiles.
+/// Zooms above [`MAX_TILE_ZOOM`] are clamped, as in [`visited_tiles`].
+pub fn compute_exploration(tiles: &HashSet<(u32, u32)>, zoom: u32) -> ExplorationStats {
+    let zoom = zoom.min(MAX_TILE_ZOOM);
+    let mut sorted: Vec<(u32, u32)> = tiles.iter().copied().collect();
+    sorted.sort_unstable();
+
+    let (max_square_size, max_square_origin) = max_square(&sorted);
+
+    let cluster: HashSet<(u32, u32)> = sorted
+        .iter()
+        .copied()
+        .filter(|&tile| neighbours(tile).iter().all(|n| n.is_some_and(|n| tiles.contains(&n))))
+        .collect();
+
+    ExplorationStats {
+        zoom,
+        tiles: sorted.iter().map(|&(x, y)| TileCoord { z: zoom, x, y }).collect(),
+        total_tiles: sorted.len() as u32,
+        cluster_tiles: cluster.len() as u32,
+        max_cluster_size: largest_component(&cluster),
+        max_square_size,
+        max_square_origin: max_square_origin.map(|(x, y)| TileCoord { z: zoom, x, y }),
+    }
+}
+
+/// Exploration statistics from full GPS tracks `(activity_id, points)`.
+pub fn exploration_from_tracks(tracks: &[(String, Vec<GpsPoint>)], zoom: u32) -> ExplorationStats {
+    let mut tiles = HashSet::new();
+    for (_, points) in tracks {
+        tiles.extend(visited_tiles(points, zoom));
+    }
+    compute_exploration(&tiles, zoom)
+}
+
+/// Exploration statistics from route signatures.
+///
+/// Signatures are simplified, so lines between their points are walked;
+/// tiles only clipped by the original track at a corner may be missed.
+pub fn exploration_from_signatures(signatures: &[RouteSignature], zoom: u32) -> ExplorationStats {
+    let mut tiles = HashSet::new();
+    for sig in signatures {
+        tiles.extend(visited_tiles(&sig.points, zoom));
+    }
+    compute_exploration(&tiles, zoom)
+}
+
+/// Four edge neighbours (None outside the grid's top/left edge).
+fn neighbours((x, y): (u32, u32)) -> [Option<(u32, u32)>; 4] {
+    [
+        x.checked_sub(1).map(|x| (x, y)),
+        Some((x + 1, y)),
+        y.checked_sub(1).map(|y| (x, y)),
+        Some((x, y + 1)),
+    ]
+}
+
+/// Largest fully visited square: (side, top-left tile).
+/// `tiles` must be sorted by `(x, y)` so left/above/diagonal are seen first.
+fn max_square(tiles: &[(u32, u32)]) -> (u32, Option<(u32, u32)>) {
+    let mut side: HashMap<(u32, u32), u32> = HashMap::with_capacity(tiles.len());
+    let mut best = (0, None);
+
+    for &(x, y) in tiles {
+        let get = |dx: u32, dy: u32| -> u32 {
+            match (x.checked_sub(dx), y.checked_sub(dy)) {
+                (Some(nx), Some(ny)) => side.get(&(nx, ny)).copied().unwrap_or(0),
+                _ => 0,
+            }
+        };
+        let s = 1 + get(1, 0).min(get(0, 1)).min(get(1, 1));
+        side.insert((x, y), s);
+
+        // Prefer the top-left-most square on ties for stable output
+        if s > best.0 {
+            best = (s, Some((x + 1 - s, y + 1 - s)));
+        }
+    }
+
+    best
+}
+
+/// Size of the largest 4-connected component.
+fn largest_component(tiles: &HashSet<(u32, u32)>) -> u32 {
+    let mut seen: HashSet<(u32, u32)> = HashSet::with_capacity(tiles.len());
+    let mut largest = 0;
+
+    for &start in tiles {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut size = 0;
+        let mut stack = vec![start];
+        while let Some(tile) = stack.pop() {
+            size += 1;
+            for n in neighbours(tile).into_iter().flatten() {
+                if tiles.contains(&n) && seen.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+
+    largest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(x0: u32, y0: u32, side: u32) -> HashSet<(u32, u32)> {
+        (x0..x0 + side).flat_map(|x| (y0..y0 + side).map(move |y| (x, y))).collect()
+    }
+
+    #[test]
+    fn test_square_and_cluster() {
+        // 5x5 block: 3x3 interior are cluster tiles, plus a detached tile
+        let mut tiles = grid(100, 200, 5);
+        tiles.insert((120, 200));
+
+        let stats = compute_exploration(&tiles, EXPLORER_ZOOM);
+        assert_eq!(stats.total_tiles, 26);
+        assert_eq!(stats.max_square_size, 5);
+        assert_eq!(stats.max_square_origin, Some(TileCoord { z: EXPLORER_ZOOM, x: 100, y: 200 }));
+        assert_eq!(stats.cluster_tiles, 9);
+        assert_eq!(stats.max_cluster_size, 9);
+    }
+
+    #[test]
+    fn test_max_square_with_hole() {
+        // 4x4 block missing one corner tile: best square is 3x3
+        let mut tiles = grid(0, 0, 4);
+        tiles.remove(&(3, 3));
+        let stats = compute_exploration(&tiles, EXPLORER_ZOOM);
+        assert_eq!(stats.max_square_size, 3);
+        assert_eq!(stats.max_cluster_size, 4); // (1,1) (2,1) (1,2) (2,2)
+    }
+
+    #[test]
+    fn test_sparse_points_walk_tiles() {
+        // Two points ~20km apart: every tile between them is visited
+        let points = vec![GpsPoint::new(47.37, 8.40), GpsPoint::new(47.37, 8.67)];
+        let tiles = visited_tiles(&points, EXPLORER_ZOOM);
+
+        let mut xs: Vec<u32> = tiles.iter().map(|t| t.0).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        assert!(xs.len() >= 12);
+        assert!(xs.windows(2).all(|w| w[1] == w[0] + 1));
+        assert!(visited_tiles(&[], EXPLORER_ZOOM).is_empty());
+    }
+
+    #[test]
+    fn test_zoom_clamped_to_max() {
+        let track = vec![("ride".to_string(), vec![GpsPoint::new(47.37, 8.50), GpsPoint::new(47.37, 8.5001)])];
+        let max = exploration_from_tracks(&track, MAX_TILE_ZOOM);
+
+        for zoom in [30, 64] {
+            let stats = exploration_from_tracks(&track, zoom);
+            assert_eq!(stats.zoom, MAX_TILE_ZOOM);
+            assert_eq!(stats.tiles, max.tiles);
+            assert!(stats.tiles.iter().all(|t| t.z == MAX_TILE_ZOOM));
+        }
+    }
+}
@@ -0,0 +1,639 @@
+//! # Export
+//!
+//! Writes routes out in formats other tools and devices read.
+//!
+//! ## FIT Courses
+//! [`fit_course`] encodes a polyline as a FIT course file, so a matched route
+//! can be sent back to a Garmin or Wahoo head unit for navigation. The file
+//! holds a course name and sport, one lap spanning the route, timer start and
+//! stop events, and a record per point with its distance along the route.
+//! Devices expect timestamps, so each point gets one at a constant
+//! `speed_meters_per_second` from the start time.
+//!
+//! With an elevation per point, records carry altitude and each climb becomes
+//! a pair of course points: a valley where it starts and a summit where it
+//! tops out, named with its gain. A climb gains at least
+//! `min_climb_gain_meters` and ends once the road drops 10m below its top.
+//!
+//! ## Anonymized Sharing
+//! [`anonymized_gpx`] and [`anonymized_geojson`] write tracks that are safe
+//! to share publicly, e.g. frequent sections (see [`SharedTrack`]'s `From`
+//! impl, which keeps only name, sport and polyline). No timestamps are
+//! written. Points inside privacy zones are dropped, splitting a track where
+//! it passes through one, and coordinates are rounded to
+//! `coordinate_decimals` (4 decimals is about 11m).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::export::{FitCourseConfig, fit_course};
+//!
+//! let route: Vec<GpsPoint> = (0..=20).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.10)).collect();
+//! let config = FitCourseConfig { name: "Hill repeats".to_string(), ..FitCourseConfig::default() };
+//!
+//! let bytes = fit_course(&route, &[], &config);
+//! assert_eq!(&bytes[8..12], b".FIT");
+//! ```
+
+use crate::geo_utils::{haversine_distance, polyline_length, project_point_onto_route};
+use crate::network::{json_string, xml_escape};
+use crate::places::Place;
+use crate::{FrequentSection, GpsPoint, RouteGroup, RouteSignature};
+
+/// Seconds between the Unix epoch and the FIT epoch (1989-12-31T00:00:00Z)
+const FIT_EPOCH_OFFSET: i64 = 631_065_600;
+
+/// FIT profile version written in the header (21.32)
+const FIT_PROFILE_VERSION: u16 = 2132;
+
+/// Size of the FIT course name field, including its terminating zero
+const FIT_NAME_SIZE: usize = 16;
+
+/// Drop below a climb's top that ends it (meters)
+const CLIMB_END_DROP: f64 = 10.0;
+
+// Global message numbers
+const MSG_FILE_ID: u16 = 0;
+const MSG_LAP: u16 = 19;
+const MSG_RECORD: u16 = 20;
+const MSG_EVENT: u16 = 21;
+const MSG_COURSE: u16 = 31;
+const MSG_COURSE_POINT: u16 = 32;
+
+// Base types
+const ENUM: u8 = 0x00;
+const UINT16: u8 = 0x84;
+const SINT32: u8 = 0x85;
+const UINT32: u8 = 0x86;
+const STRING: u8 = 0x07;
+
+// Course point types
+const COURSE_POINT_SUMMIT: u8 = 1;
+const COURSE_POINT_VALLEY: u8 = 2;
+
+/// Options for [`fit_course`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct FitCourseConfig {
+    /// Course name shown on the device (up to 15 bytes are kept). Default: "Route"
+    pub name: String,
+    /// Sport type, e.g. "Ride" or "Run". Default: "Ride"
+    pub sport_type: String,
+    /// Pace for the point timestamps (m/s). Default: 5.0 (18 km/h)
+    pub speed_meters_per_second: f64,
+    /// Start time (Unix seconds). Default: None (now)
+    pub start_time: Option<i64>,
+    /// Smallest elevation gain that counts as a climb (meters). Default: 30
+    pub min_climb_gain_meters: f64,
+}
+
+impl Default for FitCourseConfig {
+    fn default() -> Self {
+        Self {
+            name: "Route".to_string(),
+            sport_type: "Ride".to_string(),
+            speed_meters_per_second: 5.0,
+            start_time: None,
+            min_climb_gain_meters: 30.0,
+        }
+    }
+}
+
+/// A climb between two polyline indices
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Climb {
+    start: usize,
+    top: usize,
+    gain: f64,
+}
+
+/// Encode `polyline` as a FIT course file.
+///
+/// `elevations` (meters, one per point) add altitude and climbs; pass an
+/// empty slice without them. Invalid points are skipped.
+pub fn fit_course(polyline: &[GpsPoint], elevations: &[f64], config: &FitCourseConfig) -> Vec<u8> {
+    let with_elevation = elevations.len() == polyline.len();
+    let (points, elevations): (Vec<GpsPoint>, Vec<f64>) = polyline
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.is_valid())
+        .map(|(i, &p)| (p, if with_elevation { elevations[i] } else { f64::NAN }))
+        .unzip();
+
+    let mut distances = Vec::with_capacity(points.len());
+    let mut along = 0.0;
+    for (i, p) in points.iter().enumerate() {
+        if i > 0 {
+            along += haversine_distance(&points[i - 1], p);
+        }
+        distances.push(along);
+    }
+
+    let start = config.start_time.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(FIT_EPOCH_OFFSET, |d| d.as_secs() as i64)
+    });
+    let start = (start - FIT_EPOCH_OFFSET).max(0) as u32;
+    let speed = config.speed_meters_per_second.max(0.1);
+    let time_at = |distance: f64| start + (distance / speed).round() as u32;
+    let total = distances.last().copied().unwrap_or(0.0);
+    let end = time_at(total);
+    let first = points.first().copied().unwrap_or(GpsPoint::new(0.0, 0.0));
+    let last = points.last().copied().unwrap_or(first);
+
+    let mut fit = FitWriter::default();
+
+    fit.define(0, MSG_FILE_ID, &[(0, 1, ENUM), (1, 2, UINT16), (2, 2, UINT16), (4, 4, UINT32)]);
+    fit.message(0, &[&[6], &255u16.to_le_bytes(), &0u16.to_le_bytes(), &start.to_le_bytes()]);
+
+    fit.define(1, MSG_COURSE, &[(5, FIT_NAME_SIZE as u8, STRING), (4, 1, ENUM)]);
+    fit.message(1, &[&fit_string(&config.name), &[fit_sport(&config.sport_type)]]);
+
+    fit.define(
+        2,
+        MSG_LAP,
+        &[
+            (253, 4, UINT32),
+            (2, 4, UINT32),
+            (3, 4, SINT32),
+            (4, 4, SINT32),
+            (5, 4, SINT32),
+            (6, 4, SINT32),
+            (7, 4, UINT32),
+            (8, 4, UINT32),
+            (9, 4, UINT32),
+        ],
+    );
+    let elapsed_ms = (end - start).saturating_mul(1000);
+    fit.message(
+        2,
+        &[
+            &end.to_le_bytes(),
+            &start.to_le_bytes(),
+            &semicircles(first.latitude),
+            &semicircles(first.longitude),
+            &semicircles(last.latitude),
+            &semicircles(last.longitude),
+            &elapsed_ms.to_le_bytes(),
+            &elapsed_ms.to_le_bytes(),
+            &centimeters(total),
+        ],
+    );
+
+    // Timer start (event 0, type start) and stop (type stop_all)
+    fit.define(3, MSG_EVENT, &[(253, 4, UINT32), (0, 1, ENUM), (1, 1, ENUM)]);
+    fit.message(3, &[&start.to_le_bytes(), &[0], &[0]]);
+
+    let mut record_fields = vec![(253, 4, UINT32), (0, 4, SINT32), (1, 4, SINT32), (5, 4, UINT32)];
+    if with_elevation {
+        record_fields.push((2, 2, UINT16));
+    }
+    fit.define(4, MSG_RECORD, &record_fields);
+    for (i, p) in points.iter().enumerate() {
+        let altitude = altitude(elevations[i]);
+        let mut values: Vec<&[u8]> = Vec::with_capacity(5);
+        let (timestamp, lat, lng, distance) =
+            (time_at(distances[i]).to_le_bytes(), semicircles(p.latitude), semicircles(p.longitude), centimeters(distances[i]));
+        values.extend([&timestamp[..], &lat[..], &lng[..], &distance[..]]);
+        if with_elevation {
+            values.push(&altitude);
+        }
+        fit.message(4, &values);
+    }
+
+    if with_elevation {
+        fit.define(
+            5,
+            MSG_COURSE_POINT,
+            &[(1, 4, UINT32), (2, 4, SINT32), (3, 4, SINT32), (4, 4, UINT32), (5, 1, ENUM), (6, FIT_NAME_SIZE as u8, STRING)],
+        );
+        for (n, climb) in find_climbs(&elevations, config.min_climb_gain_meters).iter().enumerate() {
+            let start_name = format!("Climb {}", n + 1);
+            let top_name = format!("Top {} +{:.0}m", n + 1, climb.gain);
+            for (i, kind, name) in [(climb.start, COURSE_POINT_VALLEY, start_name), (climb.top, COURSE_POINT_SUMMIT, top_name)] {
+                fit.message(
+                    5,
+                    &[
+                        &time_at(distances[i]).to_le_bytes(),
+                        &semicircles(points[i].latitude),
+                        &semicircles(points[i].longitude),
+                        &centimeters(distances[i]),
+                        &[kind],
+                        &fit_string(&name),
+                    ],
+                );
+            }
+        }
+    }
+
+    fit.message(3, &[&end.to_le_bytes(), &[0], &[4]]);
+    fit.finish()
+}
+
+/// FIT course for a route group, along its representative signature (the
+/// signature of its `group_id`, or of its first activity with one). None if
+/// no signature of the group is given.
+pub fn fit_course_for_group(
+    group: &RouteGroup,
+    signatures: &[RouteSignature],
+    config: &FitCourseConfig,
+) -> Option<Vec<u8>> {
+    let routes = crate::network::route_representatives(signatures, std::slice::from_ref(group));
+    let (_, _, signature) = routes.into_iter().find(|(id, _, _)| *id == group.group_id)?;
+    Some(fit_course(&signature.points, &[], config))
+}
+
+/// A circle whose points are never shared, e.g. around home or work
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct PrivacyZone {
+    pub center: GpsPoint,
+    /// Radius in meters
+    pub radius_meters: f64,
+}
+
+impl PrivacyZone {
+    /// Whether `point` lies within the zone.
+    pub fn contains(&self, point: &GpsPoint) -> bool {
+        haversine_distance(&self.center, point) <= self.radius_meters
+    }
+
+    /// Whether the leg from `a` to `b` passes through the zone.
+    fn crosses(&self, a: &GpsPoint, b: &GpsPoint) -> bool {
+        project_point_onto_route(&self.center, &[*a, *b])
+            .is_some_and(|projection| projection.offset <= self.radius_meters)
+    }
+}
+
+impl From<&Place> for PrivacyZone {
+    fn from(place: &Place) -> Self {
+        Self { center: place.center, radius_meters: place.radius_meters }
+    }
+}
+
+/// Options for [`anonymize_polyline`], [`anonymized_gpx`] and
+/// [`anonymized_geojson`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct AnonymizeConfig {
+    /// Areas whose points are removed. Default: none
+    pub privacy_zones: Vec<PrivacyZone>,
+    /// Decimal places kept in coordinates. Default: 4 (about 11m)
+    pub coordinate_decimals: u32,
+}
+
+impl Default for AnonymizeConfig {
+    fn default() -> Self {
+        Self {
+            privacy_zones: Vec::new(),
+            coordinate_decimals: 4,
+        }
+    }
+}
+
+/// A named track to share, with nothing but its shape
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SharedTrack {
+    pub name: String,
+    /// Sport type ("Run", "Ride", etc.)
+    pub sport_type: String,
+    pub polyline: Vec<GpsPoint>,
+}
+
+impl From<&FrequentSection> for SharedTrack {
+    /// The section's consensus polyline, named by its suggested name or ID.
+    fn from(section: &FrequentSection) -> Self {
+        Self {
+            name: section.suggested_name.clone().unwrap_or_else(|| section.id.clone()),
+            sport_type: section.sport_type.clone(),
+            polyline: section.polyline.clone(),
+        }
+    }
+}
+
+/// The pieces of `polyline` outside every privacy zone, with rounded
+/// coordinates. A track is split where a point, or the line between two
+/// points, falls in a zone; pieces shorter than two points are dropped, as
+/// are invalid points and repeats left by rounding.
+pub fn anonymize_polyline(polyline: &[GpsPoint], config: &AnonymizeConfig) -> Vec<Vec<GpsPoint>> {
+    let scale = 10f64.powi(config.coordinate_decimals.min(15) as i32);
+    let round = |p: &GpsPoint| GpsPoint::new((p.latitude * scale).round() / scale, (p.longitude * scale).round() / scale);
+    let zones = &config.privacy_zones;
+
+    let mut pieces: Vec<Vec<GpsPoint>> = Vec::new();
+    let mut piece: Vec<GpsPoint> = Vec::new();
+    let mut last: Option<GpsPoint> = None;
+    for p in polyline.iter().filter(|p| p.is_valid()) {
+        let hidden = zones.iter().any(|zone| {
+            zone.contains(p) || zone.contains(&round(p)) || last.is_some_and(|prev| zone.crosses(&prev, p))
+        });
+        if hidden {
+            pieces.push(std::mem::take(&mut piece));
+            last = None;
+            continue;
+        }
+        let rounded = round(p);
+        if piece.last() != Some(&rounded) {
+            piece.push(rounded);
+        }
+        last = Some(*p);
+    }
+    pieces.push(piece);
+    pieces.retain(|piece| piece.len() >= 2);
+    pieces
+}
+
+/// GPX 1.1 document with one `<trk>` per track and one `<trkseg>` per
+/// anonymized piece. Tracks left empty are skipped.
+pub fn anonymized_gpx(tracks: &[SharedTrack], config: &AnonymizeConfig) -> String {
+    let decimals = config.coordinate_decimals.min(15) as usize;
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<gpx version=\"1.1\" creator=\"route-matcher\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n");
+    for track in tracks {
+        let pieces = anonymize_polyline(&track.polyline, config);
+        if pieces.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "  <trk>\n    <name>{}</name>\n    <type>{}</type>\n",
+            xml_escape(&track.name),
+            xml_escape(&track.sport_type)
+        ));
+        for piece in &pieces {
+            out.push_str("    <trkseg>\n");
+            for p in piece {
+                out.push_str(&format!(
+                    "      <trkpt lat=\"{:.*}\" lon=\"{:.*}\"/>\n",
+                    decimals, p.latitude, decimals, p.longitude
+                ));
+            }
+            out.push_str("    </trkseg>\n");
+        }
+        out.push_str("  </trk>\n");
+    }
+    out.push_str("</gpx>\n");
+    out
+}
+
+/// GeoJSON FeatureCollection with a MultiLineString feature per track
+/// (properties `name`, `sport_type` and `distance_meters`). Tracks left
+/// empty are skipped.
+pub fn anonymized_geojson(tracks: &[SharedTrack], config: &AnonymizeConfig) -> String {
+    let decimals = config.coordinate_decimals.min(15) as usize;
+    let features: Vec<String> = tracks
+        .iter()
+        .filter_map(|track| {
+            let pieces = anonymize_polyline(&track.polyline, config);
+            if pieces.is_empty() {
+                return None;
+            }
+            let distance: f64 = pieces.iter().map(|piece| polyline_length(piece)).sum();
+            let lines: Vec<String> = pieces
+                .iter()
+                .map(|piece| {
+                    let coords: Vec<String> = piece
+                        .iter()
+                        .map(|p| format!("[{:.*},{:.*}]", decimals, p.longitude, decimals, p.latitude))
+                        .collect();
+                    format!("[{}]", coords.join(","))
+                })
+                .collect();
+            Some(format!(
+                r#"{{"type":"Feature","geometry":{{"type":"MultiLineString","coordinates":[{}]}},"properties":{{"name":{},"sport_type":{},"distance_meters":{:.0}}}}}"#,
+                lines.join(","),
+                json_string(&track.name),
+                json_string(&track.sport_type),
+                distance
+            ))
+        })
+        .collect();
+    format!(r#"{{"type":"FeatureCollection","features":[{}]}}"#, features.join(","))
+}
+
+/// Climbs along `elevations` gaining at least `min_gain` meters. A climb
+/// starts at the lowest point before it and ends at its top, once elevation
+/// drops [`CLIMB_END_DROP`] below that top.
+fn find_climbs(elevations: &[f64], min_gain: f64) -> Vec<Climb> {
+    let mut climbs = Vec::new();
+    let Some(first) = elevations.iter().position(|e| e.is_finite()) else {
+        return climbs;
+    };
+    let (mut low, mut high) = (first, first);
+
+    for (i, &e) in elevations.iter().enumerate().skip(first + 1) {
+        if !e.is_finite() {
+            continue;
+        }
+        if e > elevations[high] {
+            high = i;
+        }
+        if elevations[high] - e >= CLIMB_END_DROP || i + 1 == elevations.len() {
+            let gain = elevations[high] - elevations[low];
+            if high > low && gain >= min_gain {
+                climbs.push(Climb { start: low, top: high, gain });
+            }
+            if elevations[high] - e >= CLIMB_END_DROP {
+                (low, high) = (i, i);
+            }
+        } else if e < elevations[low] && high == low {
+            (low, high) = (i, i);
+        }
+    }
+    climbs
+}
+
+/// Builds the data records of a FIT file
+#[derive(Default)]
+struct FitWriter {
+    data: Vec<u8>,
+}
+
+impl FitWriter {
+    /// Definition message for `local`: little-endian fields of
+    /// (field number, size, base type).
+    fn define(&mut self, local: u8, global: u16, fields: &[(u8, u8, u8)]) {
+        self.data.extend([0x40 | local, 0, 0]);
+        self.data.extend(global.to_le_bytes());
+        self.data.push(fields.len() as u8);
+        for &(field, size, base_type) in fields {
+            self.data.extend([field, size, base_type]);
+        }
+    }
+
+    /// Data message for `local`, with the field values in definition order.
+    fn message(&mut self, local: u8, values: &[&[u8]]) {
+        self.data.push(local);
+        for value in values {
+            self.data.extend_from_slice(value);
+        }
+    }
+
+    /// The complete file: header with its CRC, data, then the file CRC.
+    fn finish(self) -> Vec<u8> {
+        let mut file = vec![14, 0x20];
+        file.extend(FIT_PROFILE_VERSION.to_le_bytes());
+        file.extend((self.data.len() as u32).to_le_bytes());
+        file.extend(b".FIT");
+        file.extend(fit_crc(&file).to_le_bytes());
+        file.extend(self.data);
+        file.extend(fit_crc(&file).to_le_bytes());
+        file
+    }
+}
+
+/// FIT CRC-16 of `bytes`.
+fn fit_crc(bytes: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800, 0xB401, 0x5000,
+        0x9C01, 0x8801, 0x4400,
+    ];
+    bytes.iter().fold(0u16, |crc, &byte| {
+        let crc = (crc >> 4) ^ TABLE[(crc & 0xF) as usize] ^ TABLE[(byte & 0xF) as usize];
+        (crc >> 4) ^ TABLE[(crc & 0xF) as usize] ^ TABLE[(byte >> 4) as usize]
+    })
+}
+
+/// sint32 semicircles for `degrees`.
+fn semicircles(degrees: f64) -> [u8; 4] {
+    ((degrees / 180.0 * 2_147_483_648.0).round() as i32).to_le_bytes()
+}
+
+/// uint32 distance in centimeters (FIT scale 100).
+fn centimeters(meters: f64) -> [u8; 4] {
+    ((meters * 100.0).round().max(0.0) as u32).to_le_bytes()
+}
+
+/// uint16 altitude (FIT scale 5, offset 500); the invalid marker for NaN.
+fn altitude(meters: f64) -> [u8; 2] {
+    if meters.is_finite() {
+        (((meters + 500.0) * 5.0).round().clamp(0.0, 65_534.0) as u16).to_le_bytes()
+    } else {
+        u16::MAX.to_le_bytes()
+    }
+}
+
+/// Fixed-size, zero-terminated FIT string, cut at a character boundary.
+fn fit_string(s: &str) -> [u8; FIT_NAME_SIZE] {
+    let mut end = s.len().min(FIT_NAME_SIZE - 1);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut bytes = [0u8; FIT_NAME_SIZE];
+    bytes[..end].copy_from_slice(&s.as_bytes()[..end]);
+    bytes
+}
+
+/// FIT `sport` enum value for a sport type.
+fn fit_sport(sport_type: &str) -> u8 {
+    match sport_type {
+        "Run" | "TrailRun" | "VirtualRun" => 1,
+        "Ride" | "GravelRide" | "MountainBikeRide" | "VirtualRide" => 2,
+        "Swim" => 5,
+        "Walk" => 11,
+        "NordicSki" => 12,
+        "AlpineSki" => 13,
+        "Rowing" => 15,
+        "Hike" => 17,
+        "EBikeRide" => 21,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::parse_fit;
+
+    fn route() -> Vec<GpsPoint> {
+        (0..=40).map(|i| GpsPoint::new(51.50 + i as f64 * 0.0005, -0.10)).collect()
+    }
+
+    #[test]
+    fn test_fit_course_round_trips_with_valid_crcs() {
+        let points = route();
+        let elevations: Vec<f64> = (0..=40).map(|i| if i <= 20 { 10.0 + 4.0 * i as f64 } else { 90.0 - 3.0 * (i - 20) as f64 }).collect();
+        let config = FitCourseConfig {
+            name: "Box Hill loop via the long way".to_string(),
+            start_time: Some(1_700_000_000),
+            ..FitCourseConfig::default()
+        };
+        let bytes = fit_course(&points, &elevations, &config);
+
+        // A CRC over data followed by its CRC is zero
+        assert_eq!(fit_crc(&bytes[..14]), 0);
+        assert_eq!(fit_crc(&bytes), 0);
+        let data_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+        assert_eq!(bytes.len(), 14 + data_size + 2);
+
+        let parsed = parse_fit(&bytes).unwrap();
+        assert_eq!(parsed.points.len(), points.len());
+        assert!(parsed.points.iter().zip(&points).all(|(a, b)| haversine_distance(a, b) < 0.1));
+        assert_eq!(parsed.timestamp, Some(1_700_000_000));
+
+        // The name is cut to the field; one climb gives a valley and a summit
+        assert!(bytes.windows(15).any(|w| w == b"Box Hill loop v"));
+        assert!(bytes.windows(8).any(|w| w == b"Top 1 +8"));
+    }
+
+    #[test]
+    fn test_find_climbs_with_hysteresis() {
+        // Up 40m with a 5m dip, down 20m, up 20m (too small), then flat
+        let elevations = [0.0, 10.0, 20.0, 15.0, 30.0, 40.0, 30.0, 20.0, 30.0, 40.0, 40.0];
+        let climbs = find_climbs(&elevations, 30.0);
+        assert_eq!(climbs, vec![Climb { start: 0, top: 5, gain: 40.0 }]);
+        assert!(find_climbs(&[], 30.0).is_empty());
+        assert!(find_climbs(&[f64::NAN, 5.0], 30.0).is_empty());
+    }
+
+    #[test]
+    fn test_fit_string_cuts_at_char_boundary() {
+        // The second "é" would straddle the 15-byte limit
+        let bytes = fit_string("Alpe d'Huez éé");
+        assert_eq!(&bytes[..14], "Alpe d'Huez é".as_bytes());
+        assert!(bytes[14..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_anonymize_splits_at_privacy_zones_and_rounds() {
+        let home = PrivacyZone { center: GpsPoint::new(51.505, -0.10), radius_meters: 200.0 };
+        let config = AnonymizeConfig { privacy_zones: vec![home], ..AnonymizeConfig::default() };
+        let pieces = anonymize_polyline(&route(), &config);
+
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().flatten().all(|p| !home.contains(p)));
+        assert!(pieces.iter().flatten().all(|p| ((p.latitude * 1e4).round() - p.latitude * 1e4).abs() < 1e-6));
+
+        // A straight line across the zone with no points inside is still cut
+        let sparse = [GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+        assert!(anonymize_polyline(&sparse, &config).is_empty());
+    }
+
+    #[test]
+    fn test_anonymized_gpx_and_geojson() {
+        let mut section = FrequentSection {
+            id: "sec_ride_1".to_string(),
+            sport_type: "Ride".to_string(),
+            polyline: route(),
+            activity_ids: vec!["private-activity".to_string()],
+            ..FrequentSection::default()
+        };
+        section.suggested_name = Some("Mall & <Strand>".to_string());
+        let tracks = [SharedTrack::from(&section)];
+        let config = AnonymizeConfig { coordinate_decimals: 3, ..AnonymizeConfig::default() };
+
+        let gpx = anonymized_gpx(&tracks, &config);
+        assert!(gpx.contains("<name>Mall &amp; &lt;Strand&gt;</name>"));
+        assert!(gpx.contains(r#"<trkpt lat="51.500" lon="-0.100"/>"#));
+        assert!(!gpx.contains("<time>") && !gpx.contains("private-activity"));
+
+        let geojson: serde_json::Value = serde_json::from_str(&anonymized_geojson(&tracks, &config)).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(feature["properties"]["name"], "Mall & <Strand>");
+        // Rounding to 3 decimals merges every other point
+        assert_eq!(feature["geometry"]["coordinates"][0].as_array().unwrap().len(), 21);
+        assert_eq!(feature["geometry"]["coordinates"][0][1][1], 51.501);
+    }
+}
@@ -11,10 +11,22 @@
 //! |----------|-------------|
 //! | [`haversine_distance`] | Great-circle distance between two GPS points |
 //! | [`polyline_length`] | Total length of a GPS track in meters |
+//! | [`DistanceModel`] | Choice of equirectangular, haversine or geodesic distances |
 //! | [`compute_bounds`] | Bounding box of a GPS track |
 //! | [`compute_center`] | Centroid of a GPS track |
 //! | [`bounds_overlap`] | Check if two bounding boxes overlap |
 //! | [`meters_to_degrees`] | Convert meters to approximate degrees at a latitude |
+//! | [`normalize_longitude`] | Wrap a longitude into [-180, 180) |
+//! | [`longitude_delta`] | Shortest signed longitude difference (antimeridian-aware) |
+//! | [`interpolate_point`] | Linear interpolation between two points (antimeridian-aware) |
+//! | [`project_point_onto_route`] | Closest point on a track, with distance along and offset |
+//! | [`bearing`] | Initial compass bearing from one point to another |
+//! | [`turn_angle`] | Signed change of direction at a point |
+//! | [`detect_turns`] | Significant direction changes along a track (e.g. for cue sheets) |
+//! | [`resample_polyline`] | A fixed number of points evenly spaced along a track |
+//! | [`resample_by_distance`] | Points at a fixed spacing along a track |
+//! | [`smooth_polyline`] | Moving average of a track, keeping its ends |
+//! | [`simplify_polyline`] | Douglas-Peucker simplification with a tolerance in meters |
 //!
 //! ## Example
 //!
@@ -32,7 +44,7 @@
 //! println!("Track length: {:.0}m", length);
 //!
 //! // Get bounding box
-//! let bounds = geo_utils::compute_bounds(&track);
+//! let bounds = geo_utils::compute_bounds(&track).unwrap();
 //! println!("Bounds: {:.4}N to {:.4}N", bounds.min_lat, bounds.max_lat);
 //!
 //! // Distance between two points
@@ -50,12 +62,32 @@
 //!
 //! Reference: [Haversine formula (Wikipedia)](https://en.wikipedia.org/wiki/Haversine_formula)
 //!
+//! ### Distance Models
+//!
+//! Haversine is the default everywhere. [`DistanceModel`] selects a faster or
+//! more precise formula for [`crate::MatchConfig::distance_model`] and
+//! [`crate::SectionConfig::distance_model`] (see `benches/distance.rs`):
+//!
+//! | Model | Relative cost | Error vs. WGS84 ellipsoid |
+//! |-------|---------------|---------------------------|
+//! | [`DistanceModel::Equirectangular`] | ~0.5x | Up to 0.5% (spherical) plus <0.1% within 50 km |
+//! | [`DistanceModel::Haversine`] | 1x | Up to 0.5% (spherical Earth) |
+//! | [`DistanceModel::Geodesic`] | ~17x | ~15 nm (Karney) |
+//!
 //! ### Coordinate System
 //!
 //! All functions expect WGS84 coordinates (latitude/longitude in degrees), which is the
 //! standard used by GPS receivers and mapping services.
+//!
+//! ### Antimeridian
+//!
+//! Tracks crossing ±180° longitude (Fiji, the Aleutians, Chukotka) are handled by
+//! always taking the shorter way around the globe. A [`Bounds`] that crosses the
+//! antimeridian is stored with `min_lng > max_lng` (the GeoJSON convention), e.g.
+//! `min_lng: 179.5, max_lng: -179.5`. Use [`Bounds::split_at_antimeridian`] to get
+//! ordinary boxes for libraries that cannot wrap.
 
-use geo::{Point, Haversine, Distance};
+use geo::{Coord, Distance, Geodesic, Haversine, LineString, Point, Simplify};
 use crate::{GpsPoint, Bounds};
 
 // =============================================================================
@@ -100,6 +132,63 @@ pub fn haversine_distance(p1: &GpsPoint, p2: &GpsPoint) -> f64 {
     Haversine::distance(point1, point2)
 }
 
+/// Mean Earth radius in meters (the sphere used by haversine)
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Formula used for distances between GPS points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum DistanceModel {
+    /// Flat-Earth approximation around the pair's mean latitude. Fastest,
+    /// accurate for the short hops between consecutive GPS points
+    Equirectangular,
+    /// Great-circle distance on a sphere
+    #[default]
+    Haversine,
+    /// Karney's geodesic on the WGS84 ellipsoid. Slowest, most precise
+    Geodesic,
+}
+
+impl DistanceModel {
+    /// Distance between two points in meters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use route_matcher::{GpsPoint, geo_utils::DistanceModel};
+    ///
+    /// let a = GpsPoint::new(51.5074, -0.1278);
+    /// let b = GpsPoint::new(51.5090, -0.1300);
+    ///
+    /// let haversine = DistanceModel::Haversine.distance(&a, &b);
+    /// let fast = DistanceModel::Equirectangular.distance(&a, &b);
+    /// let precise = DistanceModel::Geodesic.distance(&a, &b);
+    /// assert!((fast - haversine).abs() < 0.01);
+    /// assert!((precise - haversine).abs() / haversine < 0.005);
+    /// ```
+    #[inline]
+    pub fn distance(&self, p1: &GpsPoint, p2: &GpsPoint) -> f64 {
+        match self {
+            DistanceModel::Equirectangular => {
+                let mean_lat = ((p1.latitude + p2.latitude) / 2.0).to_radians();
+                let dx = longitude_delta(p1.longitude, p2.longitude).to_radians() * mean_lat.cos();
+                let dy = (p2.latitude - p1.latitude).to_radians();
+                EARTH_RADIUS_METERS * (dx * dx + dy * dy).sqrt()
+            }
+            DistanceModel::Haversine => haversine_distance(p1, p2),
+            DistanceModel::Geodesic => Geodesic::distance(
+                Point::new(p1.longitude, p1.latitude),
+                Point::new(p2.longitude, p2.latitude),
+            ),
+        }
+    }
+
+    /// Total length of a polyline in meters.
+    pub fn polyline_length(&self, points: &[GpsPoint]) -> f64 {
+        points.windows(2).map(|w| self.distance(&w[0], &w[1])).sum()
+    }
+}
+
 /// Calculate the total length of a polyline (GPS track) in meters.
 ///
 /// Sums the haversine distance between consecutive points. Empty or single-point
@@ -170,6 +259,68 @@ pub fn meters_to_degrees(meters: f64, latitude: f64) -> f64 {
     meters / meters_per_degree
 }
 
+// =============================================================================
+// Longitude Functions
+// =============================================================================
+
+/// Wrap a longitude into the range [-180, 180).
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::geo_utils;
+///
+/// assert_eq!(geo_utils::normalize_longitude(181.0), -179.0);
+/// assert_eq!(geo_utils::normalize_longitude(-190.0), 170.0);
+/// assert_eq!(geo_utils::normalize_longitude(12.5), 12.5);
+/// ```
+#[inline]
+pub fn normalize_longitude(lng: f64) -> f64 {
+    (lng + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Shortest signed longitude difference from `from` to `to`, in degrees.
+///
+/// Positive values are eastwards. Crossing the antimeridian gives a small
+/// difference rather than one close to ±360°.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::geo_utils;
+///
+/// // 179.9°E to 179.9°W is 0.2° east, not 359.8° west
+/// assert!((geo_utils::longitude_delta(179.9, -179.9) - 0.2).abs() < 1e-9);
+/// assert!((geo_utils::longitude_delta(10.0, 12.0) - 2.0).abs() < 1e-9);
+/// ```
+#[inline]
+pub fn longitude_delta(from: f64, to: f64) -> f64 {
+    normalize_longitude(to - from)
+}
+
+/// Linearly interpolate between two GPS points (`t` = 0 gives `a`, 1 gives `b`).
+///
+/// Takes the short way around the globe, so interpolating across the
+/// antimeridian stays near ±180° instead of sweeping through 0°.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// let a = GpsPoint::new(-17.0, 179.9);
+/// let b = GpsPoint::new(-17.0, -179.9);
+/// let mid = geo_utils::interpolate_point(&a, &b, 0.5);
+/// assert!(mid.longitude.abs() > 179.9);
+/// ```
+#[inline]
+pub fn interpolate_point(a: &GpsPoint, b: &GpsPoint, t: f64) -> GpsPoint {
+    GpsPoint::new(
+        a.latitude + t * (b.latitude - a.latitude),
+        normalize_longitude(a.longitude + t * longitude_delta(a.longitude, b.longitude)),
+    )
+}
+
 // =============================================================================
 // Bounding Box Functions
 // =============================================================================
@@ -185,8 +336,8 @@ pub fn meters_to_degrees(meters: f64, latitude: f64) -> f64 {
 ///
 /// # Returns
 ///
-/// A [`Bounds`] struct with the bounding box coordinates. For empty input,
-/// returns a bounds with MIN/MAX values that will fail any overlap check.
+/// A [`Bounds`] struct with the bounding box coordinates of the valid points
+/// (see [`GpsPoint::is_valid`]), or `None` if there are none.
 ///
 /// # Example
 ///
@@ -199,26 +350,42 @@ pub fn meters_to_degrees(meters: f64, latitude: f64) -> f64 {
 ///     GpsPoint::new(51.5050, -0.1250),
 /// ];
 ///
-/// let bounds = geo_utils::compute_bounds(&track);
+/// let bounds = geo_utils::compute_bounds(&track).unwrap();
 /// assert_eq!(bounds.min_lat, 51.5000);
 /// assert_eq!(bounds.max_lat, 51.5100);
 /// assert_eq!(bounds.min_lng, -0.1300);
 /// assert_eq!(bounds.max_lng, -0.1200);
 /// ```
-pub fn compute_bounds(points: &[GpsPoint]) -> Bounds {
+pub fn compute_bounds(points: &[GpsPoint]) -> Option<Bounds> {
+    let mut valid = points.iter().filter(|p| p.is_valid()).peekable();
+    valid.peek()?;
+
     let mut min_lat = f64::MAX;
     let mut max_lat = f64::MIN;
     let mut min_lng = f64::MAX;
     let mut max_lng = f64::MIN;
+    // Longitudes shifted into [0, 360) to detect antimeridian crossings
+    let mut min_shifted = f64::MAX;
+    let mut max_shifted = f64::MIN;
 
-    for p in points {
+    for p in valid {
         min_lat = min_lat.min(p.latitude);
         max_lat = max_lat.max(p.latitude);
         min_lng = min_lng.min(p.longitude);
         max_lng = max_lng.max(p.longitude);
+
+        let shifted = if p.longitude < 0.0 { p.longitude + 360.0 } else { p.longitude };
+        min_shifted = min_shifted.min(shifted);
+        max_shifted = max_shifted.max(shifted);
+    }
+
+    // Take the shorter way around: a track from 179.9° to -179.9° spans 0.2°, not 359.8°
+    if max_lng - min_lng > 180.0 && max_shifted - min_shifted < max_lng - min_lng {
+        min_lng = normalize_longitude(min_shifted);
+        max_lng = normalize_longitude(max_shifted);
     }
 
-    Bounds { min_lat, max_lat, min_lng, max_lng }
+    Some(Bounds { min_lat, max_lat, min_lng, max_lng })
 }
 
 /// Compute the bounding box as a tuple (min_lat, max_lat, min_lng, max_lng).
@@ -232,11 +399,10 @@ pub fn compute_bounds(points: &[GpsPoint]) -> Bounds {
 ///
 /// # Returns
 ///
-/// Tuple of (min_lat, max_lat, min_lng, max_lng).
+/// Tuple of (min_lat, max_lat, min_lng, max_lng), or `None` without valid points.
 #[inline]
-pub fn compute_bounds_tuple(points: &[GpsPoint]) -> (f64, f64, f64, f64) {
-    let bounds = compute_bounds(points);
-    (bounds.min_lat, bounds.max_lat, bounds.min_lng, bounds.max_lng)
+pub fn compute_bounds_tuple(points: &[GpsPoint]) -> Option<(f64, f64, f64, f64)> {
+    compute_bounds(points).map(|b| (b.min_lat, b.max_lat, b.min_lng, b.max_lng))
 }
 
 /// Check if two bounding boxes overlap, with an optional buffer.
@@ -279,10 +445,30 @@ pub fn compute_bounds_tuple(points: &[GpsPoint]) -> (f64, f64, f64, f64) {
 pub fn bounds_overlap(a: &Bounds, b: &Bounds, buffer_meters: f64, reference_lat: f64) -> bool {
     let buffer_deg = meters_to_degrees(buffer_meters, reference_lat);
 
-    !(a.max_lat + buffer_deg < b.min_lat ||
-      b.max_lat + buffer_deg < a.min_lat ||
-      a.max_lng + buffer_deg < b.min_lng ||
-      b.max_lng + buffer_deg < a.min_lng)
+    if a.max_lat + buffer_deg < b.min_lat || b.max_lat + buffer_deg < a.min_lat {
+        return false;
+    }
+
+    // Near a pole all longitudes converge, so the longitude test is meaningless
+    let lat_buffer = buffer_meters / 111_320.0;
+    if a.max_lat.max(b.max_lat) + lat_buffer >= 90.0 || a.min_lat.min(b.min_lat) - lat_buffer <= -90.0 {
+        return true;
+    }
+
+    // Compare longitude ranges piecewise so boxes crossing ±180° still match
+    a.split_at_antimeridian().iter().any(|pa| {
+        b.split_at_antimeridian().iter().any(|pb| {
+            pa.max_lng + buffer_deg >= pb.min_lng && pb.max_lng + buffer_deg >= pa.min_lng
+        })
+    }) || lng_overlap_across_antimeridian(a, b, buffer_deg)
+}
+
+/// Whether two boxes on opposite sides of ±180° come within `buffer_deg` of each other.
+fn lng_overlap_across_antimeridian(a: &Bounds, b: &Bounds, buffer_deg: f64) -> bool {
+    let gap_east = |west: &Bounds, east: &Bounds| (east.min_lng + 360.0) - west.max_lng;
+    let gap_a_b = gap_east(a, b);
+    let gap_b_a = gap_east(b, a);
+    (0.0..=buffer_deg).contains(&gap_a_b) || (0.0..=buffer_deg).contains(&gap_b_a)
 }
 
 // =============================================================================
@@ -304,9 +490,9 @@ pub fn bounds_overlap(a: &Bounds, b: &Bounds, buffer_meters: f64, reference_lat:
 ///
 /// # Notes
 ///
-/// For tracks spanning large areas or crossing the antimeridian (180°/-180° longitude),
-/// this simple averaging may produce unexpected results. For such cases, consider
-/// using a proper spherical centroid calculation.
+/// Longitudes are averaged relative to the first point, so tracks crossing the
+/// antimeridian (180°/-180° longitude) get a sensible center. For tracks spanning
+/// large areas, consider using a proper spherical centroid calculation.
 ///
 /// # Example
 ///
@@ -327,11 +513,421 @@ pub fn compute_center(points: &[GpsPoint]) -> GpsPoint {
         return GpsPoint::new(0.0, 0.0);
     }
 
+    // Average longitude offsets from the first point so tracks crossing ±180° stay together
+    let origin_lng = points[0].longitude;
     let sum_lat: f64 = points.iter().map(|p| p.latitude).sum();
-    let sum_lng: f64 = points.iter().map(|p| p.longitude).sum();
+    let sum_lng_delta: f64 = points.iter().map(|p| longitude_delta(origin_lng, p.longitude)).sum();
     let n = points.len() as f64;
 
-    GpsPoint::new(sum_lat / n, sum_lng / n)
+    GpsPoint::new(sum_lat / n, normalize_longitude(origin_lng + sum_lng_delta / n))
+}
+
+// =============================================================================
+// Projection Functions
+// =============================================================================
+
+/// Result of projecting a GPS point onto a route polyline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct RouteProjection {
+    /// Closest point on the route polyline
+    pub point: GpsPoint,
+    /// Distance in meters from the route start to the projected point
+    pub distance_along: f64,
+    /// Distance in meters from the query point to the projected point
+    pub offset: f64,
+    /// Fraction of the route completed at the projected point (0.0-1.0)
+    pub fraction: f64,
+}
+
+/// Project a GPS point onto the closest position along a route polyline.
+///
+/// Each leg of the polyline is treated as a straight line in a local
+/// equirectangular projection, which is accurate for the short legs of a
+/// GPS track. Returns `None` for routes with fewer than 2 points.
+///
+/// # Arguments
+///
+/// * `point` - The point to project (e.g. the current location)
+/// * `route` - The route polyline
+///
+/// # Returns
+///
+/// A [`RouteProjection`] with the projected point, distance along the route,
+/// and the perpendicular offset from the route.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// let route = vec![
+///     GpsPoint::new(51.500, -0.100),
+///     GpsPoint::new(51.510, -0.100),
+/// ];
+///
+/// // A point ~70m east of the route's midpoint
+/// let proj = geo_utils::project_point_onto_route(&GpsPoint::new(51.505, -0.099), &route).unwrap();
+/// assert!((proj.fraction - 0.5).abs() < 0.01);
+/// assert!((proj.offset - 69.0).abs() < 2.0);
+/// ```
+pub fn project_point_onto_route(point: &GpsPoint, route: &[GpsPoint]) -> Option<RouteProjection> {
+    if route.len() < 2 {
+        return None;
+    }
+
+    let mut best: Option<(GpsPoint, f64, f64)> = None; // (projected, distance_along, offset)
+    let mut cumulative = 0.0;
+
+    for w in route.windows(2) {
+        let (a, b) = (&w[0], &w[1]);
+        let leg_length = haversine_distance(a, b);
+
+        // Local planar coordinates (meters) relative to a
+        let cos_lat = a.latitude.to_radians().cos();
+        let bx = longitude_delta(a.longitude, b.longitude) * cos_lat;
+        let by = b.latitude - a.latitude;
+        let px = longitude_delta(a.longitude, point.longitude) * cos_lat;
+        let py = point.latitude - a.latitude;
+
+        let len_sq = bx * bx + by * by;
+        let t = if len_sq > 0.0 {
+            ((px * bx + py * by) / len_sq).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let projected = interpolate_point(a, b, t);
+        let offset = haversine_distance(point, &projected);
+
+        if best.is_none_or(|(_, _, best_offset)| offset < best_offset) {
+            best = Some((projected, cumulative + t * leg_length, offset));
+        }
+
+        cumulative += leg_length;
+    }
+
+    best.map(|(projected, distance_along, offset)| RouteProjection {
+        point: projected,
+        distance_along,
+        offset,
+        fraction: if cumulative > 0.0 { distance_along / cumulative } else { 0.0 },
+    })
+}
+
+// =============================================================================
+// Bearing Functions
+// =============================================================================
+
+/// Heading is measured over at least this distance before and after a point,
+/// so GPS jitter between dense points doesn't register as turns.
+const TURN_WINDOW_METERS: f64 = 20.0;
+
+/// Initial great-circle bearing from `p1` to `p2` in degrees (0 = north,
+/// 90 = east), in the range [0, 360).
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// let origin = GpsPoint::new(0.0, 0.0);
+/// assert!((geo_utils::bearing(&origin, &GpsPoint::new(1.0, 0.0)) - 0.0).abs() < 1e-9);
+/// assert!((geo_utils::bearing(&origin, &GpsPoint::new(0.0, 1.0)) - 90.0).abs() < 1e-9);
+/// ```
+pub fn bearing(p1: &GpsPoint, p2: &GpsPoint) -> f64 {
+    let lat1 = p1.latitude.to_radians();
+    let lat2 = p2.latitude.to_radians();
+    let dlng = longitude_delta(p1.longitude, p2.longitude).to_radians();
+
+    let y = dlng.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlng.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Signed change of direction at `b` when travelling `a` → `b` → `c`, in
+/// degrees within (-180, 180]. Positive is a right turn, negative a left turn.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// // Heading north, then east: a right turn
+/// let angle = geo_utils::turn_angle(
+///     &GpsPoint::new(51.500, -0.100),
+///     &GpsPoint::new(51.501, -0.100),
+///     &GpsPoint::new(51.501, -0.098),
+/// );
+/// assert!((angle - 90.0).abs() < 1.0);
+/// ```
+pub fn turn_angle(a: &GpsPoint, b: &GpsPoint, c: &GpsPoint) -> f64 {
+    let change = bearing(b, c) - bearing(a, b);
+    let wrapped = (change + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 { 180.0 } else { wrapped }
+}
+
+/// A significant change of direction along a track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct Turn {
+    /// Index of the turn's apex in the track
+    pub index: u32,
+    /// Location of the apex
+    pub point: GpsPoint,
+    /// Signed turn angle in degrees (positive = right, see [`turn_angle`])
+    pub angle: f64,
+    /// Distance in meters from the track start to the apex
+    pub distance_along: f64,
+}
+
+/// Find direction changes of at least `min_angle_degrees` along a track.
+///
+/// Headings are measured over ~20m either side of each point, and each bend
+/// is reported once at its sharpest point, so jitter and curves drawn with
+/// many points don't produce a burst of small turns.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// // North for ~200m, then west for ~200m
+/// let mut track: Vec<GpsPoint> = (0..=20).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect();
+/// track.extend((1..=14).map(|i| GpsPoint::new(51.502, -0.1 - i as f64 * 0.0001)));
+///
+/// let turns = geo_utils::detect_turns(&track, 45.0);
+/// assert_eq!(turns.len(), 1);
+/// assert_eq!(turns[0].index, 20);
+/// assert!(turns[0].angle < -80.0); // left
+/// ```
+pub fn detect_turns(track: &[GpsPoint], min_angle_degrees: f64) -> Vec<Turn> {
+    if track.len() < 3 {
+        return Vec::new();
+    }
+
+    let mut cumulative = Vec::with_capacity(track.len());
+    let mut total = 0.0;
+    cumulative.push(0.0);
+    for w in track.windows(2) {
+        total += haversine_distance(&w[0], &w[1]);
+        cumulative.push(total);
+    }
+
+    let mut turns: Vec<Turn> = Vec::new();
+    let mut before = 0;
+    let mut after = 0;
+    // Sharpest point of the bend currently being traversed
+    let mut bend: Option<Turn> = None;
+
+    for i in 1..track.len() - 1 {
+        while before + 1 < i && cumulative[i] - cumulative[before + 1] >= TURN_WINDOW_METERS {
+            before += 1;
+        }
+        after = after.max(i + 1);
+        while after + 1 < track.len() && cumulative[after] - cumulative[i] < TURN_WINDOW_METERS {
+            after += 1;
+        }
+
+        let angle = turn_angle(&track[before], &track[i], &track[after]);
+        if angle.abs() >= min_angle_degrees {
+            let turn = Turn { index: i as u32, point: track[i], angle, distance_along: cumulative[i] };
+            match bend {
+                Some(current) if current.angle.signum() == angle.signum() => {
+                    if angle.abs() > current.angle.abs() {
+                        bend = Some(turn);
+                    }
+                }
+                Some(current) => {
+                    turns.push(current);
+                    bend = Some(turn);
+                }
+                None => bend = Some(turn),
+            }
+        } else if let Some(current) = bend.take() {
+            turns.push(current);
+        }
+    }
+    turns.extend(bend);
+
+    turns
+}
+
+// =============================================================================
+// Polyline Functions
+// =============================================================================
+
+/// Resample a track to exactly `count` points, evenly spaced by distance.
+///
+/// Tracks that already have `count` points, or fewer than 2, are returned
+/// unchanged. This is the resampling used for route AMD.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+/// let resampled = geo_utils::resample_polyline(&track, 11);
+/// assert_eq!(resampled.len(), 11);
+/// assert!((resampled[5].latitude - 51.505).abs() < 1e-9);
+/// ```
+pub fn resample_polyline(points: &[GpsPoint], count: usize) -> Vec<GpsPoint> {
+    if points.len() == count {
+        return points.to_vec();
+    }
+    resample_evenly(points, count)
+}
+
+/// Resample a track to exactly `count` evenly spaced points, even if it
+/// already has `count` points. Tracks with fewer than 2 points are returned
+/// unchanged, and a zero-length track as its first `count` points. This is
+/// the resampling used for section AMD.
+pub(crate) fn resample_evenly(points: &[GpsPoint], count: usize) -> Vec<GpsPoint> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+
+    let total_dist = polyline_length(points);
+    if total_dist == 0.0 || count < 2 {
+        return points[..count.min(points.len())].to_vec();
+    }
+
+    let step_dist = total_dist / (count - 1) as f64;
+    let mut resampled: Vec<GpsPoint> = vec![points[0]];
+
+    let mut accumulated = 0.0;
+    let mut next_threshold = step_dist;
+    let mut prev_point = &points[0];
+
+    for curr in points.iter().skip(1) {
+        let seg_dist = haversine_distance(prev_point, curr);
+
+        while accumulated + seg_dist >= next_threshold && resampled.len() < count - 1 {
+            // Interpolate point at the threshold distance
+            let ratio = (next_threshold - accumulated) / seg_dist;
+            resampled.push(interpolate_point(prev_point, curr, ratio));
+            next_threshold += step_dist;
+        }
+
+        accumulated += seg_dist;
+        prev_point = curr;
+    }
+
+    // Always include the last point
+    if resampled.len() < count {
+        resampled.push(*points.last().unwrap());
+    }
+
+    resampled
+}
+
+/// Resample a track to a point every `spacing_meters` along it, plus its
+/// last point.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// // About 1112m
+/// let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+/// let resampled = geo_utils::resample_by_distance(&track, 100.0);
+/// assert_eq!(resampled.len(), 13);
+/// ```
+pub fn resample_by_distance(points: &[GpsPoint], spacing_meters: f64) -> Vec<GpsPoint> {
+    let total = polyline_length(points);
+    if points.len() < 2 || spacing_meters.is_nan() || spacing_meters <= 0.0 || total == 0.0 {
+        return points.to_vec();
+    }
+    let count = (total / spacing_meters).floor() as usize + 1;
+
+    let mut resampled = Vec::with_capacity(count + 1);
+    let mut accumulated = 0.0;
+    for w in points.windows(2) {
+        let seg_dist = haversine_distance(&w[0], &w[1]);
+        while resampled.len() < count && resampled.len() as f64 * spacing_meters <= accumulated + seg_dist {
+            let ratio = if seg_dist > 0.0 { (resampled.len() as f64 * spacing_meters - accumulated) / seg_dist } else { 0.0 };
+            resampled.push(interpolate_point(&w[0], &w[1], ratio.clamp(0.0, 1.0)));
+        }
+        accumulated += seg_dist;
+    }
+    let last = *points.last().unwrap();
+    if resampled.last().is_none_or(|p| haversine_distance(p, &last) > 1e-6) {
+        resampled.push(last);
+    }
+    resampled
+}
+
+/// Smooth a track with a centered moving average over `window` points
+/// (rounded up to an odd number, shrinking near the ends). The first and
+/// last points are kept.
+///
+/// For removing GPS jitter before matching, see [`crate::preprocess`].
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// let zigzag: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1 + (i % 2) as f64 * 0.001)).collect();
+/// let smooth = geo_utils::smooth_polyline(&zigzag, 3);
+/// assert!((smooth[4].longitude - zigzag[4].longitude).abs() < 0.001);
+/// assert_eq!(smooth[0], zigzag[0]);
+/// ```
+pub fn smooth_polyline(points: &[GpsPoint], window: u32) -> Vec<GpsPoint> {
+    let half = (window / 2) as usize;
+    if points.len() < 3 || half == 0 {
+        return points.to_vec();
+    }
+    let last = points.len() - 1;
+    (0..points.len())
+        .map(|i| {
+            let reach = half.min(i).min(last - i);
+            if reach == 0 {
+                return points[i];
+            }
+            let neighbours = &points[i - reach..=i + reach];
+            let n = neighbours.len() as f64;
+            let center = points[i];
+            // Longitudes relative to the point, so the antimeridian doesn't split the average
+            let latitude = neighbours.iter().map(|p| p.latitude).sum::<f64>() / n;
+            let offset = neighbours.iter().map(|p| longitude_delta(center.longitude, p.longitude)).sum::<f64>() / n;
+            GpsPoint::new(latitude, normalize_longitude(center.longitude + offset))
+        })
+        .collect()
+}
+
+/// Simplify a track with Douglas-Peucker, dropping points closer than
+/// `tolerance_meters` to the simplified line. Distances are scaled at the
+/// track's mean latitude, so the tolerance is the same in every direction.
+///
+/// # Example
+///
+/// ```rust
+/// use route_matcher::{GpsPoint, geo_utils};
+///
+/// // A straight road with 1m of jitter
+/// let track: Vec<GpsPoint> = (0..100).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1 + (i % 2) as f64 * 0.00001)).collect();
+/// let simplified = geo_utils::simplify_polyline(&track, 5.0);
+/// assert_eq!(simplified.len(), 2);
+/// ```
+pub fn simplify_polyline(points: &[GpsPoint], tolerance_meters: f64) -> Vec<GpsPoint> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let origin = points[0].longitude;
+    let mean_latitude = points.iter().map(|p| p.latitude).sum::<f64>() / points.len() as f64;
+    let lng_scale = mean_latitude.to_radians().cos().max(0.01);
+    let line: LineString<f64> = points
+        .iter()
+        .map(|p| Coord { x: longitude_delta(origin, p.longitude) * lng_scale, y: p.latitude })
+        .collect();
+    line.simplify(&(tolerance_meters / 111_320.0))
+        .0
+        .into_iter()
+        .map(|c| GpsPoint::new(c.y, normalize_longitude(origin + c.x / lng_scale)))
+        .collect()
 }
 
 // =============================================================================
@@ -390,12 +986,17 @@ mod tests {
             GpsPoint::new(51.50, -0.13),
             GpsPoint::new(51.51, -0.12),
             GpsPoint::new(51.505, -0.125),
+            GpsPoint::new(f64::NAN, -0.125),
+            GpsPoint::new(95.0, 200.0),
         ];
-        let bounds = compute_bounds(&track);
+        let bounds = compute_bounds(&track).unwrap();
         assert_eq!(bounds.min_lat, 51.50);
         assert_eq!(bounds.max_lat, 51.51);
         assert_eq!(bounds.min_lng, -0.13);
         assert_eq!(bounds.max_lng, -0.12);
+
+        assert!(compute_bounds(&[]).is_none());
+        assert!(compute_bounds(&[GpsPoint::new(f64::NAN, 0.0)]).is_none());
     }
 
     #[test]
@@ -439,6 +1040,75 @@ mod tests {
         assert!(bounds_overlap(&a, &b, 5000.0, 51.5));
     }
 
+    #[test]
+    fn test_compute_bounds_antimeridian() {
+        // Fiji track crossing 180°
+        let track = vec![
+            GpsPoint::new(-16.80, 179.95),
+            GpsPoint::new(-16.81, 179.99),
+            GpsPoint::new(-16.82, -179.97),
+        ];
+        let bounds = compute_bounds(&track).unwrap();
+        assert!(bounds.crosses_antimeridian());
+        assert!(approx_eq(bounds.min_lng, 179.95, 1e-9));
+        assert!(approx_eq(bounds.max_lng, -179.97, 1e-9));
+        assert!(compute_center(&track).longitude.abs() > 179.9);
+
+        // Tracks on either side of 0° are unaffected
+        let greenwich = vec![GpsPoint::new(51.48, -0.01), GpsPoint::new(51.48, 0.01)];
+        assert!(!compute_bounds(&greenwich).unwrap().crosses_antimeridian());
+    }
+
+    #[test]
+    fn test_bounds_overlap_antimeridian() {
+        let fiji = Bounds { min_lat: -16.9, max_lat: -16.7, min_lng: 179.9, max_lng: -179.9 };
+        let west_of_line = Bounds { min_lat: -16.85, max_lat: -16.75, min_lng: 179.95, max_lng: 179.99 };
+        let east_of_line = Bounds { min_lat: -16.85, max_lat: -16.75, min_lng: -179.99, max_lng: -179.95 };
+        let greenwich = Bounds { min_lat: -16.85, max_lat: -16.75, min_lng: -0.1, max_lng: 0.1 };
+
+        assert!(bounds_overlap(&fiji, &west_of_line, 0.0, -16.8));
+        assert!(bounds_overlap(&fiji, &east_of_line, 0.0, -16.8));
+        assert!(!bounds_overlap(&fiji, &greenwich, 0.0, -16.8));
+
+        // Aleutians: boxes either side of the line, 1.5km apart across it
+        let amchitka = Bounds { min_lat: 51.9, max_lat: 52.0, min_lng: 179.5, max_lng: 179.99 };
+        let semisopochnoi = Bounds { min_lat: 51.9, max_lat: 52.0, min_lng: -179.99, max_lng: -179.5 };
+        assert!(!bounds_overlap(&amchitka, &semisopochnoi, 0.0, 51.95));
+        assert!(bounds_overlap(&amchitka, &semisopochnoi, 2000.0, 51.95));
+    }
+
+    #[test]
+    fn test_bounds_overlap_polar() {
+        // Near the North Pole, boxes on opposite sides of the globe are close together
+        let a = Bounds { min_lat: 89.99, max_lat: 89.999, min_lng: 0.0, max_lng: 1.0 };
+        let b = Bounds { min_lat: 89.99, max_lat: 89.999, min_lng: 179.0, max_lng: 179.5 };
+        assert!(bounds_overlap(&a, &b, 500.0, 89.99));
+        assert!(!bounds_overlap(&a, &b, 0.0, 89.99));
+    }
+
+    #[test]
+    fn test_project_point_onto_route() {
+        let route = vec![
+            GpsPoint::new(51.500, -0.100),
+            GpsPoint::new(51.510, -0.100),
+            GpsPoint::new(51.510, -0.090),
+        ];
+        let total = polyline_length(&route);
+
+        // Point on the route start
+        let proj = project_point_onto_route(&route[0], &route).unwrap();
+        assert!(approx_eq(proj.distance_along, 0.0, 0.01));
+        assert!(approx_eq(proj.offset, 0.0, 0.01));
+
+        // Point beyond the end projects to the end
+        let proj = project_point_onto_route(&GpsPoint::new(51.510, -0.080), &route).unwrap();
+        assert!(approx_eq(proj.distance_along, total, 1.0));
+        assert!(approx_eq(proj.fraction, 1.0, 0.001));
+
+        // Too-short routes have no projection
+        assert!(project_point_onto_route(&route[0], &route[..1]).is_none());
+    }
+
     #[test]
     fn test_meters_to_degrees() {
         // At equator, 111km = 1 degree
@@ -449,4 +1119,110 @@ mod tests {
         let deg_45 = meters_to_degrees(111_320.0, 45.0);
         assert!(deg_45 > 1.0);
     }
+
+    #[test]
+    fn test_bearing_and_turn_angle() {
+        let origin = GpsPoint::new(0.0, 0.0);
+        assert!(approx_eq(bearing(&origin, &GpsPoint::new(-1.0, 0.0)), 180.0, 1e-9));
+        assert!(approx_eq(bearing(&origin, &GpsPoint::new(0.0, -1.0)), 270.0, 1e-9));
+        // Across the antimeridian, heading east
+        assert!(approx_eq(bearing(&GpsPoint::new(0.0, 179.9), &GpsPoint::new(0.0, -179.9)), 90.0, 1e-6));
+
+        let a = GpsPoint::new(0.0, 0.0);
+        let b = GpsPoint::new(0.001, 0.0);
+        assert!(approx_eq(turn_angle(&a, &b, &GpsPoint::new(0.002, 0.0)), 0.0, 1e-6));
+        assert!(approx_eq(turn_angle(&a, &b, &GpsPoint::new(0.001, -0.001)), -90.0, 0.1));
+        assert!(approx_eq(turn_angle(&a, &b, &a), 180.0, 1e-6));
+    }
+
+    #[test]
+    fn test_detect_turns_ignores_jitter() {
+        // ~300m north with 2m lateral jitter every point, then a right turn east
+        let mut track: Vec<GpsPoint> = (0..=60)
+            .map(|i| {
+                let jitter = if i % 2 == 0 { 0.00002 } else { -0.00002 };
+                GpsPoint::new(51.5 + i as f64 * 0.00005, -0.1 + jitter)
+            })
+            .collect();
+        track.extend((1..=60).map(|i| GpsPoint::new(51.503, -0.1 + i as f64 * 0.00008)));
+
+        let turns = detect_turns(&track, 45.0);
+        assert_eq!(turns.len(), 1);
+        assert!((58..=62).contains(&turns[0].index));
+        assert!(turns[0].angle > 80.0);
+        assert!(approx_eq(turns[0].distance_along, polyline_length(&track[..=turns[0].index as usize]), 1e-6));
+
+        assert!(detect_turns(&track[..60], 45.0).is_empty());
+        assert!(detect_turns(&track[..2], 45.0).is_empty());
+    }
+
+    #[test]
+    fn test_distance_models_agree() {
+        let london = GpsPoint::new(51.5074, -0.1278);
+        let paris = GpsPoint::new(48.8566, 2.3522);
+        let haversine = DistanceModel::Haversine.distance(&london, &paris);
+        let geodesic = DistanceModel::Geodesic.distance(&london, &paris);
+        let equirectangular = DistanceModel::Equirectangular.distance(&london, &paris);
+
+        assert_eq!(haversine, haversine_distance(&london, &paris));
+        // The ellipsoid differs from the sphere by a few tenths of a percent
+        assert!((geodesic - haversine).abs() / haversine < 0.005);
+        // Flat-Earth error is still small at 344km
+        assert!((equirectangular - haversine).abs() / haversine < 0.001);
+
+        // Antimeridian: the short way around
+        let a = GpsPoint::new(-17.0, 179.99);
+        let b = GpsPoint::new(-17.0, -179.99);
+        assert!(DistanceModel::Equirectangular.distance(&a, &b) < 2_200.0);
+        assert!(DistanceModel::Geodesic.distance(&a, &b) < 2_200.0);
+
+        let track = vec![london, paris, london];
+        assert!(approx_eq(DistanceModel::Haversine.polyline_length(&track), polyline_length(&track), 1e-6));
+    }
+
+    #[test]
+    fn test_resampling_keeps_ends_and_spacing() {
+        let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.505, -0.10), GpsPoint::new(51.505, -0.09)];
+        let total = polyline_length(&track);
+
+        let resampled = resample_polyline(&track, 20);
+        assert_eq!(resampled.len(), 20);
+        assert_eq!(resampled[19], track[2]);
+
+        // Upsampling interpolates evenly between the original points
+        let line = [GpsPoint::new(0.0, 0.0), GpsPoint::new(0.002, 0.0)];
+        let upsampled = resample_polyline(&line, 5);
+        assert!(upsampled.iter().enumerate().all(|(i, p)| approx_eq(p.latitude, i as f64 * 0.0005, 1e-9)));
+
+        // A track already at the target count is kept as recorded for route
+        // AMD; section AMD still spaces it evenly
+        let uneven = [GpsPoint::new(0.0, 0.0), GpsPoint::new(0.0005, 0.0), GpsPoint::new(0.002, 0.0)];
+        assert_eq!(resample_polyline(&uneven, 3), uneven);
+        let even = resample_evenly(&uneven, 3);
+        assert_eq!((even[0], even[2]), (uneven[0], uneven[2]));
+        assert!(approx_eq(even[1].latitude, 0.001, 1e-9));
+        assert_eq!(resample_evenly(&uneven, 1).len(), 1);
+
+        let spaced = resample_by_distance(&track, 50.0);
+        assert_eq!(spaced.len(), (total / 50.0).floor() as usize + 2);
+        // Spacing is along the track; chords only match it on the first leg
+        assert!(spaced.windows(2).take(10).all(|w| approx_eq(haversine_distance(&w[0], &w[1]), 50.0, 0.1)));
+        assert_eq!(spaced.last(), Some(&track[2]));
+        assert_eq!(resample_by_distance(&track, 0.0), track);
+    }
+
+    #[test]
+    fn test_smooth_and_simplify_across_antimeridian() {
+        let track: Vec<GpsPoint> = (0..20)
+            .map(|i| GpsPoint::new(-17.0 + (i % 2) as f64 * 0.0001, normalize_longitude(179.99 + i as f64 * 0.001)))
+            .collect();
+
+        let smooth = smooth_polyline(&track, 5);
+        assert_eq!(smooth.len(), track.len());
+        assert!(smooth.iter().all(|p| p.longitude.abs() > 179.9));
+        assert!(smooth[10].latitude > -17.0 && smooth[10].latitude < -16.9999);
+
+        let simplified = simplify_polyline(&track, 20.0);
+        assert_eq!(simplified, vec![track[0], track[19]]);
+    }
 }
@@ -6,9 +6,39 @@
 //! - Activity references for drill-down
 //!
 //! Optimized for 120Hz rendering by pre-computing all data.
+//!
+//! Consecutive points are joined by rasterizing the line between them
+//! (Bresenham over the grid), so every traversed cell is counted even when
+//! points are sparser than the cell size.
+//!
+//! Grid columns are measured from the first point's longitude, so data crossing
+//! the antimeridian (±180°) lands in contiguous columns. Result bounds crossing
+//! the antimeridian have `min_lng > max_lng`.
+//!
+//! Activities can be filtered by time, sport type or ID, either while
+//! generating (via [`HeatmapConfig`]) or afterwards with [`filter_heatmap`],
+//! which derives a filtered view from a full heatmap without re-binning.
+//!
+//! Each cell also breaks its visits down by sport, and with
+//! [`generate_heatmap_with_intensity`] averages the speed and power recorded
+//! inside it, so roads can be colored by sport or by effort.
 
 use std::collections::HashMap;
-use crate::RouteSignature;
+use geo::{Contains, Coord, LineString, Point, Polygon};
+use crate::{CancellationToken, GpsPoint, RouteSignature};
+use crate::geo_utils::{compute_bounds, haversine_distance, longitude_delta, normalize_longitude};
+use crate::indoor::{is_indoor, IndoorConfig};
+use crate::preprocess::is_null_island;
+
+// Slippy-map (XYZ) tile output
+pub mod tiles;
+
+// Frequency isolines as polygons
+pub mod contour;
+
+// PNG raster rendering
+#[cfg(feature = "render")]
+pub mod render;
 
 /// Configuration for heatmap generation
 #[derive(Debug, Clone)]
@@ -18,6 +48,30 @@ pub struct HeatmapConfig {
     pub cell_size_meters: f64,
     /// Optional bounds to limit computation
     pub bounds: Option<HeatmapBounds>,
+    /// Only include activities with a timestamp in this range
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub time_range: Option<HeatmapTimeRange>,
+    /// Only include activities of these sport types ("Run", "Ride", etc.)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub sport_types: Option<Vec<String>>,
+    /// Only include these activities
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub activity_ids: Option<Vec<String>>,
+    /// Leave out virtual and indoor activities (see [`crate::indoor`]),
+    /// whose fictional or stationary GPS would show up as hot spots.
+    /// Not applied by [`filter_heatmap`], which has no tracks to inspect.
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub exclude_indoor: bool,
+    /// Fill [`HeatmapCell::corners`] with each cell's exact quad, for
+    /// renderers drawing cells as polygons
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub include_cell_corners: bool,
+    /// Lines between consecutive points longer than this (meters) are GPS
+    /// glitches or recording gaps: only their endpoints are counted, not the
+    /// cells in between.
+    /// Default: 100km
+    #[cfg_attr(feature = "ffi", uniffi(default = 100000.0))]
+    pub max_leg_meters: f64,
 }
 
 impl Default for HeatmapConfig {
@@ -25,11 +79,58 @@ impl Default for HeatmapConfig {
         Self {
             cell_size_meters: 100.0,
             bounds: None,
+            time_range: None,
+            sport_types: None,
+            activity_ids: None,
+            exclude_indoor: false,
+            include_cell_corners: false,
+            max_leg_meters: 100_000.0,
+        }
+    }
+}
+
+impl HeatmapConfig {
+    /// Whether an activity passes the time, sport type and ID filters.
+    ///
+    /// Activities without the metadata a filter needs (no timestamp, no sport
+    /// type) are excluded by that filter.
+    pub fn includes_activity(&self, activity_id: &str, data: Option<&ActivityHeatmapData>) -> bool {
+        if let Some(ids) = &self.activity_ids {
+            if !ids.iter().any(|id| id == activity_id) {
+                return false;
+            }
+        }
+        if let Some(range) = &self.time_range {
+            match data.and_then(|d| d.timestamp) {
+                Some(ts) if range.contains(ts) => {}
+                _ => return false,
+            }
         }
+        if let Some(sports) = &self.sport_types {
+            match data.and_then(|d| d.sport_type.as_deref()) {
+                Some(sport) if sports.iter().any(|s| s == sport) => {}
+                _ => return false,
+            }
+        }
+        true
     }
 }
 
-/// Bounding box for heatmap computation
+/// Inclusive time range (Unix timestamps in seconds)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct HeatmapTimeRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl HeatmapTimeRange {
+    pub fn contains(&self, timestamp: i64) -> bool {
+        timestamp >= self.start && timestamp <= self.end
+    }
+}
+
+/// Bounding box for heatmap computation (`min_lng > max_lng` crosses the antimeridian)
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
 pub struct HeatmapBounds {
@@ -64,7 +165,7 @@ pub struct HeatmapCell {
     pub center_lng: f64,
     /// Normalized density (0.0-1.0) for color mapping
     pub density: f32,
-    /// Total visit count (sum of all point traversals)
+    /// Total visit count (number of times a track entered this cell)
     pub visit_count: u32,
     /// Routes passing through this cell
     pub route_refs: Vec<RouteRef>,
@@ -72,12 +173,60 @@ pub struct HeatmapCell {
     pub unique_route_count: u32,
     /// All activity IDs that pass through
     pub activity_ids: Vec<String>,
+    /// Visit count per activity, parallel to `activity_ids`
+    pub activity_visit_counts: Vec<u32>,
     /// Earliest visit (Unix timestamp)
     pub first_visit: Option<i64>,
     /// Most recent visit (Unix timestamp)
     pub last_visit: Option<i64>,
     /// True if 2+ routes share this cell (intersection/common path)
     pub is_common_path: bool,
+    /// Visits per sport type, most visited first. Activities without a
+    /// sport type are not counted, so the sum can be below `visit_count`.
+    #[cfg_attr(feature = "ffi", uniffi(default = []))]
+    pub sport_visit_counts: Vec<SportVisitCount>,
+    /// Average speed (m/s) over the visits that supplied one
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub avg_speed: Option<f64>,
+    /// Average power (watts) over the visits that supplied one
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub avg_power: Option<f64>,
+    /// Cell corners SW, SE, NE, NW when [`HeatmapConfig::include_cell_corners`]
+    /// is set, otherwise empty. Longitudes are continuous around the cell, so
+    /// the east edge of a cell straddling the antimeridian exceeds 180°.
+    #[cfg_attr(feature = "ffi", uniffi(default = []))]
+    pub corners: Vec<GpsPoint>,
+}
+
+/// Number of visits to a cell by activities of one sport type
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SportVisitCount {
+    pub sport_type: String,
+    pub visit_count: u32,
+}
+
+/// Per-point speed and power of an activity, for intensity aggregation.
+///
+/// Both streams are index-aligned with the activity's GPS points; either may
+/// be empty when the activity didn't record it. Non-finite or negative
+/// samples are ignored.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct IntensityStreams {
+    pub activity_id: String,
+    /// Speed in m/s
+    pub speed: Vec<f64>,
+    /// Power in watts
+    pub power: Vec<f64>,
+}
+
+impl IntensityStreams {
+    /// Speed and power at a point index, where recorded
+    fn sample(&self, index: usize) -> (Option<f64>, Option<f64>) {
+        let valid = |v: Option<&f64>| v.copied().filter(|v| v.is_finite() && *v >= 0.0);
+        (valid(self.speed.get(index)), valid(self.power.get(index)))
+    }
 }
 
 /// Complete heatmap result
@@ -100,6 +249,27 @@ pub struct HeatmapResult {
     pub total_activities: u32,
 }
 
+/// Aggregated heatmap statistics inside a polygon
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct RegionQueryResult {
+    /// Number of cells whose center lies inside the polygon
+    pub cell_count: u32,
+    /// Sum of cell visit counts
+    pub total_visits: u32,
+    /// Routes passing through the region; `activity_count` is the highest
+    /// count of the route in any single cell of the region
+    pub route_refs: Vec<RouteRef>,
+    /// Number of unique routes
+    pub unique_route_count: u32,
+    /// Unique activity IDs passing through the region
+    pub activity_ids: Vec<String>,
+    /// Earliest visit (Unix timestamp)
+    pub first_visit: Option<i64>,
+    /// Most recent visit (Unix timestamp)
+    pub last_visit: Option<i64>,
+}
+
 /// Query result when user taps a location
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
@@ -115,19 +285,94 @@ pub struct CellQueryResult {
 struct CellBuilder {
     visit_count: u32,
     activity_ids: Vec<String>,
+    activity_visit_counts: Vec<u32>,
     route_counts: HashMap<String, u32>, // route_id -> count
     route_names: HashMap<String, Option<String>>, // route_id -> name
     first_visit: Option<i64>,
     last_visit: Option<i64>,
+    sport_counts: HashMap<String, u32>,
+    speed: Mean,
+    power: Mean,
+}
+
+/// Running mean of intensity samples
+#[derive(Debug, Default, Clone, Copy)]
+struct Mean {
+    sum: f64,
+    count: u32,
+}
+
+impl Mean {
+    fn add(&mut self, value: Option<f64>) {
+        if let Some(v) = value {
+            self.sum += v;
+            self.count += 1;
+        }
+    }
+
+    fn get(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+/// What a track contributes to each cell it visits besides the visit itself
+#[derive(Debug, Clone, Copy)]
+struct TrackInfo<'a> {
+    activity_id: &'a str,
+    route_id: Option<&'a str>,
+    route_name: Option<&'a str>,
+    timestamp: Option<i64>,
+    sport_type: Option<&'a str>,
+    intensity: Option<&'a IntensityStreams>,
+}
+
+/// Sport counts sorted most visited first, ties by name
+fn sorted_sport_counts<'a>(counts: impl IntoIterator<Item = (&'a str, u32)>) -> Vec<SportVisitCount> {
+    let mut sports: Vec<SportVisitCount> = counts
+        .into_iter()
+        .map(|(sport_type, visit_count)| SportVisitCount { sport_type: sport_type.to_string(), visit_count })
+        .collect();
+    sports.sort_by(|a, b| b.visit_count.cmp(&a.visit_count).then_with(|| a.sport_type.cmp(&b.sport_type)));
+    sports
 }
 
 /// Grid coordinate
 type CellCoord = (i32, i32);
 
+/// Cells on the line from `from` to `to` (inclusive), using Bresenham's algorithm.
+fn rasterize_line(from: CellCoord, to: CellCoord) -> Vec<CellCoord> {
+    let (mut row, mut col) = from;
+    let d_row = (to.0 - row).abs();
+    let d_col = (to.1 - col).abs();
+
+    let step_row = if to.0 >= row { 1 } else { -1 };
+    let step_col = if to.1 >= col { 1 } else { -1 };
+    let mut error = d_col - d_row;
+    let mut cells = Vec::with_capacity((d_row.max(d_col) + 1) as usize);
+
+    loop {
+        cells.push((row, col));
+        if (row, col) == to {
+            return cells;
+        }
+        let doubled = 2 * error;
+        if doubled > -d_row {
+            error -= d_row;
+            col += step_col;
+        }
+        if doubled < d_col {
+            error += d_col;
+            row += step_row;
+        }
+    }
+}
+
 /// Heatmap grid builder
 struct HeatmapGrid {
     cell_size_meters: f64,
-    ref_lat: f64,
+    /// Rows count from its latitude, which also sets the column width;
+    /// longitudes are unwrapped around it. Set before any cell is visited.
+    origin: Option<GpsPoint>,
     cells: HashMap<CellCoord, CellBuilder>,
     min_lat: f64,
     max_lat: f64,
@@ -136,10 +381,10 @@ struct HeatmapGrid {
 }
 
 impl HeatmapGrid {
-    fn new(cell_size_meters: f64) -> Self {
+    fn new(cell_size_meters: f64, origin: Option<GpsPoint>) -> Self {
         Self {
             cell_size_meters,
-            ref_lat: 0.0,
+            origin,
             cells: HashMap::new(),
             min_lat: f64::INFINITY,
             max_lat: f64::NEG_INFINITY,
@@ -148,58 +393,124 @@ impl HeatmapGrid {
         }
     }
 
+    fn origin(&self) -> GpsPoint {
+        self.origin.expect("grid origin is set before any cell is visited")
+    }
+
+    /// Longitude unwrapped to within 180° of the origin, so columns stay
+    /// contiguous across the antimeridian
+    fn unwrap_lng(&self, lng: f64) -> f64 {
+        let ref_lng = self.origin().longitude;
+        ref_lng + longitude_delta(ref_lng, lng)
+    }
+
     /// Convert lat/lng to grid coordinates
     fn to_grid_coords(&self, lat: f64, lng: f64) -> CellCoord {
-        // Meters per degree at reference latitude
+        // Meters per degree at the origin's latitude
+        let ref_lat = self.origin().latitude;
         let lat_meters_per_deg = 111_320.0;
-        let lng_meters_per_deg = 111_320.0 * self.ref_lat.to_radians().cos();
+        let lng_meters_per_deg = 111_320.0 * ref_lat.to_radians().cos();
 
-        let row = ((lat - self.ref_lat) * lat_meters_per_deg / self.cell_size_meters).floor() as i32;
-        let col = (lng * lng_meters_per_deg / self.cell_size_meters).floor() as i32;
+        let row = ((lat - ref_lat) * lat_meters_per_deg / self.cell_size_meters).floor() as i32;
+        let col = (self.unwrap_lng(lng) * lng_meters_per_deg / self.cell_size_meters).floor() as i32;
 
         (row, col)
     }
 
     /// Get cell center coordinates
     fn cell_center(&self, row: i32, col: i32) -> (f64, f64) {
+        let ref_lat = self.origin().latitude;
         let lat_meters_per_deg = 111_320.0;
-        let lng_meters_per_deg = 111_320.0 * self.ref_lat.to_radians().cos();
+        let lng_meters_per_deg = 111_320.0 * ref_lat.to_radians().cos();
 
-        let center_lat = self.ref_lat + ((row as f64 + 0.5) * self.cell_size_meters / lat_meters_per_deg);
+        let center_lat = ref_lat + ((row as f64 + 0.5) * self.cell_size_meters / lat_meters_per_deg);
         let center_lng = (col as f64 + 0.5) * self.cell_size_meters / lng_meters_per_deg;
 
-        (center_lat, center_lng)
+        (center_lat, normalize_longitude(center_lng))
     }
 
-    /// Add a point to the grid
-    fn add_point(
-        &mut self,
-        lat: f64,
-        lng: f64,
-        activity_id: &str,
-        route_id: Option<&str>,
-        route_name: Option<&str>,
-        timestamp: Option<i64>,
-    ) {
-        // Update bounds
-        self.min_lat = self.min_lat.min(lat);
-        self.max_lat = self.max_lat.max(lat);
-        self.min_lng = self.min_lng.min(lng);
-        self.max_lng = self.max_lng.max(lng);
+    /// Cell corners SW, SE, NE, NW, using the same meters-per-degree as the
+    /// binning so adjacent quads share edges exactly
+    fn cell_corners(&self, row: i32, col: i32) -> Vec<GpsPoint> {
+        let ref_lat = self.origin().latitude;
+        let lat_step = self.cell_size_meters / 111_320.0;
+        let lng_step = self.cell_size_meters / (111_320.0 * ref_lat.to_radians().cos());
+
+        let south = ref_lat + row as f64 * lat_step;
+        let north = south + lat_step;
+        let west = normalize_longitude(col as f64 * lng_step);
+        let east = west + lng_step;
+
+        vec![
+            GpsPoint::new(south, west),
+            GpsPoint::new(south, east),
+            GpsPoint::new(north, east),
+            GpsPoint::new(north, west),
+        ]
+    }
 
-        // Set reference latitude if not set
-        if self.ref_lat == 0.0 {
-            self.ref_lat = lat;
+    /// Add a track to the grid, counting every cell its lines pass through.
+    ///
+    /// Points outside `bounds` (if given) or at (0, 0), where devices report
+    /// before GPS lock, are skipped and break the line. Lines longer than
+    /// `config.max_leg_meters` only count their endpoints. Cells on a line take
+    /// the intensity sample of the point ending it.
+    fn add_track(&mut self, points: &[GpsPoint], config: &HeatmapConfig, track: &TrackInfo) {
+        let mut prev: Option<(CellCoord, &GpsPoint)> = None;
+
+        for (index, point) in points.iter().enumerate() {
+            if !point_included(point, config.bounds.as_ref()) {
+                prev = None;
+                continue;
+            }
+
+            let (lat, lng) = (point.latitude, point.longitude);
+
+            // Grids built without a known data extent start at the first point
+            self.origin.get_or_insert(*point);
+
+            // Update bounds (longitude unwrapped, wrapped back in build())
+            let unwrapped_lng = self.unwrap_lng(lng);
+            self.min_lat = self.min_lat.min(lat);
+            self.max_lat = self.max_lat.max(lat);
+            self.min_lng = self.min_lng.min(unwrapped_lng);
+            self.max_lng = self.max_lng.max(unwrapped_lng);
+
+            let coord = self.to_grid_coords(lat, lng);
+            let sample = track.intensity.map_or((None, None), |s| s.sample(index));
+            match prev {
+                // Still in the same cell: not a new visit
+                Some((prev_coord, _)) if prev_coord == coord => {}
+                // A jump too long to be a line actually travelled
+                Some((_, prev_point)) if haversine_distance(prev_point, point) > config.max_leg_meters => {
+                    self.visit_cell(coord, track, sample);
+                }
+                // Skip the first cell of the line, it was visited by the previous point
+                Some((prev_coord, _)) => {
+                    for cell in rasterize_line(prev_coord, coord).into_iter().skip(1) {
+                        self.visit_cell(cell, track, sample);
+                    }
+                }
+                None => self.visit_cell(coord, track, sample),
+            }
+            prev = Some((coord, point));
         }
+    }
 
-        let (row, col) = self.to_grid_coords(lat, lng);
-        let cell = self.cells.entry((row, col)).or_default();
+    /// Record one visit of a track to a cell
+    fn visit_cell(&mut self, coord: CellCoord, track: &TrackInfo, (speed, power): (Option<f64>, Option<f64>)) {
+        let TrackInfo { activity_id, route_id, route_name, timestamp, sport_type, .. } = *track;
+        let cell = self.cells.entry(coord).or_default();
 
         cell.visit_count += 1;
 
         // Track activity (dedupe)
-        if !cell.activity_ids.contains(&activity_id.to_string()) {
-            cell.activity_ids.push(activity_id.to_string());
+        match cell.activity_ids.iter().position(|id| id == activity_id) {
+            Some(i) => cell.activity_visit_counts[i] += 1,
+            None => {
+                cell.activity_ids.push(activity_id.to_string());
+                cell.activity_visit_counts.push(1);
+            }
         }
 
         // Track route
@@ -215,10 +526,16 @@ impl HeatmapGrid {
             cell.first_visit = Some(cell.first_visit.map_or(ts, |v| v.min(ts)));
             cell.last_visit = Some(cell.last_visit.map_or(ts, |v| v.max(ts)));
         }
+
+        if let Some(sport) = sport_type {
+            *cell.sport_counts.entry(sport.to_string()).or_insert(0) += 1;
+        }
+        cell.speed.add(speed);
+        cell.power.add(power);
     }
 
-    /// Build the final heatmap result
-    fn build(self) -> HeatmapResult {
+    /// Build the final heatmap result, with cell corners if `with_corners`
+    fn build(self, with_corners: bool) -> HeatmapResult {
         if self.cells.is_empty() {
             return HeatmapResult {
                 cells: vec![],
@@ -275,9 +592,14 @@ impl HeatmapGrid {
                 route_refs,
                 unique_route_count,
                 activity_ids: builder.activity_ids.clone(),
+                activity_visit_counts: builder.activity_visit_counts.clone(),
                 first_visit: builder.first_visit,
                 last_visit: builder.last_visit,
                 is_common_path: unique_route_count >= 2,
+                sport_visit_counts: sorted_sport_counts(builder.sport_counts.iter().map(|(s, &n)| (s.as_str(), n))),
+                avg_speed: builder.speed.get(),
+                avg_power: builder.power.get(),
+                corners: if with_corners { self.cell_corners(row, col) } else { Vec::new() },
             }
         }).collect();
 
@@ -294,8 +616,8 @@ impl HeatmapGrid {
             bounds: HeatmapBounds {
                 min_lat: self.min_lat,
                 max_lat: self.max_lat,
-                min_lng: self.min_lng,
-                max_lng: self.max_lng,
+                min_lng: wrap_bound(self.min_lng),
+                max_lng: wrap_bound(self.max_lng),
             },
             cell_size_meters: self.cell_size_meters,
             grid_rows: (max_row - min_row + 1) as u32,
@@ -315,74 +637,491 @@ pub struct ActivityHeatmapData {
     pub route_id: Option<String>,
     pub route_name: Option<String>,
     pub timestamp: Option<i64>,
+    /// Sport type ("Run", "Ride", etc.), used by `HeatmapConfig::sport_types`
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub sport_type: Option<String>,
 }
 
 /// Generate a heatmap from route signatures
 ///
 /// Uses the simplified GPS traces from RouteSignature (~100 points each)
 /// for efficient heatmap generation without loading full GPS tracks.
+/// Lines between the sparse points are rasterized, so paths stay continuous.
 pub fn generate_heatmap(
     signatures: &[RouteSignature],
     activity_data: &HashMap<String, ActivityHeatmapData>,
     config: &HeatmapConfig,
 ) -> HeatmapResult {
-    let mut grid = HeatmapGrid::new(config.cell_size_meters);
-
-    for sig in signatures {
-        let data = activity_data.get(&sig.activity_id);
-        let route_id = data.and_then(|d| d.route_id.as_deref());
-        let route_name = data.and_then(|d| d.route_name.as_deref());
-        let timestamp = data.and_then(|d| d.timestamp);
-
-        for point in &sig.points {
-            // Skip points outside bounds if specified
-            if let Some(bounds) = &config.bounds {
-                if point.latitude < bounds.min_lat || point.latitude > bounds.max_lat ||
-                   point.longitude < bounds.min_lng || point.longitude > bounds.max_lng {
-                    continue;
-                }
+    generate_heatmap_cancellable(signatures, activity_data, config, &CancellationToken::new())
+        .expect("fresh token is never cancelled")
+}
+
+/// [`generate_heatmap`], checking `cancel` between activities.
+///
+/// Returns `None` if cancelled before the heatmap was built.
+pub fn generate_heatmap_cancellable(
+    signatures: &[RouteSignature],
+    activity_data: &HashMap<String, ActivityHeatmapData>,
+    config: &HeatmapConfig,
+    cancel: &CancellationToken,
+) -> Option<HeatmapResult> {
+    let activities = included_activities(
+        signatures.iter().map(|s| (s.activity_id.as_str(), s.points.as_slice())),
+        activity_data,
+        config,
+    );
+    let mut grid = HeatmapGrid::new(config.cell_size_meters, grid_origin(&activities, config));
+
+    for (activity_id, points, data) in activities {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        add_activity(&mut grid, activity_id, points, data, None, config);
+    }
+
+    Some(grid.build(config.include_cell_corners))
+}
+
+/// Generate a heatmap from full-resolution GPS tracks `(activity_id, points)`.
+///
+/// More precise than [`generate_heatmap`] at small cell sizes, at the cost of
+/// loading the full tracks.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+///
+/// // Two points 1km apart: every 100m cell in between is counted
+/// let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.509, -0.10)];
+/// let result = generate_heatmap_from_tracks(
+///     &[("ride".to_string(), track)],
+///     &HashMap::new(),
+///     &HeatmapConfig::default(),
+/// );
+/// assert!(result.cells.len() >= 10);
+/// ```
+pub fn generate_heatmap_from_tracks(
+    tracks: &[(String, Vec<GpsPoint>)],
+    activity_data: &HashMap<String, ActivityHeatmapData>,
+    config: &HeatmapConfig,
+) -> HeatmapResult {
+    generate_heatmap_from_tracks_cancellable(tracks, activity_data, config, &CancellationToken::new())
+        .expect("fresh token is never cancelled")
+}
+
+/// [`generate_heatmap_from_tracks`], checking `cancel` between activities.
+///
+/// Returns `None` if cancelled before the heatmap was built.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, CancellationToken, generate_heatmap_from_tracks_cancellable};
+///
+/// let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.509, -0.10)];
+/// let token = CancellationToken::new();
+/// token.cancel();
+///
+/// let result = generate_heatmap_from_tracks_cancellable(
+///     &[("ride".to_string(), track)],
+///     &HashMap::new(),
+///     &HeatmapConfig::default(),
+///     &token,
+/// );
+/// assert!(result.is_none());
+/// ```
+pub fn generate_heatmap_from_tracks_cancellable(
+    tracks: &[(String, Vec<GpsPoint>)],
+    activity_data: &HashMap<String, ActivityHeatmapData>,
+    config: &HeatmapConfig,
+    cancel: &CancellationToken,
+) -> Option<HeatmapResult> {
+    tracks_heatmap(tracks, activity_data, &HashMap::new(), config, cancel)
+}
+
+/// [`generate_heatmap_from_tracks`], also averaging each cell's speed and
+/// power from the activities' `streams` (keyed by activity ID) into
+/// [`HeatmapCell::avg_speed`] and [`HeatmapCell::avg_power`].
+///
+/// Averages are per visit, so a road ridden ten times weighs ten times as
+/// much as one ridden once.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, IntensityStreams, generate_heatmap_with_intensity};
+///
+/// let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.509, -0.10)];
+/// let streams = IntensityStreams { activity_id: "ride".to_string(), speed: vec![8.0, 10.0], power: vec![] };
+///
+/// let result = generate_heatmap_with_intensity(
+///     &[("ride".to_string(), track)],
+///     &HashMap::new(),
+///     &HashMap::from([("ride".to_string(), streams)]),
+///     &HeatmapConfig::default(),
+/// );
+/// assert!(result.cells.iter().all(|c| c.avg_speed.is_some() && c.avg_power.is_none()));
+/// ```
+pub fn generate_heatmap_with_intensity(
+    tracks: &[(String, Vec<GpsPoint>)],
+    activity_data: &HashMap<String, ActivityHeatmapData>,
+    streams: &HashMap<String, IntensityStreams>,
+    config: &HeatmapConfig,
+) -> HeatmapResult {
+    tracks_heatmap(tracks, activity_data, streams, config, &CancellationToken::new())
+        .expect("fresh token is never cancelled")
+}
+
+/// Bin full tracks, with intensity for activities that have `streams`
+fn tracks_heatmap(
+    tracks: &[(String, Vec<GpsPoint>)],
+    activity_data: &HashMap<String, ActivityHeatmapData>,
+    streams: &HashMap<String, IntensityStreams>,
+    config: &HeatmapConfig,
+    cancel: &CancellationToken,
+) -> Option<HeatmapResult> {
+    let activities = included_activities(
+        tracks.iter().map(|(id, points)| (id.as_str(), points.as_slice())),
+        activity_data,
+        config,
+    );
+    let mut grid = HeatmapGrid::new(config.cell_size_meters, grid_origin(&activities, config));
+
+    for (activity_id, points, data) in activities {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        add_activity(&mut grid, activity_id, points, data, streams.get(activity_id), config);
+    }
+
+    Some(grid.build(config.include_cell_corners))
+}
+
+/// An activity's ID, points and metadata
+type HeatmapActivity<'a> = (&'a str, &'a [GpsPoint], Option<&'a ActivityHeatmapData>);
+
+/// Activities passing the filters of `config`, with their metadata
+fn included_activities<'a>(
+    activities: impl Iterator<Item = (&'a str, &'a [GpsPoint])>,
+    activity_data: &'a HashMap<String, ActivityHeatmapData>,
+    config: &HeatmapConfig,
+) -> Vec<HeatmapActivity<'a>> {
+    activities
+        .map(|(activity_id, points)| (activity_id, points, activity_data.get(activity_id)))
+        .filter(|(activity_id, points, data)| {
+            let sport_type = data.and_then(|d| d.sport_type.as_deref());
+            config.includes_activity(activity_id, *data)
+                && !(config.exclude_indoor && is_indoor(points, sport_type, &IndoorConfig::default()))
+        })
+        .collect()
+}
+
+/// Grid origin at the center of the points that will be binned, so cells are
+/// sized for the data's own latitude rather than wherever the first track
+/// happens to start. `None` if no point will be binned.
+fn grid_origin(activities: &[HeatmapActivity], config: &HeatmapConfig) -> Option<GpsPoint> {
+    let points: Vec<GpsPoint> = activities
+        .iter()
+        .flat_map(|(_, points, _)| points.iter())
+        .filter(|p| point_included(p, config.bounds.as_ref()))
+        .copied()
+        .collect();
+    compute_bounds(&points).map(|b| b.center())
+}
+
+/// Whether a point is binned: inside `bounds` (if given) and not at (0, 0),
+/// where devices report before GPS lock
+fn point_included(point: &GpsPoint, bounds: Option<&HeatmapBounds>) -> bool {
+    !is_null_island(point) && bounds.is_none_or(|b| bounds_contain(b, point.latitude, point.longitude))
+}
+
+/// Add one activity's points to the grid with its metadata
+fn add_activity(
+    grid: &mut HeatmapGrid,
+    activity_id: &str,
+    points: &[GpsPoint],
+    data: Option<&ActivityHeatmapData>,
+    intensity: Option<&IntensityStreams>,
+    config: &HeatmapConfig,
+) {
+    let track = TrackInfo {
+        activity_id,
+        route_id: data.and_then(|d| d.route_id.as_deref()),
+        route_name: data.and_then(|d| d.route_name.as_deref()),
+        timestamp: data.and_then(|d| d.timestamp),
+        sport_type: data.and_then(|d| d.sport_type.as_deref()),
+        intensity,
+    };
+    grid.add_track(points, config, &track);
+}
+
+/// Derive a filtered heatmap from a full one without re-binning the tracks.
+///
+/// Applies the `time_range`, `sport_types` and `activity_ids` filters of
+/// `config` (its cell size and bounds are ignored). Visit counts, routes,
+/// sports, timestamps and densities are recomputed from the remaining
+/// activities; cells with none left are dropped. Speed and power averages
+/// can't be split by activity, so they are kept only on cells that lost
+/// no activity.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, ActivityHeatmapData, generate_heatmap_from_tracks, filter_heatmap};
+///
+/// let tracks = vec![
+///     ("run".to_string(), vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.505, -0.10)]),
+///     ("ride".to_string(), vec![GpsPoint::new(51.50, -0.12), GpsPoint::new(51.505, -0.12)]),
+/// ];
+/// let mut data = HashMap::new();
+/// for (id, sport) in [("run", "Run"), ("ride", "Ride")] {
+///     data.insert(id.to_string(), ActivityHeatmapData {
+///         activity_id: id.to_string(),
+///         route_id: None,
+///         route_name: None,
+///         timestamp: None,
+///         sport_type: Some(sport.to_string()),
+///     });
+/// }
+///
+/// let full = generate_heatmap_from_tracks(&tracks, &data, &HeatmapConfig::default());
+/// let runs = filter_heatmap(&full, &data, &HeatmapConfig {
+///     sport_types: Some(vec!["Run".to_string()]),
+///     ..HeatmapConfig::default()
+/// });
+/// assert_eq!(runs.total_activities, 1);
+/// assert!(runs.cells.len() < full.cells.len());
+/// ```
+pub fn filter_heatmap(
+    heatmap: &HeatmapResult,
+    activity_data: &HashMap<String, ActivityHeatmapData>,
+    config: &HeatmapConfig,
+) -> HeatmapResult {
+    let mut all_routes = std::collections::HashSet::new();
+    let mut all_activities = std::collections::HashSet::new();
+
+    let mut cells: Vec<HeatmapCell> = heatmap.cells.iter().filter_map(|cell| {
+        let mut activity_ids = Vec::new();
+        let mut activity_visit_counts = Vec::new();
+        let mut route_counts: HashMap<&str, u32> = HashMap::new();
+        let mut sport_counts: HashMap<&str, u32> = HashMap::new();
+        let mut first_visit: Option<i64> = None;
+        let mut last_visit: Option<i64> = None;
+
+        for (i, activity_id) in cell.activity_ids.iter().enumerate() {
+            let data = activity_data.get(activity_id);
+            if !config.includes_activity(activity_id, data) {
+                continue;
             }
+            // Older results without per-activity counts count each activity once
+            let visits = cell.activity_visit_counts.get(i).copied().unwrap_or(1);
+            activity_ids.push(activity_id.clone());
+            activity_visit_counts.push(visits);
+
+            if let Some(rid) = data.and_then(|d| d.route_id.as_deref()) {
+                *route_counts.entry(rid).or_insert(0) += visits;
+            }
+            if let Some(sport) = data.and_then(|d| d.sport_type.as_deref()) {
+                *sport_counts.entry(sport).or_insert(0) += visits;
+            }
+            if let Some(ts) = data.and_then(|d| d.timestamp) {
+                first_visit = Some(first_visit.map_or(ts, |v| v.min(ts)));
+                last_visit = Some(last_visit.map_or(ts, |v| v.max(ts)));
+            }
+        }
 
-            grid.add_point(
-                point.latitude,
-                point.longitude,
-                &sig.activity_id,
-                route_id,
-                route_name,
-                timestamp,
-            );
+        if activity_ids.is_empty() {
+            return None;
         }
+
+        let route_refs: Vec<RouteRef> = cell.route_refs.iter().filter_map(|r| {
+            route_counts.get(r.route_id.as_str()).map(|&count| RouteRef {
+                route_id: r.route_id.clone(),
+                activity_count: count,
+                name: r.name.clone(),
+            })
+        }).collect();
+
+        all_routes.extend(route_refs.iter().map(|r| r.route_id.clone()));
+        all_activities.extend(activity_ids.iter().cloned());
+
+        let unique_route_count = route_refs.len() as u32;
+        let unchanged = activity_ids.len() == cell.activity_ids.len();
+        Some(HeatmapCell {
+            row: cell.row,
+            col: cell.col,
+            center_lat: cell.center_lat,
+            center_lng: cell.center_lng,
+            density: 0.0,
+            visit_count: activity_visit_counts.iter().sum(),
+            route_refs,
+            unique_route_count,
+            activity_ids,
+            activity_visit_counts,
+            first_visit,
+            last_visit,
+            is_common_path: unique_route_count >= 2,
+            sport_visit_counts: sorted_sport_counts(sport_counts),
+            avg_speed: cell.avg_speed.filter(|_| unchanged),
+            avg_power: cell.avg_power.filter(|_| unchanged),
+            corners: cell.corners.clone(),
+        })
+    }).collect();
+
+    if cells.is_empty() {
+        return HeatmapGrid::new(heatmap.cell_size_meters, None).build(false);
+    }
+
+    let max_visits = cells.iter().map(|c| c.visit_count).max().unwrap_or(1);
+    for cell in &mut cells {
+        cell.density = cell.visit_count as f32 / max_visits as f32;
     }
 
-    grid.build()
+    let centers: Vec<GpsPoint> = cells.iter().map(|c| GpsPoint::new(c.center_lat, c.center_lng)).collect();
+    let Some(bounds) = compute_bounds(&centers) else {
+        return HeatmapGrid::new(heatmap.cell_size_meters, None).build(false);
+    };
+    let row_span = cells.iter().map(|c| c.row).max().unwrap_or(0) - cells.iter().map(|c| c.row).min().unwrap_or(0);
+    let col_span = cells.iter().map(|c| c.col).max().unwrap_or(0) - cells.iter().map(|c| c.col).min().unwrap_or(0);
+
+    HeatmapResult {
+        cells,
+        bounds: HeatmapBounds {
+            min_lat: bounds.min_lat,
+            max_lat: bounds.max_lat,
+            min_lng: bounds.min_lng,
+            max_lng: bounds.max_lng,
+        },
+        cell_size_meters: heatmap.cell_size_meters,
+        grid_rows: (row_span + 1) as u32,
+        grid_cols: (col_span + 1) as u32,
+        max_density: max_visits as f32,
+        total_routes: all_routes.len() as u32,
+        total_activities: all_activities.len() as u32,
+    }
+}
+
+/// Wrap an unwrapped longitude bound back into [-180, 180], keeping 180 itself.
+fn wrap_bound(lng: f64) -> f64 {
+    if (-180.0..=180.0).contains(&lng) { lng } else { normalize_longitude(lng) }
+}
+
+/// Whether bounds (possibly crossing the antimeridian) contain a point.
+fn bounds_contain(bounds: &HeatmapBounds, lat: f64, lng: f64) -> bool {
+    if lat < bounds.min_lat || lat > bounds.max_lat {
+        return false;
+    }
+    if bounds.min_lng > bounds.max_lng {
+        lng >= bounds.min_lng || lng <= bounds.max_lng
+    } else {
+        lng >= bounds.min_lng && lng <= bounds.max_lng
+    }
 }
 
 /// Query the heatmap at a specific location
+///
+/// Scans all cells; for repeated taps on the same heatmap build a
+/// [`HeatmapIndex`] once and query that instead.
 pub fn query_heatmap_cell(
     heatmap: &HeatmapResult,
     lat: f64,
     lng: f64,
     cell_size_meters: f64,
 ) -> Option<CellQueryResult> {
-    // Find the cell at this location
-    // We need to calculate the grid coords the same way as during generation
-    if heatmap.cells.is_empty() {
-        return None;
+    let first = heatmap.cells.first()?;
+    let ref_lat = grid_ref_lat(first, cell_size_meters);
+    let (target_row, target_cols) = candidate_coords(ref_lat, lat, lng, cell_size_meters);
+
+    let cell = heatmap.cells.iter().find(|c| c.row == target_row && target_cols.contains(&c.col))?;
+    Some(CellQueryResult {
+        cell: cell.clone(),
+        suggested_label: suggest_label(cell),
+    })
+}
+
+/// Constant-time cell lookup for tap queries on dense heatmaps.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+/// use route_matcher::heatmap::HeatmapIndex;
+///
+/// let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+/// let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+///
+/// let index = HeatmapIndex::new(heatmap);
+/// assert!(index.query(51.505, -0.10).is_some());
+/// assert!(index.query(51.505, -0.12).is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeatmapIndex {
+    heatmap: HeatmapResult,
+    ref_lat: f64,
+    lookup: HashMap<CellCoord, usize>,
+}
+
+impl HeatmapIndex {
+    /// Index the cells of a heatmap by grid coordinate.
+    pub fn new(heatmap: HeatmapResult) -> Self {
+        let ref_lat = heatmap
+            .cells
+            .first()
+            .map_or(0.0, |c| grid_ref_lat(c, heatmap.cell_size_meters));
+        let lookup = heatmap
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| ((c.row, c.col), i))
+            .collect();
+
+        Self { heatmap, ref_lat, lookup }
     }
 
-    // Use the heatmap's bounds to calculate ref_lat
-    let ref_lat = (heatmap.bounds.min_lat + heatmap.bounds.max_lat) / 2.0;
+    /// The indexed heatmap.
+    pub fn heatmap(&self) -> &HeatmapResult {
+        &self.heatmap
+    }
+
+    /// Cell containing a location, if any.
+    pub fn cell_at(&self, lat: f64, lng: f64) -> Option<&HeatmapCell> {
+        let (row, cols) = candidate_coords(self.ref_lat, lat, lng, self.heatmap.cell_size_meters);
+        cols.iter()
+            .find_map(|&col| self.lookup.get(&(row, col)))
+            .map(|&i| &self.heatmap.cells[i])
+    }
+
+    /// Same as [`query_heatmap_cell`], in constant time.
+    pub fn query(&self, lat: f64, lng: f64) -> Option<CellQueryResult> {
+        let cell = self.cell_at(lat, lng)?;
+        Some(CellQueryResult {
+            cell: cell.clone(),
+            suggested_label: suggest_label(cell),
+        })
+    }
+}
+
+/// Reference latitude of the grid, recovered from any cell's row and center
+/// (generation anchors rows on the center of the data, not the bounds' edge).
+fn grid_ref_lat(cell: &HeatmapCell, cell_size_meters: f64) -> f64 {
+    cell.center_lat - (cell.row as f64 + 0.5) * cell_size_meters / 111_320.0
+}
+
+/// Grid row and candidate columns for a location.
+fn candidate_coords(ref_lat: f64, lat: f64, lng: f64, cell_size_meters: f64) -> (i32, [i32; 3]) {
     let lat_meters_per_deg = 111_320.0;
     let lng_meters_per_deg = 111_320.0 * ref_lat.to_radians().cos();
 
-    let target_row = ((lat - ref_lat) * lat_meters_per_deg / cell_size_meters).floor() as i32;
-    let target_col = (lng * lng_meters_per_deg / cell_size_meters).floor() as i32;
-
-    // Find the cell
-    let cell = heatmap.cells.iter().find(|c| c.row == target_row && c.col == target_col)?;
+    let row = ((lat - ref_lat) * lat_meters_per_deg / cell_size_meters).floor() as i32;
+    // Columns may have been unwrapped across the antimeridian, so try each wrap of the world
+    let col = |l: f64| (l * lng_meters_per_deg / cell_size_meters).floor() as i32;
+    (row, [col(lng), col(lng - 360.0), col(lng + 360.0)])
+}
 
-    // Generate suggested label
-    let suggested_label = if cell.unique_route_count == 0 {
+/// Suggested label for a tapped cell, based on its routes.
+fn suggest_label(cell: &HeatmapCell) -> String {
+    if cell.unique_route_count == 0 {
         if cell.activity_ids.len() == 1 {
             "Explored once".to_string()
         } else {
@@ -399,12 +1138,92 @@ pub fn query_heatmap_cell(
         format!("Common path ({} routes)", cell.unique_route_count)
     } else {
         format!("{} routes", cell.unique_route_count)
+    }
+}
+
+/// Aggregate visits, routes and activities of all cells inside a polygon
+/// (e.g. a neighborhood the user draws).
+///
+/// A cell belongs to the region if its center lies inside the polygon. The
+/// polygon does not need to be closed; fewer than 3 vertices yields an empty result.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks, query_heatmap_region};
+///
+/// let track = vec![GpsPoint::new(51.500, -0.100), GpsPoint::new(51.520, -0.100)];
+/// let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+///
+/// // Box around the northern half of the track
+/// let polygon = vec![
+///     GpsPoint::new(51.510, -0.11),
+///     GpsPoint::new(51.510, -0.09),
+///     GpsPoint::new(51.530, -0.09),
+///     GpsPoint::new(51.530, -0.11),
+/// ];
+/// let region = query_heatmap_region(&heatmap, &polygon);
+/// assert!(region.cell_count > 5 && region.cell_count < heatmap.cells.len() as u32);
+/// assert_eq!(region.activity_ids, vec!["a".to_string()]);
+/// ```
+pub fn query_heatmap_region(heatmap: &HeatmapResult, polygon: &[GpsPoint]) -> RegionQueryResult {
+    let mut result = RegionQueryResult {
+        cell_count: 0,
+        total_visits: 0,
+        route_refs: Vec::new(),
+        unique_route_count: 0,
+        activity_ids: Vec::new(),
+        first_visit: None,
+        last_visit: None,
     };
+    if polygon.len() < 3 {
+        return result;
+    }
 
-    Some(CellQueryResult {
-        cell: cell.clone(),
-        suggested_label,
-    })
+    // Longitudes relative to the first vertex, so polygons across ±180° stay contiguous
+    let ref_lng = polygon[0].longitude;
+    let to_coord = |lat: f64, lng: f64| Coord { x: longitude_delta(ref_lng, lng), y: lat };
+    let exterior: LineString<f64> = polygon.iter().map(|p| to_coord(p.latitude, p.longitude)).collect();
+    let area = Polygon::new(exterior, vec![]);
+
+    let mut routes: HashMap<&str, RouteRef> = HashMap::new();
+    let mut activities: Vec<&str> = Vec::new();
+    let mut seen_activities = std::collections::HashSet::new();
+
+    for cell in &heatmap.cells {
+        if !area.contains(&Point::from(to_coord(cell.center_lat, cell.center_lng))) {
+            continue;
+        }
+
+        result.cell_count += 1;
+        result.total_visits += cell.visit_count;
+
+        for route in &cell.route_refs {
+            routes
+                .entry(route.route_id.as_str())
+                .and_modify(|r| r.activity_count = r.activity_count.max(route.activity_count))
+                .or_insert_with(|| route.clone());
+        }
+        for id in &cell.activity_ids {
+            if seen_activities.insert(id.as_str()) {
+                activities.push(id);
+            }
+        }
+        if let Some(ts) = cell.first_visit {
+            result.first_visit = Some(result.first_visit.map_or(ts, |v| v.min(ts)));
+        }
+        if let Some(ts) = cell.last_visit {
+            result.last_visit = Some(result.last_visit.map_or(ts, |v| v.max(ts)));
+        }
+    }
+
+    let mut route_refs: Vec<RouteRef> = routes.into_values().collect();
+    route_refs.sort_by(|a, b| b.activity_count.cmp(&a.activity_count).then_with(|| a.route_id.cmp(&b.route_id)));
+
+    result.unique_route_count = route_refs.len() as u32;
+    result.route_refs = route_refs;
+    result.activity_ids = activities.into_iter().map(String::from).collect();
+    result
 }
 
 #[cfg(test)]
@@ -433,6 +1252,7 @@ mod tests {
             end_point: gps_points.last().cloned().unwrap_or(GpsPoint::new(0.0, 0.0)),
             bounds: Bounds { min_lat, max_lat, min_lng, max_lng },
             center: GpsPoint::new(center_lat, center_lng),
+            gap_legs: Vec::new(),
         }
     }
 
@@ -457,6 +1277,7 @@ mod tests {
             route_id: None,
             route_name: None,
             timestamp: Some(1000000),
+            sport_type: None,
         });
 
         let result = generate_heatmap(&[sig], &data, &HeatmapConfig::default());
@@ -483,12 +1304,14 @@ mod tests {
             route_id: Some("route1".to_string()),
             route_name: Some("Morning Run".to_string()),
             timestamp: None,
+            sport_type: None,
         });
         data.insert("act2".to_string(), ActivityHeatmapData {
             activity_id: "act2".to_string(),
             route_id: Some("route1".to_string()),
             route_name: Some("Morning Run".to_string()),
             timestamp: None,
+            sport_type: None,
         });
 
         let result = generate_heatmap(&[sig1, sig2], &data, &HeatmapConfig::default());
@@ -519,12 +1342,14 @@ mod tests {
             route_id: Some("route1".to_string()),
             route_name: None,
             timestamp: None,
+            sport_type: None,
         });
         data.insert("act2".to_string(), ActivityHeatmapData {
             activity_id: "act2".to_string(),
             route_id: Some("route2".to_string()),
             route_name: None,
             timestamp: None,
+            sport_type: None,
         });
 
         let result = generate_heatmap(&[sig1, sig2], &data, &HeatmapConfig::default());
@@ -533,4 +1358,314 @@ mod tests {
         let common_cells: Vec<_> = result.cells.iter().filter(|c| c.is_common_path).collect();
         assert!(!common_cells.is_empty());
     }
+
+    fn activity(id: &str, route_id: &str, timestamp: i64, sport_type: &str) -> (String, ActivityHeatmapData) {
+        (id.to_string(), ActivityHeatmapData {
+            activity_id: id.to_string(),
+            route_id: Some(route_id.to_string()),
+            route_name: None,
+            timestamp: Some(timestamp),
+            sport_type: Some(sport_type.to_string()),
+        })
+    }
+
+    #[test]
+    fn test_filter_heatmap_matches_filtered_generation() {
+        let path = vec![(37.7700, -122.4194), (37.7750, -122.4194)];
+        let sigs = vec![
+            make_signature("run_2023", path.clone()),
+            make_signature("run_2024", path.clone()),
+            make_signature("ride_2024", vec![(37.7700, -122.4194), (37.7700, -122.4100)]),
+        ];
+        let data: HashMap<String, ActivityHeatmapData> = [
+            activity("run_2023", "r1", 1_680_000_000, "Run"),
+            activity("run_2024", "r1", 1_710_000_000, "Run"),
+            activity("ride_2024", "r2", 1_712_000_000, "Ride"),
+        ].into_iter().collect();
+
+        let full = generate_heatmap(&sigs, &data, &HeatmapConfig::default());
+        assert!(full.cells.iter().any(|c| c.is_common_path));
+
+        let config = HeatmapConfig {
+            time_range: Some(HeatmapTimeRange { start: 1_704_067_200, end: 1_735_689_599 }), // 2024
+            sport_types: Some(vec!["Run".to_string()]),
+            ..HeatmapConfig::default()
+        };
+        let filtered = filter_heatmap(&full, &data, &config);
+        let regenerated = generate_heatmap(&sigs, &data, &config);
+
+        assert_eq!(filtered.total_activities, 1);
+        assert_eq!(filtered.total_routes, 1);
+        assert_eq!(filtered.cells.len(), regenerated.cells.len());
+        assert!(filtered.cells.iter().all(|c| c.visit_count == 1 && !c.is_common_path));
+        assert!(filtered.cells.iter().all(|c| c.first_visit == Some(1_710_000_000)));
+        assert_eq!(filtered.max_density, 1.0);
+    }
+
+    #[test]
+    fn test_filter_by_activity_ids_and_missing_metadata() {
+        let sigs = vec![
+            make_signature("a", vec![(37.7700, -122.4194), (37.7750, -122.4194)]),
+            make_signature("b", vec![(37.7700, -122.4194), (37.7750, -122.4194)]),
+        ];
+        let full = generate_heatmap(&sigs, &HashMap::new(), &HeatmapConfig::default());
+        assert!(full.cells.iter().all(|c| c.activity_visit_counts.iter().sum::<u32>() == c.visit_count));
+
+        let only_a = HeatmapConfig { activity_ids: Some(vec!["a".to_string()]), ..HeatmapConfig::default() };
+        let filtered = filter_heatmap(&full, &HashMap::new(), &only_a);
+        assert_eq!(filtered.total_activities, 1);
+        assert_eq!(filtered.cells.len(), full.cells.len());
+
+        // Without sport metadata nothing passes a sport filter
+        let rides = HeatmapConfig { sport_types: Some(vec!["Ride".to_string()]), ..HeatmapConfig::default() };
+        assert!(filter_heatmap(&full, &HashMap::new(), &rides).cells.is_empty());
+    }
+
+    #[test]
+    fn test_region_query_aggregates_routes() {
+        let sig1 = make_signature("act1", vec![(37.7700, -122.4194), (37.7800, -122.4194)]);
+        let sig2 = make_signature("act2", vec![(37.7700, -122.4194), (37.7700, -122.4000)]);
+        let mut data = HashMap::new();
+        for (id, route, ts) in [("act1", "r1", 100), ("act2", "r2", 200)] {
+            data.insert(id.to_string(), ActivityHeatmapData {
+                activity_id: id.to_string(),
+                route_id: Some(route.to_string()),
+                route_name: None,
+                timestamp: Some(ts),
+                sport_type: None,
+            });
+        }
+        let heatmap = generate_heatmap(&[sig1, sig2], &data, &HeatmapConfig::default());
+
+        // Small square around the shared start cell only
+        let around_start = vec![
+            GpsPoint::new(37.7695, -122.4200),
+            GpsPoint::new(37.7695, -122.4185),
+            GpsPoint::new(37.7705, -122.4185),
+            GpsPoint::new(37.7705, -122.4200),
+        ];
+        let region = query_heatmap_region(&heatmap, &around_start);
+        assert!(region.cell_count >= 1);
+        assert_eq!(region.unique_route_count, 2);
+        assert_eq!(region.activity_ids.len(), 2);
+        assert_eq!((region.first_visit, region.last_visit), (Some(100), Some(200)));
+
+        // East of the shared start only act2 passes
+        let east = vec![
+            GpsPoint::new(37.7690, -122.4150),
+            GpsPoint::new(37.7690, -122.4050),
+            GpsPoint::new(37.7710, -122.4050),
+            GpsPoint::new(37.7710, -122.4150),
+        ];
+        let region = query_heatmap_region(&heatmap, &east);
+        assert!(region.cell_count >= 5);
+        assert_eq!(region.activity_ids, vec!["act2".to_string()]);
+        assert_eq!(region.route_refs[0].route_id, "r2");
+
+        assert_eq!(query_heatmap_region(&heatmap, &east[..2]).cell_count, 0);
+    }
+
+    #[test]
+    fn test_index_matches_scan() {
+        // Long north-south track: the grid is anchored on the first point, not the bounds center
+        let sig = make_signature("act1", vec![(37.7000, -122.4194), (37.8000, -122.4194), (37.8000, -122.3000)]);
+        let heatmap = generate_heatmap(&[sig], &HashMap::new(), &HeatmapConfig::default());
+        let index = HeatmapIndex::new(heatmap.clone());
+
+        for cell in &heatmap.cells {
+            let scanned = query_heatmap_cell(&heatmap, cell.center_lat, cell.center_lng, 100.0).unwrap();
+            let indexed = index.query(cell.center_lat, cell.center_lng).unwrap();
+            assert_eq!((scanned.cell.row, scanned.cell.col), (cell.row, cell.col));
+            assert_eq!((indexed.cell.row, indexed.cell.col), (cell.row, cell.col));
+        }
+        assert!(index.cell_at(37.75, -122.35).is_none());
+        assert!(HeatmapIndex::new(generate_heatmap(&[], &HashMap::new(), &HeatmapConfig::default())).cell_at(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_rasterize_line() {
+        assert_eq!(rasterize_line((0, 0), (0, 3)), vec![(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(rasterize_line((2, 2), (0, 0)), vec![(2, 2), (1, 1), (0, 0)]);
+        assert_eq!(rasterize_line((5, 5), (5, 5)), vec![(5, 5)]);
+
+        // Shallow line: one cell per column, no gaps
+        let cells = rasterize_line((0, 0), (2, 10));
+        assert_eq!(cells.len(), 11);
+        assert!(cells.windows(2).all(|w| (w[1].0 - w[0].0).abs() <= 1 && w[1].1 - w[0].1 == 1));
+    }
+
+    #[test]
+    fn test_long_legs_only_mark_endpoints() {
+        // Two points ~1km apart north-south, 100m cells
+        let sigs = [make_signature("act1", vec![(37.7700, -122.4194), (37.7790, -122.4194)])];
+        let config = HeatmapConfig { max_leg_meters: 500.0, ..HeatmapConfig::default() };
+        assert_eq!(generate_heatmap(&sigs, &HashMap::new(), &config).cells.len(), 2);
+
+        let config = HeatmapConfig { max_leg_meters: 2_000.0, ..config };
+        assert!(generate_heatmap(&sigs, &HashMap::new(), &config).cells.len() >= 10);
+    }
+
+    #[test]
+    fn test_sparse_points_fill_traversed_cells() {
+        // Two points ~1km apart north-south, 100m cells
+        let sig = make_signature("act1", vec![(37.7700, -122.4194), (37.7790, -122.4194)]);
+        let result = generate_heatmap(&[sig], &HashMap::new(), &HeatmapConfig::default());
+
+        let mut rows: Vec<i32> = result.cells.iter().map(|c| c.row).collect();
+        rows.sort();
+        assert!(rows.len() >= 10);
+        assert!(rows.windows(2).all(|w| w[1] == w[0] + 1), "gap in rows {:?}", rows);
+        assert!(result.cells.iter().all(|c| c.visit_count == 1));
+    }
+
+    #[test]
+    fn test_heatmap_from_tracks_counts_entries() {
+        // Dense track staying within one cell, then leaving and coming back
+        let mut points: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(37.7700 + i as f64 * 0.000001, -122.4194)).collect();
+        points.push(GpsPoint::new(37.7730, -122.4194));
+        points.push(GpsPoint::new(37.7700, -122.4194));
+
+        let result = generate_heatmap_from_tracks(
+            &[("act1".to_string(), points)],
+            &HashMap::new(),
+            &HeatmapConfig::default(),
+        );
+
+        let start = result.cells.iter().max_by_key(|c| c.visit_count).unwrap();
+        assert_eq!(start.visit_count, 2); // entered at the start and on return
+        assert_eq!(result.total_activities, 1);
+    }
+
+    #[test]
+    fn test_null_island_points_are_skipped() {
+        let mut points = vec![GpsPoint::new(0.0, 0.0); 3];
+        points.extend((0..10).map(|i| GpsPoint::new(37.7700 + i as f64 * 0.001, -122.4194)));
+
+        let result = generate_heatmap_from_tracks(&[("act1".to_string(), points)], &HashMap::new(), &HeatmapConfig::default());
+        // Only the cells along the real track, none on a line from (0, 0)
+        assert!(result.cells.len() <= 12);
+        assert!(result.bounds.min_lat > 37.0);
+    }
+
+    #[test]
+    fn test_antimeridian_grid_wraps() {
+        // 2km along a constant latitude across 180° (Taveuni, Fiji)
+        let points: Vec<(f64, f64)> = (0..20)
+            .map(|i| (-16.8, crate::geo_utils::normalize_longitude(179.99 + i as f64 * 0.001)))
+            .collect();
+        let sig = make_signature("fiji", points);
+
+        let result = generate_heatmap(&[sig], &HashMap::new(), &HeatmapConfig::default());
+
+        // Contiguous columns rather than a grid spanning the whole globe
+        assert!(result.grid_cols <= 25, "grid_cols = {}", result.grid_cols);
+        assert!(result.cells.iter().all(|c| c.center_lng.abs() > 179.9));
+        assert!(result.bounds.min_lng > result.bounds.max_lng);
+
+        // Cells on both sides of the line can be queried
+        assert!(query_heatmap_cell(&result, -16.8, 179.995, 100.0).is_some());
+        assert!(query_heatmap_cell(&result, -16.8, -179.995, 100.0).is_some());
+    }
+
+    #[test]
+    fn test_grid_independent_of_track_order() {
+        // One track starts exactly on the equator, the other well north of it
+        let equator = ("equator".to_string(), vec![GpsPoint::new(0.0, 32.5), GpsPoint::new(0.01, 32.5)]);
+        let north = ("north".to_string(), vec![GpsPoint::new(0.3, 32.6), GpsPoint::new(0.31, 32.6)]);
+        let config = HeatmapConfig::default();
+
+        let cells = |tracks: &[(String, Vec<GpsPoint>)]| {
+            let mut cells: Vec<(i32, i32, String)> = generate_heatmap_from_tracks(tracks, &HashMap::new(), &config)
+                .cells
+                .into_iter()
+                .map(|c| (c.row, c.col, format!("{:.6},{:.6}", c.center_lat, c.center_lng)))
+                .collect();
+            cells.sort();
+            cells
+        };
+
+        let forward = cells(&[equator.clone(), north.clone()]);
+        assert_eq!(forward, cells(&[north, equator]));
+        assert!(forward.len() >= 20);
+    }
+
+    #[test]
+    fn test_cell_corners_contain_center() {
+        // Far north, where a square in meters is tall and narrow in degrees
+        let track = vec![GpsPoint::new(69.60, 18.90), GpsPoint::new(69.61, 18.93)];
+        let config = HeatmapConfig { include_cell_corners: true, ..HeatmapConfig::default() };
+        let result = generate_heatmap_from_tracks(&[("tromso".to_string(), track.clone())], &HashMap::new(), &config);
+
+        for cell in &result.cells {
+            let [sw, se, ne, nw] = cell.corners[..] else { panic!("expected 4 corners") };
+            assert_eq!((sw.latitude, se.longitude), (se.latitude, ne.longitude));
+            assert_eq!((ne.latitude, nw.longitude), (nw.latitude, sw.longitude));
+            assert!(((sw.latitude + ne.latitude) / 2.0 - cell.center_lat).abs() < 1e-9);
+            assert!(((sw.longitude + ne.longitude) / 2.0 - cell.center_lng).abs() < 1e-9);
+            // ~100m wide at 69.6°N is ~0.0026° of longitude
+            assert!((ne.longitude - sw.longitude - 0.0026).abs() < 0.0001);
+        }
+
+        let plain = generate_heatmap_from_tracks(&[("tromso".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+        assert!(plain.cells.iter().all(|c| c.corners.is_empty()));
+    }
+
+    #[test]
+    fn test_exclude_indoor() {
+        let outdoor = make_signature("outdoor", vec![(51.50, -0.10), (51.51, -0.10)]);
+        let treadmill = make_signature("treadmill", vec![(48.2, 16.4), (48.2, 16.40001)]);
+        let sigs = [outdoor, treadmill];
+
+        assert_eq!(generate_heatmap(&sigs, &HashMap::new(), &HeatmapConfig::default()).total_activities, 2);
+        let config = HeatmapConfig { exclude_indoor: true, ..HeatmapConfig::default() };
+        assert_eq!(generate_heatmap(&sigs, &HashMap::new(), &config).total_activities, 1);
+    }
+
+    #[test]
+    fn test_sport_and_intensity_breakdown() {
+        let path = vec![GpsPoint::new(51.500, -0.10), GpsPoint::new(51.505, -0.10)];
+        let tracks = vec![
+            ("run".to_string(), path.clone()),
+            ("ride1".to_string(), path.clone()),
+            ("ride2".to_string(), path),
+        ];
+        let data: HashMap<String, ActivityHeatmapData> = [
+            activity("run", "r1", 100, "Run"),
+            activity("ride1", "r1", 200, "Ride"),
+            activity("ride2", "r1", 300, "Ride"),
+        ].into_iter().collect();
+        let streams = HashMap::from([
+            ("ride1".to_string(), IntensityStreams { activity_id: "ride1".to_string(), speed: vec![8.0, 8.0], power: vec![200.0, 200.0] }),
+            ("ride2".to_string(), IntensityStreams { activity_id: "ride2".to_string(), speed: vec![10.0, 10.0], power: vec![] }),
+        ]);
+
+        let result = generate_heatmap_with_intensity(&tracks, &data, &streams, &HeatmapConfig::default());
+        for cell in &result.cells {
+            let sports: Vec<(&str, u32)> = cell.sport_visit_counts.iter().map(|s| (s.sport_type.as_str(), s.visit_count)).collect();
+            assert_eq!(sports, vec![("Ride", 2), ("Run", 1)]);
+            assert!((cell.avg_speed.unwrap() - 9.0).abs() < 1e-9);
+            assert_eq!(cell.avg_power, Some(200.0));
+        }
+
+        let runs = filter_heatmap(&result, &data, &HeatmapConfig {
+            sport_types: Some(vec!["Run".to_string()]),
+            ..HeatmapConfig::default()
+        });
+        assert!(runs.cells.iter().all(|c| c.sport_visit_counts.len() == 1 && c.avg_speed.is_none()));
+        assert!(filter_heatmap(&result, &data, &HeatmapConfig::default()).cells.iter().all(|c| c.avg_speed.is_some()));
+    }
+
+    #[test]
+    fn test_bounds_filter_across_antimeridian() {
+        let sig = make_signature("fiji", vec![(-16.8, 179.99), (-16.8, -179.99), (-16.8, 170.0)]);
+        let config = HeatmapConfig {
+            bounds: Some(HeatmapBounds { min_lat: -17.0, max_lat: -16.0, min_lng: 179.0, max_lng: -179.0 }),
+            ..HeatmapConfig::default()
+        };
+
+        // The line across 180° is rasterized, the point at 170° is filtered out
+        let result = generate_heatmap(&[sig], &HashMap::new(), &config);
+        assert!(result.cells.len() >= 20);
+        assert!(result.cells.iter().all(|c| c.center_lng.abs() > 179.9));
+    }
 }
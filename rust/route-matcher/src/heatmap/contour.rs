@@ -0,0 +1,280 @@
+//! # Frequency Contours
+//!
+//! Extracts isolines from a [`HeatmapResult`] as polygons, e.g. "visited 5+",
+//! "20+" and "100+ times". A few smooth polygons render far faster and look
+//! far better on a map than thousands of discrete squares.
+//!
+//! ## Algorithm
+//! 1. Cell centers are treated as samples of a visit-count surface
+//!    (missing cells count as 0)
+//! 2. Marching squares traces the `min_visits - 0.5` level, interpolating
+//!    along each edge so outlines follow the counts rather than cell edges
+//! 3. Only squares touching a cell at or above the level are visited, so
+//!    cost scales with the number of cells, not the grid's extent
+//! 4. Line pieces are chained into closed rings; counter-clockwise rings are
+//!    outlines and clockwise rings are holes, assigned to the smallest
+//!    outline containing them
+//!
+//! Saddle squares (two diagonal corners inside) are resolved by the average
+//! of the four corners. Longitudes are continuous across the grid, so data
+//! crossing the antimeridian yields longitudes beyond ±180°.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+//! use route_matcher::heatmap::contour::heatmap_contours;
+//!
+//! let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+//! let tracks = vec![("a".to_string(), track.clone()), ("b".to_string(), track)];
+//! let heatmap = generate_heatmap_from_tracks(&tracks, &HashMap::new(), &HeatmapConfig::default());
+//!
+//! let bands = heatmap_contours(&heatmap, &[1, 2, 3]);
+//! assert_eq!(bands[0].polygons.len(), 1);
+//! assert_eq!(bands[1].polygons.len(), 1);
+//! assert!(bands[2].polygons.is_empty());
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::GpsPoint;
+use super::{grid_ref_lat, HeatmapResult};
+
+/// A contour polygon: one outline and the holes cut out of it.
+///
+/// Rings are closed (the first point is repeated at the end); outlines run
+/// counter-clockwise and holes clockwise.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ContourPolygon {
+    pub exterior: Vec<GpsPoint>,
+    pub holes: Vec<Vec<GpsPoint>>,
+}
+
+/// Area visited at least `min_visits` times.
+///
+/// Bands of increasing thresholds are nested, not exclusive: the "20+" area
+/// lies inside the "5+" area.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ContourBand {
+    pub min_visits: u32,
+    pub polygons: Vec<ContourPolygon>,
+}
+
+/// Grid corner (row, col); corners are the heatmap's cell centers
+type Corner = (i32, i32);
+
+/// Grid edge between two adjacent corners, lower corner first
+type Edge = (Corner, Corner);
+
+/// Point in grid space (row, col), fractional between corners
+type GridPoint = (f64, f64);
+
+/// Contours of `heatmap` at each threshold, in the order given.
+///
+/// A threshold of 0 is treated as 1 (everything visited at all).
+pub fn heatmap_contours(heatmap: &HeatmapResult, thresholds: &[u32]) -> Vec<ContourBand> {
+    let Some(first) = heatmap.cells.first() else {
+        return thresholds
+            .iter()
+            .map(|&min_visits| ContourBand { min_visits, polygons: Vec::new() })
+            .collect();
+    };
+
+    let values: HashMap<Corner, f64> = heatmap
+        .cells
+        .iter()
+        .map(|c| ((c.row, c.col), c.visit_count as f64))
+        .collect();
+
+    let ref_lat = grid_ref_lat(first, heatmap.cell_size_meters);
+    let lat_step = heatmap.cell_size_meters / 111_320.0;
+    let lng_step = heatmap.cell_size_meters / (111_320.0 * ref_lat.to_radians().cos());
+    let to_gps = |(row, col): GridPoint| GpsPoint::new(ref_lat + (row + 0.5) * lat_step, (col + 0.5) * lng_step);
+
+    thresholds
+        .iter()
+        .map(|&min_visits| {
+            let level = min_visits.max(1) as f64 - 0.5;
+            let polygons = trace_polygons(&values, level)
+                .into_iter()
+                .map(|(exterior, holes)| ContourPolygon {
+                    exterior: exterior.into_iter().map(to_gps).collect(),
+                    holes: holes.into_iter().map(|h| h.into_iter().map(to_gps).collect()).collect(),
+                })
+                .collect();
+            ContourBand { min_visits, polygons }
+        })
+        .collect()
+}
+
+/// Closed rings around the corners whose value exceeds `level`, grouped into
+/// (outline, holes) in grid space.
+fn trace_polygons(values: &HashMap<Corner, f64>, level: f64) -> Vec<(Vec<GridPoint>, Vec<Vec<GridPoint>>)> {
+    let value = |corner: Corner| values.get(&corner).copied().unwrap_or(0.0);
+
+    // Squares are addressed by their south-west corner
+    let squares: BTreeSet<Corner> = values
+        .iter()
+        .filter(|(_, &v)| v > level)
+        .flat_map(|(&(row, col), _)| [(row - 1, col - 1), (row - 1, col), (row, col - 1), (row, col)])
+        .collect();
+
+    // Line piece start edge -> (end edge, start point), inside on the left
+    let mut pieces: BTreeMap<Edge, (Edge, GridPoint)> = BTreeMap::new();
+    for &(row, col) in &squares {
+        // Corners counter-clockwise: SW, SE, NE, NW
+        let corners = [(row, col), (row, col + 1), (row + 1, col + 1), (row + 1, col)];
+        let inside = corners.map(|c| value(c) > level);
+
+        // Edges crossed walking counter-clockwise, and whether the walk leaves the inside there
+        let crossings: Vec<(Edge, GridPoint, bool)> = (0..4)
+            .filter(|&i| inside[i] != inside[(i + 1) % 4])
+            .map(|i| {
+                let (from, to) = (corners[i], corners[(i + 1) % 4]);
+                let point = interpolate(from, to, value(from), value(to), level);
+                (edge(from, to), point, inside[i])
+            })
+            .collect();
+
+        // A saddle's diagonal corners connect through the middle if its average is inside
+        let connected = crossings.len() == 4 && corners.iter().map(|&c| value(c)).sum::<f64>() / 4.0 > level;
+        let n = crossings.len();
+        for (i, &(start, point, exits)) in crossings.iter().enumerate() {
+            if !exits {
+                continue;
+            }
+            let (end, _, _) = crossings[if connected || n == 2 { (i + 1) % n } else { (i + n - 1) % n }];
+            pieces.insert(start, (end, point));
+        }
+    }
+
+    let mut outlines: Vec<Vec<GridPoint>> = Vec::new();
+    let mut holes: Vec<Vec<GridPoint>> = Vec::new();
+    while let Some((&start, _)) = pieces.iter().next() {
+        let mut ring = Vec::new();
+        let mut current = start;
+        while let Some((next, point)) = pieces.remove(&current) {
+            ring.push(point);
+            current = next;
+        }
+        if ring.len() < 3 {
+            continue;
+        }
+        ring.push(ring[0]);
+        if signed_area(&ring) > 0.0 {
+            outlines.push(ring);
+        } else {
+            holes.push(ring);
+        }
+    }
+
+    let mut polygons: Vec<(Vec<GridPoint>, Vec<Vec<GridPoint>>)> =
+        outlines.into_iter().map(|o| (o, Vec::new())).collect();
+    for hole in holes {
+        let owner = polygons
+            .iter_mut()
+            .filter(|(outline, _)| ring_contains(outline, hole[0]))
+            .min_by(|(a, _), (b, _)| signed_area(a).total_cmp(&signed_area(b)));
+        if let Some((_, owned)) = owner {
+            owned.push(hole);
+        }
+    }
+    polygons
+}
+
+fn edge(a: Corner, b: Corner) -> Edge {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+/// Point on the edge from `a` to `b` where the value crosses `level`
+fn interpolate(a: Corner, b: Corner, value_a: f64, value_b: f64, level: f64) -> GridPoint {
+    let t = (level - value_a) / (value_b - value_a);
+    (
+        a.0 as f64 + t * (b.0 - a.0) as f64,
+        a.1 as f64 + t * (b.1 - a.1) as f64,
+    )
+}
+
+/// Shoelace area with columns as x and rows as y; positive when counter-clockwise
+fn signed_area(ring: &[GridPoint]) -> f64 {
+    ring.windows(2).map(|w| w[0].1 * w[1].0 - w[1].1 * w[0].0).sum::<f64>() / 2.0
+}
+
+/// Even-odd point-in-ring test in grid space
+fn ring_contains(ring: &[GridPoint], (row, col): GridPoint) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((r1, c1), (r2, c2)) = (w[0], w[1]);
+        if (r1 > row) != (r2 > row) && col < c1 + (row - r1) * (c2 - c1) / (r2 - r1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HeatmapConfig, generate_heatmap_from_tracks};
+
+    fn values(cells: &[(Corner, f64)]) -> HashMap<Corner, f64> {
+        cells.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_single_cell_is_diamond() {
+        let polygons = trace_polygons(&values(&[((0, 0), 1.0)]), 0.5);
+        assert_eq!(polygons.len(), 1);
+        let (outline, holes) = &polygons[0];
+        assert!(holes.is_empty());
+        // Four edge midpoints around the cell, closed
+        assert_eq!(outline.len(), 5);
+        assert_eq!(outline.first(), outline.last());
+        assert!((signed_area(outline) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ring_of_cells_has_hole() {
+        let ring: Vec<(Corner, f64)> = (0..3)
+            .flat_map(|r| (0..3).map(move |c| (r, c)))
+            .filter(|&(r, c)| (r, c) != (1, 1))
+            .map(|corner| (corner, 3.0))
+            .collect();
+
+        let polygons = trace_polygons(&values(&ring), 0.5);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].1.len(), 1);
+        assert!(ring_contains(&polygons[0].1[0], (1.0, 1.0)));
+
+        // Above every count nothing remains
+        assert!(trace_polygons(&values(&ring), 3.5).is_empty());
+    }
+
+    #[test]
+    fn test_bands_are_nested() {
+        // A 1km square loop, with one side ridden twice more
+        let corners = [(51.500, -0.100), (51.509, -0.100), (51.509, -0.086), (51.500, -0.086), (51.500, -0.100)];
+        let lap: Vec<GpsPoint> = corners.iter().map(|&(lat, lng)| GpsPoint::new(lat, lng)).collect();
+        let side = lap[..2].to_vec();
+        let tracks = vec![
+            ("lap".to_string(), lap),
+            ("side1".to_string(), side.clone()),
+            ("side2".to_string(), side),
+        ];
+        let heatmap = generate_heatmap_from_tracks(&tracks, &HashMap::new(), &HeatmapConfig::default());
+
+        let bands = heatmap_contours(&heatmap, &[1, 3, 50]);
+        assert_eq!(bands.iter().map(|b| b.min_visits).collect::<Vec<_>>(), vec![1, 3, 50]);
+        assert_eq!(bands[0].polygons.len(), 1);
+        assert_eq!(bands[0].polygons[0].holes.len(), 1);
+
+        // Only the west side is visited 3+ times
+        assert!(!bands[1].polygons.is_empty());
+        let points = bands[1].polygons.iter().flat_map(|p| p.exterior.iter());
+        assert!(points.clone().all(|p| p.longitude < -0.098));
+        assert!(points.clone().any(|p| p.latitude > 51.508));
+        assert!(bands[2].polygons.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+//! # Raster Rendering
+//!
+//! Colorizes a [`HeatmapResult`] into an RGBA image or PNG for sharing and
+//! export, so host apps don't need their own rasterizer.
+//!
+//! ## Algorithm
+//! 1. Map the heatmap bounds onto the image (equirectangular; fine at heatmap scales,
+//!    bounds crossing the antimeridian are unwrapped)
+//! 2. Fill each cell's pixel rectangle with its density
+//! 3. Optionally blur with a separable Gaussian kernel for a smooth glow
+//! 4. Re-normalize to the brightest pixel and map through a color gradient
+//!
+//! Pixels with no intensity stay fully transparent, so the PNG can be laid
+//! over a map screenshot.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+//! use route_matcher::heatmap::render::{render_heatmap_png, HeatmapRenderConfig};
+//!
+//! let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.11)];
+//! let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+//!
+//! let png = render_heatmap_png(&heatmap, &HeatmapRenderConfig::default()).unwrap();
+//! assert_eq!(&png[1..4], b"PNG");
+//! ```
+
+use super::HeatmapResult;
+
+/// Color at a position along the gradient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct GradientStop {
+    /// Position in 0.0-1.0 (normalized intensity)
+    pub position: f32,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl GradientStop {
+    pub fn new(position: f32, r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { position, r, g, b, a }
+    }
+}
+
+/// Configuration for raster rendering.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct HeatmapRenderConfig {
+    /// Image width in pixels
+    pub width: u32,
+    /// Image height in pixels (0 = derive from the bounds aspect ratio)
+    pub height: u32,
+    /// Color stops sorted by position (default: blue → cyan → yellow → red)
+    pub gradient: Vec<GradientStop>,
+    /// Overall opacity multiplier (0.0-1.0)
+    pub opacity: f32,
+    /// Gaussian blur radius in pixels (0 = sharp cells)
+    pub blur_radius: u32,
+}
+
+impl Default for HeatmapRenderConfig {
+    fn default() -> Self {
+        Self {
+            width: 1024,
+            height: 0,
+            gradient: vec![
+                GradientStop::new(0.0, 0, 0, 255, 96),
+                GradientStop::new(0.35, 0, 255, 255, 160),
+                GradientStop::new(0.7, 255, 255, 0, 220),
+                GradientStop::new(1.0, 255, 0, 0, 255),
+            ],
+            opacity: 1.0,
+            blur_radius: 2,
+        }
+    }
+}
+
+/// A rendered RGBA image (row-major, 4 bytes per pixel).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct HeatmapImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Render a heatmap to raw RGBA pixels.
+pub fn render_heatmap_rgba(heatmap: &HeatmapResult, config: &HeatmapRenderConfig) -> HeatmapImage {
+    let bounds = &heatmap.bounds;
+    let lat_span = (bounds.max_lat - bounds.min_lat).max(1e-9);
+    let lng_span = (bounds.max_lng - bounds.min_lng).rem_euclid(360.0).max(1e-9);
+
+    let width = config.width.max(1);
+    let height = if config.height > 0 {
+        config.height
+    } else {
+        let mid_lat = (bounds.min_lat + bounds.max_lat) / 2.0;
+        let aspect = lat_span / (lng_span * mid_lat.to_radians().cos().max(0.01));
+        ((width as f64 * aspect).round() as u32).clamp(1, width.saturating_mul(8))
+    };
+    let (w, h) = (width as usize, height as usize);
+
+    // Cell footprint in pixels
+    let cell_lat = heatmap.cell_size_meters / 111_320.0;
+    let px_per_lat = h as f64 / lat_span;
+    let px_per_lng = w as f64 / lng_span;
+
+    let mut intensity = vec![0.0f32; w * h];
+    for cell in &heatmap.cells {
+        let cell_lng = cell_lat / cell.center_lat.to_radians().cos().max(0.01);
+        let x = (cell.center_lng - bounds.min_lng).rem_euclid(360.0) * px_per_lng;
+        let y = (bounds.max_lat - cell.center_lat) * px_per_lat;
+        let half_w = (cell_lng * px_per_lng / 2.0).max(0.5);
+        let half_h = (cell_lat * px_per_lat / 2.0).max(0.5);
+
+        let x0 = ((x - half_w).floor().max(0.0) as usize).min(w);
+        let x1 = ((x + half_w).ceil() as usize).min(w);
+        let y0 = (y - half_h).floor().max(0.0) as usize;
+        let y1 = ((y + half_h).ceil() as usize).min(h);
+
+        for row in y0..y1 {
+            for px in &mut intensity[row * w + x0..row * w + x1.max(x0)] {
+                *px = px.max(cell.density);
+            }
+        }
+    }
+
+    if config.blur_radius > 0 {
+        gaussian_blur(&mut intensity, w, h, config.blur_radius as usize);
+    }
+
+    let max = intensity.iter().cloned().fold(0.0f32, f32::max);
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    let mut rgba = vec![0u8; w * h * 4];
+    if max > 0.0 {
+        for (i, &value) in intensity.iter().enumerate() {
+            if value <= max * 1e-3 {
+                continue;
+            }
+            let [r, g, b, a] = sample_gradient(&config.gradient, value / max);
+            rgba[i * 4..i * 4 + 4].copy_from_slice(&[r, g, b, (a as f32 * opacity).round() as u8]);
+        }
+    }
+
+    HeatmapImage { width, height, rgba }
+}
+
+/// Render a heatmap to PNG bytes.
+pub fn render_heatmap_png(heatmap: &HeatmapResult, config: &HeatmapRenderConfig) -> Result<Vec<u8>, String> {
+    let image = render_heatmap_rgba(heatmap, config);
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, image.width, image.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| format!("PNG header error: {}", e))?;
+        writer.write_image_data(&image.rgba).map_err(|e| format!("PNG encode error: {}", e))?;
+    }
+    Ok(bytes)
+}
+
+/// Linear interpolation between gradient stops.
+fn sample_gradient(gradient: &[GradientStop], t: f32) -> [u8; 4] {
+    let Some(first) = gradient.first() else {
+        return [255, 255, 255, (t.clamp(0.0, 1.0) * 255.0) as u8];
+    };
+    if t <= first.position {
+        return [first.r, first.g, first.b, first.a];
+    }
+
+    for pair in gradient.windows(2) {
+        let (lo, hi) = (pair[0], pair[1]);
+        if t <= hi.position {
+            let f = if hi.position > lo.position { (t - lo.position) / (hi.position - lo.position) } else { 1.0 };
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * f).round() as u8;
+            return [mix(lo.r, hi.r), mix(lo.g, hi.g), mix(lo.b, hi.b), mix(lo.a, hi.a)];
+        }
+    }
+
+    let last = gradient[gradient.len() - 1];
+    [last.r, last.g, last.b, last.a]
+}
+
+/// Separable Gaussian blur (sigma = radius / 2), edges clamped.
+fn gaussian_blur(values: &mut [f32], width: usize, height: usize, radius: usize) {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let kernel: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let d = i as f32 - radius as f32;
+            (-d * d / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    let kernel: Vec<f32> = kernel.iter().map(|k| k / sum).collect();
+
+    let mut scratch = vec![0.0f32; values.len()];
+    // Horizontal pass
+    for y in 0..height {
+        for x in 0..width {
+            scratch[y * width + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| {
+                    let sx = (x + k).saturating_sub(radius).min(width - 1);
+                    values[y * width + sx] * weight
+                })
+                .sum();
+        }
+    }
+    // Vertical pass
+    for y in 0..height {
+        for x in 0..width {
+            values[y * width + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| {
+                    let sy = (y + k).saturating_sub(radius).min(height - 1);
+                    scratch[sy * width + x] * weight
+                })
+                .sum();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+
+    fn heatmap() -> HeatmapResult {
+        // L-shaped track leaves most of its bounding box empty
+        let track = vec![GpsPoint::new(47.37, 8.50), GpsPoint::new(47.37, 8.55), GpsPoint::new(47.40, 8.55)];
+        generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default())
+    }
+
+    #[test]
+    fn test_sample_gradient() {
+        let gradient = vec![GradientStop::new(0.0, 0, 0, 0, 0), GradientStop::new(1.0, 200, 100, 0, 255)];
+        assert_eq!(sample_gradient(&gradient, 0.0), [0, 0, 0, 0]);
+        assert_eq!(sample_gradient(&gradient, 0.5), [100, 50, 0, 128]);
+        assert_eq!(sample_gradient(&gradient, 2.0), [200, 100, 0, 255]);
+    }
+
+    #[test]
+    fn test_render_transparent_background() {
+        let config = HeatmapRenderConfig { width: 200, blur_radius: 0, ..Default::default() };
+        let image = render_heatmap_rgba(&heatmap(), &config);
+
+        assert_eq!(image.rgba.len(), (image.width * image.height * 4) as usize);
+        let opaque = image.rgba.chunks(4).filter(|px| px[3] > 0).count();
+        assert!(opaque > 0);
+        // Outside the L the image stays transparent
+        assert!(opaque < (image.width * image.height) as usize);
+    }
+
+    #[test]
+    fn test_blur_spreads_and_opacity_scales() {
+        let sharp = render_heatmap_rgba(&heatmap(), &HeatmapRenderConfig { width: 200, height: 200, blur_radius: 0, ..Default::default() });
+        let blurred = render_heatmap_rgba(&heatmap(), &HeatmapRenderConfig { width: 200, height: 200, blur_radius: 4, ..Default::default() });
+        let count = |img: &HeatmapImage| img.rgba.chunks(4).filter(|px| px[3] > 0).count();
+        assert!(count(&blurred) > count(&sharp));
+
+        let faded = render_heatmap_rgba(&heatmap(), &HeatmapRenderConfig { width: 200, height: 200, blur_radius: 0, opacity: 0.5, ..Default::default() });
+        let max_alpha = |img: &HeatmapImage| img.rgba.chunks(4).map(|px| px[3]).max().unwrap();
+        assert!(max_alpha(&faded) <= max_alpha(&sharp) / 2 + 1);
+    }
+}
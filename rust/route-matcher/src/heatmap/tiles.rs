@@ -0,0 +1,191 @@
+//! # Slippy-Map Tiles
+//!
+//! Splits a [`HeatmapResult`] into per-tile cell lists addressed by the
+//! standard XYZ (Web Mercator) tile scheme used by OSM, MapLibre and Mapbox.
+//!
+//! Mobile apps can serve these directly from a tile overlay source instead of
+//! adding thousands of individual cell annotations to the map.
+//!
+//! ## Algorithm
+//! 1. Each cell is assigned to the tile containing its center
+//!    (cells straddling a tile edge appear in exactly one tile)
+//! 2. Cells are bucketed by `(x, y)` at the requested zoom
+//! 3. Tiles are returned sorted by `x`, then `y`
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+//! use route_matcher::heatmap::tiles::{heatmap_to_tiles, lat_lng_to_tile};
+//!
+//! let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+//! let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+//!
+//! let tiles = heatmap_to_tiles(&heatmap, 14);
+//! let tile = lat_lng_to_tile(51.505, -0.10, 14);
+//! assert!(tiles.iter().any(|t| t.x == tile.x && t.y == tile.y));
+//! ```
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use super::{HeatmapBounds, HeatmapCell, HeatmapResult};
+
+/// Maximum zoom level supported (tile indices must fit in u32)
+pub const MAX_TILE_ZOOM: u32 = 24;
+
+/// Latitude limit of the Web Mercator projection
+const MAX_MERCATOR_LAT: f64 = 85.051_128_779_806_59;
+
+/// Address of a slippy-map tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct TileCoord {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Heatmap cells falling within one tile.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct HeatmapTile {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+    /// Cells whose center lies within this tile
+    pub cells: Vec<HeatmapCell>,
+    /// Highest visit count in this tile (the global maximum is on the result)
+    pub max_visit_count: u32,
+}
+
+/// Tile containing a location at the given zoom (clamped to the Mercator range).
+pub fn lat_lng_to_tile(lat: f64, lng: f64, zoom: u32) -> TileCoord {
+    let zoom = zoom.min(MAX_TILE_ZOOM);
+    let n = (1u64 << zoom) as f64;
+    let (x, y) = world_tile_position(lat, lng, zoom);
+
+    TileCoord {
+        z: zoom,
+        x: x.floor().clamp(0.0, n - 1.0) as u32,
+        y: y.floor().clamp(0.0, n - 1.0) as u32,
+    }
+}
+
+/// Fractional Web Mercator position in tile units at the given zoom
+/// (integer part is the tile, fraction the position within it).
+pub(crate) fn world_tile_position(lat: f64, lng: f64, zoom: u32) -> (f64, f64) {
+    let n = (1u64 << zoom.min(MAX_TILE_ZOOM)) as f64;
+    let lat_rad = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+    (
+        (lng + 180.0) / 360.0 * n,
+        (1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI) / 2.0 * n,
+    )
+}
+
+/// Geographic bounds of a tile.
+pub fn tile_bounds(tile: TileCoord) -> HeatmapBounds {
+    let n = (1u64 << tile.z.min(MAX_TILE_ZOOM)) as f64;
+    let lng_at = |x: f64| x / n * 360.0 - 180.0;
+    let lat_at = |y: f64| (PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+
+    HeatmapBounds {
+        min_lat: lat_at(tile.y as f64 + 1.0),
+        max_lat: lat_at(tile.y as f64),
+        min_lng: lng_at(tile.x as f64),
+        max_lng: lng_at(tile.x as f64 + 1.0),
+    }
+}
+
+/// Split a heatmap into tiles at the given zoom level.
+///
+/// Only tiles containing at least one cell are returned.
+pub fn heatmap_to_tiles(heatmap: &HeatmapResult, zoom: u32) -> Vec<HeatmapTile> {
+    let mut buckets: BTreeMap<(u32, u32), Vec<HeatmapCell>> = BTreeMap::new();
+
+    for cell in &heatmap.cells {
+        let tile = lat_lng_to_tile(cell.center_lat, cell.center_lng, zoom);
+        buckets.entry((tile.x, tile.y)).or_default().push(cell.clone());
+    }
+
+    let zoom = zoom.min(MAX_TILE_ZOOM);
+    buckets
+        .into_iter()
+        .map(|((x, y), cells)| make_tile(zoom, x, y, cells))
+        .collect()
+}
+
+/// Cells for a single tile, for on-demand tile sources.
+///
+/// Returns `None` if the tile contains no cells or its zoom is above
+/// [`MAX_TILE_ZOOM`].
+pub fn heatmap_tile(heatmap: &HeatmapResult, tile: TileCoord) -> Option<HeatmapTile> {
+    if tile.z > MAX_TILE_ZOOM {
+        return None;
+    }
+    let cells: Vec<HeatmapCell> = heatmap
+        .cells
+        .iter()
+        .filter(|c| lat_lng_to_tile(c.center_lat, c.center_lng, tile.z) == tile)
+        .cloned()
+        .collect();
+
+    if cells.is_empty() {
+        return None;
+    }
+    Some(make_tile(tile.z, tile.x, tile.y, cells))
+}
+
+fn make_tile(z: u32, x: u32, y: u32, cells: Vec<HeatmapCell>) -> HeatmapTile {
+    let max_visit_count = cells.iter().map(|c| c.visit_count).max().unwrap_or(0);
+    HeatmapTile { z, x, y, cells, max_visit_count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+
+    #[test]
+    fn test_lat_lng_to_tile_known_values() {
+        // London at z10 is tile 511/340 (OSM)
+        assert_eq!(lat_lng_to_tile(51.5074, -0.1278, 10), TileCoord { z: 10, x: 511, y: 340 });
+        // z0 is a single tile
+        assert_eq!(lat_lng_to_tile(-33.9, 151.2, 0), TileCoord { z: 0, x: 0, y: 0 });
+        // Out-of-range inputs are clamped
+        let edge = lat_lng_to_tile(89.9, 180.0, 5);
+        assert_eq!((edge.x, edge.y), (31, 0));
+    }
+
+    #[test]
+    fn test_tile_bounds_contain_point() {
+        let tile = lat_lng_to_tile(47.3769, 8.5417, 14);
+        let bounds = tile_bounds(tile);
+        assert!(bounds.min_lat <= 47.3769 && 47.3769 <= bounds.max_lat);
+        assert!(bounds.min_lng <= 8.5417 && 8.5417 <= bounds.max_lng);
+    }
+
+    #[test]
+    fn test_heatmap_to_tiles_partitions_cells() {
+        // ~5km east-west line crosses several z14 tiles (~1.5km wide here)
+        let track = vec![GpsPoint::new(47.37, 8.50), GpsPoint::new(47.37, 8.57)];
+        let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+
+        let tiles = heatmap_to_tiles(&heatmap, 14);
+        assert!(tiles.len() >= 3);
+        assert_eq!(tiles.iter().map(|t| t.cells.len()).sum::<usize>(), heatmap.cells.len());
+        assert!(tiles.windows(2).all(|w| (w[0].x, w[0].y) < (w[1].x, w[1].y)));
+
+        let first = &tiles[0];
+        let single = heatmap_tile(&heatmap, TileCoord { z: 14, x: first.x, y: first.y }).unwrap();
+        assert_eq!(single.cells.len(), first.cells.len());
+        assert!(heatmap_tile(&heatmap, TileCoord { z: 14, x: 0, y: 0 }).is_none());
+
+        // Zooms past the maximum have no tiles rather than the max zoom's cells
+        let cell = &heatmap.cells[0];
+        let deepest = lat_lng_to_tile(cell.center_lat, cell.center_lng, MAX_TILE_ZOOM);
+        assert!(heatmap_tile(&heatmap, deepest).is_some());
+        assert!(heatmap_tile(&heatmap, TileCoord { z: MAX_TILE_ZOOM + 1, ..deepest }).is_none());
+    }
+}
@@ -0,0 +1,391 @@
+//! # Hierarchical Route Grouping
+//!
+//! Builds a two-level tree on top of the strict route groups:
+//!
+//! - **Level 0** - strict same-journey groups from [`crate::group_signatures`]
+//! - **Level 1** - clusters of level-0 groups that share most of their geometry
+//!   (variants of the same route: a detour, an extra block, a different finish)
+//!
+//! This lets apps show "Route X and its 3 variants" without loosening the
+//! strict grouping thresholds.
+//!
+//! ## Algorithm
+//! 1. Pick a representative signature for each strict group (its first activity)
+//! 2. Pre-filter candidate group pairs by bounding box overlap
+//! 3. Measure shared geometry as the fraction of each resampled representative
+//!    lying within `endpoint_threshold` of the other, and take the minimum of
+//!    both directions (so a short route fully contained in a long one is not a variant)
+//! 4. Union-Find groups whose shared geometry exceeds `loose_group_min_overlap`
+//! 5. The group with the most activities becomes the primary of each cluster
+//!
+//! ## Variants within a group
+//!
+//! Strict groups tolerate small differences: a shortcut through a park or an
+//! extra lap of a loop still counts as the same journey. [`group_variants`]
+//! splits each group one level further into [`RouteVariant`]s of activities
+//! that share almost all of their geometry and length:
+//!
+//! 1. Resample each activity to a point every `perfect_threshold` meters
+//! 2. Walk the activities in group order; each joins the first variant whose
+//!    first activity shares at least `variant_min_overlap` of its geometry
+//!    (within `perfect_threshold`, in both directions) and length with it
+//! 3. Activities matching no variant start a new one
+
+use std::collections::HashMap;
+use crate::{GpsPoint, MatchConfig, RouteGroup, RouteSignature};
+use crate::geo_utils::{bounds_overlap, resample_polyline};
+use crate::metrics::shared_fraction;
+
+/// A level-1 cluster: a primary route group and the groups that are variants of it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct RouteHierarchy {
+    /// ID of the cluster (the group_id of the primary group)
+    pub hierarchy_id: String,
+    /// The strict group with the most activities
+    pub primary: RouteGroup,
+    /// Other strict groups sharing most of their geometry with the primary,
+    /// sorted by activity count (most first)
+    pub variants: Vec<RouteGroup>,
+}
+
+impl RouteHierarchy {
+    /// Total number of activities across the primary group and all variants.
+    pub fn activity_count(&self) -> usize {
+        self.primary.activity_ids.len()
+            + self.variants.iter().map(|g| g.activity_ids.len()).sum::<usize>()
+    }
+}
+
+/// Cluster existing strict route groups into a variant hierarchy.
+///
+/// Groups whose representative signature cannot be found in `signatures` are
+/// returned as standalone clusters without variants.
+///
+/// # Example
+/// ```
+/// use route_matcher::{GpsPoint, RouteSignature, MatchConfig, RouteGroup, build_group_hierarchy};
+///
+/// let config = MatchConfig::default();
+/// let points: Vec<GpsPoint> = (0..10)
+///     .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+///     .collect();
+/// let sig = RouteSignature::from_points("a", &points, &config).unwrap();
+/// let groups = vec![RouteGroup { group_id: "a".to_string(), activity_ids: vec!["a".to_string()] }];
+///
+/// let tree = build_group_hierarchy(&groups, &[sig], &config);
+/// assert_eq!(tree.len(), 1);
+/// assert!(tree[0].variants.is_empty());
+/// ```
+pub fn build_group_hierarchy(
+    groups: &[RouteGroup],
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+) -> Vec<RouteHierarchy> {
+    if groups.is_empty() {
+        return vec![];
+    }
+
+    let sig_map: HashMap<&str, &RouteSignature> = signatures
+        .iter()
+        .map(|s| (s.activity_id.as_str(), s))
+        .collect();
+
+    // Representative (resampled) polyline for each group
+    let representatives: Vec<Option<(&RouteSignature, Vec<GpsPoint>)>> = groups
+        .iter()
+        .map(|g| {
+            let sig = g.activity_ids.iter().find_map(|id| sig_map.get(id.as_str()))?;
+            let (resampled, _) = crate::resample_signature(sig, config.resample_count as usize);
+            Some((*sig, resampled))
+        })
+        .collect();
+
+    // Union-Find over group IDs
+    let mut parent: HashMap<String, String> = groups
+        .iter()
+        .map(|g| (g.group_id.clone(), g.group_id.clone()))
+        .collect();
+
+    for i in 0..groups.len() {
+        let Some((sig_i, points_i)) = &representatives[i] else { continue };
+
+        for j in (i + 1)..groups.len() {
+            let Some((sig_j, points_j)) = &representatives[j] else { continue };
+
+            let ref_lat = (sig_i.bounds.min_lat + sig_i.bounds.max_lat) / 2.0;
+            if !bounds_overlap(&sig_i.bounds, &sig_j.bounds, config.endpoint_threshold, ref_lat) {
+                continue;
+            }
+
+            let overlap = shared_fraction(points_i, points_j, config.endpoint_threshold);
+            if overlap >= config.loose_group_min_overlap {
+                crate::union(&mut parent, &groups[i].group_id, &groups[j].group_id);
+            }
+        }
+    }
+
+    // Collect clusters
+    let mut clusters: HashMap<String, Vec<&RouteGroup>> = HashMap::new();
+    for group in groups {
+        let root = crate::find(&mut parent, &group.group_id);
+        clusters.entry(root).or_default().push(group);
+    }
+
+    let mut result: Vec<RouteHierarchy> = clusters
+        .into_values()
+        .map(|mut members| {
+            // Most activities first, then by ID for deterministic output
+            members.sort_by(|a, b| {
+                b.activity_ids.len().cmp(&a.activity_ids.len())
+                    .then_with(|| a.group_id.cmp(&b.group_id))
+            });
+            let primary = members[0].clone();
+            let variants = members[1..].iter().map(|g| (*g).clone()).collect();
+            RouteHierarchy {
+                hierarchy_id: primary.group_id.clone(),
+                primary,
+                variants,
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        b.activity_count().cmp(&a.activity_count())
+            .then_with(|| a.hierarchy_id.cmp(&b.hierarchy_id))
+    });
+
+    result
+}
+
+/// Group signatures strictly, then cluster the groups into a variant hierarchy.
+///
+/// Uses parallel grouping when the `parallel` feature is enabled.
+pub fn group_signatures_hierarchical(
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+) -> Vec<RouteHierarchy> {
+    #[cfg(feature = "parallel")]
+    let groups = crate::group_signatures_parallel(signatures, config);
+
+    #[cfg(not(feature = "parallel"))]
+    let groups = crate::group_signatures(signatures, config);
+
+    build_group_hierarchy(&groups, signatures, config)
+}
+
+/// Most resampled points per activity when splitting a group into variants.
+const MAX_VARIANT_SAMPLES: usize = 1000;
+
+/// Activities within a route group that follow the same variant of the route.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct RouteVariant {
+    /// ID of the variant (its first activity)
+    pub variant_id: String,
+    /// Activities taking this variant, in group order
+    pub activity_ids: Vec<String>,
+    /// Route distance of the first activity in meters
+    pub distance_meters: f64,
+}
+
+/// The variants of one route group, most activities first.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct GroupVariants {
+    pub group_id: String,
+    pub variants: Vec<RouteVariant>,
+}
+
+/// Split each route group into variants of activities sharing almost all of
+/// their geometry (see the module docs).
+///
+/// Activities without a signature in `signatures` are left out.
+///
+/// # Example
+/// ```
+/// use route_matcher::{GpsPoint, RouteSignature, MatchConfig, RouteGroup, group_variants};
+///
+/// let config = MatchConfig::default();
+/// let lap: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1)).collect();
+/// let mut two_laps = lap.clone();
+/// two_laps.extend(lap.iter().rev().copied());
+///
+/// let signatures = vec![
+///     RouteSignature::from_points("a", &lap, &config).unwrap(),
+///     RouteSignature::from_points("b", &lap, &config).unwrap(),
+///     RouteSignature::from_points("extra-lap", &two_laps, &config).unwrap(),
+/// ];
+/// let ids = ["a", "b", "extra-lap"].map(String::from).to_vec();
+/// let groups = vec![RouteGroup { group_id: "a".to_string(), activity_ids: ids }];
+///
+/// let variants = group_variants(&groups, &signatures, &config);
+/// assert_eq!(variants[0].variants.len(), 2);
+/// assert_eq!(variants[0].variants[0].activity_ids, vec!["a".to_string(), "b".to_string()]);
+/// ```
+pub fn group_variants(
+    groups: &[RouteGroup],
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+) -> Vec<GroupVariants> {
+    let sig_map: HashMap<&str, &RouteSignature> = signatures
+        .iter()
+        .map(|s| (s.activity_id.as_str(), s))
+        .collect();
+
+    groups
+        .iter()
+        .map(|group| {
+            let members: Vec<&RouteSignature> = group
+                .activity_ids
+                .iter()
+                .filter_map(|id| sig_map.get(id.as_str()).copied())
+                .collect();
+            GroupVariants {
+                group_id: group.group_id.clone(),
+                variants: split_variants(&members, config),
+            }
+        })
+        .collect()
+}
+
+/// Leader clustering of one group's activities into variants.
+fn split_variants(members: &[&RouteSignature], config: &MatchConfig) -> Vec<RouteVariant> {
+    let tolerance = config.perfect_threshold;
+    let samples = |sig: &RouteSignature| {
+        let count = (sig.total_distance / tolerance).ceil() as usize;
+        resample_polyline(&sig.points, count.clamp(config.resample_count as usize, MAX_VARIANT_SAMPLES))
+    };
+
+    // (leader, leader samples, variant)
+    let mut variants: Vec<(&RouteSignature, Vec<GpsPoint>, RouteVariant)> = Vec::new();
+    for sig in members {
+        let points = samples(sig);
+        let existing = variants.iter_mut().find(|(leader, leader_points, _)| {
+            let (shorter, longer) = if leader.total_distance < sig.total_distance {
+                (leader.total_distance, sig.total_distance)
+            } else {
+                (sig.total_distance, leader.total_distance)
+            };
+            longer > 0.0
+                && shorter / longer >= config.variant_min_overlap
+                && shared_fraction(leader_points, &points, tolerance) >= config.variant_min_overlap
+        });
+        match existing {
+            Some((_, _, variant)) => variant.activity_ids.push(sig.activity_id.clone()),
+            None => {
+                let variant = RouteVariant {
+                    variant_id: sig.activity_id.clone(),
+                    activity_ids: vec![sig.activity_id.clone()],
+                    distance_meters: sig.total_distance,
+                };
+                variants.push((sig, points, variant));
+            }
+        }
+    }
+
+    let mut variants: Vec<RouteVariant> = variants.into_iter().map(|(_, _, v)| v).collect();
+    // Stable sort keeps group order among variants of equal size
+    variants.sort_by_key(|v| std::cmp::Reverse(v.activity_ids.len()));
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Straight north-bound route starting at the given longitude offset
+    fn straight_route(lng_offset: f64, count: usize) -> Vec<GpsPoint> {
+        (0..count)
+            .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278 + lng_offset))
+            .collect()
+    }
+
+    fn group(id: &str, members: &[&str]) -> RouteGroup {
+        RouteGroup {
+            group_id: id.to_string(),
+            activity_ids: members.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_variant_clustered_with_primary() {
+        let config = MatchConfig::default();
+        let base = straight_route(0.0, 20);
+
+        // Variant: same route with a ~300m detour east in the middle
+        let mut variant = base.clone();
+        for p in variant.iter_mut().skip(9).take(2) {
+            p.longitude += 0.004;
+        }
+
+        let sig_a = RouteSignature::from_points("a", &base, &config).unwrap();
+        let sig_b = RouteSignature::from_points("b", &base, &config).unwrap();
+        let sig_c = RouteSignature::from_points("c", &variant, &config).unwrap();
+
+        let groups = vec![group("a", &["a", "b"]), group("c", &["c"])];
+        let tree = build_group_hierarchy(&groups, &[sig_a, sig_b, sig_c], &config);
+
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].primary.group_id, "a");
+        assert_eq!(tree[0].variants.len(), 1);
+        assert_eq!(tree[0].variants[0].group_id, "c");
+        assert_eq!(tree[0].activity_count(), 3);
+    }
+
+    #[test]
+    fn test_contained_short_route_not_variant() {
+        let config = MatchConfig::default();
+        let long = straight_route(0.0, 40);
+        let short = straight_route(0.0, 10);
+
+        let sig_long = RouteSignature::from_points("long", &long, &config).unwrap();
+        let sig_short = RouteSignature::from_points("short", &short, &config).unwrap();
+
+        let groups = vec![group("long", &["long"]), group("short", &["short"])];
+        let tree = build_group_hierarchy(&groups, &[sig_long, sig_short], &config);
+
+        assert_eq!(tree.len(), 2);
+        assert!(tree.iter().all(|h| h.variants.is_empty()));
+    }
+
+    #[test]
+    fn test_shortcut_is_separate_variant() {
+        let config = MatchConfig::default();
+        // Out east, north, back west; the shortcut cuts the corner
+        let mut full: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.5, -0.1 + i as f64 * 0.001)).collect();
+        full.extend((1..10).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.091)));
+        let mut shortcut = full.clone();
+        shortcut.drain(8..11);
+
+        let sigs = vec![
+            RouteSignature::from_points("a", &full, &config).unwrap(),
+            RouteSignature::from_points("cut", &shortcut, &config).unwrap(),
+            RouteSignature::from_points("b", &full, &config).unwrap(),
+        ];
+        let groups = vec![group("a", &["a", "cut", "b"])];
+
+        let variants = group_variants(&groups, &sigs, &config);
+        assert_eq!(variants.len(), 1);
+        let ids: Vec<Vec<String>> = variants[0].variants.iter().map(|v| v.activity_ids.clone()).collect();
+        assert_eq!(ids, vec![vec!["a".to_string(), "b".to_string()], vec!["cut".to_string()]]);
+        assert_eq!(variants[0].variants[1].variant_id, "cut");
+    }
+
+    #[test]
+    fn test_distant_groups_stay_separate() {
+        let config = MatchConfig::default();
+        let london = straight_route(0.0, 10);
+        let nyc: Vec<GpsPoint> = (0..10)
+            .map(|i| GpsPoint::new(40.7128 + i as f64 * 0.001, -74.0060))
+            .collect();
+
+        let sigs = vec![
+            RouteSignature::from_points("l", &london, &config).unwrap(),
+            RouteSignature::from_points("n", &nyc, &config).unwrap(),
+        ];
+
+        let tree = group_signatures_hierarchical(&sigs, &config);
+        assert_eq!(tree.len(), 2);
+    }
+}
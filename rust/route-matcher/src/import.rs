@@ -0,0 +1,587 @@
+//! # Offline Import
+//!
+//! Reads activities from GPX and FIT files, for users who keep their history
+//! as exported files rather than on intervals.icu.
+//!
+//! [`import_directory`] walks a folder (recursively), parses every `.gpx` and
+//! `.fit` file in parallel, and returns one [`ImportedActivity`] per file with
+//! its start time, sport type and name taken from the file's metadata. The
+//! activity ID is derived from the start time (`import-<unix seconds>`), so
+//! re-importing a folder yields the same IDs, and a GPX and FIT export of the
+//! same activity collapse into one. Files without a start time fall back to
+//! their file name.
+//!
+//! Unreadable or malformed files are logged and skipped rather than failing
+//! the whole import.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use route_matcher::{MatchConfig, RouteSignature, group_signatures};
+//! use route_matcher::import::import_directory;
+//!
+//! let activities = import_directory("/home/me/exports".as_ref()).unwrap();
+//! let config = MatchConfig::default();
+//! let signatures: Vec<RouteSignature> = activities
+//!     .iter()
+//!     .filter_map(|a| RouteSignature::from_points(&a.activity_id, &a.points, &config))
+//!     .collect();
+//! let groups = group_signatures(&signatures, &config);
+//! ```
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use crate::GpsPoint;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Seconds between the Unix epoch and the FIT epoch (1989-12-31T00:00:00Z)
+const FIT_EPOCH_OFFSET: i64 = 631_065_600;
+
+/// FIT global message numbers and fields read by the importer
+const FIT_MSG_FILE_ID: u16 = 0;
+const FIT_MSG_SPORT: u16 = 12;
+const FIT_MSG_SESSION: u16 = 18;
+const FIT_MSG_RECORD: u16 = 20;
+const FIT_FIELD_TIMESTAMP: u8 = 253;
+
+/// An activity read from a file
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ImportedActivity {
+    /// `import-<start timestamp>`, or the file stem if the file has no times
+    pub activity_id: String,
+    /// Track name, if the file has one
+    pub name: Option<String>,
+    /// Sport type ("Run", "Ride", etc.), "Other" if not recorded
+    pub sport_type: String,
+    /// Start time (Unix timestamp, seconds)
+    pub timestamp: Option<i64>,
+    /// Valid GPS points in recording order
+    pub points: Vec<GpsPoint>,
+}
+
+impl ImportedActivity {
+    fn new(name: Option<String>, sport_type: String, timestamp: Option<i64>, points: Vec<GpsPoint>) -> Self {
+        Self {
+            activity_id: timestamp.map(|t| format!("import-{}", t)).unwrap_or_default(),
+            name,
+            sport_type,
+            timestamp,
+            points,
+        }
+    }
+}
+
+/// Import every GPX and FIT file under `dir`, in parallel with the
+/// `parallel` feature.
+///
+/// Activities are returned in file path order. When several files derive the
+/// same activity ID (e.g. GPX and FIT exports of one activity), the first is
+/// kept. Fails only if `dir` can't be read.
+pub fn import_directory(dir: &Path) -> Result<Vec<ImportedActivity>, String> {
+    let start = std::time::Instant::now();
+    let mut paths = Vec::new();
+    collect_files(dir, &mut paths)?;
+    paths.sort();
+
+    #[cfg(feature = "parallel")]
+    let iter = paths.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let iter = paths.iter();
+
+    let parsed: Vec<Option<ImportedActivity>> = iter
+        .map(|path| match import_file(path) {
+            Ok(activity) => Some(activity),
+            Err(e) => {
+                warn!("[Import] Skipping {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    let activities: Vec<ImportedActivity> = parsed
+        .into_iter()
+        .flatten()
+        .filter(|a| seen.insert(a.activity_id.clone()))
+        .collect();
+
+    info!(
+        "[Import] {} activities from {} files in {:?}",
+        activities.len(),
+        paths.len(),
+        start.elapsed()
+    );
+    Ok(activities)
+}
+
+/// Import one GPX or FIT file, chosen by extension.
+pub fn import_file(path: &Path) -> Result<ImportedActivity, String> {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    let mut activity = match extension.as_deref() {
+        Some("gpx") => {
+            let xml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            parse_gpx(&xml)?
+        }
+        Some("fit") => {
+            let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+            parse_fit(&bytes)?
+        }
+        _ => return Err("Unrecognized file type".to_string()),
+    };
+    if activity.activity_id.is_empty() {
+        let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+        activity.activity_id = format!("import-{}", stem);
+    }
+    Ok(activity)
+}
+
+fn is_recognized(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("gpx") || e.eq_ignore_ascii_case("fit"))
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            // Unreadable subdirectories are skipped, not fatal
+            if let Err(e) = collect_files(&path, paths) {
+                warn!("[Import] {}", e);
+            }
+        } else if is_recognized(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// GPX
+// ============================================================================
+
+/// Parse a GPX document. Track points (`trkpt`) are used, or route points
+/// (`rtept`) for files without a track. The activity ID is empty if no point
+/// or metadata time is present.
+pub fn parse_gpx(xml: &str) -> Result<ImportedActivity, String> {
+    if !xml.contains("<gpx") {
+        return Err("Not a GPX document".to_string());
+    }
+
+    let mut track_points = Vec::new();
+    let mut route_points = Vec::new();
+    let mut track_name = None;
+    let mut metadata_name = None;
+    let mut sport = None;
+    let mut first_point_time = None;
+    let mut metadata_time = None;
+    // Innermost open element of interest: "metadata", "trk" or "trkpt"
+    let mut context: Vec<&str> = Vec::new();
+
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(close) = rest.find('>') else { break };
+        let tag = &rest[..close];
+        rest = &rest[close + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        // Ignore namespace prefixes (gpx:trkpt)
+        let name = name.rsplit(':').next().unwrap_or(name);
+
+        if closing {
+            if context.last() == Some(&name) {
+                context.pop();
+            }
+            continue;
+        }
+
+        match name {
+            "metadata" | "trk" if !self_closing => context.push(name),
+            "trkpt" | "rtept" => {
+                let lat = attribute(tag, "lat").and_then(|v| v.parse::<f64>().ok());
+                let lon = attribute(tag, "lon").and_then(|v| v.parse::<f64>().ok());
+                if let (Some(lat), Some(lon)) = (lat, lon) {
+                    let point = GpsPoint::new(lat, lon);
+                    if point.is_valid() {
+                        if name == "trkpt" { track_points.push(point) } else { route_points.push(point) }
+                    }
+                }
+                if name == "trkpt" && !self_closing {
+                    context.push(name);
+                }
+            }
+            "name" | "type" | "time" if !self_closing => {
+                let text = element_text(rest);
+                match (name, context.last().copied()) {
+                    ("name", Some("trk")) if track_name.is_none() => track_name = Some(text),
+                    ("name", Some("metadata")) => metadata_name = Some(text),
+                    ("type", Some("trk")) if sport.is_none() => sport = Some(text),
+                    ("time", Some("trkpt")) if first_point_time.is_none() => first_point_time = parse_iso8601(&text),
+                    ("time", Some("metadata")) => metadata_time = parse_iso8601(&text),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let points = if track_points.is_empty() { route_points } else { track_points };
+    if points.is_empty() {
+        return Err("No valid track points".to_string());
+    }
+    let sport_type = sport.as_deref().map_or("Other", sport_type_from_name).to_string();
+    Ok(ImportedActivity::new(
+        track_name.or(metadata_name).filter(|n| !n.is_empty()),
+        sport_type,
+        first_point_time.or(metadata_time),
+        points,
+    ))
+}
+
+/// Value of attribute `name` in the inside of a tag (`trkpt lat="51.5" ...`).
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut search = tag;
+    while let Some(pos) = search.find(name) {
+        let preceded_by_space = search[..pos].ends_with(char::is_whitespace);
+        let after = search[pos + name.len()..].trim_start();
+        if let (true, Some(value)) = (preceded_by_space, after.strip_prefix('=')) {
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = &value[1..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+        search = &search[pos + name.len()..];
+    }
+    None
+}
+
+/// Text content of an element starting at `rest` (just after its start tag),
+/// unescaped and trimmed.
+fn element_text(rest: &str) -> String {
+    let rest = rest.trim_start();
+    if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+        return cdata.find("]]>").map_or("", |end| &cdata[..end]).trim().to_string();
+    }
+    let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+    text.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Sport type from a free-form activity type ("running", "cycling", "Ride").
+fn sport_type_from_name(name: &str) -> &'static str {
+    let name = name.to_ascii_lowercase();
+    if name.contains("run") {
+        "Run"
+    } else if name.contains("cycl") || name.contains("bik") || name.contains("ride") {
+        "Ride"
+    } else if name.contains("swim") {
+        "Swim"
+    } else if name.contains("walk") {
+        "Walk"
+    } else if name.contains("hik") {
+        "Hike"
+    } else if name.contains("ski") {
+        "NordicSki"
+    } else if name.contains("row") {
+        "Rowing"
+    } else {
+        "Other"
+    }
+}
+
+/// Unix timestamp from ISO 8601 `YYYY-MM-DDTHH:MM:SS[.fff][Z|±HH:MM]`.
+fn parse_iso8601(value: &str) -> Option<i64> {
+    let (date, time) = value.trim().split_once('T')?;
+    let mut date = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Split off the offset: "Z", "+01:00" or "-05:00"
+    let (time, offset_seconds) = match time.find(['Z', '+', '-']) {
+        Some(i) if &time[i..] == "Z" => (&time[..i], 0),
+        Some(i) => {
+            let sign = if time[i..].starts_with('-') { -1 } else { 1 };
+            let mut offset = time[i + 1..].split(':').map(|p| p.parse::<i64>().ok());
+            let (hours, minutes) = (offset.next()??, offset.next().flatten().unwrap_or(0));
+            (&time[..i], sign * (hours * 3600 + minutes * 60))
+        }
+        None => (time, 0),
+    };
+    let mut time = time.split(':').map(|p| p.parse::<f64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next().flatten().unwrap_or(0.0));
+
+    let days = crate::analytics::days_from_civil(year, month as u32, day as u32);
+    Some(days * SECONDS_PER_DAY + (hour * 3600.0 + minute * 60.0 + second) as i64 - offset_seconds)
+}
+
+// ============================================================================
+// FIT
+// ============================================================================
+
+/// Field layout of a FIT local message type
+#[derive(Clone)]
+struct FitDefinition {
+    global: u16,
+    big_endian: bool,
+    /// (field number, size in bytes)
+    fields: Vec<(u8, usize)>,
+    developer_size: usize,
+}
+
+/// Parse a FIT activity file: positions and timestamps from record messages,
+/// sport from the sport or session message. CRCs are not checked.
+pub fn parse_fit(bytes: &[u8]) -> Result<ImportedActivity, String> {
+    let header_size = *bytes.first().ok_or("Empty file")? as usize;
+    if header_size < 12 || bytes.len() < header_size || &bytes[8..12] != b".FIT" {
+        return Err("Not a FIT file".to_string());
+    }
+    let data_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let data = bytes
+        .get(header_size..header_size + data_size)
+        .ok_or("Truncated FIT file")?;
+
+    let mut definitions: [Option<FitDefinition>; 16] = Default::default();
+    let mut points = Vec::new();
+    let mut first_timestamp = None;
+    let mut time_created = None;
+    let mut sport = None;
+    let mut last_timestamp: u32 = 0;
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let header = data[pos];
+        pos += 1;
+
+        // Compressed timestamp header: data message with a 5-bit time offset
+        let (local, compressed_time) = if header & 0x80 != 0 {
+            let offset = (header & 0x1F) as u32;
+            let mut timestamp = (last_timestamp & !0x1F) + offset;
+            if offset < last_timestamp & 0x1F {
+                timestamp += 0x20;
+            }
+            (((header >> 5) & 0x03) as usize, Some(timestamp))
+        } else if header & 0x40 != 0 {
+            let has_developer_fields = header & 0x20 != 0;
+            let (definition, size) = parse_fit_definition(&data[pos..], has_developer_fields)?;
+            definitions[(header & 0x0F) as usize] = Some(definition);
+            pos += size;
+            continue;
+        } else {
+            ((header & 0x0F) as usize, None)
+        };
+
+        let definition = definitions[local].as_ref().ok_or("Data message without definition")?;
+        let mut record_timestamp = compressed_time;
+        let mut lat = None;
+        let mut lng = None;
+        for &(field, size) in &definition.fields {
+            let raw = data.get(pos..pos + size).ok_or("Truncated FIT record")?;
+            pos += size;
+            let value = fit_uint(raw, definition.big_endian);
+            match (definition.global, field, size) {
+                (_, FIT_FIELD_TIMESTAMP, 4) if value != u32::MAX as u64 => record_timestamp = Some(value as u32),
+                (FIT_MSG_RECORD, 0, 4) => lat = fit_semicircles(value),
+                (FIT_MSG_RECORD, 1, 4) => lng = fit_semicircles(value),
+                (FIT_MSG_FILE_ID, 4, 4) if value != u32::MAX as u64 => time_created = Some(value as u32),
+                (FIT_MSG_SPORT, 0, 1) | (FIT_MSG_SESSION, 5, 1) if sport.is_none() && value != 0xFF => {
+                    sport = Some(value as u8)
+                }
+                _ => {}
+            }
+        }
+        pos += definition.developer_size;
+
+        if let Some(timestamp) = record_timestamp {
+            last_timestamp = timestamp;
+        }
+        if definition.global == FIT_MSG_RECORD {
+            if let (Some(lat), Some(lng)) = (lat, lng) {
+                let point = GpsPoint::new(lat, lng);
+                if point.is_valid() {
+                    first_timestamp = first_timestamp.or(record_timestamp);
+                    points.push(point);
+                }
+            }
+        }
+    }
+
+    if points.is_empty() {
+        return Err("No valid track points".to_string());
+    }
+    let timestamp = first_timestamp.or(time_created).map(|t| t as i64 + FIT_EPOCH_OFFSET);
+    let sport_type = sport.map_or("Other", fit_sport_type).to_string();
+    Ok(ImportedActivity::new(None, sport_type, timestamp, points))
+}
+
+/// Parse a definition message body, returning it and its size in bytes.
+fn parse_fit_definition(data: &[u8], has_developer_fields: bool) -> Result<(FitDefinition, usize), String> {
+    let truncated = || "Truncated FIT definition".to_string();
+    let head = data.get(..5).ok_or_else(truncated)?;
+    let big_endian = head[1] == 1;
+    let global = if big_endian { u16::from_be_bytes([head[2], head[3]]) } else { u16::from_le_bytes([head[2], head[3]]) };
+    let field_count = head[4] as usize;
+    let mut size = 5 + field_count * 3;
+    let fields = data
+        .get(5..size)
+        .ok_or_else(truncated)?
+        .chunks(3)
+        .map(|f| (f[0], f[1] as usize))
+        .collect();
+
+    let mut developer_size = 0;
+    if has_developer_fields {
+        let count = *data.get(size).ok_or_else(truncated)? as usize;
+        let developer_fields = data.get(size + 1..size + 1 + count * 3).ok_or_else(truncated)?;
+        developer_size = developer_fields.chunks(3).map(|f| f[1] as usize).sum();
+        size += 1 + count * 3;
+    }
+    Ok((FitDefinition { global, big_endian, fields, developer_size }, size))
+}
+
+/// Unsigned integer of up to 8 bytes
+fn fit_uint(raw: &[u8], big_endian: bool) -> u64 {
+    let fold = |acc: u64, b: &u8| (acc << 8) | *b as u64;
+    if big_endian {
+        raw.iter().take(8).fold(0, fold)
+    } else {
+        raw.iter().take(8).rev().fold(0, fold)
+    }
+}
+
+/// Degrees from a sint32 semicircle value; None for the invalid marker.
+fn fit_semicircles(value: u64) -> Option<f64> {
+    let semicircles = value as u32 as i32;
+    (semicircles != i32::MAX).then(|| semicircles as f64 * (180.0 / 2_147_483_648.0))
+}
+
+/// Sport type for a FIT `sport` enum value.
+fn fit_sport_type(sport: u8) -> &'static str {
+    match sport {
+        1 => "Run",
+        2 => "Ride",
+        5 => "Swim",
+        11 => "Walk",
+        12 => "NordicSki",
+        13 => "AlpineSki",
+        15 => "Rowing",
+        17 => "Hike",
+        21 => "EBikeRide",
+        _ => "Other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <metadata><name>Export</name><time>2024-10-01T06:00:00Z</time></metadata>
+  <trk>
+    <name><![CDATA[Morning Run & Coffee]]></name>
+    <type>running</type>
+    <trkseg>
+      <trkpt lat="51.5000" lon="-0.1000"><ele>10</ele><time>2024-10-01T08:00:00+01:00</time></trkpt>
+      <!-- <trkpt lat="0" lon="0"/> -->
+      <trkpt lon='-0.1010' lat='51.5010'><time>2024-10-01T08:00:05+01:00</time></trkpt>
+      <trkpt lat="999" lon="0"/>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    /// Minimal little-endian FIT file: a sport message and record messages
+    /// of (timestamp, lat, lng), the last with a compressed timestamp.
+    fn fit_file(sport: u8, records: &[(u32, f64, f64)]) -> Vec<u8> {
+        let semicircles = |deg: f64| ((deg / 180.0 * 2_147_483_648.0) as i32).to_le_bytes();
+        let mut data = vec![0x40, 0, 0, 12, 0, 1, 0, 1, 0x00, 0x00, sport];
+        data.extend([0x41, 0, 0, 20, 0, 3, 253, 4, 0x86, 0, 4, 0x85, 1, 4, 0x85]);
+        for (i, &(timestamp, lat, lng)) in records.iter().enumerate() {
+            if i + 1 == records.len() && i > 0 {
+                // Compressed header, local type 2 -> defined below as lat/lng only
+                data.extend([0x42, 0, 0, 20, 0, 2, 0, 4, 0x85, 1, 4, 0x85]);
+                data.push(0x80 | (2 << 5) | (timestamp & 0x1F) as u8);
+            } else {
+                data.push(0x01);
+                data.extend(timestamp.to_le_bytes());
+            }
+            data.extend(semicircles(lat));
+            data.extend(semicircles(lng));
+        }
+        let mut file = vec![12, 0x10, 0, 0];
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(b".FIT");
+        file.extend(data);
+        file.extend([0, 0]);
+        file
+    }
+
+    #[test]
+    fn test_parse_gpx() {
+        let activity = parse_gpx(GPX).unwrap();
+        assert_eq!(activity.points.len(), 2);
+        assert_eq!(activity.points[1], GpsPoint::new(51.501, -0.101));
+        assert_eq!(activity.name.as_deref(), Some("Morning Run & Coffee"));
+        assert_eq!(activity.sport_type, "Run");
+        // 08:00 +01:00 is 07:00 UTC
+        assert_eq!(activity.timestamp, parse_iso8601("2024-10-01T07:00:00Z"));
+        assert_eq!(activity.activity_id, format!("import-{}", activity.timestamp.unwrap()));
+        assert!(parse_gpx("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_parse_fit() {
+        let records = [(1_000_000_000, 51.5, -0.1), (1_000_000_005, 51.501, -0.1), (1_000_000_010, 51.502, -0.1)];
+        let activity = parse_fit(&fit_file(2, &records)).unwrap();
+        assert_eq!(activity.sport_type, "Ride");
+        assert_eq!(activity.timestamp, Some(1_000_000_000 + FIT_EPOCH_OFFSET));
+        assert_eq!(activity.points.len(), 3);
+        assert!((activity.points[2].latitude - 51.502).abs() < 1e-6);
+        assert!(parse_fit(b"not a fit file").is_err());
+    }
+
+    #[test]
+    fn test_import_directory_dedupes_and_recurses() {
+        let dir = std::env::temp_dir().join(format!("route-matcher-import-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("a.gpx"), GPX).unwrap();
+        std::fs::write(dir.join("nested/b.GPX"), GPX).unwrap();
+        std::fs::write(dir.join("c.fit"), fit_file(1, &[(1_000_000_000, 51.5, -0.1)])).unwrap();
+        std::fs::write(dir.join("broken.fit"), b"garbage").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let activities = import_directory(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // b.GPX duplicates a.gpx; broken.fit and notes.txt are skipped
+        let sports: Vec<_> = activities.iter().map(|a| a.sport_type.as_str()).collect();
+        assert_eq!(sports, ["Run", "Run"]);
+        assert_ne!(activities[0].activity_id, activities[1].activity_id);
+    }
+}
@@ -0,0 +1,180 @@
+//! # Virtual and Indoor Activities
+//!
+//! Detection of activities whose GPS doesn't describe a real-world path:
+//! virtual rides and runs (Zwift and similar apps), treadmill runs and
+//! trainer rides recorded with a phone lying on a shelf.
+//!
+//! Their coordinates pollute real-world results: a Zwift ride is placed on a
+//! fictional island in the Pacific, a treadmill run is a smudge of points in
+//! one living room, and both are repeated often enough to form route groups,
+//! sections and heatmap hot spots of their own.
+//!
+//! ## Detection
+//!
+//! | [`IndoorKind`] | Detected when |
+//! |----------------|---------------|
+//! | `VirtualSport` | The sport type is one of [`IndoorConfig::virtual_sport_types`] ("VirtualRide", "VirtualRun", ...) |
+//! | `NoGps` | Fewer than 2 valid points remain after dropping (0, 0) fixes |
+//! | `Stationary` | Every point lies within [`IndoorConfig::min_extent_meters`] of the first |
+//! | `VirtualWorld` | Most points lie inside one of [`IndoorConfig::virtual_regions`] |
+//!
+//! ## Exclusion
+//!
+//! Set `exclude_indoor` on [`crate::MatchConfig`] (used by
+//! [`crate::group_signatures_by_sport`] and [`crate::RouteLibrary`]),
+//! [`crate::SectionConfig`] or [`crate::HeatmapConfig`] to leave detected
+//! activities out of grouping, section detection or heatmaps. Detection then
+//! uses [`IndoorConfig::default`]; call [`detect_indoor`] directly for other
+//! settings.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, indoor::{detect_indoor, IndoorConfig, IndoorKind}};
+//!
+//! let outdoor: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1)).collect();
+//! let treadmill: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.5 + (i % 2) as f64 * 0.00001, -0.1)).collect();
+//! let config = IndoorConfig::default();
+//!
+//! assert_eq!(detect_indoor(&outdoor, Some("Run"), &config), None);
+//! assert_eq!(detect_indoor(&treadmill, Some("Run"), &config), Some(IndoorKind::Stationary));
+//! assert_eq!(detect_indoor(&outdoor, Some("VirtualRide"), &config), Some(IndoorKind::VirtualSport));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use crate::geo_utils::haversine_distance;
+use crate::preprocess::is_null_island;
+use crate::{Bounds, GpsPoint};
+
+/// Why an activity was classified as virtual or indoor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum IndoorKind {
+    /// The sport type marks the activity as virtual
+    VirtualSport,
+    /// No usable GPS: empty, invalid or all at (0, 0)
+    NoGps,
+    /// GPS never moved further than a room's width (treadmill, trainer)
+    Stationary,
+    /// GPS lies on a fictional map of a virtual riding app
+    VirtualWorld,
+}
+
+/// Settings for [`detect_indoor`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct IndoorConfig {
+    /// Sport types that are always virtual.
+    /// Default: "VirtualRide", "VirtualRun", "VirtualRow"
+    pub virtual_sport_types: Vec<String>,
+    /// Tracks whose points all stay within this distance (meters) of the
+    /// first point are treated as stationary. Default: 50.0
+    pub min_extent_meters: f64,
+    /// Regions holding fictional maps. Default: Zwift's Watopia, which is
+    /// placed in open sea east of the Solomon Islands.
+    pub virtual_regions: Vec<Bounds>,
+}
+
+impl Default for IndoorConfig {
+    fn default() -> Self {
+        Self {
+            virtual_sport_types: ["VirtualRide", "VirtualRun", "VirtualRow"].map(String::from).to_vec(),
+            min_extent_meters: 50.0,
+            virtual_regions: vec![Bounds { min_lat: -11.75, max_lat: -11.60, min_lng: 166.85, max_lng: 167.05 }],
+        }
+    }
+}
+
+/// Classify an activity as virtual or indoor.
+///
+/// Returns `None` for activities that look like real outdoor recordings.
+/// Checks run in the order of the [`IndoorKind`] variants.
+pub fn detect_indoor(points: &[GpsPoint], sport_type: Option<&str>, config: &IndoorConfig) -> Option<IndoorKind> {
+    if sport_type.is_some_and(|sport| config.virtual_sport_types.iter().any(|s| s == sport)) {
+        return Some(IndoorKind::VirtualSport);
+    }
+
+    let valid: Vec<&GpsPoint> = points.iter().filter(|p| p.is_valid() && !is_null_island(p)).collect();
+    let Some(first) = valid.first() else {
+        return Some(IndoorKind::NoGps);
+    };
+    if valid.len() < 2 {
+        return Some(IndoorKind::NoGps);
+    }
+
+    if valid.iter().all(|p| haversine_distance(first, p) <= config.min_extent_meters) {
+        return Some(IndoorKind::Stationary);
+    }
+
+    let in_virtual_world = config.virtual_regions.iter().any(|region| {
+        let inside = valid.iter().filter(|p| region_contains(region, p)).count();
+        inside * 2 > valid.len()
+    });
+    in_virtual_world.then_some(IndoorKind::VirtualWorld)
+}
+
+/// Whether [`detect_indoor`] classifies an activity as virtual or indoor.
+pub fn is_indoor(points: &[GpsPoint], sport_type: Option<&str>, config: &IndoorConfig) -> bool {
+    detect_indoor(points, sport_type, config).is_some()
+}
+
+/// IDs of the virtual or indoor activities among `tracks`.
+///
+/// `sport_types` maps activity ID to sport type; activities missing from it
+/// are judged by their GPS alone.
+pub fn indoor_activity_ids(
+    tracks: &[(String, Vec<GpsPoint>)],
+    sport_types: &HashMap<String, String>,
+    config: &IndoorConfig,
+) -> HashSet<String> {
+    tracks
+        .iter()
+        .filter(|(id, points)| is_indoor(points, sport_types.get(id).map(String::as_str), config))
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+fn region_contains(region: &Bounds, point: &GpsPoint) -> bool {
+    let lat_ok = point.latitude >= region.min_lat && point.latitude <= region.max_lat;
+    let lng_ok = if region.crosses_antimeridian() {
+        point.longitude >= region.min_lng || point.longitude <= region.max_lng
+    } else {
+        point.longitude >= region.min_lng && point.longitude <= region.max_lng
+    };
+    lat_ok && lng_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(lat: f64, lng: f64, count: usize) -> Vec<GpsPoint> {
+        (0..count).map(|i| GpsPoint::new(lat + i as f64 * 0.001, lng)).collect()
+    }
+
+    #[test]
+    fn test_detects_each_kind() {
+        let config = IndoorConfig::default();
+
+        assert_eq!(detect_indoor(&line(51.5, -0.1, 20), Some("Ride"), &config), None);
+        assert_eq!(detect_indoor(&line(51.5, -0.1, 20), Some("VirtualRun"), &config), Some(IndoorKind::VirtualSport));
+        assert_eq!(detect_indoor(&[], None, &config), Some(IndoorKind::NoGps));
+        assert_eq!(detect_indoor(&vec![GpsPoint::new(0.0, 0.0); 50], Some("Run"), &config), Some(IndoorKind::NoGps));
+        assert_eq!(detect_indoor(&vec![GpsPoint::new(51.5, -0.1); 50], Some("Run"), &config), Some(IndoorKind::Stationary));
+        // A Zwift ride exported with its sport type changed to "Ride"
+        assert_eq!(detect_indoor(&line(-11.7, 166.95, 50), Some("Ride"), &config), Some(IndoorKind::VirtualWorld));
+    }
+
+    #[test]
+    fn test_indoor_activity_ids() {
+        let tracks = vec![
+            ("outdoor".to_string(), line(51.5, -0.1, 20)),
+            ("zwift".to_string(), line(51.5, -0.1, 20)),
+            ("treadmill".to_string(), vec![GpsPoint::new(48.2, 16.4); 20]),
+        ];
+        let sports = HashMap::from([("zwift".to_string(), "VirtualRide".to_string())]);
+
+        let ids = indoor_activity_ids(&tracks, &sports, &IndoorConfig::default());
+        assert_eq!(ids, HashSet::from(["zwift".to_string(), "treadmill".to_string()]));
+    }
+}
@@ -12,6 +12,10 @@
 //! - **`parallel`** - Enable parallel processing with rayon
 //! - **`http`** - Enable HTTP client for activity fetching
 //! - **`ffi`** - Enable FFI bindings for mobile platforms (iOS/Android)
+//! - **`persist`** - Enable saving/loading of [`RouteIndex`], signatures and sections
+//! - **`mvt`** - Enable Mapbox Vector Tile encoding of heatmaps and sections
+//! - **`render`** - Enable PNG raster rendering of heatmaps
+//! - **`map_match`** - Enable snapping GPS tracks to an OpenStreetMap road graph
 //! - **`full`** - Enable all features
 //!
 //! ## Quick Start
@@ -43,44 +47,204 @@ use geo::{
     algorithm::simplify::Simplify,
 };
 use rstar::{RTree, RTreeObject, AABB};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use projection::{LocalProjection, PlanarPoint};
 
 // Geographic utilities (distance, bounds, center calculations)
 pub mod geo_utils;
 
+// Cooperative cancellation for long-running operations
+pub mod cancel;
+pub use cancel::CancellationToken;
+
+// Pipeline spans (structured with the `tracing` feature)
+mod telemetry;
+use telemetry::pipeline_span;
+
 // HTTP module for activity fetching
 #[cfg(feature = "http")]
 pub mod http;
 
 #[cfg(feature = "http")]
-pub use http::{ActivityFetcher, ActivityMapResult, MapBounds};
+pub use http::{
+    ActivityFetcher, ActivityListItem, ActivityMapResult, ActivityStreams, FetchErrorKind, FetcherConfig, MapBounds,
+    ProgressCallback, Provider, ResultCallback, StreamType, SyncChunk, fetch_activities_sync, fetch_activity_maps_sync,
+    fetch_activity_maps_sync_progressive, fetch_activity_maps_sync_resumable, fetch_activity_maps_sync_with_config,
+    fetch_activity_streams_sync, sync_in_chunks,
+};
 
 // Frequent sections detection (medoid-based algorithm for smooth polylines)
 pub mod sections;
-pub use sections::{FrequentSection, SectionConfig, SectionPortion, detect_frequent_sections, detect_sections_from_tracks};
+pub use sections::{
+    FrequentSection, SectionConfig, SectionPortion, DirectionStats, SectionPhase, SectionProgress,
+    detect_frequent_sections, detect_sections_from_tracks, detect_sections_from_tracks_with_progress,
+    detect_sections_from_tracks_cancellable, detect_sections_from_tracks_with_accuracy,
+    reconcile_section_ids, stable_section_id,
+};
+#[cfg(feature = "persist")]
+pub use sections::{load_sections, save_sections};
+
+// Hierarchical grouping (strict route groups clustered into loose variants)
+pub mod hierarchy;
+pub use hierarchy::{
+    GroupVariants, RouteHierarchy, RouteVariant, build_group_hierarchy, group_signatures_hierarchical, group_variants,
+};
+
+// Spatial index for matching new activities against an existing library
+pub mod route_index;
+pub use route_index::{RouteIndex, NearestRoute};
+
+// One-vs-many and many-vs-many comparison
+pub mod batch;
+pub use batch::{SimilarRoutes, compare_one_to_many, similarity_matrix, top_k_similar};
+pub use geo_utils::{DistanceModel, RouteProjection, Turn, project_point_onto_route};
+
+// Local planar projection for distance-heavy inner loops
+pub mod projection;
+
+// Shared AMD and overlap metrics
+pub mod metrics;
+use metrics::{amd_to_percentage, average_min_distance, average_min_distance_to_polyline, average_min_distance_with_model};
+
+// Optional track smoothing before simplification
+pub mod preprocess;
+pub use preprocess::{
+    CleaningConfig, CleaningReport, FlatPolylines, ResampleConfig, ResampleMode, TrackSmoothing, ValidationPolicy,
+    clean_track, merge_tracks, resample_track,
+};
+
+// Detection of virtual and indoor activities (Zwift, treadmill)
+pub mod indoor;
+pub use indoor::{IndoorConfig, IndoorKind, detect_indoor};
+
+// Explorer tiles (VeloViewer-style max square / cluster)
+pub mod exploration;
+pub use exploration::{ExplorationStats, exploration_from_signatures, exploration_from_tracks};
+
+// Eddington number, distance per group and section traversal statistics
+pub mod analytics;
+
+// Auto-naming of routes and sections from a reverse geocoder
+pub mod naming;
+
+// Frequent places (home, work, trailheads) from activity start/end points
+pub mod places;
+
+// Route network: junctions where routes cross or diverge, joined by sections
+pub mod network;
+
+// Export of routes for other tools and devices (FIT courses)
+pub mod export;
+pub use export::{
+    AnonymizeConfig, FitCourseConfig, PrivacyZone, SharedTrack, anonymize_polyline, anonymized_geojson,
+    anonymized_gpx, fit_course, fit_course_for_group,
+};
+
+// Live matching of an activity while it is recorded
+pub mod live;
+pub use live::{LiveConfig, LiveEvent, LiveEventKind, LiveMatcher, LiveTarget, LiveTargetKind};
+
+// Off-route detection against a planned route
+pub mod deviation;
+pub use deviation::{
+    CoverageReport, CoveragePortion, Deviation, DeviationConfig, DeviationReport, DeviationTracker, coverage,
+    coverage_within, detect_deviations,
+};
+
+// Per-traversal splits of an activity over known sections
+pub mod splits;
+pub use splits::{SectionTraversal, split_by_sections};
+
+// Duration, speed and rank of each activity's effort over a section
+pub mod efforts;
+pub use efforts::{EffortStreams, SectionEffort, adjusted_speed, compare_section_efforts};
+
+// Search for activities along a user-drawn polyline
+pub mod sketch;
+pub use sketch::{SketchMatch, find_routes_similar_to};
+
+// Memory and thread caps for batch processing
+pub mod limits;
+pub use limits::ResourceLimits;
+
+// Fixed-point coordinates for memory-constrained targets
+pub mod compact;
+pub use compact::{CompactHeatmapCell, CompactPoint, CompactSection, CompactSignature};
+
+// GPX/FIT file import for offline use
+pub mod import;
+pub use import::{ImportedActivity, import_directory};
+
+// Facade owning signatures, groups, sections and heatmaps
+pub mod library;
+pub use library::RouteLibrary;
 
 // Heatmap generation module
 pub mod heatmap;
 pub use heatmap::{
     HeatmapConfig, HeatmapBounds, HeatmapCell, HeatmapResult,
-    RouteRef, CellQueryResult, ActivityHeatmapData,
-    generate_heatmap, query_heatmap_cell,
+    RouteRef, CellQueryResult, RegionQueryResult, ActivityHeatmapData, HeatmapTimeRange, HeatmapIndex,
+    SportVisitCount, IntensityStreams,
+    generate_heatmap, generate_heatmap_from_tracks, generate_heatmap_cancellable, generate_heatmap_with_intensity,
+    generate_heatmap_from_tracks_cancellable, query_heatmap_cell, query_heatmap_region, filter_heatmap,
 };
 
+// Vector tile encoding for map overlays
+#[cfg(feature = "mvt")]
+pub mod mvt;
+
+// Map matching onto an OpenStreetMap road graph
+#[cfg(feature = "map_match")]
+pub mod map_match;
+
+// Node.js bindings
+#[cfg(feature = "node")]
+pub mod node;
+
+// Plain C bindings
+#[cfg(feature = "capi")]
+pub mod capi;
+
 #[cfg(feature = "ffi")]
 uniffi::setup_scaffolding!();
 
+/// Level set over FFI, as a `log::LevelFilter` discriminant (Debug by default)
+#[cfg(feature = "ffi")]
+static LOG_LEVEL: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(log::LevelFilter::Debug as usize);
+
+/// Quiet mode caps logging at warnings without forgetting `LOG_LEVEL`
+#[cfg(feature = "ffi")]
+static QUIET_LOGGING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Effective FFI log level, taking quiet mode into account.
+#[cfg(feature = "ffi")]
+fn log_level() -> log::LevelFilter {
+    use std::sync::atomic::Ordering;
+
+    let level = log::LevelFilter::iter()
+        .nth(LOG_LEVEL.load(Ordering::Relaxed))
+        .unwrap_or(log::LevelFilter::Debug);
+    if QUIET_LOGGING.load(Ordering::Relaxed) {
+        level.min(log::LevelFilter::Warn)
+    } else {
+        level
+    }
+}
+
 /// Initialize logging for Android (only used in FFI)
 #[cfg(all(feature = "ffi", target_os = "android"))]
 fn init_logging() {
     use android_logger::Config;
     use log::LevelFilter;
 
+    // The logger itself accepts everything; `log::max_level` does the filtering
+    // so `ffi_set_log_level` can raise or lower it later.
     android_logger::init_once(
         Config::default()
-            .with_max_level(LevelFilter::Debug)
+            .with_max_level(LevelFilter::Trace)
             .with_tag("RouteMatcherRust")
     );
+    log::set_max_level(log_level());
 }
 
 #[cfg(all(feature = "ffi", not(target_os = "android")))]
@@ -101,6 +265,7 @@ fn init_logging() {
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
 pub struct GpsPoint {
     pub latitude: f64,
     pub longitude: f64,
@@ -126,6 +291,7 @@ impl GpsPoint {
 /// Bounding box for a route.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bounds {
     pub min_lat: f64,
     pub max_lat: f64,
@@ -134,41 +300,62 @@ pub struct Bounds {
 }
 
 impl Bounds {
-    /// Create bounds from GPS points.
+    /// Create bounds from GPS points, ignoring invalid ones (NaN or out of
+    /// range). Returns `None` if no valid point remains.
+    ///
+    /// Tracks crossing the antimeridian produce bounds with `min_lng > max_lng`.
     pub fn from_points(points: &[GpsPoint]) -> Option<Self> {
-        if points.is_empty() {
-            return None;
-        }
-        let mut min_lat = f64::MAX;
-        let mut max_lat = f64::MIN;
-        let mut min_lng = f64::MAX;
-        let mut max_lng = f64::MIN;
-
-        for p in points {
-            min_lat = min_lat.min(p.latitude);
-            max_lat = max_lat.max(p.latitude);
-            min_lng = min_lng.min(p.longitude);
-            max_lng = max_lng.max(p.longitude);
-        }
+        geo_utils::compute_bounds(points)
+    }
 
-        Some(Self { min_lat, max_lat, min_lng, max_lng })
+    /// Whether all coordinates are finite and in range, as bounds from
+    /// [`Bounds::from_points`] always are. Bounds of hand-built or
+    /// deserialized signatures may not be.
+    pub fn is_valid(&self) -> bool {
+        GpsPoint::new(self.min_lat, self.min_lng).is_valid()
+            && GpsPoint::new(self.max_lat, self.max_lng).is_valid()
+            && self.min_lat <= self.max_lat
     }
 
     /// Get the center point of the bounds.
     pub fn center(&self) -> GpsPoint {
-        GpsPoint::new(
-            (self.min_lat + self.max_lat) / 2.0,
-            (self.min_lng + self.max_lng) / 2.0,
-        )
+        let center_lng = if self.crosses_antimeridian() {
+            geo_utils::normalize_longitude((self.min_lng + self.max_lng + 360.0) / 2.0)
+        } else {
+            (self.min_lng + self.max_lng) / 2.0
+        };
+        GpsPoint::new((self.min_lat + self.max_lat) / 2.0, center_lng)
+    }
+
+    /// Whether the bounds cross ±180° longitude (stored as `min_lng > max_lng`).
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lng > self.max_lng
+    }
+
+    /// Split bounds crossing the antimeridian into an eastern and a western box.
+    ///
+    /// Bounds that don't cross are returned unchanged as a single box.
+    pub fn split_at_antimeridian(&self) -> Vec<Bounds> {
+        if !self.crosses_antimeridian() {
+            return vec![*self];
+        }
+        vec![
+            Bounds { min_lng: self.min_lng, max_lng: 180.0, ..*self },
+            Bounds { min_lng: -180.0, max_lng: self.max_lng, ..*self },
+        ]
     }
 }
 
+/// Number of points simplified at a time by [`RouteSignature::from_point_iter`].
+const STREAM_CHUNK_POINTS: usize = 1024;
+
 /// A simplified route signature for efficient matching.
 ///
 /// The signature contains a simplified version of the original GPS track,
 /// optimized for comparison using the Fréchet distance algorithm.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
 pub struct RouteSignature {
     /// Unique identifier for the activity/route
     pub activity_id: String,
@@ -184,15 +371,28 @@ pub struct RouteSignature {
     pub bounds: Bounds,
     /// Pre-computed center point (for map rendering without JS calculation)
     pub center: GpsPoint,
+    /// Indices `i` of legs `points[i]` → `points[i + 1]` that bridge a
+    /// recording gap such as a tunnel (see [`MatchConfig::max_gap_meters`]).
+    /// These legs are left out of AMD.
+    #[cfg_attr(feature = "ffi", uniffi(default = []))]
+    #[cfg_attr(feature = "persist", serde(default))]
+    pub gap_legs: Vec<u32>,
 }
 
 impl RouteSignature {
     /// Create a route signature from raw GPS points.
     ///
-    /// The points are simplified using the Douglas-Peucker algorithm and
-    /// optionally limited to a maximum number of points.
+    /// The points are optionally smoothed (see [`MatchConfig::smoothing`]),
+    /// simplified using the Douglas-Peucker algorithm and optionally limited
+    /// to a maximum number of points.
     ///
-    /// Returns `None` if the input has fewer than 2 valid points.
+    /// Returns `None` if the input has fewer than 2 valid points, or if they
+    /// are all identical (a zero-length route). Points at (0, 0) count as
+    /// invalid (see [`preprocess::is_null_island`]).
+    ///
+    /// With [`MatchConfig::max_gap_meters`], jumps between consecutive points
+    /// longer than that keep both ends and are recorded in
+    /// [`RouteSignature::gap_legs`].
     ///
     /// # Example
     /// ```
@@ -212,30 +412,308 @@ impl RouteSignature {
             return None;
         }
 
-        // Filter invalid points and convert to geo coordinates
-        let coords: Vec<Coord> = points
-            .iter()
-            .filter(|p| p.is_valid())
-            .map(|p| Coord { x: p.longitude, y: p.latitude })
-            .collect();
+        // Gap ends must survive simplification, which the pinned path guarantees
+        if config.max_gap_meters.is_some() {
+            let valid: Vec<GpsPoint> = points.iter().copied().filter(is_track_point).collect();
+            if !gap_starts(&valid, config).is_empty() {
+                return Self::from_points_with_landmarks(activity_id, points, &[], config);
+            }
+        }
+
+        // Filter invalid points, optionally smooth, and convert to geo coordinates
+        let valid = points.iter().copied().filter(is_track_point);
+        let coords: Vec<Coord> = match &config.smoothing {
+            Some(smoothing) => preprocess::smooth_iter(valid, smoothing)
+                .map(|p| Coord { x: p.longitude, y: p.latitude })
+                .collect(),
+            None => valid.map(|p| Coord { x: p.longitude, y: p.latitude }).collect(),
+        };
 
         if coords.len() < 2 {
             return None;
         }
 
-        let line = LineString::new(coords);
+        // Douglas-Peucker simplification, around the track's mean latitude
+        let reference_latitude = coords.iter().map(|c| c.y).sum::<f64>() / coords.len() as f64;
+        let simplifier = Simplifier::new(config, reference_latitude);
+        let line: LineString<f64> = coords.into_iter().map(|c| simplifier.scale(c)).collect();
+        let simplified = simplifier.unscale(line.simplify(&simplifier.tolerance).0);
+
+        Self::from_simplified_coords(activity_id, simplified, &[], &[], config)
+    }
+
+    /// Create a single route signature from an activity recorded across
+    /// multiple files, in recording order (see [`merge_tracks`]).
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, RouteSignature, MatchConfig};
+    ///
+    /// let parts = vec![
+    ///     vec![GpsPoint::new(51.5000, -0.1), GpsPoint::new(51.5010, -0.1)],
+    ///     vec![GpsPoint::new(51.5020, -0.1), GpsPoint::new(51.5030, -0.1)],
+    /// ];
+    ///
+    /// let signature = RouteSignature::from_parts("crashed-ride", parts, &MatchConfig::default()).unwrap();
+    /// assert!(signature.total_distance > 300.0);
+    /// ```
+    pub fn from_parts(activity_id: &str, parts: Vec<Vec<GpsPoint>>, config: &MatchConfig) -> Option<Self> {
+        Self::from_points(activity_id, &merge_tracks(parts), config)
+    }
+
+    /// Create a route signature from a track with timestamps (Unix seconds,
+    /// one per point), leaving out stop points past
+    /// `stops.max_stationary_seconds` (see [`preprocess::strip_stationary`]).
+    ///
+    /// GPS jitter during a long stop survives simplification as spikes that
+    /// inflate AMD against other recordings of the same route.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, MatchConfig, ResampleConfig, RouteSignature};
+    ///
+    /// let mut points: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.1)).collect();
+    /// let mut timestamps: Vec<i64> = (0..10).map(|i| i * 20).collect();
+    /// // Ten minutes jittering around the last point
+    /// for i in 1..=600 {
+    ///     points.push(GpsPoint::new(51.509 + (i % 3) as f64 * 0.00002, -0.1));
+    ///     timestamps.push(180 + i);
+    /// }
+    ///
+    /// let sig = RouteSignature::from_timed_points("ride", &points, &timestamps, &MatchConfig::default(), &ResampleConfig::default());
+    /// assert!(sig.unwrap().total_distance < 1050.0);
+    /// ```
+    pub fn from_timed_points(
+        activity_id: &str,
+        points: &[GpsPoint],
+        timestamps: &[i64],
+        config: &MatchConfig,
+        stops: &ResampleConfig,
+    ) -> Option<Self> {
+        Self::from_points(activity_id, &preprocess::strip_stationary(points, timestamps, stops), config)
+    }
+
+    /// Create a route signature after cleaning the points with
+    /// [`preprocess::clean_track`], returning its report alongside.
+    ///
+    /// Errors where [`clean_track`] does ([`ValidationPolicy::Strict`]), or
+    /// where [`RouteSignature::from_points`] would return `None`.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{CleaningConfig, GpsPoint, MatchConfig, RouteSignature};
+    ///
+    /// let points = vec![
+    ///     GpsPoint::new(0.0, 0.0), // before GPS lock
+    ///     GpsPoint::new(51.5074, -0.1278),
+    ///     GpsPoint::new(51.5090, -0.1300),
+    /// ];
+    ///
+    /// let (signature, report) =
+    ///     RouteSignature::from_points_cleaned("ride", &points, &MatchConfig::default(), &CleaningConfig::default()).unwrap();
+    /// assert_eq!(report.null_island_runs, 1);
+    /// assert_eq!(signature.activity_id, "ride");
+    /// ```
+    pub fn from_points_cleaned(
+        activity_id: &str,
+        points: &[GpsPoint],
+        config: &MatchConfig,
+        cleaning: &CleaningConfig,
+    ) -> Result<(Self, CleaningReport), String> {
+        let (cleaned, report) = clean_track(points, cleaning)?;
+        let signature = Self::from_points(activity_id, &cleaned, config).ok_or_else(|| {
+            format!("Activity {} has fewer than 2 distinct valid points ({} kept)", activity_id, report.kept_points)
+        })?;
+        Ok((signature, report))
+    }
+
+    /// Create a route signature from a stream of GPS points with bounded memory.
+    ///
+    /// Points are simplified in chunks as they arrive, so multi-hour activities
+    /// with 100k+ points never need to be materialized as a single vector.
+    /// The result closely matches [`RouteSignature::from_points`]; small
+    /// differences can occur at chunk boundaries. Recording gaps are not
+    /// detected.
+    ///
+    /// Returns `None` if the stream has fewer than 2 valid points.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, RouteSignature, MatchConfig};
+    ///
+    /// // Points generated lazily, e.g. while parsing a large file
+    /// let points = (0..100_000).map(|i| GpsPoint::new(51.5 + i as f64 * 0.00001, -0.1278));
+    ///
+    /// let signature = RouteSignature::from_point_iter("long-ride", points, &MatchConfig::default());
+    /// assert!(signature.is_some());
+    /// ```
+    pub fn from_point_iter<I>(activity_id: &str, points: I, config: &MatchConfig) -> Option<Self>
+    where
+        I: IntoIterator<Item = GpsPoint>,
+    {
+        let mut simplifier: Option<Simplifier> = None;
+        let mut kept: Vec<Coord> = Vec::new();
+        let mut chunk: Vec<Coord> = Vec::with_capacity(STREAM_CHUNK_POINTS);
+
+        let valid = points.into_iter().filter(is_track_point);
+        let stream: Box<dyn Iterator<Item = GpsPoint> + '_> = match &config.smoothing {
+            Some(smoothing) => preprocess::smooth_iter(valid, smoothing),
+            None => Box::new(valid),
+        };
+
+        for p in stream {
+            // The first point's latitude is the reference for meter tolerances
+            let simplifier = simplifier.get_or_insert_with(|| Simplifier::new(config, p.latitude));
+            let tolerance = simplifier.tolerance;
+            chunk.push(simplifier.scale(Coord { x: p.longitude, y: p.latitude }));
+
+            if chunk.len() >= STREAM_CHUNK_POINTS {
+                // Simplify the chunk, carrying its last point over as the next anchor
+                let anchor = chunk[chunk.len() - 1];
+                let simplified = LineString::new(std::mem::take(&mut chunk)).simplify(&tolerance);
+                kept.extend_from_slice(&simplified.0[..simplified.0.len() - 1]);
+                chunk.push(anchor);
+
+                // Keep the retained points bounded as well
+                if kept.len() > STREAM_CHUNK_POINTS {
+                    kept = LineString::new(kept).simplify(&tolerance).0;
+                    if kept.len() > STREAM_CHUNK_POINTS {
+                        let step = kept.len() as f64 / (STREAM_CHUNK_POINTS / 2) as f64;
+                        kept = (0..STREAM_CHUNK_POINTS / 2)
+                            .map(|i| kept[(i as f64 * step) as usize])
+                            .collect();
+                    }
+                }
+            }
+        }
+
+        kept.extend(chunk);
+        let simplifier = simplifier?;
+        if kept.len() < 2 {
+            return None;
+        }
+
+        let simplified = LineString::new(kept).simplify(&simplifier.tolerance);
+        Self::from_simplified_coords(activity_id, simplifier.unscale(simplified.0), &[], &[], config)
+    }
+
+    /// Create a route signature that keeps the points at `landmarks` (indices
+    /// into `points`, e.g. laps or manual waypoints), so markers placed on the
+    /// original track still lie on the simplified polyline.
+    ///
+    /// The track is simplified piece by piece between landmarks, and landmarks
+    /// are kept when limiting to [`MatchConfig::max_simplified_points`] (even
+    /// if there are more landmarks than that). Landmark points are kept
+    /// unsmoothed. Indices of invalid points or past the end are ignored.
+    /// Recording gaps are pinned and recorded as in [`RouteSignature::from_points`].
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, RouteSignature, MatchConfig};
+    ///
+    /// // A straight line simplifies to its two ends...
+    /// let points: Vec<GpsPoint> = (0..100).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect();
+    /// let config = MatchConfig::default();
+    /// assert_eq!(RouteSignature::from_points("r", &points, &config).unwrap().points.len(), 2);
+    ///
+    /// // ...unless a lap marker pins the middle point
+    /// let signature = RouteSignature::from_points_with_landmarks("r", &points, &[50], &config).unwrap();
+    /// assert!(signature.points.contains(&points[50]));
+    /// ```
+    pub fn from_points_with_landmarks(
+        activity_id: &str,
+        points: &[GpsPoint],
+        landmarks: &[usize],
+        config: &MatchConfig,
+    ) -> Option<Self> {
+        // Valid points with their index into `points`
+        let (indices, valid): (Vec<usize>, Vec<GpsPoint>) =
+            points.iter().copied().enumerate().filter(|(_, p)| is_track_point(p)).unzip();
+        if valid.len() < 2 {
+            return None;
+        }
+
+        // Positions of landmarks among the valid points, plus both ends
+        let mut landmarks = landmarks.to_vec();
+        landmarks.sort_unstable();
+        let mut pins: Vec<usize> = indices
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| landmarks.binary_search(i).is_ok())
+            .map(|(pos, _)| pos)
+            .collect();
+        pins.push(0);
+        pins.push(valid.len() - 1);
+        let gaps = gap_starts(&valid, config);
+        pins.extend(gaps.iter().flat_map(|&g| [g, g + 1]));
+        pins.sort_unstable();
+        pins.dedup();
+
+        let mut track: Vec<GpsPoint> = match &config.smoothing {
+            Some(smoothing) => preprocess::smooth_iter(valid.iter().copied(), smoothing).collect(),
+            None => valid.clone(),
+        };
+        for &pos in &pins {
+            track[pos] = valid[pos];
+        }
+
+        // Douglas-Peucker between consecutive pins, so every pin is kept
+        let reference_latitude = track.iter().map(|p| p.latitude).sum::<f64>() / track.len() as f64;
+        let simplifier = Simplifier::new(config, reference_latitude);
+        let mut simplified: Vec<Coord> = vec![simplifier.scale(Coord { x: track[0].longitude, y: track[0].latitude })];
+        let mut kept_pins = vec![0];
+        for piece in pins.windows(2) {
+            let line: LineString<f64> = track[piece[0]..=piece[1]]
+                .iter()
+                .map(|p| simplifier.scale(Coord { x: p.longitude, y: p.latitude }))
+                .collect();
+            simplified.extend_from_slice(&line.simplify(&simplifier.tolerance).0[1..]);
+            kept_pins.push(simplified.len() - 1);
+        }
+
+        // Gap legs run between consecutive pins, so they start at a kept pin
+        let kept_gaps: Vec<usize> = gaps
+            .iter()
+            .filter_map(|g| pins.binary_search(g).ok().map(|k| kept_pins[k]))
+            .collect();
+
+        Self::from_simplified_coords(activity_id, simplifier.unscale(simplified), &kept_pins, &kept_gaps, config)
+    }
 
-        // Douglas-Peucker simplification
-        let simplified = line.simplify(&config.simplification_tolerance);
+    /// Finish signature creation from already-simplified coordinates, keeping
+    /// the coordinates at the sorted positions in `pins`. `gaps` are positions
+    /// of pinned coordinates whose leg to the next (pinned) one is a gap.
+    fn from_simplified_coords(
+        activity_id: &str,
+        simplified: Vec<Coord>,
+        pins: &[usize],
+        gaps: &[usize],
+        config: &MatchConfig,
+    ) -> Option<Self> {
+        let max_points = config.max_simplified_points as usize;
 
         // Limit to max points if needed (uniform sampling)
-        let final_coords: Vec<Coord> = if simplified.0.len() > config.max_simplified_points as usize {
-            let step = simplified.0.len() as f64 / config.max_simplified_points as f64;
-            (0..config.max_simplified_points)
-                .map(|i| simplified.0[(i as f64 * step) as usize])
-                .collect()
+        let (final_coords, gap_legs): (Vec<Coord>, Vec<u32>) = if simplified.len() <= max_points {
+            (simplified, gaps.iter().map(|&g| g as u32).collect())
+        } else if pins.is_empty() {
+            let step = simplified.len() as f64 / config.max_simplified_points as f64;
+            let sampled = (0..config.max_simplified_points)
+                .map(|i| simplified[(i as f64 * step) as usize])
+                .collect();
+            (sampled, Vec::new())
         } else {
-            simplified.0.clone()
+            // Sample the unpinned coordinates into whatever budget pins leave
+            let free: Vec<usize> = (0..simplified.len()).filter(|i| pins.binary_search(i).is_err()).collect();
+            let budget = max_points.saturating_sub(pins.len());
+            let step = free.len() as f64 / budget.max(1) as f64;
+            let mut keep: Vec<usize> = pins.to_vec();
+            keep.extend((0..budget).map(|i| free[(i as f64 * step) as usize]));
+            keep.sort_unstable();
+            let gap_legs = gaps
+                .iter()
+                .filter_map(|g| keep.binary_search(g).ok().map(|i| i as u32))
+                .collect();
+            (keep.into_iter().map(|i| simplified[i]).collect(), gap_legs)
         };
 
         if final_coords.len() < 2 {
@@ -247,7 +725,11 @@ impl RouteSignature {
             .map(|c| GpsPoint::new(c.y, c.x))
             .collect();
 
-        let total_distance = calculate_route_distance(&simplified_points);
+        // All points identical: no route to match against
+        let total_distance = config.distance_model().polyline_length(&simplified_points);
+        if !is_positive_distance(total_distance) {
+            return None;
+        }
 
         // Pre-compute bounds and center for 120Hz map rendering
         let bounds = Bounds::from_points(&simplified_points)?;
@@ -261,9 +743,88 @@ impl RouteSignature {
             total_distance,
             bounds,
             center,
+            gap_legs,
         })
     }
 
+    /// Coarser polyline with at most `max_points` points (minimum 2), for list
+    /// thumbnails.
+    ///
+    /// Douglas-Peucker is re-run with a doubling tolerance until the point
+    /// budget is met, so the thumbnail keeps the route's most prominent corners
+    /// rather than uniformly sampled points.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, RouteSignature, MatchConfig};
+    ///
+    /// let zigzag: Vec<GpsPoint> = (0..100)
+    ///     .map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1 + (i % 2) as f64 * 0.001))
+    ///     .collect();
+    /// let signature = RouteSignature::from_points("zigzag", &zigzag, &MatchConfig::default()).unwrap();
+    ///
+    /// let thumbnail = signature.thumbnail_points(16);
+    /// assert!(thumbnail.len() <= 16);
+    /// assert_eq!(thumbnail[0], signature.start_point);
+    /// ```
+    pub fn thumbnail_points(&self, max_points: u32) -> Vec<GpsPoint> {
+        let max_points = max_points.max(2) as usize;
+        if self.points.len() <= max_points {
+            return self.points.clone();
+        }
+
+        let line: LineString<f64> = self.points.iter().map(|p| Coord { x: p.longitude, y: p.latitude }).collect();
+        let extent = (self.bounds.max_lat - self.bounds.min_lat)
+            .max(geo_utils::longitude_delta(self.bounds.min_lng, self.bounds.max_lng).abs());
+        let mut tolerance = (extent / 1000.0).max(f64::EPSILON);
+
+        loop {
+            let simplified = line.simplify(&tolerance);
+            if simplified.0.len() <= max_points {
+                return simplified.0.iter().map(|c| GpsPoint::new(c.y, c.x)).collect();
+            }
+            tolerance *= 2.0;
+        }
+    }
+
+    /// Polyline simplified to about one screen pixel at a web map `zoom` level
+    /// (256px tiles), so detail invisible at that zoom is dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, RouteSignature, MatchConfig};
+    ///
+    /// let points: Vec<GpsPoint> = (0..50)
+    ///     .map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1 + (i % 2) as f64 * 0.0005))
+    ///     .collect();
+    /// let signature = RouteSignature::from_points("r", &points, &MatchConfig::default()).unwrap();
+    ///
+    /// let city = signature.simplified_for_zoom(16);
+    /// let country = signature.simplified_for_zoom(6);
+    /// assert!(country.len() < city.len());
+    /// ```
+    pub fn simplified_for_zoom(&self, zoom: u8) -> Vec<GpsPoint> {
+        let pixel_degrees = 360.0 / (256.0 * 2f64.powi(zoom.min(30) as i32));
+        let line: LineString<f64> = self.points.iter().map(|p| Coord { x: p.longitude, y: p.latitude }).collect();
+        line.simplify(&pixel_degrees).0.iter().map(|c| GpsPoint::new(c.y, c.x)).collect()
+    }
+
+    /// Points as a flat `[lat, lng, lat, lng, ...]` array, for building map
+    /// geometry without iterating point records.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::{GpsPoint, RouteSignature, MatchConfig};
+    ///
+    /// let points = vec![GpsPoint::new(51.5, -0.12), GpsPoint::new(51.51, -0.13)];
+    /// let signature = RouteSignature::from_points("r", &points, &MatchConfig::default()).unwrap();
+    ///
+    /// assert_eq!(signature.flat_coords(), vec![51.5, -0.12, 51.51, -0.13]);
+    /// ```
+    pub fn flat_coords(&self) -> Vec<f64> {
+        self.points.iter().flat_map(|p| [p.latitude, p.longitude]).collect()
+    }
+
     /// Get the bounding box of this route as RouteBounds (for R-tree indexing).
     pub fn route_bounds(&self) -> RouteBounds {
         RouteBounds {
@@ -294,6 +855,25 @@ pub struct MatchResult {
 }
 
 /// Configuration for route matching algorithms.
+///
+/// # Pre-filters
+///
+/// Before any AMD is computed, candidate pairs pass three cheap checks, from
+/// coarsest to finest:
+///
+/// 1. [`search_tolerance_degrees`](Self::search_tolerance_degrees): grouping,
+///    [`RouteIndex`] and batch comparison only consider routes whose bounding
+///    boxes come within this many degrees of each other
+/// 2. [`min_distance_ratio`](Self::min_distance_ratio): the shorter route must
+///    be at least this fraction of the longer one to be compared at all
+/// 3. [`max_distance_diff_ratio`](Self::max_distance_diff_ratio): after
+///    comparison, grouping additionally requires lengths this close
+///
+/// Only (2) limits [`compare_routes`]; (3) only limits grouping. To compare a
+/// 300 km ride with its 160 km variant, lower `min_distance_ratio` below
+/// 0.53; they still won't be grouped unless `max_distance_diff_ratio` is
+/// raised too. Direction is decided separately, from the endpoints, with a
+/// margin of [`min_direction_diff_meters`](Self::min_direction_diff_meters).
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
 pub struct MatchConfig {
@@ -322,16 +902,149 @@ pub struct MatchConfig {
     pub endpoint_threshold: f64,
 
     /// Number of points to resample routes to for comparison.
+    /// Larger values (200+) are practical: AMD indexes big routes spatially.
     /// Default: 50
     pub resample_count: u32,
 
-    /// Tolerance for Douglas-Peucker simplification (in degrees).
+    /// Tolerance for Douglas-Peucker simplification, in the unit given by
+    /// `simplification_unit` (degrees unless set).
     /// Smaller values preserve more detail. Default: 0.0001 (~11 meters)
     pub simplification_tolerance: f64,
 
     /// Maximum points after simplification.
     /// Fewer points = faster comparison. Default: 100
     pub max_simplified_points: u32,
+
+    /// Minimum fraction of shared geometry for two route groups to be
+    /// clustered together as variants in hierarchical grouping (level 1).
+    /// Default: 0.6 (60%)
+    #[cfg_attr(feature = "ffi", uniffi(default = 0.6))]
+    pub loose_group_min_overlap: f64,
+
+    /// Minimum fraction of shared geometry and length for two activities of
+    /// a route group to take the same variant (see [`group_variants`]).
+    /// Default: 0.95 (95%)
+    #[cfg_attr(feature = "ffi", uniffi(default = 0.95))]
+    pub variant_min_overlap: f64,
+
+    /// Smoothing applied to raw GPS points before simplification.
+    /// Helps with jittery watch data recorded in cities or under tree cover.
+    /// Default: None (raw points are used)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub smoothing: Option<TrackSmoothing>,
+
+    /// Unit of `simplification_tolerance`.
+    /// Degrees distort with latitude (a degree of longitude is ~111km at the
+    /// equator but ~56km at 60°N); meters simplify the same everywhere.
+    /// Default: None (degrees, for backward compatibility)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub simplification_unit: Option<ToleranceUnit>,
+
+    /// Measure AMD from each point to the nearest line of the other route
+    /// instead of its nearest vertex. Straight roads sampled at different
+    /// rates otherwise score a non-zero AMD purely from vertex spacing.
+    /// Default: false
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub amd_to_polyline: bool,
+
+    /// Formula for route distances, endpoint checks and AMD. AMD finds nearest
+    /// points on a local planar projection (see [`projection`]), which matches
+    /// haversine and equirectangular at route scale; with geodesic the
+    /// distances to those points are measured on the ellipsoid.
+    /// Default: None (haversine)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub distance_model: Option<DistanceModel>,
+
+    /// Jumps between consecutive raw points longer than this (meters) are
+    /// treated as recording gaps (tunnels, GPS dropouts): the straight chord
+    /// across them is left out of AMD. Around 300m suits 1s recording.
+    /// Default: None (no gap detection)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub max_gap_meters: Option<f64>,
+
+    /// Let activities of different sports share a route group in
+    /// [`group_signatures_by_sport`] and [`RouteLibrary`]. Off by default so a
+    /// run and a ride along the same path stay separate routes.
+    /// Default: false
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub cross_sport_grouping: bool,
+
+    /// Keep virtual and indoor activities (see [`indoor`]) out of groups in
+    /// [`group_signatures_by_sport`] and [`RouteLibrary`]: each stays in a
+    /// group of its own.
+    /// Default: false
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub exclude_indoor: bool,
+
+    /// Pairs whose shorter route is less than this fraction of the longer
+    /// one are never compared.
+    /// Default: 0.5
+    #[cfg_attr(feature = "ffi", uniffi(default = 0.5))]
+    pub min_distance_ratio: f64,
+
+    /// Candidate routes must have bounding boxes within this many degrees of
+    /// latitude of each other (0.01° is about 1.1 km). The east-west reach is
+    /// the same distance in meters at every latitude.
+    /// Default: 0.01
+    #[cfg_attr(feature = "ffi", uniffi(default = 0.01))]
+    pub search_tolerance_degrees: f64,
+
+    /// A pair is called "reverse" only if its endpoints fit the reversed
+    /// route by at least this many meters better than the same direction.
+    /// Default: 100.0 meters
+    #[cfg_attr(feature = "ffi", uniffi(default = 100.0))]
+    pub min_direction_diff_meters: f64,
+}
+
+impl MatchConfig {
+    /// The configured distance model, haversine if unset.
+    pub fn distance_model(&self) -> DistanceModel {
+        self.distance_model.unwrap_or_default()
+    }
+}
+
+/// Unit of [`MatchConfig::simplification_tolerance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum ToleranceUnit {
+    /// Degrees of latitude/longitude, applied directly to raw coordinates
+    Degrees,
+    /// Meters, converted using the track's latitude so the tolerance is the
+    /// same east-west and north-south
+    Meters,
+}
+
+/// Douglas-Peucker parameters in a (scaled longitude, latitude) plane where
+/// the tolerance applies equally along both axes.
+struct Simplifier {
+    tolerance: f64,
+    lng_scale: f64,
+}
+
+impl Simplifier {
+    fn new(config: &MatchConfig, reference_latitude: f64) -> Self {
+        match config.simplification_unit {
+            Some(ToleranceUnit::Meters) => Self {
+                tolerance: config.simplification_tolerance / 111_320.0,
+                lng_scale: reference_latitude.to_radians().cos().max(0.01),
+            },
+            None | Some(ToleranceUnit::Degrees) => Self {
+                tolerance: config.simplification_tolerance,
+                lng_scale: 1.0,
+            },
+        }
+    }
+
+    fn scale(&self, c: Coord) -> Coord {
+        Coord { x: c.x * self.lng_scale, y: c.y }
+    }
+
+    fn unscale(&self, coords: Vec<Coord>) -> Vec<Coord> {
+        if self.lng_scale == 1.0 {
+            return coords;
+        }
+        coords.into_iter().map(|c| Coord { x: c.x / self.lng_scale, y: c.y }).collect()
+    }
 }
 
 impl Default for MatchConfig {
@@ -346,6 +1059,18 @@ impl Default for MatchConfig {
             resample_count: 50,
             simplification_tolerance: 0.0001,
             max_simplified_points: 100,
+            loose_group_min_overlap: 0.6,
+            variant_min_overlap: 0.95,
+            smoothing: None,
+            simplification_unit: None,
+            amd_to_polyline: false,
+            distance_model: None,
+            max_gap_meters: None,
+            cross_sport_grouping: false,
+            exclude_indoor: false,
+            min_distance_ratio: 0.5,
+            search_tolerance_degrees: 0.01,
+            min_direction_diff_meters: 100.0,
         }
     }
 }
@@ -361,7 +1086,8 @@ pub struct RouteGroup {
 }
 
 /// Bounding box for a route (used for spatial indexing).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
 pub struct RouteBounds {
     pub activity_id: String,
     pub min_lat: f64,
@@ -375,13 +1101,44 @@ impl RTreeObject for RouteBounds {
     type Envelope = AABB<[f64; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
+        // Routes crossing the antimeridian cover the full longitude range in the
+        // (non-wrapping) R-tree; the exact comparison afterwards filters them
+        let (min_lng, max_lng) = if self.min_lng > self.max_lng {
+            (-180.0, 180.0)
+        } else {
+            (self.min_lng, self.max_lng)
+        };
         AABB::from_corners(
-            [self.min_lng, self.min_lat],
-            [self.max_lng, self.max_lat],
+            [min_lng, self.min_lat],
+            [max_lng, self.max_lat],
         )
     }
 }
 
+/// R-tree search box around route bounds, expanded by `tolerance` degrees of
+/// latitude.
+///
+/// The longitude buffer covers the same ground distance at the bounds'
+/// poleward edge, so the search reaches as far east-west as north-south at
+/// any latitude. Boxes crossing (or within the buffer of) the antimeridian
+/// search the full longitude range, since the R-tree does not wrap.
+fn search_envelope(bounds: &Bounds, tolerance: f64) -> AABB<[f64; 2]> {
+    let poleward_lat = bounds.min_lat.abs().max(bounds.max_lat.abs());
+    let lng_tolerance = geo_utils::meters_to_degrees(tolerance * 111_320.0, poleward_lat);
+    let (min_lng, max_lng) = if bounds.crosses_antimeridian()
+        || bounds.min_lng - lng_tolerance < -180.0
+        || bounds.max_lng + lng_tolerance > 180.0
+    {
+        (-180.0, 180.0)
+    } else {
+        (bounds.min_lng - lng_tolerance, bounds.max_lng + lng_tolerance)
+    };
+    AABB::from_corners(
+        [min_lng, bounds.min_lat - tolerance],
+        [max_lng, bounds.max_lat + tolerance],
+    )
+}
+
 // ============================================================================
 // Core Functions
 // ============================================================================
@@ -392,7 +1149,9 @@ impl RTreeObject for RouteBounds {
 /// For each point in route1, we find the minimum distance to any point in route2,
 /// then average all those distances.
 ///
-/// Returns `None` if the routes don't meet the minimum match threshold.
+/// Returns `None` if the routes don't meet the minimum match threshold. A
+/// signature compared with itself (same activity ID) and zero-length routes
+/// also return `None`, so they never produce a match or a self-group.
 ///
 /// # Example
 /// ```
@@ -409,33 +1168,65 @@ impl RTreeObject for RouteBounds {
 ///
 /// let result = compare_routes(&sig1, &sig2, &MatchConfig::default());
 /// assert!(result.is_some());
+///
+/// // Never matches itself
+/// assert!(compare_routes(&sig1, &sig1, &MatchConfig::default()).is_none());
 /// ```
 pub fn compare_routes(
     sig1: &RouteSignature,
     sig2: &RouteSignature,
     config: &MatchConfig,
 ) -> Option<MatchResult> {
-    // Quick distance filter - routes must be within 50% of each other's length
-    let distance_ratio = if sig1.total_distance > sig2.total_distance {
-        sig2.total_distance / sig1.total_distance
-    } else {
-        sig1.total_distance / sig2.total_distance
-    };
+    if sig1.activity_id == sig2.activity_id {
+        return None;
+    }
+    if !is_positive_distance(sig1.total_distance) || !is_positive_distance(sig2.total_distance) {
+        return None;
+    }
 
-    if distance_ratio < 0.5 {
+    // Quick distance filter - routes must be of comparable length
+    if !distance_ratio_ok(sig1.total_distance, sig2.total_distance, config) {
         return None;
     }
 
     // Resample both routes to same number of points for fair comparison
-    let resampled1 = resample_route(&sig1.points, config.resample_count as usize);
-    let resampled2 = resample_route(&sig2.points, config.resample_count as usize);
+    let (resampled1, gaps1) = resample_signature(sig1, config.resample_count as usize);
+    let (resampled2, gaps2) = resample_signature(sig2, config.resample_count as usize);
+
+    // Project both into local meters around the pair's midpoint
+    let projection = LocalProjection::new(geo_utils::interpolate_point(&sig1.center, &sig2.center, 0.5));
 
     // Calculate AMD in both directions (AMD is asymmetric)
-    let amd_1_to_2 = average_min_distance(&resampled1, &resampled2);
-    let amd_2_to_1 = average_min_distance(&resampled2, &resampled1);
+    let (amd_1_to_2, amd_2_to_1) = match config.distance_model() {
+        // The projection is equirectangular on haversine's sphere, so planar
+        // distances already follow these models at route scale
+        DistanceModel::Equirectangular | DistanceModel::Haversine => {
+            let planar1 = projection.project_all(&resampled1);
+            let planar2 = projection.project_all(&resampled2);
+            let amd = |from: &[PlanarPoint], to: &[PlanarPoint], from_gaps: &[bool], to_gaps: &[bool]| {
+                if config.amd_to_polyline {
+                    average_min_distance_to_polyline(from, to, from_gaps, &line_gaps(to_gaps))
+                } else {
+                    average_min_distance(from, to, from_gaps, to_gaps)
+                }
+            };
+            (amd(&planar1, &planar2, &gaps1, &gaps2), amd(&planar2, &planar1, &gaps2, &gaps1))
+        }
+        // Nearest matches are still found on the plane, then measured on the ellipsoid
+        model @ DistanceModel::Geodesic => {
+            let amd = |from: &[GpsPoint], to: &[GpsPoint], from_gaps: &[bool], to_gaps: &[bool]| {
+                let to_gaps = if config.amd_to_polyline { line_gaps(to_gaps) } else { to_gaps.to_vec() };
+                average_min_distance_with_model(from, to, from_gaps, &to_gaps, config.amd_to_polyline, &projection, model)
+            };
+            (amd(&resampled1, &resampled2, &gaps1, &gaps2), amd(&resampled2, &resampled1, &gaps2, &gaps1))
+        }
+    };
 
     // Use average of both directions
     let avg_amd = (amd_1_to_2 + amd_2_to_1) / 2.0;
+    if !avg_amd.is_finite() {
+        return None;
+    }
 
     // Convert AMD to percentage using thresholds
     let match_percentage = amd_to_percentage(avg_amd, config.perfect_threshold, config.zero_threshold);
@@ -446,7 +1237,7 @@ pub fn compare_routes(
     }
 
     // Determine direction using endpoint comparison (AMD is symmetric)
-    let direction = determine_direction_by_endpoints(sig1, sig2, config.endpoint_threshold);
+    let direction = determine_direction_by_endpoints(sig1, sig2, config);
 
     // Direction type based on match quality
     let direction_str = if match_percentage >= 70.0 {
@@ -464,130 +1255,72 @@ pub fn compare_routes(
     })
 }
 
-/// Calculate Average Minimum Distance from route1 to route2.
-/// For each point in route1, find the minimum distance to any point in route2.
-/// Return the average of these minimum distances.
-fn average_min_distance(route1: &[GpsPoint], route2: &[GpsPoint]) -> f64 {
-    if route1.is_empty() || route2.is_empty() {
-        return f64::INFINITY;
+/// Resample a signature like [`geo_utils::resample_polyline`], flagging resampled points
+/// that lie inside one of its gap legs (empty if it has none).
+fn resample_signature(sig: &RouteSignature, target_count: usize) -> (Vec<GpsPoint>, Vec<bool>) {
+    let resampled = geo_utils::resample_polyline(&sig.points, target_count);
+    if sig.gap_legs.is_empty() {
+        return (resampled, Vec::new());
     }
 
-    let total_min_dist: f64 = route1
-        .iter()
-        .map(|p1| {
-            route2
-                .iter()
-                .map(|p2| haversine_distance(p1, p2))
-                .fold(f64::INFINITY, f64::min)
+    // Distance from the start at each vertex; resampled points are evenly spaced
+    let mut cumulative = vec![0.0];
+    for w in sig.points.windows(2) {
+        cumulative.push(cumulative[cumulative.len() - 1] + haversine_distance(&w[0], &w[1]));
+    }
+    let total = cumulative[cumulative.len() - 1];
+    if total == 0.0 || resampled.len() < 2 {
+        return (resampled, Vec::new());
+    }
+    let step = total / (target_count - 1) as f64;
+
+    let gaps = (0..resampled.len())
+        .map(|k| {
+            let along = if k == resampled.len() - 1 { total } else { k as f64 * step };
+            sig.gap_legs.iter().any(|&g| {
+                let g = g as usize;
+                g + 1 < cumulative.len() && cumulative[g] < along && along < cumulative[g + 1]
+            })
         })
-        .sum();
+        .collect();
+    (resampled, gaps)
+}
 
-    total_min_dist / route1.len() as f64
+/// Flags for the lines between consecutive points, from per-point gap flags.
+fn line_gaps(point_gaps: &[bool]) -> Vec<bool> {
+    point_gaps.windows(2).map(|w| w[0] || w[1]).collect()
 }
 
-/// Convert AMD to a match percentage using thresholds.
-/// - AMD <= perfect_threshold → 100% match
-/// - AMD >= zero_threshold → 0% match
-/// - Linear interpolation between
-fn amd_to_percentage(amd: f64, perfect_threshold: f64, zero_threshold: f64) -> f64 {
-    if amd <= perfect_threshold {
-        return 100.0;
-    }
-    if amd >= zero_threshold {
-        return 0.0;
-    }
+// Use shared haversine_distance from geo_utils
+use crate::geo_utils::haversine_distance;
 
-    // Linear interpolation
-    100.0 * (1.0 - (amd - perfect_threshold) / (zero_threshold - perfect_threshold))
-}
+/// Determine direction using endpoint comparison.
+/// Returns "same" if sig2 starts near sig1's start, "reverse" if near sig1's end.
+fn determine_direction_by_endpoints(sig1: &RouteSignature, sig2: &RouteSignature, config: &MatchConfig) -> String {
+    let loop_threshold = config.endpoint_threshold;
+    let model = config.distance_model();
+    let start1 = &sig1.start_point;
+    let end1 = &sig1.end_point;
+    let start2 = &sig2.start_point;
+    let end2 = &sig2.end_point;
+    let distance = |a: &GpsPoint, b: &GpsPoint| model.distance(a, b);
 
-/// Resample a route to have exactly n points, evenly spaced by distance.
-fn resample_route(points: &[GpsPoint], target_count: usize) -> Vec<GpsPoint> {
-    if points.len() < 2 {
-        return points.to_vec();
-    }
-    if points.len() == target_count {
-        return points.to_vec();
-    }
+    // Check if either route is a loop (start ≈ end)
+    let sig1_is_loop = distance(start1, end1) < loop_threshold;
+    let sig2_is_loop = distance(start2, end2) < loop_threshold;
 
-    // Calculate total distance
-    let total_dist = calculate_route_distance(points);
-    if total_dist == 0.0 {
-        return points[..target_count.min(points.len())].to_vec();
-    }
-
-    let step_dist = total_dist / (target_count - 1) as f64;
-    let mut resampled: Vec<GpsPoint> = vec![points[0]];
-
-    let mut accumulated = 0.0;
-    let mut next_threshold = step_dist;
-    let mut prev_point = &points[0];
-
-    for curr in points.iter().skip(1) {
-        let seg_dist = haversine_distance(prev_point, curr);
-
-        while accumulated + seg_dist >= next_threshold && resampled.len() < target_count - 1 {
-            // Interpolate point at the threshold distance
-            let ratio = (next_threshold - accumulated) / seg_dist;
-            let new_lat = prev_point.latitude + ratio * (curr.latitude - prev_point.latitude);
-            let new_lng = prev_point.longitude + ratio * (curr.longitude - prev_point.longitude);
-            resampled.push(GpsPoint::new(new_lat, new_lng));
-            next_threshold += step_dist;
-        }
-
-        accumulated += seg_dist;
-        prev_point = curr;
-    }
-
-    // Always include the last point
-    if resampled.len() < target_count {
-        resampled.push(*points.last().unwrap());
-    }
-
-    resampled
-}
-
-/// Calculate the total distance of a route in meters.
-fn calculate_route_distance(points: &[GpsPoint]) -> f64 {
-    points
-        .windows(2)
-        .map(|w| haversine_distance(&w[0], &w[1]))
-        .sum()
-}
-
-// Use shared haversine_distance from geo_utils
-use crate::geo_utils::haversine_distance;
-
-/// Determine direction using endpoint comparison.
-/// Returns "same" if sig2 starts near sig1's start, "reverse" if near sig1's end.
-fn determine_direction_by_endpoints(
-    sig1: &RouteSignature,
-    sig2: &RouteSignature,
-    loop_threshold: f64,
-) -> String {
-    let start1 = &sig1.start_point;
-    let end1 = &sig1.end_point;
-    let start2 = &sig2.start_point;
-    let end2 = &sig2.end_point;
-
-    // Check if either route is a loop (start ≈ end)
-    let sig1_is_loop = haversine_distance(start1, end1) < loop_threshold;
-    let sig2_is_loop = haversine_distance(start2, end2) < loop_threshold;
-
-    // If both are loops, direction is meaningless
-    if sig1_is_loop && sig2_is_loop {
-        return "same".to_string();
+    // If both are loops, direction is meaningless
+    if sig1_is_loop && sig2_is_loop {
+        return "same".to_string();
     }
 
     // Score for same direction: start2→start1 + end2→end1
-    let same_score = haversine_distance(start2, start1) + haversine_distance(end2, end1);
+    let same_score = distance(start2, start1) + distance(end2, end1);
     // Score for reverse direction: start2→end1 + end2→start1
-    let reverse_score = haversine_distance(start2, end1) + haversine_distance(end2, start1);
+    let reverse_score = distance(start2, end1) + distance(end2, start1);
 
-    // Require a significant difference (100m) to call it 'reverse'
-    let min_direction_diff = 100.0;
-
-    if reverse_score < same_score - min_direction_diff {
+    // Require a significant difference to call it 'reverse'
+    if reverse_score < same_score - config.min_direction_diff_meters {
         "reverse".to_string()
     } else {
         "same".to_string()
@@ -633,25 +1366,27 @@ fn should_group_routes(
     let end1 = &sig1.end_point;
     let start2 = &sig2.start_point;
     let end2 = &sig2.end_point;
+    let model = config.distance_model();
+    let distance = |a: &GpsPoint, b: &GpsPoint| model.distance(a, b);
 
     // Check if routes are loops
-    let sig1_is_loop = haversine_distance(start1, end1) < config.endpoint_threshold;
-    let sig2_is_loop = haversine_distance(start2, end2) < config.endpoint_threshold;
+    let sig1_is_loop = distance(start1, end1) < config.endpoint_threshold;
+    let sig2_is_loop = distance(start2, end2) < config.endpoint_threshold;
 
     // For loops, check that starts are close and both are actually loops
     if sig1_is_loop && sig2_is_loop {
-        let start_dist = haversine_distance(start1, start2);
+        let start_dist = distance(start1, start2);
         if start_dist > config.endpoint_threshold {
             return false;
         }
-        return check_middle_points_match(&sig1.points, &sig2.points, config.endpoint_threshold * 2.0);
+        return check_middle_points_match(&sig1.points, &sig2.points, config.endpoint_threshold * 2.0, model);
     }
 
     // Determine direction by checking which endpoint pairing is closer
-    let same_start_dist = haversine_distance(start1, start2);
-    let same_end_dist = haversine_distance(end1, end2);
-    let reverse_start_dist = haversine_distance(start1, end2);
-    let reverse_end_dist = haversine_distance(end1, start2);
+    let same_start_dist = distance(start1, start2);
+    let same_end_dist = distance(end1, end2);
+    let reverse_start_dist = distance(start1, end2);
+    let reverse_end_dist = distance(end1, start2);
 
     let same_direction_ok = same_start_dist < config.endpoint_threshold && same_end_dist < config.endpoint_threshold;
     let reverse_direction_ok = reverse_start_dist < config.endpoint_threshold && reverse_end_dist < config.endpoint_threshold;
@@ -667,11 +1402,11 @@ fn should_group_routes(
         sig2.points.clone()
     };
 
-    check_middle_points_match(&sig1.points, &points2_for_middle, config.endpoint_threshold * 2.0)
+    check_middle_points_match(&sig1.points, &points2_for_middle, config.endpoint_threshold * 2.0, model)
 }
 
 /// Check that the middle portions of two routes also match.
-fn check_middle_points_match(points1: &[GpsPoint], points2: &[GpsPoint], threshold: f64) -> bool {
+fn check_middle_points_match(points1: &[GpsPoint], points2: &[GpsPoint], threshold: f64, model: DistanceModel) -> bool {
     if points1.len() < 5 || points2.len() < 5 {
         return true; // Not enough points to check middle
     }
@@ -686,7 +1421,7 @@ fn check_middle_points_match(points1: &[GpsPoint], points2: &[GpsPoint], thresho
         let p1 = &points1[idx1];
         let p2 = &points2[idx2];
 
-        let dist = haversine_distance(p1, p2);
+        let dist = model.distance(p1, p2);
         if dist > threshold {
             return false;
         }
@@ -718,13 +1453,141 @@ fn check_middle_points_match(points1: &[GpsPoint], points2: &[GpsPoint], thresho
 /// assert_eq!(groups.len(), 1); // Both routes in same group
 /// ```
 pub fn group_signatures(signatures: &[RouteSignature], config: &MatchConfig) -> Vec<RouteGroup> {
+    group_in_span(signatures.len(), || group_signatures_sequential(signatures, config, &CancellationToken::new()))
+        .unwrap_or_default()
+}
+
+/// Group signatures, stopping early if `cancel` is cancelled.
+///
+/// Uses parallel processing when the `parallel` feature is enabled.
+/// Returns `None` if cancelled before grouping finished.
+///
+/// # Example
+/// ```
+/// use route_matcher::{GpsPoint, RouteSignature, MatchConfig, CancellationToken, group_signatures_cancellable};
+///
+/// let points: Vec<GpsPoint> = (0..10)
+///     .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+///     .collect();
+/// let sig = RouteSignature::from_points("a", &points, &MatchConfig::default()).unwrap();
+///
+/// let token = CancellationToken::new();
+/// assert!(group_signatures_cancellable(&[sig.clone()], &MatchConfig::default(), &token).is_some());
+///
+/// token.cancel();
+/// assert!(group_signatures_cancellable(&[sig], &MatchConfig::default(), &token).is_none());
+/// ```
+pub fn group_signatures_cancellable(
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+    cancel: &CancellationToken,
+) -> Option<Vec<RouteGroup>> {
+    group_in_span(signatures.len(), || {
+        #[cfg(feature = "parallel")]
+        {
+            group_signatures_parallel_impl(signatures, config, cancel)
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            group_signatures_sequential(signatures, config, cancel)
+        }
+    })
+}
+
+/// Group similar routes, keeping each sport in its own groups.
+///
+/// `sport_types` maps activity ID to sport type (e.g. "Run", "Ride").
+/// Signatures are grouped separately per sport, so a run and a ride along
+/// the same path never share a group; activities missing from the map are
+/// grouped among themselves. With [`MatchConfig::cross_sport_grouping`] set
+/// this is the same as [`group_signatures`].
+///
+/// With [`MatchConfig::exclude_indoor`], virtual and indoor activities are
+/// each returned in a group of their own.
+///
+/// Uses parallel processing when the `parallel` feature is enabled.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, RouteSignature, MatchConfig, group_signatures_by_sport};
+///
+/// let points: Vec<GpsPoint> = (0..10)
+///     .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+///     .collect();
+/// let config = MatchConfig::default();
+/// let run = RouteSignature::from_points("run", &points, &config).unwrap();
+/// let ride = RouteSignature::from_points("ride", &points, &config).unwrap();
+///
+/// let sports = HashMap::from([
+///     ("run".to_string(), "Run".to_string()),
+///     ("ride".to_string(), "Ride".to_string()),
+/// ]);
+/// let signatures = [run, ride];
+/// assert_eq!(group_signatures_by_sport(&signatures, &sports, &config).len(), 2);
+///
+/// let mixed = MatchConfig { cross_sport_grouping: true, ..config };
+/// assert_eq!(group_signatures_by_sport(&signatures, &sports, &mixed).len(), 1);
+/// ```
+pub fn group_signatures_by_sport(
+    signatures: &[RouteSignature],
+    sport_types: &HashMap<String, String>,
+    config: &MatchConfig,
+) -> Vec<RouteGroup> {
+    let cancel = CancellationToken::new();
+    let indoor_config = IndoorConfig::default();
+    let mut indoor_groups = Vec::new();
+
+    // BTreeMap keeps the output order stable across runs
+    let mut by_sport: BTreeMap<Option<&str>, Vec<RouteSignature>> = BTreeMap::new();
+    for sig in signatures {
+        let sport = sport_types.get(&sig.activity_id).map(String::as_str);
+        if config.exclude_indoor && indoor::is_indoor(&sig.points, sport, &indoor_config) {
+            indoor_groups.push(RouteGroup {
+                group_id: sig.activity_id.clone(),
+                activity_ids: vec![sig.activity_id.clone()],
+            });
+            continue;
+        }
+        let key = if config.cross_sport_grouping { None } else { sport };
+        by_sport.entry(key).or_default().push(sig.clone());
+    }
+
+    let mut groups: Vec<RouteGroup> = by_sport
+        .values()
+        .flat_map(|sigs| group_signatures_cancellable(sigs, config, &cancel).unwrap_or_default())
+        .collect();
+    groups.extend(indoor_groups);
+    groups
+}
+
+/// Run a grouping under a `group` span.
+fn group_in_span(
+    signature_count: usize,
+    op: impl FnOnce() -> Option<Vec<RouteGroup>>,
+) -> Option<Vec<RouteGroup>> {
+    let mut span = pipeline_span!("group", signatures = signature_count; groups);
+    let groups = span.in_scope(op);
+    if let Some(groups) = &groups {
+        span.record("groups", groups.len());
+    }
+    span.finish();
+    groups
+}
+
+/// Sequential grouping, checking `cancel` once per signature.
+fn group_signatures_sequential(
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+    cancel: &CancellationToken,
+) -> Option<Vec<RouteGroup>> {
     if signatures.is_empty() {
-        return vec![];
+        return Some(vec![]);
     }
 
     // Build spatial index
-    let bounds: Vec<RouteBounds> = signatures.iter().map(|s| s.route_bounds()).collect();
-    let rtree = RTree::bulk_load(bounds.clone());
+    let rtree = RTree::bulk_load(indexable_bounds(signatures));
 
     // Create signature lookup
     let sig_map: HashMap<&str, &RouteSignature> = signatures
@@ -739,14 +1602,13 @@ pub fn group_signatures(signatures: &[RouteSignature], config: &MatchConfig) ->
         .collect();
 
     // Find matching pairs
-    let tolerance = 0.01; // ~1km
+    let tolerance = config.search_tolerance_degrees;
 
     for sig1 in signatures {
-        let (min_lat, max_lat, min_lng, max_lng) = calculate_bounds(&sig1.points);
-        let search_bounds = AABB::from_corners(
-            [min_lng - tolerance, min_lat - tolerance],
-            [max_lng + tolerance, max_lat + tolerance],
-        );
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let search_bounds = search_envelope(&sig1.bounds, tolerance);
 
         for bounds in rtree.locate_in_envelope_intersecting(&search_bounds) {
             // Skip self and already-processed pairs
@@ -758,7 +1620,7 @@ pub fn group_signatures(signatures: &[RouteSignature], config: &MatchConfig) ->
             }
 
             // Distance pre-filter
-            if !distance_ratio_ok(sig1.total_distance, bounds.distance) {
+            if !distance_ratio_ok(sig1.total_distance, bounds.distance, config) {
                 continue;
             }
 
@@ -773,17 +1635,7 @@ pub fn group_signatures(signatures: &[RouteSignature], config: &MatchConfig) ->
         }
     }
 
-    // Build groups
-    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
-    for sig in signatures {
-        let root = find(&mut parent, &sig.activity_id);
-        groups.entry(root).or_default().push(sig.activity_id.clone());
-    }
-
-    groups
-        .into_iter()
-        .map(|(group_id, activity_ids)| RouteGroup { group_id, activity_ids })
-        .collect()
+    Some(build_groups(&mut parent, signatures.iter().map(|s| &s.activity_id)))
 }
 
 /// Group signatures using parallel processing.
@@ -795,15 +1647,25 @@ pub fn group_signatures_parallel(
     signatures: &[RouteSignature],
     config: &MatchConfig,
 ) -> Vec<RouteGroup> {
+    group_in_span(signatures.len(), || group_signatures_parallel_impl(signatures, config, &CancellationToken::new()))
+        .unwrap_or_default()
+}
+
+/// Parallel grouping; workers skip remaining signatures once `cancel` is cancelled.
+#[cfg(feature = "parallel")]
+fn group_signatures_parallel_impl(
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+    cancel: &CancellationToken,
+) -> Option<Vec<RouteGroup>> {
     use rayon::prelude::*;
 
     if signatures.is_empty() {
-        return vec![];
+        return Some(vec![]);
     }
 
     // Build spatial index
-    let bounds: Vec<RouteBounds> = signatures.iter().map(|s| s.route_bounds()).collect();
-    let rtree = RTree::bulk_load(bounds.clone());
+    let rtree = RTree::bulk_load(indexable_bounds(signatures));
 
     // Create signature lookup
     let sig_map: HashMap<&str, &RouteSignature> = signatures
@@ -812,22 +1674,21 @@ pub fn group_signatures_parallel(
         .collect();
 
     // Find matches in parallel (with strict grouping criteria)
-    let tolerance = 0.01;
+    let tolerance = config.search_tolerance_degrees;
     let matches: Vec<(String, String)> = signatures
         .par_iter()
         .flat_map(|sig1| {
-            let (min_lat, max_lat, min_lng, max_lng) = calculate_bounds(&sig1.points);
-            let search_bounds = AABB::from_corners(
-                [min_lng - tolerance, min_lat - tolerance],
-                [max_lng + tolerance, max_lat + tolerance],
-            );
+            if cancel.is_cancelled() {
+                return Vec::new();
+            }
+            let search_bounds = search_envelope(&sig1.bounds, tolerance);
 
             rtree
                 .locate_in_envelope_intersecting(&search_bounds)
                 .filter(|b| {
                     b.activity_id != sig1.activity_id
                         && sig1.activity_id < b.activity_id
-                        && distance_ratio_ok(sig1.total_distance, b.distance)
+                        && distance_ratio_ok(sig1.total_distance, b.distance, config)
                 })
                 .filter_map(|b| {
                     let sig2 = sig_map.get(b.activity_id.as_str())?;
@@ -843,6 +1704,10 @@ pub fn group_signatures_parallel(
         })
         .collect();
 
+    if cancel.is_cancelled() {
+        return None;
+    }
+
     // Union-Find (sequential - fast enough)
     let mut parent: HashMap<String, String> = signatures
         .iter()
@@ -853,17 +1718,7 @@ pub fn group_signatures_parallel(
         union(&mut parent, &id1, &id2);
     }
 
-    // Build groups
-    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
-    for sig in signatures {
-        let root = find(&mut parent, &sig.activity_id);
-        groups.entry(root).or_default().push(sig.activity_id.clone());
-    }
-
-    groups
-        .into_iter()
-        .map(|(group_id, activity_ids)| RouteGroup { group_id, activity_ids })
-        .collect()
+    Some(build_groups(&mut parent, signatures.iter().map(|s| &s.activity_id)))
 }
 
 /// Incremental grouping: efficiently add new signatures to existing groups.
@@ -887,6 +1742,20 @@ pub fn group_incremental(
     existing_groups: &[RouteGroup],
     existing_signatures: &[RouteSignature],
     config: &MatchConfig,
+) -> Vec<RouteGroup> {
+    let signature_count = new_signatures.len() + existing_signatures.len();
+    group_in_span(signature_count, || {
+        Some(group_incremental_impl(new_signatures, existing_groups, existing_signatures, config))
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(feature = "parallel")]
+fn group_incremental_impl(
+    new_signatures: &[RouteSignature],
+    existing_groups: &[RouteGroup],
+    existing_signatures: &[RouteSignature],
+    config: &MatchConfig,
 ) -> Vec<RouteGroup> {
     use rayon::prelude::*;
 
@@ -896,7 +1765,7 @@ pub fn group_incremental(
 
     if existing_groups.is_empty() {
         // No existing groups - just group the new signatures
-        return group_signatures_parallel(new_signatures, config);
+        return group_signatures_parallel_impl(new_signatures, config, &CancellationToken::new()).unwrap_or_default();
     }
 
     // Combine all signatures for R-tree indexing
@@ -906,7 +1775,7 @@ pub fn group_incremental(
         .collect();
 
     // Build spatial index from all signatures
-    let all_bounds: Vec<RouteBounds> = all_signatures.iter().map(|s| s.route_bounds()).collect();
+    let all_bounds = indexable_bounds(all_signatures.iter().copied());
     let rtree = RTree::bulk_load(all_bounds);
 
     // Create signature lookup
@@ -940,20 +1809,17 @@ pub fn group_incremental(
     }
 
     // Find matches in parallel - but ONLY where at least one signature is new
-    let tolerance = 0.01;
+    let tolerance = config.search_tolerance_degrees;
     let matches: Vec<(String, String)> = new_signatures
         .par_iter()
         .flat_map(|new_sig| {
-            let search_bounds = AABB::from_corners(
-                [new_sig.bounds.min_lng - tolerance, new_sig.bounds.min_lat - tolerance],
-                [new_sig.bounds.max_lng + tolerance, new_sig.bounds.max_lat + tolerance],
-            );
+            let search_bounds = search_envelope(&new_sig.bounds, tolerance);
 
             rtree
                 .locate_in_envelope_intersecting(&search_bounds)
                 .filter(|b| {
                     b.activity_id != new_sig.activity_id
-                        && distance_ratio_ok(new_sig.total_distance, b.distance)
+                        && distance_ratio_ok(new_sig.total_distance, b.distance, config)
                 })
                 .filter_map(|b| {
                     let other_sig = sig_map.get(b.activity_id.as_str())?;
@@ -986,16 +1852,7 @@ pub fn group_incremental(
     }
 
     // Build groups from all signatures
-    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
-    for sig in all_signatures {
-        let root = find(&mut parent, &sig.activity_id);
-        groups.entry(root).or_default().push(sig.activity_id.clone());
-    }
-
-    groups
-        .into_iter()
-        .map(|(group_id, activity_ids)| RouteGroup { group_id, activity_ids })
-        .collect()
+    build_groups(&mut parent, all_signatures.iter().map(|s| &s.activity_id))
 }
 
 // ============================================================================
@@ -1021,6 +1878,149 @@ mod ffi {
         fn on_progress(&self, completed: u32, total: u32);
     }
 
+    /// Callback interface receiving each activity's map as soon as it is
+    /// fetched, so routes can be drawn while the batch is still running.
+    #[cfg(feature = "http")]
+    #[uniffi::export(callback_interface)]
+    pub trait ActivityCompleteCallback: Send + Sync {
+        /// Called from a background thread once per fetched activity,
+        /// successful or not.
+        fn on_activity_complete(&self, result: FfiActivityMapResult);
+    }
+
+    /// Callback interface for receiving progress updates during section detection.
+    /// Phases run once per sport type, so the percentage restarts for each sport.
+    #[uniffi::export(callback_interface)]
+    pub trait SectionProgressCallback: Send + Sync {
+        /// Called when the current phase's progress changes.
+        /// - phase: Detection phase currently running
+        /// - percent: Progress through that phase (0-100)
+        fn on_progress(&self, phase: SectionPhase, percent: u32);
+    }
+
+    // ========================================================================
+    // Chunked Results
+    // ========================================================================
+
+    /// Receives heatmap cells in chunks. Return false to stop streaming.
+    #[uniffi::export(callback_interface)]
+    pub trait HeatmapCellSink: Send + Sync {
+        fn on_cells(&self, cells: Vec<HeatmapCell>) -> bool;
+    }
+
+    /// Receives route signatures in chunks. Return false to stop streaming.
+    #[uniffi::export(callback_interface)]
+    pub trait SignatureSink: Send + Sync {
+        fn on_signatures(&self, signatures: Vec<RouteSignature>) -> bool;
+    }
+
+    /// Copy of `items[offset..offset + limit]`, clamped to the slice.
+    fn page<T: Clone>(items: &[T], offset: u32, limit: u32) -> Vec<T> {
+        let start = (offset as usize).min(items.len());
+        let end = start.saturating_add(limit as usize).min(items.len());
+        items[start..end].to_vec()
+    }
+
+    /// Fetch pages of `chunk_size` with `next(offset, limit)` and hand each to
+    /// `emit` until a page comes back short or `emit` returns false.
+    /// Returns the number of items emitted.
+    fn stream_pages<T>(
+        chunk_size: u32,
+        mut next: impl FnMut(u32, u32) -> Vec<T>,
+        mut emit: impl FnMut(Vec<T>) -> bool,
+    ) -> u32 {
+        let chunk_size = chunk_size.max(1);
+        let mut offset = 0u32;
+        loop {
+            let chunk = next(offset, chunk_size);
+            let len = chunk.len() as u32;
+            if len == 0 {
+                return offset;
+            }
+            offset += len;
+            if !emit(chunk) || len < chunk_size {
+                return offset;
+            }
+        }
+    }
+
+    // ========================================================================
+    // Cancellation
+    // ========================================================================
+
+    /// Cancellation handle for long-running calls. Create one per operation,
+    /// pass it in, and call `cancel()` from any thread (e.g. when the user
+    /// navigates away) to stop the work early.
+    #[derive(uniffi::Object)]
+    pub struct FfiCancellationToken {
+        token: CancellationToken,
+    }
+
+    #[uniffi::export]
+    impl FfiCancellationToken {
+        #[uniffi::constructor]
+        pub fn new() -> std::sync::Arc<Self> {
+            std::sync::Arc::new(Self { token: CancellationToken::new() })
+        }
+
+        /// Request cancellation. Cannot be undone.
+        pub fn cancel(&self) {
+            info!("[RouteMatcherRust] Cancellation requested");
+            self.token.cancel();
+        }
+
+        /// Whether cancellation has been requested.
+        pub fn is_cancelled(&self) -> bool {
+            self.token.is_cancelled()
+        }
+    }
+
+    // ========================================================================
+    // Logging
+    // ========================================================================
+
+    /// Log verbosity, from `Off` to `Trace`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+    pub enum LogLevel {
+        Off,
+        Error,
+        Warn,
+        Info,
+        Debug,
+        Trace,
+    }
+
+    impl From<LogLevel> for log::LevelFilter {
+        fn from(level: LogLevel) -> Self {
+            match level {
+                LogLevel::Off => log::LevelFilter::Off,
+                LogLevel::Error => log::LevelFilter::Error,
+                LogLevel::Warn => log::LevelFilter::Warn,
+                LogLevel::Info => log::LevelFilter::Info,
+                LogLevel::Debug => log::LevelFilter::Debug,
+                LogLevel::Trace => log::LevelFilter::Trace,
+            }
+        }
+    }
+
+    /// Set how much the library logs. Defaults to `Debug`.
+    #[uniffi::export]
+    pub fn ffi_set_log_level(level: LogLevel) {
+        crate::LOG_LEVEL.store(log::LevelFilter::from(level) as usize, std::sync::atomic::Ordering::Relaxed);
+        init_logging();
+        log::set_max_level(crate::log_level());
+    }
+
+    /// Quiet mode keeps only warnings and errors, e.g. so per-request HTTP
+    /// logs don't flood logcat during a full-library sync. Turning it off
+    /// restores the level from `ffi_set_log_level`.
+    #[uniffi::export]
+    pub fn ffi_set_quiet_logging(quiet: bool) {
+        crate::QUIET_LOGGING.store(quiet, std::sync::atomic::Ordering::Relaxed);
+        init_logging();
+        log::set_max_level(crate::log_level());
+    }
+
     /// Create a route signature from GPS points.
     #[uniffi::export]
     pub fn create_signature(activity_id: String, points: Vec<GpsPoint>) -> Option<RouteSignature> {
@@ -1045,6 +2045,121 @@ mod ffi {
         RouteSignature::from_points(&activity_id, &points, &config)
     }
 
+    /// Create a route signature that keeps the points at `landmarks` (lap
+    /// starts, manual waypoints) so markers stay on the simplified polyline.
+    #[uniffi::export]
+    pub fn create_signature_with_landmarks(
+        activity_id: String,
+        points: Vec<GpsPoint>,
+        landmarks: Vec<u32>,
+        config: MatchConfig,
+    ) -> Option<RouteSignature> {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] create_signature_with_landmarks for {} ({} points, {} landmarks)",
+            activity_id, points.len(), landmarks.len()
+        );
+        let landmarks: Vec<usize> = landmarks.into_iter().map(|i| i as usize).collect();
+        RouteSignature::from_points_with_landmarks(&activity_id, &points, &landmarks, &config)
+    }
+
+    /// Merge an activity recorded across multiple files into one track.
+    #[uniffi::export]
+    pub fn ffi_merge_tracks(parts: Vec<Vec<GpsPoint>>) -> Vec<GpsPoint> {
+        crate::merge_tracks(parts)
+    }
+
+    /// Create a single route signature from an activity recorded across multiple files.
+    #[uniffi::export]
+    pub fn create_signature_from_parts(
+        activity_id: String,
+        parts: Vec<Vec<GpsPoint>>,
+        config: MatchConfig,
+    ) -> Option<RouteSignature> {
+        init_logging();
+        info!("[RouteMatcherRust] create_signature_from_parts for {} ({} parts)", activity_id, parts.len());
+        RouteSignature::from_parts(&activity_id, parts, &config)
+    }
+
+    /// A signature with the report from cleaning its points. `error` is set,
+    /// and `signature` None, if cleaning failed or too few points were left.
+    #[derive(Debug, Clone, uniffi::Record)]
+    pub struct CleanedSignature {
+        pub signature: Option<RouteSignature>,
+        pub report: Option<crate::CleaningReport>,
+        pub error: Option<String>,
+    }
+
+    /// Create a route signature after cleaning invalid points by `cleaning`.
+    #[uniffi::export]
+    pub fn create_signature_cleaned(
+        activity_id: String,
+        points: Vec<GpsPoint>,
+        config: MatchConfig,
+        cleaning: crate::CleaningConfig,
+    ) -> CleanedSignature {
+        init_logging();
+        match RouteSignature::from_points_cleaned(&activity_id, &points, &config, &cleaning) {
+            Ok((signature, report)) => {
+                info!(
+                    "[RouteMatcherRust] create_signature_cleaned for {}: {} dropped, {} clamped, {} null island runs",
+                    activity_id, report.dropped_points, report.clamped_points, report.null_island_runs
+                );
+                CleanedSignature { signature: Some(signature), report: Some(report), error: None }
+            }
+            Err(e) => {
+                info!("[RouteMatcherRust] create_signature_cleaned for {} failed: {}", activity_id, e);
+                CleanedSignature { signature: None, report: None, error: Some(e) }
+            }
+        }
+    }
+
+    /// Get default resampling and stop config.
+    #[uniffi::export]
+    pub fn default_resample_config() -> crate::ResampleConfig {
+        crate::ResampleConfig::default()
+    }
+
+    /// Create a route signature from a timed track, capping how much of each
+    /// stop is kept. `timestamps` are Unix seconds, one per point.
+    #[uniffi::export]
+    pub fn create_signature_with_timestamps(
+        activity_id: String,
+        points: Vec<GpsPoint>,
+        timestamps: Vec<i64>,
+        config: MatchConfig,
+        stops: crate::ResampleConfig,
+    ) -> Option<RouteSignature> {
+        init_logging();
+        info!("[RouteMatcherRust] create_signature_with_timestamps for {} ({} points)", activity_id, points.len());
+        RouteSignature::from_timed_points(&activity_id, &points, &timestamps, &config, &stops)
+    }
+
+    /// Resample a track to `count` points evenly spaced by distance or time.
+    /// `timestamps` (one per point) may be empty.
+    #[uniffi::export]
+    pub fn ffi_resample_track(
+        points: Vec<GpsPoint>,
+        timestamps: Vec<i64>,
+        count: u32,
+        config: crate::ResampleConfig,
+    ) -> Vec<GpsPoint> {
+        crate::resample_track(&points, &timestamps, count as usize, &config)
+    }
+
+    /// Thumbnail polylines (at most `max_points` each) for a list of routes,
+    /// in the same order as `signatures`.
+    #[uniffi::export]
+    pub fn ffi_thumbnail_points(signatures: Vec<RouteSignature>, max_points: u32) -> Vec<Vec<GpsPoint>> {
+        signatures.iter().map(|sig| sig.thumbnail_points(max_points)).collect()
+    }
+
+    /// Route polyline simplified for a web map zoom level.
+    #[uniffi::export]
+    pub fn ffi_simplified_for_zoom(signature: &RouteSignature, zoom: u8) -> Vec<GpsPoint> {
+        signature.simplified_for_zoom(zoom)
+    }
+
     /// Compare two routes and return match result.
     #[uniffi::export]
     pub fn ffi_compare_routes(
@@ -1061,32 +2176,207 @@ mod ffi {
         result
     }
 
-    /// Group signatures into route groups.
+    /// Compare one route against each candidate; returns only the matches.
     #[uniffi::export]
-    pub fn ffi_group_signatures(
-        signatures: Vec<RouteSignature>,
+    pub fn ffi_compare_one_to_many(
+        signature: RouteSignature,
+        candidates: Vec<RouteSignature>,
         config: MatchConfig,
-    ) -> Vec<RouteGroup> {
+    ) -> Vec<MatchResult> {
         init_logging();
-        info!("[RouteMatcherRust] RUST groupSignatures called with {} signatures", signatures.len());
+        let results = crate::compare_one_to_many(&signature, &candidates, &config);
+        info!(
+            "[RouteMatcherRust] compare_one_to_many: {} of {} candidates match {}",
+            results.len(),
+            candidates.len(),
+            signature.activity_id
+        );
+        results
+    }
 
+    /// Compare every pair of routes once; returns only the matching pairs.
+    #[uniffi::export]
+    pub fn ffi_similarity_matrix(signatures: Vec<RouteSignature>, config: MatchConfig) -> Vec<MatchResult> {
+        init_logging();
         let start = std::time::Instant::now();
-
-        #[cfg(feature = "parallel")]
-        let groups = {
-            info!("[RouteMatcherRust] Using PARALLEL processing (rayon)");
-            group_signatures_parallel(&signatures, &config)
-        };
+        let results = crate::similarity_matrix(&signatures, &config);
+        info!(
+            "[RouteMatcherRust] similarity_matrix: {} matching pairs among {} signatures in {:?}",
+            results.len(),
+            signatures.len(),
+            start.elapsed()
+        );
+        results
+    }
+
+    /// Each route's `k` most similar other routes, even below the grouping threshold.
+    #[uniffi::export]
+    pub fn ffi_top_k_similar(
+        signatures: Vec<RouteSignature>,
+        k: u32,
+        config: MatchConfig,
+    ) -> Vec<crate::SimilarRoutes> {
+        init_logging();
+        info!("[RouteMatcherRust] top_k_similar: {} signatures, k = {}", signatures.len(), k);
+        crate::top_k_similar(&signatures, k, &config)
+    }
+
+    /// Group signatures into route groups.
+    #[uniffi::export]
+    pub fn ffi_group_signatures(
+        signatures: Vec<RouteSignature>,
+        config: MatchConfig,
+    ) -> Vec<RouteGroup> {
+        init_logging();
+
+        #[cfg(feature = "parallel")]
+        let groups = group_signatures_parallel(&signatures, &config);
 
         #[cfg(not(feature = "parallel"))]
-        let groups = {
-            info!("[RouteMatcherRust] Using sequential processing");
-            group_signatures(&signatures, &config)
-        };
+        let groups = group_signatures(&signatures, &config);
 
-        let elapsed = start.elapsed();
-        info!("[RouteMatcherRust] Grouped into {} groups in {:?}", groups.len(), elapsed);
+        groups
+    }
+
+    /// Group signatures into route groups, never mixing sports unless
+    /// `config.cross_sport_grouping` is set.
+    #[uniffi::export]
+    pub fn ffi_group_signatures_by_sport(
+        signatures: Vec<RouteSignature>,
+        sport_types: Vec<ActivitySportType>,
+        config: MatchConfig,
+    ) -> Vec<RouteGroup> {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] group_signatures_by_sport: {} signatures, {} sport types",
+            signatures.len(),
+            sport_types.len()
+        );
+
+        let sport_map = sport_types_by_id(sport_types);
+
+        crate::group_signatures_by_sport(&signatures, &sport_map, &config)
+    }
+
+    /// Signature kept on the native side. Records are deep-copied on every
+    /// call; handles cross the bridge as a pointer, so large libraries only
+    /// copy the fields the app actually reads.
+    #[derive(uniffi::Object)]
+    pub struct RouteSignatureHandle {
+        signature: RouteSignature,
+    }
+
+    #[uniffi::export]
+    impl RouteSignatureHandle {
+        #[uniffi::constructor]
+        pub fn new(signature: RouteSignature) -> std::sync::Arc<Self> {
+            std::sync::Arc::new(Self { signature })
+        }
+
+        pub fn activity_id(&self) -> String {
+            self.signature.activity_id.clone()
+        }
+
+        pub fn point_count(&self) -> u32 {
+            self.signature.points.len() as u32
+        }
+
+        /// Total route distance in meters.
+        pub fn total_distance(&self) -> f64 {
+            self.signature.total_distance
+        }
+
+        pub fn start_point(&self) -> GpsPoint {
+            self.signature.start_point
+        }
+
+        pub fn end_point(&self) -> GpsPoint {
+            self.signature.end_point
+        }
+
+        pub fn bounds(&self) -> Bounds {
+            self.signature.bounds
+        }
+
+        pub fn center(&self) -> GpsPoint {
+            self.signature.center
+        }
+
+        pub fn points(&self) -> Vec<GpsPoint> {
+            self.signature.points.clone()
+        }
+
+        /// Points as a flat `[lat, lng, ...]` array.
+        pub fn coords_flat(&self) -> Vec<f64> {
+            self.signature.flat_coords()
+        }
+
+        /// A full copy of the signature as a record.
+        pub fn signature(&self) -> RouteSignature {
+            self.signature.clone()
+        }
+
+        /// Compare with another route.
+        pub fn compare(&self, other: std::sync::Arc<RouteSignatureHandle>, config: MatchConfig) -> Option<MatchResult> {
+            compare_routes(&self.signature, &other.signature, &config)
+        }
+    }
+
+    /// `create_signatures_from_flat` returning handles instead of records.
+    #[uniffi::export]
+    pub fn create_signature_handles_from_flat(
+        tracks: Vec<FlatGpsTrack>,
+        config: MatchConfig,
+    ) -> Vec<std::sync::Arc<RouteSignatureHandle>> {
+        init_logging();
+        info!("[RouteMatcherRust] FLAT BUFFER createSignatureHandles called with {} tracks", tracks.len());
+
+        signatures_from_flat(&tracks, &config)
+            .into_iter()
+            .map(RouteSignatureHandle::new)
+            .collect()
+    }
+
+    /// Points of many signature handles in one flat buffer with per-activity
+    /// point offsets, for building map layers in one pass.
+    #[uniffi::export]
+    pub fn ffi_signature_handles_flat(handles: Vec<std::sync::Arc<RouteSignatureHandle>>) -> crate::FlatPolylines {
+        crate::FlatPolylines::from_polylines(
+            handles.iter().map(|h| (h.signature.activity_id.as_str(), &h.signature.points[..])),
+        )
+    }
+
+    /// Group signature handles into route groups.
+    #[uniffi::export]
+    pub fn ffi_group_signature_handles(
+        handles: Vec<std::sync::Arc<RouteSignatureHandle>>,
+        config: MatchConfig,
+    ) -> Vec<RouteGroup> {
+        init_logging();
+        let signatures: Vec<RouteSignature> = handles.iter().map(|h| h.signature.clone()).collect();
+
+        #[cfg(feature = "parallel")]
+        let groups = group_signatures_parallel(&signatures, &config);
+
+        #[cfg(not(feature = "parallel"))]
+        let groups = group_signatures(&signatures, &config);
+
+        groups
+    }
 
+    /// Group signatures into route groups, stopping early if `token` is cancelled.
+    /// Returns None if cancelled.
+    #[uniffi::export]
+    pub fn ffi_group_signatures_cancellable(
+        signatures: Vec<RouteSignature>,
+        config: MatchConfig,
+        token: std::sync::Arc<FfiCancellationToken>,
+    ) -> Option<Vec<RouteGroup>> {
+        init_logging();
+        let groups = group_signatures_cancellable(&signatures, &config, &token.token);
+        if groups.is_none() {
+            info!("[RouteMatcherRust] Grouping cancelled");
+        }
         groups
     }
 
@@ -1100,13 +2390,6 @@ mod ffi {
         config: MatchConfig,
     ) -> Vec<RouteGroup> {
         init_logging();
-        info!(
-            "[RouteMatcherRust] INCREMENTAL grouping: {} new + {} existing signatures",
-            new_signatures.len(),
-            existing_signatures.len()
-        );
-
-        let start = std::time::Instant::now();
 
         #[cfg(feature = "parallel")]
         let groups = group_incremental(&new_signatures, &existing_groups, &existing_signatures, &config);
@@ -1121,10 +2404,56 @@ mod ffi {
             group_signatures(&all_sigs, &config)
         };
 
+        groups
+    }
+
+    /// Group signatures and cluster the groups into a variant hierarchy.
+    /// Level 0 is strict same-journey grouping; level 1 clusters groups that
+    /// share most of their geometry ("Route X and its 3 variants").
+    #[uniffi::export]
+    pub fn ffi_group_signatures_hierarchical(
+        signatures: Vec<RouteSignature>,
+        config: MatchConfig,
+    ) -> Vec<crate::RouteHierarchy> {
+        init_logging();
+        info!("[RouteMatcherRust] HIERARCHICAL grouping called with {} signatures", signatures.len());
+
+        let start = std::time::Instant::now();
+        let hierarchy = crate::group_signatures_hierarchical(&signatures, &config);
+
         let elapsed = start.elapsed();
-        info!("[RouteMatcherRust] Incremental grouped into {} groups in {:?}", groups.len(), elapsed);
+        info!("[RouteMatcherRust] Built {} route hierarchies in {:?}", hierarchy.len(), elapsed);
 
-        groups
+        hierarchy
+    }
+
+    /// Split route groups into variants of activities sharing almost all of
+    /// their geometry (a shortcut or an extra lap makes a new variant).
+    #[uniffi::export]
+    pub fn ffi_group_variants(
+        groups: Vec<RouteGroup>,
+        signatures: Vec<RouteSignature>,
+        config: MatchConfig,
+    ) -> Vec<crate::GroupVariants> {
+        init_logging();
+        let variants = crate::group_variants(&groups, &signatures, &config);
+        info!(
+            "[RouteMatcherRust] group_variants: {} variants across {} groups",
+            variants.iter().map(|g| g.variants.len()).sum::<usize>(),
+            groups.len()
+        );
+        variants
+    }
+
+    /// Cluster already-computed strict groups into a variant hierarchy.
+    #[uniffi::export]
+    pub fn ffi_build_group_hierarchy(
+        groups: Vec<RouteGroup>,
+        signatures: Vec<RouteSignature>,
+        config: MatchConfig,
+    ) -> Vec<crate::RouteHierarchy> {
+        init_logging();
+        crate::build_group_hierarchy(&groups, &signatures, &config)
     }
 
     /// Get default configuration.
@@ -1150,52 +2479,81 @@ mod ffi {
         pub coords: Vec<f64>,
     }
 
+    impl FlatGpsTrack {
+        /// The coordinates as points
+        fn points(&self) -> Vec<GpsPoint> {
+            self.coords.chunks_exact(2).map(|c| GpsPoint::new(c[0], c[1])).collect()
+        }
+    }
+
+    /// Unpack flat coordinate buffers into `(activity_id, points)` tracks.
+    fn tracks_from_flat(tracks: Vec<FlatGpsTrack>) -> Vec<(String, Vec<GpsPoint>)> {
+        tracks
+            .into_iter()
+            .map(|track| {
+                let points = track.points();
+                (track.activity_id, points)
+            })
+            .collect()
+    }
+
+    /// Sport type per activity ID.
+    fn sport_types_by_id(sport_types: Vec<ActivitySportType>) -> std::collections::HashMap<String, String> {
+        sport_types
+            .into_iter()
+            .map(|st| (st.activity_id, st.sport_type))
+            .collect()
+    }
+
     /// Create signatures from flat coordinate buffers (optimized for TypedArray input).
     /// Each track's coords array contains [lat1, lng1, lat2, lng2, ...].
     /// This avoids the overhead of deserializing GpsPoint objects.
     #[uniffi::export]
     pub fn create_signatures_from_flat(tracks: Vec<FlatGpsTrack>, config: MatchConfig) -> Vec<RouteSignature> {
         init_logging();
-        info!("[RouteMatcherRust] FLAT BUFFER createSignatures called with {} tracks", tracks.len());
+        signatures_from_flat(&tracks, &config)
+    }
 
-        let start = std::time::Instant::now();
+    /// `create_signatures_from_flat` with fixed-point coordinates, halving the
+    /// returned payload. Restore with `CompactSignature::to_signature` on the
+    /// Rust side, or use the points directly for drawing.
+    #[uniffi::export]
+    pub fn create_compact_signatures_from_flat(
+        tracks: Vec<FlatGpsTrack>,
+        config: MatchConfig,
+    ) -> Vec<crate::CompactSignature> {
+        init_logging();
+        info!("[RouteMatcherRust] FLAT BUFFER createCompactSignatures called with {} tracks", tracks.len());
 
-        #[cfg(feature = "parallel")]
-        let signatures: Vec<RouteSignature> = {
-            use rayon::prelude::*;
-            info!("[RouteMatcherRust] Using PARALLEL flat buffer processing (rayon)");
-            tracks
-                .par_iter()
-                .filter_map(|track| {
-                    // Convert flat coords to GpsPoints
-                    let points: Vec<GpsPoint> = track.coords
-                        .chunks_exact(2)
-                        .map(|chunk| GpsPoint::new(chunk[0], chunk[1]))
-                        .collect();
-                    RouteSignature::from_points(&track.activity_id, &points, &config)
-                })
-                .collect()
-        };
+        signatures_from_flat(&tracks, &config)
+            .iter()
+            .map(crate::CompactSignature::from)
+            .collect()
+    }
 
-        #[cfg(not(feature = "parallel"))]
-        let signatures: Vec<RouteSignature> = {
-            info!("[RouteMatcherRust] Using sequential flat buffer processing");
-            tracks
-                .iter()
-                .filter_map(|track| {
-                    let points: Vec<GpsPoint> = track.coords
-                        .chunks_exact(2)
-                        .map(|chunk| GpsPoint::new(chunk[0], chunk[1]))
-                        .collect();
-                    RouteSignature::from_points(&track.activity_id, &points, &config)
-                })
-                .collect()
+    /// Signatures for borrowed flat tracks. Each track's points are only
+    /// materialized while its signature is built.
+    fn signatures_from_flat(tracks: &[FlatGpsTrack], config: &MatchConfig) -> Vec<RouteSignature> {
+        let signature = |track: &FlatGpsTrack| {
+            RouteSignature::from_points(&track.activity_id, &track.points(), config)
         };
 
-        let elapsed = start.elapsed();
-        info!("[RouteMatcherRust] FLAT created {} signatures from {} tracks in {:?}",
-              signatures.len(), tracks.len(), elapsed);
+        let mut span = pipeline_span!("signature_create", tracks = tracks.len(); signatures);
+        let signatures: Vec<RouteSignature> = span.in_scope(|| {
+            #[cfg(feature = "parallel")]
+            {
+                use rayon::prelude::*;
+                tracks.par_iter().filter_map(signature).collect()
+            }
 
+            #[cfg(not(feature = "parallel"))]
+            {
+                tracks.iter().filter_map(signature).collect()
+            }
+        });
+
+        span.record("signatures", signatures.len());
+        span.finish();
         signatures
     }
 
@@ -1208,8 +2566,13 @@ mod ffi {
 
         let start = std::time::Instant::now();
 
-        // Step 1: Create all signatures from flat buffers
-        let signatures = create_signatures_from_flat(tracks.clone(), config.clone());
+        // Step 1: Create all signatures from flat buffers, then free them
+        // before grouping so only one copy of the tracks is ever held
+        let track_count = tracks.len();
+        let signatures = signatures_from_flat(&tracks, &config);
+        drop(tracks);
+        info!("[RouteMatcherRust] FLAT created {} signatures from {} tracks",
+              signatures.len(), track_count);
 
         // Step 2: Group signatures
         #[cfg(feature = "parallel")]
@@ -1225,44 +2588,93 @@ mod ffi {
         groups
     }
 
-    /// Create multiple route signatures in parallel (batch processing).
-    /// Much faster than calling create_signature repeatedly due to:
-    /// 1. Single FFI call instead of N calls
-    /// 2. Parallel processing with rayon
+    /// `process_routes_from_flat` within memory and thread limits. Tracks are
+    /// converted to points in batches of at most `limits.max_points_in_memory`
+    /// points, and each flat buffer is freed once converted.
     #[uniffi::export]
-    pub fn create_signatures_batch(tracks: Vec<GpsTrack>, config: MatchConfig) -> Vec<RouteSignature> {
+    pub fn process_routes_from_flat_with_limits(
+        tracks: Vec<FlatGpsTrack>,
+        config: MatchConfig,
+        limits: crate::ResourceLimits,
+    ) -> Vec<RouteGroup> {
         init_logging();
-        info!("[RouteMatcherRust] BATCH create_signatures called with {} tracks", tracks.len());
+        info!(
+            "[RouteMatcherRust] FLAT BATCH process_routes with limits {:?} for {} tracks",
+            limits, tracks.len()
+        );
 
         let start = std::time::Instant::now();
+        let point_counts: Vec<usize> = tracks.iter().map(|t| t.coords.len() / 2).collect();
+        let batches = limits.batches(&point_counts);
 
-        #[cfg(feature = "parallel")]
-        let signatures: Vec<RouteSignature> = {
+        let groups = limits.install(move || {
+            #[cfg(feature = "parallel")]
             use rayon::prelude::*;
-            info!("[RouteMatcherRust] Using PARALLEL signature creation (rayon)");
-            tracks
-                .par_iter()
-                .filter_map(|track| {
-                    RouteSignature::from_points(&track.activity_id, &track.points, &config)
-                })
-                .collect()
-        };
 
-        #[cfg(not(feature = "parallel"))]
-        let signatures: Vec<RouteSignature> = {
-            info!("[RouteMatcherRust] Using sequential signature creation");
-            tracks
-                .iter()
-                .filter_map(|track| {
-                    RouteSignature::from_points(&track.activity_id, &track.points, &config)
-                })
-                .collect()
-        };
+            let mut signatures = Vec::with_capacity(tracks.len());
+            let mut tracks = tracks.into_iter();
+            for batch in batches {
+                let batch: Vec<FlatGpsTrack> = tracks.by_ref().take(batch.len()).collect();
+
+                #[cfg(feature = "parallel")]
+                let iter = batch.into_par_iter();
+                #[cfg(not(feature = "parallel"))]
+                let iter = batch.into_iter();
+
+                let batch_signatures: Vec<RouteSignature> = iter
+                    .filter_map(|track| {
+                        let points = crate::limits::points_from_flat(&track.coords, 1);
+                        RouteSignature::from_points(&track.activity_id, &points, &config)
+                    })
+                    .collect();
+                signatures.extend(batch_signatures);
+            }
 
-        let elapsed = start.elapsed();
-        info!("[RouteMatcherRust] Created {} signatures from {} tracks in {:?}",
-              signatures.len(), tracks.len(), elapsed);
+            #[cfg(feature = "parallel")]
+            let groups = group_signatures_parallel(&signatures, &config);
+            #[cfg(not(feature = "parallel"))]
+            let groups = group_signatures(&signatures, &config);
+            groups
+        });
+
+        info!("[RouteMatcherRust] FLAT batch processing with limits: {} groups in {:?}", groups.len(), start.elapsed());
+        groups
+    }
+
+    /// Create multiple route signatures in parallel (batch processing).
+    /// Much faster than calling create_signature repeatedly due to:
+    /// 1. Single FFI call instead of N calls
+    /// 2. Parallel processing with rayon
+    #[uniffi::export]
+    pub fn create_signatures_batch(tracks: Vec<GpsTrack>, config: MatchConfig) -> Vec<RouteSignature> {
+        init_logging();
+        let mut span = pipeline_span!("signature_create", tracks = tracks.len(); signatures);
+
+        let signatures: Vec<RouteSignature> = span.in_scope(|| {
+            #[cfg(feature = "parallel")]
+            {
+                use rayon::prelude::*;
+                tracks
+                    .par_iter()
+                    .filter_map(|track| {
+                        RouteSignature::from_points(&track.activity_id, &track.points, &config)
+                    })
+                    .collect()
+            }
+
+            #[cfg(not(feature = "parallel"))]
+            {
+                tracks
+                    .iter()
+                    .filter_map(|track| {
+                        RouteSignature::from_points(&track.activity_id, &track.points, &config)
+                    })
+                    .collect()
+            }
+        });
 
+        span.record("signatures", signatures.len());
+        span.finish();
         signatures
     }
 
@@ -1292,6 +2704,24 @@ mod ffi {
         groups
     }
 
+    /// Import every GPX and FIT file under a directory (e.g. an unzipped
+    /// Strava or Garmin bulk export). Map each result to a `GpsTrack` for
+    /// `process_routes_batch`. Returns an empty list if the directory can't
+    /// be read; unparseable files are skipped.
+    #[uniffi::export]
+    pub fn ffi_import_directory(path: String) -> Vec<crate::ImportedActivity> {
+        init_logging();
+        info!("[RouteMatcherRust] import_directory called for {}", path);
+
+        match crate::import_directory(std::path::Path::new(&path)) {
+            Ok(activities) => activities,
+            Err(e) => {
+                info!("[RouteMatcherRust] Import failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
     // ========================================================================
     // HTTP Activity Fetching (requires "http" feature)
     // ========================================================================
@@ -1307,6 +2737,27 @@ mod ffi {
         pub latlngs: Vec<f64>,
         pub success: bool,
         pub error: Option<String>,
+        /// Why the fetch failed, or None on success
+        pub error_kind: Option<crate::FetchErrorKind>,
+        /// Retries after 429s or network errors before the final outcome
+        pub retries: u32,
+    }
+
+    #[cfg(feature = "http")]
+    impl From<crate::http::ActivityMapResult> for FfiActivityMapResult {
+        fn from(r: crate::http::ActivityMapResult) -> Self {
+            Self {
+                activity_id: r.activity_id,
+                bounds: r.bounds.map_or(vec![], |b| vec![b.ne[0], b.ne[1], b.sw[0], b.sw[1]]),
+                latlngs: r.latlngs.map_or(vec![], |coords| {
+                    coords.into_iter().flat_map(|p| vec![p[0], p[1]]).collect()
+                }),
+                success: r.success,
+                error: r.error,
+                error_kind: r.error_kind,
+                retries: r.retries,
+            }
+        }
     }
 
     /// Fetch map data for multiple activities in parallel.
@@ -1326,20 +2777,12 @@ mod ffi {
         init_logging();
         info!("[RouteMatcherRust] fetch_activity_maps called for {} activities", activity_ids.len());
 
-        let results = crate::http::fetch_activity_maps_sync(api_key, activity_ids, None);
+        let results = crate::http::fetch_activity_maps_sync(api_key, activity_ids, None, None);
 
         // Convert to FFI-friendly format
         results
             .into_iter()
-            .map(|r| FfiActivityMapResult {
-                activity_id: r.activity_id,
-                bounds: r.bounds.map_or(vec![], |b| vec![b.ne[0], b.ne[1], b.sw[0], b.sw[1]]),
-                latlngs: r.latlngs.map_or(vec![], |coords| {
-                    coords.into_iter().flat_map(|p| vec![p[0], p[1]]).collect()
-                }),
-                success: r.success,
-                error: r.error,
-            })
+            .map(FfiActivityMapResult::from)
             .collect()
     }
 
@@ -1369,33 +2812,243 @@ mod ffi {
             api_key,
             activity_ids,
             Some(progress_callback),
+            None,
         );
 
         // Convert to FFI-friendly format
         results
             .into_iter()
-            .map(|r| FfiActivityMapResult {
-                activity_id: r.activity_id,
-                bounds: r.bounds.map_or(vec![], |b| vec![b.ne[0], b.ne[1], b.sw[0], b.sw[1]]),
-                latlngs: r.latlngs.map_or(vec![], |coords| {
-                    coords.into_iter().flat_map(|p| vec![p[0], p[1]]).collect()
-                }),
-                success: r.success,
-                error: r.error,
-            })
+            .map(FfiActivityMapResult::from)
             .collect()
     }
 
-    /// Result of fetch_and_process_activities
+    /// Fetch map data with progress callbacks, stopping early if `token` is cancelled.
+    ///
+    /// Activities not yet requested when cancelled are returned with
+    /// success = false and error = "Cancelled".
     #[cfg(feature = "http")]
-    #[derive(Debug, Clone, uniffi::Record)]
-    pub struct FetchAndProcessResult {
-        pub map_results: Vec<FfiActivityMapResult>,
-        pub signatures: Vec<RouteSignature>,
-    }
-
-    // ========================================================================
-    // Frequent Sections Detection
+    #[uniffi::export]
+    pub fn fetch_activity_maps_cancellable(
+        api_key: String,
+        activity_ids: Vec<String>,
+        callback: Box<dyn FetchProgressCallback>,
+        token: std::sync::Arc<FfiCancellationToken>,
+    ) -> Vec<FfiActivityMapResult> {
+        use std::sync::Arc;
+
+        init_logging();
+        info!("[RouteMatcherRust] fetch_activity_maps_cancellable called for {} activities", activity_ids.len());
+
+        let callback = Arc::new(callback);
+        let progress_callback: crate::http::ProgressCallback = Arc::new(move |completed, total| {
+            callback.on_progress(completed, total);
+        });
+
+        let results = crate::http::fetch_activity_maps_sync(
+            api_key,
+            activity_ids,
+            Some(progress_callback),
+            Some(token.token.clone()),
+        );
+
+        // Convert to FFI-friendly format
+        results
+            .into_iter()
+            .map(FfiActivityMapResult::from)
+            .collect()
+    }
+
+    /// Get default fetcher configuration (intervals.icu rate limits).
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn default_fetcher_config() -> crate::FetcherConfig {
+        crate::FetcherConfig::default()
+    }
+
+    /// Get fetcher configuration for the Strava API (Strava rate limits).
+    /// Pass an OAuth access token wherever an API key is expected.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn strava_fetcher_config() -> crate::FetcherConfig {
+        crate::FetcherConfig::strava()
+    }
+
+    /// Fetch map data with custom rate limits and endpoint, e.g. for a
+    /// self-hosted intervals.icu instance. Progress callback and cancellation
+    /// token are optional.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_activity_maps_with_config(
+        api_key: String,
+        activity_ids: Vec<String>,
+        config: crate::FetcherConfig,
+        callback: Option<Box<dyn FetchProgressCallback>>,
+        token: Option<std::sync::Arc<FfiCancellationToken>>,
+    ) -> Vec<FfiActivityMapResult> {
+        use std::sync::Arc;
+
+        init_logging();
+        info!(
+            "[RouteMatcherRust] fetch_activity_maps_with_config called for {} activities ({} at {} req/s)",
+            activity_ids.len(), config.base_url, config.requests_per_second
+        );
+
+        let progress_callback: Option<crate::http::ProgressCallback> = callback.map(|callback| {
+            let callback = Arc::new(callback);
+            Arc::new(move |completed, total| callback.on_progress(completed, total)) as crate::http::ProgressCallback
+        });
+
+        let results = crate::http::fetch_activity_maps_sync_with_config(
+            api_key,
+            &config,
+            activity_ids,
+            progress_callback,
+            token.map(|t| t.token.clone()),
+        );
+
+        // Convert to FFI-friendly format
+        results
+            .into_iter()
+            .map(FfiActivityMapResult::from)
+            .collect()
+    }
+
+    /// Fetch map data, checkpointing each completed map to `checkpoint_path`
+    /// (e.g. a file in the app's documents directory). After an interrupted
+    /// sync, call again with the same IDs and path to fetch only what is
+    /// missing; the file is deleted once every activity has been fetched.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_activity_maps_resumable(
+        api_key: String,
+        activity_ids: Vec<String>,
+        checkpoint_path: String,
+        config: Option<crate::FetcherConfig>,
+        callback: Option<Box<dyn FetchProgressCallback>>,
+        token: Option<std::sync::Arc<FfiCancellationToken>>,
+    ) -> Vec<FfiActivityMapResult> {
+        use std::sync::Arc;
+
+        init_logging();
+        info!(
+            "[RouteMatcherRust] fetch_activity_maps_resumable called for {} activities (checkpoint: {})",
+            activity_ids.len(), checkpoint_path
+        );
+
+        let progress_callback: Option<crate::http::ProgressCallback> = callback.map(|callback| {
+            let callback = Arc::new(callback);
+            Arc::new(move |completed, total| callback.on_progress(completed, total)) as crate::http::ProgressCallback
+        });
+
+        let results = crate::http::fetch_activity_maps_sync_resumable(
+            api_key,
+            &config.unwrap_or_default(),
+            activity_ids,
+            &checkpoint_path,
+            progress_callback,
+            token.map(|t| t.token.clone()),
+        );
+
+        results
+            .into_iter()
+            .map(FfiActivityMapResult::from)
+            .collect()
+    }
+
+    /// Fetch map data, delivering each result to `callback` as it arrives
+    /// instead of returning the whole batch. Activities skipped because
+    /// `token` was cancelled are not delivered.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_activity_maps_progressive(
+        api_key: String,
+        activity_ids: Vec<String>,
+        config: Option<crate::FetcherConfig>,
+        callback: Box<dyn ActivityCompleteCallback>,
+        token: Option<std::sync::Arc<FfiCancellationToken>>,
+    ) {
+        use std::sync::Arc;
+
+        init_logging();
+        info!(
+            "[RouteMatcherRust] fetch_activity_maps_progressive called for {} activities",
+            activity_ids.len()
+        );
+
+        let callback = Arc::new(callback);
+        let on_result: crate::http::ResultCallback = Arc::new(move |r: &crate::http::ActivityMapResult| {
+            callback.on_activity_complete(FfiActivityMapResult::from(r.clone()))
+        });
+
+        crate::http::fetch_activity_maps_sync_progressive(
+            api_key,
+            &config.unwrap_or_default(),
+            activity_ids,
+            on_result,
+            token.map(|t| t.token.clone()),
+        );
+    }
+
+    /// List an athlete's activities started between `oldest` and `newest`
+    /// (Unix timestamps, inclusive), newest first, paging through the API.
+    /// Use athlete ID "0" for the API key's own athlete. Returns an empty list
+    /// on error.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_activities(
+        api_key: String,
+        athlete_id: String,
+        oldest: i64,
+        newest: i64,
+        config: Option<crate::FetcherConfig>,
+    ) -> Vec<crate::ActivityListItem> {
+        init_logging();
+        info!("[RouteMatcherRust] fetch_activities called for athlete {} ({}..{})", athlete_id, oldest, newest);
+
+        let config = config.unwrap_or_default();
+        match crate::http::fetch_activities_sync(&api_key, &config, &athlete_id, oldest, newest) {
+            Ok(activities) => activities,
+            Err(e) => {
+                info!("[RouteMatcherRust] fetch_activities failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Fetch time, heart rate, power, altitude or velocity streams of an
+    /// activity, aligned with its GPS points. Returns None on error.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_activity_streams(
+        api_key: String,
+        activity_id: String,
+        stream_types: Vec<crate::StreamType>,
+        config: Option<crate::FetcherConfig>,
+    ) -> Option<crate::ActivityStreams> {
+        init_logging();
+        let config = config.unwrap_or_default();
+        match crate::http::fetch_activity_streams_sync(&api_key, &config, &activity_id, &stream_types) {
+            Ok(streams) => {
+                info!("[RouteMatcherRust] fetch_activity_streams {}: {} points", activity_id, streams.points.len());
+                Some(streams)
+            }
+            Err(e) => {
+                info!("[RouteMatcherRust] fetch_activity_streams {} failed: {}", activity_id, e);
+                None
+            }
+        }
+    }
+
+    /// Result of fetch_and_process_activities
+    #[cfg(feature = "http")]
+    #[derive(Debug, Clone, uniffi::Record)]
+    pub struct FetchAndProcessResult {
+        pub map_results: Vec<FfiActivityMapResult>,
+        pub signatures: Vec<RouteSignature>,
+    }
+
+    // ========================================================================
+    // Frequent Sections Detection
     // ========================================================================
 
     /// Input mapping activity IDs to sport types
@@ -1405,6 +3058,14 @@ mod ffi {
         pub sport_type: String,
     }
 
+    /// Input mapping activity IDs to per-point horizontal accuracy (meters),
+    /// aligned with the activity's coordinates
+    #[derive(Debug, Clone, uniffi::Record)]
+    pub struct ActivityAccuracy {
+        pub activity_id: String,
+        pub accuracy: Vec<f64>,
+    }
+
     /// Detect frequent sections from route signatures.
     /// Returns sections sorted by visit count (most visited first).
     #[uniffi::export]
@@ -1423,11 +3084,7 @@ mod ffi {
 
         let start = std::time::Instant::now();
 
-        // Convert sport types to HashMap
-        let sport_map: std::collections::HashMap<String, String> = sport_types
-            .into_iter()
-            .map(|st| (st.activity_id, st.sport_type))
-            .collect();
+        let sport_map = sport_types_by_id(sport_types);
 
         let sections = crate::sections::detect_frequent_sections(
             &signatures,
@@ -1446,202 +3103,1928 @@ mod ffi {
         sections
     }
 
-    /// Get default section detection configuration
-    #[uniffi::export]
-    pub fn default_section_config() -> crate::SectionConfig {
-        crate::SectionConfig::default()
-    }
-
-    /// Detect frequent sections from FULL GPS tracks.
-    /// Uses medoid-based algorithm to select actual GPS traces as representative polylines.
-    /// This produces smooth, natural section shapes that follow real roads.
-    ///
-    /// # Arguments
-    /// * `activity_ids` - List of activity IDs (in same order as coordinates)
-    /// * `all_coords` - Flat array of coordinates: [lat1, lng1, lat2, lng2, ...]
-    /// * `offsets` - Start offset for each activity in all_coords (length = activity count + 1)
-    /// * `sport_types` - Sport type for each activity
-    /// * `groups` - Route groups (for linking sections to routes)
-    /// * `config` - Section detection configuration
+    /// Detect frequent sections from full-resolution flat coordinate buffers.
+    /// Each track's coords array contains [lat1, lng1, lat2, lng2, ...].
+    /// Uses the consensus algorithm on full tracks, so section portions index
+    /// into the original tracks. Returns sections sorted by visit count.
     #[uniffi::export]
-    pub fn ffi_detect_sections_from_tracks(
-        activity_ids: Vec<String>,
-        all_coords: Vec<f64>,
-        offsets: Vec<u32>,
-        sport_types: Vec<ActivitySportType>,
+    pub fn ffi_detect_sections_from_flat_tracks(
+        tracks: Vec<FlatGpsTrack>,
         groups: Vec<RouteGroup>,
+        sport_types: Vec<ActivitySportType>,
         config: crate::SectionConfig,
     ) -> Vec<crate::FrequentSection> {
         init_logging();
         info!(
-            "[RouteMatcherRust] detect_sections_from_tracks: {} activities, {} coords",
-            activity_ids.len(),
-            all_coords.len() / 2
+            "[RouteMatcherRust] detect_sections_from_flat_tracks: {} tracks, {} sport types",
+            tracks.len(),
+            sport_types.len()
         );
 
         let start = std::time::Instant::now();
 
-        // Convert flat coordinates to tracks
-        let mut tracks: Vec<(String, Vec<GpsPoint>)> = Vec::with_capacity(activity_ids.len());
+        let tracks = tracks_from_flat(tracks);
 
-        for (i, activity_id) in activity_ids.iter().enumerate() {
-            let start_offset = offsets[i] as usize;
-            let end_offset = offsets.get(i + 1).map(|&o| o as usize).unwrap_or(all_coords.len() / 2);
+        let sport_map = sport_types_by_id(sport_types);
 
-            let mut points = Vec::with_capacity(end_offset - start_offset);
-            for j in start_offset..end_offset {
-                let coord_idx = j * 2;
-                if coord_idx + 1 < all_coords.len() {
-                    points.push(GpsPoint::new(all_coords[coord_idx], all_coords[coord_idx + 1]));
-                }
-            }
+        let sections = crate::sections::detect_sections_from_tracks(
+            &tracks,
+            &sport_map,
+            &groups,
+            &config,
+        );
 
-            if !points.is_empty() {
-                tracks.push((activity_id.clone(), points));
-            }
-        }
+        let elapsed = start.elapsed();
+        info!(
+            "[RouteMatcherRust] Found {} sections from full tracks in {:?}",
+            sections.len(),
+            elapsed
+        );
+
+        sections
+    }
+
+    /// `ffi_detect_sections_from_flat_tracks` with fixed-point polylines and
+    /// traces, for storing or drawing sections on memory-constrained devices.
+    #[uniffi::export]
+    pub fn ffi_detect_compact_sections_from_flat_tracks(
+        tracks: Vec<FlatGpsTrack>,
+        groups: Vec<RouteGroup>,
+        sport_types: Vec<ActivitySportType>,
+        config: crate::SectionConfig,
+    ) -> Vec<crate::CompactSection> {
+        ffi_detect_sections_from_flat_tracks(tracks, groups, sport_types, config)
+            .iter()
+            .map(crate::CompactSection::from)
+            .collect()
+    }
 
+    /// `ffi_detect_sections_from_flat_tracks` weighting consensus by per-point
+    /// horizontal accuracy, so imprecise tracks pull section polylines less.
+    #[uniffi::export]
+    pub fn ffi_detect_sections_from_flat_tracks_with_accuracy(
+        tracks: Vec<FlatGpsTrack>,
+        groups: Vec<RouteGroup>,
+        sport_types: Vec<ActivitySportType>,
+        accuracies: Vec<ActivityAccuracy>,
+        config: crate::SectionConfig,
+    ) -> Vec<crate::FrequentSection> {
+        init_logging();
         info!(
-            "[RouteMatcherRust] Converted to {} tracks with full GPS data",
-            tracks.len()
+            "[RouteMatcherRust] detect_sections_from_flat_tracks_with_accuracy: {} tracks, {} with accuracy",
+            tracks.len(),
+            accuracies.len()
         );
 
-        // Convert sport types to HashMap
-        let sport_map: std::collections::HashMap<String, String> = sport_types
+        let start = std::time::Instant::now();
+
+        let tracks = tracks_from_flat(tracks);
+
+        let sport_map = sport_types_by_id(sport_types);
+
+        let accuracy_map: std::collections::HashMap<String, Vec<f64>> = accuracies
             .into_iter()
-            .map(|st| (st.activity_id, st.sport_type))
+            .map(|a| (a.activity_id, a.accuracy))
             .collect();
 
-        let sections = crate::sections::detect_sections_from_tracks(
+        let sections = crate::sections::detect_sections_from_tracks_with_accuracy(
             &tracks,
             &sport_map,
             &groups,
             &config,
+            &accuracy_map,
         );
 
-        let elapsed = start.elapsed();
         info!(
-            "[RouteMatcherRust] Found {} sections (medoid-based) in {:?}",
+            "[RouteMatcherRust] Found {} accuracy-weighted sections in {:?}",
             sections.len(),
-            elapsed
+            start.elapsed()
+        );
+
+        sections
+    }
+
+    /// `ffi_detect_sections_from_flat_tracks` within memory and thread limits.
+    /// Section detection needs every track at once, so if the tracks exceed
+    /// `limits.max_points_in_memory` they are thinned evenly to fit, trading
+    /// some section precision for memory.
+    #[uniffi::export]
+    pub fn ffi_detect_sections_from_flat_tracks_with_limits(
+        tracks: Vec<FlatGpsTrack>,
+        groups: Vec<RouteGroup>,
+        sport_types: Vec<ActivitySportType>,
+        config: crate::SectionConfig,
+        limits: crate::ResourceLimits,
+    ) -> Vec<crate::FrequentSection> {
+        init_logging();
+        let total_points: usize = tracks.iter().map(|t| t.coords.len() / 2).sum();
+        let stride = limits.stride(total_points);
+        info!(
+            "[RouteMatcherRust] detect_sections_from_flat_tracks with limits {:?}: {} tracks, {} points, stride {}",
+            limits, tracks.len(), total_points, stride
+        );
+
+        let start = std::time::Instant::now();
+
+        // Consume the flat buffers one by one so they're freed as we go
+        let tracks: Vec<(String, Vec<GpsPoint>)> = tracks
+            .into_iter()
+            .map(|track| {
+                let points = crate::limits::points_from_flat(&track.coords, stride);
+                (track.activity_id, points)
+            })
+            .collect();
+
+        let sport_map = sport_types_by_id(sport_types);
+
+        let sections = limits.install(|| {
+            crate::sections::detect_sections_from_tracks(&tracks, &sport_map, &groups, &config)
+        });
+
+        info!(
+            "[RouteMatcherRust] Found {} sections with limits in {:?}",
+            sections.len(),
+            start.elapsed()
+        );
+
+        sections
+    }
+
+    /// Carry section IDs over from a previous run by geometry, so names and
+    /// stars assigned by the user survive recomputation.
+    #[uniffi::export]
+    pub fn ffi_reconcile_section_ids(
+        previous: Vec<crate::FrequentSection>,
+        current: Vec<crate::FrequentSection>,
+        config: crate::SectionConfig,
+    ) -> Vec<crate::FrequentSection> {
+        init_logging();
+        let mut current = current;
+        crate::sections::reconcile_section_ids(&previous, &mut current, &config);
+        info!(
+            "[RouteMatcherRust] reconcile_section_ids: {} previous, {} current sections",
+            previous.len(),
+            current.len()
+        );
+        current
+    }
+
+    /// Serialize sections to JSON. Returns None on failure.
+    #[cfg(feature = "persist")]
+    #[uniffi::export]
+    pub fn ffi_sections_to_json(sections: Vec<crate::FrequentSection>) -> Option<String> {
+        let mut buffer = Vec::new();
+        crate::sections::save_sections(&sections, &mut buffer).ok()?;
+        String::from_utf8(buffer).ok()
+    }
+
+    /// Deserialize sections written by `ffi_sections_to_json`. Returns None on failure.
+    #[cfg(feature = "persist")]
+    #[uniffi::export]
+    pub fn ffi_sections_from_json(json: String) -> Option<Vec<crate::FrequentSection>> {
+        init_logging();
+        match crate::sections::load_sections(json.as_bytes()) {
+            Ok(sections) => Some(sections),
+            Err(e) => {
+                info!("[RouteMatcherRust] {}", e);
+                None
+            }
+        }
+    }
+
+    /// Detect frequent sections from full-resolution flat coordinate buffers,
+    /// reporting progress to `callback`. Same input and output as
+    /// `ffi_detect_sections_from_flat_tracks`.
+    #[uniffi::export]
+    pub fn ffi_detect_sections_with_progress(
+        tracks: Vec<FlatGpsTrack>,
+        groups: Vec<RouteGroup>,
+        sport_types: Vec<ActivitySportType>,
+        config: crate::SectionConfig,
+        callback: Box<dyn SectionProgressCallback>,
+    ) -> Vec<crate::FrequentSection> {
+        use std::sync::Arc;
+
+        init_logging();
+        info!(
+            "[RouteMatcherRust] detect_sections_with_progress: {} tracks, {} sport types",
+            tracks.len(),
+            sport_types.len()
+        );
+
+        let start = std::time::Instant::now();
+
+        let tracks = tracks_from_flat(tracks);
+
+        let sport_map = sport_types_by_id(sport_types);
+
+        // Wrap the callback to match the expected type
+        let callback = Arc::new(callback);
+        let progress_callback: crate::SectionProgress = Arc::new(move |phase, percent| {
+            callback.on_progress(phase, percent);
+        });
+
+        let sections = crate::sections::detect_sections_from_tracks_with_progress(
+            &tracks,
+            &sport_map,
+            &groups,
+            &config,
+            Some(progress_callback),
+        );
+
+        info!(
+            "[RouteMatcherRust] Found {} sections with progress in {:?}",
+            sections.len(),
+            start.elapsed()
+        );
+
+        sections
+    }
+
+    /// Detect frequent sections from full-resolution flat coordinate buffers,
+    /// reporting progress and stopping early if `token` is cancelled.
+    /// Returns None if cancelled.
+    #[uniffi::export]
+    pub fn ffi_detect_sections_cancellable(
+        tracks: Vec<FlatGpsTrack>,
+        groups: Vec<RouteGroup>,
+        sport_types: Vec<ActivitySportType>,
+        config: crate::SectionConfig,
+        callback: Box<dyn SectionProgressCallback>,
+        token: std::sync::Arc<FfiCancellationToken>,
+    ) -> Option<Vec<crate::FrequentSection>> {
+        use std::sync::Arc;
+
+        init_logging();
+        info!(
+            "[RouteMatcherRust] detect_sections_cancellable: {} tracks, {} sport types",
+            tracks.len(),
+            sport_types.len()
+        );
+
+        let start = std::time::Instant::now();
+
+        let tracks = tracks_from_flat(tracks);
+
+        let sport_map = sport_types_by_id(sport_types);
+
+        let callback = Arc::new(callback);
+        let progress_callback: crate::SectionProgress = Arc::new(move |phase, percent| {
+            callback.on_progress(phase, percent);
+        });
+
+        let sections = crate::sections::detect_sections_from_tracks_cancellable(
+            &tracks,
+            &sport_map,
+            &groups,
+            &config,
+            Some(progress_callback),
+            &token.token,
+        );
+
+        match &sections {
+            Some(sections) => info!("[RouteMatcherRust] Found {} sections in {:?}", sections.len(), start.elapsed()),
+            None => info!("[RouteMatcherRust] Section detection cancelled after {:?}", start.elapsed()),
+        }
+
+        sections
+    }
+
+    /// Get default section detection configuration
+    #[uniffi::export]
+    pub fn default_section_config() -> crate::SectionConfig {
+        crate::SectionConfig::default()
+    }
+
+    /// Detect frequent sections from FULL GPS tracks.
+    /// Uses medoid-based algorithm to select actual GPS traces as representative polylines.
+    /// This produces smooth, natural section shapes that follow real roads.
+    ///
+    /// # Arguments
+    /// * `activity_ids` - List of activity IDs (in same order as coordinates)
+    /// * `all_coords` - Flat array of coordinates: [lat1, lng1, lat2, lng2, ...]
+    /// * `offsets` - Start offset for each activity in all_coords (length = activity count + 1)
+    /// * `sport_types` - Sport type for each activity
+    /// * `groups` - Route groups (for linking sections to routes)
+    /// * `config` - Section detection configuration
+    #[uniffi::export]
+    pub fn ffi_detect_sections_from_tracks(
+        activity_ids: Vec<String>,
+        all_coords: Vec<f64>,
+        offsets: Vec<u32>,
+        sport_types: Vec<ActivitySportType>,
+        groups: Vec<RouteGroup>,
+        config: crate::SectionConfig,
+    ) -> Vec<crate::FrequentSection> {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] detect_sections_from_tracks: {} activities, {} coords",
+            activity_ids.len(),
+            all_coords.len() / 2
+        );
+
+        let start = std::time::Instant::now();
+
+        // Convert flat coordinates to tracks
+        let mut tracks: Vec<(String, Vec<GpsPoint>)> = Vec::with_capacity(activity_ids.len());
+
+        for (i, activity_id) in activity_ids.iter().enumerate() {
+            let start_offset = offsets[i] as usize;
+            let end_offset = offsets.get(i + 1).map(|&o| o as usize).unwrap_or(all_coords.len() / 2);
+
+            let mut points = Vec::with_capacity(end_offset - start_offset);
+            for j in start_offset..end_offset {
+                let coord_idx = j * 2;
+                if coord_idx + 1 < all_coords.len() {
+                    points.push(GpsPoint::new(all_coords[coord_idx], all_coords[coord_idx + 1]));
+                }
+            }
+
+            if !points.is_empty() {
+                tracks.push((activity_id.clone(), points));
+            }
+        }
+
+        info!(
+            "[RouteMatcherRust] Converted to {} tracks with full GPS data",
+            tracks.len()
+        );
+
+        let sport_map = sport_types_by_id(sport_types);
+
+        let sections = crate::sections::detect_sections_from_tracks(
+            &tracks,
+            &sport_map,
+            &groups,
+            &config,
+        );
+
+        let elapsed = start.elapsed();
+        info!(
+            "[RouteMatcherRust] Found {} sections (medoid-based) in {:?}",
+            sections.len(),
+            elapsed
+        );
+
+        sections
+    }
+
+    /// Fetch map data AND create route signatures in one call.
+    /// Most efficient for initial sync - fetches from API and processes GPS data.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_and_process_activities(
+        api_key: String,
+        activity_ids: Vec<String>,
+        config: MatchConfig,
+    ) -> FetchAndProcessResult {
+        init_logging();
+        info!("[RouteMatcherRust] fetch_and_process_activities for {} activities", activity_ids.len());
+
+        let start = std::time::Instant::now();
+
+        // Fetch all activity maps
+        let results = crate::http::fetch_activity_maps_sync(api_key, activity_ids, None, None);
+
+        // Convert to FFI format and create signatures from successful fetches
+        let mut map_results = Vec::with_capacity(results.len());
+        let mut signatures = Vec::new();
+
+        for r in results {
+            let bounds_vec = r.bounds.as_ref().map_or(vec![], |b| {
+                vec![b.ne[0], b.ne[1], b.sw[0], b.sw[1]]
+            });
+
+            let latlngs_flat: Vec<f64> = r.latlngs.as_ref().map_or(vec![], |coords| {
+                coords.iter().flat_map(|p| vec![p[0], p[1]]).collect()
+            });
+
+            // Create signature if we have GPS data
+            if r.success && r.latlngs.is_some() {
+                let points: Vec<GpsPoint> = r.latlngs.as_ref().unwrap()
+                    .iter()
+                    .map(|p| GpsPoint::new(p[0], p[1]))
+                    .collect();
+
+                if let Some(sig) = RouteSignature::from_points(&r.activity_id, &points, &config) {
+                    signatures.push(sig);
+                }
+            }
+
+            map_results.push(FfiActivityMapResult {
+                activity_id: r.activity_id,
+                bounds: bounds_vec,
+                latlngs: latlngs_flat,
+                success: r.success,
+                error: r.error,
+                error_kind: r.error_kind,
+                retries: r.retries,
+            });
+        }
+
+        let elapsed = start.elapsed();
+        info!("[RouteMatcherRust] Fetched {} activities, created {} signatures in {:?}",
+              map_results.len(), signatures.len(), elapsed);
+
+        FetchAndProcessResult { map_results, signatures }
+    }
+
+    /// Stage reported by `fetch_and_process_all`
+    #[cfg(feature = "http")]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+    pub enum SyncPhase {
+        Fetching,
+        Signatures,
+        Grouping,
+        Sections,
+        Heatmap,
+    }
+
+    /// Callback interface for progress of `fetch_and_process_all`.
+    #[cfg(feature = "http")]
+    #[uniffi::export(callback_interface)]
+    pub trait SyncProgressCallback: Send + Sync {
+        /// Called as each phase progresses. Fetching counts activities,
+        /// Sections counts percent (total 100), and the other phases report
+        /// 0/total when they start and total/total when they finish.
+        fn on_progress(&self, phase: SyncPhase, completed: u32, total: u32);
+    }
+
+    /// What `fetch_and_process_all` should compute after fetching.
+    #[cfg(feature = "http")]
+    #[derive(Debug, Clone, uniffi::Record)]
+    pub struct SyncOptions {
+        pub match_config: MatchConfig,
+        /// Default: intervals.icu rate limits
+        pub fetcher_config: Option<crate::FetcherConfig>,
+        /// Sport type per activity, for sections and heatmap filters
+        pub sport_types: Vec<ActivitySportType>,
+        /// Group signatures into routes (always done when detecting sections)
+        pub group_routes: bool,
+        /// Detect frequent sections with this config; None to skip
+        pub section_config: Option<crate::SectionConfig>,
+        /// Generate a heatmap from the full tracks with this config; None to skip
+        pub heatmap_config: Option<crate::HeatmapConfig>,
+    }
+
+    /// Everything produced by `fetch_and_process_all`. Phases after a
+    /// cancellation are left empty.
+    #[cfg(feature = "http")]
+    #[derive(Debug, Clone, uniffi::Record)]
+    pub struct SyncResult {
+        pub map_results: Vec<FfiActivityMapResult>,
+        pub signatures: Vec<RouteSignature>,
+        pub groups: Vec<RouteGroup>,
+        pub sections: Vec<crate::FrequentSection>,
+        pub heatmap: Option<crate::HeatmapResult>,
+        pub cancelled: bool,
+    }
+
+    /// Fetch map data and run signatures, grouping, section detection and
+    /// heatmap generation in one call, as selected by `options`. Sections and
+    /// the heatmap use the full-resolution tracks. Progress of every phase
+    /// goes to `callback`; cancelling `token` stops at the next phase
+    /// boundary (or sooner, inside fetching, grouping, sections and heatmap).
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn fetch_and_process_all(
+        api_key: String,
+        activity_ids: Vec<String>,
+        options: SyncOptions,
+        callback: Option<Box<dyn SyncProgressCallback>>,
+        token: Option<std::sync::Arc<FfiCancellationToken>>,
+    ) -> SyncResult {
+        use std::sync::Arc;
+
+        init_logging();
+        info!(
+            "[RouteMatcherRust] fetch_and_process_all for {} activities (groups: {}, sections: {}, heatmap: {})",
+            activity_ids.len(),
+            options.group_routes,
+            options.section_config.is_some(),
+            options.heatmap_config.is_some()
+        );
+
+        let start = std::time::Instant::now();
+        let callback = callback.map(Arc::new);
+        let report = |phase: SyncPhase, completed: usize, total: usize| {
+            if let Some(ref callback) = callback {
+                callback.on_progress(phase, completed as u32, total as u32);
+            }
+        };
+        let cancel = token.map(|t| t.token.clone()).unwrap_or_default();
+        let mut result = SyncResult {
+            map_results: Vec::new(),
+            signatures: Vec::new(),
+            groups: Vec::new(),
+            sections: Vec::new(),
+            heatmap: None,
+            cancelled: false,
+        };
+
+        // Phase 1: fetch
+        let fetch_progress: Option<crate::http::ProgressCallback> = callback.clone().map(|callback| {
+            Arc::new(move |completed, total| callback.on_progress(SyncPhase::Fetching, completed, total))
+                as crate::http::ProgressCallback
+        });
+        let fetched = crate::http::fetch_activity_maps_sync_with_config(
+            api_key,
+            &options.fetcher_config.clone().unwrap_or_default(),
+            activity_ids,
+            fetch_progress,
+            Some(cancel.clone()),
+        );
+        let tracks: Vec<(String, Vec<GpsPoint>)> = fetched
+            .iter()
+            .filter(|r| r.success)
+            .filter_map(|r| {
+                let points = r.latlngs.as_ref()?.iter().map(|p| GpsPoint::new(p[0], p[1])).collect();
+                Some((r.activity_id.clone(), points))
+            })
+            .collect();
+        result.map_results = fetched.into_iter().map(FfiActivityMapResult::from).collect();
+        if cancel.is_cancelled() {
+            result.cancelled = true;
+            return result;
+        }
+
+        // Phase 2: signatures
+        report(SyncPhase::Signatures, 0, tracks.len());
+        let config = &options.match_config;
+        #[cfg(feature = "parallel")]
+        let signatures: Vec<RouteSignature> = {
+            use rayon::prelude::*;
+            tracks.par_iter().filter_map(|(id, points)| RouteSignature::from_points(id, points, config)).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let signatures: Vec<RouteSignature> =
+            tracks.iter().filter_map(|(id, points)| RouteSignature::from_points(id, points, config)).collect();
+        report(SyncPhase::Signatures, tracks.len(), tracks.len());
+        result.signatures = signatures;
+
+        // Phase 3: grouping
+        if options.group_routes || options.section_config.is_some() {
+            report(SyncPhase::Grouping, 0, result.signatures.len());
+            match group_signatures_cancellable(&result.signatures, config, &cancel) {
+                Some(groups) => result.groups = groups,
+                None => {
+                    result.cancelled = true;
+                    return result;
+                }
+            }
+            report(SyncPhase::Grouping, result.signatures.len(), result.signatures.len());
+        }
+
+        let sport_map = sport_types_by_id(options.sport_types);
+
+        // Phase 4: sections
+        if let Some(ref section_config) = options.section_config {
+            let section_progress: crate::SectionProgress = {
+                let callback = callback.clone();
+                Arc::new(move |_phase, percent| {
+                    if let Some(ref callback) = callback {
+                        callback.on_progress(SyncPhase::Sections, percent, 100);
+                    }
+                })
+            };
+            match crate::sections::detect_sections_from_tracks_cancellable(
+                &tracks,
+                &sport_map,
+                &result.groups,
+                section_config,
+                Some(section_progress),
+                &cancel,
+            ) {
+                Some(sections) => result.sections = sections,
+                None => {
+                    result.cancelled = true;
+                    return result;
+                }
+            }
+        }
+
+        // Phase 5: heatmap
+        if let Some(ref heatmap_config) = options.heatmap_config {
+            report(SyncPhase::Heatmap, 0, tracks.len());
+            let route_of: HashMap<&str, &str> = result
+                .groups
+                .iter()
+                .flat_map(|g| g.activity_ids.iter().map(move |id| (id.as_str(), g.group_id.as_str())))
+                .collect();
+            let activity_data: HashMap<String, crate::ActivityHeatmapData> = tracks
+                .iter()
+                .map(|(id, _)| {
+                    let data = crate::ActivityHeatmapData {
+                        activity_id: id.clone(),
+                        route_id: route_of.get(id.as_str()).map(|r| r.to_string()),
+                        route_name: None,
+                        timestamp: None,
+                        sport_type: sport_map.get(id).cloned(),
+                    };
+                    (id.clone(), data)
+                })
+                .collect();
+            match crate::generate_heatmap_from_tracks_cancellable(&tracks, &activity_data, heatmap_config, &cancel) {
+                Some(heatmap) => result.heatmap = Some(heatmap),
+                None => {
+                    result.cancelled = true;
+                    return result;
+                }
+            }
+            report(SyncPhase::Heatmap, tracks.len(), tracks.len());
+        }
+
+        info!(
+            "[RouteMatcherRust] fetch_and_process_all: {} fetched, {} signatures, {} groups, {} sections in {:?}",
+            result.map_results.len(),
+            result.signatures.len(),
+            result.groups.len(),
+            result.sections.len(),
+            start.elapsed()
+        );
+
+        result
+    }
+
+    /// Receives each chunk of `sync_in_chunks`. Return false to stop after
+    /// this chunk.
+    #[cfg(feature = "http")]
+    #[uniffi::export(callback_interface)]
+    pub trait SyncChunkCallback: Send + Sync {
+        fn on_chunk(&self, chunk: crate::SyncChunk) -> bool;
+    }
+
+    /// Fetch and group activities `chunk_size` at a time, so memory stays
+    /// bounded and progress can be saved between chunks (e.g. within an iOS
+    /// background task). `callback` receives each chunk's new signatures and
+    /// the groups so far. Returns the final groups, or an empty list if the
+    /// fetcher couldn't start.
+    #[cfg(feature = "http")]
+    #[uniffi::export]
+    pub fn sync_in_chunks(
+        api_key: String,
+        activity_ids: Vec<String>,
+        chunk_size: u32,
+        config: MatchConfig,
+        fetcher_config: Option<crate::FetcherConfig>,
+        callback: Box<dyn SyncChunkCallback>,
+        token: Option<std::sync::Arc<FfiCancellationToken>>,
+    ) -> Vec<RouteGroup> {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] sync_in_chunks for {} activities, {} per chunk",
+            activity_ids.len(),
+            chunk_size
+        );
+
+        let result = crate::http::sync_in_chunks(
+            &api_key,
+            &fetcher_config.unwrap_or_default(),
+            activity_ids,
+            chunk_size as usize,
+            &config,
+            token.map(|t| t.token.clone()),
+            |chunk| callback.on_chunk(chunk),
+        );
+
+        match result {
+            Ok(groups) => groups,
+            Err(e) => {
+                info!("[RouteMatcherRust] sync_in_chunks failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    // ========================================================================
+    // Heatmap Generation FFI
+    // ========================================================================
+
+    /// Generate a heatmap from route signatures.
+    /// Uses the simplified GPS traces (~100 points each) for efficient generation.
+    #[uniffi::export]
+    pub fn ffi_generate_heatmap(
+        signatures: Vec<RouteSignature>,
+        activity_data: Vec<crate::ActivityHeatmapData>,
+        config: crate::HeatmapConfig,
+    ) -> crate::HeatmapResult {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] generate_heatmap: {} signatures, {}m cells",
+            signatures.len(),
+            config.cell_size_meters
+        );
+
+        let start = std::time::Instant::now();
+
+        // Convert Vec to HashMap for efficient lookup
+        let data_map: std::collections::HashMap<String, crate::ActivityHeatmapData> =
+            activity_data.into_iter()
+                .map(|d| (d.activity_id.clone(), d))
+                .collect();
+
+        let result = crate::generate_heatmap(&signatures, &data_map, &config);
+
+        let elapsed = start.elapsed();
+        info!(
+            "[RouteMatcherRust] Heatmap generated: {} cells, {} routes, {} activities in {:?}",
+            result.cells.len(),
+            result.total_routes,
+            result.total_activities,
+            elapsed
+        );
+
+        result
+    }
+
+    /// Generate a heatmap from route signatures, stopping early if `token` is cancelled.
+    /// Returns None if cancelled.
+    #[uniffi::export]
+    pub fn ffi_generate_heatmap_cancellable(
+        signatures: Vec<RouteSignature>,
+        activity_data: Vec<crate::ActivityHeatmapData>,
+        config: crate::HeatmapConfig,
+        token: std::sync::Arc<FfiCancellationToken>,
+    ) -> Option<crate::HeatmapResult> {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] generate_heatmap_cancellable: {} signatures, {}m cells",
+            signatures.len(),
+            config.cell_size_meters
+        );
+
+        let data_map: std::collections::HashMap<String, crate::ActivityHeatmapData> =
+            activity_data.into_iter()
+                .map(|d| (d.activity_id.clone(), d))
+                .collect();
+
+        crate::generate_heatmap_cancellable(&signatures, &data_map, &config, &token.token)
+    }
+
+    /// Generate a heatmap from full-resolution flat coordinate buffers.
+    /// Each track's coords array contains [lat1, lng1, lat2, lng2, ...].
+    /// More accurate than `ffi_generate_heatmap` on frequently ridden roads,
+    /// which simplified signatures under-represent.
+    #[uniffi::export]
+    pub fn ffi_generate_heatmap_from_flat(
+        tracks: Vec<FlatGpsTrack>,
+        activity_data: Vec<crate::ActivityHeatmapData>,
+        config: crate::HeatmapConfig,
+    ) -> crate::HeatmapResult {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] generate_heatmap_from_flat: {} tracks, {}m cells",
+            tracks.len(),
+            config.cell_size_meters
+        );
+
+        let start = std::time::Instant::now();
+
+        let tracks = tracks_from_flat(tracks);
+
+        let data_map: std::collections::HashMap<String, crate::ActivityHeatmapData> =
+            activity_data.into_iter()
+                .map(|d| (d.activity_id.clone(), d))
+                .collect();
+
+        let result = crate::generate_heatmap_from_tracks(&tracks, &data_map, &config);
+
+        let elapsed = start.elapsed();
+        info!(
+            "[RouteMatcherRust] Heatmap generated from flat tracks: {} cells, {} routes, {} activities in {:?}",
+            result.cells.len(),
+            result.total_routes,
+            result.total_activities,
+            elapsed
+        );
+
+        result
+    }
+
+    /// `ffi_generate_heatmap_from_flat` with per-cell average speed and power
+    /// from each activity's streams (index-aligned with its coords).
+    #[uniffi::export]
+    pub fn ffi_generate_heatmap_with_intensity(
+        tracks: Vec<FlatGpsTrack>,
+        activity_data: Vec<crate::ActivityHeatmapData>,
+        streams: Vec<crate::IntensityStreams>,
+        config: crate::HeatmapConfig,
+    ) -> crate::HeatmapResult {
+        init_logging();
+        info!(
+            "[RouteMatcherRust] generate_heatmap_with_intensity: {} tracks, {} with streams, {}m cells",
+            tracks.len(),
+            streams.len(),
+            config.cell_size_meters
+        );
+
+        let tracks = tracks_from_flat(tracks);
+        let data_map: std::collections::HashMap<String, crate::ActivityHeatmapData> =
+            activity_data.into_iter()
+                .map(|d| (d.activity_id.clone(), d))
+                .collect();
+        let stream_map: std::collections::HashMap<String, crate::IntensityStreams> =
+            streams.into_iter()
+                .map(|s| (s.activity_id.clone(), s))
+                .collect();
+
+        crate::generate_heatmap_with_intensity(&tracks, &data_map, &stream_map, &config)
+    }
+
+    /// Cells of `ffi_generate_heatmap_from_flat` with fixed-point centers and
+    /// without drill-down lists, for rendering on memory-constrained devices.
+    /// Tap queries need the full heatmap from `ffi_generate_heatmap_from_flat`.
+    #[uniffi::export]
+    pub fn ffi_generate_compact_heatmap_from_flat(
+        tracks: Vec<FlatGpsTrack>,
+        activity_data: Vec<crate::ActivityHeatmapData>,
+        config: crate::HeatmapConfig,
+    ) -> Vec<crate::CompactHeatmapCell> {
+        crate::compact::compact_heatmap_cells(&ffi_generate_heatmap_from_flat(tracks, activity_data, config))
+    }
+
+    /// Derive a filtered heatmap (time range, sport types, activity IDs)
+    /// from a full one without re-binning.
+    #[uniffi::export]
+    pub fn ffi_filter_heatmap(
+        heatmap: crate::HeatmapResult,
+        activity_data: Vec<crate::ActivityHeatmapData>,
+        config: crate::HeatmapConfig,
+    ) -> crate::HeatmapResult {
+        init_logging();
+        let data_map: std::collections::HashMap<String, crate::ActivityHeatmapData> =
+            activity_data.into_iter()
+                .map(|d| (d.activity_id.clone(), d))
+                .collect();
+
+        let result = crate::filter_heatmap(&heatmap, &data_map, &config);
+        info!(
+            "[RouteMatcherRust] filter_heatmap: {} -> {} cells, {} activities",
+            heatmap.cells.len(),
+            result.cells.len(),
+            result.total_activities
+        );
+        result
+    }
+
+    /// Query the heatmap at a specific location.
+    #[uniffi::export]
+    pub fn ffi_query_heatmap_cell(
+        heatmap: crate::HeatmapResult,
+        lat: f64,
+        lng: f64,
+    ) -> Option<crate::CellQueryResult> {
+        crate::query_heatmap_cell(&heatmap, lat, lng, heatmap.cell_size_meters)
+    }
+
+    /// Heatmap handle with constant-time cell lookup, built once per heatmap
+    /// and kept on the native side for repeated tap queries.
+    #[derive(uniffi::Object)]
+    pub struct FfiHeatmapIndex {
+        index: crate::HeatmapIndex,
+    }
+
+    #[uniffi::export]
+    impl FfiHeatmapIndex {
+        #[uniffi::constructor]
+        pub fn new(heatmap: crate::HeatmapResult) -> std::sync::Arc<Self> {
+            init_logging();
+            info!("[RouteMatcherRust] Indexing heatmap with {} cells", heatmap.cells.len());
+            std::sync::Arc::new(Self { index: crate::HeatmapIndex::new(heatmap) })
+        }
+
+        /// Query the cell at a location.
+        pub fn query_cell(&self, lat: f64, lng: f64) -> Option<crate::CellQueryResult> {
+            self.index.query(lat, lng)
+        }
+
+        /// The indexed heatmap.
+        pub fn heatmap(&self) -> crate::HeatmapResult {
+            self.index.heatmap().clone()
+        }
+
+        /// Number of cells in the heatmap.
+        pub fn cell_count(&self) -> u32 {
+            self.index.heatmap().cells.len() as u32
+        }
+
+        /// Up to `limit` cells starting at `offset`, for paging through large heatmaps.
+        pub fn cells(&self, offset: u32, limit: u32) -> Vec<HeatmapCell> {
+            page(&self.index.heatmap().cells, offset, limit)
+        }
+
+        /// Stream all cells to `sink` in chunks of `chunk_size`.
+        /// Returns the number of cells delivered.
+        pub fn stream_cells(&self, chunk_size: u32, sink: Box<dyn HeatmapCellSink>) -> u32 {
+            let cells = &self.index.heatmap().cells;
+            stream_pages(chunk_size, |offset, limit| page(cells, offset, limit), |chunk| sink.on_cells(chunk))
+        }
+    }
+
+    /// Aggregate visits, routes and activities inside a polygon drawn by the user.
+    #[uniffi::export]
+    pub fn ffi_query_heatmap_region(
+        heatmap: crate::HeatmapResult,
+        polygon: Vec<GpsPoint>,
+    ) -> crate::RegionQueryResult {
+        crate::query_heatmap_region(&heatmap, &polygon)
+    }
+
+    /// Get default heatmap configuration.
+    #[uniffi::export]
+    pub fn default_heatmap_config() -> crate::HeatmapConfig {
+        crate::HeatmapConfig::default()
+    }
+
+    // ========================================================================
+    // Analytics FFI
+    // ========================================================================
+
+    /// Compute the Eddington number (km or miles) from activity distances and dates.
+    #[uniffi::export]
+    pub fn ffi_eddington_number(
+        activities: Vec<crate::analytics::ActivitySummary>,
+        unit: crate::analytics::DistanceUnit,
+        utc_offset_seconds: i64,
+    ) -> crate::analytics::EddingtonResult {
+        init_logging();
+        let result = crate::analytics::eddington_number(&activities, unit, utc_offset_seconds);
+        info!("[RouteMatcherRust] eddington_number: E={} from {} activities", result.number, activities.len());
+        result
+    }
+
+    /// Cumulative distance, count and date range per route group.
+    #[uniffi::export]
+    pub fn ffi_distance_by_group(
+        groups: Vec<RouteGroup>,
+        activities: Vec<crate::analytics::ActivitySummary>,
+    ) -> Vec<crate::analytics::GroupDistanceStats> {
+        crate::analytics::distance_by_group(&groups, &activities)
+    }
+
+    /// Per-section traversal counts bucketed by day, week, month or year.
+    #[uniffi::export]
+    pub fn ffi_section_traversals_over_time(
+        sections: Vec<crate::FrequentSection>,
+        activities: Vec<crate::analytics::ActivitySummary>,
+        bucket: crate::analytics::TimeBucket,
+        utc_offset_seconds: i64,
+    ) -> Vec<crate::analytics::SectionTraversalHistory> {
+        crate::analytics::section_traversals_over_time(&sections, &activities, bucket, utc_offset_seconds)
+    }
+
+    // ========================================================================
+    // Sketch Search FFI
+    // ========================================================================
+
+    /// Activities following a polyline drawn on the map, best match first.
+    #[uniffi::export]
+    pub fn ffi_find_routes_similar_to(
+        polyline: Vec<GpsPoint>,
+        signatures: Vec<RouteSignature>,
+        config: MatchConfig,
+    ) -> Vec<crate::SketchMatch> {
+        init_logging();
+        let matches = crate::find_routes_similar_to(&polyline, &signatures, &config);
+        info!(
+            "[RouteMatcherRust] find_routes_similar_to: {} matches from {} signatures",
+            matches.len(),
+            signatures.len()
+        );
+        matches
+    }
+
+    // ========================================================================
+    // Explorer Tiles FFI
+    // ========================================================================
+
+    /// Compute explorer-tile statistics (total tiles, max square, max cluster)
+    /// from route signatures. Zooms above 24 are clamped to 24.
+    #[uniffi::export]
+    pub fn ffi_exploration_from_signatures(
+        signatures: Vec<RouteSignature>,
+        zoom: u32,
+    ) -> crate::ExplorationStats {
+        init_logging();
+        let stats = crate::exploration_from_signatures(&signatures, zoom);
+        info!(
+            "[RouteMatcherRust] exploration: {} tiles, max square {}, max cluster {}",
+            stats.total_tiles, stats.max_square_size, stats.max_cluster_size
+        );
+        stats
+    }
+
+    /// Compute explorer-tile statistics from full GPS tracks (flat coordinate buffers).
+    /// Zooms above 24 are clamped to 24.
+    #[uniffi::export]
+    pub fn ffi_exploration_from_tracks(tracks: Vec<FlatGpsTrack>, zoom: u32) -> crate::ExplorationStats {
+        init_logging();
+        let tracks = tracks_from_flat(tracks);
+        let stats = crate::exploration_from_tracks(&tracks, zoom);
+        info!(
+            "[RouteMatcherRust] exploration: {} tracks, {} tiles, max square {}, max cluster {}",
+            tracks.len(), stats.total_tiles, stats.max_square_size, stats.max_cluster_size
+        );
+        stats
+    }
+
+    /// Split a heatmap into slippy-map tiles (z/x/y) for tile overlay sources.
+    #[uniffi::export]
+    pub fn ffi_heatmap_to_tiles(
+        heatmap: crate::HeatmapResult,
+        zoom: u32,
+    ) -> Vec<crate::heatmap::tiles::HeatmapTile> {
+        init_logging();
+        let tiles = crate::heatmap::tiles::heatmap_to_tiles(&heatmap, zoom);
+        info!(
+            "[RouteMatcherRust] heatmap_to_tiles: {} cells -> {} tiles at z{}",
+            heatmap.cells.len(),
+            tiles.len(),
+            zoom
+        );
+        tiles
+    }
+
+    /// Get the heatmap cells for a single slippy-map tile.
+    /// Zooms above 24 have no tiles and return None.
+    #[uniffi::export]
+    pub fn ffi_heatmap_tile(
+        heatmap: crate::HeatmapResult,
+        z: u32,
+        x: u32,
+        y: u32,
+    ) -> Option<crate::heatmap::tiles::HeatmapTile> {
+        crate::heatmap::tiles::heatmap_tile(&heatmap, crate::heatmap::tiles::TileCoord { z, x, y })
+    }
+
+    /// Frequency isolines of a heatmap as polygons, one band per threshold
+    /// (e.g. visited 5+, 20+, 100+ times).
+    #[uniffi::export]
+    pub fn ffi_heatmap_contours(
+        heatmap: crate::HeatmapResult,
+        thresholds: Vec<u32>,
+    ) -> Vec<crate::heatmap::contour::ContourBand> {
+        init_logging();
+        let bands = crate::heatmap::contour::heatmap_contours(&heatmap, &thresholds);
+        info!(
+            "[RouteMatcherRust] heatmap_contours: {} cells, {} bands, {} polygons",
+            heatmap.cells.len(),
+            bands.len(),
+            bands.iter().map(|b| b.polygons.len()).sum::<usize>()
+        );
+        bands
+    }
+
+    /// Encode the heatmap cells of one tile as a Mapbox Vector Tile (`heatmap` layer).
+    /// Zooms above 24 return an empty tile.
+    #[cfg(feature = "mvt")]
+    #[uniffi::export]
+    pub fn ffi_heatmap_mvt_tile(heatmap: crate::HeatmapResult, z: u32, x: u32, y: u32) -> Vec<u8> {
+        crate::mvt::encode_heatmap_tile(&heatmap, crate::heatmap::tiles::TileCoord { z, x, y })
+    }
+
+    /// Encode section polylines of one tile as a Mapbox Vector Tile (`sections` layer).
+    /// Zooms above 24 return an empty tile.
+    #[cfg(feature = "mvt")]
+    #[uniffi::export]
+    pub fn ffi_sections_mvt_tile(sections: Vec<crate::FrequentSection>, z: u32, x: u32, y: u32) -> Vec<u8> {
+        crate::mvt::encode_sections_tile(&sections, crate::heatmap::tiles::TileCoord { z, x, y })
+    }
+
+    /// Render a heatmap to PNG bytes for sharing/export.
+    /// Returns None if encoding fails.
+    #[cfg(feature = "render")]
+    #[uniffi::export]
+    pub fn ffi_render_heatmap_png(
+        heatmap: crate::HeatmapResult,
+        config: crate::heatmap::render::HeatmapRenderConfig,
+    ) -> Option<Vec<u8>> {
+        init_logging();
+        match crate::heatmap::render::render_heatmap_png(&heatmap, &config) {
+            Ok(bytes) => {
+                info!("[RouteMatcherRust] render_heatmap_png: {} cells -> {} bytes", heatmap.cells.len(), bytes.len());
+                Some(bytes)
+            }
+            Err(e) => {
+                log::warn!("[RouteMatcherRust] render_heatmap_png failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Get default heatmap render configuration.
+    #[cfg(feature = "render")]
+    #[uniffi::export]
+    pub fn default_heatmap_render_config() -> crate::heatmap::render::HeatmapRenderConfig {
+        crate::heatmap::render::HeatmapRenderConfig::default()
+    }
+
+    // ========================================================================
+    // Auto-Naming FFI
+    // ========================================================================
+
+    /// Fill `suggested_name` of sections from user-defined places and named roads.
+    #[uniffi::export]
+    pub fn ffi_name_sections(
+        sections: Vec<crate::FrequentSection>,
+        places: Vec<crate::naming::NamedPlace>,
+        roads: Vec<crate::naming::NamedRoad>,
+    ) -> Vec<crate::FrequentSection> {
+        init_logging();
+        let geocoder = crate::naming::OfflineGeocoder::new(places, roads);
+        let mut sections = sections;
+        crate::naming::name_sections(&mut sections, &geocoder);
+        info!(
+            "[RouteMatcherRust] name_sections: named {} of {} sections",
+            sections.iter().filter(|s| s.suggested_name.is_some()).count(),
+            sections.len()
+        );
+        sections
+    }
+
+    /// Suggested route group names (group ID -> name) from user-defined places
+    /// and named roads. Groups without a suggestion are left out.
+    #[uniffi::export]
+    pub fn ffi_suggest_route_names(
+        groups: Vec<RouteGroup>,
+        signatures: Vec<RouteSignature>,
+        places: Vec<crate::naming::NamedPlace>,
+        roads: Vec<crate::naming::NamedRoad>,
+    ) -> std::collections::HashMap<String, String> {
+        init_logging();
+        let geocoder = crate::naming::OfflineGeocoder::new(places, roads);
+        crate::naming::suggest_route_names(&groups, &signatures, &geocoder)
+    }
+
+    /// Fill unnamed route names of a heatmap (user-defined names are kept).
+    #[uniffi::export]
+    pub fn ffi_apply_route_names(
+        heatmap: crate::HeatmapResult,
+        names: std::collections::HashMap<String, String>,
+    ) -> crate::HeatmapResult {
+        let mut heatmap = heatmap;
+        crate::naming::apply_route_names(&mut heatmap, &names);
+        heatmap
+    }
+
+    // ========================================================================
+    // Turn Detection FFI
+    // ========================================================================
+
+    /// Significant direction changes along a track, e.g. for cue sheets.
+    #[uniffi::export]
+    pub fn ffi_detect_turns(points: Vec<GpsPoint>, min_angle_degrees: f64) -> Vec<crate::Turn> {
+        crate::geo_utils::detect_turns(&points, min_angle_degrees)
+    }
+
+    // ========================================================================
+    // Polyline Utilities FFI
+    // ========================================================================
+
+    /// Resample a polyline to exactly `count` points evenly spaced by distance.
+    #[uniffi::export]
+    pub fn ffi_resample_polyline(points: Vec<GpsPoint>, count: u32) -> Vec<GpsPoint> {
+        crate::geo_utils::resample_polyline(&points, count as usize)
+    }
+
+    /// Resample a polyline to a point every `spacing_meters`.
+    #[uniffi::export]
+    pub fn ffi_resample_by_distance(points: Vec<GpsPoint>, spacing_meters: f64) -> Vec<GpsPoint> {
+        crate::geo_utils::resample_by_distance(&points, spacing_meters)
+    }
+
+    /// Smooth a polyline with a moving average over `window` points, for display.
+    #[uniffi::export]
+    pub fn ffi_smooth_polyline(points: Vec<GpsPoint>, window: u32) -> Vec<GpsPoint> {
+        crate::geo_utils::smooth_polyline(&points, window)
+    }
+
+    /// Simplify a polyline with a Douglas-Peucker tolerance in meters.
+    #[uniffi::export]
+    pub fn ffi_simplify_polyline(points: Vec<GpsPoint>, tolerance_meters: f64) -> Vec<GpsPoint> {
+        crate::geo_utils::simplify_polyline(&points, tolerance_meters)
+    }
+
+    // ========================================================================
+    // Indoor Detection FFI
+    // ========================================================================
+
+    /// Get default virtual/indoor activity detection configuration.
+    #[uniffi::export]
+    pub fn default_indoor_config() -> crate::IndoorConfig {
+        crate::IndoorConfig::default()
+    }
+
+    /// Classify an activity as virtual or indoor; `None` for outdoor activities.
+    #[uniffi::export]
+    pub fn ffi_detect_indoor(
+        points: Vec<GpsPoint>,
+        sport_type: Option<String>,
+        config: crate::IndoorConfig,
+    ) -> Option<crate::IndoorKind> {
+        crate::detect_indoor(&points, sport_type.as_deref(), &config)
+    }
+
+    // ========================================================================
+    // Section Splits FFI
+    // ========================================================================
+
+    /// Split an activity into its traversals of `sections`, with timing.
+    /// `times` holds one timestamp (seconds) per point.
+    #[uniffi::export]
+    pub fn ffi_split_by_sections(
+        sections: Vec<crate::FrequentSection>,
+        points: Vec<GpsPoint>,
+        times: Vec<i64>,
+        config: crate::SectionConfig,
+    ) -> Vec<crate::SectionTraversal> {
+        init_logging();
+        match crate::split_by_sections(&sections, &points, &times, &config) {
+            Ok(traversals) => {
+                info!(
+                    "[RouteMatcherRust] split_by_sections: {} traversals of {} sections",
+                    traversals.len(),
+                    sections.len()
+                );
+                traversals
+            }
+            Err(e) => {
+                info!("[RouteMatcherRust] split_by_sections failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Duration, average speed and rank of every activity's effort over a
+    /// section, fastest first. Streams must be aligned with the full tracks.
+    #[uniffi::export]
+    pub fn ffi_compare_section_efforts(
+        section: crate::FrequentSection,
+        streams: Vec<crate::EffortStreams>,
+    ) -> Vec<crate::SectionEffort> {
+        crate::compare_section_efforts(&section, &streams)
+    }
+
+    /// Grade-adjusted (runs) or normalized (rides) speed in m/s over a whole
+    /// activity. None without distance and altitude streams or for other sports.
+    #[uniffi::export]
+    pub fn ffi_adjusted_speed(streams: crate::EffortStreams, sport_type: String) -> Option<f64> {
+        crate::adjusted_speed(&streams, &sport_type)
+    }
+
+    // ========================================================================
+    // Route Library FFI
+    // ========================================================================
+
+    /// Route library handle owning signatures, groups, sections and heatmaps,
+    /// so apps add and remove activities instead of orchestrating each step.
+    #[derive(uniffi::Object)]
+    pub struct FfiRouteLibrary {
+        library: std::sync::Mutex<crate::RouteLibrary>,
+    }
+
+    #[uniffi::export]
+    impl FfiRouteLibrary {
+        #[uniffi::constructor]
+        pub fn new(match_config: MatchConfig, section_config: crate::SectionConfig) -> std::sync::Arc<Self> {
+            init_logging();
+            std::sync::Arc::new(Self {
+                library: std::sync::Mutex::new(crate::RouteLibrary::new(match_config, section_config)),
+            })
+        }
+
+        /// Add (or replace) an activity. Returns false if the track is too short.
+        pub fn add_activity(&self, activity_id: String, points: Vec<GpsPoint>, sport_type: String, timestamp: Option<i64>) -> bool {
+            let mut library = self.library.lock().unwrap();
+            let added = library.add_activity(&activity_id, points, &sport_type, timestamp);
+            info!("[RouteMatcherRust] RouteLibrary add {}: {} ({} activities)", activity_id, added, library.len());
+            added
+        }
+
+        /// Remove an activity. Returns false if it wasn't in the library.
+        pub fn remove_activity(&self, activity_id: String) -> bool {
+            self.library.lock().unwrap().remove_activity(&activity_id)
+        }
+
+        /// Add a batch of new or changed activities as flat coordinate arrays.
+        ///
+        /// Only the delta crosses the bridge: existing signatures and groups stay
+        /// on the native side. Returns the route groups the added activities
+        /// ended up in, so the app can update just those.
+        pub fn add_flat_activities(
+            &self,
+            tracks: Vec<FlatGpsTrack>,
+            sport_types: std::collections::HashMap<String, String>,
+            timestamps: std::collections::HashMap<String, i64>,
+        ) -> Vec<RouteGroup> {
+            let start = std::time::Instant::now();
+            let mut library = self.library.lock().unwrap();
+            let count = tracks.len();
+
+            let mut added: Vec<String> = Vec::with_capacity(count);
+            for track in tracks {
+                let points = track.points();
+                let sport_type = sport_types.get(&track.activity_id).map(String::as_str).unwrap_or("Unknown");
+                let timestamp = timestamps.get(&track.activity_id).copied();
+                if library.add_activity(&track.activity_id, points, sport_type, timestamp) {
+                    added.push(track.activity_id);
+                }
+            }
+
+            let mut changed: Vec<RouteGroup> = Vec::new();
+            for id in &added {
+                if let Some(group) = library.route_of(id) {
+                    if !changed.iter().any(|g| g.group_id == group.group_id) {
+                        changed.push(group.clone());
+                    }
+                }
+            }
+
+            info!(
+                "[RouteMatcherRust] RouteLibrary added {}/{} activities ({} total, {} groups changed) in {:?}",
+                added.len(), count, library.len(), changed.len(), start.elapsed()
+            );
+            changed
+        }
+
+        /// Remove a batch of activities. Returns how many were in the library.
+        pub fn remove_activities(&self, activity_ids: Vec<String>) -> u32 {
+            let mut library = self.library.lock().unwrap();
+            activity_ids.iter().filter(|id| library.remove_activity(id)).count() as u32
+        }
+
+        /// The route group an activity belongs to.
+        pub fn route_of(&self, activity_id: String) -> Option<RouteGroup> {
+            self.library.lock().unwrap().route_of(&activity_id).cloned()
+        }
+
+        /// Signature of an activity.
+        pub fn signature(&self, activity_id: String) -> Option<RouteSignature> {
+            self.library.lock().unwrap().signature(&activity_id).cloned()
+        }
+
+        /// Signature of an activity as a handle, without copying its points
+        /// across the bridge.
+        pub fn signature_handle(&self, activity_id: String) -> Option<std::sync::Arc<RouteSignatureHandle>> {
+            self.library.lock().unwrap().signature(&activity_id).cloned().map(RouteSignatureHandle::new)
+        }
+
+        /// Signature points of an activity as a flat `[lat, lng, ...]` array
+        /// (empty if unknown).
+        pub fn signature_coords_flat(&self, activity_id: String) -> Vec<f64> {
+            self.library
+                .lock()
+                .unwrap()
+                .signature(&activity_id)
+                .map(RouteSignature::flat_coords)
+                .unwrap_or_default()
+        }
+
+        /// Consensus polyline of a section as a flat `[lat, lng, ...]` array
+        /// (empty if unknown).
+        pub fn section_polyline_flat(&self, section_id: String) -> Vec<f64> {
+            let mut library = self.library.lock().unwrap();
+            library
+                .sections()
+                .iter()
+                .find(|s| s.id == section_id)
+                .map(|s| s.polyline.iter().flat_map(|p| [p.latitude, p.longitude]).collect())
+                .unwrap_or_default()
+        }
+
+        /// All signatures in one flat buffer with per-activity point offsets.
+        pub fn signatures_flat(&self) -> crate::FlatPolylines {
+            let library = self.library.lock().unwrap();
+            crate::FlatPolylines::from_polylines(
+                library.signatures().map(|s| (s.activity_id.as_str(), &s.points[..])),
+            )
+        }
+
+        /// All section polylines in one flat buffer with per-section point offsets.
+        pub fn sections_flat(&self) -> crate::FlatPolylines {
+            let mut library = self.library.lock().unwrap();
+            crate::FlatPolylines::from_polylines(
+                library.sections().iter().map(|s| (s.id.as_str(), &s.polyline[..])),
+            )
+        }
+
+        /// Number of activities.
+        pub fn activity_count(&self) -> u32 {
+            self.library.lock().unwrap().len() as u32
+        }
+
+        /// Route groups, oldest first.
+        pub fn routes(&self) -> Vec<RouteGroup> {
+            self.library.lock().unwrap().routes().to_vec()
+        }
+
+        /// Frequent sections (re-detected if activities changed).
+        pub fn sections(&self) -> Vec<crate::FrequentSection> {
+            self.library.lock().unwrap().sections().to_vec()
+        }
+
+        /// Heatmap for a web map zoom level.
+        pub fn heatmap(&self, zoom: u8) -> crate::HeatmapResult {
+            self.library.lock().unwrap().heatmap(zoom).clone()
+        }
+
+        /// Signatures in the order their activities were added, `limit` at a time.
+        pub fn signatures(&self, offset: u32, limit: u32) -> Vec<RouteSignature> {
+            self.library
+                .lock()
+                .unwrap()
+                .signatures()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect()
+        }
+
+        /// Route groups starting at `offset`, `limit` at a time.
+        pub fn routes_page(&self, offset: u32, limit: u32) -> Vec<RouteGroup> {
+            page(self.library.lock().unwrap().routes(), offset, limit)
+        }
+
+        /// Heatmap bounds and stats for a zoom level, without cells.
+        /// Fetch the cells with `heatmap_cells` or `stream_heatmap_cells`.
+        pub fn heatmap_summary(&self, zoom: u8) -> crate::HeatmapResult {
+            let mut library = self.library.lock().unwrap();
+            let heatmap = library.heatmap(zoom);
+            crate::HeatmapResult { cells: Vec::new(), bounds: heatmap.bounds.clone(), ..*heatmap }
+        }
+
+        /// Up to `limit` heatmap cells starting at `offset`.
+        pub fn heatmap_cells(&self, zoom: u8, offset: u32, limit: u32) -> Vec<HeatmapCell> {
+            page(&self.library.lock().unwrap().heatmap(zoom).cells, offset, limit)
+        }
+
+        /// Stream signatures to `sink` in chunks. The library is only locked
+        /// while copying each chunk, so the sink may call back into it.
+        /// Returns the number of signatures delivered.
+        pub fn stream_signatures(&self, chunk_size: u32, sink: Box<dyn SignatureSink>) -> u32 {
+            let start = std::time::Instant::now();
+            let count = stream_pages(chunk_size, |offset, limit| self.signatures(offset, limit), |chunk| sink.on_signatures(chunk));
+            info!("[RouteMatcherRust] Streamed {} signatures in {:?}", count, start.elapsed());
+            count
+        }
+
+        /// Stream heatmap cells to `sink` in chunks. The library is only
+        /// locked while copying each chunk. Returns the number of cells delivered.
+        pub fn stream_heatmap_cells(&self, zoom: u8, chunk_size: u32, sink: Box<dyn HeatmapCellSink>) -> u32 {
+            let start = std::time::Instant::now();
+            let count = stream_pages(chunk_size, |offset, limit| self.heatmap_cells(zoom, offset, limit), |chunk| sink.on_cells(chunk));
+            info!("[RouteMatcherRust] Streamed {} heatmap cells (zoom {}) in {:?}", count, zoom, start.elapsed());
+            count
+        }
+    }
+
+    // ========================================================================
+    // Places FFI
+    // ========================================================================
+
+    /// Get default place clustering config.
+    #[uniffi::export]
+    pub fn default_place_config() -> crate::places::PlaceConfig {
+        crate::places::PlaceConfig::default()
+    }
+
+    /// Cluster start and end points of route signatures into frequent places,
+    /// optionally named from user-defined places.
+    #[uniffi::export]
+    pub fn ffi_cluster_places(
+        signatures: Vec<RouteSignature>,
+        config: crate::places::PlaceConfig,
+        named_places: Vec<crate::naming::NamedPlace>,
+    ) -> Vec<crate::places::Place> {
+        init_logging();
+        let endpoints = crate::places::endpoints_from_signatures(&signatures);
+        let mut places = crate::places::cluster_places(&endpoints, &config);
+        if !named_places.is_empty() {
+            let geocoder = crate::naming::OfflineGeocoder::new(named_places, vec![]);
+            crate::places::name_places(&mut places, &geocoder);
+        }
+        info!(
+            "[RouteMatcherRust] cluster_places: {} places from {} activities",
+            places.len(),
+            signatures.len()
+        );
+        places
+    }
+
+    /// Activity IDs of signatures starting in `place` ("rides from home").
+    #[uniffi::export]
+    pub fn ffi_activities_from_place(
+        place: crate::places::Place,
+        signatures: Vec<RouteSignature>,
+    ) -> Vec<String> {
+        let endpoints = crate::places::endpoints_from_signatures(&signatures);
+        crate::places::activities_from_place(&place, &endpoints)
+    }
+
+    // ========================================================================
+    // Network FFI
+    // ========================================================================
+
+    /// Get default junction detection config.
+    #[uniffi::export]
+    pub fn default_network_config() -> crate::network::NetworkConfig {
+        crate::network::NetworkConfig::default()
+    }
+
+    /// Junctions where routes cross or diverge, busiest first.
+    #[uniffi::export]
+    pub fn ffi_detect_network_nodes(
+        signatures: Vec<RouteSignature>,
+        groups: Vec<RouteGroup>,
+        config: crate::network::NetworkConfig,
+    ) -> Vec<crate::network::NetworkNode> {
+        init_logging();
+        let nodes = crate::network::detect_network_nodes(&signatures, &groups, &config);
+        info!(
+            "[RouteMatcherRust] detect_network_nodes: {} nodes from {} signatures, {} groups",
+            nodes.len(),
+            signatures.len(),
+            groups.len()
+        );
+        nodes
+    }
+
+    /// Route network graph: junctions joined by frequent sections.
+    #[uniffi::export]
+    pub fn ffi_build_route_network(
+        signatures: Vec<RouteSignature>,
+        groups: Vec<RouteGroup>,
+        sections: Vec<crate::FrequentSection>,
+        config: crate::network::NetworkConfig,
+    ) -> crate::network::RouteNetwork {
+        init_logging();
+        let network = crate::network::build_route_network(&signatures, &groups, &sections, &config);
+        info!(
+            "[RouteMatcherRust] build_route_network: {} nodes, {} edges from {} sections",
+            network.nodes.len(),
+            network.edges.len(),
+            sections.len()
+        );
+        network
+    }
+
+    /// Route network as a GeoJSON FeatureCollection.
+    #[uniffi::export]
+    pub fn ffi_route_network_to_geojson(network: crate::network::RouteNetwork) -> String {
+        network.to_geojson()
+    }
+
+    /// Route network as a GraphML document.
+    #[uniffi::export]
+    pub fn ffi_route_network_to_graphml(network: crate::network::RouteNetwork) -> String {
+        network.to_graphml()
+    }
+
+    /// Get default route suggestion config.
+    #[uniffi::export]
+    pub fn default_suggestion_config() -> crate::network::SuggestionConfig {
+        crate::network::SuggestionConfig::default()
+    }
+
+    /// Loops of about `target_distance` meters through the route network from
+    /// near `start_point`, most familiar first.
+    #[uniffi::export]
+    pub fn ffi_suggest_routes(
+        network: crate::network::RouteNetwork,
+        target_distance: f64,
+        start_point: GpsPoint,
+        config: crate::network::SuggestionConfig,
+    ) -> Vec<crate::network::RouteSuggestion> {
+        init_logging();
+        let suggestions = network.suggest_routes(target_distance, &start_point, &config);
+        info!(
+            "[RouteMatcherRust] suggest_routes: {} loops of ~{:.0}m from {} edges",
+            suggestions.len(),
+            target_distance,
+            network.edges.len()
+        );
+        suggestions
+    }
+
+    // ========================================================================
+    // Export FFI
+    // ========================================================================
+
+    /// Get default FIT course config.
+    #[uniffi::export]
+    pub fn default_fit_course_config() -> crate::FitCourseConfig {
+        crate::FitCourseConfig::default()
+    }
+
+    /// FIT course file for a route polyline, to send to a head unit.
+    /// `elevations` (one per point) add climbs as course points; may be empty.
+    #[uniffi::export]
+    pub fn ffi_fit_course(polyline: Vec<GpsPoint>, elevations: Vec<f64>, config: crate::FitCourseConfig) -> Vec<u8> {
+        init_logging();
+        let bytes = crate::fit_course(&polyline, &elevations, &config);
+        info!("[RouteMatcherRust] fit_course: {} points -> {} bytes", polyline.len(), bytes.len());
+        bytes
+    }
+
+    /// Get default anonymized export config.
+    #[uniffi::export]
+    pub fn default_anonymize_config() -> crate::AnonymizeConfig {
+        crate::AnonymizeConfig::default()
+    }
+
+    /// Shareable tracks for frequent sections: name, sport and polyline only.
+    #[uniffi::export]
+    pub fn ffi_shared_tracks_for_sections(sections: Vec<crate::FrequentSection>) -> Vec<crate::SharedTrack> {
+        sections.iter().map(crate::SharedTrack::from).collect()
+    }
+
+    /// GPX for public sharing: no timestamps, privacy zones removed, coordinates rounded.
+    #[uniffi::export]
+    pub fn ffi_anonymized_gpx(tracks: Vec<crate::SharedTrack>, config: crate::AnonymizeConfig) -> String {
+        init_logging();
+        let gpx = crate::anonymized_gpx(&tracks, &config);
+        info!("[RouteMatcherRust] anonymized_gpx: {} tracks, {} zones -> {} bytes", tracks.len(), config.privacy_zones.len(), gpx.len());
+        gpx
+    }
+
+    /// GeoJSON for public sharing: no timestamps, privacy zones removed, coordinates rounded.
+    #[uniffi::export]
+    pub fn ffi_anonymized_geojson(tracks: Vec<crate::SharedTrack>, config: crate::AnonymizeConfig) -> String {
+        init_logging();
+        let geojson = crate::anonymized_geojson(&tracks, &config);
+        info!("[RouteMatcherRust] anonymized_geojson: {} tracks, {} zones -> {} bytes", tracks.len(), config.privacy_zones.len(), geojson.len());
+        geojson
+    }
+
+    // ========================================================================
+    // Deviation FFI
+    // ========================================================================
+
+    /// Get default off-route detection config.
+    #[uniffi::export]
+    pub fn default_deviation_config() -> crate::DeviationConfig {
+        crate::DeviationConfig::default()
+    }
+
+    /// Deviations of an activity from a planned route. `timestamps` (Unix
+    /// seconds, one per point) may be empty.
+    #[uniffi::export]
+    pub fn ffi_detect_deviations(
+        planned: Vec<GpsPoint>,
+        actual: Vec<GpsPoint>,
+        timestamps: Vec<i64>,
+        config: crate::DeviationConfig,
+    ) -> crate::DeviationReport {
+        init_logging();
+        let report = crate::detect_deviations(&planned, &actual, &timestamps, &config);
+        info!(
+            "[RouteMatcherRust] detect_deviations: {} deviations, {:.0}m off route",
+            report.deviations.len(),
+            report.off_route_meters
+        );
+        report
+    }
+
+    /// Coverage of a planned course by an activity, in any order or direction:
+    /// share ridden, missed and extra portions. The course counts as ridden
+    /// within `tolerance_meters` (50 if None).
+    #[uniffi::export]
+    pub fn ffi_coverage(
+        planned: Vec<GpsPoint>,
+        actual: Vec<GpsPoint>,
+        tolerance_meters: Option<f64>,
+    ) -> crate::CoverageReport {
+        init_logging();
+        let report = match tolerance_meters {
+            Some(tolerance) => crate::coverage_within(&planned, &actual, tolerance),
+            None => crate::coverage(&planned, &actual),
+        };
+        info!(
+            "[RouteMatcherRust] coverage: {:.1}% of {:.0}m, {} missed, {} extra",
+            report.covered_fraction * 100.0,
+            report.planned_meters,
+            report.missed.len(),
+            report.extra.len()
         );
+        report
+    }
 
-        sections
+    /// Off-route tracker handle for following a planned route live.
+    #[derive(uniffi::Object)]
+    pub struct FfiDeviationTracker {
+        tracker: std::sync::Mutex<crate::DeviationTracker>,
     }
 
-    /// Fetch map data AND create route signatures in one call.
-    /// Most efficient for initial sync - fetches from API and processes GPS data.
-    #[cfg(feature = "http")]
     #[uniffi::export]
-    pub fn fetch_and_process_activities(
-        api_key: String,
-        activity_ids: Vec<String>,
-        config: MatchConfig,
-    ) -> FetchAndProcessResult {
-        init_logging();
-        info!("[RouteMatcherRust] fetch_and_process_activities for {} activities", activity_ids.len());
+    impl FfiDeviationTracker {
+        #[uniffi::constructor]
+        pub fn new(planned: Vec<GpsPoint>, config: crate::DeviationConfig) -> std::sync::Arc<Self> {
+            init_logging();
+            let tracker = crate::DeviationTracker::new(&planned, config);
+            std::sync::Arc::new(Self { tracker: std::sync::Mutex::new(tracker) })
+        }
 
-        let start = std::time::Instant::now();
+        /// Add the next point. Returns an event when it confirms leaving or
+        /// rejoining the route.
+        pub fn push_point(&self, point: GpsPoint, timestamp: Option<i64>) -> Option<crate::deviation::DeviationEvent> {
+            self.tracker.lock().unwrap().push_point(point, timestamp)
+        }
 
-        // Fetch all activity maps
-        let results = crate::http::fetch_activity_maps_sync(api_key, activity_ids, None);
+        /// Deviations so far, including one still open.
+        pub fn report(&self) -> crate::DeviationReport {
+            self.tracker.lock().unwrap().report()
+        }
+    }
 
-        // Convert to FFI format and create signatures from successful fetches
-        let mut map_results = Vec::with_capacity(results.len());
-        let mut signatures = Vec::new();
+    // ========================================================================
+    // Live Matching FFI
+    // ========================================================================
 
-        for r in results {
-            let bounds_vec = r.bounds.as_ref().map_or(vec![], |b| {
-                vec![b.ne[0], b.ne[1], b.sw[0], b.sw[1]]
-            });
+    /// Live matcher handle for an activity being recorded, fed points as they
+    /// arrive.
+    #[derive(uniffi::Object)]
+    pub struct FfiLiveMatcher {
+        matcher: std::sync::Mutex<crate::LiveMatcher>,
+    }
 
-            let latlngs_flat: Vec<f64> = r.latlngs.as_ref().map_or(vec![], |coords| {
-                coords.iter().flat_map(|p| vec![p[0], p[1]]).collect()
-            });
+    #[uniffi::export]
+    impl FfiLiveMatcher {
+        #[uniffi::constructor]
+        pub fn new(
+            signatures: Vec<RouteSignature>,
+            groups: Vec<RouteGroup>,
+            sections: Vec<crate::FrequentSection>,
+            match_config: MatchConfig,
+            config: crate::LiveConfig,
+        ) -> std::sync::Arc<Self> {
+            init_logging();
+            info!(
+                "[RouteMatcherRust] LiveMatcher over {} groups, {} signatures, {} sections",
+                groups.len(),
+                signatures.len(),
+                sections.len()
+            );
+            let matcher = crate::LiveMatcher::new(&signatures, &groups, &sections, match_config, config);
+            std::sync::Arc::new(Self { matcher: std::sync::Mutex::new(matcher) })
+        }
 
-            // Create signature if we have GPS data
-            if r.success && r.latlngs.is_some() {
-                let points: Vec<GpsPoint> = r.latlngs.as_ref().unwrap()
-                    .iter()
-                    .map(|p| GpsPoint::new(p[0], p[1]))
-                    .collect();
+        /// Add the next recorded point. Returns the events it caused.
+        pub fn push_point(&self, point: GpsPoint) -> Vec<crate::LiveEvent> {
+            self.matcher.lock().unwrap().push_point(point)
+        }
 
-                if let Some(sig) = RouteSignature::from_points(&r.activity_id, &points, &config) {
-                    signatures.push(sig);
-                }
-            }
+        /// Add the next recorded point with its time (Unix seconds), for the
+        /// ETA. Returns the events it caused.
+        pub fn push_timed_point(&self, point: GpsPoint, timestamp: i64) -> Vec<crate::LiveEvent> {
+            self.matcher.lock().unwrap().push_timed_point(point, timestamp)
+        }
 
-            map_results.push(FfiActivityMapResult {
-                activity_id: r.activity_id,
-                bounds: bounds_vec,
-                latlngs: latlngs_flat,
-                success: r.success,
-                error: r.error,
-            });
+        /// Add several points in order, e.g. after the app was in the background.
+        pub fn push_points(&self, points: Vec<GpsPoint>) -> Vec<crate::LiveEvent> {
+            self.matcher.lock().unwrap().push_points(&points)
         }
 
-        let elapsed = start.elapsed();
-        info!("[RouteMatcherRust] Fetched {} activities, created {} signatures in {:?}",
-              map_results.len(), signatures.len(), elapsed);
+        /// Targets being followed, most probable first.
+        pub fn status(&self) -> Vec<crate::LiveTarget> {
+            self.matcher.lock().unwrap().status()
+        }
 
-        FetchAndProcessResult { map_results, signatures }
+        /// Forget the activity so far.
+        pub fn reset(&self) {
+            self.matcher.lock().unwrap().reset()
+        }
+    }
+
+    /// Get default live matching config.
+    #[uniffi::export]
+    pub fn default_live_config() -> crate::LiveConfig {
+        crate::LiveConfig::default()
     }
 
     // ========================================================================
-    // Heatmap Generation FFI
+    // Map Matching FFI
     // ========================================================================
 
-    /// Generate a heatmap from route signatures.
-    /// Uses the simplified GPS traces (~100 points each) for efficient generation.
+    /// Road graph handle, loaded once (e.g. from a downloaded regional extract)
+    /// and kept on the native side for matching many tracks.
+    #[cfg(feature = "map_match")]
+    #[derive(uniffi::Object)]
+    pub struct FfiRoadGraph {
+        graph: crate::map_match::RoadGraph,
+    }
+
+    #[cfg(feature = "map_match")]
     #[uniffi::export]
-    pub fn ffi_generate_heatmap(
-        signatures: Vec<RouteSignature>,
-        activity_data: Vec<crate::ActivityHeatmapData>,
-        config: crate::HeatmapConfig,
-    ) -> crate::HeatmapResult {
-        init_logging();
-        info!(
-            "[RouteMatcherRust] generate_heatmap: {} signatures, {}m cells",
-            signatures.len(),
-            config.cell_size_meters
-        );
+    impl FfiRoadGraph {
+        /// Number of road nodes.
+        pub fn node_count(&self) -> u32 {
+            self.graph.node_count() as u32
+        }
 
-        let start = std::time::Instant::now();
+        /// Number of road edges.
+        pub fn edge_count(&self) -> u32 {
+            self.graph.edge_count() as u32
+        }
 
-        // Convert Vec to HashMap for efficient lookup
-        let data_map: std::collections::HashMap<String, crate::ActivityHeatmapData> =
-            activity_data.into_iter()
-                .map(|d| (d.activity_id.clone(), d))
-                .collect();
+        /// Snap one track to the roads.
+        pub fn match_track(
+            &self,
+            points: Vec<GpsPoint>,
+            config: crate::map_match::MapMatchConfig,
+        ) -> crate::map_match::MapMatchResult {
+            crate::map_match::map_match(&self.graph, &points, &config)
+        }
 
-        let result = crate::generate_heatmap(&signatures, &data_map, &config);
+        /// Snap flat coordinate tracks to the roads, e.g. before section detection
+        /// or heatmap generation. Output coords are [lat1, lng1, lat2, lng2, ...].
+        pub fn match_tracks(
+            &self,
+            tracks: Vec<FlatGpsTrack>,
+            config: crate::map_match::MapMatchConfig,
+        ) -> Vec<FlatGpsTrack> {
+            init_logging();
+            let start = std::time::Instant::now();
 
-        let elapsed = start.elapsed();
-        info!(
-            "[RouteMatcherRust] Heatmap generated: {} cells, {} routes, {} activities in {:?}",
-            result.cells.len(),
-            result.total_routes,
-            result.total_activities,
-            elapsed
-        );
+            let tracks = tracks_from_flat(tracks);
 
-        result
+            let matched = crate::map_match::map_match_tracks(&self.graph, &tracks, &config);
+            info!("[RouteMatcherRust] map_match_tracks: {} tracks in {:?}", matched.len(), start.elapsed());
+
+            matched
+                .into_iter()
+                .map(|(activity_id, points)| FlatGpsTrack {
+                    activity_id,
+                    coords: points.iter().flat_map(|p| [p.latitude, p.longitude]).collect(),
+                })
+                .collect()
+        }
+
+        /// Road portions the heatmap never visits, for exploration.
+        pub fn unvisited_roads(
+            &self,
+            heatmap: crate::HeatmapResult,
+            config: crate::map_match::UnvisitedRoadsConfig,
+        ) -> Vec<crate::map_match::UnvisitedRoad> {
+            init_logging();
+            let index = crate::heatmap::HeatmapIndex::new(heatmap);
+            let roads = crate::map_match::unvisited_roads(&self.graph, &index, &config);
+            info!("[RouteMatcherRust] unvisited_roads: {} portions", roads.len());
+            roads
+        }
     }
 
-    /// Query the heatmap at a specific location.
+    /// Load a road graph from an OSM PBF extract.
+    /// Returns None if the data can't be parsed.
+    #[cfg(feature = "map_match")]
     #[uniffi::export]
-    pub fn ffi_query_heatmap_cell(
-        heatmap: crate::HeatmapResult,
-        lat: f64,
-        lng: f64,
-    ) -> Option<crate::CellQueryResult> {
-        crate::query_heatmap_cell(&heatmap, lat, lng, heatmap.cell_size_meters)
+    pub fn ffi_road_graph_from_pbf(data: Vec<u8>) -> Option<std::sync::Arc<FfiRoadGraph>> {
+        init_logging();
+        match crate::map_match::RoadGraph::from_osm_pbf(&data) {
+            Ok(graph) => {
+                info!(
+                    "[RouteMatcherRust] Loaded road graph: {} nodes, {} edges from {} bytes",
+                    graph.node_count(),
+                    graph.edge_count(),
+                    data.len()
+                );
+                Some(std::sync::Arc::new(FfiRoadGraph { graph }))
+            }
+            Err(e) => {
+                info!("[RouteMatcherRust] road_graph_from_pbf failed: {}", e);
+                None
+            }
+        }
     }
 
-    /// Get default heatmap configuration.
+    /// Build a road graph from a precomputed node list and flat edge list
+    /// [from1, to1, from2, to2, ...] of node indices.
+    /// Returns None if an edge refers to a missing node.
+    #[cfg(feature = "map_match")]
     #[uniffi::export]
-    pub fn default_heatmap_config() -> crate::HeatmapConfig {
-        crate::HeatmapConfig::default()
+    pub fn ffi_road_graph_from_edges(nodes: Vec<GpsPoint>, edges: Vec<u32>) -> Option<std::sync::Arc<FfiRoadGraph>> {
+        init_logging();
+        let edges: Vec<(u32, u32)> = edges.chunks_exact(2).map(|e| (e[0], e[1])).collect();
+        match crate::map_match::RoadGraph::new(nodes, &edges) {
+            Ok(graph) => Some(std::sync::Arc::new(FfiRoadGraph { graph })),
+            Err(e) => {
+                info!("[RouteMatcherRust] road_graph_from_edges failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Get default map matching configuration.
+    #[cfg(feature = "map_match")]
+    #[uniffi::export]
+    pub fn default_map_match_config() -> crate::map_match::MapMatchConfig {
+        crate::map_match::MapMatchConfig::default()
+    }
+
+    /// Get default unvisited roads configuration.
+    #[cfg(feature = "map_match")]
+    #[uniffi::export]
+    pub fn default_unvisited_roads_config() -> crate::map_match::UnvisitedRoadsConfig {
+        crate::map_match::UnvisitedRoadsConfig::default()
     }
 }
 
@@ -1649,17 +5032,67 @@ mod ffi {
 // Helper Functions
 // ============================================================================
 
-// Use shared bounds calculation from geo_utils
-fn calculate_bounds(points: &[GpsPoint]) -> (f64, f64, f64, f64) {
-    crate::geo_utils::compute_bounds_tuple(points)
+/// Whether signature creation keeps `point`: valid coordinates, not at
+/// (0, 0) where devices report before GPS lock.
+fn is_track_point(point: &GpsPoint) -> bool {
+    point.is_valid() && !preprocess::is_null_island(point)
+}
+
+/// Positions `i` in `points` where the jump to `points[i + 1]` exceeds
+/// [`MatchConfig::max_gap_meters`].
+fn gap_starts(points: &[GpsPoint], config: &MatchConfig) -> Vec<usize> {
+    let Some(max_gap) = config.max_gap_meters else {
+        return Vec::new();
+    };
+    let model = config.distance_model();
+    points
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| model.distance(&w[0], &w[1]) > max_gap)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Whether a route distance is usable as a divisor (rules out zero-length
+/// routes and NaN).
+fn is_positive_distance(distance: f64) -> bool {
+    distance.is_finite() && distance > 0.0
+}
+
+/// R-tree entries for signatures with valid bounds. Signatures left out can't
+/// match anything, so they end up in groups of their own.
+fn indexable_bounds<'a>(signatures: impl IntoIterator<Item = &'a RouteSignature>) -> Vec<RouteBounds> {
+    signatures.into_iter().filter(|s| s.bounds.is_valid()).map(|s| s.route_bounds()).collect()
 }
 
-fn distance_ratio_ok(d1: f64, d2: f64) -> bool {
+/// Whether two route lengths pass [`MatchConfig::min_distance_ratio`].
+fn distance_ratio_ok(d1: f64, d2: f64, config: &MatchConfig) -> bool {
     if d1 <= 0.0 || d2 <= 0.0 {
         return false;
     }
     let ratio = if d1 > d2 { d2 / d1 } else { d1 / d2 };
-    ratio >= 0.5
+    ratio >= config.min_distance_ratio
+}
+
+/// Route groups from Union-Find roots. An activity ID passed more than once
+/// is listed once, so a duplicated activity never forms a group with itself.
+fn build_groups<'a>(
+    parent: &mut HashMap<String, String>,
+    activity_ids: impl Iterator<Item = &'a String>,
+) -> Vec<RouteGroup> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for id in activity_ids {
+        if seen.insert(id.as_str()) {
+            let root = find(parent, id);
+            groups.entry(root).or_default().push(id.clone());
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(group_id, activity_ids)| RouteGroup { group_id, activity_ids })
+        .collect()
 }
 
 fn find(parent: &mut HashMap<String, String>, id: &str) -> String {
@@ -1717,6 +5150,219 @@ mod tests {
         assert!(sig.total_distance > 0.0);
     }
 
+    #[test]
+    fn test_signature_skips_null_island() {
+        let mut points = vec![GpsPoint::new(0.0, 0.0); 2];
+        points.extend(sample_route());
+        let config = MatchConfig::default();
+
+        let sig = RouteSignature::from_points("test-1", &points, &config).unwrap();
+        assert_eq!(sig.start_point, sample_route()[0]);
+        let streamed = RouteSignature::from_point_iter("test-1", points.iter().copied(), &config).unwrap();
+        assert_eq!(streamed.start_point, sample_route()[0]);
+    }
+
+    #[test]
+    fn test_signature_from_point_iter() {
+        let config = MatchConfig::default();
+
+        // Short tracks produce the same signature as from_points
+        let points = sample_route();
+        let from_slice = RouteSignature::from_points("a", &points, &config).unwrap();
+        let from_iter = RouteSignature::from_point_iter("a", points.iter().copied(), &config).unwrap();
+        assert_eq!(from_slice.points, from_iter.points);
+
+        // Long wiggly track spanning many chunks stays close to the full-vector result
+        let long: Vec<GpsPoint> = (0..20_000)
+            .map(|i| {
+                let t = i as f64 * 0.0005;
+                GpsPoint::new(51.5 + t * 0.01, -0.1278 + (t * 3.0).sin() * 0.01)
+            })
+            .collect();
+        let from_slice = RouteSignature::from_points("b", &long, &config).unwrap();
+        let from_iter = RouteSignature::from_point_iter("b", long.iter().copied(), &config).unwrap();
+        assert!(from_iter.points.len() <= config.max_simplified_points as usize);
+        assert_eq!(from_iter.start_point, long[0]);
+        assert_eq!(from_iter.end_point, long[long.len() - 1]);
+        let ratio = from_iter.total_distance / from_slice.total_distance;
+        assert!((ratio - 1.0).abs() < 0.05, "distance ratio {}", ratio);
+
+        // Invalid-only streams produce nothing
+        assert!(RouteSignature::from_point_iter("c", vec![GpsPoint::new(f64::NAN, 0.0); 5], &config).is_none());
+    }
+
+    #[test]
+    fn test_landmarks_survive_point_limit() {
+        let config = MatchConfig { max_simplified_points: 10, ..MatchConfig::default() };
+        let wiggly: Vec<GpsPoint> = (0..500)
+            .map(|i| GpsPoint::new(51.5 + i as f64 * 0.0002, -0.1 + (i as f64 * 0.3).sin() * 0.002))
+            .collect();
+        let landmarks = [37, 101, 250, 333, 499];
+
+        let signature = RouteSignature::from_points_with_landmarks("laps", &wiggly, &landmarks, &config).unwrap();
+        assert!(signature.points.len() <= 10);
+        for &i in &landmarks {
+            assert!(signature.points.contains(&wiggly[i]), "landmark {} dropped", i);
+        }
+        assert_eq!(signature.start_point, wiggly[0]);
+    }
+
+    #[test]
+    fn test_landmarks_ignore_invalid_and_stay_unsmoothed() {
+        let mut points = sample_route();
+        points.insert(2, GpsPoint::new(f64::NAN, 0.0));
+        let config = MatchConfig {
+            smoothing: Some(crate::TrackSmoothing::Median { window: 5 }),
+            ..MatchConfig::default()
+        };
+
+        let signature = RouteSignature::from_points_with_landmarks("r", &points, &[2, 4, 1_000], &config).unwrap();
+        assert!(signature.points.contains(&points[4]));
+        assert!(RouteSignature::from_points_with_landmarks("r", &points[..1], &[0], &config).is_none());
+    }
+
+    #[test]
+    fn test_gap_legs_are_kept_and_left_out_of_amd() {
+        // 2km north then 2km east; the second recording loses signal around
+        // the corner and jumps straight across it
+        let road: Vec<GpsPoint> = (0..40)
+            .map(|i| GpsPoint::new(51.5 + i as f64 * 0.0005, -0.1))
+            .chain((0..40).map(|i| GpsPoint::new(51.52, -0.1 + i as f64 * 0.0008)))
+            .collect();
+        let mut tunnel = road.clone();
+        tunnel.drain(30..50);
+
+        let base = MatchConfig { amd_to_polyline: true, min_match_percentage: 0.0, ..MatchConfig::default() };
+        let gap_config = MatchConfig { max_gap_meters: Some(300.0), ..base.clone() };
+
+        let sig = RouteSignature::from_points("tunnel", &tunnel, &gap_config).unwrap();
+        assert_eq!(sig.gap_legs.len(), 1);
+        let g = sig.gap_legs[0] as usize;
+        assert_eq!((sig.points[g], sig.points[g + 1]), (tunnel[29], tunnel[30]));
+        assert!(RouteSignature::from_points("tunnel", &tunnel, &base).unwrap().gap_legs.is_empty());
+
+        // The chord cutting the corner no longer counts against the match
+        let amd = |config: &MatchConfig| {
+            let a = RouteSignature::from_points("road", &road, config).unwrap();
+            let b = RouteSignature::from_points("tunnel", &tunnel, config).unwrap();
+            compare_routes(&a, &b, config).unwrap().amd
+        };
+        assert!(amd(&gap_config) < 5.0);
+        assert!(amd(&base) > amd(&gap_config) + 20.0);
+
+        let geodesic = MatchConfig { distance_model: Some(DistanceModel::Geodesic), ..gap_config.clone() };
+        assert!(amd(&geodesic) < 5.0);
+    }
+
+    #[test]
+    fn test_amd_follows_distance_model() {
+        // Two parallel roads ~35m apart
+        let road: Vec<GpsPoint> = (0..40).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0005, -0.1)).collect();
+        let parallel: Vec<GpsPoint> = road.iter().map(|p| GpsPoint::new(p.latitude, p.longitude + 0.0005)).collect();
+
+        let amd = |config: &MatchConfig| {
+            let a = RouteSignature::from_points("road", &road, config).unwrap();
+            let b = RouteSignature::from_points("parallel", &parallel, config).unwrap();
+            compare_routes(&a, &b, config).unwrap().amd
+        };
+        for amd_to_polyline in [false, true] {
+            let planar = MatchConfig { amd_to_polyline, ..MatchConfig::default() };
+            let geodesic = MatchConfig { distance_model: Some(DistanceModel::Geodesic), ..planar.clone() };
+            let (planar, geodesic) = (amd(&planar), amd(&geodesic));
+            assert!((planar - 34.7).abs() < 0.5);
+            assert!(geodesic != planar);
+            assert!((geodesic - planar).abs() / planar < 0.005);
+        }
+    }
+
+    #[test]
+    fn test_signature_with_smoothing() {
+        // Zig-zag jitter of ~15m around a straight line survives plain simplification
+        let jittery: Vec<GpsPoint> = (0..100)
+            .map(|i| {
+                let jitter = if i % 2 == 0 { 0.0002 } else { -0.0002 };
+                GpsPoint::new(51.5 + i as f64 * 0.0002, -0.1278 + jitter)
+            })
+            .collect();
+
+        let raw = RouteSignature::from_points("raw", &jittery, &MatchConfig::default()).unwrap();
+        let config = MatchConfig {
+            smoothing: Some(TrackSmoothing::Median { window: 5 }),
+            ..MatchConfig::default()
+        };
+        let smoothed = RouteSignature::from_points("smooth", &jittery, &config).unwrap();
+        let streamed = RouteSignature::from_point_iter("smooth", jittery.iter().copied(), &config).unwrap();
+
+        assert!(smoothed.points.len() < raw.points.len());
+        assert!(smoothed.total_distance < raw.total_distance);
+        assert_eq!(smoothed.points, streamed.points);
+    }
+
+    #[test]
+    fn test_thumbnail_points_budget() {
+        // Loop with plenty of corners
+        let points: Vec<GpsPoint> = (0..360)
+            .map(|deg| {
+                let r = 0.01 + (deg % 7) as f64 * 0.0005;
+                let a = (deg as f64).to_radians();
+                GpsPoint::new(51.5 + r * a.sin(), -0.1 + r * a.cos())
+            })
+            .collect();
+        let sig = RouteSignature::from_points("loop", &points, &MatchConfig::default()).unwrap();
+        assert!(sig.points.len() > 32);
+
+        for budget in [0, 2, 8, 32] {
+            let thumbnail = sig.thumbnail_points(budget);
+            assert!(thumbnail.len() <= budget.max(2) as usize);
+            assert_eq!(thumbnail.first(), Some(&sig.start_point));
+            assert_eq!(thumbnail.last(), Some(&sig.end_point));
+        }
+        assert_eq!(sig.thumbnail_points(u32::MAX), sig.points);
+    }
+
+    #[test]
+    fn test_simplified_for_zoom_coarsens() {
+        let sig = RouteSignature::from_points("r", &sample_route(), &MatchConfig::default()).unwrap();
+        let counts: Vec<usize> = [20, 14, 8, 2].iter().map(|&z| sig.simplified_for_zoom(z).len()).collect();
+        assert!(counts.windows(2).all(|w| w[1] <= w[0]));
+        assert_eq!(counts[3], 2);
+    }
+
+    #[test]
+    fn test_meter_tolerance_is_latitude_independent() {
+        // Same east-west zig-zag (in meters) at the equator and at 60°N
+        let zigzag = |lat: f64| -> Vec<GpsPoint> {
+            let lng_per_meter = 1.0 / (111_320.0 * lat.to_radians().cos());
+            (0..100)
+                .map(|i| {
+                    let wiggle = if i % 2 == 0 { 2.0 } else { -2.0 };
+                    GpsPoint::new(lat + i as f64 * 0.0005, 10.0 + wiggle * lng_per_meter)
+                })
+                .collect()
+        };
+        let meters = MatchConfig {
+            simplification_tolerance: 5.0,
+            simplification_unit: Some(ToleranceUnit::Meters),
+            ..MatchConfig::default()
+        };
+        let degrees = MatchConfig { simplification_tolerance: 5.0 / 111_320.0, ..MatchConfig::default() };
+
+        let count = |lat: f64, config: &MatchConfig| {
+            let points = zigzag(lat);
+            let sig = RouteSignature::from_points("z", &points, config).unwrap();
+            let streamed = RouteSignature::from_point_iter("z", points, config).unwrap();
+            assert_eq!(sig.points.len(), streamed.points.len());
+            sig.points.len()
+        };
+
+        // A 4m wiggle is within 5m everywhere
+        assert_eq!(count(0.0, &meters), 2);
+        assert_eq!(count(60.0, &meters), 2);
+        // In degrees the wiggle is twice as wide at 60°N and survives simplification
+        assert_eq!(count(0.0, &degrees), 2);
+        assert!(count(60.0, &degrees) > 50);
+    }
+
     #[test]
     fn test_identical_routes_match() {
         let points = sample_route();
@@ -1731,6 +5377,33 @@ mod tests {
         assert_eq!(result.direction, "same");
     }
 
+    #[test]
+    fn test_degenerate_routes_are_rejected() {
+        let config = MatchConfig::default();
+
+        // All points identical: no signature rather than a zero-length one
+        let parked = vec![GpsPoint::new(51.5, -0.1); 20];
+        assert!(RouteSignature::from_points("parked", &parked, &config).is_none());
+
+        // A signature never matches itself
+        let sig = RouteSignature::from_points("a", &sample_route(), &config).unwrap();
+        assert!(compare_routes(&sig, &sig, &config).is_none());
+
+        // Zero-length signatures built by hand don't produce NaN matches
+        let zero = RouteSignature { activity_id: "zero".to_string(), total_distance: 0.0, ..sig.clone() };
+        let zero2 = RouteSignature { activity_id: "zero2".to_string(), ..zero.clone() };
+        assert!(compare_routes(&zero, &zero2, &config).is_none());
+    }
+
+    #[test]
+    fn test_duplicate_ids_do_not_self_group() {
+        let config = MatchConfig::default();
+        let sig = RouteSignature::from_points("a", &sample_route(), &config).unwrap();
+        let groups = group_signatures(&[sig.clone(), sig], &config);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].activity_ids, vec!["a".to_string()]);
+    }
+
     #[test]
     fn test_reverse_routes_match() {
         let points = sample_route();
@@ -1772,4 +5445,156 @@ mod tests {
         assert!(!group_with_1.activity_ids.contains(&"test-3".to_string()));
     }
 
+    #[test]
+    fn test_high_resample_count_matches() {
+        // High resample counts (indexed AMD) give the same verdict as the default
+        let points: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278)).collect();
+        let config = MatchConfig { resample_count: 400, ..MatchConfig::default() };
+        let sig1 = RouteSignature::from_points("a", &points, &config).unwrap();
+        let sig2 = RouteSignature::from_points("b", &points, &config).unwrap();
+        assert_eq!(compare_routes(&sig1, &sig2, &config).unwrap().match_percentage, 100.0);
+    }
+
+    /// ~10km east-west route across the antimeridian on Taveuni, Fiji
+    fn fiji_route(lat_offset: f64) -> Vec<GpsPoint> {
+        (0..100)
+            .map(|i| GpsPoint::new(-16.8 + lat_offset, geo_utils::normalize_longitude(179.95 + i as f64 * 0.001)))
+            .collect()
+    }
+
+    #[test]
+    fn test_antimeridian_signature_bounds() {
+        let sig = RouteSignature::from_points("fiji", &fiji_route(0.0), &MatchConfig::default()).unwrap();
+
+        assert!(sig.bounds.crosses_antimeridian());
+        assert!((sig.bounds.min_lng - 179.95).abs() < 1e-6);
+        assert!((sig.bounds.max_lng - (-179.951)).abs() < 1e-6);
+        assert!(sig.center.longitude.abs() > 179.9);
+        assert!((sig.total_distance - 10_600.0).abs() < 200.0);
+
+        let halves = sig.bounds.split_at_antimeridian();
+        assert_eq!(halves.len(), 2);
+        assert_eq!(halves[0].max_lng, 180.0);
+        assert_eq!(halves[1].min_lng, -180.0);
+    }
+
+    #[test]
+    fn test_search_envelope_latitude_corrected() {
+        use rstar::Envelope;
+
+        // 0.01° of latitude (~1.1 km) is ~0.02° of longitude at 60°N
+        let north = Bounds { min_lat: 59.99, max_lat: 60.0, min_lng: 10.0, max_lng: 10.01 };
+        let envelope = search_envelope(&north, 0.01);
+        assert!(envelope.contains_point(&[10.0295, 59.995]));
+        assert!(!envelope.contains_point(&[10.031, 59.995]));
+        assert!(!envelope.contains_point(&[10.005, 60.011]));
+
+        let equator = Bounds { min_lat: 0.0, max_lat: 0.01, min_lng: 10.0, max_lng: 10.01 };
+        let envelope = search_envelope(&equator, 0.01);
+        assert!(envelope.contains_point(&[10.0199, 0.005]));
+        assert!(!envelope.contains_point(&[10.0201, 0.005]));
+    }
+
+    #[test]
+    fn test_min_distance_ratio_allows_long_variants() {
+        // Two and a bit laps of a narrow loop against one lap: same roads, 44% of the length
+        let up = (0..40).map(|i| GpsPoint::new(51.5 + i as f64 * 0.001, -0.1));
+        let down = (0..40).map(|i| GpsPoint::new(51.539 - i as f64 * 0.001, -0.1003));
+        let leg: Vec<GpsPoint> = up.chain(down).collect();
+        let mut laps: Vec<GpsPoint> = leg.iter().chain(&leg).copied().collect();
+        laps.extend(leg.iter().take(20).copied());
+
+        let config = MatchConfig { min_match_percentage: 0.0, ..MatchConfig::default() };
+        let short = RouteSignature::from_points("short", &leg, &config).unwrap();
+        let long = RouteSignature::from_points("long", &laps, &config).unwrap();
+        assert!(compare_routes(&short, &long, &config).is_none());
+
+        let relaxed = MatchConfig { min_distance_ratio: 0.4, ..config };
+        assert!(compare_routes(&short, &long, &relaxed).is_some());
+        assert_eq!(RouteIndex::from_signatures(vec![long]).find_matches(&short, &relaxed).len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_bounds_group_alone() {
+        let route: Vec<GpsPoint> = (0..10)
+            .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+            .collect();
+        let config = MatchConfig::default();
+        let a = RouteSignature::from_points("a", &route, &config).unwrap();
+        let b = RouteSignature::from_points("b", &route, &config).unwrap();
+        let mut broken = a.clone();
+        broken.activity_id = "broken".to_string();
+        broken.bounds.min_lat = f64::NAN;
+        assert!(!broken.bounds.is_valid());
+
+        let groups = group_signatures(&[a, broken, b], &config);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().any(|g| g.activity_ids == vec!["broken".to_string()]));
+    }
+
+    #[test]
+    fn test_group_signatures_by_sport() {
+        let route: Vec<GpsPoint> = (0..10)
+            .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+            .collect();
+        let config = MatchConfig::default();
+        let signatures: Vec<RouteSignature> = ["run-1", "run-2", "ride", "unknown"]
+            .iter()
+            .map(|id| RouteSignature::from_points(id, &route, &config).unwrap())
+            .collect();
+        let sports = HashMap::from([
+            ("run-1".to_string(), "Run".to_string()),
+            ("run-2".to_string(), "Run".to_string()),
+            ("ride".to_string(), "Ride".to_string()),
+        ]);
+
+        let groups = group_signatures_by_sport(&signatures, &sports, &config);
+        assert_eq!(groups.len(), 3);
+        let runs = groups.iter().find(|g| g.activity_ids.contains(&"run-1".to_string())).unwrap();
+        assert_eq!(runs.activity_ids.len(), 2);
+        assert!(runs.activity_ids.contains(&"run-2".to_string()));
+
+        let mixed = MatchConfig { cross_sport_grouping: true, ..config };
+        assert_eq!(group_signatures_by_sport(&signatures, &sports, &mixed).len(), 1);
+    }
+
+    #[test]
+    fn test_group_signatures_by_sport_excludes_indoor() {
+        let route: Vec<GpsPoint> = (0..10)
+            .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+            .collect();
+        let signatures: Vec<RouteSignature> = ["a", "b", "zwift"]
+            .iter()
+            .map(|id| RouteSignature::from_points(id, &route, &MatchConfig::default()).unwrap())
+            .collect();
+        let sports = HashMap::from([("zwift".to_string(), "VirtualRide".to_string())]);
+
+        let config = MatchConfig { exclude_indoor: true, cross_sport_grouping: true, ..MatchConfig::default() };
+        let groups = group_signatures_by_sport(&signatures, &sports, &config);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().any(|g| g.activity_ids == vec!["zwift".to_string()]));
+    }
+
+    #[test]
+    fn test_antimeridian_routes_match_and_group() {
+        let config = MatchConfig::default();
+        let sig1 = RouteSignature::from_points("fiji-1", &fiji_route(0.0), &config).unwrap();
+        let sig2 = RouteSignature::from_points("fiji-2", &fiji_route(0.0001), &config).unwrap();
+
+        // Resampling must not interpolate through 0° longitude
+        let resampled = geo_utils::resample_polyline(&sig1.points, 50);
+        assert!(resampled.iter().all(|p| p.longitude.abs() > 179.9));
+
+        let result = compare_routes(&sig1, &sig2, &config).unwrap();
+        assert!(result.match_percentage > 95.0, "match {}", result.match_percentage);
+
+        // A route just east of the antimeridian (Aleutians side) is found by the R-tree
+        let east_only: Vec<GpsPoint> = (0..60).map(|i| GpsPoint::new(-16.8, -179.999 + i as f64 * 0.0001)).collect();
+        let sig3 = RouteSignature::from_points("east", &east_only, &config).unwrap();
+
+        let groups = group_signatures(&[sig1, sig2, sig3], &config);
+        let fiji_group = groups.iter().find(|g| g.activity_ids.contains(&"fiji-1".to_string())).unwrap();
+        assert!(fiji_group.activity_ids.contains(&"fiji-2".to_string()));
+        assert!(!fiji_group.activity_ids.contains(&"east".to_string()));
+    }
 }
@@ -0,0 +1,362 @@
+//! # Route Library
+//!
+//! A single object owning everything derived from a user's activities —
+//! signatures, route groups, frequent sections and heatmaps — and keeping it
+//! consistent as activities are added and removed.
+//!
+//! Without it, apps orchestrate [`crate::RouteSignature::from_points`],
+//! [`crate::group_signatures`], [`crate::detect_sections_from_tracks`],
+//! [`crate::reconcile_section_ids`] and [`crate::generate_heatmap`] themselves
+//! and shuttle the intermediate state between calls.
+//!
+//! ## Consistency
+//!
+//! | Data | Update on add/remove |
+//! |------|----------------------|
+//! | Signatures | Immediate, via a [`RouteIndex`] |
+//! | Route groups | Immediate: a new activity joins (and merges) the groups it matches within its sport; removing one re-groups only its former group |
+//! | Sections | Recomputed on the next [`RouteLibrary::sections`] call, with IDs carried over from the previous result |
+//! | Heatmaps | Cached per zoom level, rebuilt on the next [`RouteLibrary::heatmap`] call |
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, RouteLibrary};
+//!
+//! let commute: Vec<GpsPoint> = (0..50)
+//!     .map(|i| GpsPoint::new(51.5 + i as f64 * 0.0005, -0.1))
+//!     .collect();
+//!
+//! let mut library = RouteLibrary::default();
+//! library.add_activity("monday", commute.clone(), "Ride", Some(1_700_000_000));
+//! library.add_activity("tuesday", commute.clone(), "Ride", Some(1_700_086_400));
+//!
+//! assert_eq!(library.routes().len(), 1);
+//! assert_eq!(library.routes()[0].activity_ids.len(), 2);
+//! assert!(!library.heatmap(14).cells.is_empty());
+//!
+//! library.remove_activity("tuesday");
+//! assert_eq!(library.routes()[0].activity_ids, vec!["monday".to_string()]);
+//! ```
+
+use std::collections::HashMap;
+use crate::{
+    ActivityHeatmapData, FrequentSection, GpsPoint, HeatmapConfig, HeatmapResult, IndoorConfig, MatchConfig,
+    RouteGroup, RouteIndex, RouteSignature, SectionConfig,
+};
+
+/// Heatmap cells span about this many screen pixels at the requested zoom
+const HEATMAP_CELL_PIXELS: f64 = 4.0;
+
+/// Activities, route groups, sections and heatmaps kept consistent together.
+#[derive(Debug, Default)]
+pub struct RouteLibrary {
+    match_config: MatchConfig,
+    section_config: SectionConfig,
+    index: RouteIndex,
+    /// Full-resolution tracks, needed for section detection
+    tracks: Vec<(String, Vec<GpsPoint>)>,
+    activity_data: HashMap<String, ActivityHeatmapData>,
+    groups: Vec<RouteGroup>,
+    /// `None` when activities changed since the last detection
+    sections: Option<Vec<FrequentSection>>,
+    previous_sections: Vec<FrequentSection>,
+    heatmaps: HashMap<u8, HeatmapResult>,
+}
+
+impl RouteLibrary {
+    /// Create an empty library.
+    pub fn new(match_config: MatchConfig, section_config: SectionConfig) -> Self {
+        Self {
+            match_config,
+            section_config,
+            ..Self::default()
+        }
+    }
+
+    /// Number of activities.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether the library has no activities.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Signature of an activity.
+    pub fn signature(&self, activity_id: &str) -> Option<&RouteSignature> {
+        self.index.get(activity_id)
+    }
+
+    /// Signatures in the order their activities were added.
+    pub fn signatures(&self) -> impl Iterator<Item = &RouteSignature> {
+        self.tracks.iter().filter_map(|(id, _)| self.index.get(id))
+    }
+
+    /// Add an activity, or replace one with the same ID.
+    ///
+    /// Returns `false` (and leaves the library unchanged) if the track is too
+    /// short to produce a signature.
+    pub fn add_activity(&mut self, activity_id: &str, points: Vec<GpsPoint>, sport_type: &str, timestamp: Option<i64>) -> bool {
+        let Some(signature) = RouteSignature::from_points(activity_id, &points, &self.match_config) else {
+            return false;
+        };
+        if self.index.get(activity_id).is_some() {
+            self.remove_activity(activity_id);
+        }
+
+        // Groups containing an activity this one should be grouped with;
+        // excluded indoor activities match nothing
+        let indoor = self.match_config.exclude_indoor
+            && crate::indoor::is_indoor(&signature.points, Some(sport_type), &IndoorConfig::default());
+        let matches = if indoor { Vec::new() } else { self.index.find_matches(&signature, &self.match_config) };
+        let mut joined: Vec<usize> = matches
+            .iter()
+            .filter(|m| self.match_config.cross_sport_grouping || self.sport_of(&m.activity_id_2) == Some(sport_type))
+            .filter(|m| !self.is_excluded_indoor(&m.activity_id_2))
+            .filter(|m| {
+                self.index
+                    .get(&m.activity_id_2)
+                    .is_some_and(|other| crate::should_group_routes(&signature, other, m, &self.match_config))
+            })
+            .filter_map(|m| self.group_index(&m.activity_id_2))
+            .collect();
+        joined.sort_unstable();
+        joined.dedup();
+
+        // Merge them into the first (oldest) one
+        let mut group = match joined.first() {
+            Some(&first) => self.groups[first].clone(),
+            None => RouteGroup { group_id: activity_id.to_string(), activity_ids: Vec::new() },
+        };
+        for &i in joined.iter().skip(1) {
+            group.activity_ids.extend(self.groups[i].activity_ids.iter().cloned());
+        }
+        group.activity_ids.push(activity_id.to_string());
+        for &i in joined.iter().rev() {
+            self.groups.remove(i);
+        }
+        self.groups.insert(joined.first().copied().unwrap_or(self.groups.len()), group);
+
+        self.index.insert(signature);
+        self.tracks.push((activity_id.to_string(), points));
+        self.activity_data.insert(
+            activity_id.to_string(),
+            ActivityHeatmapData {
+                activity_id: activity_id.to_string(),
+                route_id: None,
+                route_name: None,
+                timestamp,
+                sport_type: Some(sport_type.to_string()),
+            },
+        );
+        self.invalidate();
+        true
+    }
+
+    /// Remove an activity. Returns `false` if it wasn't in the library.
+    pub fn remove_activity(&mut self, activity_id: &str) -> bool {
+        if self.index.remove(activity_id).is_none() {
+            return false;
+        }
+        self.tracks.retain(|(id, _)| id != activity_id);
+        self.activity_data.remove(activity_id);
+
+        // The removed activity may have been the only link between others in
+        // its group, so re-group the remaining members
+        if let Some(i) = self.group_index(activity_id) {
+            let old = self.groups.remove(i);
+            let remaining: Vec<RouteSignature> = old
+                .activity_ids
+                .iter()
+                .filter(|id| *id != activity_id)
+                .filter_map(|id| self.index.get(id).cloned())
+                .collect();
+
+            let mut regrouped = crate::group_signatures(&remaining, &self.match_config);
+            for group in &mut regrouped {
+                if group.activity_ids.contains(&old.group_id) {
+                    group.group_id = old.group_id.clone();
+                }
+                // Keep the original join order within each group
+                group.activity_ids.sort_by_key(|id| old.activity_ids.iter().position(|o| o == id));
+            }
+            regrouped.sort_by_key(|g| old.activity_ids.iter().position(|o| *o == g.activity_ids[0]));
+            for (offset, group) in regrouped.into_iter().enumerate() {
+                self.groups.insert(i + offset, group);
+            }
+        }
+
+        self.invalidate();
+        true
+    }
+
+    /// Route groups, including single-activity groups, oldest first.
+    pub fn routes(&self) -> &[RouteGroup] {
+        &self.groups
+    }
+
+    /// Sport type an activity was added with.
+    fn sport_of(&self, activity_id: &str) -> Option<&str> {
+        self.activity_data.get(activity_id)?.sport_type.as_deref()
+    }
+
+    /// Whether an activity is indoor and kept out of groups by
+    /// [`MatchConfig::exclude_indoor`].
+    fn is_excluded_indoor(&self, activity_id: &str) -> bool {
+        self.match_config.exclude_indoor
+            && self.index.get(activity_id).is_some_and(|sig| {
+                crate::indoor::is_indoor(&sig.points, self.sport_of(activity_id), &IndoorConfig::default())
+            })
+    }
+
+    /// The route group an activity belongs to.
+    pub fn route_of(&self, activity_id: &str) -> Option<&RouteGroup> {
+        self.group_index(activity_id).map(|i| &self.groups[i])
+    }
+
+    /// Frequent sections, detected on demand after activities changed.
+    /// Sections that survive a re-detection keep their IDs.
+    pub fn sections(&mut self) -> &[FrequentSection] {
+        if self.sections.is_none() {
+            let sport_types: HashMap<String, String> = self
+                .activity_data
+                .iter()
+                .filter_map(|(id, data)| Some((id.clone(), data.sport_type.clone()?)))
+                .collect();
+            let mut sections =
+                crate::detect_sections_from_tracks(&self.tracks, &sport_types, &self.groups, &self.section_config);
+            crate::reconcile_section_ids(&self.previous_sections, &mut sections, &self.section_config);
+            self.previous_sections = sections.clone();
+            self.sections = Some(sections);
+        }
+        self.sections.as_deref().unwrap_or_default()
+    }
+
+    /// Heatmap for a web map zoom level (256px tiles), with cells of a few
+    /// screen pixels. Cached until activities change.
+    pub fn heatmap(&mut self, zoom: u8) -> &HeatmapResult {
+        if !self.heatmaps.contains_key(&zoom) {
+            let meters_per_pixel = 156_543.03 / 2f64.powi(zoom.min(24) as i32);
+            let config = HeatmapConfig {
+                cell_size_meters: (meters_per_pixel * HEATMAP_CELL_PIXELS).max(1.0),
+                ..HeatmapConfig::default()
+            };
+
+            let mut activity_data = self.activity_data.clone();
+            for group in &self.groups {
+                for id in &group.activity_ids {
+                    if let Some(data) = activity_data.get_mut(id) {
+                        data.route_id = Some(group.group_id.clone());
+                    }
+                }
+            }
+
+            let signatures: Vec<RouteSignature> = self.index.signatures().cloned().collect();
+            let heatmap = crate::generate_heatmap(&signatures, &activity_data, &config);
+            self.heatmaps.insert(zoom, heatmap);
+        }
+        &self.heatmaps[&zoom]
+    }
+
+    fn group_index(&self, activity_id: &str) -> Option<usize> {
+        self.groups.iter().position(|g| g.activity_ids.iter().any(|id| id == activity_id))
+    }
+
+    fn invalidate(&mut self) {
+        if let Some(sections) = self.sections.take() {
+            self.previous_sections = sections;
+        }
+        self.heatmaps.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(lng: f64, lat_from: f64, count: usize) -> Vec<GpsPoint> {
+        (0..count).map(|i| GpsPoint::new(lat_from + i as f64 * 0.0005, lng)).collect()
+    }
+
+    #[test]
+    fn test_groups_follow_adds_and_removes() {
+        let mut library = RouteLibrary::default();
+        assert!(library.add_activity("a", track(-0.1, 51.5, 40), "Ride", None));
+        assert!(library.add_activity("other", track(-0.3, 51.5, 40), "Ride", None));
+        assert!(library.add_activity("b", track(-0.1, 51.5, 40), "Ride", None));
+        assert!(!library.add_activity("short", vec![GpsPoint::new(51.5, -0.1)], "Ride", None));
+
+        assert_eq!(library.len(), 3);
+        assert_eq!(library.routes().len(), 2);
+        assert_eq!(library.routes()[0].group_id, "a");
+        assert_eq!(library.routes()[0].activity_ids, vec!["a".to_string(), "b".to_string()]);
+
+        // Replacing an activity moves it to the group it now matches
+        assert!(library.add_activity("b", track(-0.3, 51.5, 40), "Ride", None));
+        assert_eq!(library.len(), 3);
+        let other = library.routes().iter().find(|g| g.group_id == "other").unwrap();
+        assert_eq!(other.activity_ids.len(), 2);
+
+        assert!(library.remove_activity("a"));
+        assert!(!library.remove_activity("a"));
+        assert_eq!(library.routes().len(), 1);
+        assert_eq!(library.route_of("b").unwrap().group_id, "other");
+        assert!(library.route_of("a").is_none());
+        assert!(library.signature("a").is_none());
+        let ids: Vec<&str> = library.signatures().map(|s| s.activity_id.as_str()).collect();
+        assert_eq!(ids, vec!["other", "b"]);
+    }
+
+    #[test]
+    fn test_groups_stay_within_sport() {
+        let mut library = RouteLibrary::default();
+        library.add_activity("run", track(-0.1, 51.5, 40), "Run", None);
+        library.add_activity("ride", track(-0.1, 51.5, 40), "Ride", None);
+        assert_eq!(library.routes().len(), 2);
+
+        let config = MatchConfig { cross_sport_grouping: true, ..MatchConfig::default() };
+        let mut library = RouteLibrary::new(config, SectionConfig::default());
+        library.add_activity("run", track(-0.1, 51.5, 40), "Run", None);
+        library.add_activity("ride", track(-0.1, 51.5, 40), "Ride", None);
+        assert_eq!(library.routes().len(), 1);
+    }
+
+    #[test]
+    fn test_heatmap_cache_invalidated() {
+        let mut library = RouteLibrary::default();
+        library.add_activity("a", track(-0.1, 51.5, 40), "Ride", Some(100));
+        library.add_activity("b", track(-0.1, 51.5, 40), "Ride", Some(200));
+
+        let coarse = library.heatmap(10).cells.len();
+        let fine = library.heatmap(16).cells.len();
+        assert!(fine > coarse);
+        let cell = &library.heatmap(16).cells[0];
+        assert_eq!(cell.route_refs[0].route_id, "a");
+
+        library.add_activity("c", track(-0.2, 51.5, 40), "Ride", Some(300));
+        assert!(library.heatmap(16).cells.len() > fine);
+
+        library.remove_activity("c");
+        assert_eq!(library.heatmap(16).cells.len(), fine);
+    }
+
+    #[test]
+    fn test_sections_keep_ids_across_changes() {
+        let mut library = RouteLibrary::default();
+        // Shared 2km climb followed by different continuations
+        for (i, lng) in [-0.10, -0.11, -0.12, -0.13].iter().enumerate() {
+            let mut points = track(-0.1, 51.5, 40);
+            points.extend((1..30).map(|j| GpsPoint::new(51.5195, -0.1 + (lng + 0.1) * j as f64 / 3.0)));
+            library.add_activity(&format!("ride{}", i), points, "Ride", None);
+        }
+
+        let ids: Vec<String> = library.sections().iter().map(|s| s.id.clone()).collect();
+        assert!(!ids.is_empty());
+
+        library.remove_activity("ride3");
+        let after: Vec<String> = library.sections().iter().map(|s| s.id.clone()).collect();
+        assert!(after.iter().any(|id| ids.contains(id)));
+    }
+}
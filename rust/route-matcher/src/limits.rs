@@ -0,0 +1,154 @@
+//! # Resource Limits
+//!
+//! Caps on memory and threads for batch processing, so multi-year libraries
+//! can be processed on phones without running out of memory or starving the
+//! UI thread.
+//!
+//! - `max_points_in_memory` bounds how many full-resolution GPS points are
+//!   materialized at once. Signature creation works through tracks in batches
+//!   under the budget; section detection, which needs every track at once,
+//!   thins tracks evenly until they fit.
+//! - `max_parallelism` runs the work on a dedicated pool of that many threads
+//!   instead of rayon's global pool (one per core).
+//!
+//! Zero means unlimited for both.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::ResourceLimits;
+//!
+//! let limits = ResourceLimits { max_points_in_memory: 1_000, max_parallelism: 2 };
+//!
+//! // Tracks of 600, 300 and 500 points: the first two fit together
+//! assert_eq!(limits.batches(&[600, 300, 500]), vec![0..2, 2..3]);
+//!
+//! // 4,000 points must be thinned to every 4th to fit the budget
+//! assert_eq!(limits.stride(4_000), 4);
+//!
+//! let sum = limits.install(|| (1..=10).sum::<u32>());
+//! assert_eq!(sum, 55);
+//! ```
+
+use std::ops::Range;
+use crate::GpsPoint;
+
+/// Memory and thread caps for batch processing. Zero means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ResourceLimits {
+    /// Maximum full-resolution GPS points held at once. Default: 0 (unlimited)
+    pub max_points_in_memory: u32,
+    /// Maximum worker threads. Default: 0 (one per core)
+    pub max_parallelism: u32,
+}
+
+impl ResourceLimits {
+    /// No limits.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Run `op` within the thread limit. Without the `parallel` feature, or
+    /// if a dedicated pool can't be created, `op` runs as is.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        #[cfg(feature = "parallel")]
+        if self.max_parallelism > 0 {
+            match rayon::ThreadPoolBuilder::new().num_threads(self.max_parallelism as usize).build() {
+                Ok(pool) => return pool.install(op),
+                Err(e) => log::warn!("[ResourceLimits] Falling back to the global pool: {}", e),
+            }
+        }
+        op()
+    }
+
+    /// Split tracks with the given point counts into consecutive batches of
+    /// at most `max_points_in_memory` points. A track over the budget on its
+    /// own gets a batch to itself.
+    pub fn batches(&self, point_counts: &[usize]) -> Vec<Range<usize>> {
+        if point_counts.is_empty() {
+            return Vec::new();
+        }
+        if self.max_points_in_memory == 0 {
+            return std::iter::once(0..point_counts.len()).collect();
+        }
+
+        let budget = self.max_points_in_memory as usize;
+        let mut batches = Vec::new();
+        let mut start = 0;
+        let mut points = 0;
+        for (i, &count) in point_counts.iter().enumerate() {
+            if i > start && points + count > budget {
+                batches.push(start..i);
+                start = i;
+                points = 0;
+            }
+            points += count;
+        }
+        batches.push(start..point_counts.len());
+        batches
+    }
+
+    /// Keep every `stride`-th point so `total_points` fit the budget (1 if
+    /// they already fit).
+    pub fn stride(&self, total_points: usize) -> usize {
+        match self.max_points_in_memory as usize {
+            0 => 1,
+            budget => total_points.div_ceil(budget).max(1),
+        }
+    }
+}
+
+/// Points from a flat `[lat, lng, lat, lng, ...]` buffer, keeping every
+/// `stride`-th point plus the last so the track keeps its full extent. Use
+/// with [`ResourceLimits::stride`] to thin tracks to a memory budget.
+///
+/// ```rust
+/// use route_matcher::limits::points_from_flat;
+///
+/// let coords = [51.50, -0.1, 51.51, -0.1, 51.52, -0.1, 51.53, -0.1];
+/// assert_eq!(points_from_flat(&coords, 2).len(), 3);
+/// ```
+pub fn points_from_flat(coords: &[f64], stride: usize) -> Vec<GpsPoint> {
+    let count = coords.len() / 2;
+    let point = |i: usize| GpsPoint::new(coords[i * 2], coords[i * 2 + 1]);
+    let mut points: Vec<GpsPoint> = (0..count).step_by(stride.max(1)).map(point).collect();
+    if count > 0 && !(count - 1).is_multiple_of(stride.max(1)) {
+        points.push(point(count - 1));
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_respect_budget() {
+        let limits = ResourceLimits { max_points_in_memory: 100, max_parallelism: 0 };
+        assert_eq!(limits.batches(&[40, 40, 40, 250, 10]), vec![0..2, 2..3, 3..4, 4..5]);
+        assert_eq!(ResourceLimits::unlimited().batches(&[40, 40]), vec![0..2]);
+        assert!(limits.batches(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_points_from_flat_keeps_ends() {
+        let coords: Vec<f64> = (0..5).flat_map(|i| [51.0 + i as f64, -0.1]).collect();
+        let thinned = points_from_flat(&coords, 3);
+        let lats: Vec<f64> = thinned.iter().map(|p| p.latitude).collect();
+        assert_eq!(lats, [51.0, 54.0, 55.0]);
+        assert_eq!(points_from_flat(&coords, 1).len(), 5);
+        assert!(points_from_flat(&[], 2).is_empty());
+    }
+
+    #[test]
+    fn test_stride_and_install() {
+        let limits = ResourceLimits { max_points_in_memory: 1_000, max_parallelism: 1 };
+        assert_eq!(limits.stride(999), 1);
+        assert_eq!(limits.stride(2_001), 3);
+        assert_eq!(ResourceLimits::unlimited().stride(1_000_000), 1);
+
+        #[cfg(feature = "parallel")]
+        assert_eq!(limits.install(rayon::current_num_threads), 1);
+    }
+}
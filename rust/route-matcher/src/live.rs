@@ -0,0 +1,603 @@
+//! # Live Matching
+//!
+//! Matches an activity while it is being recorded: points arrive one at a
+//! time (e.g. every few seconds from a watch) and the matcher reports which
+//! routes and sections the athlete is on and how far along they are ("on
+//! Route X, 40% complete").
+//!
+//! ## Algorithm
+//! Each route group (its representative signature) and each frequent section
+//! is a target, followed in its recorded direction:
+//! 1. Points closer than `min_point_spacing` to the last one are skipped
+//! 2. A target is picked up when a point passes within `start_radius` of its
+//!    start, and confirmed ([`LiveEventKind::Started`]) once progress along it
+//!    passes twice that, so crossing a start doesn't count
+//! 3. Prefix matching: each point is projected onto the part of the target
+//!    just around the progress so far, so out-and-back routes and loops are
+//!    followed in order. Progress only moves forwards
+//! 4. Incremental AMD: the running mean of those projection offsets, kept as a
+//!    sum and count, gives the match percentage with the same thresholds as
+//!    [`crate::compare_routes`]
+//! 5. After `max_off_route_points` consecutive points further than
+//!    `off_route_distance`, the target is left; within `start_radius` of its
+//!    end it is completed
+//!
+//! ## Remaining Distance and ETA
+//! Remaining distance is measured along the target's geometry from the
+//! progress so far. Points pushed with a timestamp
+//! ([`LiveMatcher::push_timed_point`]) give a pace: progress along the target
+//! over the last `pace_window_seconds`, which projects the time to the end.
+//!
+//! Targets sharing a road are equally likely; the probability of each
+//! followed target is a Gaussian likelihood of its AMD, normalised over the
+//! confirmed targets of the same kind (routes, or sections).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, MatchConfig, RouteSignature};
+//! use route_matcher::live::{LiveConfig, LiveEventKind, LiveMatcher};
+//!
+//! let config = MatchConfig::default();
+//! let road: Vec<GpsPoint> = (0..=40).map(|i| GpsPoint::new(51.50 + i as f64 * 0.0005, -0.10)).collect();
+//! let signatures = vec![RouteSignature::from_points("commute", &road, &config).unwrap()];
+//! let mut matcher = LiveMatcher::new(&signatures, &[], &[], config, LiveConfig::default());
+//!
+//! // Ride the first 40% of the route
+//! let mut events = Vec::new();
+//! for i in 0..=16 {
+//!     events.extend(matcher.push_point(GpsPoint::new(51.50 + i as f64 * 0.0005, -0.10)));
+//! }
+//! assert_eq!(events[0].kind, LiveEventKind::Started);
+//! let last = events.last().unwrap();
+//! assert_eq!(last.target.target_id, "commute");
+//! assert_eq!(last.target.progress_percent.floor(), 40.0);
+//! ```
+
+use std::collections::VecDeque;
+use crate::geo_utils::project_point_onto_route;
+use crate::network::route_representatives;
+use crate::metrics::amd_to_percentage;
+use crate::{FrequentSection, GpsPoint, MatchConfig, RouteGroup, RouteSignature};
+
+/// Configuration for live matching
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct LiveConfig {
+    /// Points closer than this to the last used point are skipped (meters). Default: 10
+    pub min_point_spacing: f64,
+    /// Distance from a target's start that picks it up, and from its end that
+    /// completes it (meters). Default: 50
+    pub start_radius: f64,
+    /// Points further than this from a target are off it (meters). Default: 50
+    pub off_route_distance: f64,
+    /// Consecutive off-target points before a target is left. Default: 3
+    pub max_off_route_points: u32,
+    /// Progress events are emitted every this many percent. Default: 10
+    pub progress_step_percent: f64,
+    /// Recent time over which pace is measured for the ETA (seconds). Default: 300
+    #[cfg_attr(feature = "ffi", uniffi(default = 300.0))]
+    pub pace_window_seconds: f64,
+}
+
+impl Default for LiveConfig {
+    fn default() -> Self {
+        Self {
+            min_point_spacing: 10.0,
+            start_radius: 50.0,
+            off_route_distance: 50.0,
+            max_off_route_points: 3,
+            progress_step_percent: 10.0,
+            pace_window_seconds: 300.0,
+        }
+    }
+}
+
+/// What a live target is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum LiveTargetKind {
+    /// A route group, followed along its representative activity
+    Route,
+    /// A frequent section
+    Section,
+}
+
+/// What happened to a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum LiveEventKind {
+    /// The activity is following the target
+    Started,
+    /// Progress passed another `progress_step_percent`
+    Progress,
+    /// The end of the target was reached
+    Completed,
+    /// The activity went off the target before its end
+    Left,
+}
+
+/// A target being followed, and how well
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct LiveTarget {
+    /// Route group ID or section ID
+    pub target_id: String,
+    pub target_kind: LiveTargetKind,
+    /// Distance along the target reached (meters)
+    pub distance_along: f64,
+    /// Target length (meters)
+    pub length_meters: f64,
+    /// Distance along as a percentage of the length (0-100)
+    pub progress_percent: f64,
+    /// Distance left to the end of the target (meters)
+    pub remaining_meters: f64,
+    /// Time to the end at the recent pace (seconds); None without timed
+    /// points or while not moving along the target
+    pub remaining_seconds: Option<f64>,
+    /// Projected time of reaching the end (Unix seconds), when
+    /// `remaining_seconds` is known
+    pub estimated_finish: Option<i64>,
+    /// Average distance from the activity to the target so far (meters)
+    pub amd: f64,
+    /// Match percentage from the AMD (0-100)
+    pub match_percentage: f64,
+    /// Likelihood relative to the other followed targets (0-1)
+    pub probability: f64,
+}
+
+/// A change to a followed target
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct LiveEvent {
+    pub kind: LiveEventKind,
+    /// The target after the point was added
+    pub target: LiveTarget,
+}
+
+/// A route or section polyline with cumulative distances
+struct Target {
+    id: String,
+    kind: LiveTargetKind,
+    polyline: Vec<GpsPoint>,
+    /// Distance from the start to each polyline point (meters)
+    cumulative: Vec<f64>,
+    /// Whether the activity has been away from the start since the target
+    /// was last followed, so loops aren't picked up again on completion
+    armed: bool,
+}
+
+impl Target {
+    fn length(&self) -> f64 {
+        self.cumulative[self.cumulative.len() - 1]
+    }
+
+    /// Projection of `point` onto the polyline between `from` and `to` meters
+    /// along: (distance along, offset).
+    fn project_between(&self, point: &GpsPoint, from: f64, to: f64) -> Option<(f64, f64)> {
+        let first = self.cumulative.partition_point(|&d| d < from).saturating_sub(1);
+        let last = self.cumulative.partition_point(|&d| d <= to).min(self.polyline.len() - 1);
+        if last <= first {
+            return None;
+        }
+        project_point_onto_route(point, &self.polyline[first..=last])
+            .map(|proj| (self.cumulative[first] + proj.distance_along, proj.offset))
+    }
+}
+
+/// Match state of a target being followed
+#[derive(Clone)]
+struct Following {
+    target: usize,
+    progress: f64,
+    offset_sum: f64,
+    offset_count: u32,
+    off_route_points: u32,
+    /// Progress steps already reported
+    steps_reported: u32,
+    confirmed: bool,
+    /// (timestamp, progress) of recent timed points on the target
+    pace_samples: VecDeque<(i64, f64)>,
+}
+
+impl Following {
+    fn amd(&self) -> f64 {
+        if self.offset_count == 0 {
+            0.0
+        } else {
+            self.offset_sum / self.offset_count as f64
+        }
+    }
+
+    /// Recent speed along the target (m/s), if it moved forwards.
+    fn pace(&self) -> Option<f64> {
+        let (&(t0, p0), &(t1, p1)) = (self.pace_samples.front()?, self.pace_samples.back()?);
+        let speed = (p1 - p0) / (t1 - t0) as f64;
+        (t1 > t0 && speed > 0.0).then_some(speed)
+    }
+}
+
+/// Streaming matcher for an activity in progress.
+///
+/// Feed points with [`LiveMatcher::push_point`] as they arrive; it returns
+/// the events each point causes. [`LiveMatcher::status`] lists the targets
+/// being followed, most probable first.
+pub struct LiveMatcher {
+    targets: Vec<Target>,
+    following: Vec<Following>,
+    last_point: Option<GpsPoint>,
+    last_timestamp: Option<i64>,
+    match_config: MatchConfig,
+    config: LiveConfig,
+}
+
+impl LiveMatcher {
+    /// Matcher over the routes of `groups` (plus ungrouped signatures) and
+    /// `sections`.
+    pub fn new(
+        signatures: &[RouteSignature],
+        groups: &[RouteGroup],
+        sections: &[FrequentSection],
+        match_config: MatchConfig,
+        config: LiveConfig,
+    ) -> Self {
+        let model = match_config.distance_model();
+        let routes = route_representatives(signatures, groups)
+            .into_iter()
+            .map(|(id, _, sig)| (id, LiveTargetKind::Route, sig.points.clone()));
+        let sections = sections
+            .iter()
+            .map(|s| (s.id.clone(), LiveTargetKind::Section, s.polyline.clone()));
+        let targets = routes
+            .chain(sections)
+            .filter(|(_, _, polyline)| polyline.len() >= 2 && model.polyline_length(polyline) > 0.0)
+            .map(|(id, kind, polyline)| {
+                let mut cumulative = Vec::with_capacity(polyline.len());
+                let mut along = 0.0;
+                cumulative.push(0.0);
+                for w in polyline.windows(2) {
+                    along += model.distance(&w[0], &w[1]);
+                    cumulative.push(along);
+                }
+                Target { id, kind, polyline, cumulative, armed: true }
+            })
+            .collect();
+
+        Self {
+            targets,
+            following: Vec::new(),
+            last_point: None,
+            last_timestamp: None,
+            match_config,
+            config,
+        }
+    }
+
+    /// Add the next recorded point. Returns the events it caused, in target
+    /// order. Invalid and too-close points are ignored.
+    pub fn push_point(&mut self, point: GpsPoint) -> Vec<LiveEvent> {
+        self.push(point, None)
+    }
+
+    /// Add the next recorded point with its time (Unix seconds), for pace and
+    /// ETA. Otherwise like [`Self::push_point`].
+    pub fn push_timed_point(&mut self, point: GpsPoint, timestamp: i64) -> Vec<LiveEvent> {
+        self.push(point, Some(timestamp))
+    }
+
+    fn push(&mut self, point: GpsPoint, timestamp: Option<i64>) -> Vec<LiveEvent> {
+        if !point.is_valid() {
+            return Vec::new();
+        }
+        let model = self.match_config.distance_model();
+        let step = match self.last_point {
+            Some(last) => {
+                let step = model.distance(&last, &point);
+                if step < self.config.min_point_spacing {
+                    return Vec::new();
+                }
+                step
+            }
+            None => 0.0,
+        };
+        self.last_point = Some(point);
+        if timestamp.is_some() {
+            self.last_timestamp = timestamp;
+        }
+
+        // (event, target state after this point); finished targets are removed
+        let mut changes: Vec<(LiveEventKind, Following)> = Vec::new();
+        let config = &self.config;
+
+        // Advance the targets already followed
+        let mut following = std::mem::take(&mut self.following);
+        following.retain_mut(|f| {
+            let target = &self.targets[f.target];
+            let window = (f.progress - config.off_route_distance, f.progress + step + config.off_route_distance);
+            match target.project_between(&point, window.0, window.1) {
+                Some((along, offset)) if offset <= config.off_route_distance => {
+                    f.progress = f.progress.max(along);
+                    f.offset_sum += offset;
+                    f.offset_count += 1;
+                    f.off_route_points = 0;
+                    if let Some(t) = timestamp {
+                        f.pace_samples.push_back((t, f.progress));
+                        // Keep one sample at or before the start of the window
+                        while f.pace_samples.get(1).is_some_and(|&(t1, _)| (t - t1) as f64 >= config.pace_window_seconds) {
+                            f.pace_samples.pop_front();
+                        }
+                    }
+                }
+                _ => f.off_route_points += 1,
+            }
+
+            if f.off_route_points >= config.max_off_route_points {
+                if f.confirmed {
+                    changes.push((LiveEventKind::Left, f.clone()));
+                }
+                return false;
+            }
+            if target.length() - f.progress <= config.start_radius && f.confirmed {
+                changes.push((LiveEventKind::Completed, f.clone()));
+                return false;
+            }
+            if !f.confirmed && f.progress >= 2.0 * config.start_radius {
+                f.confirmed = true;
+                changes.push((LiveEventKind::Started, f.clone()));
+            } else if f.confirmed && config.progress_step_percent > 0.0 {
+                let steps = (100.0 * f.progress / target.length() / config.progress_step_percent) as u32;
+                if steps > f.steps_reported {
+                    f.steps_reported = steps;
+                    changes.push((LiveEventKind::Progress, f.clone()));
+                }
+            }
+            true
+        });
+
+        // Pick up targets starting here
+        for (i, target) in self.targets.iter_mut().enumerate() {
+            let near_start = model.distance(&point, &target.polyline[0]) <= config.start_radius;
+            if !near_start {
+                target.armed = true;
+                continue;
+            }
+            if !target.armed || following.iter().any(|f| f.target == i) {
+                continue;
+            }
+            if changes.iter().any(|(kind, f)| f.target == i && matches!(kind, LiveEventKind::Completed | LiveEventKind::Left)) {
+                target.armed = false;
+                continue;
+            }
+            if let Some((along, offset)) = target.project_between(&point, 0.0, 2.0 * config.start_radius) {
+                target.armed = false;
+                following.push(Following {
+                    target: i,
+                    progress: along,
+                    offset_sum: offset,
+                    offset_count: 1,
+                    off_route_points: 0,
+                    steps_reported: 0,
+                    confirmed: false,
+                    pace_samples: timestamp.map(|t| (t, along)).into_iter().collect(),
+                });
+            }
+        }
+        self.following = following;
+
+        // Finished targets are weighed against the ones still followed
+        let finished = changes
+            .iter()
+            .filter(|(kind, _)| matches!(kind, LiveEventKind::Completed | LiveEventKind::Left))
+            .map(|(_, f)| f);
+        let totals = self.total_likelihoods(self.following.iter().filter(|f| f.confirmed).chain(finished));
+        changes.sort_by_key(|(_, f)| f.target);
+        changes
+            .into_iter()
+            .map(|(kind, f)| LiveEvent { kind, target: self.live_target(&f, &totals) })
+            .collect()
+    }
+
+    /// Add several points in order. Returns all their events.
+    pub fn push_points(&mut self, points: &[GpsPoint]) -> Vec<LiveEvent> {
+        points.iter().flat_map(|&p| self.push_point(p)).collect()
+    }
+
+    /// Confirmed targets being followed, most probable first.
+    pub fn status(&self) -> Vec<LiveTarget> {
+        let confirmed = || self.following.iter().filter(|f| f.confirmed);
+        let totals = self.total_likelihoods(confirmed());
+        let mut targets: Vec<LiveTarget> = confirmed().map(|f| self.live_target(f, &totals)).collect();
+        targets.sort_by(|a, b| {
+            b.probability
+                .total_cmp(&a.probability)
+                .then_with(|| b.progress_percent.total_cmp(&a.progress_percent))
+        });
+        targets
+    }
+
+    /// Forget the activity so far, e.g. when a new one starts.
+    pub fn reset(&mut self) {
+        self.following.clear();
+        self.last_point = None;
+        self.last_timestamp = None;
+        for target in &mut self.targets {
+            target.armed = true;
+        }
+    }
+
+    /// Gaussian likelihood of an AMD, with the perfect-match threshold as sigma.
+    fn likelihood(&self, amd: f64) -> f64 {
+        let sigma = self.match_config.perfect_threshold.max(1.0);
+        (-0.5 * (amd / sigma).powi(2)).exp()
+    }
+
+    /// Summed likelihoods of `following` for routes and for sections.
+    fn total_likelihoods<'a>(&self, following: impl Iterator<Item = &'a Following>) -> [f64; 2] {
+        let mut totals = [0.0; 2];
+        for f in following {
+            totals[self.targets[f.target].kind as usize] += self.likelihood(f.amd());
+        }
+        totals
+    }
+
+    /// Public state of `f`, given the summed likelihoods of the targets it
+    /// competes with (itself included) from [`Self::total_likelihoods`].
+    fn live_target(&self, f: &Following, totals: &[f64; 2]) -> LiveTarget {
+        let target = &self.targets[f.target];
+        let amd = f.amd();
+        let total_likelihood = totals[target.kind as usize];
+        let remaining_meters = (target.length() - f.progress).max(0.0);
+        let remaining_seconds = f.pace().map(|speed| remaining_meters / speed);
+        LiveTarget {
+            target_id: target.id.clone(),
+            target_kind: target.kind,
+            distance_along: f.progress,
+            length_meters: target.length(),
+            progress_percent: (100.0 * f.progress / target.length()).clamp(0.0, 100.0),
+            remaining_meters,
+            remaining_seconds,
+            estimated_finish: remaining_seconds
+                .zip(self.last_timestamp)
+                .map(|(seconds, t)| t + seconds.round() as i64),
+            amd,
+            match_percentage: amd_to_percentage(amd, self.match_config.perfect_threshold, self.match_config.zero_threshold),
+            probability: if total_likelihood > 0.0 { self.likelihood(amd) / total_likelihood } else { 0.0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DistanceModel;
+
+    /// `n` steps of ~22m from `start`, heading north or east
+    fn road(start: (f64, f64), n: usize, north: bool) -> Vec<GpsPoint> {
+        (0..=n)
+            .map(|i| {
+                let step = i as f64 * 0.0002;
+                if north {
+                    GpsPoint::new(start.0 + step, start.1)
+                } else {
+                    GpsPoint::new(start.0, start.1 + step * 1.6)
+                }
+            })
+            .collect()
+    }
+
+    fn signature(id: &str, points: &[GpsPoint]) -> RouteSignature {
+        RouteSignature::from_points(id, points, &MatchConfig::default()).unwrap()
+    }
+
+    /// "straight" heads north 2.2km; "fork" follows it for 1.1km, then turns
+    /// east; section "shared" is the common 1.1km
+    fn matcher() -> (LiveMatcher, Vec<GpsPoint>) {
+        matcher_with(LiveConfig::default())
+    }
+
+    fn matcher_with(config: LiveConfig) -> (LiveMatcher, Vec<GpsPoint>) {
+        let straight = road((51.50, -0.10), 100, true);
+        let mut fork = road((51.50, -0.10), 50, true);
+        fork.extend(road((51.51, -0.10), 50, false).into_iter().skip(1));
+        let shared = FrequentSection {
+            id: "shared".to_string(),
+            polyline: road((51.50, -0.10), 50, true),
+            ..Default::default()
+        };
+        let signatures = vec![signature("straight", &straight), signature("fork", &fork)];
+        let matcher = LiveMatcher::new(&signatures, &[], &[shared], MatchConfig::default(), config);
+        (matcher, fork)
+    }
+
+    #[test]
+    fn test_follows_routes_and_sections_as_points_arrive() {
+        let (mut matcher, ride) = matcher();
+
+        let events = matcher.push_points(&ride[..25]);
+        let started: Vec<&str> = events
+            .iter()
+            .filter(|e| e.kind == LiveEventKind::Started)
+            .map(|e| e.target.target_id.as_str())
+            .collect();
+        assert_eq!(started, vec!["straight", "fork", "shared"]);
+
+        // Halfway along the shared road both routes are equally likely
+        let status = matcher.status();
+        assert_eq!(status.len(), 3);
+        let route = status.iter().find(|t| t.target_id == "fork").unwrap();
+        assert!((route.probability - 0.5).abs() < 0.01);
+        assert!(route.match_percentage > 99.0);
+        let section = status.iter().find(|t| t.target_kind == LiveTargetKind::Section).unwrap();
+        assert!((section.probability - 1.0).abs() < 1e-9);
+        assert!((section.progress_percent - 48.0).abs() < 2.0);
+
+        let events = matcher.push_points(&ride[25..]);
+        let outcomes: Vec<(LiveEventKind, &str)> = events
+            .iter()
+            .filter(|e| e.kind != LiveEventKind::Progress)
+            .map(|e| (e.kind, e.target.target_id.as_str()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (LiveEventKind::Completed, "shared"),
+                (LiveEventKind::Left, "straight"),
+                (LiveEventKind::Completed, "fork"),
+            ]
+        );
+        assert!(events.iter().any(|e| e.kind == LiveEventKind::Progress
+            && e.target.target_id == "fork"
+            && e.target.progress_percent.floor() == 50.0));
+        assert!(matcher.status().is_empty());
+    }
+
+    #[test]
+    fn test_remaining_distance_and_eta_from_recent_pace() {
+        let (mut live, ride) = matcher_with(LiveConfig { pace_window_seconds: 60.0, ..LiveConfig::default() });
+        // ~22m every 5s for the first 10 points, then ~22m every 4s
+        let mut t = 1_700_000_000;
+        for (i, &point) in ride[..40].iter().enumerate() {
+            t += if i < 10 { 5 } else { 4 };
+            live.push_timed_point(point, t);
+        }
+        let status = live.status();
+        let fork = status.iter().find(|s| s.target_id == "fork").unwrap();
+        let remaining = fork.length_meters - fork.distance_along;
+        assert!((fork.remaining_meters - remaining).abs() < 1e-9);
+        // The window only covers the faster pace
+        let speed = 0.0002 * 111_195.0 / 4.0;
+        assert!((fork.remaining_seconds.unwrap() - remaining / speed).abs() < 5.0, "{:?}", fork.remaining_seconds);
+        assert_eq!(fork.estimated_finish, Some(t + fork.remaining_seconds.unwrap().round() as i64));
+
+        // Untimed points give no ETA
+        let (mut untimed, _) = matcher();
+        untimed.push_points(&ride[..40]);
+        assert!(untimed.status().iter().all(|s| s.remaining_seconds.is_none()));
+    }
+
+    #[test]
+    fn test_target_lengths_follow_distance_model() {
+        let straight = road((51.50, -0.10), 100, true);
+        let signatures = vec![signature("straight", &straight)];
+        for model in [DistanceModel::Haversine, DistanceModel::Geodesic] {
+            let match_config = MatchConfig { distance_model: Some(model), ..MatchConfig::default() };
+            let mut live = LiveMatcher::new(&signatures, &[], &[], match_config, LiveConfig::default());
+            live.push_points(&straight[..20]);
+            let status = live.status();
+            assert!((status[0].length_meters - model.polyline_length(&signatures[0].points)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_skips_close_points_and_crossing_starts() {
+        let (mut matcher, _) = matcher();
+        // Crossing the shared start heading east never confirms a target
+        let crossing = road((51.50, -0.1016), 20, false);
+        assert!(matcher.push_points(&crossing).is_empty());
+        assert!(matcher.status().is_empty());
+
+        matcher.reset();
+        let start = GpsPoint::new(51.50, -0.10);
+        assert!(matcher.push_points(&[start, start, GpsPoint::new(51.50002, -0.10)]).is_empty());
+    }
+}
@@ -0,0 +1,539 @@
+//! # Map Matching
+//!
+//! Snaps noisy GPS tracks onto an OpenStreetMap road graph, so tracks of the
+//! same street line up exactly. Matched tracks give tighter section consensus
+//! and crisper heatmaps, especially in cities where buildings degrade GPS.
+//!
+//! ## Road Graph
+//! A [`RoadGraph`] is built either from an OSM PBF extract
+//! ([`RoadGraph::from_osm_pbf`], `highway=*` ways only) or from a precomputed
+//! node/edge list ([`RoadGraph::new`]). Edges are undirected: one-way
+//! restrictions don't apply to runners and are often ignored by cyclists.
+//!
+//! ## Algorithm
+//! Hidden Markov Model matching (Newson & Krumm, 2009):
+//! 1. Thin the track so consecutive observations are `min_point_spacing` apart
+//! 2. Candidates: projections onto road edges within `search_radius`
+//! 3. Emission: Gaussian on the GPS-to-road distance (`gps_sigma`)
+//! 4. Transition: exponential on |route distance − straight-line distance|
+//!    (`transition_beta`), with route distances from bounded Dijkstra searches
+//! 5. Viterbi picks the most likely candidate sequence; consecutive matches
+//!    are joined by the road nodes between them
+//!
+//! Observations with no road nearby (trails missing from OSM, tunnels) are
+//! kept as recorded and restart the matching after them.
+//!
+//! ## Unvisited Roads
+//! [`unvisited_roads`] compares the graph with a heatmap and returns road
+//! portions in an area that have never been visited, for exploration.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::map_match::{RoadGraph, MapMatchConfig, map_match};
+//!
+//! // A straight 1km road, and a track recorded ~8m beside it
+//! let graph = RoadGraph::new(
+//!     vec![GpsPoint::new(51.500, -0.100), GpsPoint::new(51.509, -0.100)],
+//!     &[(0, 1)],
+//! ).unwrap();
+//! let track: Vec<GpsPoint> = (0..50).map(|i| GpsPoint::new(51.5005 + i as f64 * 0.00016, -0.09988)).collect();
+//!
+//! let result = map_match(&graph, &track, &MapMatchConfig::default());
+//! assert_eq!(result.matched_fraction, 1.0);
+//! assert!(result.points.iter().all(|p| (p.longitude + 0.100).abs() < 1e-6));
+//! ```
+
+mod osm_pbf;
+mod unvisited;
+
+pub use unvisited::{UnvisitedRoad, UnvisitedRoadsConfig, unvisited_roads};
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use rstar::{RTree, RTreeObject, AABB};
+use crate::GpsPoint;
+use crate::geo_utils::{haversine_distance, interpolate_point, meters_to_degrees};
+use crate::projection::LocalProjection;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Configuration for map matching
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct MapMatchConfig {
+    /// Maximum distance from a GPS point to a candidate road (meters). Default: 35
+    pub search_radius: f64,
+    /// Standard deviation of GPS error (meters). Default: 10
+    pub gps_sigma: f64,
+    /// Scale of the route vs straight-line distance penalty (meters). Default: 5
+    pub transition_beta: f64,
+    /// Longest route considered between observations, as a multiple of their
+    /// straight-line distance (plus twice the search radius). Default: 3.0
+    pub max_detour_factor: f64,
+    /// Observations closer than this to the previous one are skipped (meters). Default: 10
+    pub min_point_spacing: f64,
+    /// Maximum candidate roads per observation. Default: 8
+    pub max_candidates: u32,
+}
+
+impl Default for MapMatchConfig {
+    fn default() -> Self {
+        Self {
+            search_radius: 35.0,
+            gps_sigma: 10.0,
+            transition_beta: 5.0,
+            max_detour_factor: 3.0,
+            min_point_spacing: 10.0,
+            max_candidates: 8,
+        }
+    }
+}
+
+/// Result of matching one track
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct MapMatchResult {
+    /// Matched track: snapped positions joined by the road nodes between them,
+    /// with unmatched observations kept as recorded
+    pub points: Vec<GpsPoint>,
+    /// Fraction of (thinned) observations that were snapped to a road (0.0-1.0)
+    pub matched_fraction: f64,
+}
+
+/// Road edge stored in the spatial index
+struct EdgeEntry {
+    from: u32,
+    to: u32,
+    envelope: AABB<[f64; 2]>,
+}
+
+impl RTreeObject for EdgeEntry {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Undirected road network with a spatial index over its edges.
+pub struct RoadGraph {
+    nodes: Vec<GpsPoint>,
+    /// Neighbours of each node with the edge length in meters
+    adjacency: Vec<Vec<(u32, f64)>>,
+    edges: RTree<EdgeEntry>,
+}
+
+impl RoadGraph {
+    /// Build a graph from nodes and undirected edges (pairs of node indices).
+    ///
+    /// Self-loops and duplicate edges are ignored. Fails if an edge refers to
+    /// a node that doesn't exist.
+    pub fn new(nodes: Vec<GpsPoint>, edges: &[(u32, u32)]) -> Result<Self, String> {
+        let mut adjacency: Vec<Vec<(u32, f64)>> = vec![Vec::new(); nodes.len()];
+        let mut entries = Vec::with_capacity(edges.len());
+
+        for &(from, to) in edges {
+            let (a, b) = match (nodes.get(from as usize), nodes.get(to as usize)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Err(format!("Edge ({}, {}) refers to a missing node", from, to)),
+            };
+            if from == to || adjacency[from as usize].iter().any(|&(n, _)| n == to) {
+                continue;
+            }
+
+            let length = haversine_distance(a, b);
+            adjacency[from as usize].push((to, length));
+            adjacency[to as usize].push((from, length));
+            entries.push(EdgeEntry {
+                from,
+                to,
+                envelope: AABB::from_corners(
+                    [a.latitude.min(b.latitude), a.longitude.min(b.longitude)],
+                    [a.latitude.max(b.latitude), a.longitude.max(b.longitude)],
+                ),
+            });
+        }
+
+        Ok(Self {
+            nodes,
+            adjacency,
+            edges: RTree::bulk_load(entries),
+        })
+    }
+
+    /// Build a graph from the `highway=*` ways of an OSM PBF extract.
+    ///
+    /// Motorways and ways under construction, proposed or abandoned are skipped.
+    pub fn from_osm_pbf(data: &[u8]) -> Result<Self, String> {
+        let (nodes, edges) = osm_pbf::read_roads(data)?;
+        Self::new(nodes, &edges)
+    }
+
+    /// Number of road nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of road edges
+    pub fn edge_count(&self) -> usize {
+        self.edges.size()
+    }
+
+    /// Candidate positions on roads within `radius` of `point`, nearest first
+    fn candidates(&self, point: &GpsPoint, radius: f64, max: usize) -> Vec<Candidate> {
+        let deg = meters_to_degrees(radius, point.latitude);
+        let search = AABB::from_corners(
+            [point.latitude - deg, point.longitude - deg],
+            [point.latitude + deg, point.longitude + deg],
+        );
+        let projection = LocalProjection::new(*point);
+
+        let mut candidates: Vec<Candidate> = self
+            .edges
+            .locate_in_envelope_intersecting(&search)
+            .filter_map(|edge| {
+                let a = &self.nodes[edge.from as usize];
+                let b = &self.nodes[edge.to as usize];
+                let (pa, pb) = (projection.project(a), projection.project(b));
+                let (dx, dy) = (pb[0] - pa[0], pb[1] - pa[1]);
+                let len_sq = dx * dx + dy * dy;
+                let t = if len_sq > 0.0 {
+                    (-(pa[0] * dx + pa[1] * dy) / len_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (x, y) = (pa[0] + t * dx, pa[1] + t * dy);
+                let distance = (x * x + y * y).sqrt();
+
+                (distance <= radius).then(|| Candidate {
+                    from: edge.from,
+                    to: edge.to,
+                    t,
+                    point: interpolate_point(a, b, t),
+                    distance,
+                    edge_length: haversine_distance(a, b),
+                })
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(Ordering::Equal));
+        candidates.truncate(max);
+        candidates
+    }
+
+    /// Bounded Dijkstra from a candidate's position: distance and predecessor
+    /// of every node reachable within `limit` meters.
+    fn shortest_paths(&self, source: &Candidate, limit: f64) -> HashMap<u32, (f64, Option<u32>)> {
+        let mut settled: HashMap<u32, (f64, Option<u32>)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        heap.push(State { cost: source.t * source.edge_length, node: source.from, prev: None });
+        heap.push(State { cost: (1.0 - source.t) * source.edge_length, node: source.to, prev: None });
+
+        while let Some(State { cost, node, prev }) = heap.pop() {
+            if cost > limit || settled.contains_key(&node) {
+                continue;
+            }
+            settled.insert(node, (cost, prev));
+            for &(next, length) in &self.adjacency[node as usize] {
+                if !settled.contains_key(&next) {
+                    heap.push(State { cost: cost + length, node: next, prev: Some(node) });
+                }
+            }
+        }
+
+        settled
+    }
+}
+
+/// Projection of an observation onto a road edge
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    from: u32,
+    to: u32,
+    /// Position along the edge from `from` (0) to `to` (1)
+    t: f64,
+    point: GpsPoint,
+    /// Distance from the observation (meters)
+    distance: f64,
+    edge_length: f64,
+}
+
+impl Candidate {
+    fn same_edge(&self, other: &Candidate) -> bool {
+        self.from == other.from && self.to == other.to
+    }
+}
+
+/// Dijkstra queue entry (min-heap on cost)
+struct State {
+    cost: f64,
+    node: u32,
+    prev: Option<u32>,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Route distance from `a` to `b` given shortest paths from `a`, and the
+/// road node `b` is entered through (None when both lie on the same edge).
+fn route_distance(
+    a: &Candidate,
+    b: &Candidate,
+    paths: &HashMap<u32, (f64, Option<u32>)>,
+) -> Option<(f64, Option<u32>)> {
+    if a.same_edge(b) {
+        return Some(((b.t - a.t).abs() * a.edge_length, None));
+    }
+    let via_from = paths.get(&b.from).map(|&(d, _)| (d + b.t * b.edge_length, Some(b.from)));
+    let via_to = paths.get(&b.to).map(|&(d, _)| (d + (1.0 - b.t) * b.edge_length, Some(b.to)));
+    match (via_from, via_to) {
+        (Some(f), Some(t)) => Some(if f.0 <= t.0 { f } else { t }),
+        (f, t) => f.or(t),
+    }
+}
+
+/// One observation in a Viterbi chain
+struct Step {
+    candidates: Vec<Candidate>,
+    scores: Vec<f64>,
+    /// Best predecessor candidate in the previous step
+    back: Vec<usize>,
+}
+
+/// Match a GPS track onto the road graph.
+///
+/// See the module docs for the algorithm. Returns the input unchanged (with
+/// `matched_fraction` 0) when no road is near the track.
+pub fn map_match(graph: &RoadGraph, points: &[GpsPoint], config: &MapMatchConfig) -> MapMatchResult {
+    // Thin the track: HMM matching assumes observations a few sigma apart
+    let mut observations: Vec<GpsPoint> = Vec::new();
+    for point in points.iter().filter(|p| p.is_valid()) {
+        if observations.last().is_none_or(|last| haversine_distance(last, point) >= config.min_point_spacing) {
+            observations.push(*point);
+        }
+    }
+
+    let mut output: Vec<GpsPoint> = Vec::new();
+    let mut chain: Vec<Step> = Vec::new();
+    let mut matched = 0usize;
+    let max_candidates = config.max_candidates.max(1) as usize;
+    let emission = |c: &Candidate| -0.5 * (c.distance / config.gps_sigma).powi(2);
+
+    for (i, point) in observations.iter().enumerate() {
+        let candidates = graph.candidates(point, config.search_radius, max_candidates);
+        if candidates.is_empty() {
+            // Off the road network: keep the recorded point and restart
+            flush_chain(graph, &mut chain, &mut output);
+            output.push(*point);
+            continue;
+        }
+        matched += 1;
+
+        let Some(prev) = chain.last() else {
+            let scores = candidates.iter().map(emission).collect();
+            chain.push(Step { back: vec![0; candidates.len()], candidates, scores });
+            continue;
+        };
+
+        let straight = haversine_distance(&observations[i - 1], point);
+        let limit = straight * config.max_detour_factor + 2.0 * config.search_radius;
+        let mut scores = vec![f64::NEG_INFINITY; candidates.len()];
+        let mut back = vec![0; candidates.len()];
+
+        for (j, a) in prev.candidates.iter().enumerate() {
+            if prev.scores[j] == f64::NEG_INFINITY {
+                continue;
+            }
+            let paths = graph.shortest_paths(a, limit);
+            for (k, b) in candidates.iter().enumerate() {
+                if let Some((route, _)) = route_distance(a, b, &paths).filter(|&(d, _)| d <= limit) {
+                    let score = prev.scores[j] - (route - straight).abs() / config.transition_beta + emission(b);
+                    if score > scores[k] {
+                        scores[k] = score;
+                        back[k] = j;
+                    }
+                }
+            }
+        }
+
+        if scores.iter().all(|&s| s == f64::NEG_INFINITY) {
+            // No plausible route from the previous observation: restart here
+            flush_chain(graph, &mut chain, &mut output);
+            let scores = candidates.iter().map(emission).collect();
+            chain.push(Step { back: vec![0; candidates.len()], candidates, scores });
+        } else {
+            chain.push(Step { candidates, scores, back });
+        }
+    }
+    flush_chain(graph, &mut chain, &mut output);
+
+    output.dedup_by(|a, b| a.latitude == b.latitude && a.longitude == b.longitude);
+
+    MapMatchResult {
+        points: output,
+        matched_fraction: if observations.is_empty() { 0.0 } else { matched as f64 / observations.len() as f64 },
+    }
+}
+
+/// Backtrack the best candidate sequence of a chain and append it to `output`,
+/// joined by the road nodes between consecutive matches.
+fn flush_chain(graph: &RoadGraph, chain: &mut Vec<Step>, output: &mut Vec<GpsPoint>) {
+    let Some(last) = chain.last() else { return };
+
+    let mut best = last
+        .scores
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Ordering::Equal))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let mut path: Vec<Candidate> = Vec::with_capacity(chain.len());
+    for step in chain.iter().rev() {
+        path.push(step.candidates[best]);
+        best = step.back[best];
+    }
+    path.reverse();
+
+    output.push(path[0].point);
+    for pair in path.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if !a.same_edge(b) {
+            output.extend(road_nodes_between(graph, a, b));
+        }
+        output.push(b.point);
+    }
+
+    chain.clear();
+}
+
+/// Road nodes on the shortest route from `a` to `b`, in travel order.
+fn road_nodes_between(graph: &RoadGraph, a: &Candidate, b: &Candidate) -> Vec<GpsPoint> {
+    let direct = haversine_distance(&a.point, &b.point);
+    let mut limit = direct + a.edge_length + b.edge_length;
+    // The Viterbi limit allowed long detours; widen until the route is found
+    for _ in 0..4 {
+        let paths = graph.shortest_paths(a, limit);
+        if let Some((_, Some(entry))) = route_distance(a, b, &paths) {
+            let mut nodes = Vec::new();
+            let mut node = Some(entry);
+            while let Some(n) = node {
+                nodes.push(graph.nodes[n as usize]);
+                node = paths.get(&n).and_then(|&(_, prev)| prev);
+            }
+            nodes.reverse();
+            return nodes;
+        }
+        limit *= 4.0;
+    }
+    Vec::new()
+}
+
+/// Match many tracks `(activity_id, points)`, e.g. before section detection
+/// or heatmap generation. Uses parallel processing when the `parallel`
+/// feature is enabled.
+pub fn map_match_tracks(
+    graph: &RoadGraph,
+    tracks: &[(String, Vec<GpsPoint>)],
+    config: &MapMatchConfig,
+) -> Vec<(String, Vec<GpsPoint>)> {
+    #[cfg(feature = "parallel")]
+    let iter = tracks.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let iter = tracks.iter();
+
+    iter.map(|(id, points)| (id.clone(), map_match(graph, points, config).points))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_utils::project_point_onto_route;
+
+    /// L-shaped street: 500m north, then 500m east
+    fn corner_graph() -> RoadGraph {
+        let nodes = vec![
+            GpsPoint::new(51.5000, -0.1000),
+            GpsPoint::new(51.5045, -0.1000),
+            GpsPoint::new(51.5045, -0.0928),
+        ];
+        RoadGraph::new(nodes, &[(0, 1), (1, 2)]).unwrap()
+    }
+
+    #[test]
+    fn test_snaps_noisy_track_through_corner() {
+        let graph = corner_graph();
+        let road = graph.nodes.clone();
+        // Alternate ~7m either side of the road
+        let mut track = Vec::new();
+        for i in 0..20 {
+            let side = if i % 2 == 0 { 0.0001 } else { -0.0001 };
+            track.push(GpsPoint::new(51.5000 + i as f64 * 0.000225, -0.1000 + side));
+        }
+        for i in 1..20 {
+            let side = if i % 2 == 0 { 0.00006 } else { -0.00006 };
+            track.push(GpsPoint::new(51.5045 + side, -0.1000 + i as f64 * 0.00036));
+        }
+
+        let result = map_match(&graph, &track, &MapMatchConfig::default());
+        assert_eq!(result.matched_fraction, 1.0);
+        for p in &result.points {
+            assert!(project_point_onto_route(p, &road).unwrap().offset < 0.5);
+        }
+        // The corner node is part of the matched track
+        assert!(result.points.iter().any(|p| haversine_distance(p, &road[1]) < 0.1));
+    }
+
+    #[test]
+    fn test_prefers_connected_route_over_nearest_road() {
+        // Main road east-west, plus an unconnected parallel road 30m north
+        let nodes = vec![
+            GpsPoint::new(51.5000, -0.1000),
+            GpsPoint::new(51.5000, -0.0900),
+            GpsPoint::new(51.50027, -0.0960),
+            GpsPoint::new(51.50027, -0.0940),
+        ];
+        let graph = RoadGraph::new(nodes, &[(0, 1), (2, 3)]).unwrap();
+
+        // Along the main road, with one glitch 20m north (closer to the other road)
+        let mut track: Vec<GpsPoint> = (0..30).map(|i| GpsPoint::new(51.50002, -0.0995 + i as f64 * 0.0003)).collect();
+        track[12].latitude = 51.50019;
+
+        let result = map_match(&graph, &track, &MapMatchConfig::default());
+        assert!(result.points.iter().all(|p| (p.latitude - 51.5000).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_off_network_points_kept() {
+        let graph = corner_graph();
+        let far = GpsPoint::new(52.0, 0.0);
+        let track = vec![GpsPoint::new(51.5001, -0.10005), far, GpsPoint::new(51.5030, -0.10005)];
+
+        let result = map_match(&graph, &track, &MapMatchConfig::default());
+        assert_eq!(result.points.len(), 3);
+        assert_eq!(result.points[1], far);
+        assert!((result.matched_fraction - 2.0 / 3.0).abs() < 1e-9);
+
+        assert!(RoadGraph::new(vec![far], &[(0, 1)]).is_err());
+    }
+}
@@ -0,0 +1,421 @@
+//! Minimal OSM PBF reader: extracts road nodes and edges from `highway=*` ways.
+//!
+//! Decodes just enough of the format (fileblock framing, zlib blobs,
+//! `PrimitiveBlock` with plain/dense nodes and ways) to build a road graph;
+//! tags other than `highway`, metadata and relations are skipped.
+
+use std::collections::HashMap;
+use std::io::Read;
+use flate2::read::ZlibDecoder;
+use crate::GpsPoint;
+
+/// `highway=*` values that aren't usable by runners or cyclists
+const EXCLUDED_HIGHWAYS: &[&str] = &[
+    "motorway", "motorway_link", "construction", "proposed", "abandoned",
+    "raceway", "bus_guideway", "platform", "elevator",
+];
+
+/// Largest uncompressed blob allowed by the PBF spec (32 MiB)
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// Road nodes and undirected edges (pairs of node indices)
+type Roads = (Vec<GpsPoint>, Vec<(u32, u32)>);
+
+/// Road nodes and edges of a PBF extract.
+pub(super) fn read_roads(data: &[u8]) -> Result<Roads, String> {
+    let mut coords: HashMap<i64, GpsPoint> = HashMap::new();
+    let mut ways: Vec<Vec<i64>> = Vec::new();
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let len_bytes = data.get(pos..pos + 4).ok_or("Truncated PBF block length")?;
+        let header_len = u32::from_be_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]) as usize;
+        pos += 4;
+
+        let header = data.get(pos..pos + header_len).ok_or("Truncated PBF block header")?;
+        pos += header_len;
+        let (blob_type, blob_len) = read_blob_header(header)?;
+
+        let blob = data.get(pos..pos + blob_len).ok_or("Truncated PBF blob")?;
+        pos += blob_len;
+
+        // OSMHeader blocks only describe required features
+        if blob_type == "OSMData" {
+            read_block(&decode_blob(blob)?, &mut coords, &mut ways)?;
+        }
+    }
+
+    // Keep only nodes used by roads
+    let mut index: HashMap<i64, u32> = HashMap::new();
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for refs in &ways {
+        let mut prev: Option<u32> = None;
+        for id in refs {
+            let Some(point) = coords.get(id) else {
+                // Node outside the extract: break the way here
+                prev = None;
+                continue;
+            };
+            let idx = *index.entry(*id).or_insert_with(|| {
+                nodes.push(*point);
+                (nodes.len() - 1) as u32
+            });
+            if let Some(p) = prev {
+                edges.push((p, idx));
+            }
+            prev = Some(idx);
+        }
+    }
+
+    Ok((nodes, edges))
+}
+
+fn read_blob_header(buf: &[u8]) -> Result<(String, usize), String> {
+    let mut blob_type = String::new();
+    let mut data_size = None;
+    let mut reader = Reader::new(buf);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, Value::Bytes(b)) => blob_type = String::from_utf8_lossy(b).into_owned(),
+            (3, Value::Varint(v)) => data_size = Some(v as usize),
+            _ => {}
+        }
+    }
+    Ok((blob_type, data_size.ok_or("PBF blob header without size")?))
+}
+
+fn decode_blob(buf: &[u8]) -> Result<Vec<u8>, String> {
+    let mut raw_size = 0;
+    let mut reader = Reader::new(buf);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, Value::Bytes(b)) => return Ok(b.to_vec()),
+            (2, Value::Varint(v)) => raw_size = v as usize,
+            (3, Value::Bytes(b)) => {
+                let mut out = Vec::with_capacity(raw_size.min(MAX_BLOB_SIZE));
+                ZlibDecoder::new(b)
+                    .take(MAX_BLOB_SIZE as u64)
+                    .read_to_end(&mut out)
+                    .map_err(|e| format!("PBF zlib error: {}", e))?;
+                return Ok(out);
+            }
+            (4..=7, Value::Bytes(_)) => return Err("Unsupported PBF blob compression".to_string()),
+            _ => {}
+        }
+    }
+    Err("Empty PBF blob".to_string())
+}
+
+/// Read nodes and highway ways from a `PrimitiveBlock`
+fn read_block(buf: &[u8], coords: &mut HashMap<i64, GpsPoint>, ways: &mut Vec<Vec<i64>>) -> Result<(), String> {
+    let mut strings: Vec<&[u8]> = Vec::new();
+    let mut groups: Vec<&[u8]> = Vec::new();
+    let mut granularity: i64 = 100;
+    let (mut lat_offset, mut lon_offset): (i64, i64) = (0, 0);
+
+    let mut reader = Reader::new(buf);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, Value::Bytes(table)) => {
+                let mut table_reader = Reader::new(table);
+                while let Some((field, value)) = table_reader.next_field()? {
+                    if let (1, Value::Bytes(s)) = (field, value) {
+                        strings.push(s);
+                    }
+                }
+            }
+            (2, Value::Bytes(group)) => groups.push(group),
+            (17, Value::Varint(v)) => granularity = v as i64,
+            (19, Value::Varint(v)) => lat_offset = v as i64,
+            (20, Value::Varint(v)) => lon_offset = v as i64,
+            _ => {}
+        }
+    }
+
+    let to_point = |lat: i64, lon: i64| {
+        GpsPoint::new(
+            1e-9 * (lat_offset + granularity * lat) as f64,
+            1e-9 * (lon_offset + granularity * lon) as f64,
+        )
+    };
+
+    for group in groups {
+        let mut reader = Reader::new(group);
+        while let Some((field, value)) = reader.next_field()? {
+            match (field, value) {
+                (1, Value::Bytes(node)) => {
+                    let (mut id, mut lat, mut lon) = (0, 0, 0);
+                    let mut node_reader = Reader::new(node);
+                    while let Some((field, value)) = node_reader.next_field()? {
+                        match (field, value) {
+                            (1, Value::Varint(v)) => id = zigzag(v),
+                            (8, Value::Varint(v)) => lat = zigzag(v),
+                            (9, Value::Varint(v)) => lon = zigzag(v),
+                            _ => {}
+                        }
+                    }
+                    coords.insert(id, to_point(lat, lon));
+                }
+                (2, Value::Bytes(dense)) => {
+                    let (mut ids, mut lats, mut lons) = (Vec::new(), Vec::new(), Vec::new());
+                    let mut dense_reader = Reader::new(dense);
+                    while let Some((field, value)) = dense_reader.next_field()? {
+                        match (field, value) {
+                            (1, Value::Bytes(b)) => ids = delta_decode(b)?,
+                            (8, Value::Bytes(b)) => lats = delta_decode(b)?,
+                            (9, Value::Bytes(b)) => lons = delta_decode(b)?,
+                            _ => {}
+                        }
+                    }
+                    if ids.len() != lats.len() || ids.len() != lons.len() {
+                        return Err("Malformed PBF dense nodes".to_string());
+                    }
+                    for ((id, lat), lon) in ids.into_iter().zip(lats).zip(lons) {
+                        coords.insert(id, to_point(lat, lon));
+                    }
+                }
+                (3, Value::Bytes(way)) => {
+                    let (mut keys, mut vals, mut refs) = (Vec::new(), Vec::new(), Vec::new());
+                    let mut way_reader = Reader::new(way);
+                    while let Some((field, value)) = way_reader.next_field()? {
+                        match (field, value) {
+                            (2, Value::Bytes(b)) => keys = packed_varints(b)?,
+                            (3, Value::Bytes(b)) => vals = packed_varints(b)?,
+                            (8, Value::Bytes(b)) => refs = delta_decode(b)?,
+                            _ => {}
+                        }
+                    }
+                    let string = |i: u64| strings.get(i as usize).copied().unwrap_or_default();
+                    let highway = keys
+                        .iter()
+                        .zip(&vals)
+                        .find(|(k, _)| string(**k) == b"highway")
+                        .map(|(_, v)| string(*v));
+                    let is_road = highway.is_some_and(|h| {
+                        !EXCLUDED_HIGHWAYS.iter().any(|excluded| excluded.as_bytes() == h)
+                    });
+                    if is_road && refs.len() >= 2 {
+                        ways.push(refs);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn zigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+fn packed_varints(buf: &[u8]) -> Result<Vec<u64>, String> {
+    let mut reader = Reader::new(buf);
+    let mut values = Vec::new();
+    while reader.pos < buf.len() {
+        values.push(reader.varint()?);
+    }
+    Ok(values)
+}
+
+/// Packed, zigzag and delta encoded sint64 values
+fn delta_decode(buf: &[u8]) -> Result<Vec<i64>, String> {
+    let mut current = 0i64;
+    Ok(packed_varints(buf)?
+        .into_iter()
+        .map(|v| {
+            current = current.wrapping_add(zigzag(v));
+            current
+        })
+        .collect())
+}
+
+/// Protobuf field value (fixed-width values are skipped)
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Protobuf wire-format reader
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.buf.get(self.pos).ok_or("Truncated PBF varint")?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("PBF varint too long".to_string())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.buf.get(self.pos..self.pos + len).ok_or("Truncated PBF field")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u32, Value<'a>)>, String> {
+        if self.pos >= self.buf.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            wire => return Err(format!("Unsupported PBF wire type {}", wire)),
+        };
+        Ok(Some(((key >> 3) as u32, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    fn varint(out: &mut Vec<u8>, mut v: u64) {
+        while v >= 0x80 {
+            out.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn field_varint(out: &mut Vec<u8>, field: u32, v: u64) {
+        varint(out, (field as u64) << 3);
+        varint(out, v);
+    }
+
+    fn field_bytes(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+        varint(out, ((field as u64) << 3) | 2);
+        varint(out, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+
+    fn packed(values: impl IntoIterator<Item = u64>) -> Vec<u8> {
+        let mut out = Vec::new();
+        for v in values {
+            varint(&mut out, v);
+        }
+        out
+    }
+
+    fn zz(v: i64) -> u64 {
+        ((v << 1) ^ (v >> 63)) as u64
+    }
+
+    fn deltas(values: &[i64]) -> Vec<u8> {
+        let mut prev = 0;
+        packed(values.iter().map(|&v| {
+            let d = zz(v - prev);
+            prev = v;
+            d
+        }))
+    }
+
+    fn way(id: u64, refs: &[i64], key: u64, val: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        field_varint(&mut out, 1, id);
+        field_bytes(&mut out, 2, &packed([key]));
+        field_bytes(&mut out, 3, &packed([val]));
+        field_bytes(&mut out, 8, &deltas(refs));
+        out
+    }
+
+    /// Block with 4 dense nodes, one residential road, one building and one motorway
+    fn block() -> Vec<u8> {
+        let mut table = Vec::new();
+        for s in ["", "highway", "residential", "building", "yes", "motorway"] {
+            field_bytes(&mut table, 1, s.as_bytes());
+        }
+
+        // Granularity 100 nanodegrees
+        let mut dense = Vec::new();
+        field_bytes(&mut dense, 1, &deltas(&[1, 2, 3, 4]));
+        field_bytes(&mut dense, 8, &deltas(&[515_000_000, 515_010_000, 515_020_000, 515_030_000]));
+        field_bytes(&mut dense, 9, &deltas(&[-1_000_000, -1_000_000, -1_000_000, -990_000]));
+
+        let mut group = Vec::new();
+        field_bytes(&mut group, 2, &dense);
+        field_bytes(&mut group, 3, &way(10, &[1, 2, 3], 1, 2));
+        field_bytes(&mut group, 3, &way(11, &[3, 4], 3, 4));
+        field_bytes(&mut group, 3, &way(12, &[3, 4], 1, 5));
+
+        let mut block = Vec::new();
+        field_bytes(&mut block, 1, &table);
+        field_bytes(&mut block, 2, &group);
+        block
+    }
+
+    fn file_block(blob_type: &str, content: &[u8], compress: bool) -> Vec<u8> {
+        let mut blob = Vec::new();
+        if compress {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(content).unwrap();
+            field_varint(&mut blob, 2, content.len() as u64);
+            field_bytes(&mut blob, 3, &encoder.finish().unwrap());
+        } else {
+            field_bytes(&mut blob, 1, content);
+        }
+
+        let mut header = Vec::new();
+        field_bytes(&mut header, 1, blob_type.as_bytes());
+        field_varint(&mut header, 3, blob.len() as u64);
+
+        let mut out = (header.len() as u32).to_be_bytes().to_vec();
+        out.extend(header);
+        out.extend(blob);
+        out
+    }
+
+    #[test]
+    fn test_reads_highways_only() {
+        for compress in [false, true] {
+            let mut data = file_block("OSMHeader", b"ignored", compress);
+            data.extend(file_block("OSMData", &block(), compress));
+
+            let (nodes, edges) = read_roads(&data).unwrap();
+            assert_eq!(nodes.len(), 3);
+            assert_eq!(edges, vec![(0, 1), (1, 2)]);
+            assert!((nodes[0].latitude - 51.5).abs() < 1e-9);
+            assert!((nodes[2].latitude - 51.502).abs() < 1e-9);
+            assert!((nodes[0].longitude + 0.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_rejects_truncated_data() {
+        let data = file_block("OSMData", &block(), true);
+        assert!(read_roads(&data[..data.len() - 10]).is_err());
+        assert!(read_roads(&data[..2]).is_err());
+        assert_eq!(read_roads(&[]).unwrap().0.len(), 0);
+    }
+}
@@ -0,0 +1,224 @@
+//! Roads the user has never visited, from a [`RoadGraph`] and a heatmap.
+//!
+//! A road edge is visited if any point along it (sampled every half cell)
+//! falls in a heatmap cell, so resolution is that of the heatmap: a side
+//! street sharing a cell with a ridden road counts as visited. The first and
+//! last 1.5 cells of each edge are not sampled, so a street branching off a
+//! ridden road isn't visited just by its junction. Unvisited edges are joined
+//! through nodes with no other roads into portions between junctions and dead
+//! ends.
+
+use std::collections::HashSet;
+use rstar::AABB;
+use crate::geo_utils::{haversine_distance, interpolate_point, polyline_length, project_point_onto_route};
+use crate::heatmap::{HeatmapBounds, HeatmapIndex};
+use crate::GpsPoint;
+use super::RoadGraph;
+
+/// Options for [`unvisited_roads`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct UnvisitedRoadsConfig {
+    /// Area to search. Default: None (the heatmap's bounds)
+    pub bounds: Option<HeatmapBounds>,
+    /// Sort portions by distance from here instead of by length. Default: None
+    pub near: Option<GpsPoint>,
+    /// Shorter portions are left out (meters). Default: 100
+    pub min_length_meters: f64,
+    /// Maximum portions returned. Default: 50
+    pub max_results: u32,
+}
+
+impl Default for UnvisitedRoadsConfig {
+    fn default() -> Self {
+        Self {
+            bounds: None,
+            near: None,
+            min_length_meters: 100.0,
+            max_results: 50,
+        }
+    }
+}
+
+/// A stretch of road with no heatmap visits
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct UnvisitedRoad {
+    pub polyline: Vec<GpsPoint>,
+    /// Portion length in meters
+    pub length_meters: f64,
+    /// Distance from [`UnvisitedRoadsConfig::near`] to the closest point of
+    /// the portion (meters), if set
+    pub distance_meters: Option<f64>,
+}
+
+/// Road portions inside the search area that the heatmap never visits,
+/// nearest first (with [`UnvisitedRoadsConfig::near`]) or longest first.
+pub fn unvisited_roads(graph: &RoadGraph, heatmap: &HeatmapIndex, config: &UnvisitedRoadsConfig) -> Vec<UnvisitedRoad> {
+    let bounds = config.bounds.as_ref().unwrap_or(&heatmap.heatmap().bounds);
+    let cell_size = heatmap.heatmap().cell_size_meters.max(1.0);
+
+    // Unvisited edges inside the bounds, as (smaller node, larger node)
+    let unvisited: HashSet<(u32, u32)> = search_envelopes(bounds)
+        .iter()
+        .flat_map(|envelope| graph.edges.locate_in_envelope_intersecting(envelope))
+        .filter(|edge| {
+            let (a, b) = (&graph.nodes[edge.from as usize], &graph.nodes[edge.to as usize]);
+            edge_samples(a, b, cell_size).iter().all(|p| heatmap.cell_at(p.latitude, p.longitude).is_none())
+        })
+        .map(|edge| (edge.from.min(edge.to), edge.from.max(edge.to)))
+        .collect();
+
+    let mut used: HashSet<(u32, u32)> = HashSet::new();
+    let mut edges: Vec<(u32, u32)> = unvisited.iter().copied().collect();
+    edges.sort_unstable();
+
+    let mut roads: Vec<UnvisitedRoad> = Vec::new();
+    for (a, b) in edges {
+        if !used.insert((a, b)) {
+            continue;
+        }
+        // Extend through pass-through nodes in both directions
+        let forward = extend_chain(graph, &unvisited, &mut used, a, b);
+        let backward = extend_chain(graph, &unvisited, &mut used, b, a);
+        let nodes: Vec<u32> = backward.into_iter().rev().chain(forward.into_iter().skip(2)).collect();
+
+        let polyline: Vec<GpsPoint> = nodes.iter().map(|&n| graph.nodes[n as usize]).collect();
+        let length_meters = polyline_length(&polyline);
+        if length_meters < config.min_length_meters {
+            continue;
+        }
+        let distance_meters = config
+            .near
+            .and_then(|near| project_point_onto_route(&near, &polyline))
+            .map(|projection| projection.offset);
+        roads.push(UnvisitedRoad { polyline, length_meters, distance_meters });
+    }
+
+    if config.near.is_some() {
+        roads.sort_by(|a, b| a.distance_meters.unwrap_or(f64::MAX).total_cmp(&b.distance_meters.unwrap_or(f64::MAX)));
+    } else {
+        roads.sort_by(|a, b| b.length_meters.total_cmp(&a.length_meters));
+    }
+    roads.truncate(config.max_results as usize);
+    roads
+}
+
+/// Points every half cell along an edge, leaving out 1.5 cells at each end
+/// (just the midpoint for short edges).
+fn edge_samples(a: &GpsPoint, b: &GpsPoint, cell_size: f64) -> Vec<GpsPoint> {
+    let length = haversine_distance(a, b);
+    let margin = 1.5 * cell_size;
+    if length <= 2.0 * margin {
+        return vec![interpolate_point(a, b, 0.5)];
+    }
+    let inner = length - 2.0 * margin;
+    let samples = (inner / (cell_size / 2.0)).ceil() as usize;
+    (0..=samples)
+        .map(|i| interpolate_point(a, b, (margin + inner * i as f64 / samples as f64) / length))
+        .collect()
+}
+
+/// Nodes from `from` through `to` and on while each node has exactly two
+/// roads and the next edge is unvisited and unused.
+fn extend_chain(
+    graph: &RoadGraph,
+    unvisited: &HashSet<(u32, u32)>,
+    used: &mut HashSet<(u32, u32)>,
+    from: u32,
+    to: u32,
+) -> Vec<u32> {
+    let mut chain = vec![from, to];
+    let (mut prev, mut node) = (from, to);
+    loop {
+        let neighbours = &graph.adjacency[node as usize];
+        if neighbours.len() != 2 {
+            break;
+        }
+        let next = if neighbours[0].0 == prev { neighbours[1].0 } else { neighbours[0].0 };
+        let key = (node.min(next), node.max(next));
+        if !unvisited.contains(&key) || !used.insert(key) {
+            break;
+        }
+        chain.push(next);
+        (prev, node) = (node, next);
+    }
+    chain
+}
+
+/// R-tree envelopes covering `bounds`, split in two across the antimeridian.
+fn search_envelopes(bounds: &HeatmapBounds) -> Vec<AABB<[f64; 2]>> {
+    let envelope = |min_lng: f64, max_lng: f64| {
+        AABB::from_corners([bounds.min_lat, min_lng], [bounds.max_lat, max_lng])
+    };
+    if bounds.min_lng > bounds.max_lng {
+        vec![envelope(bounds.min_lng, 180.0), envelope(-180.0, bounds.max_lng)]
+    } else {
+        vec![envelope(bounds.min_lng, bounds.max_lng)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::{HeatmapConfig, generate_heatmap_from_tracks};
+
+    /// A main road north with side streets: one ridden, one with a bend and
+    /// one heading west from the start, neither ridden
+    fn graph() -> RoadGraph {
+        let nodes = vec![
+            GpsPoint::new(51.500, -0.100),
+            GpsPoint::new(51.505, -0.100),
+            GpsPoint::new(51.510, -0.100),
+            GpsPoint::new(51.505, -0.095),
+            GpsPoint::new(51.510, -0.096),
+            GpsPoint::new(51.511, -0.092),
+            GpsPoint::new(51.500, -0.106),
+        ];
+        RoadGraph::new(nodes, &[(0, 1), (1, 2), (1, 3), (2, 4), (4, 5), (0, 6)]).unwrap()
+    }
+
+    fn heatmap() -> HeatmapIndex {
+        let ride = vec![
+            GpsPoint::new(51.500, -0.100),
+            GpsPoint::new(51.510, -0.100),
+            GpsPoint::new(51.505, -0.100),
+            GpsPoint::new(51.505, -0.095),
+        ];
+        let config = HeatmapConfig { cell_size_meters: 50.0, ..HeatmapConfig::default() };
+        HeatmapIndex::new(generate_heatmap_from_tracks(&[("ride".to_string(), ride)], &HashMap::new(), &config))
+    }
+
+    fn area(min_lng: f64) -> UnvisitedRoadsConfig {
+        UnvisitedRoadsConfig {
+            bounds: Some(HeatmapBounds { min_lat: 51.49, max_lat: 51.52, min_lng, max_lng: -0.08 }),
+            ..UnvisitedRoadsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_finds_unridden_streets_longest_first() {
+        let roads = unvisited_roads(&graph(), &heatmap(), &area(-0.11));
+        assert_eq!(roads.len(), 2);
+        // The bend joins the street's two edges into one portion
+        assert_eq!(roads[0].polyline.len(), 3);
+        assert!(roads[0].length_meters > roads[1].length_meters);
+        assert!(roads[0].distance_meters.is_none());
+    }
+
+    #[test]
+    fn test_sorts_by_distance_and_respects_bounds() {
+        let index = heatmap();
+        let near = GpsPoint::new(51.500, -0.106);
+        let config = UnvisitedRoadsConfig { near: Some(near), ..area(-0.11) };
+        let roads = unvisited_roads(&graph(), &index, &config);
+        assert_eq!(roads[0].polyline.len(), 2);
+        assert!(roads[0].distance_meters.unwrap() < 1.0);
+
+        // The western street is outside the area
+        let roads = unvisited_roads(&graph(), &index, &area(-0.099));
+        assert_eq!(roads.len(), 1);
+        assert_eq!(roads[0].polyline.len(), 3);
+    }
+}
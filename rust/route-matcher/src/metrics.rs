@@ -0,0 +1,364 @@
+//! # Similarity Metrics
+//!
+//! The shared implementations of the polyline comparisons used by route
+//! matching, section detection and route hierarchies, so every caller scores
+//! geometry the same way.
+//!
+//! | Function | Description |
+//! |----------|-------------|
+//! | [`average_min_distance`] | Mean distance from each point to the nearest point of another route (projected) |
+//! | [`average_min_distance_to_polyline`] | Same, to the nearest line of another route |
+//! | [`average_min_distance_with_model`] | Either, between GPS routes with distances from a [`DistanceModel`] |
+//! | [`polyline_amd`] | Symmetric AMD of two GPS polylines after resampling both |
+//! | [`amd_to_percentage`] | AMD as a 0-100 match percentage between two thresholds |
+//! | [`containment`] | Fraction of one polyline's points near another |
+//! | [`shared_fraction`] | Containment in both directions (the smaller) |
+//!
+//! [`polyline_amd`] always respaces both polylines evenly, even those already
+//! at the sample count; route matching uses [`crate::geo_utils::resample_polyline`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::metrics::{amd_to_percentage, polyline_amd, shared_fraction};
+//!
+//! let a: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.1000)).collect();
+//! let b: Vec<GpsPoint> = (0..20).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.1003)).collect();
+//!
+//! let amd = polyline_amd(&a, &b, 50);
+//! assert!((amd - 20.8).abs() < 0.5);
+//! assert_eq!(amd_to_percentage(amd, 30.0, 250.0), 100.0);
+//! assert_eq!(shared_fraction(&a, &b, 30.0), 1.0);
+//! ```
+
+use rstar::primitives::{GeomWithData, Line};
+use rstar::{PointDistance, RTree};
+use crate::geo_utils::{resample_evenly, DistanceModel};
+use crate::projection::{distance_sq, LocalProjection, PlanarPoint};
+use crate::GpsPoint;
+
+/// Number of point pairs above which AMD switches from brute force to an R-tree.
+/// Below this the index build costs more than it saves (default resample_count is 50).
+const AMD_INDEX_MIN_PAIRS: usize = 64 * 64;
+
+/// Calculate Average Minimum Distance from route1 to route2 (projected, meters).
+/// For each point in route1, find the minimum distance to any point in route2.
+/// Return the average of these minimum distances.
+///
+/// Small routes use an O(n·m) scan; larger ones index route2 in an R-tree so
+/// each lookup is O(log m), which keeps `resample_count` values of 200+ cheap.
+///
+/// `gaps1` / `gaps2` flag points lying on a recording gap (empty: no gaps).
+/// route1 points on a gap, or whose nearest route2 point is on one, are left
+/// out: there is no evidence either way there.
+pub fn average_min_distance(route1: &[PlanarPoint], route2: &[PlanarPoint], gaps1: &[bool], gaps2: &[bool]) -> f64 {
+    if route1.is_empty() || route2.is_empty() {
+        return f64::INFINITY;
+    }
+
+    mean_nearest_distance(&nearest_points(route1, route2), gaps1, gaps2)
+}
+
+/// For each route1 point, the squared distance to and index of the nearest
+/// route2 point.
+fn nearest_points(route1: &[PlanarPoint], route2: &[PlanarPoint]) -> Vec<(f64, usize)> {
+    // Compare squared distances, take a single sqrt per point
+    if route1.len() * route2.len() >= AMD_INDEX_MIN_PAIRS {
+        let indexed = route2.iter().enumerate().map(|(j, p)| GeomWithData::new(*p, j)).collect();
+        let tree = RTree::bulk_load(indexed);
+        route1
+            .iter()
+            .map(|p1| {
+                tree.nearest_neighbor(p1)
+                    .map_or((f64::INFINITY, 0), |p2| (distance_sq(*p1, *p2.geom()), p2.data))
+            })
+            .collect()
+    } else {
+        route1
+            .iter()
+            .map(|p1| {
+                route2
+                    .iter()
+                    .enumerate()
+                    .map(|(j, p2)| (distance_sq(*p1, *p2), j))
+                    .fold((f64::INFINITY, 0), |best, d| if d.0 < best.0 { d } else { best })
+            })
+            .collect()
+    }
+}
+
+/// Calculate Average Minimum Distance from route1 to the polyline through
+/// route2 (projected, meters): like [`average_min_distance`], but each point is
+/// measured against the nearest line between consecutive route2 points.
+/// `line_gaps` flags lines `route2[j]` → `route2[j + 1]` on a recording gap.
+pub fn average_min_distance_to_polyline(
+    route1: &[PlanarPoint],
+    route2: &[PlanarPoint],
+    gaps1: &[bool],
+    line_gaps: &[bool],
+) -> f64 {
+    if route2.len() < 2 {
+        return average_min_distance(route1, route2, gaps1, &[]);
+    }
+    if route1.is_empty() {
+        return f64::INFINITY;
+    }
+
+    let lines = planar_lines(route2);
+    mean_nearest_distance(&nearest_lines(route1, &lines), gaps1, line_gaps)
+}
+
+/// The lines between consecutive points of a projected route.
+fn planar_lines(route: &[PlanarPoint]) -> Vec<Line<PlanarPoint>> {
+    route.windows(2).map(|w| Line::new(w[0], w[1])).collect()
+}
+
+/// For each route1 point, the squared distance to and index of the nearest line.
+fn nearest_lines(route1: &[PlanarPoint], lines: &[Line<PlanarPoint>]) -> Vec<(f64, usize)> {
+    if route1.len() * lines.len() >= AMD_INDEX_MIN_PAIRS {
+        let indexed = lines.iter().copied().enumerate().map(|(j, line)| GeomWithData::new(line, j)).collect();
+        let tree = RTree::bulk_load(indexed);
+        route1
+            .iter()
+            .map(|p1| {
+                tree.nearest_neighbor(p1)
+                    .map_or((f64::INFINITY, 0), |line| (line.geom().distance_2(p1), line.data))
+            })
+            .collect()
+    } else {
+        route1
+            .iter()
+            .map(|p1| {
+                lines
+                    .iter()
+                    .enumerate()
+                    .map(|(j, line)| (line.distance_2(p1), j))
+                    .fold((f64::INFINITY, 0), |best, d| if d.0 < best.0 { d } else { best })
+            })
+            .collect()
+    }
+}
+
+/// Average Minimum Distance from route1 to route2 (GPS, meters) with
+/// distances measured by `model`: each point's nearest route2 point (or line,
+/// with `to_polyline`) is found in `projection` as in [`average_min_distance`]
+/// and [`average_min_distance_to_polyline`], then the distance to it is
+/// measured with `model`. `gaps2` flags route2 lines when `to_polyline`.
+pub fn average_min_distance_with_model(
+    route1: &[GpsPoint],
+    route2: &[GpsPoint],
+    gaps1: &[bool],
+    gaps2: &[bool],
+    to_polyline: bool,
+    projection: &LocalProjection,
+    model: DistanceModel,
+) -> f64 {
+    if route1.is_empty() || route2.is_empty() {
+        return f64::INFINITY;
+    }
+
+    let planar1 = projection.project_all(route1);
+    let planar2 = projection.project_all(route2);
+
+    if to_polyline && route2.len() >= 2 {
+        let lines = planar_lines(&planar2);
+        let nearest: Vec<(f64, usize)> = nearest_lines(&planar1, &lines)
+            .into_iter()
+            .zip(planar1.iter().zip(route1))
+            .map(|((_, j), (planar, point))| {
+                let closest = projection.unproject(lines[j].nearest_point(planar));
+                (model.distance(point, &closest).powi(2), j)
+            })
+            .collect();
+        mean_nearest_distance(&nearest, gaps1, gaps2)
+    } else {
+        let gaps2 = if to_polyline { &[] } else { gaps2 };
+        let nearest: Vec<(f64, usize)> = nearest_points(&planar1, &planar2)
+            .into_iter()
+            .zip(route1)
+            .map(|((_, j), point)| (model.distance(point, &route2[j]).powi(2), j))
+            .collect();
+        mean_nearest_distance(&nearest, gaps1, gaps2)
+    }
+}
+
+/// Mean of the (squared distance, nearest index) pairs from an AMD, leaving
+/// out points flagged in `gaps1` and those whose nearest feature is flagged
+/// in `gaps2`. Infinite if nothing is left.
+fn mean_nearest_distance(nearest: &[(f64, usize)], gaps1: &[bool], gaps2: &[bool]) -> f64 {
+    let on_gap = |gaps: &[bool], i: usize| gaps.get(i).copied().unwrap_or(false);
+    let (total, count) = nearest
+        .iter()
+        .enumerate()
+        .filter(|&(i, &(_, j))| !on_gap(gaps1, i) && !on_gap(gaps2, j))
+        .fold((0.0, 0usize), |(total, count), (_, &(dist_sq, _))| (total + dist_sq.sqrt(), count + 1));
+
+    if count == 0 {
+        f64::INFINITY
+    } else {
+        total / count as f64
+    }
+}
+
+/// Convert AMD to a match percentage using thresholds.
+/// - AMD <= perfect_threshold → 100% match
+/// - AMD >= zero_threshold → 0% match
+/// - Linear interpolation between
+pub fn amd_to_percentage(amd: f64, perfect_threshold: f64, zero_threshold: f64) -> f64 {
+    if amd <= perfect_threshold {
+        return 100.0;
+    }
+    if amd >= zero_threshold {
+        return 0.0;
+    }
+
+    // Linear interpolation
+    100.0 * (1.0 - (amd - perfect_threshold) / (zero_threshold - perfect_threshold))
+}
+
+/// Symmetric Average Minimum Distance between two GPS polylines (meters):
+/// both are resampled to `samples` points and AMD is averaged over both
+/// directions. Infinite if either is empty.
+pub fn polyline_amd(poly_a: &[GpsPoint], poly_b: &[GpsPoint], samples: usize) -> f64 {
+    if poly_a.is_empty() || poly_b.is_empty() {
+        return f64::INFINITY;
+    }
+
+    let resampled_a = resample_evenly(poly_a, samples);
+    let resampled_b = resample_evenly(poly_b, samples);
+
+    // Project both into local meters around polyline A
+    let projection = LocalProjection::for_points(&resampled_a);
+    let planar_a = projection.project_all(&resampled_a);
+    let planar_b = projection.project_all(&resampled_b);
+
+    (average_min_distance(&planar_a, &planar_b, &[], &[]) + average_min_distance(&planar_b, &planar_a, &[], &[])) / 2.0
+}
+
+/// Fraction of points in `points_a` within `threshold` meters of any point
+/// in `points_b` (0 if either is empty).
+pub fn containment(points_a: &[GpsPoint], points_b: &[GpsPoint], threshold: f64) -> f64 {
+    if points_a.is_empty() || points_b.is_empty() {
+        return 0.0;
+    }
+
+    let projection = LocalProjection::for_points(points_a);
+    let planar_a = projection.project_all(points_a);
+    let planar_b = projection.project_all(points_b);
+    let threshold_sq = threshold * threshold;
+
+    let contained = planar_a
+        .iter()
+        .filter(|&&p| planar_b.iter().any(|&q| distance_sq(p, q) <= threshold_sq))
+        .count();
+
+    contained as f64 / points_a.len() as f64
+}
+
+/// Fraction of geometry shared by two polylines: the smaller
+/// [`containment`] of the two directions.
+pub fn shared_fraction(points_a: &[GpsPoint], points_b: &[GpsPoint], threshold: f64) -> f64 {
+    containment(points_a, points_b, threshold).min(containment(points_b, points_a, threshold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexed_amd_matches_brute_force() {
+        // Two wiggly 300-point routes, large enough to use the R-tree path
+        let route = |phase: f64| -> Vec<PlanarPoint> {
+            (0..300)
+                .map(|i| {
+                    let t = i as f64 * 10.0;
+                    [t, (t / 150.0 + phase).sin() * 40.0]
+                })
+                .collect()
+        };
+        let (a, b) = (route(0.0), route(0.3));
+        assert!(a.len() * b.len() >= AMD_INDEX_MIN_PAIRS);
+
+        let brute: f64 = a
+            .iter()
+            .map(|p| b.iter().map(|q| distance_sq(*p, *q)).fold(f64::INFINITY, f64::min).sqrt())
+            .sum::<f64>()
+            / a.len() as f64;
+
+        assert!((average_min_distance(&a, &b, &[], &[]) - brute).abs() < 1e-9);
+        assert!(average_min_distance(&a, &[], &[], &[]).is_infinite());
+    }
+
+    #[test]
+    fn test_polyline_amd_ignores_vertex_spacing() {
+        // The same straight 1km road, sampled every 100m with a 50m phase shift
+        let a: Vec<PlanarPoint> = (0..=10).map(|i| [i as f64 * 100.0, 0.0]).collect();
+        let b: Vec<PlanarPoint> = (0..10).map(|i| [i as f64 * 100.0 + 50.0, 0.0]).collect();
+
+        assert!(average_min_distance(&a, &b, &[], &[]) > 40.0);
+        // Only a's two points overhanging b's ends (50m each) still count
+        assert!((average_min_distance_to_polyline(&a, &b, &[], &[]) - 100.0 / 11.0).abs() < 1e-9);
+        assert!(average_min_distance_to_polyline(&b, &a, &[], &[]) < 1e-9);
+
+        // Parallel road 30m away still scores its true offset
+        let c: Vec<PlanarPoint> = a.iter().map(|p| [p[0], 30.0]).collect();
+        assert!((average_min_distance_to_polyline(&c, &a, &[], &[]) - 30.0).abs() < 1e-9);
+
+        // Indexed path agrees with the scan
+        let long: Vec<PlanarPoint> = (0..300).map(|i| [i as f64 * 10.0, (i as f64 / 15.0).sin() * 40.0]).collect();
+        let shifted: Vec<PlanarPoint> = long.iter().map(|p| [p[0] + 5.0, p[1] + 3.0]).collect();
+        let brute: f64 = shifted
+            .iter()
+            .map(|p| long.windows(2).map(|w| Line::new(w[0], w[1]).distance_2(p)).fold(f64::INFINITY, f64::min).sqrt())
+            .sum::<f64>()
+            / shifted.len() as f64;
+        assert!((average_min_distance_to_polyline(&shifted, &long, &[], &[]) - brute).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_polyline_amd_is_symmetric_and_resampled() {
+        // The same road, one recorded with 5x the points
+        let sparse: Vec<GpsPoint> = (0..=10).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.1)).collect();
+        let dense: Vec<GpsPoint> = (0..=50).map(|i| GpsPoint::new(51.50 + i as f64 * 0.0002, -0.1)).collect();
+        assert!(polyline_amd(&sparse, &dense, 50) < 0.01);
+
+        let shifted: Vec<GpsPoint> = sparse.iter().map(|p| GpsPoint::new(p.latitude, p.longitude + 0.001)).collect();
+        let amd = polyline_amd(&sparse, &shifted, 50);
+        assert!((amd - polyline_amd(&shifted, &sparse, 50)).abs() < 1e-6);
+        assert!((amd - 69.4).abs() < 0.5);
+        assert!(polyline_amd(&sparse, &[], 50).is_infinite());
+    }
+
+    #[test]
+    fn test_amd_with_model_matches_planar() {
+        // A north-south road and a copy 0.001° to the east (~69m at 51.5°N)
+        let road: Vec<GpsPoint> = (0..=10).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.1)).collect();
+        let shifted: Vec<GpsPoint> = road.iter().map(|p| GpsPoint::new(p.latitude + 0.0005, p.longitude + 0.001)).collect();
+        let projection = LocalProjection::for_points(&road);
+        let (planar_road, planar_shifted) = (projection.project_all(&road), projection.project_all(&shifted));
+
+        let planar = average_min_distance(&planar_road, &planar_shifted, &[], &[]);
+        let haversine = average_min_distance_with_model(&road, &shifted, &[], &[], false, &projection, DistanceModel::Haversine);
+        let geodesic = average_min_distance_with_model(&road, &shifted, &[], &[], false, &projection, DistanceModel::Geodesic);
+        assert!((haversine - planar).abs() < 0.1);
+        assert!((geodesic - planar).abs() / planar < 0.005);
+        assert!(geodesic != haversine);
+
+        let planar = average_min_distance_to_polyline(&planar_road, &planar_shifted, &[], &[]);
+        let geodesic = average_min_distance_with_model(&road, &shifted, &[], &[], true, &projection, DistanceModel::Geodesic);
+        assert!((geodesic - planar).abs() / planar < 0.005);
+        assert!(average_min_distance_with_model(&road, &[], &[], &[], true, &projection, DistanceModel::Geodesic).is_infinite());
+    }
+
+    #[test]
+    fn test_containment_and_shared_fraction() {
+        let road: Vec<GpsPoint> = (0..10).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.1)).collect();
+        let half: Vec<GpsPoint> = road[..5].to_vec();
+
+        assert_eq!(containment(&half, &road, 10.0), 1.0);
+        assert_eq!(containment(&road, &half, 10.0), 0.5);
+        assert_eq!(shared_fraction(&road, &half, 10.0), 0.5);
+        assert_eq!(containment(&road, &[], 10.0), 0.0);
+    }
+}
@@ -0,0 +1,625 @@
+//! # Mapbox Vector Tiles
+//!
+//! Encodes heatmap cells and section polylines as [Mapbox Vector Tiles]
+//! (MVT 2.1) so MapLibre on iOS/Android can render them on the GPU as
+//! ordinary vector sources.
+//!
+//! [Mapbox Vector Tiles]: https://github.com/mapbox/vector-tile-spec/tree/master/2.1
+//!
+//! ## Layers
+//!
+//! | Layer | Geometry | Properties |
+//! |-------|----------|------------|
+//! | `heatmap` | Polygon (one square per cell) | `density`, `visit_count`, `unique_route_count`, `is_common_path` |
+//! | `sections` | LineString / MultiLineString | `id`, `sport_type`, `visit_count`, `distance_meters`, `confidence` |
+//!
+//! Each function returns an uncompressed tile with a single layer. MVT layers
+//! are a repeated protobuf field, so concatenating the bytes of
+//! [`encode_heatmap_tile`] and [`encode_sections_tile`] yields one valid tile
+//! containing both layers.
+//!
+//! Geometry outside the tile is dropped, except for a small buffer so lines
+//! and cells are not visibly cut at tile edges. Lines and cells reaching
+//! beyond the buffer are cut at its edge. The protobuf encoding is
+//! written by hand to keep the mobile binaries free of extra dependencies.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use route_matcher::{GpsPoint, HeatmapConfig, generate_heatmap_from_tracks};
+//! use route_matcher::heatmap::tiles::lat_lng_to_tile;
+//! use route_matcher::mvt::encode_heatmap_tile;
+//!
+//! let track = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+//! let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+//!
+//! let tile = lat_lng_to_tile(51.505, -0.10, 14);
+//! let bytes = encode_heatmap_tile(&heatmap, tile);
+//! assert!(!bytes.is_empty());
+//! ```
+
+use std::collections::HashMap;
+use crate::heatmap::tiles::{MAX_TILE_ZOOM, TileCoord, world_tile_position};
+use crate::{FrequentSection, GpsPoint, HeatmapResult};
+
+/// Tile coordinate extent (MVT default)
+pub const TILE_EXTENT: u32 = 4096;
+
+/// Geometry kept beyond each tile edge, in tile units
+const TILE_BUFFER: i32 = 64;
+
+/// Name of the heatmap layer
+pub const HEATMAP_LAYER: &str = "heatmap";
+
+/// Name of the sections layer
+pub const SECTIONS_LAYER: &str = "sections";
+
+/// Encode heatmap cells intersecting `tile` as a single-layer MVT.
+///
+/// Returns an empty vector when no cell intersects the tile or its zoom is
+/// above [`MAX_TILE_ZOOM`].
+pub fn encode_heatmap_tile(heatmap: &HeatmapResult, tile: TileCoord) -> Vec<u8> {
+    if tile.z > MAX_TILE_ZOOM {
+        return Vec::new();
+    }
+    let mut layer = LayerBuilder::new(HEATMAP_LAYER);
+    let half_lat = heatmap.cell_size_meters / 2.0 / 111_320.0;
+
+    for cell in &heatmap.cells {
+        let half_lng = half_lat / cell.center_lat.to_radians().cos().max(0.01);
+        let (x0, y0) = to_tile_units(cell.center_lat + half_lat, cell.center_lng - half_lng, tile);
+        let (x1, y1) = to_tile_units(cell.center_lat - half_lat, cell.center_lng + half_lng, tile);
+        let (x0, y0, x1, y1) = (x0.round(), y0.round(), x1.round(), y1.round());
+        // Keep cells visible at low zoom where they shrink below one unit
+        let (x1, y1) = (x1.max(x0 + 1.0), y1.max(y0 + 1.0));
+
+        if !intersects_tile(x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)) {
+            continue;
+        }
+
+        let ring = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)].map(buffered_units);
+        layer.add_feature(
+            GEOM_POLYGON,
+            encode_polygon(&ring),
+            vec![
+                ("density", Value::Double(cell.density as f64)),
+                ("visit_count", Value::Uint(cell.visit_count as u64)),
+                ("unique_route_count", Value::Uint(cell.unique_route_count as u64)),
+                ("is_common_path", Value::Bool(cell.is_common_path)),
+            ],
+        );
+    }
+
+    layer.encode_tile()
+}
+
+/// Encode section polylines intersecting `tile` as a single-layer MVT.
+///
+/// Polylines leaving and re-entering the tile become MultiLineStrings.
+/// Returns an empty vector when no section intersects the tile or its zoom
+/// is above [`MAX_TILE_ZOOM`].
+pub fn encode_sections_tile(sections: &[FrequentSection], tile: TileCoord) -> Vec<u8> {
+    if tile.z > MAX_TILE_ZOOM {
+        return Vec::new();
+    }
+    let mut layer = LayerBuilder::new(SECTIONS_LAYER);
+
+    for section in sections {
+        let lines = clip_polyline(&section.polyline, tile);
+        if lines.is_empty() {
+            continue;
+        }
+
+        layer.add_feature(
+            GEOM_LINESTRING,
+            encode_lines(&lines),
+            vec![
+                ("id", Value::String(section.id.clone())),
+                ("sport_type", Value::String(section.sport_type.clone())),
+                ("visit_count", Value::Uint(section.visit_count as u64)),
+                ("distance_meters", Value::Double(section.distance_meters)),
+                ("confidence", Value::Double(section.confidence)),
+            ],
+        );
+    }
+
+    layer.encode_tile()
+}
+
+// =============================================================================
+// Projection
+// =============================================================================
+
+/// Web Mercator position in tile units relative to the tile's top-left corner.
+///
+/// Unrounded: far from the tile at high zoom these exceed the `i32` range of
+/// encoded geometry, so positions are cut to the buffered tile first.
+fn to_tile_units(lat: f64, lng: f64, tile: TileCoord) -> (f64, f64) {
+    let (world_x, world_y) = world_tile_position(lat, lng, tile.z);
+    let extent = TILE_EXTENT as f64;
+    ((world_x - tile.x as f64) * extent, (world_y - tile.y as f64) * extent)
+}
+
+/// Lowest and highest tile units kept (the tile plus its buffer).
+fn buffer_bounds() -> (f64, f64) {
+    (-TILE_BUFFER as f64, (TILE_EXTENT as i32 + TILE_BUFFER) as f64)
+}
+
+fn intersects_tile(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> bool {
+    let (lo, hi) = buffer_bounds();
+    max_x >= lo && min_x <= hi && max_y >= lo && min_y <= hi
+}
+
+/// Round a position to tile units, clamped to the buffered tile.
+fn buffered_units((x, y): (f64, f64)) -> (i32, i32) {
+    let (lo, hi) = buffer_bounds();
+    (x.clamp(lo, hi).round() as i32, y.clamp(lo, hi).round() as i32)
+}
+
+/// The part of the step `a` → `b` inside the buffered tile (Liang–Barsky).
+fn clip_step(a: (f64, f64), b: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (lo, hi) = buffer_bounds();
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+
+    for (p, q) in [(-dx, a.0 - lo), (dx, hi - a.0), (-dy, a.1 - lo), (dy, hi - a.1)] {
+        if p == 0.0 {
+            // Parallel to this edge: inside it or never
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+
+    (t0 <= t1).then_some(((a.0 + t0 * dx, a.1 + t0 * dy), (a.0 + t1 * dx, a.1 + t1 * dy)))
+}
+
+/// Split a polyline into the runs inside the buffered tile, cut at its edge.
+fn clip_polyline(points: &[GpsPoint], tile: TileCoord) -> Vec<Vec<(i32, i32)>> {
+    let projected: Vec<(f64, f64)> = points.iter().map(|p| to_tile_units(p.latitude, p.longitude, tile)).collect();
+
+    let mut lines = Vec::new();
+    let mut current: Vec<(i32, i32)> = Vec::new();
+    let mut finish = |current: &mut Vec<(i32, i32)>| {
+        if current.len() >= 2 {
+            lines.push(std::mem::take(current));
+        } else {
+            current.clear();
+        }
+    };
+
+    for pair in projected.windows(2) {
+        match clip_step(pair[0], pair[1]) {
+            Some((a, b)) => {
+                let (a, b) = (buffered_units(a), buffered_units(b));
+                // A step re-entering the buffer starts a new line
+                if current.last() != Some(&a) {
+                    finish(&mut current);
+                    current.push(a);
+                }
+                if current.last() != Some(&b) {
+                    current.push(b);
+                }
+            }
+            None => finish(&mut current),
+        }
+    }
+    finish(&mut current);
+
+    lines
+}
+
+// =============================================================================
+// Geometry Encoding
+// =============================================================================
+
+const GEOM_LINESTRING: u32 = 2;
+const GEOM_POLYGON: u32 = 3;
+
+const CMD_MOVE_TO: u32 = 1;
+const CMD_LINE_TO: u32 = 2;
+const CMD_CLOSE_PATH: u32 = 7;
+
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Geometry encoder tracking the cursor between commands.
+#[derive(Default)]
+struct GeometryWriter {
+    data: Vec<u32>,
+    cursor: (i32, i32),
+}
+
+impl GeometryWriter {
+    fn move_to(&mut self, point: (i32, i32)) {
+        self.data.push(command(CMD_MOVE_TO, 1));
+        self.push_delta(point);
+    }
+
+    fn line_to(&mut self, points: &[(i32, i32)]) {
+        self.data.push(command(CMD_LINE_TO, points.len() as u32));
+        for &point in points {
+            self.push_delta(point);
+        }
+    }
+
+    fn close_path(&mut self) {
+        self.data.push(command(CMD_CLOSE_PATH, 1));
+    }
+
+    fn push_delta(&mut self, point: (i32, i32)) {
+        self.data.push(zigzag(point.0 - self.cursor.0));
+        self.data.push(zigzag(point.1 - self.cursor.1));
+        self.cursor = point;
+    }
+}
+
+/// Encode a single exterior ring (clockwise in tile coordinates, not closed).
+fn encode_polygon(ring: &[(i32, i32)]) -> Vec<u32> {
+    let mut writer = GeometryWriter::default();
+    writer.move_to(ring[0]);
+    writer.line_to(&ring[1..]);
+    writer.close_path();
+    writer.data
+}
+
+fn encode_lines(lines: &[Vec<(i32, i32)>]) -> Vec<u32> {
+    let mut writer = GeometryWriter::default();
+    for line in lines {
+        writer.move_to(line[0]);
+        writer.line_to(&line[1..]);
+    }
+    writer.data
+}
+
+// =============================================================================
+// Protobuf Encoding
+// =============================================================================
+
+/// Feature property value.
+enum Value {
+    String(String),
+    Double(f64),
+    Uint(u64),
+    Bool(bool),
+}
+
+impl Value {
+    /// Encoded `Value` message, also used as the interning key.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            Value::String(s) => write_bytes_field(&mut buf, 1, s.as_bytes()),
+            Value::Double(v) => {
+                write_key(&mut buf, 3, WIRE_FIXED64);
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+            Value::Uint(v) => write_varint_field(&mut buf, 5, *v),
+            Value::Bool(v) => write_varint_field(&mut buf, 7, *v as u64),
+        }
+        buf
+    }
+}
+
+/// Collects features for one layer, interning property keys and values.
+struct LayerBuilder {
+    name: &'static str,
+    keys: Vec<&'static str>,
+    key_index: HashMap<&'static str, u32>,
+    values: Vec<Vec<u8>>,
+    value_index: HashMap<Vec<u8>, u32>,
+    features: Vec<Vec<u8>>,
+}
+
+impl LayerBuilder {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            keys: Vec::new(),
+            key_index: HashMap::new(),
+            values: Vec::new(),
+            value_index: HashMap::new(),
+            features: Vec::new(),
+        }
+    }
+
+    fn add_feature(&mut self, geom_type: u32, geometry: Vec<u32>, properties: Vec<(&'static str, Value)>) {
+        let mut tags = Vec::with_capacity(properties.len() * 2);
+        for (key, value) in properties {
+            let key_id = *self.key_index.entry(key).or_insert_with(|| {
+                self.keys.push(key);
+                (self.keys.len() - 1) as u32
+            });
+            let encoded = value.encode();
+            let value_id = match self.value_index.get(&encoded) {
+                Some(&id) => id,
+                None => {
+                    self.values.push(encoded.clone());
+                    let id = (self.values.len() - 1) as u32;
+                    self.value_index.insert(encoded, id);
+                    id
+                }
+            };
+            tags.push(key_id);
+            tags.push(value_id);
+        }
+
+        let mut feature = Vec::new();
+        write_varint_field(&mut feature, 1, self.features.len() as u64 + 1);
+        write_packed_field(&mut feature, 2, &tags);
+        write_varint_field(&mut feature, 3, geom_type as u64);
+        write_packed_field(&mut feature, 4, &geometry);
+        self.features.push(feature);
+    }
+
+    /// Encode as a `Tile` message holding this layer (empty if no features).
+    fn encode_tile(self) -> Vec<u8> {
+        if self.features.is_empty() {
+            return Vec::new();
+        }
+
+        let mut layer = Vec::new();
+        write_varint_field(&mut layer, 15, 2); // version
+        write_bytes_field(&mut layer, 1, self.name.as_bytes());
+        for feature in &self.features {
+            write_bytes_field(&mut layer, 2, feature);
+        }
+        for key in &self.keys {
+            write_bytes_field(&mut layer, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            write_bytes_field(&mut layer, 4, value);
+        }
+        write_varint_field(&mut layer, 5, TILE_EXTENT as u64);
+
+        let mut tile = Vec::with_capacity(layer.len() + 8);
+        write_bytes_field(&mut tile, 3, &layer);
+        tile
+    }
+}
+
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LEN: u32 = 2;
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn write_key(buf: &mut Vec<u8>, field: u32, wire_type: u32) {
+    write_varint(buf, ((field << 3) | wire_type) as u64);
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field: u32, value: u64) {
+    write_key(buf, field, WIRE_VARINT);
+    write_varint(buf, value);
+}
+
+fn write_bytes_field(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    write_key(buf, field, WIRE_LEN);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_packed_field(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = Vec::with_capacity(values.len() * 2);
+    for &v in values {
+        write_varint(&mut packed, v as u64);
+    }
+    write_bytes_field(buf, field, &packed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heatmap::tiles::lat_lng_to_tile;
+    use crate::{HeatmapConfig, generate_heatmap_from_tracks};
+
+    /// Minimal protobuf reader: (field, varint value or length-delimited bytes).
+    fn read_fields(mut buf: &[u8]) -> Vec<(u32, u64, Vec<u8>)> {
+        fn varint(buf: &mut &[u8]) -> u64 {
+            let mut value = 0u64;
+            let mut shift = 0;
+            loop {
+                let byte = buf[0];
+                *buf = &buf[1..];
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte < 0x80 {
+                    return value;
+                }
+                shift += 7;
+            }
+        }
+
+        let mut fields = Vec::new();
+        while !buf.is_empty() {
+            let key = varint(&mut buf);
+            let (field, wire) = ((key >> 3) as u32, (key & 0x7) as u32);
+            match wire {
+                WIRE_VARINT => fields.push((field, varint(&mut buf), Vec::new())),
+                WIRE_FIXED64 => {
+                    fields.push((field, 0, buf[..8].to_vec()));
+                    buf = &buf[8..];
+                }
+                _ => {
+                    let len = varint(&mut buf) as usize;
+                    fields.push((field, 0, buf[..len].to_vec()));
+                    buf = &buf[len..];
+                }
+            }
+        }
+        fields
+    }
+
+    fn layers(tile: &[u8]) -> Vec<Vec<(u32, u64, Vec<u8>)>> {
+        read_fields(tile).into_iter().filter(|f| f.0 == 3).map(|f| read_fields(&f.2)).collect()
+    }
+
+    fn section(id: &str, polyline: Vec<GpsPoint>) -> FrequentSection {
+        FrequentSection {
+            id: id.to_string(),
+            sport_type: "Run".to_string(),
+            polyline,
+            representative_activity_id: "a".to_string(),
+            activity_ids: vec!["a".to_string()],
+            activity_portions: Vec::new(),
+            route_ids: Vec::new(),
+            visit_count: 3,
+            distance_meters: 1000.0,
+            activity_traces: HashMap::new(),
+            confidence: 0.8,
+            observation_count: 3,
+            average_spread: 5.0,
+            point_density: Vec::new(),
+            same_direction: None,
+            reverse_direction: None,
+            suggested_name: None,
+            matching_threshold: None,
+        }
+    }
+
+    #[test]
+    fn test_geometry_commands() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        // Example from the MVT spec: a polygon ring (3,6) (8,12) (20,34)
+        assert_eq!(
+            encode_polygon(&[(3, 6), (8, 12), (20, 34)]),
+            vec![9, 6, 12, 18, 10, 12, 24, 44, 15]
+        );
+    }
+
+    #[test]
+    fn test_heatmap_tile_layer() {
+        let track = vec![GpsPoint::new(47.37, 8.54), GpsPoint::new(47.375, 8.54)];
+        let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), track)], &HashMap::new(), &HeatmapConfig::default());
+        let tile = lat_lng_to_tile(47.372, 8.54, 14);
+
+        let bytes = encode_heatmap_tile(&heatmap, tile);
+        let layers = layers(&bytes);
+        assert_eq!(layers.len(), 1);
+
+        let layer = &layers[0];
+        assert!(layer.contains(&(15, 2, Vec::new())));
+        assert!(layer.contains(&(1, 0, HEATMAP_LAYER.as_bytes().to_vec())));
+        assert!(layer.contains(&(5, TILE_EXTENT as u64, Vec::new())));
+        let features = layer.iter().filter(|f| f.0 == 2).count();
+        assert!(features > 0 && features <= heatmap.cells.len());
+
+        // A tile on the other side of the world is empty, as are zooms past the maximum
+        assert!(encode_heatmap_tile(&heatmap, lat_lng_to_tile(-33.9, 151.2, 14)).is_empty());
+        let deepest = lat_lng_to_tile(heatmap.cells[0].center_lat, heatmap.cells[0].center_lng, MAX_TILE_ZOOM);
+        assert!(!encode_heatmap_tile(&heatmap, deepest).is_empty());
+        assert!(encode_heatmap_tile(&heatmap, TileCoord { z: MAX_TILE_ZOOM + 1, ..deepest }).is_empty());
+    }
+
+    /// Absolute vertices of encoded geometry commands.
+    fn vertices(geometry: &[u8]) -> Vec<(i64, i64)> {
+        let mut values = Vec::new();
+        let mut buf = geometry;
+        while !buf.is_empty() {
+            let mut value = 0u32;
+            let mut shift = 0;
+            loop {
+                let byte = buf[0];
+                buf = &buf[1..];
+                value |= ((byte & 0x7f) as u32) << shift;
+                if byte < 0x80 {
+                    break;
+                }
+                shift += 7;
+            }
+            values.push(value);
+        }
+
+        let unzigzag = |v: u32| ((v >> 1) as i64) ^ -((v & 1) as i64);
+        let (mut cursor, mut points, mut i) = ((0, 0), Vec::new(), 0);
+        while i < values.len() {
+            let (id, count) = (values[i] & 0x7, values[i] >> 3);
+            i += 1;
+            if id == CMD_CLOSE_PATH {
+                continue;
+            }
+            for _ in 0..count {
+                cursor = (cursor.0 + unzigzag(values[i]), cursor.1 + unzigzag(values[i + 1]));
+                points.push(cursor);
+                i += 2;
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_far_geometry_is_cut_at_buffer() {
+        // Max zoom tile in Zurich: a section from Seattle to Sydney is far
+        // beyond the i32 range in tile units, a 2km cell well beyond the buffer
+        let tile = lat_lng_to_tile(47.37, 8.54, 24);
+        let polyline = vec![GpsPoint::new(47.6, -122.3), GpsPoint::new(47.37, 8.54), GpsPoint::new(-33.9, 151.2)];
+        let sections = vec![section("long", polyline)];
+        let bytes = encode_sections_tile(&sections, tile);
+        let geometry = &read_fields(&layers(&bytes)[0].iter().find(|f| f.0 == 2).unwrap().2)
+            .into_iter()
+            .find(|f| f.0 == 4)
+            .unwrap()
+            .2;
+
+        let (lo, hi) = (-TILE_BUFFER as i64, (TILE_EXTENT as i32 + TILE_BUFFER) as i64);
+        let points = vertices(geometry);
+        assert_eq!(points.len(), 3);
+        assert!(points.iter().all(|&(x, y)| (lo..=hi).contains(&x) && (lo..=hi).contains(&y)));
+
+        let config = HeatmapConfig { cell_size_meters: 2000.0, ..HeatmapConfig::default() };
+        let heatmap = generate_heatmap_from_tracks(&[("a".to_string(), sections[0].polyline[1..2].to_vec())], &HashMap::new(), &config);
+        let bytes = encode_heatmap_tile(&heatmap, tile);
+        let geometry = &read_fields(&layers(&bytes)[0].iter().find(|f| f.0 == 2).unwrap().2)
+            .into_iter()
+            .find(|f| f.0 == 4)
+            .unwrap()
+            .2;
+        let points = vertices(geometry);
+        assert_eq!(points.len(), 4);
+        assert!(points.iter().all(|&(x, y)| (lo..=hi).contains(&x) && (lo..=hi).contains(&y)));
+        assert_eq!((points[0], points[2].0), ((lo, lo), hi));
+    }
+
+    #[test]
+    fn test_sections_tile_clips_to_tile() {
+        // Long north-south line through Zurich; only part of it is in the tile
+        let polyline: Vec<GpsPoint> = (0..100).map(|i| GpsPoint::new(47.0 + i as f64 * 0.01, 8.54)).collect();
+        let sections = vec![section("s1", polyline), section("far", vec![GpsPoint::new(0.0, 0.0), GpsPoint::new(0.01, 0.0)])];
+        let tile = lat_lng_to_tile(47.37, 8.54, 12);
+
+        let bytes = encode_sections_tile(&sections, tile);
+        let layer = &layers(&bytes)[0];
+        let features: Vec<_> = layer.iter().filter(|f| f.0 == 2).map(|f| read_fields(&f.2)).collect();
+        assert_eq!(features.len(), 1);
+
+        let geometry = &features[0].iter().find(|f| f.0 == 4).unwrap().2;
+        // Fewer vertices than the full polyline were encoded
+        assert!(geometry.len() < 100 * 2);
+        assert!(layer.contains(&(3, 0, b"sport_type".to_vec())));
+
+        // Layers concatenate into a single valid tile
+        let heatmap_bytes = encode_heatmap_tile(
+            &generate_heatmap_from_tracks(&[("a".to_string(), sections[0].polyline.clone())], &HashMap::new(), &HeatmapConfig::default()),
+            tile,
+        );
+        let combined: Vec<u8> = heatmap_bytes.iter().chain(bytes.iter()).copied().collect();
+        assert_eq!(layers(&combined).len(), 2);
+    }
+}
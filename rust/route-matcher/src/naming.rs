@@ -0,0 +1,297 @@
+//! # Auto-Naming
+//!
+//! Human-readable names for route groups and frequent sections, e.g.
+//! "Home → Office" or "Along Thames Path", from a reverse [`Geocoder`].
+//!
+//! ## Geocoders
+//! [`Geocoder`] is a small trait so apps can plug in any source of names.
+//! [`OfflineGeocoder`] works without network access from user-defined places
+//! (home, office, a favourite café) and named roads (e.g. extracted from OSM).
+//!
+//! ## Naming Rules
+//! | Kind | Preference |
+//! |------|------------|
+//! | Route | "Start → End" if the endpoints are different places, then "Along Road", then "Place loop" |
+//! | Section | "Along Road", then "Start → End" |
+//!
+//! A road names a polyline when at least 60% of evenly spaced samples along
+//! it are on that road.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::naming::{NamedPlace, OfflineGeocoder, suggest_route_name};
+//!
+//! let home = GpsPoint::new(51.500, -0.100);
+//! let office = GpsPoint::new(51.510, -0.100);
+//! let geocoder = OfflineGeocoder::new(
+//!     vec![
+//!         NamedPlace { name: "Home".to_string(), center: home, radius_meters: 200.0 },
+//!         NamedPlace { name: "Office".to_string(), center: office, radius_meters: 200.0 },
+//!     ],
+//!     vec![],
+//! );
+//!
+//! let commute = vec![home, GpsPoint::new(51.505, -0.101), office];
+//! assert_eq!(suggest_route_name(&commute, &geocoder).as_deref(), Some("Home → Office"));
+//! ```
+
+use std::collections::HashMap;
+use crate::geo_utils::{haversine_distance, project_point_onto_route};
+use crate::{Bounds, FrequentSection, GpsPoint, HeatmapResult, RouteGroup, RouteSignature};
+
+/// Points sampled along a polyline when looking for a dominant road
+const NAME_SAMPLES: usize = 11;
+
+/// Share of samples that must be on one road for it to name the polyline
+const DOMINANT_ROAD_SHARE: f64 = 0.6;
+
+/// Default distance (meters) within which a point is considered on a named road
+const DEFAULT_ROAD_TOLERANCE: f64 = 25.0;
+
+/// Reverse geocoder: names for locations.
+pub trait Geocoder {
+    /// Name of the place at `point` (e.g. "Home", "Richmond"), if known
+    fn place_name(&self, point: &GpsPoint) -> Option<String>;
+
+    /// Name of the road or path at `point` (e.g. "Thames Path"), if known
+    fn road_name(&self, _point: &GpsPoint) -> Option<String> {
+        None
+    }
+}
+
+/// A named circular area, such as home or a regular café stop.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct NamedPlace {
+    pub name: String,
+    pub center: GpsPoint,
+    pub radius_meters: f64,
+}
+
+/// A named road or path.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct NamedRoad {
+    pub name: String,
+    pub polyline: Vec<GpsPoint>,
+}
+
+/// Offline geocoder over named places and roads.
+pub struct OfflineGeocoder {
+    places: Vec<NamedPlace>,
+    roads: Vec<(NamedRoad, Bounds)>,
+    road_tolerance: f64,
+}
+
+impl OfflineGeocoder {
+    /// Create a geocoder; points within 25m of a road are considered on it.
+    pub fn new(places: Vec<NamedPlace>, roads: Vec<NamedRoad>) -> Self {
+        let roads = roads
+            .into_iter()
+            .filter_map(|road| Bounds::from_points(&road.polyline).map(|b| (road, b)))
+            .collect();
+        Self {
+            places,
+            roads,
+            road_tolerance: DEFAULT_ROAD_TOLERANCE,
+        }
+    }
+
+    /// Set the distance (meters) within which a point is considered on a road.
+    pub fn with_road_tolerance(mut self, meters: f64) -> Self {
+        self.road_tolerance = meters;
+        self
+    }
+}
+
+impl Geocoder for OfflineGeocoder {
+    /// Nearest place whose radius contains `point`
+    fn place_name(&self, point: &GpsPoint) -> Option<String> {
+        self.places
+            .iter()
+            .map(|place| (place, haversine_distance(point, &place.center)))
+            .filter(|(place, d)| *d <= place.radius_meters)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(place, _)| place.name.clone())
+    }
+
+    /// Nearest road within the road tolerance of `point`
+    fn road_name(&self, point: &GpsPoint) -> Option<String> {
+        let margin = crate::geo_utils::meters_to_degrees(self.road_tolerance, point.latitude);
+        self.roads
+            .iter()
+            .filter(|(_, b)| {
+                point.latitude >= b.min_lat - margin
+                    && point.latitude <= b.max_lat + margin
+                    && point.longitude >= b.min_lng - margin
+                    && point.longitude <= b.max_lng + margin
+            })
+            .filter_map(|(road, _)| {
+                project_point_onto_route(point, &road.polyline)
+                    .filter(|p| p.offset <= self.road_tolerance)
+                    .map(|p| (road, p.offset))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(road, _)| road.name.clone())
+    }
+}
+
+/// Road covering at least [`DOMINANT_ROAD_SHARE`] of evenly spaced samples
+fn dominant_road<G: Geocoder + ?Sized>(polyline: &[GpsPoint], geocoder: &G) -> Option<String> {
+    let samples = NAME_SAMPLES.min(polyline.len());
+    if samples == 0 {
+        return None;
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for i in 0..samples {
+        let idx = if samples > 1 { i * (polyline.len() - 1) / (samples - 1) } else { 0 };
+        if let Some(road) = geocoder.road_name(&polyline[idx]) {
+            *counts.entry(road).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .filter(|(_, n)| *n as f64 >= samples as f64 * DOMINANT_ROAD_SHARE)
+        .map(|(road, _)| road)
+}
+
+fn endpoint_places<G: Geocoder + ?Sized>(polyline: &[GpsPoint], geocoder: &G) -> (Option<String>, Option<String>) {
+    (
+        polyline.first().and_then(|p| geocoder.place_name(p)),
+        polyline.last().and_then(|p| geocoder.place_name(p)),
+    )
+}
+
+/// Suggested name for a route, e.g. "Home → Office" or "Home loop".
+pub fn suggest_route_name<G: Geocoder + ?Sized>(polyline: &[GpsPoint], geocoder: &G) -> Option<String> {
+    match endpoint_places(polyline, geocoder) {
+        (Some(start), Some(end)) if start != end => Some(format!("{} → {}", start, end)),
+        (start, end) => dominant_road(polyline, geocoder)
+            .map(|road| format!("Along {}", road))
+            .or_else(|| start.filter(|s| end.as_ref() == Some(s)).map(|s| format!("{} loop", s))),
+    }
+}
+
+/// Suggested name for a section, e.g. "Along Thames Path".
+pub fn suggest_section_name<G: Geocoder + ?Sized>(polyline: &[GpsPoint], geocoder: &G) -> Option<String> {
+    dominant_road(polyline, geocoder)
+        .map(|road| format!("Along {}", road))
+        .or_else(|| match endpoint_places(polyline, geocoder) {
+            (Some(start), Some(end)) if start != end => Some(format!("{} → {}", start, end)),
+            _ => None,
+        })
+}
+
+/// Fill `suggested_name` of each section.
+pub fn name_sections<G: Geocoder + ?Sized>(sections: &mut [FrequentSection], geocoder: &G) {
+    for section in sections {
+        section.suggested_name = suggest_section_name(&section.polyline, geocoder);
+    }
+}
+
+/// Suggested names for route groups (group ID -> name).
+///
+/// Each group is named from its representative signature (the one whose
+/// activity ID is the group ID, else the first member with a signature).
+/// Groups without a suggestion are left out.
+pub fn suggest_route_names<G: Geocoder + ?Sized>(
+    groups: &[RouteGroup],
+    signatures: &[RouteSignature],
+    geocoder: &G,
+) -> HashMap<String, String> {
+    let by_id: HashMap<&str, &RouteSignature> = signatures
+        .iter()
+        .map(|s| (s.activity_id.as_str(), s))
+        .collect();
+
+    groups
+        .iter()
+        .filter_map(|group| {
+            let sig = by_id
+                .get(group.group_id.as_str())
+                .or_else(|| group.activity_ids.iter().find_map(|id| by_id.get(id.as_str())))?;
+            suggest_route_name(&sig.points, geocoder).map(|name| (group.group_id.clone(), name))
+        })
+        .collect()
+}
+
+/// Fill unnamed `RouteRef::name`s of a heatmap from suggested route names.
+/// User-defined names already set are kept.
+pub fn apply_route_names(heatmap: &mut HeatmapResult, names: &HashMap<String, String>) {
+    for cell in &mut heatmap.cells {
+        for route in &mut cell.route_refs {
+            if route.name.is_none() {
+                route.name = names.get(&route.route_id).cloned();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(lat: f64, lng: f64, n: usize) -> Vec<GpsPoint> {
+        (0..n).map(|i| GpsPoint::new(lat + i as f64 * 0.001, lng)).collect()
+    }
+
+    fn geocoder() -> OfflineGeocoder {
+        OfflineGeocoder::new(
+            vec![
+                NamedPlace { name: "Home".to_string(), center: GpsPoint::new(51.500, -0.100), radius_meters: 150.0 },
+                NamedPlace { name: "Office".to_string(), center: GpsPoint::new(51.509, -0.100), radius_meters: 150.0 },
+            ],
+            vec![NamedRoad { name: "Thames Path".to_string(), polyline: line(51.600, -0.200, 10) }],
+        )
+    }
+
+    #[test]
+    fn test_offline_lookup() {
+        let geocoder = geocoder();
+        assert_eq!(geocoder.place_name(&GpsPoint::new(51.5005, -0.100)).as_deref(), Some("Home"));
+        assert_eq!(geocoder.place_name(&GpsPoint::new(51.505, -0.100)), None);
+        assert_eq!(geocoder.road_name(&GpsPoint::new(51.603, -0.2001)).as_deref(), Some("Thames Path"));
+        assert_eq!(geocoder.road_name(&GpsPoint::new(51.603, -0.199)), None);
+    }
+
+    #[test]
+    fn test_route_and_section_names() {
+        let geocoder = geocoder();
+        assert_eq!(suggest_route_name(&line(51.500, -0.100, 10), &geocoder).as_deref(), Some("Home → Office"));
+
+        let mut out_and_back = line(51.500, -0.100, 5);
+        out_and_back.extend(line(51.500, -0.100, 5).into_iter().rev());
+        assert_eq!(suggest_route_name(&out_and_back, &geocoder).as_deref(), Some("Home loop"));
+
+        let riverside = line(51.601, -0.2001, 6);
+        assert_eq!(suggest_section_name(&riverside, &geocoder).as_deref(), Some("Along Thames Path"));
+        assert_eq!(suggest_section_name(&line(52.0, 0.0, 5), &geocoder), None);
+    }
+
+    #[test]
+    fn test_apply_route_names_keeps_user_names() {
+        let geocoder = geocoder();
+        let sig = RouteSignature::from_points("a", &line(51.500, -0.100, 10), &crate::MatchConfig::default()).unwrap();
+        let groups = vec![RouteGroup { group_id: "g1".to_string(), activity_ids: vec!["a".to_string()] }];
+        let names = suggest_route_names(&groups, &[sig], &geocoder);
+        assert_eq!(names.get("g1").map(String::as_str), Some("Home → Office"));
+
+        let mut heatmap = crate::generate_heatmap_from_tracks(
+            &[("a".to_string(), line(51.500, -0.100, 3))],
+            &HashMap::new(),
+            &crate::HeatmapConfig::default(),
+        );
+        heatmap.cells[0].route_refs = vec![
+            crate::RouteRef { route_id: "g1".to_string(), activity_count: 1, name: None },
+            crate::RouteRef { route_id: "g1".to_string(), activity_count: 1, name: Some("Commute".to_string()) },
+        ];
+        apply_route_names(&mut heatmap, &names);
+        assert_eq!(heatmap.cells[0].route_refs[0].name.as_deref(), Some("Home → Office"));
+        assert_eq!(heatmap.cells[0].route_refs[1].name.as_deref(), Some("Commute"));
+    }
+}
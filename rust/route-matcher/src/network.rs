@@ -0,0 +1,909 @@
+//! # Route Network
+//!
+//! Detects junctions — points where distinct routes cross or diverge — across
+//! a user's library, with how many directions meet there (degree) and how many
+//! routes and activities pass through. Junctions are the nodes of the user's
+//! personal road network, for junction-based analysis ("which way do I usually
+//! turn here?") and for cutting sections at forks.
+//!
+//! ## Algorithm
+//! 1. Each route (one representative signature per [`RouteGroup`], plus
+//!    ungrouped activities) is resampled every `sample_spacing_meters`
+//! 2. Walking along each route, note where another route starts or stops
+//!    running within `radius_meters` of it — where they join, split or cross.
+//!    Changes at the other route's own start or end are not junctions
+//! 3. Nearby events are merged into one candidate node
+//! 4. Each route through a candidate contributes the bearings in which it
+//!    leaves the node; bearings within 30° are one branch. Candidates with at
+//!    least 3 branches (a fork, T-junction or crossing) are nodes
+//!
+//! Neighbour queries use an R-tree in degrees, refined with haversine.
+//!
+//! ## Graph Export
+//! [`build_route_network`] joins junctions with frequent sections into a
+//! graph: sections are cut where they pass through a junction, and each piece
+//! becomes an edge with the section's traversal count. Section ends away from
+//! any junction become `end_N` nodes, shared by sections that meet there.
+//! [`RouteNetwork::to_geojson`] and [`RouteNetwork::to_graphml`] export it
+//! for reachability and variety analysis in other tools.
+//!
+//! ## Route Suggestions
+//! [`RouteNetwork::suggest_routes`] searches the graph depth-first for loops
+//! of a requested length from the node nearest a start point, riding each
+//! edge at most once, and ranks them by familiarity: how often their sections
+//! have been ridden.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, MatchConfig, RouteSignature};
+//! use route_matcher::network::{NetworkConfig, detect_network_nodes};
+//!
+//! let config = MatchConfig::default();
+//! // North along a road, and east along a road crossing it
+//! let north: Vec<GpsPoint> = (0..=20).map(|i| GpsPoint::new(51.50 + i as f64 * 0.0005, -0.10)).collect();
+//! let east: Vec<GpsPoint> = (0..=20).map(|i| GpsPoint::new(51.505, -0.1075 + i as f64 * 0.00075)).collect();
+//! let signatures = vec![
+//!     RouteSignature::from_points("north", &north, &config).unwrap(),
+//!     RouteSignature::from_points("east", &east, &config).unwrap(),
+//! ];
+//!
+//! let nodes = detect_network_nodes(&signatures, &[], &NetworkConfig::default());
+//! assert_eq!(nodes.len(), 1);
+//! assert_eq!(nodes[0].degree, 4);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use rstar::primitives::GeomWithData;
+use rstar::{RTree, AABB};
+use crate::geo_utils::{
+    bearing, compute_center, haversine_distance, interpolate_point, meters_to_degrees, polyline_length,
+    project_point_onto_route,
+};
+use crate::{FrequentSection, GpsPoint, RouteGroup, RouteSignature};
+
+/// Bearings (degrees) closer than this leave a node along the same branch
+const BRANCH_ANGLE_TOLERANCE: f64 = 30.0;
+
+/// Minimum branches for a junction (a fork or T-junction has 3)
+const MIN_NODE_DEGREE: usize = 3;
+
+/// Configuration for junction detection
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct NetworkConfig {
+    /// Distance within which two routes count as sharing a road (meters). Default: 30
+    pub radius_meters: f64,
+    /// Spacing of resampled route points (meters). Default: 15
+    pub sample_spacing_meters: f64,
+    /// Minimum distinct routes through a node. Default: 2
+    pub min_routes: u32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            radius_meters: 30.0,
+            sample_spacing_meters: 15.0,
+            min_routes: 2,
+        }
+    }
+}
+
+/// A junction where routes cross or diverge
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct NetworkNode {
+    /// Node ID (`node_0` has the most traffic; `end_N` for section ends away
+    /// from junctions in a [`RouteNetwork`])
+    pub id: String,
+    /// Location of the junction
+    pub center: GpsPoint,
+    /// Number of distinct directions (branches) meeting here
+    pub degree: u32,
+    /// Distinct routes passing through
+    pub route_count: u32,
+    /// Activities passing through (every activity of each route)
+    pub traffic: u32,
+    /// Route IDs (group IDs, or activity IDs of ungrouped activities)
+    pub route_ids: Vec<String>,
+}
+
+/// A section, or the part of one between two nodes
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct NetworkEdge {
+    /// Section ID, suffixed `_N` for the Nth piece of a section cut at junctions
+    pub id: String,
+    /// The section this edge belongs to
+    pub section_id: String,
+    /// Node at the start of the polyline
+    pub from_node: String,
+    /// Node at the end of the polyline
+    pub to_node: String,
+    /// Traversals of the section (one per activity portion)
+    pub traversal_count: u32,
+    /// Edge length in meters
+    pub distance_meters: f64,
+    pub polyline: Vec<GpsPoint>,
+}
+
+/// Undirected graph of junctions joined by frequent sections
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct RouteNetwork {
+    pub nodes: Vec<NetworkNode>,
+    pub edges: Vec<NetworkEdge>,
+}
+
+/// Configuration for [`RouteNetwork::suggest_routes`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SuggestionConfig {
+    /// Accepted deviation from the target distance, as a fraction. Default: 0.2
+    pub distance_tolerance: f64,
+    /// Furthest a loop may start from the start point (meters). Default: 1000
+    pub max_start_distance_meters: f64,
+    /// Maximum suggestions returned. Default: 10
+    pub max_results: u32,
+}
+
+impl Default for SuggestionConfig {
+    fn default() -> Self {
+        Self {
+            distance_tolerance: 0.2,
+            max_start_distance_meters: 1000.0,
+            max_results: 10,
+        }
+    }
+}
+
+/// A loop of network edges suggested as a route
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct RouteSuggestion {
+    /// The loop, starting and ending at its start node
+    pub polyline: Vec<GpsPoint>,
+    /// Loop length in meters
+    pub distance_meters: f64,
+    /// How well-trodden the loop is (0-1): each edge's traversal count relative
+    /// to the busiest edge in the network, averaged over the loop's length
+    pub familiarity: f64,
+    /// Node the loop starts and ends at
+    pub start_node: String,
+    /// Edges in riding order
+    pub edge_ids: Vec<String>,
+}
+
+/// A resampled route: (route ID, activity count, evenly spaced points)
+struct NetworkRoute {
+    id: String,
+    activity_count: u32,
+    points: Vec<GpsPoint>,
+}
+
+/// Resampled routes with an R-tree over all their points
+struct NetworkRoutes {
+    routes: Vec<NetworkRoute>,
+    tree: RTree<GeomWithData<[f64; 2], (usize, usize)>>,
+    radius: f64,
+}
+
+impl NetworkRoutes {
+    fn new(signatures: &[RouteSignature], groups: &[RouteGroup], config: &NetworkConfig) -> Self {
+        let routes = network_routes(signatures, groups, config.sample_spacing_meters);
+        let tree = RTree::bulk_load(
+            routes
+                .iter()
+                .enumerate()
+                .flat_map(|(r, route)| {
+                    route.points.iter().enumerate().map(move |(i, p)| GeomWithData::new([p.latitude, p.longitude], (r, i)))
+                })
+                .collect(),
+        );
+        Self { routes, tree, radius: config.radius_meters }
+    }
+
+    /// Indices of routes passing within the radius of `p`
+    fn near(&self, p: &GpsPoint) -> HashSet<usize> {
+        let deg = meters_to_degrees(self.radius, p.latitude);
+        let envelope = AABB::from_corners([p.latitude - deg, p.longitude - deg], [p.latitude + deg, p.longitude + deg]);
+        self.tree
+            .locate_in_envelope(&envelope)
+            .filter(|g| haversine_distance(p, &self.routes[g.data.0].points[g.data.1]) <= self.radius)
+            .map(|g| g.data.0)
+            .collect()
+    }
+
+    /// A node at `center` with the routes passing through it (unnamed)
+    fn node_at(&self, center: GpsPoint, degree: u32) -> NetworkNode {
+        let mut through: Vec<usize> = self.near(&center).into_iter().collect();
+        through.sort_unstable();
+        NetworkNode {
+            id: String::new(),
+            center,
+            degree,
+            route_count: through.len() as u32,
+            traffic: through.iter().map(|&r| self.routes[r].activity_count).sum(),
+            route_ids: through.iter().map(|&r| self.routes[r].id.clone()).collect(),
+        }
+    }
+}
+
+/// Detect junctions between routes, busiest first.
+///
+/// Each group is represented by the signature of its `group_id` (or its first
+/// activity with a signature); activities in no group are routes of their own.
+pub fn detect_network_nodes(
+    signatures: &[RouteSignature],
+    groups: &[RouteGroup],
+    config: &NetworkConfig,
+) -> Vec<NetworkNode> {
+    junctions(&NetworkRoutes::new(signatures, groups, config), config)
+}
+
+fn junctions(index: &NetworkRoutes, config: &NetworkConfig) -> Vec<NetworkNode> {
+    let routes = &index.routes;
+    let radius = config.radius_meters;
+
+    // Where other routes join or leave each route
+    let mut events: Vec<GpsPoint> = Vec::new();
+    for (r, route) in routes.iter().enumerate() {
+        let mut previous: HashSet<usize> = HashSet::new();
+        for (i, point) in route.points.iter().enumerate() {
+            let mut near = index.near(point);
+            near.remove(&r);
+            if i > 0 {
+                let at_own_end = |s: usize| {
+                    let other = &routes[s].points;
+                    [other[0], other[other.len() - 1]].iter().any(|end| haversine_distance(end, point) <= 2.0 * radius)
+                };
+                if previous.symmetric_difference(&near).any(|&s| !at_own_end(s)) {
+                    events.push(*point);
+                }
+            }
+            previous = near;
+        }
+    }
+
+    let mut nodes: Vec<NetworkNode> = merge_events(&events, 2.0 * radius)
+        .into_iter()
+        .filter_map(|center| {
+            let through = index.near(&center);
+            if through.len() < config.min_routes.max(1) as usize {
+                return None;
+            }
+
+            let bearings: Vec<f64> = through
+                .iter()
+                .flat_map(|&r| branch_bearings(&routes[r].points, &center, radius, config.sample_spacing_meters))
+                .collect();
+            let degree = count_branches(bearings);
+            if degree < MIN_NODE_DEGREE {
+                return None;
+            }
+            Some(index.node_at(center, degree as u32))
+        })
+        .collect();
+
+    nodes.sort_by(|a, b| b.traffic.cmp(&a.traffic).then(b.route_count.cmp(&a.route_count)));
+    for (i, node) in nodes.iter_mut().enumerate() {
+        node.id = format!("node_{}", i);
+    }
+    nodes
+}
+
+/// Build the route network graph: junctions as nodes, frequent sections (cut
+/// at the junctions they pass through) as edges.
+pub fn build_route_network(
+    signatures: &[RouteSignature],
+    groups: &[RouteGroup],
+    sections: &[FrequentSection],
+    config: &NetworkConfig,
+) -> RouteNetwork {
+    let index = NetworkRoutes::new(signatures, groups, config);
+    let mut nodes = junctions(&index, config);
+    let junction_count = nodes.len();
+    let snap = 2.0 * config.radius_meters;
+    let mut edges = Vec::new();
+
+    for section in sections.iter().filter(|s| s.polyline.len() >= 2) {
+        let length = polyline_length(&section.polyline);
+        let mut cuts: Vec<f64> = nodes[..junction_count]
+            .iter()
+            .filter_map(|node| project_point_onto_route(&node.center, &section.polyline))
+            .filter(|proj| proj.offset <= config.radius_meters)
+            .map(|proj| proj.distance_along)
+            .filter(|&along| along > snap && along < length - snap)
+            .collect();
+        cuts.sort_by(f64::total_cmp);
+
+        let pieces = split_polyline(&section.polyline, &cuts);
+        let traversal_count = if section.activity_portions.is_empty() {
+            section.visit_count
+        } else {
+            section.activity_portions.len() as u32
+        };
+        let piece_count = pieces.len();
+        for (k, polyline) in pieces.into_iter().enumerate() {
+            let from_node = node_for_end(&mut nodes, junction_count, &index, polyline[0], snap);
+            let to_node = node_for_end(&mut nodes, junction_count, &index, polyline[polyline.len() - 1], snap);
+            edges.push(NetworkEdge {
+                id: if piece_count == 1 { section.id.clone() } else { format!("{}_{}", section.id, k) },
+                section_id: section.id.clone(),
+                from_node,
+                to_node,
+                traversal_count,
+                distance_meters: polyline_length(&polyline),
+                polyline,
+            });
+        }
+    }
+
+    // Section ends meeting at a point have the graph degree of an end node
+    for node in nodes.iter_mut().skip(junction_count) {
+        node.degree = edges
+            .iter()
+            .map(|e| (e.from_node == node.id) as u32 + (e.to_node == node.id) as u32)
+            .sum();
+    }
+    RouteNetwork { nodes, edges }
+}
+
+/// ID of the node at a section end: the nearest junction or end node within
+/// `snap` meters, or a new end node.
+fn node_for_end(
+    nodes: &mut Vec<NetworkNode>,
+    junction_count: usize,
+    index: &NetworkRoutes,
+    end: GpsPoint,
+    snap: f64,
+) -> String {
+    let nearest = nodes
+        .iter()
+        .map(|n| (n, haversine_distance(&n.center, &end)))
+        .filter(|&(_, d)| d <= snap)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((node, _)) = nearest {
+        return node.id.clone();
+    }
+    let mut node = index.node_at(end, 0);
+    node.id = format!("end_{}", nodes.len() - junction_count);
+    nodes.push(node);
+    nodes[nodes.len() - 1].id.clone()
+}
+
+/// Cut a polyline at increasing distances along it (meters).
+fn split_polyline(polyline: &[GpsPoint], cuts: &[f64]) -> Vec<Vec<GpsPoint>> {
+    let mut pieces = Vec::new();
+    let mut current = vec![polyline[0]];
+    let mut along = 0.0;
+    let mut cuts = cuts.iter().copied().peekable();
+
+    for w in polyline.windows(2) {
+        let seg = haversine_distance(&w[0], &w[1]);
+        while let Some(cut) = cuts.next_if(|&c| c <= along + seg) {
+            let t = if seg > 0.0 { (cut - along) / seg } else { 0.0 };
+            let point = interpolate_point(&w[0], &w[1], t);
+            current.push(point);
+            pieces.push(std::mem::replace(&mut current, vec![point]));
+        }
+        current.push(w[1]);
+        along += seg;
+    }
+    pieces.push(current);
+    pieces
+}
+
+impl RouteNetwork {
+    /// GeoJSON FeatureCollection: nodes as Points (`kind` "junction" or
+    /// "end"), edges as LineStrings, with their fields as properties.
+    pub fn to_geojson(&self) -> String {
+        let mut features: Vec<String> = Vec::with_capacity(self.nodes.len() + self.edges.len());
+        for node in &self.nodes {
+            let kind = if node.id.starts_with("end_") { "end" } else { "junction" };
+            features.push(format!(
+                r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[{},{}]}},"properties":{{"id":{},"kind":"{}","degree":{},"route_count":{},"traffic":{}}}}}"#,
+                node.center.longitude,
+                node.center.latitude,
+                json_string(&node.id),
+                kind,
+                node.degree,
+                node.route_count,
+                node.traffic,
+            ));
+        }
+        for edge in &self.edges {
+            let coordinates: Vec<String> = edge
+                .polyline
+                .iter()
+                .map(|p| format!("[{},{}]", p.longitude, p.latitude))
+                .collect();
+            features.push(format!(
+                r#"{{"type":"Feature","geometry":{{"type":"LineString","coordinates":[{}]}},"properties":{{"id":{},"section_id":{},"from":{},"to":{},"traversal_count":{},"distance_meters":{}}}}}"#,
+                coordinates.join(","),
+                json_string(&edge.id),
+                json_string(&edge.section_id),
+                json_string(&edge.from_node),
+                json_string(&edge.to_node),
+                edge.traversal_count,
+                edge.distance_meters,
+            ));
+        }
+        format!(r#"{{"type":"FeatureCollection","features":[{}]}}"#, features.join(","))
+    }
+
+    /// GraphML document of the undirected graph, with node coordinates,
+    /// degree and traffic, and edge section IDs, traversal counts and lengths.
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"lat\" for=\"node\" attr.name=\"lat\" attr.type=\"double\"/>\n",
+            "  <key id=\"lng\" for=\"node\" attr.name=\"lng\" attr.type=\"double\"/>\n",
+            "  <key id=\"degree\" for=\"node\" attr.name=\"degree\" attr.type=\"int\"/>\n",
+            "  <key id=\"route_count\" for=\"node\" attr.name=\"route_count\" attr.type=\"int\"/>\n",
+            "  <key id=\"traffic\" for=\"node\" attr.name=\"traffic\" attr.type=\"int\"/>\n",
+            "  <key id=\"section_id\" for=\"edge\" attr.name=\"section_id\" attr.type=\"string\"/>\n",
+            "  <key id=\"traversal_count\" for=\"edge\" attr.name=\"traversal_count\" attr.type=\"int\"/>\n",
+            "  <key id=\"distance_meters\" for=\"edge\" attr.name=\"distance_meters\" attr.type=\"double\"/>\n",
+            "  <graph id=\"routes\" edgedefault=\"undirected\">\n",
+        ));
+        for node in &self.nodes {
+            xml.push_str(&format!(
+                "    <node id=\"{}\"><data key=\"lat\">{}</data><data key=\"lng\">{}</data><data key=\"degree\">{}</data><data key=\"route_count\">{}</data><data key=\"traffic\">{}</data></node>\n",
+                xml_escape(&node.id),
+                node.center.latitude,
+                node.center.longitude,
+                node.degree,
+                node.route_count,
+                node.traffic,
+            ));
+        }
+        for edge in &self.edges {
+            xml.push_str(&format!(
+                "    <edge id=\"{}\" source=\"{}\" target=\"{}\"><data key=\"section_id\">{}</data><data key=\"traversal_count\">{}</data><data key=\"distance_meters\">{}</data></edge>\n",
+                xml_escape(&edge.id),
+                xml_escape(&edge.from_node),
+                xml_escape(&edge.to_node),
+                xml_escape(&edge.section_id),
+                edge.traversal_count,
+                edge.distance_meters,
+            ));
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+/// Upper bound on search steps per suggestion query, so dense networks stay
+/// responsive
+const MAX_SUGGESTION_STEPS: usize = 100_000;
+
+impl RouteNetwork {
+    /// Loops of about `target_distance` meters through the network, starting
+    /// at the node nearest `start_point`, most familiar first.
+    ///
+    /// Loops never ride an edge twice. The same loop in the other direction
+    /// is suggested once. Returns an empty list when no node is within
+    /// [`SuggestionConfig::max_start_distance_meters`].
+    pub fn suggest_routes(
+        &self,
+        target_distance: f64,
+        start_point: &GpsPoint,
+        config: &SuggestionConfig,
+    ) -> Vec<RouteSuggestion> {
+        let start = self
+            .nodes
+            .iter()
+            .map(|n| (n, haversine_distance(&n.center, start_point)))
+            .filter(|&(_, d)| d <= config.max_start_distance_meters)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((start, _)) = start else {
+            return Vec::new();
+        };
+
+        // node ID -> (edge index, node at the other end, traversed forwards)
+        let mut adjacency: HashMap<&str, Vec<(usize, &str, bool)>> = HashMap::new();
+        for (i, edge) in self.edges.iter().enumerate().filter(|(_, e)| e.polyline.len() >= 2) {
+            adjacency.entry(&edge.from_node).or_default().push((i, &edge.to_node, true));
+            adjacency.entry(&edge.to_node).or_default().push((i, &edge.from_node, false));
+        }
+
+        let search = LoopSearch {
+            adjacency: &adjacency,
+            edges: &self.edges,
+            start: &start.id,
+            min_distance: target_distance * (1.0 - config.distance_tolerance),
+            max_distance: target_distance * (1.0 + config.distance_tolerance),
+        };
+        let mut loops: Vec<Vec<(usize, bool)>> = Vec::new();
+        let mut steps = 0;
+        search.extend(&start.id, 0.0, &mut Vec::new(), &mut loops, &mut steps);
+
+        let busiest = self.edges.iter().map(|e| e.traversal_count).max().unwrap_or(0).max(1) as f64;
+        let mut seen: HashSet<Vec<usize>> = HashSet::new();
+        let mut suggestions: Vec<RouteSuggestion> = loops
+            .into_iter()
+            .filter(|path| {
+                let mut key: Vec<usize> = path.iter().map(|&(i, _)| i).collect();
+                key.sort_unstable();
+                seen.insert(key)
+            })
+            .map(|path| {
+                let mut polyline: Vec<GpsPoint> = Vec::new();
+                let (mut distance_meters, mut weighted) = (0.0, 0.0);
+                for &(i, forward) in &path {
+                    let edge = &self.edges[i];
+                    let skip = usize::from(!polyline.is_empty());
+                    if forward {
+                        polyline.extend(edge.polyline.iter().skip(skip));
+                    } else {
+                        polyline.extend(edge.polyline.iter().rev().skip(skip));
+                    }
+                    distance_meters += edge.distance_meters;
+                    weighted += edge.distance_meters * edge.traversal_count as f64 / busiest;
+                }
+                RouteSuggestion {
+                    polyline,
+                    distance_meters,
+                    familiarity: if distance_meters > 0.0 { weighted / distance_meters } else { 0.0 },
+                    start_node: start.id.clone(),
+                    edge_ids: path.iter().map(|&(i, _)| self.edges[i].id.clone()).collect(),
+                }
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| {
+            b.familiarity.total_cmp(&a.familiarity).then_with(|| {
+                (a.distance_meters - target_distance)
+                    .abs()
+                    .total_cmp(&(b.distance_meters - target_distance).abs())
+            })
+        });
+        suggestions.truncate(config.max_results as usize);
+        suggestions
+    }
+}
+
+/// Depth-first search for loops back to `start` within a distance range
+struct LoopSearch<'a> {
+    adjacency: &'a HashMap<&'a str, Vec<(usize, &'a str, bool)>>,
+    edges: &'a [NetworkEdge],
+    start: &'a str,
+    min_distance: f64,
+    max_distance: f64,
+}
+
+impl LoopSearch<'_> {
+    /// Extend `path` (edge index, forwards) from `node`, recording each path
+    /// that closes the loop within range.
+    fn extend(
+        &self,
+        node: &str,
+        distance: f64,
+        path: &mut Vec<(usize, bool)>,
+        loops: &mut Vec<Vec<(usize, bool)>>,
+        steps: &mut usize,
+    ) {
+        for &(i, next, forward) in self.adjacency.get(node).into_iter().flatten() {
+            *steps += 1;
+            if *steps > MAX_SUGGESTION_STEPS {
+                return;
+            }
+            let total = distance + self.edges[i].distance_meters;
+            if total > self.max_distance || path.iter().any(|&(j, _)| j == i) {
+                continue;
+            }
+            path.push((i, forward));
+            if next == self.start {
+                if total >= self.min_distance {
+                    loops.push(path.clone());
+                }
+            } else {
+                self.extend(next, total, path, loops, steps);
+            }
+            path.pop();
+        }
+    }
+}
+
+/// `s` as a quoted JSON string.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `s` with XML special characters escaped.
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// One resampled polyline per route group, plus ungrouped signatures.
+fn network_routes(signatures: &[RouteSignature], groups: &[RouteGroup], spacing: f64) -> Vec<NetworkRoute> {
+    route_representatives(signatures, groups)
+        .into_iter()
+        .filter(|(_, _, sig)| sig.points.len() >= 2)
+        .map(|(id, activity_count, sig)| {
+            let count = (polyline_length(&sig.points) / spacing.max(1.0)).ceil() as usize + 1;
+            NetworkRoute {
+                id,
+                activity_count,
+                points: crate::geo_utils::resample_polyline(&sig.points, count.max(2)),
+            }
+        })
+        .collect()
+}
+
+/// (route ID, activity count, signature) for each route: the signature of each
+/// group's `group_id` (or its first activity with a signature), then each
+/// activity in no group.
+pub(crate) fn route_representatives<'a>(
+    signatures: &'a [RouteSignature],
+    groups: &[RouteGroup],
+) -> Vec<(String, u32, &'a RouteSignature)> {
+    let by_id: HashMap<&str, &RouteSignature> = signatures.iter().map(|s| (s.activity_id.as_str(), s)).collect();
+    let mut grouped: HashSet<&str> = HashSet::new();
+    let mut routes: Vec<(String, u32, &RouteSignature)> = Vec::new();
+
+    for group in groups {
+        grouped.extend(group.activity_ids.iter().map(|id| id.as_str()));
+        let representative = by_id
+            .get(group.group_id.as_str())
+            .or_else(|| group.activity_ids.iter().find_map(|id| by_id.get(id.as_str())));
+        if let Some(sig) = representative {
+            routes.push((group.group_id.clone(), group.activity_ids.len() as u32, sig));
+        }
+    }
+    for sig in signatures {
+        if !grouped.contains(sig.activity_id.as_str()) {
+            routes.push((sig.activity_id.clone(), 1, sig));
+        }
+    }
+    routes
+}
+
+/// Greedily merge points closer than `distance` to a running cluster center.
+fn merge_events(events: &[GpsPoint], distance: f64) -> Vec<GpsPoint> {
+    let mut clusters: Vec<Vec<GpsPoint>> = Vec::new();
+    let mut centers: Vec<GpsPoint> = Vec::new();
+    for event in events {
+        match centers.iter().position(|c| haversine_distance(c, event) <= distance) {
+            Some(c) => {
+                clusters[c].push(*event);
+                centers[c] = compute_center(&clusters[c]);
+            }
+            None => {
+                clusters.push(vec![*event]);
+                centers.push(*event);
+            }
+        }
+    }
+    centers
+}
+
+/// Bearings from `center` to where a route is a few radii away from it, on
+/// each side of the route's closest point.
+fn branch_bearings(points: &[GpsPoint], center: &GpsPoint, radius: f64, spacing: f64) -> Vec<f64> {
+    let Some(closest) = (0..points.len()).min_by(|&a, &b| {
+        haversine_distance(&points[a], center).total_cmp(&haversine_distance(&points[b], center))
+    }) else {
+        return Vec::new();
+    };
+    let steps = (3.0 * radius / spacing.max(1.0)).ceil() as usize;
+    [closest.checked_sub(steps), Some(closest + steps)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| points.get(i))
+        .filter(|p| haversine_distance(center, p) > radius)
+        .map(|p| bearing(center, p))
+        .collect()
+}
+
+/// Number of distinct directions among `bearings` (degrees, circular).
+fn count_branches(mut bearings: Vec<f64>) -> usize {
+    if bearings.is_empty() {
+        return 0;
+    }
+    bearings.sort_by(f64::total_cmp);
+    let mut branches = 1 + bearings.windows(2).filter(|w| w[1] - w[0] > BRANCH_ANGLE_TOLERANCE).count();
+    // The first and last branch may meet across north
+    let wrap = 360.0 - bearings[bearings.len() - 1] + bearings[0];
+    if branches > 1 && wrap <= BRANCH_ANGLE_TOLERANCE {
+        branches -= 1;
+    }
+    branches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MatchConfig;
+
+    fn signature(id: &str, points: Vec<GpsPoint>) -> RouteSignature {
+        RouteSignature::from_points(id, &points, &MatchConfig::default()).unwrap()
+    }
+
+    /// `n` points ~55m apart from `start`, heading north or east
+    fn road(start: (f64, f64), n: usize, north: bool) -> Vec<GpsPoint> {
+        (0..=n)
+            .map(|i| {
+                let step = i as f64 * 0.0005;
+                if north {
+                    GpsPoint::new(start.0 + step, start.1)
+                } else {
+                    GpsPoint::new(start.0, start.1 + step * 1.6)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fork_and_dead_end() {
+        // "straight" heads north for 2.2km; "fork" follows it for 1.1km, then turns east
+        let straight = road((51.50, -0.10), 40, true);
+        let mut fork = road((51.50, -0.10), 20, true);
+        fork.extend(road((51.51, -0.10), 20, false).into_iter().skip(1));
+        // A shorter ride ending on the shared road is not a junction
+        let short = road((51.50, -0.10), 10, true);
+
+        let signatures = vec![signature("straight", straight), signature("fork", fork), signature("short", short)];
+        let groups = vec![RouteGroup {
+            group_id: "straight".to_string(),
+            activity_ids: vec!["straight".to_string(), "other".to_string()],
+        }];
+        let nodes = detect_network_nodes(&signatures, &groups, &NetworkConfig::default());
+
+        assert_eq!(nodes.len(), 1, "{:?}", nodes);
+        let node = &nodes[0];
+        assert!(haversine_distance(&node.center, &GpsPoint::new(51.51, -0.10)) < 50.0);
+        assert_eq!(node.degree, 3);
+        assert_eq!(node.route_ids, vec!["straight".to_string(), "fork".to_string()]);
+        assert_eq!(node.traffic, 3);
+    }
+
+    #[test]
+    fn test_network_cuts_sections_at_junctions_and_exports() {
+        let straight = road((51.50, -0.10), 40, true);
+        let mut fork = road((51.50, -0.10), 20, true);
+        let east = road((51.51, -0.10), 20, false);
+        fork.extend(east.iter().skip(1).copied());
+        let signatures = vec![signature("straight", straight.clone()), signature("fork", fork)];
+
+        let section = |id: &str, polyline: Vec<GpsPoint>| FrequentSection {
+            id: id.to_string(),
+            polyline,
+            visit_count: 4,
+            ..Default::default()
+        };
+        // "north" runs through the fork; "east" starts at it
+        let sections = vec![section("north", straight), section("east", east)];
+        let network = build_route_network(&signatures, &[], &sections, &NetworkConfig::default());
+
+        let ids: Vec<&str> = network.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["node_0", "end_0", "end_1", "end_2"]);
+        let edges: Vec<(&str, &str, &str)> = network
+            .edges
+            .iter()
+            .map(|e| (e.id.as_str(), e.from_node.as_str(), e.to_node.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![("north_0", "end_0", "node_0"), ("north_1", "node_0", "end_1"), ("east", "node_0", "end_2")]
+        );
+        assert!((network.edges[0].distance_meters - 1112.0).abs() < 60.0);
+        assert_eq!(network.edges[2].traversal_count, 4);
+        assert_eq!(network.nodes[1].degree, 1);
+
+        let geojson: serde_json::Value = serde_json::from_str(&network.to_geojson()).unwrap();
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 7);
+        assert_eq!(features[0]["properties"]["kind"], "junction");
+        assert_eq!(features[4]["geometry"]["type"], "LineString");
+
+        let graphml = network.to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), 4);
+        assert!(graphml.contains(r#"<edge id="east" source="node_0" target="end_2">"#));
+    }
+
+    /// A 1km square of sections with a diagonal, ridden mostly on its west
+    /// and south sides
+    fn square_network() -> RouteNetwork {
+        let corner = |i: usize| {
+            let (lat, lng) = [(51.500, -0.100), (51.509, -0.100), (51.509, -0.0856), (51.500, -0.0856)][i];
+            NetworkNode {
+                id: format!("node_{}", i),
+                center: GpsPoint::new(lat, lng),
+                degree: 3,
+                route_count: 2,
+                traffic: 2,
+                route_ids: Vec::new(),
+            }
+        };
+        let nodes: Vec<NetworkNode> = (0..4).map(corner).collect();
+        let edge = |id: &str, from: usize, to: usize, traversal_count: u32| {
+            let polyline = vec![nodes[from].center, nodes[to].center];
+            NetworkEdge {
+                id: id.to_string(),
+                section_id: id.to_string(),
+                from_node: nodes[from].id.clone(),
+                to_node: nodes[to].id.clone(),
+                traversal_count,
+                distance_meters: polyline_length(&polyline),
+                polyline,
+            }
+        };
+        let edges = vec![
+            edge("west", 0, 1, 10),
+            edge("north", 1, 2, 2),
+            edge("east", 2, 3, 2),
+            edge("south", 3, 0, 10),
+            edge("diagonal", 1, 3, 5),
+        ];
+        RouteNetwork { nodes, edges }
+    }
+
+    #[test]
+    fn test_suggests_loops_by_length_and_familiarity() {
+        let network = square_network();
+        let start = GpsPoint::new(51.4995, -0.1005);
+        let config = SuggestionConfig::default();
+
+        // The square, once, whichever way round
+        let square = network.suggest_routes(4000.0, &start, &SuggestionConfig { distance_tolerance: 0.05, ..config.clone() });
+        assert_eq!(square.len(), 1);
+        assert_eq!(square[0].start_node, "node_0");
+        assert_eq!(square[0].polyline.len(), 5);
+        assert_eq!(square[0].polyline[0], square[0].polyline[4]);
+
+        // Only the west-south triangle starts at node_0
+        let triangles = network.suggest_routes(3400.0, &start, &SuggestionConfig { distance_tolerance: 0.1, ..config.clone() });
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0].edge_ids, vec!["west", "diagonal", "south"]);
+        assert!((triangles[0].familiarity - 0.79).abs() < 0.01, "{}", triangles[0].familiarity);
+        assert!((triangles[0].distance_meters - 3414.0).abs() < 50.0);
+
+        // Both fit a looser target; the triangle is more familiar
+        let both = network.suggest_routes(3700.0, &start, &SuggestionConfig { distance_tolerance: 0.3, ..config.clone() });
+        assert_eq!(both.len(), 2);
+        assert_eq!(both[0].edge_ids.len(), 3);
+        assert!(both[0].familiarity > both[1].familiarity);
+
+        // Too far from any node
+        assert!(network.suggest_routes(4000.0, &GpsPoint::new(51.6, -0.1), &config).is_empty());
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(json_string("a\"b\\c\n"), r#""a\"b\\c\u000a""#);
+        assert_eq!(xml_escape("<a & 'b'>"), "&lt;a &amp; &apos;b&apos;&gt;");
+    }
+
+    #[test]
+    fn test_parallel_routes_have_no_nodes() {
+        let a = road((51.50, -0.10), 20, true);
+        let b = road((51.50, -0.10001), 20, true);
+        let nodes = detect_network_nodes(&[signature("a", a), signature("b", b)], &[], &NetworkConfig::default());
+        assert!(nodes.is_empty());
+    }
+
+    #[test]
+    fn test_count_branches_wraps_north() {
+        assert_eq!(count_branches(vec![355.0, 5.0, 90.0, 180.0]), 3);
+        assert_eq!(count_branches(vec![]), 0);
+    }
+}
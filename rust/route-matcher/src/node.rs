@@ -0,0 +1,203 @@
+//! # Node.js Bindings
+//!
+//! N-API bindings for server-side pre-computation of route groups and heatmaps
+//! for web users. Enabled with the `node` feature; build with
+//! `napi build --release --features node` to produce a `.node` addon.
+//!
+//! Tracks are passed as one flat `Float64Array` of `[lat, lng, lat, lng, ...]`
+//! plus a `Uint32Array` of point offsets, so a whole batch crosses into Rust
+//! without copying or per-point JS objects. Track `i` is points
+//! `offsets[i]..offsets[i + 1]`, so `offsets` has one more entry than
+//! `activityIds`.
+//!
+//! ```js
+//! const { groupTracks, generateHeatmap } = require('./route-matcher.node');
+//!
+//! const ids = ['a', 'b'];
+//! const coords = new Float64Array([51.50, -0.10, 51.51, -0.10, 51.50, -0.10, 51.51, -0.10]);
+//! const offsets = new Uint32Array([0, 2, 4]);
+//!
+//! const groups = groupTracks(ids, coords, offsets);
+//! const heatmap = generateHeatmap(ids, coords, offsets, { cellSizeMeters: 50 }, groups);
+//! ```
+
+use std::collections::HashMap;
+use napi::bindgen_prelude::{Float32Array, Float64Array, Uint32Array};
+use napi_derive::napi;
+use crate::preprocess::split_flat_tracks;
+use crate::{ActivityHeatmapData, GpsPoint, HeatmapConfig, MatchConfig, RouteSignature};
+
+/// Route matching options. Unset fields use the `MatchConfig` defaults.
+#[napi(object)]
+#[derive(Default)]
+pub struct NodeMatchOptions {
+    pub perfect_threshold: Option<f64>,
+    pub zero_threshold: Option<f64>,
+    pub min_match_percentage: Option<f64>,
+    pub min_route_distance: Option<f64>,
+    pub endpoint_threshold: Option<f64>,
+}
+
+/// A group of activities on the same route.
+#[napi(object)]
+pub struct NodeRouteGroup {
+    pub group_id: String,
+    pub activity_ids: Vec<String>,
+}
+
+/// Heatmap options. Unset fields use the `HeatmapConfig` defaults.
+#[napi(object)]
+#[derive(Default)]
+pub struct NodeHeatmapOptions {
+    pub cell_size_meters: Option<f64>,
+    pub sport_types: Option<Vec<String>>,
+}
+
+/// Heatmap cells as parallel typed arrays, ready for WebGL layers.
+#[napi(object)]
+pub struct NodeHeatmap {
+    /// Cell centers: `[lat, lng, lat, lng, ...]`
+    pub centers: Float64Array,
+    /// Normalized density (0.0-1.0) per cell
+    pub densities: Float32Array,
+    pub visit_counts: Uint32Array,
+    pub unique_route_counts: Uint32Array,
+    pub cell_size_meters: f64,
+    pub total_routes: u32,
+    pub total_activities: u32,
+}
+
+/// Split a flat coordinate buffer into per-activity tracks.
+fn tracks_from_flat(activity_ids: &[String], coords: &[f64], offsets: &[u32]) -> Result<Vec<(String, Vec<GpsPoint>)>, String> {
+    if offsets.len() != activity_ids.len() + 1 {
+        return Err(format!(
+            "Expected {} offsets for {} activities, got {}",
+            activity_ids.len() + 1,
+            activity_ids.len(),
+            offsets.len()
+        ));
+    }
+    let tracks = split_flat_tracks(coords, offsets)?;
+    Ok(activity_ids.iter().cloned().zip(tracks).collect())
+}
+
+fn match_config(options: Option<NodeMatchOptions>) -> MatchConfig {
+    let options = options.unwrap_or_default();
+    let defaults = MatchConfig::default();
+    MatchConfig {
+        perfect_threshold: options.perfect_threshold.unwrap_or(defaults.perfect_threshold),
+        zero_threshold: options.zero_threshold.unwrap_or(defaults.zero_threshold),
+        min_match_percentage: options.min_match_percentage.unwrap_or(defaults.min_match_percentage),
+        min_route_distance: options.min_route_distance.unwrap_or(defaults.min_route_distance),
+        endpoint_threshold: options.endpoint_threshold.unwrap_or(defaults.endpoint_threshold),
+        ..defaults
+    }
+}
+
+/// Group tracks that follow the same route. Tracks too short to produce a
+/// signature are left out.
+#[napi]
+pub fn group_tracks(
+    activity_ids: Vec<String>,
+    coords: Float64Array,
+    offsets: Uint32Array,
+    options: Option<NodeMatchOptions>,
+) -> napi::Result<Vec<NodeRouteGroup>> {
+    let tracks = tracks_from_flat(&activity_ids, &coords, &offsets).map_err(napi::Error::from_reason)?;
+    let config = match_config(options);
+
+    let signatures: Vec<RouteSignature> = tracks
+        .iter()
+        .filter_map(|(id, points)| RouteSignature::from_points(id, points, &config))
+        .collect();
+    let groups = crate::group_signatures_parallel(&signatures, &config);
+
+    Ok(groups
+        .into_iter()
+        .map(|g| NodeRouteGroup { group_id: g.group_id, activity_ids: g.activity_ids })
+        .collect())
+}
+
+/// Generate a heatmap from full-resolution tracks. Pass the result of
+/// `groupTracks` as `groups` to count routes per cell.
+#[napi]
+pub fn generate_heatmap(
+    activity_ids: Vec<String>,
+    coords: Float64Array,
+    offsets: Uint32Array,
+    options: Option<NodeHeatmapOptions>,
+    groups: Option<Vec<NodeRouteGroup>>,
+) -> napi::Result<NodeHeatmap> {
+    let tracks = tracks_from_flat(&activity_ids, &coords, &offsets).map_err(napi::Error::from_reason)?;
+    let options = options.unwrap_or_default();
+    let defaults = HeatmapConfig::default();
+    let config = HeatmapConfig {
+        cell_size_meters: options.cell_size_meters.unwrap_or(defaults.cell_size_meters),
+        sport_types: options.sport_types,
+        ..defaults
+    };
+
+    let route_of: HashMap<String, String> = groups
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|g| g.activity_ids.into_iter().map(move |id| (id, g.group_id.clone())))
+        .collect();
+    let activity_data: HashMap<String, ActivityHeatmapData> = activity_ids
+        .iter()
+        .map(|id| {
+            let route_id = route_of.get(id).cloned();
+            let data = ActivityHeatmapData {
+                activity_id: id.clone(),
+                route_id,
+                route_name: None,
+                timestamp: None,
+                sport_type: None,
+            };
+            (id.clone(), data)
+        })
+        .collect();
+
+    let heatmap = crate::generate_heatmap_from_tracks(&tracks, &activity_data, &config);
+    let cells = &heatmap.cells;
+    Ok(NodeHeatmap {
+        centers: cells.iter().flat_map(|c| [c.center_lat, c.center_lng]).collect::<Vec<_>>().into(),
+        densities: cells.iter().map(|c| c.density).collect::<Vec<_>>().into(),
+        visit_counts: cells.iter().map(|c| c.visit_count).collect::<Vec<_>>().into(),
+        unique_route_counts: cells.iter().map(|c| c.unique_route_count).collect::<Vec<_>>().into(),
+        cell_size_meters: heatmap.cell_size_meters,
+        total_routes: heatmap.total_routes,
+        total_activities: heatmap.total_activities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("a{}", i)).collect()
+    }
+
+    #[test]
+    fn test_tracks_from_flat() {
+        let coords = [51.5, -0.1, 51.6, -0.1, 51.7, -0.1];
+        let tracks = tracks_from_flat(&ids(2), &coords, &[0, 1, 3]).unwrap();
+        assert_eq!(tracks[0].1.len(), 1);
+        assert_eq!(tracks[1].0, "a1");
+        assert_eq!(tracks[1].1[1].latitude, 51.7);
+    }
+
+    #[test]
+    fn test_tracks_from_flat_checks_id_count() {
+        let coords = [51.5, -0.1, 51.6, -0.1];
+        assert!(tracks_from_flat(&ids(2), &coords, &[0, 2]).is_err());
+        assert!(tracks_from_flat(&ids(1), &coords, &[0, 3]).is_err());
+    }
+
+    #[test]
+    fn test_match_config_overrides() {
+        let config = match_config(Some(NodeMatchOptions { min_match_percentage: Some(80.0), ..Default::default() }));
+        assert_eq!(config.min_match_percentage, 80.0);
+        assert_eq!(config.zero_threshold, MatchConfig::default().zero_threshold);
+    }
+}
@@ -0,0 +1,298 @@
+//! # Frequent Places
+//!
+//! Clusters activity start and end points into places the user keeps coming
+//! back to (home, work, a café stop, a trailhead), with visit counts.
+//!
+//! Places drive "rides from home" filters ([`activities_from_place`]) and
+//! privacy zones ([`Place::contains`]), and can be named with
+//! [`crate::naming::Geocoder`] via [`name_places`].
+//!
+//! ## Algorithm
+//! DBSCAN over all start and end points:
+//! 1. A point is a *core* point if at least `min_visits` endpoints (itself
+//!    included) lie within `radius_meters`
+//! 2. Clusters grow from core points through their neighbours
+//! 3. Points not reachable from any core point are noise (occasional places)
+//!
+//! Neighbour queries use an R-tree in degrees, refined with haversine, so
+//! libraries spanning several continents cluster correctly.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::GpsPoint;
+//! use route_matcher::places::{ActivityEndpoints, PlaceConfig, cluster_places};
+//!
+//! // Three rides from home (a few meters of GPS jitter), ending elsewhere
+//! let endpoints: Vec<ActivityEndpoints> = (0..3)
+//!     .map(|i| ActivityEndpoints {
+//!         activity_id: format!("ride{}", i),
+//!         start: GpsPoint::new(51.5000 + i as f64 * 0.0001, -0.1000),
+//!         end: GpsPoint::new(51.6 + i as f64, -0.2),
+//!     })
+//!     .collect();
+//!
+//! let places = cluster_places(&endpoints, &PlaceConfig::default());
+//! assert_eq!(places.len(), 1);
+//! assert_eq!(places[0].start_count, 3);
+//! ```
+
+use std::collections::HashSet;
+use rstar::primitives::GeomWithData;
+use rstar::{RTree, AABB};
+use crate::geo_utils::{compute_center, haversine_distance, meters_to_degrees};
+use crate::naming::Geocoder;
+use crate::{GpsPoint, RouteSignature};
+
+/// Configuration for place clustering
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct PlaceConfig {
+    /// Neighbourhood radius in meters. Default: 150
+    pub radius_meters: f64,
+    /// Minimum endpoints (starts or ends) within the radius to form a place. Default: 3
+    pub min_visits: u32,
+}
+
+impl Default for PlaceConfig {
+    fn default() -> Self {
+        Self {
+            radius_meters: 150.0,
+            min_visits: 3,
+        }
+    }
+}
+
+/// Start and end point of one activity
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ActivityEndpoints {
+    pub activity_id: String,
+    pub start: GpsPoint,
+    pub end: GpsPoint,
+}
+
+/// A frequently visited place
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct Place {
+    /// Place ID (`place_0` is the most visited)
+    pub id: String,
+    /// Centroid of the clustered endpoints
+    pub center: GpsPoint,
+    /// Distance from the center to the farthest clustered endpoint (meters)
+    pub radius_meters: f64,
+    /// Activities starting here
+    pub start_count: u32,
+    /// Activities ending here
+    pub end_count: u32,
+    /// Activities starting or ending here (each listed once)
+    pub activity_ids: Vec<String>,
+    /// Name from a geocoder (see [`name_places`])
+    pub name: Option<String>,
+}
+
+impl Place {
+    /// Total visits (starts plus ends).
+    pub fn visit_count(&self) -> u32 {
+        self.start_count + self.end_count
+    }
+
+    /// Whether `point` lies within the place's radius, e.g. for privacy zones.
+    pub fn contains(&self, point: &GpsPoint) -> bool {
+        haversine_distance(&self.center, point) <= self.radius_meters
+    }
+}
+
+/// Endpoints of route signatures.
+pub fn endpoints_from_signatures(signatures: &[RouteSignature]) -> Vec<ActivityEndpoints> {
+    signatures
+        .iter()
+        .map(|sig| ActivityEndpoints {
+            activity_id: sig.activity_id.clone(),
+            start: sig.start_point,
+            end: sig.end_point,
+        })
+        .collect()
+}
+
+/// Cluster activity start and end points into places, most visited first.
+pub fn cluster_places(endpoints: &[ActivityEndpoints], config: &PlaceConfig) -> Vec<Place> {
+    // (point, activity index, is_start)
+    let points: Vec<(GpsPoint, usize, bool)> = endpoints
+        .iter()
+        .enumerate()
+        .flat_map(|(i, e)| [(e.start, i, true), (e.end, i, false)])
+        .filter(|(p, _, _)| p.is_valid())
+        .collect();
+
+    let tree: RTree<GeomWithData<[f64; 2], usize>> = RTree::bulk_load(
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, (p, _, _))| GeomWithData::new([p.latitude, p.longitude], i))
+            .collect(),
+    );
+    let neighbours = |i: usize| -> Vec<usize> {
+        let p = &points[i].0;
+        let deg = meters_to_degrees(config.radius_meters, p.latitude);
+        let envelope = AABB::from_corners([p.latitude - deg, p.longitude - deg], [p.latitude + deg, p.longitude + deg]);
+        tree.locate_in_envelope(&envelope)
+            .map(|g| g.data)
+            .filter(|&j| haversine_distance(p, &points[j].0) <= config.radius_meters)
+            .collect()
+    };
+
+    let min_points = config.min_visits.max(1) as usize;
+    let mut cluster_of: Vec<Option<usize>> = vec![None; points.len()];
+    let mut visited = vec![false; points.len()];
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+
+    for i in 0..points.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+        let seeds = neighbours(i);
+        if seeds.len() < min_points {
+            continue;
+        }
+
+        let cluster = clusters.len();
+        clusters.push(vec![i]);
+        cluster_of[i] = Some(cluster);
+        let mut queue = seeds;
+        while let Some(j) = queue.pop() {
+            if cluster_of[j].is_none() {
+                cluster_of[j] = Some(cluster);
+                clusters[cluster].push(j);
+            }
+            if !visited[j] {
+                visited[j] = true;
+                let more = neighbours(j);
+                if more.len() >= min_points {
+                    queue.extend(more);
+                }
+            }
+        }
+    }
+
+    let mut places: Vec<Place> = clusters
+        .iter()
+        .map(|members| {
+            let member_points: Vec<GpsPoint> = members.iter().map(|&m| points[m].0).collect();
+            let center = compute_center(&member_points);
+            let radius_meters = member_points
+                .iter()
+                .map(|p| haversine_distance(&center, p))
+                .fold(0.0, f64::max);
+
+            let mut seen = HashSet::new();
+            let activity_ids = members
+                .iter()
+                .filter(|&&m| seen.insert(points[m].1))
+                .map(|&m| endpoints[points[m].1].activity_id.clone())
+                .collect();
+
+            Place {
+                id: String::new(),
+                center,
+                radius_meters,
+                start_count: members.iter().filter(|&&m| points[m].2).count() as u32,
+                end_count: members.iter().filter(|&&m| !points[m].2).count() as u32,
+                activity_ids,
+                name: None,
+            }
+        })
+        .collect();
+
+    places.sort_by_key(|p| std::cmp::Reverse(p.visit_count()));
+    for (i, place) in places.iter_mut().enumerate() {
+        place.id = format!("place_{}", i);
+    }
+    places
+}
+
+/// Activities starting in `place` ("rides from home").
+pub fn activities_from_place(place: &Place, endpoints: &[ActivityEndpoints]) -> Vec<String> {
+    endpoints
+        .iter()
+        .filter(|e| place.contains(&e.start))
+        .map(|e| e.activity_id.clone())
+        .collect()
+}
+
+/// Fill each place's `name` from a geocoder.
+pub fn name_places<G: Geocoder + ?Sized>(places: &mut [Place], geocoder: &G) {
+    for place in places {
+        place.name = geocoder.place_name(&place.center);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(id: &str, start: (f64, f64), end: (f64, f64)) -> ActivityEndpoints {
+        ActivityEndpoints {
+            activity_id: id.to_string(),
+            start: GpsPoint::new(start.0, start.1),
+            end: GpsPoint::new(end.0, end.1),
+        }
+    }
+
+    #[test]
+    fn test_home_and_work_clusters() {
+        let home = (51.5000, -0.1000);
+        let work = (51.5200, -0.0800);
+        let mut all = vec![
+            endpoints("c1", home, work),
+            endpoints("c2", (51.5003, -0.1002), (51.5201, -0.0801)),
+            endpoints("c3", work, home),
+            endpoints("loop", (51.5001, -0.1001), (51.5002, -0.0999)),
+        ];
+        // One-off ride far away: noise
+        all.push(endpoints("trip", (48.85, 2.35), (48.86, 2.36)));
+
+        let places = cluster_places(&all, &PlaceConfig::default());
+        assert_eq!(places.len(), 2);
+
+        let home_place = &places[0];
+        assert_eq!(home_place.id, "place_0");
+        assert_eq!((home_place.start_count, home_place.end_count), (3, 2));
+        assert_eq!(home_place.activity_ids.len(), 4);
+        assert!(home_place.radius_meters < 50.0);
+
+        assert_eq!(places[1].visit_count(), 3);
+        assert!(!places.iter().any(|p| p.activity_ids.contains(&"trip".to_string())));
+    }
+
+    #[test]
+    fn test_activities_from_place() {
+        let all: Vec<ActivityEndpoints> = (0..4)
+            .map(|i| endpoints(&format!("a{}", i), (51.5 + i as f64 * 0.0002, -0.1), (51.6, -0.2)))
+            .collect();
+        let places = cluster_places(&all, &PlaceConfig::default());
+        assert_eq!(places.len(), 2);
+
+        let start_place = places.iter().find(|p| p.start_count > 0).unwrap();
+        assert_eq!(activities_from_place(start_place, &all).len(), 4);
+        assert!(!start_place.contains(&GpsPoint::new(51.6, -0.2)));
+    }
+
+    #[test]
+    fn test_name_places() {
+        use crate::naming::{NamedPlace, OfflineGeocoder};
+
+        let all: Vec<ActivityEndpoints> = (0..3).map(|i| endpoints(&format!("a{}", i), (51.5, -0.1), (51.5, -0.1))).collect();
+        let mut places = cluster_places(&all, &PlaceConfig::default());
+        let geocoder = OfflineGeocoder::new(
+            vec![NamedPlace { name: "Home".to_string(), center: GpsPoint::new(51.5, -0.1), radius_meters: 100.0 }],
+            vec![],
+        );
+        name_places(&mut places, &geocoder);
+        assert_eq!(places.len(), 1);
+        assert_eq!(places[0].name.as_deref(), Some("Home"));
+        assert!(cluster_places(&[], &PlaceConfig::default()).is_empty());
+    }
+}
@@ -0,0 +1,818 @@
+//! # Track Preprocessing
+//!
+//! Optional cleanup applied to raw GPS tracks before simplification.
+//!
+//! Watch and phone GPS recorded in cities (urban canyons) or forests (canopy)
+//! jitters several meters around the true path. That jitter survives
+//! Douglas-Peucker simplification and shows up as noise in AMD scores and
+//! section consensus. Smoothing the raw track first removes most of it.
+//!
+//! ## Filters
+//!
+//! | Filter | Behaviour |
+//! |--------|-----------|
+//! | [`TrackSmoothing::Median`] | Rolling median over a centered window. Robust to single-point spikes, keeps corners sharp |
+//! | [`TrackSmoothing::Kalman`] | Forward constant-position Kalman filter. Strong smoothing of continuous jitter, slight lag on corners |
+//!
+//! Both filters are streaming: [`smooth_iter`] holds at most one window of
+//! points, so it can be combined with [`crate::RouteSignature::from_point_iter`].
+//!
+//! Set [`crate::MatchConfig::smoothing`] to smooth during signature creation.
+//! For section detection on full tracks, run [`smooth_track`] on each track
+//! before [`crate::detect_sections_from_tracks`]: both filters emit exactly one
+//! point per input point, so portion indices still refer to the original track.
+//!
+//! ## Merging
+//!
+//! [`merge_tracks`] joins an activity recorded across several files (e.g. a
+//! watch that crashed mid-ride) into one track. Points repeated at the join are
+//! dropped and the gap between parts is bridged with straight-line points, so
+//! the merged track has no jump that section detection would treat as a break.
+//! [`crate::RouteSignature::from_parts`] builds a single signature from the parts.
+//!
+//! ## Cleaning
+//!
+//! Signature creation silently drops points outside valid coordinate ranges.
+//! [`clean_track`] makes that explicit: with a [`ValidationPolicy`] it drops
+//! them, clamps them back into range, or fails on the first one, and returns
+//! a [`CleaningReport`] counting what it did.
+//!
+//! Before GPS lock some devices emit points at (0, 0) ("Null Island"), or
+//! repeat a stale fix for a long time. [`clean_track`] strips both: points at
+//! (0, 0) are removed, and runs of more than `max_repeated_points` identical
+//! points are frozen GPS, collapsed to one point (or removed entirely at the
+//! track start, where they are a stale fix rather than a stop). Signature
+//! creation and heatmaps always skip (0, 0) points, which would otherwise
+//! move start points to the Gulf of Guinea and rasterize a line across the
+//! world. [`crate::RouteSignature::from_points_cleaned`] returns the report
+//! with the signature.
+//!
+//! ## Resampling
+//!
+//! [`resample_track`] spaces a raw track's points evenly by distance or, with
+//! timestamps, by time. Stops distort both: GPS jitter while stationary adds
+//! spurious distance, and a long café stop would take most of a time-based
+//! sample. With timestamps, the track counts as stationary while slower than
+//! `stationary_speed`, and each stop counts for at most
+//! `max_stationary_seconds`. [`strip_stationary`] drops the points past that
+//! cap, and [`crate::RouteSignature::from_timed_points`] applies it before
+//! simplification, so stop jitter doesn't inflate AMD.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, preprocess::{smooth_track, TrackSmoothing}};
+//!
+//! let noisy = vec![
+//!     GpsPoint::new(51.5000, -0.1000),
+//!     GpsPoint::new(51.5001, -0.1000),
+//!     GpsPoint::new(51.5100, -0.1000), // spike
+//!     GpsPoint::new(51.5003, -0.1000),
+//!     GpsPoint::new(51.5004, -0.1000),
+//! ];
+//!
+//! let smoothed = smooth_track(&noisy, &TrackSmoothing::Median { window: 3 });
+//! assert!(smoothed[2].latitude < 51.501);
+//! ```
+
+use std::collections::VecDeque;
+use crate::GpsPoint;
+use crate::geo_utils::{haversine_distance, interpolate_point, normalize_longitude};
+
+/// Leading points of a part within this distance of the previous part's end
+/// are treated as duplicates recorded on both sides of the crash.
+const JOIN_TOLERANCE_METERS: f64 = 5.0;
+
+/// Spacing of the straight-line points bridging a gap between parts.
+const GAP_FILL_SPACING_METERS: f64 = 20.0;
+
+/// Points within this many degrees of (0, 0) count as Null Island.
+const NULL_ISLAND_DEGREES: f64 = 1e-6;
+
+/// Smoothing filter applied to raw tracks before simplification.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum TrackSmoothing {
+    /// Rolling median of latitude and longitude over `window` points
+    /// (rounded up to an odd number, windows shrink at the track ends).
+    Median { window: u32 },
+    /// Forward Kalman filter with a constant-position model.
+    /// Noise values are standard deviations in meters; a typical watch uses
+    /// `process_noise: 3.0`, `measurement_noise: 10.0`.
+    Kalman { process_noise: f64, measurement_noise: f64 },
+}
+
+/// Smooth a complete track.
+pub fn smooth_track(points: &[GpsPoint], smoothing: &TrackSmoothing) -> Vec<GpsPoint> {
+    smooth_iter(points.iter().copied(), smoothing).collect()
+}
+
+/// Smooth a stream of points, holding at most one filter window in memory.
+pub fn smooth_iter<'a, I>(points: I, smoothing: &TrackSmoothing) -> Box<dyn Iterator<Item = GpsPoint> + 'a>
+where
+    I: IntoIterator<Item = GpsPoint>,
+    I::IntoIter: 'a,
+{
+    match *smoothing {
+        TrackSmoothing::Median { window } => Box::new(MedianSmoother::new(points.into_iter(), window)),
+        TrackSmoothing::Kalman { process_noise, measurement_noise } => {
+            Box::new(KalmanSmoother::new(points.into_iter(), process_noise, measurement_noise))
+        }
+    }
+}
+
+// =============================================================================
+// Rolling Median
+// =============================================================================
+
+/// Streaming centered rolling median.
+struct MedianSmoother<I> {
+    inner: I,
+    /// Half window size (window = 2 * half + 1)
+    half: usize,
+    /// Buffered points, `buffer[0]` is the point at index `buffer_start`
+    buffer: VecDeque<GpsPoint>,
+    buffer_start: usize,
+    /// Index of the next point to emit
+    next_out: usize,
+    /// Number of points read from `inner`
+    read: usize,
+    exhausted: bool,
+}
+
+impl<I: Iterator<Item = GpsPoint>> MedianSmoother<I> {
+    fn new(inner: I, window: u32) -> Self {
+        let half = (window.max(1) as usize) / 2;
+        Self {
+            inner,
+            half,
+            buffer: VecDeque::with_capacity(2 * half + 1),
+            buffer_start: 0,
+            next_out: 0,
+            read: 0,
+            exhausted: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = GpsPoint>> Iterator for MedianSmoother<I> {
+    type Item = GpsPoint;
+
+    fn next(&mut self) -> Option<GpsPoint> {
+        // Read ahead until the window around next_out is complete
+        while !self.exhausted && self.read <= self.next_out + self.half {
+            match self.inner.next() {
+                Some(p) => {
+                    self.buffer.push_back(p);
+                    self.read += 1;
+                }
+                None => self.exhausted = true,
+            }
+        }
+
+        if self.next_out >= self.read {
+            return None;
+        }
+
+        // Drop points that fell out of the window
+        let window_start = self.next_out.saturating_sub(self.half);
+        while self.buffer_start < window_start {
+            self.buffer.pop_front();
+            self.buffer_start += 1;
+        }
+
+        // Shrink symmetrically near the ends so the output stays centered
+        let available_after = self.read - 1 - self.next_out;
+        let radius = self.half.min(self.next_out).min(available_after);
+        let from = self.next_out - radius - self.buffer_start;
+        let to = self.next_out + radius - self.buffer_start;
+
+        let mut lats: Vec<f64> = self.buffer.range(from..=to).map(|p| p.latitude).collect();
+        let mut lngs: Vec<f64> = self.buffer.range(from..=to).map(|p| p.longitude).collect();
+        self.next_out += 1;
+
+        Some(GpsPoint::new(median(&mut lats), median(&mut lngs)))
+    }
+}
+
+/// Median of an odd-length, non-empty slice.
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    values[values.len() / 2]
+}
+
+// =============================================================================
+// Kalman Filter
+// =============================================================================
+
+/// Streaming constant-position Kalman filter (independent per axis, shared variance).
+struct KalmanSmoother<I> {
+    inner: I,
+    /// Process noise variance (m²) added per step
+    q: f64,
+    /// Measurement noise variance (m²)
+    r: f64,
+    state: Option<(GpsPoint, f64)>,
+}
+
+impl<I: Iterator<Item = GpsPoint>> KalmanSmoother<I> {
+    fn new(inner: I, process_noise: f64, measurement_noise: f64) -> Self {
+        Self {
+            inner,
+            q: process_noise * process_noise,
+            r: (measurement_noise * measurement_noise).max(f64::EPSILON),
+            state: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = GpsPoint>> Iterator for KalmanSmoother<I> {
+    type Item = GpsPoint;
+
+    fn next(&mut self) -> Option<GpsPoint> {
+        let measured = self.inner.next()?;
+
+        let (estimate, variance) = match self.state {
+            None => (measured, self.r),
+            Some((prev, prev_variance)) => {
+                // Predict: position unchanged, uncertainty grows
+                let predicted_variance = prev_variance + self.q;
+                // Update towards the measurement
+                let gain = predicted_variance / (predicted_variance + self.r);
+                let estimate = GpsPoint::new(
+                    prev.latitude + gain * (measured.latitude - prev.latitude),
+                    prev.longitude + gain * (measured.longitude - prev.longitude),
+                );
+                (estimate, (1.0 - gain) * predicted_variance)
+            }
+        };
+
+        self.state = Some((estimate, variance));
+        Some(estimate)
+    }
+}
+
+/// Merge the parts of an activity recorded across multiple files into one track.
+///
+/// Parts must be in recording order. Invalid points and empty parts are dropped,
+/// points at the start of a part that repeat the previous part's end are
+/// skipped, and gaps between parts are filled with straight-line points.
+///
+/// # Example
+/// ```
+/// use route_matcher::{GpsPoint, merge_tracks};
+///
+/// let before_crash = vec![GpsPoint::new(51.5000, -0.1), GpsPoint::new(51.5010, -0.1)];
+/// let after_restart = vec![GpsPoint::new(51.5020, -0.1), GpsPoint::new(51.5030, -0.1)];
+///
+/// let merged = merge_tracks(vec![before_crash, after_restart]);
+/// assert_eq!(merged.first(), Some(&GpsPoint::new(51.5000, -0.1)));
+/// assert_eq!(merged.last(), Some(&GpsPoint::new(51.5030, -0.1)));
+/// ```
+pub fn merge_tracks(parts: Vec<Vec<GpsPoint>>) -> Vec<GpsPoint> {
+    let mut merged: Vec<GpsPoint> = Vec::with_capacity(parts.iter().map(Vec::len).sum());
+
+    for part in parts {
+        let mut points = part.into_iter().filter(GpsPoint::is_valid).peekable();
+
+        if let Some(&last) = merged.last() {
+            while points.next_if(|p| haversine_distance(&last, p) <= JOIN_TOLERANCE_METERS).is_some() {}
+
+            if let Some(next) = points.peek() {
+                let gap = haversine_distance(&last, next);
+                let fill = (gap / GAP_FILL_SPACING_METERS).ceil() as usize;
+                merged.extend((1..fill).map(|i| interpolate_point(&last, next, i as f64 / fill as f64)));
+            }
+        }
+
+        merged.extend(points);
+    }
+
+    merged
+}
+
+/// What [`clean_track`] does with points outside valid coordinate ranges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum ValidationPolicy {
+    /// Drop them, as signature creation does.
+    #[default]
+    Drop,
+    /// Clamp latitude to ±90° and wrap longitude into ±180°. Points with
+    /// non-finite coordinates are still dropped.
+    Clamp,
+    /// Return an error naming the first one.
+    Strict,
+}
+
+/// Options for [`clean_track`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct CleaningConfig {
+    /// Handling of invalid points. Default: [`ValidationPolicy::Drop`]
+    pub policy: ValidationPolicy,
+    /// Remove points at (0, 0). Default: true
+    #[cfg_attr(feature = "ffi", uniffi(default = true))]
+    pub strip_null_island: bool,
+    /// Longer runs of identical points are frozen GPS (0 keeps them). Default: 10
+    #[cfg_attr(feature = "ffi", uniffi(default = 10))]
+    pub max_repeated_points: u32,
+}
+
+impl Default for CleaningConfig {
+    fn default() -> Self {
+        Self {
+            policy: ValidationPolicy::Drop,
+            strip_null_island: true,
+            max_repeated_points: 10,
+        }
+    }
+}
+
+/// What [`clean_track`] found and changed
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct CleaningReport {
+    pub input_points: u32,
+    pub kept_points: u32,
+    /// Points removed for invalid coordinates
+    pub dropped_points: u32,
+    /// Points moved back into valid ranges ([`ValidationPolicy::Clamp`])
+    pub clamped_points: u32,
+    /// Runs of consecutive valid points at (0, 0)
+    pub null_island_runs: u32,
+    /// Valid points at (0, 0), removed with `strip_null_island`
+    pub null_island_points: u32,
+    /// Runs of more than `max_repeated_points` identical points
+    #[cfg_attr(feature = "ffi", uniffi(default = 0))]
+    pub frozen_runs: u32,
+    /// Points removed from frozen runs
+    #[cfg_attr(feature = "ffi", uniffi(default = 0))]
+    pub frozen_points: u32,
+}
+
+/// Whether `point` is at (0, 0), where devices without a fix often report.
+pub fn is_null_island(point: &GpsPoint) -> bool {
+    point.latitude.abs() < NULL_ISLAND_DEGREES && point.longitude.abs() < NULL_ISLAND_DEGREES
+}
+
+/// Apply `config.policy` to a raw track and strip Null Island points and
+/// frozen runs, reporting what was changed. Errors only with
+/// [`ValidationPolicy::Strict`].
+///
+/// # Example
+/// ```
+/// use route_matcher::GpsPoint;
+/// use route_matcher::preprocess::{clean_track, CleaningConfig, ValidationPolicy};
+///
+/// let points = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(f64::NAN, 0.0), GpsPoint::new(51.51, -0.10)];
+///
+/// let (cleaned, report) = clean_track(&points, &CleaningConfig::default()).unwrap();
+/// assert_eq!((cleaned.len(), report.dropped_points), (2, 1));
+///
+/// let strict = CleaningConfig { policy: ValidationPolicy::Strict, ..CleaningConfig::default() };
+/// assert!(clean_track(&points, &strict).is_err());
+/// ```
+pub fn clean_track(points: &[GpsPoint], config: &CleaningConfig) -> Result<(Vec<GpsPoint>, CleaningReport), String> {
+    let mut report = CleaningReport { input_points: points.len() as u32, ..CleaningReport::default() };
+    let mut cleaned = Vec::with_capacity(points.len());
+    let mut in_null_island = false;
+
+    for (i, p) in points.iter().enumerate() {
+        let point = if p.is_valid() {
+            *p
+        } else {
+            match config.policy {
+                ValidationPolicy::Strict => {
+                    return Err(format!("Invalid coordinate ({}, {}) at point {}", p.latitude, p.longitude, i));
+                }
+                ValidationPolicy::Clamp if p.latitude.is_finite() && p.longitude.is_finite() => {
+                    report.clamped_points += 1;
+                    GpsPoint::new(p.latitude.clamp(-90.0, 90.0), normalize_longitude(p.longitude))
+                }
+                _ => {
+                    report.dropped_points += 1;
+                    continue;
+                }
+            }
+        };
+
+        let null_island = is_null_island(&point);
+        if null_island {
+            if !in_null_island {
+                report.null_island_runs += 1;
+            }
+            report.null_island_points += 1;
+        }
+        in_null_island = null_island;
+        if !(null_island && config.strip_null_island) {
+            cleaned.push(point);
+        }
+    }
+
+    if config.max_repeated_points > 0 {
+        cleaned = strip_frozen_runs(cleaned, config.max_repeated_points as usize, &mut report);
+    }
+    report.kept_points = cleaned.len() as u32;
+    Ok((cleaned, report))
+}
+
+/// Collapse runs of more than `max_repeated` identical points to their first
+/// point, dropping a leading run entirely.
+fn strip_frozen_runs(points: Vec<GpsPoint>, max_repeated: usize, report: &mut CleaningReport) -> Vec<GpsPoint> {
+    let mut kept = Vec::with_capacity(points.len());
+    for run in points.chunk_by(|a, b| a == b) {
+        if run.len() <= max_repeated {
+            kept.extend_from_slice(run);
+            continue;
+        }
+        report.frozen_runs += 1;
+        if kept.is_empty() {
+            report.frozen_points += run.len() as u32;
+        } else {
+            report.frozen_points += run.len() as u32 - 1;
+            kept.push(run[0]);
+        }
+    }
+    kept
+}
+
+/// What [`resample_track`] spaces its points evenly by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum ResampleMode {
+    /// Distance along the track.
+    #[default]
+    Distance,
+    /// Elapsed time (distance without timestamps).
+    Time,
+}
+
+/// Options for [`resample_track`] and [`strip_stationary`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct ResampleConfig {
+    /// Default: [`ResampleMode::Distance`]
+    pub mode: ResampleMode,
+    /// Slower than this the track is stationary (m/s). Default: 0.5
+    pub stationary_speed: f64,
+    /// Longest a stop counts for (seconds); None leaves stops alone. Default: 30
+    pub max_stationary_seconds: Option<f64>,
+}
+
+impl Default for ResampleConfig {
+    fn default() -> Self {
+        Self {
+            mode: ResampleMode::Distance,
+            stationary_speed: 0.5,
+            max_stationary_seconds: Some(30.0),
+        }
+    }
+}
+
+/// Resample a raw track to `count` points, evenly spaced by `config.mode`.
+///
+/// `timestamps` (Unix seconds, one per point) enable time spacing and the
+/// stop cap; pass an empty slice without them.
+///
+/// # Example
+/// ```
+/// use route_matcher::GpsPoint;
+/// use route_matcher::preprocess::{resample_track, ResampleConfig, ResampleMode};
+///
+/// // 100m in 10s, then 100m in 90s: half the time samples fall in the slow part
+/// let points = vec![GpsPoint::new(51.5, -0.1), GpsPoint::new(51.5009, -0.1), GpsPoint::new(51.5018, -0.1)];
+/// let config = ResampleConfig { mode: ResampleMode::Time, ..ResampleConfig::default() };
+///
+/// let resampled = resample_track(&points, &[0, 10, 100], 11, &config);
+/// assert_eq!(resampled.len(), 11);
+/// assert!(resampled[2].latitude > 51.5009);
+/// ```
+pub fn resample_track(points: &[GpsPoint], timestamps: &[i64], count: usize, config: &ResampleConfig) -> Vec<GpsPoint> {
+    let weights = leg_weights(points, timestamps, config);
+    let mut cumulative = Vec::with_capacity(points.len());
+    cumulative.push(0.0);
+    for w in &weights {
+        cumulative.push(cumulative[cumulative.len() - 1] + w);
+    }
+    let total = cumulative[cumulative.len() - 1];
+    if points.len() < 2 || count < 2 || total <= 0.0 {
+        return points[..count.min(points.len())].to_vec();
+    }
+
+    (0..count)
+        .map(|k| {
+            let target = total * k as f64 / (count - 1) as f64;
+            // First leg ending at or after the target, skipping empty ones
+            let seg = cumulative[1..].partition_point(|&c| c < target).min(weights.len() - 1);
+            let ratio = if weights[seg] > 0.0 { (target - cumulative[seg]) / weights[seg] } else { 1.0 };
+            interpolate_point(&points[seg], &points[seg + 1], ratio.clamp(0.0, 1.0))
+        })
+        .collect()
+}
+
+/// Drop the points of each stop past `config.max_stationary_seconds`, so a
+/// long stop's GPS jitter doesn't survive simplification. Without
+/// timestamps (one per point) the track is returned unchanged.
+pub fn strip_stationary(points: &[GpsPoint], timestamps: &[i64], config: &ResampleConfig) -> Vec<GpsPoint> {
+    if timestamps.len() != points.len() || config.max_stationary_seconds.is_none() {
+        return points.to_vec();
+    }
+    let fractions = stop_fractions(points, timestamps, config);
+    points
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i == 0 || i == points.len() - 1 || fractions[i - 1] > 0.0)
+        .map(|(_, p)| *p)
+        .collect()
+}
+
+/// Weight of each leg for [`resample_track`]: its length or duration,
+/// scaled down on stops past the cap.
+fn leg_weights(points: &[GpsPoint], timestamps: &[i64], config: &ResampleConfig) -> Vec<f64> {
+    let timed = timestamps.len() == points.len();
+    let fractions = if timed { stop_fractions(points, timestamps, config) } else { vec![1.0; points.len().saturating_sub(1)] };
+    points
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| {
+            let base = match config.mode {
+                ResampleMode::Time if timed => (timestamps[i + 1] - timestamps[i]).max(0) as f64,
+                _ => haversine_distance(&w[0], &w[1]),
+            };
+            base * fractions[i]
+        })
+        .collect()
+}
+
+/// Fraction of each leg that counts: 1 while moving, and on a stop the
+/// part of its duration within `max_stationary_seconds` of the stop's start.
+fn stop_fractions(points: &[GpsPoint], timestamps: &[i64], config: &ResampleConfig) -> Vec<f64> {
+    let Some(cap) = config.max_stationary_seconds else {
+        return vec![1.0; points.len().saturating_sub(1)];
+    };
+    let mut stopped_for = 0.0;
+    points
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| {
+            let dt = (timestamps[i + 1] - timestamps[i]) as f64;
+            let stationary = dt > 0.0 && haversine_distance(&w[0], &w[1]) < config.stationary_speed * dt;
+            if !stationary {
+                stopped_for = 0.0;
+                return 1.0;
+            }
+            let counted = (cap - stopped_for).clamp(0.0, dt);
+            stopped_for += dt;
+            counted / dt
+        })
+        .collect()
+}
+
+/// Split one flat `[lat, lng, lat, lng, ...]` buffer into tracks, track `i`
+/// being points `offsets[i]..offsets[i + 1]`.
+///
+/// Used by the Node.js and C bindings, which pass a whole batch as one buffer.
+/// Returns an error if the buffer has odd length or an offset is out of range.
+///
+/// # Example
+/// ```
+/// use route_matcher::preprocess::split_flat_tracks;
+///
+/// let coords = [51.50, -0.10, 51.51, -0.10, 51.52, -0.10];
+/// let tracks = split_flat_tracks(&coords, &[0, 1, 3]).unwrap();
+/// assert_eq!(tracks[1].len(), 2);
+/// ```
+pub fn split_flat_tracks(coords: &[f64], offsets: &[u32]) -> Result<Vec<Vec<GpsPoint>>, String> {
+    if !coords.len().is_multiple_of(2) {
+        return Err(format!("Coordinate buffer has odd length {}", coords.len()));
+    }
+
+    let point_count = coords.len() / 2;
+    offsets
+        .windows(2)
+        .map(|range| {
+            let (start, end) = (range[0] as usize, range[1] as usize);
+            if start > end || end > point_count {
+                return Err(format!("Invalid point range {}..{} for {} points", start, end, point_count));
+            }
+            Ok(coords[start * 2..end * 2].chunks_exact(2).map(|c| GpsPoint::new(c[0], c[1])).collect())
+        })
+        .collect()
+}
+
+/// Many polylines in one flat buffer: polyline `i` is `ids[i]`, with points
+/// `offsets[i]..offsets[i + 1]` of `coords` (`[lat, lng, lat, lng, ...]`).
+///
+/// The inverse of [`split_flat_tracks`], so map layers can build geometry
+/// for a whole library from three arrays.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct FlatPolylines {
+    pub ids: Vec<String>,
+    pub coords: Vec<f64>,
+    pub offsets: Vec<u32>,
+}
+
+impl FlatPolylines {
+    /// Join polylines into one buffer, in order.
+    ///
+    /// # Example
+    /// ```
+    /// use route_matcher::GpsPoint;
+    /// use route_matcher::preprocess::FlatPolylines;
+    ///
+    /// let a = vec![GpsPoint::new(51.50, -0.10), GpsPoint::new(51.51, -0.10)];
+    /// let b = vec![GpsPoint::new(48.85, 2.35)];
+    /// let flat = FlatPolylines::from_polylines([("a", &a[..]), ("b", &b[..])]);
+    ///
+    /// assert_eq!(flat.offsets, vec![0, 2, 3]);
+    /// assert_eq!(flat.coords.len(), 6);
+    /// ```
+    pub fn from_polylines<'a>(polylines: impl IntoIterator<Item = (&'a str, &'a [GpsPoint])>) -> Self {
+        let mut flat = FlatPolylines { offsets: vec![0], ..Default::default() };
+        for (id, points) in polylines {
+            flat.ids.push(id.to_string());
+            flat.coords.extend(points.iter().flat_map(|p| [p.latitude, p.longitude]));
+            flat.offsets.push((flat.coords.len() / 2) as u32);
+        }
+        flat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_utils::haversine_distance;
+
+    fn straight(count: usize) -> Vec<GpsPoint> {
+        (0..count).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect()
+    }
+
+    #[test]
+    fn test_clean_track_policies() {
+        let points = vec![
+            GpsPoint::new(0.0, 0.0),
+            GpsPoint::new(0.0, 0.0),
+            GpsPoint::new(51.50, 181.0),
+            GpsPoint::new(f64::INFINITY, -0.1),
+            GpsPoint::new(0.0, 0.0),
+            GpsPoint::new(51.51, -0.10),
+        ];
+
+        let keep = CleaningConfig { strip_null_island: false, ..CleaningConfig::default() };
+        let (cleaned, report) = clean_track(&points, &keep).unwrap();
+        assert_eq!(cleaned.len(), 4);
+        assert_eq!((report.dropped_points, report.clamped_points), (2, 0));
+        assert_eq!((report.null_island_runs, report.null_island_points), (1, 3));
+
+        let clamp = CleaningConfig { policy: ValidationPolicy::Clamp, ..keep };
+        let (cleaned, report) = clean_track(&points, &clamp).unwrap();
+        assert_eq!((report.dropped_points, report.clamped_points, report.kept_points), (1, 1, 5));
+        assert!((cleaned[2].longitude + 179.0).abs() < 1e-9);
+        // The clamped point separates the (0, 0) runs
+        assert_eq!(report.null_island_runs, 2);
+
+        let strict = CleaningConfig { policy: ValidationPolicy::Strict, ..CleaningConfig::default() };
+        assert_eq!(clean_track(&points, &strict).unwrap_err(), "Invalid coordinate (51.5, 181) at point 2");
+    }
+
+    #[test]
+    fn test_clean_track_strips_null_island_and_frozen_runs() {
+        // A stale fix held before lock, (0, 0) glitches, then a stop at lights
+        let stale = GpsPoint::new(48.85, 2.35);
+        let mut points = vec![stale; 12];
+        points.extend([GpsPoint::new(0.0, 0.0), GpsPoint::new(0.0, 0.0)]);
+        points.extend(straight(3));
+        points.extend(vec![GpsPoint::new(51.5003, -0.1); 15]);
+        points.extend(straight(3).iter().map(|p| GpsPoint::new(p.latitude + 0.0004, p.longitude)));
+
+        let (cleaned, report) = clean_track(&points, &CleaningConfig::default()).unwrap();
+        assert_eq!(cleaned.first(), Some(&GpsPoint::new(51.5, -0.1)));
+        assert_eq!(cleaned.len(), 7);
+        assert_eq!((report.null_island_runs, report.null_island_points), (1, 2));
+        assert_eq!((report.frozen_runs, report.frozen_points), (2, 12 + 14));
+        assert!(!cleaned.iter().any(is_null_island));
+
+        // Short repeats are kept
+        let stop = vec![stale; 5];
+        assert_eq!(clean_track(&stop, &CleaningConfig::default()).unwrap().0.len(), 5);
+    }
+
+    #[test]
+    fn test_resample_track_caps_stops() {
+        // 10 points 11m apart every 10s, a 5 minute stop with jitter, 10 more points
+        let mut points = straight(10);
+        let mut timestamps: Vec<i64> = (0..10).map(|i| i * 10).collect();
+        for i in 1..=30 {
+            points.push(GpsPoint::new(51.5009 + (i % 2) as f64 * 0.00003, -0.1));
+            timestamps.push(90 + i * 10);
+        }
+        points.extend(straight(20).into_iter().skip(10));
+        timestamps.extend((1..=10).map(|i| 390 + i * 10));
+
+        let time = ResampleConfig { mode: ResampleMode::Time, ..ResampleConfig::default() };
+        let uncapped = ResampleConfig { max_stationary_seconds: None, ..time.clone() };
+        let near_stop = |resampled: &[GpsPoint]| resampled.iter().filter(|p| (p.latitude - 51.5009).abs() < 0.00005).count();
+        // Uncapped, the stop takes 5 of every 7 time samples; capped, about 1 in 7
+        assert!(near_stop(&resample_track(&points, &timestamps, 70, &uncapped)) > 40);
+        assert!(near_stop(&resample_track(&points, &timestamps, 70, &time)) < 15);
+
+        // By distance, jitter past the cap adds nothing; without timestamps it does
+        let by_distance = resample_track(&points, &timestamps, 20, &ResampleConfig::default());
+        assert!(near_stop(&by_distance) <= 3);
+        assert!(near_stop(&resample_track(&points, &[], 20, &ResampleConfig::default())) > 3);
+
+        // 3 of the 30 stop points fall within the first 30 seconds
+        assert_eq!(strip_stationary(&points, &timestamps, &time).len(), 10 + 3 + 10);
+    }
+
+    #[test]
+    fn test_merge_bridges_gap() {
+        let first = straight(5);
+        let second: Vec<GpsPoint> = (10..15).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect();
+        let merged = merge_tracks(vec![first.clone(), vec![], second.clone()]);
+
+        assert_eq!(&merged[..5], &first[..]);
+        assert_eq!(&merged[merged.len() - 5..], &second[..]);
+        // ~110m gap bridged with points no further apart than the fill spacing
+        assert!(merged.len() > 10);
+        assert!(merged.windows(2).all(|w| haversine_distance(&w[0], &w[1]) <= GAP_FILL_SPACING_METERS + 0.1));
+    }
+
+    #[test]
+    fn test_merge_skips_repeated_points() {
+        let first = straight(5);
+        let mut second = vec![first[4], GpsPoint::new(f64::NAN, 0.0)];
+        second.extend((5..8).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)));
+
+        let merged = merge_tracks(vec![first, second]);
+        assert_eq!(merged, straight(8));
+        assert!(merge_tracks(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_split_flat_tracks_rejects_bad_input() {
+        let coords = [51.5, -0.1, 51.6, -0.1];
+        assert_eq!(split_flat_tracks(&coords, &[0, 2]).unwrap()[0].len(), 2);
+        assert!(split_flat_tracks(&coords, &[0, 3]).is_err());
+        assert!(split_flat_tracks(&coords, &[2, 1]).is_err());
+        assert!(split_flat_tracks(&coords[..3], &[0, 1]).is_err());
+        assert!(split_flat_tracks(&coords, &[0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_flat_polylines_round_trip() {
+        let tracks = [straight(3), vec![], straight(2)];
+        let flat = FlatPolylines::from_polylines(
+            ["a", "b", "c"].into_iter().zip(&tracks).map(|(id, t)| (id, &t[..])),
+        );
+        assert_eq!(flat.ids, ["a", "b", "c"]);
+        assert_eq!(split_flat_tracks(&flat.coords, &flat.offsets).unwrap(), tracks);
+        assert_eq!(FlatPolylines::from_polylines([]).offsets, vec![0]);
+    }
+
+    #[test]
+    fn test_median_removes_spike() {
+        let mut track = straight(11);
+        track[5].longitude += 0.01; // ~700m sideways spike
+        let smoothed = smooth_track(&track, &TrackSmoothing::Median { window: 5 });
+
+        assert_eq!(smoothed.len(), track.len());
+        assert!((smoothed[5].longitude - (-0.1)).abs() < 1e-9);
+        // Endpoints are preserved (window shrinks to a single point)
+        assert_eq!(smoothed[0], track[0]);
+        assert_eq!(smoothed[10], track[10]);
+    }
+
+    #[test]
+    fn test_median_window_one_is_identity() {
+        let track = straight(5);
+        assert_eq!(smooth_track(&track, &TrackSmoothing::Median { window: 1 }), track);
+        assert!(smooth_track(&[], &TrackSmoothing::Median { window: 5 }).is_empty());
+    }
+
+    #[test]
+    fn test_kalman_reduces_jitter() {
+        let clean = straight(200);
+        let noisy: Vec<GpsPoint> = clean
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let jitter = if i % 2 == 0 { 0.0001 } else { -0.0001 }; // ~7m zig-zag
+                GpsPoint::new(p.latitude, p.longitude + jitter)
+            })
+            .collect();
+
+        let smoothed = smooth_track(&noisy, &TrackSmoothing::Kalman { process_noise: 3.0, measurement_noise: 10.0 });
+        assert_eq!(smoothed.len(), noisy.len());
+
+        // Lateral jitter is damped even though the forward filter lags slightly
+        let lateral = |track: &[GpsPoint]| -> f64 {
+            track[100..].iter().map(|p| (p.longitude + 0.1).abs()).sum::<f64>()
+        };
+        assert!(lateral(&smoothed) < lateral(&noisy) * 0.5);
+        // Forward progress is preserved
+        assert!(haversine_distance(&smoothed[199], &clean[199]) < 50.0);
+    }
+}
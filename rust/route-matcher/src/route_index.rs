@@ -0,0 +1,371 @@
+//! # Route Index
+//!
+//! A long-lived spatial index over route signatures, so a new activity can be
+//! matched against an existing library without re-running full grouping.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, RouteSignature, MatchConfig, RouteIndex};
+//!
+//! let config = MatchConfig::default();
+//! let points: Vec<GpsPoint> = (0..10)
+//!     .map(|i| GpsPoint::new(51.5074 + i as f64 * 0.001, -0.1278))
+//!     .collect();
+//!
+//! let mut index = RouteIndex::new();
+//! index.insert(RouteSignature::from_points("existing", &points, &config).unwrap());
+//!
+//! let new_activity = RouteSignature::from_points("new", &points, &config).unwrap();
+//! let matches = index.find_matches(&new_activity, &config);
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].activity_id_2, "existing");
+//! ```
+//!
+//! ## Persistence
+//!
+//! With the `persist` feature, [`RouteIndex::save`] writes the signatures and
+//! the already-built R-tree, and [`RouteIndex::load`] restores both without a
+//! bulk rebuild, so large libraries are queryable immediately on app start.
+
+use std::collections::HashMap;
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+use crate::{GpsPoint, MatchConfig, MatchResult, RouteBounds, RouteSignature};
+use crate::geo_utils::{project_point_onto_route, RouteProjection};
+
+/// Version of the persisted index format (bump when the layout changes)
+#[cfg(feature = "persist")]
+const PERSIST_FORMAT_VERSION: u32 = 1;
+
+/// A route near a queried location, with the location projected onto it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct NearestRoute {
+    /// Activity ID of the indexed route
+    pub activity_id: String,
+    /// Projection of the query point onto the route
+    pub projection: RouteProjection,
+}
+
+// Bounding-box distance (in degrees) is a cheap lower bound for nearest-route search
+impl PointDistance for RouteBounds {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        self.envelope().distance_2(point)
+    }
+}
+
+/// Spatial index over route signatures supporting match and location queries.
+#[derive(Debug, Default)]
+pub struct RouteIndex {
+    signatures: HashMap<String, RouteSignature>,
+    rtree: RTree<RouteBounds>,
+}
+
+impl RouteIndex {
+    /// Create an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build an index from signatures using R-tree bulk loading.
+    ///
+    /// If several signatures share an activity ID, the last one wins.
+    pub fn from_signatures(signatures: Vec<RouteSignature>) -> Self {
+        let signatures: HashMap<String, RouteSignature> = signatures
+            .into_iter()
+            .map(|s| (s.activity_id.clone(), s))
+            .collect();
+        let bounds: Vec<RouteBounds> = signatures.values().map(|s| s.route_bounds()).collect();
+
+        Self {
+            signatures,
+            rtree: RTree::bulk_load(bounds),
+        }
+    }
+
+    /// Number of indexed signatures.
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// Whether the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Get an indexed signature by activity ID.
+    pub fn get(&self, activity_id: &str) -> Option<&RouteSignature> {
+        self.signatures.get(activity_id)
+    }
+
+    /// Iterate over all indexed signatures (in arbitrary order).
+    pub fn signatures(&self) -> impl Iterator<Item = &RouteSignature> {
+        self.signatures.values()
+    }
+
+    /// Add a signature, replacing any existing signature with the same activity ID.
+    pub fn insert(&mut self, signature: RouteSignature) {
+        self.remove(&signature.activity_id);
+        self.rtree.insert(signature.route_bounds());
+        self.signatures.insert(signature.activity_id.clone(), signature);
+    }
+
+    /// Remove a signature by activity ID, returning it if it was indexed.
+    pub fn remove(&mut self, activity_id: &str) -> Option<RouteSignature> {
+        let signature = self.signatures.remove(activity_id)?;
+        self.rtree.remove(&signature.route_bounds());
+        Some(signature)
+    }
+
+    /// Find indexed routes matching the given signature.
+    ///
+    /// Applies the same R-tree and distance pre-filters as grouping, then
+    /// [`crate::compare_routes`]. The query signature itself (same activity ID)
+    /// is never returned. Results are sorted by match percentage (best first),
+    /// with `activity_id_1` set to the query.
+    pub fn find_matches(&self, signature: &RouteSignature, config: &MatchConfig) -> Vec<MatchResult> {
+        let search_bounds = crate::search_envelope(&signature.bounds, config.search_tolerance_degrees);
+
+        let mut matches: Vec<MatchResult> = self.rtree
+            .locate_in_envelope_intersecting(&search_bounds)
+            .filter(|b| {
+                b.activity_id != signature.activity_id
+                    && crate::distance_ratio_ok(signature.total_distance, b.distance, config)
+            })
+            .filter_map(|b| {
+                let candidate = self.signatures.get(&b.activity_id)?;
+                crate::compare_routes(signature, candidate, config)
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.match_percentage
+                .partial_cmp(&a.match_percentage)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        matches
+    }
+
+    /// Find the `k` indexed routes passing closest to a location.
+    ///
+    /// Candidates are visited in order of bounding-box distance and each is
+    /// projected with [`project_point_onto_route`]; the search stops once no
+    /// remaining bounding box can beat the current k-th best offset. Results are
+    /// sorted by offset (closest first). Useful for "which of my routes am I on?".
+    pub fn nearest_routes(&self, lat: f64, lng: f64, k: usize) -> Vec<NearestRoute> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let point = GpsPoint::new(lat, lng);
+        let query = [lng, lat];
+        // Conservative meters-per-degree so the bbox bound never overestimates
+        let min_meters_per_degree = 111_320.0 * lat.to_radians().cos().abs().max(0.01) * 0.99;
+
+        let mut nearest: Vec<NearestRoute> = Vec::with_capacity(k + 1);
+
+        for bounds in self.rtree.nearest_neighbor_iter(&query) {
+            if nearest.len() == k {
+                let bbox_distance = bounds.distance_2(&query).sqrt() * min_meters_per_degree;
+                if bbox_distance > nearest[k - 1].projection.offset {
+                    break;
+                }
+            }
+
+            let Some(sig) = self.signatures.get(&bounds.activity_id) else { continue };
+            let Some(projection) = project_point_onto_route(&point, &sig.points) else { continue };
+
+            let pos = nearest.partition_point(|n| n.projection.offset <= projection.offset);
+            if pos < k {
+                nearest.insert(pos, NearestRoute {
+                    activity_id: sig.activity_id.clone(),
+                    projection,
+                });
+                nearest.truncate(k);
+            }
+        }
+
+        nearest
+    }
+
+    /// Write the index (signatures and R-tree structure) as JSON.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use route_matcher::RouteIndex;
+    /// # let index = RouteIndex::new();
+    /// let file = std::fs::File::create("routes.idx").unwrap();
+    /// index.save(std::io::BufWriter::new(file)).unwrap();
+    ///
+    /// let file = std::fs::File::open("routes.idx").unwrap();
+    /// let restored = RouteIndex::load(std::io::BufReader::new(file)).unwrap();
+    /// ```
+    #[cfg(feature = "persist")]
+    pub fn save<W: std::io::Write>(&self, writer: W) -> Result<(), String> {
+        let persisted = PersistedIndexRef {
+            version: PERSIST_FORMAT_VERSION,
+            signatures: self.signatures.values().collect(),
+            rtree: &self.rtree,
+        };
+        serde_json::to_writer(writer, &persisted)
+            .map_err(|e| format!("Failed to save route index: {}", e))
+    }
+
+    /// Load an index previously written by [`RouteIndex::save`].
+    ///
+    /// The R-tree is restored as saved rather than bulk-loaded again.
+    #[cfg(feature = "persist")]
+    pub fn load<R: std::io::Read>(reader: R) -> Result<Self, String> {
+        let persisted: PersistedIndex = serde_json::from_reader(reader)
+            .map_err(|e| format!("Failed to load route index: {}", e))?;
+
+        if persisted.version != PERSIST_FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported route index version {} (expected {})",
+                persisted.version, PERSIST_FORMAT_VERSION
+            ));
+        }
+
+        let signatures: HashMap<String, RouteSignature> = persisted.signatures
+            .into_iter()
+            .map(|s| (s.activity_id.clone(), s))
+            .collect();
+
+        if persisted.rtree.size() != signatures.len() {
+            return Err(format!(
+                "Corrupt route index: {} R-tree entries for {} signatures",
+                persisted.rtree.size(),
+                signatures.len()
+            ));
+        }
+
+        Ok(Self {
+            signatures,
+            rtree: persisted.rtree,
+        })
+    }
+
+    /// Find indexed routes whose bounding box contains the given point.
+    pub fn find_containing(&self, point: &GpsPoint) -> Vec<&RouteSignature> {
+        self.rtree
+            .locate_in_envelope_intersecting(&AABB::from_point([point.longitude, point.latitude]))
+            .filter_map(|b| self.signatures.get(&b.activity_id))
+            .collect()
+    }
+}
+
+/// Borrowed on-disk layout used when saving
+#[cfg(feature = "persist")]
+#[derive(serde::Serialize)]
+struct PersistedIndexRef<'a> {
+    version: u32,
+    signatures: Vec<&'a RouteSignature>,
+    rtree: &'a RTree<RouteBounds>,
+}
+
+/// Owned on-disk layout used when loading
+#[cfg(feature = "persist")]
+#[derive(serde::Deserialize)]
+struct PersistedIndex {
+    version: u32,
+    signatures: Vec<RouteSignature>,
+    rtree: RTree<RouteBounds>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(start_lat: f64, start_lng: f64) -> Vec<GpsPoint> {
+        (0..10)
+            .map(|i| GpsPoint::new(start_lat + i as f64 * 0.001, start_lng))
+            .collect()
+    }
+
+    fn sig(id: &str, points: &[GpsPoint]) -> RouteSignature {
+        RouteSignature::from_points(id, points, &MatchConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_find_matches_excludes_self_and_distant() {
+        let london = route(51.5074, -0.1278);
+        let nyc = route(40.7128, -74.0060);
+        let index = RouteIndex::from_signatures(vec![
+            sig("a", &london),
+            sig("b", &london),
+            sig("c", &nyc),
+        ]);
+
+        let matches = index.find_matches(&sig("a", &london), &MatchConfig::default());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].activity_id_1, "a");
+        assert_eq!(matches[0].activity_id_2, "b");
+    }
+
+    #[test]
+    fn test_insert_replace_and_remove() {
+        let mut index = RouteIndex::new();
+        index.insert(sig("a", &route(51.5074, -0.1278)));
+        index.insert(sig("a", &route(40.7128, -74.0060)));
+        assert_eq!(index.len(), 1);
+        assert!(index.find_containing(&GpsPoint::new(51.51, -0.1278)).is_empty());
+
+        assert!(index.remove("a").is_some());
+        assert!(index.is_empty());
+        assert!(index.remove("a").is_none());
+    }
+
+    #[test]
+    fn test_nearest_routes() {
+        let index = RouteIndex::from_signatures(vec![
+            sig("near", &route(51.5074, -0.1278)),
+            sig("mid", &route(51.5074, -0.1200)),
+            sig("far", &route(40.7128, -74.0060)),
+        ]);
+
+        // ~35m east of "near"
+        let nearest = index.nearest_routes(51.510, -0.1273, 2);
+        assert_eq!(nearest.len(), 2);
+        assert_eq!(nearest[0].activity_id, "near");
+        assert_eq!(nearest[1].activity_id, "mid");
+        assert!(nearest[0].projection.offset < 50.0);
+        assert!(nearest[0].projection.distance_along > 0.0);
+
+        assert!(index.nearest_routes(51.51, -0.1273, 0).is_empty());
+        assert_eq!(index.nearest_routes(51.51, -0.1273, 10).len(), 3);
+    }
+
+    #[cfg(feature = "persist")]
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let index = RouteIndex::from_signatures(vec![
+            sig("a", &route(51.5074, -0.1278)),
+            sig("b", &route(51.5074, -0.1278)),
+        ]);
+
+        let mut buffer = Vec::new();
+        index.save(&mut buffer).unwrap();
+        let restored = RouteIndex::load(buffer.as_slice()).unwrap();
+
+        assert_eq!(restored.len(), 2);
+        let matches = restored.find_matches(restored.get("a").unwrap(), &MatchConfig::default());
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].activity_id_2, "b");
+
+        assert!(RouteIndex::load(&b"not json"[..]).is_err());
+    }
+
+    #[test]
+    fn test_find_containing() {
+        let index = RouteIndex::from_signatures(vec![
+            sig("london", &route(51.5074, -0.1278)),
+            sig("nyc", &route(40.7128, -74.0060)),
+        ]);
+
+        let found = index.find_containing(&GpsPoint::new(51.510, -0.1278));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].activity_id, "london");
+        assert!(index.find_containing(&GpsPoint::new(0.0, 0.0)).is_empty());
+    }
+}
@@ -17,21 +17,123 @@
 //! - Normalize all tracks to common parameterization (by distance)
 //! - At each position, collect nearby points from all tracks
 //! - Compute weighted average: weight = 1 / (distance_to_reference + epsilon)
-//! - Higher observation density → higher confidence → tighter future matching
+//! - With per-point horizontal accuracy
+//!   ([`detect_sections_from_tracks_with_accuracy`]), weight =
+//!   1 / (accuracy² + distance²) and the medoid is chosen by accuracy-weighted
+//!   AMD, so one bad phone track can't drag the polyline sideways
+//! - Higher observation density → higher confidence → tighter future matching:
+//!   each section stores a matching threshold that shrinks from
+//!   [`SectionConfig::proximity_threshold`] toward a few times its spread as
+//!   confidence grows (see [`FrequentSection::proximity_threshold`])
 //!
 //! ## Adaptive Boundaries
+//! With [`SectionConfig::adapt_boundaries`]:
 //! - Track where each activity's overlap starts/ends relative to section
 //! - Section can grow if tracks consistently extend beyond current bounds
 //! - Section contracts if tracks consistently end before current bounds
+//!
+//! "Consistently" means a quorum of 75% of traversals, and an end only moves
+//! by 50m or more. An end that some traversals pass and others stop short of
+//! stays where it is, so boundaries don't flap as activities are added.
+//!
+//! ## Recording Gaps
+//! With [`SectionConfig::max_gap_meters`], traces that jump further than that
+//! between consecutive points (tunnels, GPS dropouts) are only used as the
+//! medoid when every trace has a gap, so the straight chord across a gap
+//! doesn't become the consensus line when other tracks followed the road.
+//!
+//! ## Stable IDs
+//! Section IDs are derived from a hash of the sport type and the section's
+//! coarsely quantized endpoints and midpoint (direction-independent), so the
+//! same road gets the same ID across runs. Consensus geometry still drifts as
+//! tracks are added; [`reconcile_section_ids`] carries IDs over from a previous
+//! run by geometry, so user-assigned names and stars survive recomputation.
+//! With the `persist` feature, [`save_sections`] / [`load_sections`] store
+//! sections as JSON.
+//!
+//! ## Progress
+//! [`detect_sections_from_tracks_with_progress`] reports a [`SectionPhase`] and
+//! percentage as detection runs. Phases run once per sport type, so the
+//! percentage restarts for each sport.
+//! [`detect_sections_from_tracks_cancellable`] additionally stops between
+//! track pairs, clusters and post-processing steps once its
+//! [`CancellationToken`] is cancelled.
 
 use std::collections::{HashMap, HashSet};
-use crate::{GpsPoint, RouteGroup};
-use crate::geo_utils::{haversine_distance, compute_bounds, compute_center, polyline_length, bounds_overlap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use crate::{CancellationToken, GpsPoint, RouteGroup};
+use crate::indoor::{indoor_activity_ids, IndoorConfig};
+use crate::telemetry::pipeline_span;
+use crate::geo_utils::{haversine_distance, compute_bounds, compute_center, bounds_overlap, resample_evenly, DistanceModel};
+use crate::metrics::{containment, polyline_amd};
+use crate::projection::{LocalProjection, PlanarPoint, distance_sq};
 use rstar::{RTree, RTreeObject, PointDistance, AABB};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use log::info;
 
+/// Points both traces are resampled to for AMD between section traces
+const AMD_SAMPLES: usize = 50;
+
+/// Phase of section detection, reported to progress callbacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Enum))]
+pub enum SectionPhase {
+    /// Pairwise track overlap search
+    Overlaps,
+    /// Clustering overlaps into candidate sections
+    Clustering,
+    /// Medoid selection and consensus polylines per cluster
+    Consensus,
+    /// Fold splitting, merging, dedup and density splitting
+    PostProcessing,
+}
+
+/// Progress callback type: (phase, percent complete 0-100)
+pub type SectionProgress = Arc<dyn Fn(SectionPhase, u32) + Send + Sync>;
+
+/// Progress of one phase. Only reports when the percentage changes, so it is
+/// cheap to tick per work unit (also from rayon workers).
+struct PhaseProgress<'a> {
+    callback: Option<&'a SectionProgress>,
+    phase: SectionPhase,
+    total: usize,
+    done: AtomicUsize,
+    last_percent: AtomicU32,
+}
+
+impl<'a> PhaseProgress<'a> {
+    fn start(callback: Option<&'a SectionProgress>, phase: SectionPhase, total: usize) -> Self {
+        if let Some(callback) = callback {
+            callback(phase, 0);
+        }
+        Self {
+            callback,
+            phase,
+            total,
+            done: AtomicUsize::new(0),
+            last_percent: AtomicU32::new(0),
+        }
+    }
+
+    fn tick(&self) {
+        let Some(callback) = self.callback else { return };
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let percent = (done * 100 / self.total.max(1)).min(100) as u32;
+        if percent > self.last_percent.fetch_max(percent, Ordering::Relaxed) {
+            callback(self.phase, percent);
+        }
+    }
+
+    fn finish(&self) {
+        let Some(callback) = self.callback else { return };
+        if self.last_percent.fetch_max(100, Ordering::Relaxed) < 100 {
+            callback(self.phase, 100);
+        }
+    }
+}
+
 /// Configuration for section detection
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
@@ -48,6 +150,32 @@ pub struct SectionConfig {
     pub cluster_tolerance: f64,
     /// Number of sample points for AMD comparison (not for output!)
     pub sample_points: u32,
+    /// Keep separate traversal lists and consensus statistics per direction
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub split_directions: bool,
+    /// Formula for section and portion lengths. Default: None (haversine)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub distance_model: Option<DistanceModel>,
+    /// Jumps between consecutive points longer than this (meters) mark a
+    /// recording gap; traces with gaps are avoided as the medoid.
+    /// Default: None (no gap detection)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    pub max_gap_meters: Option<f64>,
+    /// Grow or contract section ends to where traversals consistently
+    /// continue or stop (see the module docs)
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub adapt_boundaries: bool,
+    /// Leave out virtual and indoor activities (see [`crate::indoor`]), so
+    /// repeated virtual courses don't become sections
+    #[cfg_attr(feature = "ffi", uniffi(default = false))]
+    pub exclude_indoor: bool,
+}
+
+impl SectionConfig {
+    /// The configured distance model, haversine if unset.
+    pub fn distance_model(&self) -> DistanceModel {
+        self.distance_model.unwrap_or_default()
+    }
 }
 
 impl Default for SectionConfig {
@@ -59,6 +187,11 @@ impl Default for SectionConfig {
             min_activities: 3,           // Need 3+ activities
             cluster_tolerance: 80.0,     // 80m for clustering similar overlaps
             sample_points: 50,           // For AMD comparison only
+            split_directions: false,
+            distance_model: None,
+            max_gap_meters: None,
+            adapt_boundaries: false,
+            exclude_indoor: false,
         }
     }
 }
@@ -66,6 +199,7 @@ impl Default for SectionConfig {
 /// Each activity's portion of a section (for pace comparison)
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
 pub struct SectionPortion {
     /// Activity ID
     pub activity_id: String,
@@ -80,10 +214,11 @@ pub struct SectionPortion {
 }
 
 /// A frequently-traveled section with adaptive consensus representation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
 pub struct FrequentSection {
-    /// Unique section ID
+    /// Section ID, stable across runs (see [`stable_section_id`])
     pub id: String,
     /// Sport type ("Run", "Ride", etc.)
     pub sport_type: String,
@@ -116,33 +251,95 @@ pub struct FrequentSection {
     /// Per-point observation density (how many activities pass through each point)
     /// Used for detecting high-traffic portions that should become separate sections
     pub point_density: Vec<u32>,
+    /// Traversals in the polyline's direction (with `SectionConfig::split_directions`)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    #[cfg_attr(feature = "persist", serde(default))]
+    pub same_direction: Option<DirectionStats>,
+    /// Traversals against the polyline's direction (with `SectionConfig::split_directions`)
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    #[cfg_attr(feature = "persist", serde(default))]
+    pub reverse_direction: Option<DirectionStats>,
+    /// Auto-generated name, e.g. "Along Thames Path" (see [`crate::naming`])
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    #[cfg_attr(feature = "persist", serde(default))]
+    pub suggested_name: Option<String>,
+    /// Distance (meters) within which new activities match this section,
+    /// tightened from `SectionConfig::proximity_threshold` as confidence grows.
+    /// None for sections stored before adaptive thresholds.
+    #[cfg_attr(feature = "ffi", uniffi(default = None))]
+    #[cfg_attr(feature = "persist", serde(default))]
+    pub matching_threshold: Option<f64>,
+}
+
+impl FrequentSection {
+    /// Distance (meters) within which new activities match this section: the
+    /// adaptive [`FrequentSection::matching_threshold`], or
+    /// `config.proximity_threshold` if the section has none.
+    pub fn proximity_threshold(&self, config: &SectionConfig) -> f64 {
+        self.matching_threshold.unwrap_or(config.proximity_threshold)
+    }
+}
+
+/// Traversal statistics for one direction of a section.
+///
+/// A hill climbed one way and descended the other has very different effort,
+/// so apps compare activities within a direction.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+#[cfg_attr(feature = "persist", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirectionStats {
+    /// Activities traversing in this direction
+    pub activity_ids: Vec<String>,
+    /// Number of traversals in this direction
+    pub traversal_count: u32,
+    /// Consensus confidence (0.0-1.0) from this direction's traces only
+    pub confidence: f64,
+    /// Average spread (meters) of this direction's traces from the consensus line
+    pub average_spread: f64,
+    /// Average portion distance in meters
+    pub average_distance_meters: f64,
 }
 
 // =============================================================================
 // R-tree Indexed Point for Spatial Queries
 // =============================================================================
 
-/// A GPS point with its index for R-tree queries
+/// A projected GPS point with its index for R-tree queries
 #[derive(Debug, Clone, Copy)]
 struct IndexedPoint {
     idx: usize,
-    lat: f64,
-    lng: f64,
+    /// Local planar coordinates in meters
+    xy: PlanarPoint,
 }
 
 impl RTreeObject for IndexedPoint {
     type Envelope = AABB<[f64; 2]>;
 
     fn envelope(&self) -> Self::Envelope {
-        AABB::from_point([self.lat, self.lng])
+        AABB::from_point(self.xy)
     }
 }
 
 impl PointDistance for IndexedPoint {
     fn distance_2(&self, point: &[f64; 2]) -> f64 {
-        let dlat = self.lat - point[0];
-        let dlng = self.lng - point[1];
-        dlat * dlat + dlng * dlng
+        distance_sq(self.xy, *point)
+    }
+}
+
+/// R-tree over a polyline in local planar meters, projected around the
+/// polyline's own center so queries stay accurate anywhere on the globe.
+struct PointIndex {
+    projection: LocalProjection,
+    tree: RTree<IndexedPoint>,
+}
+
+impl PointIndex {
+    /// Nearest indexed point: (index into the polyline, squared distance in meters²)
+    fn nearest(&self, point: &GpsPoint) -> Option<(usize, f64)> {
+        let query = self.projection.project(point);
+        self.tree
+            .nearest_neighbor(&query)
+            .map(|nearest| (nearest.idx, nearest.distance_2(&query)))
     }
 }
 
@@ -163,17 +360,20 @@ struct FullTrackOverlap {
     center: GpsPoint,
 }
 
-/// Build R-tree from GPS points for efficient spatial queries
-fn build_rtree(points: &[GpsPoint]) -> RTree<IndexedPoint> {
+/// Build a projected R-tree from GPS points for efficient spatial queries
+fn build_rtree(points: &[GpsPoint]) -> PointIndex {
+    let projection = LocalProjection::for_points(points);
     let indexed: Vec<IndexedPoint> = points.iter()
         .enumerate()
         .map(|(i, p)| IndexedPoint {
             idx: i,
-            lat: p.latitude,
-            lng: p.longitude
+            xy: projection.project(p),
         })
         .collect();
-    RTree::bulk_load(indexed)
+    PointIndex {
+        projection,
+        tree: RTree::bulk_load(indexed),
+    }
 }
 
 /// Find overlapping portion between two FULL GPS tracks
@@ -182,13 +382,11 @@ fn find_full_track_overlap(
     track_a: &[GpsPoint],
     activity_b: &str,
     track_b: &[GpsPoint],
-    tree_b: &RTree<IndexedPoint>,
+    tree_b: &PointIndex,
     config: &SectionConfig,
 ) -> Option<FullTrackOverlap> {
-    // Convert proximity threshold from meters to approximate degrees
-    // 1 degree ≈ 111km, so 30m ≈ 0.00027 degrees
-    let threshold_deg = config.proximity_threshold / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    // Compare squared planar distances against the proximity threshold
+    let threshold_sq = config.proximity_threshold * config.proximity_threshold;
 
     let mut best_start_a: Option<usize> = None;
     let mut best_end_a = 0;
@@ -203,21 +401,17 @@ fn find_full_track_overlap(
 
     for (i, point_a) in track_a.iter().enumerate() {
         // Use R-tree to find nearest point in track B
-        let query_point = [point_a.latitude, point_a.longitude];
-
-        if let Some(nearest) = tree_b.nearest_neighbor(&query_point) {
-            let dist_sq = nearest.distance_2(&query_point);
-
-            if dist_sq <= threshold_deg_sq {
+        if let Some((nearest_idx, dist_sq)) = tree_b.nearest(point_a) {
+            if dist_sq <= threshold_sq {
                 // Point is within threshold
                 if current_start_a.is_none() {
                     current_start_a = Some(i);
-                    current_min_b = nearest.idx;
-                    current_max_b = nearest.idx;
+                    current_min_b = nearest_idx;
+                    current_max_b = nearest_idx;
                     current_length = 0.0;
                 } else {
-                    current_min_b = current_min_b.min(nearest.idx);
-                    current_max_b = current_max_b.max(nearest.idx);
+                    current_min_b = current_min_b.min(nearest_idx);
+                    current_max_b = current_max_b.max(nearest_idx);
                 }
 
                 // Accumulate distance
@@ -250,7 +444,6 @@ fn find_full_track_overlap(
             best_end_a = track_a.len();
             best_min_b = current_min_b;
             best_max_b = current_max_b;
-            best_length = current_length;
         }
     }
 
@@ -340,31 +533,18 @@ fn cluster_overlaps(
     clusters
 }
 
-/// Check if two polylines overlap geometrically
+/// Check if two polylines overlap geometrically: at least half (rounded
+/// down) of 10 points sampled along `poly_a` lie within `threshold` of `poly_b`.
 fn overlaps_match(poly_a: &[GpsPoint], poly_b: &[GpsPoint], threshold: f64) -> bool {
     if poly_a.is_empty() || poly_b.is_empty() {
         return false;
     }
 
-    // Sample points from poly_a and check how many are close to poly_b
     let sample_count = 10.min(poly_a.len());
-    let step = poly_a.len() / sample_count;
-    let mut matches = 0;
-
-    for i in (0..poly_a.len()).step_by(step.max(1)).take(sample_count) {
-        let point = &poly_a[i];
-        // Find min distance to poly_b
-        let min_dist = poly_b.iter()
-            .map(|p| haversine_distance(point, p))
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(f64::MAX);
-
-        if min_dist <= threshold {
-            matches += 1;
-        }
-    }
+    let step = (poly_a.len() / sample_count).max(1);
+    let samples: Vec<GpsPoint> = poly_a.iter().step_by(step).take(sample_count).copied().collect();
 
-    // Need at least 50% of samples to match
+    let matches = (containment(&samples, poly_b, threshold) * samples.len() as f64).round() as usize;
     matches >= sample_count / 2
 }
 
@@ -375,7 +555,14 @@ fn overlaps_match(poly_a: &[GpsPoint], poly_b: &[GpsPoint], threshold: f64) -> b
 /// Select the medoid trace from a cluster.
 /// The medoid is the actual GPS trace with minimum total AMD to all other traces.
 /// This ensures we return REAL GPS points, not artificial interpolations.
-fn select_medoid(cluster: &OverlapCluster) -> (String, Vec<GpsPoint>) {
+/// Traces with a recording gap are only candidates if every trace has one.
+/// With horizontal accuracy, each trace's AMD to the others is averaged with
+/// inverse-variance weights, so the medoid sits closest to the precise tracks.
+fn select_medoid(
+    cluster: &OverlapCluster,
+    accuracies: &TrackAccuracies,
+    config: &SectionConfig,
+) -> (String, Vec<GpsPoint>) {
     // Collect all unique activity portions in this cluster
     let mut traces: Vec<(&str, &[GpsPoint])> = Vec::new();
 
@@ -397,26 +584,37 @@ fn select_medoid(cluster: &OverlapCluster) -> (String, Vec<GpsPoint>) {
         return (traces[0].0.to_string(), traces[0].1.to_vec());
     }
 
+    let gap_free: Vec<bool> = traces.iter().map(|(_, trace)| !has_gap(trace, config)).collect();
+    let any_gap_free = gap_free.iter().any(|&free| free);
+    let is_candidate = |i: usize| gap_free[i] || !any_gap_free;
+
     // For small clusters, compute full pairwise AMD
     // For larger clusters (>10), use approximate method
     let use_full_pairwise = traces.len() <= 10;
 
-    let mut best_idx = 0;
+    let mut best_idx = (0..traces.len()).find(|&i| is_candidate(i)).unwrap_or(0);
     let mut best_total_amd = f64::MAX;
 
     if use_full_pairwise {
         // Compute AMD for each trace to all others
         for (i, (_, trace_i)) in traces.iter().enumerate() {
+            if !is_candidate(i) {
+                continue;
+            }
             let mut total_amd = 0.0;
+            let mut total_weight = 0.0;
 
-            for (j, (_, trace_j)) in traces.iter().enumerate() {
+            for (j, (id_j, trace_j)) in traces.iter().enumerate() {
                 if i != j {
-                    total_amd += average_min_distance(trace_i, trace_j);
+                    let weight = accuracies.track_weight(id_j);
+                    total_amd += weight * polyline_amd(trace_i, trace_j, AMD_SAMPLES);
+                    total_weight += weight;
                 }
             }
 
-            if total_amd < best_total_amd {
-                best_total_amd = total_amd;
+            let avg_amd = total_amd / total_weight;
+            if avg_amd < best_total_amd {
+                best_total_amd = avg_amd;
                 best_idx = i;
             }
         }
@@ -425,20 +623,24 @@ fn select_medoid(cluster: &OverlapCluster) -> (String, Vec<GpsPoint>) {
         let sample_size = 5.min(traces.len() - 1);
 
         for (i, (_, trace_i)) in traces.iter().enumerate() {
+            if !is_candidate(i) {
+                continue;
+            }
             let mut total_amd = 0.0;
-            let mut count = 0;
+            let mut total_weight = 0.0;
 
             // Sample evenly distributed traces
             let step = traces.len() / sample_size;
             for j in (0..traces.len()).step_by(step.max(1)).take(sample_size) {
                 if i != j {
-                    total_amd += average_min_distance(trace_i, traces[j].1);
-                    count += 1;
+                    let weight = accuracies.track_weight(traces[j].0);
+                    total_amd += weight * polyline_amd(trace_i, traces[j].1, AMD_SAMPLES);
+                    total_weight += weight;
                 }
             }
 
-            if count > 0 {
-                let avg_amd = total_amd / count as f64;
+            if total_weight > 0.0 {
+                let avg_amd = total_amd / total_weight;
                 if avg_amd < best_total_amd {
                     best_total_amd = avg_amd;
                     best_idx = i;
@@ -450,94 +652,14 @@ fn select_medoid(cluster: &OverlapCluster) -> (String, Vec<GpsPoint>) {
     (traces[best_idx].0.to_string(), traces[best_idx].1.to_vec())
 }
 
-/// Average Minimum Distance between two polylines
-fn average_min_distance(poly_a: &[GpsPoint], poly_b: &[GpsPoint]) -> f64 {
-    if poly_a.is_empty() || poly_b.is_empty() {
-        return f64::MAX;
-    }
-
-    // Resample both to same number of points for fair comparison
-    let n = 50;
-    let resampled_a = resample_by_distance(poly_a, n);
-    let resampled_b = resample_by_distance(poly_b, n);
-
-    // Compute AMD from A to B
-    let mut sum_a_to_b = 0.0;
-    for point_a in &resampled_a {
-        let min_dist = resampled_b.iter()
-            .map(|p| haversine_distance(point_a, p))
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(0.0);
-        sum_a_to_b += min_dist;
-    }
-
-    // Compute AMD from B to A
-    let mut sum_b_to_a = 0.0;
-    for point_b in &resampled_b {
-        let min_dist = resampled_a.iter()
-            .map(|p| haversine_distance(point_b, p))
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(0.0);
-        sum_b_to_a += min_dist;
-    }
-
-    // Average of both directions
-    (sum_a_to_b + sum_b_to_a) / (2.0 * n as f64)
-}
-
-/// Resample polyline to N points by distance
-fn resample_by_distance(points: &[GpsPoint], n: usize) -> Vec<GpsPoint> {
-    if points.len() <= n {
-        return points.to_vec();
-    }
-
-    // Compute cumulative distances
-    let mut cumulative = vec![0.0];
-    for i in 1..points.len() {
-        let d = haversine_distance(&points[i - 1], &points[i]);
-        cumulative.push(cumulative.last().unwrap() + d);
-    }
-
-    let total_length = *cumulative.last().unwrap();
-    if total_length < 1.0 {
-        return points.to_vec();
-    }
-
-    let mut resampled = Vec::with_capacity(n);
-    for i in 0..n {
-        let target_dist = (i as f64 / (n - 1) as f64) * total_length;
-
-        // Find segment containing target distance
-        let mut seg_idx = 0;
-        for j in 1..cumulative.len() {
-            if cumulative[j] >= target_dist {
-                seg_idx = j - 1;
-                break;
-            }
-            seg_idx = j - 1;
-        }
-
-        // Interpolate within segment
-        let seg_start = cumulative[seg_idx];
-        let seg_end = cumulative.get(seg_idx + 1).copied().unwrap_or(seg_start);
-        let seg_len = seg_end - seg_start;
-
-        let t = if seg_len > 0.001 {
-            (target_dist - seg_start) / seg_len
-        } else {
-            0.0
-        };
-
-        let p1 = &points[seg_idx];
-        let p2 = points.get(seg_idx + 1).unwrap_or(p1);
-
-        resampled.push(GpsPoint::new(
-            p1.latitude + t * (p2.latitude - p1.latitude),
-            p1.longitude + t * (p2.longitude - p1.longitude),
-        ));
-    }
-
-    resampled
+/// Whether consecutive points of `trace` jump further than
+/// [`SectionConfig::max_gap_meters`].
+fn has_gap(trace: &[GpsPoint], config: &SectionConfig) -> bool {
+    let Some(max_gap) = config.max_gap_meters else {
+        return false;
+    };
+    let model = config.distance_model();
+    trace.windows(2).any(|w| model.distance(&w[0], &w[1]) > max_gap)
 }
 
 // =============================================================================
@@ -545,15 +667,15 @@ fn resample_by_distance(points: &[GpsPoint], n: usize) -> Vec<GpsPoint> {
 // =============================================================================
 
 /// Compute each activity's portion of a section
-fn compute_activity_portions(
-    cluster: &OverlapCluster,
+fn compute_activity_portions<'a>(
+    activity_ids: impl IntoIterator<Item = &'a String>,
     representative_polyline: &[GpsPoint],
     all_tracks: &HashMap<String, Vec<GpsPoint>>,
     config: &SectionConfig,
 ) -> Vec<SectionPortion> {
     let mut portions = Vec::new();
 
-    for activity_id in &cluster.activity_ids {
+    for activity_id in activity_ids {
         if let Some(track) = all_tracks.get(activity_id) {
             // Find the portion of this track that overlaps with the representative
             if let Some((start_idx, end_idx, direction)) = find_track_portion(
@@ -561,7 +683,7 @@ fn compute_activity_portions(
                 representative_polyline,
                 config.proximity_threshold,
             ) {
-                let distance = polyline_length(&track[start_idx..end_idx]);
+                let distance = config.distance_model().polyline_length(&track[start_idx..end_idx]);
 
                 portions.push(SectionPortion {
                     activity_id: activity_id.clone(),
@@ -588,20 +710,15 @@ fn find_track_portion(
     }
 
     let ref_tree = build_rtree(reference);
-    let threshold_deg = threshold / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    let threshold_sq = threshold * threshold;
 
     let mut start_idx: Option<usize> = None;
     let mut end_idx = 0;
     let mut in_overlap = false;
 
     for (i, point) in track.iter().enumerate() {
-        let query = [point.latitude, point.longitude];
-
-        if let Some(nearest) = ref_tree.nearest_neighbor(&query) {
-            let dist_sq = nearest.distance_2(&query);
-
-            if dist_sq <= threshold_deg_sq {
+        if let Some((_, dist_sq)) = ref_tree.nearest(point) {
+            if dist_sq <= threshold_sq {
                 if !in_overlap {
                     start_idx = Some(i);
                     in_overlap = true;
@@ -629,7 +746,7 @@ fn find_track_portion(
 fn detect_direction_robust(
     track_portion: &[GpsPoint],
     reference: &[GpsPoint],
-    ref_tree: &RTree<IndexedPoint>,
+    ref_tree: &PointIndex,
 ) -> String {
     if track_portion.len() < 3 || reference.len() < 3 {
         return "same".to_string();
@@ -644,10 +761,9 @@ fn detect_direction_robust(
     for i in 0..sample_count {
         let track_idx = (i * step).min(track_portion.len() - 1);
         let point = &track_portion[track_idx];
-        let query = [point.latitude, point.longitude];
 
-        if let Some(nearest) = ref_tree.nearest_neighbor(&query) {
-            ref_indices.push(nearest.idx);
+        if let Some((nearest_idx, _)) = ref_tree.nearest(point) {
+            ref_indices.push(nearest_idx);
         }
     }
 
@@ -690,16 +806,17 @@ fn process_cluster(
     sport_type: &str,
     track_map: &HashMap<String, Vec<GpsPoint>>,
     activity_to_route: &HashMap<&str, &str>,
+    accuracies: &TrackAccuracies,
     config: &SectionConfig,
 ) -> Option<FrequentSection> {
     // Select medoid - an ACTUAL GPS trace
-    let (representative_id, representative_polyline) = select_medoid(&cluster);
+    let (representative_id, representative_polyline) = select_medoid(&cluster, accuracies, config);
 
     if representative_polyline.is_empty() {
         return None;
     }
 
-    let distance_meters = polyline_length(&representative_polyline);
+    let distance_meters = config.distance_model().polyline_length(&representative_polyline);
 
     // Filter by max length - sections shouldn't be whole routes
     if distance_meters > config.max_section_length {
@@ -707,8 +824,8 @@ fn process_cluster(
     }
 
     // Compute activity portions for pace comparison
-    let activity_portions = compute_activity_portions(
-        &cluster,
+    let mut activity_portions = compute_activity_portions(
+        &cluster.activity_ids,
         &representative_polyline,
         track_map,
         config,
@@ -724,24 +841,65 @@ fn process_cluster(
 
     // Pre-compute activity traces
     let activity_id_vec: Vec<String> = cluster.activity_ids.iter().cloned().collect();
-    let activity_traces = extract_all_activity_traces(
+    let trace_indices = extract_all_activity_trace_indices(
         &activity_id_vec,
         &representative_polyline,
         track_map,
     );
 
-    // Collect all traces for consensus computation
-    let all_traces: Vec<Vec<GpsPoint>> = activity_traces.values().cloned().collect();
+    // Collect all traces (and their accuracies, if known) for consensus computation
+    let mut activity_traces: HashMap<String, Vec<GpsPoint>> = HashMap::with_capacity(trace_indices.len());
+    let mut all_traces: Vec<Vec<GpsPoint>> = Vec::with_capacity(trace_indices.len());
+    let mut trace_accuracies: Vec<Vec<f64>> = Vec::new();
+    for (activity_id, indices) in trace_indices {
+        let track = &track_map[&activity_id];
+        let trace: Vec<GpsPoint> = indices.iter().map(|&i| track[i]).collect();
+        if !accuracies.is_empty() {
+            trace_accuracies.push(accuracies.for_trace(&activity_id, &indices));
+        }
+        all_traces.push(trace.clone());
+        activity_traces.insert(activity_id, trace);
+    }
 
     // Compute consensus polyline from all overlapping tracks
-    let consensus = compute_consensus_polyline(
+    let mut consensus = compute_consensus_polyline(
         &representative_polyline,
         &all_traces,
+        &trace_accuracies,
         config.proximity_threshold,
     );
 
+    // Move the ends to where traversals consistently start and stop
+    if config.adapt_boundaries {
+        if let Some((polyline, point_density)) = adapt_section_boundaries(
+            &consensus.polyline,
+            &consensus.point_density,
+            &activity_portions,
+            track_map,
+            config,
+        ) {
+            consensus.polyline = polyline;
+            consensus.point_density = point_density;
+            activity_portions = compute_activity_portions(&cluster.activity_ids, &consensus.polyline, track_map, config);
+            activity_traces = extract_all_activity_traces(&activity_id_vec, &consensus.polyline, track_map);
+        }
+    }
+
     // Use consensus polyline and update distance
-    let consensus_distance = polyline_length(&consensus.polyline);
+    let consensus_distance = config.distance_model().polyline_length(&consensus.polyline);
+
+    let (same_direction, reverse_direction) = if config.split_directions {
+        let stats = |direction: &str| compute_direction_stats(
+            direction,
+            &activity_portions,
+            &activity_traces,
+            &consensus.polyline,
+            config.proximity_threshold,
+        );
+        (stats("same"), stats("reverse"))
+    } else {
+        (None, None)
+    };
 
     Some(FrequentSection {
         id: format!("sec_{}_{}", sport_type.to_lowercase(), idx),
@@ -758,9 +916,282 @@ fn process_cluster(
         observation_count: consensus.observation_count,
         average_spread: consensus.average_spread,
         point_density: consensus.point_density,
+        same_direction,
+        reverse_direction,
+        suggested_name: None,
+        matching_threshold: Some(adaptive_threshold(consensus.confidence, consensus.average_spread, config)),
+    })
+}
+
+/// Per-direction traversal statistics, from portions and pre-computed traces.
+///
+/// Returns `None` when no activity traverses the section in `direction`.
+fn compute_direction_stats(
+    direction: &str,
+    portions: &[SectionPortion],
+    activity_traces: &HashMap<String, Vec<GpsPoint>>,
+    polyline: &[GpsPoint],
+    proximity_threshold: f64,
+) -> Option<DirectionStats> {
+    let matching: Vec<&SectionPortion> = portions.iter().filter(|p| p.direction == direction).collect();
+    if matching.is_empty() {
+        return None;
+    }
+
+    let mut activity_ids: Vec<String> = Vec::new();
+    for portion in &matching {
+        if !activity_ids.contains(&portion.activity_id) {
+            activity_ids.push(portion.activity_id.clone());
+        }
+    }
+
+    let traces: Vec<Vec<GpsPoint>> = activity_ids
+        .iter()
+        .filter_map(|id| activity_traces.get(id).cloned())
+        .filter(|trace| !trace.is_empty())
+        .collect();
+    let consensus = compute_consensus_polyline(polyline, &traces, &[], proximity_threshold);
+
+    Some(DirectionStats {
+        traversal_count: matching.len() as u32,
+        confidence: consensus.confidence,
+        average_spread: consensus.average_spread,
+        average_distance_meters: matching.iter().map(|p| p.distance_meters).sum::<f64>() / matching.len() as f64,
+        activity_ids,
     })
 }
 
+// =============================================================================
+// Adaptive Boundaries
+// =============================================================================
+
+/// Share of traversals that must agree before a section end moves
+const BOUNDARY_QUORUM: f64 = 0.75;
+
+/// Smallest boundary move (meters); smaller disagreements leave the end alone
+const MIN_BOUNDARY_SHIFT: f64 = 50.0;
+
+/// Spacing of points added when a section grows (meters)
+const BOUNDARY_STEP: f64 = 25.0;
+
+/// Furthest a section end grows in one pass (meters)
+const MAX_BOUNDARY_EXTENSION: f64 = 1000.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum BoundaryEnd {
+    Start,
+    End,
+}
+
+/// Grow or contract the ends of a section polyline to where its traversals
+/// consistently continue or stop.
+///
+/// An end contracts to the point that [`BOUNDARY_QUORUM`] of traversals cover,
+/// if that is at least [`MIN_BOUNDARY_SHIFT`] in. Otherwise it grows, one
+/// [`BOUNDARY_STEP`] at a time, while a quorum of traversals continue together
+/// past it. Returns the new polyline and point density, or `None` if neither
+/// end moved.
+fn adapt_section_boundaries(
+    polyline: &[GpsPoint],
+    point_density: &[u32],
+    portions: &[SectionPortion],
+    track_map: &HashMap<String, Vec<GpsPoint>>,
+    config: &SectionConfig,
+) -> Option<(Vec<GpsPoint>, Vec<u32>)> {
+    if polyline.len() < 2 || portions.is_empty() {
+        return None;
+    }
+    let threshold = config.proximity_threshold;
+    let length = crate::geo_utils::polyline_length(polyline);
+
+    // How far each traversal's coverage stops short of each end
+    let mut traversals: Vec<(&[GpsPoint], &SectionPortion, f64, f64)> = Vec::new();
+    for portion in portions {
+        let Some(track) = track_map.get(&portion.activity_id) else { continue };
+        let covered: Vec<f64> = track[portion.start_index as usize..portion.end_index as usize]
+            .iter()
+            .filter_map(|p| crate::geo_utils::project_point_onto_route(p, polyline))
+            .filter(|proj| proj.offset <= threshold)
+            .map(|proj| proj.distance_along)
+            .collect();
+        let Some(first) = covered.iter().copied().reduce(f64::min) else { continue };
+        let last = covered.iter().copied().fold(first, f64::max);
+        traversals.push((track, portion, first, length - last));
+    }
+    if traversals.is_empty() {
+        return None;
+    }
+    let quorum = ((traversals.len() as f64 * BOUNDARY_QUORUM).ceil() as usize).max(1);
+
+    // Contraction: the shortfall that a quorum of traversals still cover
+    let contraction = |end: BoundaryEnd| -> f64 {
+        let mut shortfalls: Vec<f64> = traversals
+            .iter()
+            .map(|&(_, _, start, end_short)| if end == BoundaryEnd::Start { start } else { end_short })
+            .collect();
+        shortfalls.sort_by(f64::total_cmp);
+        let cut = shortfalls[quorum - 1];
+        if cut >= MIN_BOUNDARY_SHIFT { cut } else { 0.0 }
+    };
+    let mut cut_start = contraction(BoundaryEnd::Start);
+    let mut cut_end = contraction(BoundaryEnd::End);
+    if length - cut_start - cut_end < config.min_section_length {
+        cut_start = 0.0;
+        cut_end = 0.0;
+    }
+
+    // Growth: only for ends that stay, from traversals that reach them
+    let mut budget = (config.max_section_length - length).min(MAX_BOUNDARY_EXTENSION);
+    let grow = |end: BoundaryEnd, budget: &mut f64| -> Vec<(GpsPoint, u32)> {
+        let continuations: Vec<Vec<GpsPoint>> = traversals
+            .iter()
+            .filter(|&&(_, _, start, end_short)| (if end == BoundaryEnd::Start { start } else { end_short }) <= threshold)
+            .map(|&(track, portion, _, _)| boundary_continuation(track, portion, end, *budget))
+            .collect();
+        let extension = grow_boundary(&continuations, quorum, threshold, *budget);
+        let grown = extension.len() as f64 * BOUNDARY_STEP;
+        if grown < MIN_BOUNDARY_SHIFT {
+            return Vec::new();
+        }
+        *budget -= grown;
+        extension
+    };
+    let grow_start = if cut_start == 0.0 { grow(BoundaryEnd::Start, &mut budget) } else { Vec::new() };
+    let grow_end = if cut_end == 0.0 { grow(BoundaryEnd::End, &mut budget) } else { Vec::new() };
+
+    if cut_start == 0.0 && cut_end == 0.0 && grow_start.is_empty() && grow_end.is_empty() {
+        return None;
+    }
+
+    // Keep the vertices between the cuts
+    let mut along = 0.0;
+    let mut kept: Vec<(GpsPoint, u32)> = Vec::with_capacity(polyline.len());
+    for (i, point) in polyline.iter().enumerate() {
+        if i > 0 {
+            along += haversine_distance(&polyline[i - 1], point);
+        }
+        if along >= cut_start && along <= length - cut_end {
+            kept.push((*point, point_density.get(i).copied().unwrap_or(0)));
+        }
+    }
+
+    let adapted: Vec<(GpsPoint, u32)> = grow_start
+        .into_iter()
+        .rev()
+        .chain(kept)
+        .chain(grow_end)
+        .collect();
+    if adapted.len() < 2 {
+        return None;
+    }
+    Some(adapted.into_iter().unzip())
+}
+
+/// The part of `track` leading away from a section end, from the edge of the
+/// traversal's portion outward, up to `max_length` meters.
+fn boundary_continuation(
+    track: &[GpsPoint],
+    portion: &SectionPortion,
+    end: BoundaryEnd,
+    max_length: f64,
+) -> Vec<GpsPoint> {
+    let start = portion.start_index as usize;
+    let last = (portion.end_index as usize).saturating_sub(1);
+    // A reverse traversal leaves the section's start from its own last point
+    let backward = (end == BoundaryEnd::Start) == (portion.direction == "same");
+    let outward: Box<dyn Iterator<Item = &GpsPoint>> = if backward {
+        Box::new(track[..=start].iter().rev())
+    } else {
+        Box::new(track[last..].iter())
+    };
+
+    let mut continuation: Vec<GpsPoint> = Vec::new();
+    let mut length = 0.0;
+    for point in outward {
+        if let Some(prev) = continuation.last() {
+            length += haversine_distance(prev, point);
+        }
+        continuation.push(*point);
+        if length > max_length {
+            break;
+        }
+    }
+    continuation
+}
+
+/// Points (with supporting traversal counts) extending a section end, one
+/// [`BOUNDARY_STEP`] at a time while at least `quorum` continuations stay
+/// within `threshold` of each other.
+fn grow_boundary(continuations: &[Vec<GpsPoint>], quorum: usize, threshold: f64, max_length: f64) -> Vec<(GpsPoint, u32)> {
+    let cumulative: Vec<Vec<f64>> = continuations
+        .iter()
+        .map(|c| {
+            let mut total = 0.0;
+            std::iter::once(0.0)
+                .chain(c.windows(2).map(|w| {
+                    total += haversine_distance(&w[0], &w[1]);
+                    total
+                }))
+                .collect()
+        })
+        .collect();
+
+    let mut together: Vec<usize> = (0..continuations.len()).collect();
+    let mut extension = Vec::new();
+    let mut distance = BOUNDARY_STEP;
+    while distance <= max_length && together.len() >= quorum {
+        let positions: Vec<(usize, GpsPoint)> = together
+            .iter()
+            .filter_map(|&i| point_at_distance(&continuations[i], &cumulative[i], distance).map(|p| (i, p)))
+            .collect();
+
+        // The position most others agree with, and the traversals near it
+        let near = |p: &GpsPoint| -> Vec<(usize, GpsPoint)> {
+            positions.iter().filter(|(_, q)| haversine_distance(p, q) <= threshold).copied().collect()
+        };
+        let Some(supporters) = positions.iter().map(|(_, p)| near(p)).max_by_key(|s| s.len()) else { break };
+        if supporters.len() < quorum {
+            break;
+        }
+
+        let points: Vec<GpsPoint> = supporters.iter().map(|&(_, p)| p).collect();
+        extension.push((compute_center(&points), supporters.len() as u32));
+        together = supporters.into_iter().map(|(i, _)| i).collect();
+        distance += BOUNDARY_STEP;
+    }
+    extension
+}
+
+/// Point `distance` meters along a polyline with precomputed cumulative
+/// distances, or `None` past its end.
+fn point_at_distance(points: &[GpsPoint], cumulative: &[f64], distance: f64) -> Option<GpsPoint> {
+    let seg = cumulative.iter().position(|&d| d >= distance)?;
+    if seg == 0 {
+        return points.first().copied();
+    }
+    let seg_len = cumulative[seg] - cumulative[seg - 1];
+    let t = if seg_len > 0.0 { (distance - cumulative[seg - 1]) / seg_len } else { 0.0 };
+    Some(crate::geo_utils::interpolate_point(&points[seg - 1], &points[seg], t))
+}
+
+/// Tightest matching threshold, in multiples of a section's average spread
+const ADAPTIVE_SPREAD_FACTOR: f64 = 3.0;
+
+/// Floor on adaptive matching thresholds (meters), covering consumer GPS error
+const MIN_ADAPTIVE_THRESHOLD: f64 = 20.0;
+
+/// Matching threshold for a section with the given consensus confidence and
+/// spread: `config.proximity_threshold` at zero confidence, shrinking linearly
+/// toward [`ADAPTIVE_SPREAD_FACTOR`] × spread (at least
+/// [`MIN_ADAPTIVE_THRESHOLD`]) at full confidence. Never looser than the config.
+fn adaptive_threshold(confidence: f64, average_spread: f64, config: &SectionConfig) -> f64 {
+    let loosest = config.proximity_threshold;
+    let tightest = (ADAPTIVE_SPREAD_FACTOR * average_spread)
+        .max(MIN_ADAPTIVE_THRESHOLD)
+        .min(loosest);
+    loosest - confidence.clamp(0.0, 1.0) * (loosest - tightest)
+}
+
 // =============================================================================
 // Main Entry Point
 // =============================================================================
@@ -773,13 +1204,161 @@ pub fn detect_sections_from_tracks(
     groups: &[RouteGroup],
     config: &SectionConfig,
 ) -> Vec<FrequentSection> {
-    info!(
-        "[Sections] Detecting from {} full GPS tracks",
-        tracks.len()
-    );
+    detect_sections_from_tracks_with_progress(tracks, sport_types, groups, config, None)
+}
+
+/// Detect frequent sections from FULL GPS tracks, reporting progress.
+///
+/// `on_progress` receives the current phase and its percentage; it may be
+/// called from worker threads when the `parallel` feature is enabled.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use std::sync::{Arc, Mutex};
+/// use route_matcher::{GpsPoint, SectionConfig, SectionPhase, SectionProgress};
+/// use route_matcher::sections::detect_sections_from_tracks_with_progress;
+///
+/// let track: Vec<GpsPoint> = (0..100).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect();
+/// let tracks: Vec<(String, Vec<GpsPoint>)> = (0..3).map(|i| (format!("a{}", i), track.clone())).collect();
+/// let sport_types: HashMap<String, String> = tracks.iter().map(|(id, _)| (id.clone(), "Run".to_string())).collect();
+///
+/// let phases = Arc::new(Mutex::new(Vec::new()));
+/// let seen = phases.clone();
+/// let on_progress: SectionProgress = Arc::new(move |phase, _percent| seen.lock().unwrap().push(phase));
+///
+/// detect_sections_from_tracks_with_progress(&tracks, &sport_types, &[], &SectionConfig::default(), Some(on_progress));
+/// assert!(phases.lock().unwrap().contains(&SectionPhase::Overlaps));
+/// ```
+pub fn detect_sections_from_tracks_with_progress(
+    tracks: &[(String, Vec<GpsPoint>)],  // (activity_id, full_gps_points)
+    sport_types: &HashMap<String, String>,
+    groups: &[RouteGroup],
+    config: &SectionConfig,
+    on_progress: Option<SectionProgress>,
+) -> Vec<FrequentSection> {
+    detect_sections_from_tracks_cancellable(
+        tracks,
+        sport_types,
+        groups,
+        config,
+        on_progress,
+        &CancellationToken::new(),
+    )
+    .unwrap_or_default()
+}
+
+/// Detect frequent sections from FULL GPS tracks, reporting progress and
+/// stopping early if `cancel` is cancelled.
+///
+/// Returns `None` if cancelled before detection finished.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, SectionConfig, CancellationToken};
+/// use route_matcher::sections::detect_sections_from_tracks_cancellable;
+///
+/// let track: Vec<GpsPoint> = (0..100).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect();
+/// let tracks: Vec<(String, Vec<GpsPoint>)> = (0..3).map(|i| (format!("a{}", i), track.clone())).collect();
+/// let sport_types: HashMap<String, String> = tracks.iter().map(|(id, _)| (id.clone(), "Run".to_string())).collect();
+///
+/// let token = CancellationToken::new();
+/// token.cancel();
+/// let sections = detect_sections_from_tracks_cancellable(&tracks, &sport_types, &[], &SectionConfig::default(), None, &token);
+/// assert!(sections.is_none());
+/// ```
+pub fn detect_sections_from_tracks_cancellable(
+    tracks: &[(String, Vec<GpsPoint>)],  // (activity_id, full_gps_points)
+    sport_types: &HashMap<String, String>,
+    groups: &[RouteGroup],
+    config: &SectionConfig,
+    on_progress: Option<SectionProgress>,
+    cancel: &CancellationToken,
+) -> Option<Vec<FrequentSection>> {
+    detect_sections_traced(tracks, sport_types, groups, config, &HashMap::new(), on_progress, cancel)
+}
+
+/// Detect frequent sections from FULL GPS tracks with per-point horizontal
+/// accuracy (meters), keyed by activity ID and aligned with each track's
+/// points.
+///
+/// Consensus averaging weights each observation by 1 / (accuracy² + distance²)
+/// instead of inverse distance alone, and the medoid is chosen by
+/// accuracy-weighted AMD, so one imprecise track can't drag a well-observed
+/// section sideways. Activities or points without accuracy are assumed to
+/// have typical phone accuracy (10 m).
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use route_matcher::{GpsPoint, SectionConfig};
+/// use route_matcher::sections::detect_sections_from_tracks_with_accuracy;
+///
+/// let track: Vec<GpsPoint> = (0..100).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0001, -0.1)).collect();
+/// let tracks: Vec<(String, Vec<GpsPoint>)> = (0..3).map(|i| (format!("a{}", i), track.clone())).collect();
+/// let sport_types: HashMap<String, String> = tracks.iter().map(|(id, _)| (id.clone(), "Run".to_string())).collect();
+/// let accuracies: HashMap<String, Vec<f64>> = tracks.iter().map(|(id, pts)| (id.clone(), vec![5.0; pts.len()])).collect();
+///
+/// let sections = detect_sections_from_tracks_with_accuracy(&tracks, &sport_types, &[], &SectionConfig::default(), &accuracies);
+/// assert_eq!(sections.len(), 1);
+/// ```
+pub fn detect_sections_from_tracks_with_accuracy(
+    tracks: &[(String, Vec<GpsPoint>)],  // (activity_id, full_gps_points)
+    sport_types: &HashMap<String, String>,
+    groups: &[RouteGroup],
+    config: &SectionConfig,
+    accuracies: &HashMap<String, Vec<f64>>,
+) -> Vec<FrequentSection> {
+    detect_sections_traced(tracks, sport_types, groups, config, accuracies, None, &CancellationToken::new())
+        .unwrap_or_default()
+}
+
+fn detect_sections_traced(
+    tracks: &[(String, Vec<GpsPoint>)],
+    sport_types: &HashMap<String, String>,
+    groups: &[RouteGroup],
+    config: &SectionConfig,
+    accuracies: &HashMap<String, Vec<f64>>,
+    on_progress: Option<SectionProgress>,
+    cancel: &CancellationToken,
+) -> Option<Vec<FrequentSection>> {
+    // Leave virtual courses and treadmill smudges out of detection entirely
+    let outdoor: Vec<(String, Vec<GpsPoint>)>;
+    let tracks = if config.exclude_indoor {
+        let indoor = indoor_activity_ids(tracks, sport_types, &IndoorConfig::default());
+        outdoor = tracks.iter().filter(|(id, _)| !indoor.contains(id)).cloned().collect();
+        &outdoor
+    } else {
+        tracks
+    };
+
+    let mut span = pipeline_span!("detect_sections", tracks = tracks.len(); sections);
+    let sections = span.in_scope(|| {
+        detect_sections_in_span(tracks, sport_types, groups, config, accuracies, on_progress.as_ref(), cancel)
+    });
+    if let Some(sections) = &sections {
+        span.record("sections", sections.len());
+        span.finish();
+    }
+    sections
+}
+
+fn detect_sections_in_span(
+    tracks: &[(String, Vec<GpsPoint>)],
+    sport_types: &HashMap<String, String>,
+    groups: &[RouteGroup],
+    config: &SectionConfig,
+    accuracies: &HashMap<String, Vec<f64>>,
+    on_progress: Option<&SectionProgress>,
+    cancel: &CancellationToken,
+) -> Option<Vec<FrequentSection>> {
+    if cancel.is_cancelled() {
+        return None;
+    }
 
     if tracks.len() < config.min_activities as usize {
-        return vec![];
+        return Some(vec![]);
     }
 
     // Filter to only groups with 2+ activities (these are the ones shown in Routes list)
@@ -807,6 +1386,7 @@ pub fn detect_sections_from_tracks(
         .iter()
         .map(|(id, pts)| (id.clone(), pts.clone()))
         .collect();
+    let accuracies = TrackAccuracies::new(accuracies);
 
     // Group tracks by sport type
     let mut tracks_by_sport: HashMap<String, Vec<(&str, &[GpsPoint])>> = HashMap::new();
@@ -822,7 +1402,7 @@ pub fn detect_sections_from_tracks(
     }
 
     let mut all_sections: Vec<FrequentSection> = Vec::new();
-    let mut section_counter = 0;
+    let mut used_ids: HashSet<String> = HashSet::new();
 
     // Process each sport type
     for (sport_type, sport_tracks) in &tracks_by_sport {
@@ -838,7 +1418,7 @@ pub fn detect_sections_from_tracks(
 
         // Build R-trees for all tracks
         let rtree_start = std::time::Instant::now();
-        let rtrees: Vec<RTree<IndexedPoint>> = sport_tracks
+        let rtrees: Vec<PointIndex> = sport_tracks
             .iter()
             .map(|(_, pts)| build_rtree(pts))
             .collect();
@@ -853,12 +1433,17 @@ pub fn detect_sections_from_tracks(
             .collect();
 
         let total_pairs = pairs.len();
+        let overlap_progress = PhaseProgress::start(on_progress, SectionPhase::Overlaps, total_pairs);
 
         // Process pairs (parallel if feature enabled)
         #[cfg(feature = "parallel")]
         let overlaps: Vec<FullTrackOverlap> = pairs
             .into_par_iter()
             .filter_map(|(i, j)| {
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                overlap_progress.tick();
                 let (id_a, track_a) = sport_tracks[i];
                 let (id_b, track_b) = sport_tracks[j];
 
@@ -881,6 +1466,10 @@ pub fn detect_sections_from_tracks(
         let overlaps: Vec<FullTrackOverlap> = pairs
             .into_iter()
             .filter_map(|(i, j)| {
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                overlap_progress.tick();
                 let (id_a, track_a) = sport_tracks[i];
                 let (id_b, track_b) = sport_tracks[j];
 
@@ -898,6 +1487,10 @@ pub fn detect_sections_from_tracks(
                 )
             })
             .collect();
+        if cancel.is_cancelled() {
+            return None;
+        }
+        overlap_progress.finish();
 
         info!(
             "[Sections] Found {} pairwise overlaps for {} ({} pairs) in {}ms",
@@ -909,7 +1502,9 @@ pub fn detect_sections_from_tracks(
 
         // Cluster overlaps
         let cluster_start = std::time::Instant::now();
+        let cluster_progress = PhaseProgress::start(on_progress, SectionPhase::Clustering, 1);
         let clusters = cluster_overlaps(overlaps, config);
+        cluster_progress.finish();
 
         // Filter to clusters with enough activities
         let significant_clusters: Vec<_> = clusters
@@ -933,13 +1528,19 @@ pub fn detect_sections_from_tracks(
             .into_iter()
             .enumerate()
             .collect();
+        let consensus_progress = PhaseProgress::start(on_progress, SectionPhase::Consensus, cluster_data.len());
 
         // Process clusters (parallel if feature enabled)
         #[cfg(feature = "parallel")]
         let sport_sections: Vec<FrequentSection> = cluster_data
             .into_par_iter()
             .filter_map(|(idx, cluster)| {
-                process_cluster(idx, cluster, &sport_type, &track_map, &activity_to_route, config)
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                let section = process_cluster(idx, cluster, sport_type, &track_map, &activity_to_route, &accuracies, config);
+                consensus_progress.tick();
+                section
             })
             .collect();
 
@@ -947,10 +1548,20 @@ pub fn detect_sections_from_tracks(
         let sport_sections: Vec<FrequentSection> = cluster_data
             .into_iter()
             .filter_map(|(idx, cluster)| {
-                process_cluster(idx, cluster, &sport_type, &track_map, &activity_to_route, config)
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                let section = process_cluster(idx, cluster, sport_type, &track_map, &activity_to_route, &accuracies, config);
+                consensus_progress.tick();
+                section
             })
             .collect();
 
+        if cancel.is_cancelled() {
+            return None;
+        }
+        consensus_progress.finish();
+
         info!(
             "[Sections] Converted {} sections for {} in {}ms",
             sport_sections.len(),
@@ -959,8 +1570,13 @@ pub fn detect_sections_from_tracks(
         );
 
         // Post-process step 1: Split sections that fold back on themselves (out-and-back)
+        let post_progress = PhaseProgress::start(on_progress, SectionPhase::PostProcessing, 4);
         let fold_start = std::time::Instant::now();
-        let split_sections = split_folding_sections(sport_sections, config);
+        let split_sections = split_folding_sections(sport_sections, &track_map, &activity_to_route, config);
+        if cancel.is_cancelled() {
+            return None;
+        }
+        post_progress.tick();
         info!(
             "[Sections] After fold splitting: {} sections in {}ms",
             split_sections.len(),
@@ -970,6 +1586,10 @@ pub fn detect_sections_from_tracks(
         // Post-process step 2: Merge sections that are nearby (reversed, parallel, GPS drift)
         let merge_start = std::time::Instant::now();
         let merged_sections = merge_nearby_sections(split_sections, config);
+        if cancel.is_cancelled() {
+            return None;
+        }
+        post_progress.tick();
         info!(
             "[Sections] After nearby merge: {} sections in {}ms",
             merged_sections.len(),
@@ -979,6 +1599,10 @@ pub fn detect_sections_from_tracks(
         // Post-process step 3: Remove sections that contain or are contained by others
         let dedup_start = std::time::Instant::now();
         let deduped_sections = remove_overlapping_sections(merged_sections, config);
+        if cancel.is_cancelled() {
+            return None;
+        }
+        post_progress.tick();
         info!(
             "[Sections] After dedup: {} unique sections in {}ms",
             deduped_sections.len(),
@@ -989,29 +1613,195 @@ pub fn detect_sections_from_tracks(
         // This creates new sections from portions that are used by many activities
         let split_start = std::time::Instant::now();
         let final_sections = split_high_variance_sections(deduped_sections, &track_map, config);
+        post_progress.finish();
         info!(
             "[Sections] After density splitting: {} sections in {}ms",
             final_sections.len(),
             split_start.elapsed().as_millis()
         );
 
-        // Re-number sections
-        for (i, mut section) in final_sections.into_iter().enumerate() {
-            section.id = format!("sec_{}_{}", sport_type.to_lowercase(), section_counter + i);
+        // Assign stable IDs (suffixed on the rare hash collision)
+        for mut section in final_sections {
+            let base_id = stable_section_id(sport_type, &section.polyline);
+            let mut id = base_id.clone();
+            let mut n = 2;
+            while !used_ids.insert(id.clone()) {
+                id = format!("{}_{}", base_id, n);
+                n += 1;
+            }
+            section.id = id;
             all_sections.push(section);
         }
-        section_counter += all_sections.len();
     }
 
     // Sort by visit count (most visited first)
-    all_sections.sort_by(|a, b| b.visit_count.cmp(&a.visit_count));
+    all_sections.sort_by_key(|s| std::cmp::Reverse(s.visit_count));
 
     info!(
         "[Sections] Detected {} total sections",
         all_sections.len()
     );
 
-    all_sections
+    Some(all_sections)
+}
+
+// =============================================================================
+// Stable IDs and Persistence
+// =============================================================================
+
+/// Grid used to quantize key points for stable IDs (~110m of latitude)
+const STABLE_ID_GRID_DEG: f64 = 0.001;
+
+/// Version of the persisted sections format (bump when the layout changes)
+#[cfg(feature = "persist")]
+const SECTIONS_FORMAT_VERSION: u32 = 1;
+
+/// Stable section ID from the sport type and geometry.
+///
+/// Start, middle and end points are snapped to a ~110m grid and hashed with
+/// FNV-1a; endpoints are ordered so a reversed polyline gets the same ID.
+///
+/// # Example
+/// ```
+/// use route_matcher::GpsPoint;
+/// use route_matcher::sections::stable_section_id;
+///
+/// let polyline = vec![GpsPoint::new(51.5000, -0.1000), GpsPoint::new(51.5100, -0.1000)];
+/// let reversed: Vec<GpsPoint> = polyline.iter().rev().cloned().collect();
+///
+/// let id = stable_section_id("Run", &polyline);
+/// assert!(id.starts_with("sec_run_"));
+/// assert_eq!(id, stable_section_id("Run", &reversed));
+/// assert_ne!(id, stable_section_id("Ride", &polyline));
+/// ```
+pub fn stable_section_id(sport_type: &str, polyline: &[GpsPoint]) -> String {
+    let quantize = |p: &GpsPoint| -> (i64, i64) {
+        (
+            (p.latitude / STABLE_ID_GRID_DEG).round() as i64,
+            (p.longitude / STABLE_ID_GRID_DEG).round() as i64,
+        )
+    };
+
+    let key_points = resample_evenly(polyline, 3);
+    let (start, mid, end) = match key_points.as_slice() {
+        [start, mid, end] => (quantize(start), quantize(mid), quantize(end)),
+        [only] => (quantize(only), quantize(only), quantize(only)),
+        [start, end] => (quantize(start), quantize(start), quantize(end)),
+        _ => ((0, 0), (0, 0), (0, 0)),
+    };
+    let (first, last) = if start <= end { (start, end) } else { (end, start) };
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for &b in bytes {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(sport_type.to_lowercase().as_bytes());
+    for (lat, lng) in [first, mid, last] {
+        feed(&lat.to_le_bytes());
+        feed(&lng.to_le_bytes());
+    }
+
+    format!("sec_{}_{:016x}", sport_type.to_lowercase(), hash)
+}
+
+/// Carry section IDs over from a previous detection run.
+///
+/// Each section in `current` takes the ID of the closest unclaimed previous
+/// section of the same sport type whose geometry matches (average minimum
+/// distance within `config.proximity_threshold`, length within 1.5x).
+/// Most visited sections claim first. Unmatched sections keep their new IDs.
+pub fn reconcile_section_ids(
+    previous: &[FrequentSection],
+    current: &mut [FrequentSection],
+    config: &SectionConfig,
+) {
+    let mut claimed: HashSet<usize> = HashSet::new();
+    let mut carried_over: HashSet<usize> = HashSet::new();
+
+    let mut order: Vec<usize> = (0..current.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(current[i].visit_count));
+
+    for i in order {
+        let section = &current[i];
+        let best = previous
+            .iter()
+            .enumerate()
+            .filter(|(j, prev)| {
+                !claimed.contains(j)
+                    && prev.sport_type == section.sport_type
+                    && (0.67..=1.5).contains(&(prev.distance_meters / section.distance_meters.max(1.0)))
+                    && bounds_overlap_tracks(&prev.polyline, &section.polyline, config.proximity_threshold)
+            })
+            .map(|(j, prev)| (j, polyline_amd(&prev.polyline, &section.polyline, AMD_SAMPLES)))
+            .filter(|&(_, amd)| amd <= config.proximity_threshold)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some((j, _)) = best {
+            claimed.insert(j);
+            carried_over.insert(i);
+            current[i].id = previous[j].id.clone();
+        }
+    }
+
+    // A new ID may now clash with one carried over; suffix the new one
+    let mut seen: HashSet<String> = carried_over.iter().map(|&i| current[i].id.clone()).collect();
+    for (i, section) in current.iter_mut().enumerate() {
+        if carried_over.contains(&i) {
+            continue;
+        }
+        let base_id = section.id.clone();
+        let mut n = 2;
+        while !seen.insert(section.id.clone()) {
+            section.id = format!("{}_{}", base_id, n);
+            n += 1;
+        }
+    }
+}
+
+/// Write sections as JSON.
+#[cfg(feature = "persist")]
+pub fn save_sections<W: std::io::Write>(sections: &[FrequentSection], writer: W) -> Result<(), String> {
+    let persisted = PersistedSectionsRef {
+        version: SECTIONS_FORMAT_VERSION,
+        sections,
+    };
+    serde_json::to_writer(writer, &persisted)
+        .map_err(|e| format!("Failed to save sections: {}", e))
+}
+
+/// Load sections previously written by [`save_sections`].
+#[cfg(feature = "persist")]
+pub fn load_sections<R: std::io::Read>(reader: R) -> Result<Vec<FrequentSection>, String> {
+    let persisted: PersistedSections = serde_json::from_reader(reader)
+        .map_err(|e| format!("Failed to load sections: {}", e))?;
+
+    if persisted.version != SECTIONS_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported sections version {} (expected {})",
+            persisted.version, SECTIONS_FORMAT_VERSION
+        ));
+    }
+
+    Ok(persisted.sections)
+}
+
+/// Borrowed on-disk layout used when saving
+#[cfg(feature = "persist")]
+#[derive(serde::Serialize)]
+struct PersistedSectionsRef<'a> {
+    version: u32,
+    sections: &'a [FrequentSection],
+}
+
+/// Owned on-disk layout used when loading
+#[cfg(feature = "persist")]
+#[derive(serde::Deserialize)]
+struct PersistedSections {
+    version: u32,
+    sections: Vec<FrequentSection>,
 }
 
 // =============================================================================
@@ -1053,8 +1843,86 @@ struct ConsensusResult {
     point_density: Vec<u32>,
 }
 
+/// Horizontal accuracy assumed for points without one when other tracks have
+/// it (meters) - typical of phones and watches under open sky
+const DEFAULT_HORIZONTAL_ACCURACY: f64 = 10.0;
+
+/// Floor on reported horizontal accuracy (meters), so a device claiming
+/// perfect fixes can't outweigh every other track
+const MIN_HORIZONTAL_ACCURACY: f64 = 3.0;
+
+/// Per-point horizontal accuracy (meters) of the input tracks, keyed by
+/// activity ID and aligned with each track's points.
+///
+/// Missing activities, missing points and non-positive values count as
+/// unknown.
+struct TrackAccuracies<'a> {
+    per_point: &'a HashMap<String, Vec<f64>>,
+    /// Median accuracy of each track, for weighting whole traces
+    typical: HashMap<&'a str, f64>,
+}
+
+impl<'a> TrackAccuracies<'a> {
+    fn new(per_point: &'a HashMap<String, Vec<f64>>) -> Self {
+        let typical = per_point
+            .iter()
+            .filter_map(|(id, accuracies)| {
+                let mut known: Vec<f64> = accuracies.iter().copied().filter(|a| is_known_accuracy(*a)).collect();
+                if known.is_empty() {
+                    return None;
+                }
+                known.sort_by(f64::total_cmp);
+                Some((id.as_str(), known[known.len() / 2]))
+            })
+            .collect();
+        Self { per_point, typical }
+    }
+
+    /// Whether any track has accuracy data; without it weighting falls back
+    /// to distance only.
+    fn is_empty(&self) -> bool {
+        self.typical.is_empty()
+    }
+
+    /// Accuracies for the points of `trace_indices` in the activity's track
+    /// (empty if the activity has none).
+    fn for_trace(&self, activity_id: &str, trace_indices: &[usize]) -> Vec<f64> {
+        match self.per_point.get(activity_id) {
+            Some(accuracies) => trace_indices
+                .iter()
+                .map(|&i| accuracies.get(i).copied().unwrap_or(f64::NAN))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Inverse-variance weight of a whole track (1 without accuracy data).
+    fn track_weight(&self, activity_id: &str) -> f64 {
+        if self.is_empty() {
+            return 1.0;
+        }
+        1.0 / accuracy_variance(self.typical.get(activity_id).copied())
+    }
+}
+
+fn is_known_accuracy(accuracy: f64) -> bool {
+    accuracy.is_finite() && accuracy > 0.0
+}
+
+/// Variance (meters²) of a point with the given horizontal accuracy
+fn accuracy_variance(accuracy: Option<f64>) -> f64 {
+    let sigma = accuracy
+        .filter(|a| is_known_accuracy(*a))
+        .unwrap_or(DEFAULT_HORIZONTAL_ACCURACY)
+        .max(MIN_HORIZONTAL_ACCURACY);
+    sigma * sigma
+}
+
 /// Compute a consensus polyline from multiple overlapping tracks.
-/// Uses weighted averaging where weight = 1 / (distance_to_reference + epsilon).
+/// Uses weighted averaging where weight = 1 / (distance_to_reference + epsilon),
+/// or 1 / (accuracy² + distance²) when `trace_accuracies` has per-point
+/// horizontal accuracy (one list per trace, aligned with its points; empty
+/// if unknown), so a noisy track can't drag the line sideways.
 ///
 /// Algorithm:
 /// 1. Normalize each track to distance parameterization
@@ -1064,6 +1932,7 @@ struct ConsensusResult {
 fn compute_consensus_polyline(
     reference: &[GpsPoint],
     all_traces: &[Vec<GpsPoint>],
+    trace_accuracies: &[Vec<f64>],
     proximity_threshold: f64,
 ) -> ConsensusResult {
     if reference.is_empty() || all_traces.is_empty() {
@@ -1077,14 +1946,14 @@ fn compute_consensus_polyline(
     }
 
     // Build R-trees for all traces for efficient spatial queries
-    let trace_trees: Vec<RTree<IndexedPoint>> = all_traces
+    let trace_trees: Vec<PointIndex> = all_traces
         .iter()
         .map(|trace| build_rtree(trace))
         .collect();
 
-    let threshold_deg = proximity_threshold / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    let threshold_sq = proximity_threshold * proximity_threshold;
     let epsilon = 0.000001; // Small constant to avoid division by zero
+    let use_accuracy = trace_accuracies.iter().any(|accuracies| !accuracies.is_empty());
 
     let mut consensus_points = Vec::with_capacity(reference.len());
     let mut point_density = Vec::with_capacity(reference.len());
@@ -1092,8 +1961,6 @@ fn compute_consensus_polyline(
     let mut total_point_observations = 0u32;
 
     for ref_point in reference {
-        let ref_coords = [ref_point.latitude, ref_point.longitude];
-
         // Collect nearby points from all traces
         let mut weighted_lat = 0.0;
         let mut weighted_lng = 0.0;
@@ -1102,18 +1969,21 @@ fn compute_consensus_polyline(
         let mut this_point_observations = 0u32;
 
         for (trace_idx, tree) in trace_trees.iter().enumerate() {
-            if let Some(nearest) = tree.nearest_neighbor(&ref_coords) {
-                let dist_sq = nearest.distance_2(&ref_coords);
-
-                if dist_sq <= threshold_deg_sq {
+            if let Some((nearest_idx, dist_sq)) = tree.nearest(ref_point) {
+                if dist_sq <= threshold_sq {
                     // Point is within threshold - include in weighted average
                     let trace = &all_traces[trace_idx];
-                    let trace_point = &trace[nearest.idx];
-
-                    // Weight inversely proportional to distance
-                    let dist_deg = dist_sq.sqrt();
-                    let dist_meters = dist_deg * 111_000.0;
-                    let weight = 1.0 / (dist_meters + epsilon);
+                    let trace_point = &trace[nearest_idx];
+
+                    // Weight inversely proportional to distance, or to the
+                    // point's expected error when accuracy is known
+                    let dist_meters = dist_sq.sqrt();
+                    let weight = if use_accuracy {
+                        let accuracy = trace_accuracies.get(trace_idx).and_then(|a| a.get(nearest_idx)).copied();
+                        1.0 / (accuracy_variance(accuracy) + dist_sq)
+                    } else {
+                        1.0 / (dist_meters + epsilon)
+                    };
 
                     weighted_lat += trace_point.latitude * weight;
                     weighted_lng += trace_point.longitude * weight;
@@ -1141,7 +2011,7 @@ fn compute_consensus_polyline(
             }
         } else {
             // No nearby points - keep reference point
-            consensus_points.push(ref_point.clone());
+            consensus_points.push(*ref_point);
         }
     }
 
@@ -1157,7 +2027,7 @@ fn compute_consensus_polyline(
     // More observations + tighter spread = higher confidence
     let obs_factor = (observation_count as f64).min(10.0) / 10.0; // Saturates at 10 observations
     let spread_factor = 1.0 - (average_spread / proximity_threshold).min(1.0); // Lower spread = higher factor
-    let confidence = (obs_factor * 0.5 + spread_factor * 0.5).min(1.0).max(0.0);
+    let confidence = (obs_factor * 0.5 + spread_factor * 0.5).clamp(0.0, 1.0);
 
     ConsensusResult {
         polyline: consensus_points,
@@ -1205,7 +2075,7 @@ struct SplitCandidate {
 
 /// Analyze a section's point density to find high-traffic portions.
 /// Returns split candidates if the section should be divided.
-fn find_split_candidates(section: &FrequentSection) -> Vec<SplitCandidate> {
+fn find_split_candidates(section: &FrequentSection, config: &SectionConfig) -> Vec<SplitCandidate> {
     let density = &section.point_density;
 
     if density.len() < MIN_SPLIT_POINTS * 2 {
@@ -1263,7 +2133,7 @@ fn find_split_candidates(section: &FrequentSection) -> Vec<SplitCandidate> {
 
             // Compute distance of this portion
             let portion_distance = if end_idx > start_idx {
-                polyline_length(&section.polyline[start_idx..=end_idx])
+                config.distance_model().polyline_length(&section.polyline[start_idx..=end_idx])
             } else {
                 0.0
             };
@@ -1302,7 +2172,7 @@ fn split_section_by_density(
     track_map: &HashMap<String, Vec<GpsPoint>>,
     config: &SectionConfig,
 ) -> Vec<FrequentSection> {
-    let candidates = find_split_candidates(&section);
+    let candidates = find_split_candidates(&section, config);
 
     if candidates.is_empty() {
         return vec![section];
@@ -1322,15 +2192,14 @@ fn split_section_by_density(
         // Extract the high-density portion
         let split_polyline = section.polyline[candidate.start_idx..=candidate.end_idx].to_vec();
         let split_density = section.point_density[candidate.start_idx..=candidate.end_idx].to_vec();
-        let split_distance = polyline_length(&split_polyline);
+        let split_distance = config.distance_model().polyline_length(&split_polyline);
 
         // Re-compute which activities overlap with this portion
         let mut split_activity_ids = Vec::new();
         let mut split_activity_traces = HashMap::new();
 
         let split_tree = build_rtree(&split_polyline);
-        let threshold_deg = config.proximity_threshold / 111_000.0;
-        let threshold_deg_sq = threshold_deg * threshold_deg;
+        let threshold_sq = config.proximity_threshold * config.proximity_threshold;
 
         for activity_id in &section.activity_ids {
             if let Some(track) = track_map.get(activity_id) {
@@ -1338,16 +2207,15 @@ fn split_section_by_density(
                 let mut overlap_points = Vec::new();
 
                 for point in track {
-                    let query = [point.latitude, point.longitude];
-                    if let Some(nearest) = split_tree.nearest_neighbor(&query) {
-                        if nearest.distance_2(&query) <= threshold_deg_sq {
-                            overlap_points.push(point.clone());
+                    if let Some((_, dist_sq)) = split_tree.nearest(point) {
+                        if dist_sq <= threshold_sq {
+                            overlap_points.push(*point);
                         }
                     }
                 }
 
                 // Need substantial overlap to count
-                let overlap_distance = polyline_length(&overlap_points);
+                let overlap_distance = config.distance_model().polyline_length(&overlap_points);
                 if overlap_distance >= split_distance * 0.5 {
                     split_activity_ids.push(activity_id.clone());
                     if !overlap_points.is_empty() {
@@ -1374,6 +2242,10 @@ fn split_section_by_density(
                 observation_count: candidate.avg_density as u32,
                 average_spread: section.average_spread,
                 point_density: split_density,
+                same_direction: None,
+                reverse_direction: None,
+                suggested_name: None,
+                matching_threshold: section.matching_threshold,
             };
 
             info!(
@@ -1416,16 +2288,14 @@ fn split_high_variance_sections(
 // =============================================================================
 //
 // Core geographic utilities (haversine_distance, compute_bounds, compute_center,
-// polyline_length, bounds_overlap) are imported from crate::geo_utils
+// bounds_overlap) are imported from crate::geo_utils; lengths use
+// SectionConfig::distance_model
 
 /// Check if two tracks' bounding boxes overlap
 fn bounds_overlap_tracks(track_a: &[GpsPoint], track_b: &[GpsPoint], buffer: f64) -> bool {
-    if track_a.is_empty() || track_b.is_empty() {
+    let (Some(bounds_a), Some(bounds_b)) = (compute_bounds(track_a), compute_bounds(track_b)) else {
         return false;
-    }
-
-    let bounds_a = compute_bounds(track_a);
-    let bounds_b = compute_bounds(track_b);
+    };
 
     // Use reference latitude from center of bounds_a for meter-to-degree conversion
     let ref_lat = (bounds_a.min_lat + bounds_a.max_lat) / 2.0;
@@ -1443,8 +2313,7 @@ fn detect_fold_point(polyline: &[GpsPoint], threshold: f64) -> Option<usize> {
         return None;
     }
 
-    let threshold_deg = threshold / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    let threshold_sq = threshold * threshold;
 
     // Build R-tree of the first half of the polyline
     let half = polyline.len() / 2;
@@ -1456,11 +2325,9 @@ fn detect_fold_point(polyline: &[GpsPoint], threshold: f64) -> Option<usize> {
 
     for (i, point) in polyline[half..].iter().enumerate() {
         let idx = half + i;
-        let query = [point.latitude, point.longitude];
 
-        if let Some(nearest) = first_half_tree.nearest_neighbor(&query) {
-            let dist_sq = nearest.distance_2(&query);
-            if dist_sq <= threshold_deg_sq {
+        if let Some((_, dist_sq)) = first_half_tree.nearest(point) {
+            if dist_sq <= threshold_sq {
                 // This point is close to an earlier point - potential fold
                 // Track the earliest point where this happens
                 fold_candidates.push((idx, dist_sq));
@@ -1486,13 +2353,12 @@ fn compute_fold_ratio(polyline: &[GpsPoint], threshold: f64) -> f64 {
         return 0.0;
     }
 
-    let threshold_deg = threshold / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    let threshold_sq = threshold * threshold;
 
     // Compare first third to last third (reversed)
     let third = polyline.len() / 3;
     let first_third = &polyline[..third];
-    let last_third: Vec<GpsPoint> = polyline[(polyline.len() - third)..].iter().cloned().collect();
+    let last_third: Vec<GpsPoint> = polyline[(polyline.len() - third)..].to_vec();
 
     // Build tree from first third
     let first_tree = build_rtree(first_third);
@@ -1500,9 +2366,8 @@ fn compute_fold_ratio(polyline: &[GpsPoint], threshold: f64) -> f64 {
     // Count how many points in last third are close to points in first third
     let mut close_count = 0;
     for point in last_third.iter().rev() {  // Reversed order for out-and-back
-        let query = [point.latitude, point.longitude];
-        if let Some(nearest) = first_tree.nearest_neighbor(&query) {
-            if nearest.distance_2(&query) <= threshold_deg_sq {
+        if let Some((_, dist_sq)) = first_tree.nearest(point) {
+            if dist_sq <= threshold_sq {
                 close_count += 1;
             }
         }
@@ -1515,6 +2380,8 @@ fn compute_fold_ratio(polyline: &[GpsPoint], threshold: f64) -> f64 {
 /// For out-and-back routes, this creates two sections: outbound and return.
 fn split_folding_sections(
     sections: Vec<FrequentSection>,
+    track_map: &HashMap<String, Vec<GpsPoint>>,
+    activity_to_route: &HashMap<&str, &str>,
     config: &SectionConfig,
 ) -> Vec<FrequentSection> {
     let mut result = Vec::new();
@@ -1527,28 +2394,27 @@ fn split_folding_sections(
             if let Some(fold_idx) = detect_fold_point(&section.polyline, config.proximity_threshold) {
                 // Create outbound section (start to fold point)
                 let outbound_polyline = section.polyline[..fold_idx].to_vec();
-                let outbound_length = polyline_length(&outbound_polyline);
+                let outbound_length = config.distance_model().polyline_length(&outbound_polyline);
 
                 if outbound_length >= config.min_section_length {
                     let mut outbound = section.clone();
                     outbound.id = format!("{}_out", section.id);
                     outbound.polyline = outbound_polyline;
                     outbound.distance_meters = outbound_length;
-                    // Update activity traces to only include outbound portion
-                    outbound.activity_traces = HashMap::new();  // Will be recomputed
+                    refresh_split_activities(&mut outbound, track_map, activity_to_route, config);
                     result.push(outbound);
                 }
 
                 // Create return section (fold point to end)
                 let return_polyline = section.polyline[fold_idx..].to_vec();
-                let return_length = polyline_length(&return_polyline);
+                let return_length = config.distance_model().polyline_length(&return_polyline);
 
                 if return_length >= config.min_section_length {
                     let mut return_section = section.clone();
                     return_section.id = format!("{}_ret", section.id);
                     return_section.polyline = return_polyline;
                     return_section.distance_meters = return_length;
-                    return_section.activity_traces = HashMap::new();
+                    refresh_split_activities(&mut return_section, track_map, activity_to_route, config);
                     result.push(return_section);
                 }
 
@@ -1569,6 +2435,58 @@ fn split_folding_sections(
     result
 }
 
+/// Recompute per-activity data for a section whose polyline was cut from a parent.
+///
+/// Traces, portions and route IDs are re-derived against the new polyline;
+/// activities that no longer touch it are dropped.
+fn refresh_split_activities(
+    section: &mut FrequentSection,
+    track_map: &HashMap<String, Vec<GpsPoint>>,
+    activity_to_route: &HashMap<&str, &str>,
+    config: &SectionConfig,
+) {
+    section.activity_traces = extract_all_activity_traces(
+        &section.activity_ids,
+        &section.polyline,
+        track_map,
+    );
+    let traces = &section.activity_traces;
+    section.activity_ids.retain(|id| traces.contains_key(id));
+
+    section.activity_portions = compute_activity_portions(
+        &section.activity_ids,
+        &section.polyline,
+        track_map,
+        config,
+    );
+
+    section.route_ids = section.activity_ids
+        .iter()
+        .filter_map(|aid| activity_to_route.get(aid.as_str()).map(|s| s.to_string()))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    if !section.activity_ids.contains(&section.representative_activity_id) {
+        if let Some(first) = section.activity_ids.first() {
+            section.representative_activity_id = first.clone();
+        }
+    }
+
+    (section.same_direction, section.reverse_direction) = if config.split_directions {
+        let stats = |direction: &str| compute_direction_stats(
+            direction,
+            &section.activity_portions,
+            &section.activity_traces,
+            &section.polyline,
+            config.proximity_threshold,
+        );
+        (stats("same"), stats("reverse"))
+    } else {
+        (None, None)
+    };
+}
+
 /// Merge sections that are geometrically close to each other.
 /// This handles: reversed sections, parallel tracks (opposite sides of road), GPS drift.
 fn merge_nearby_sections(
@@ -1580,7 +2498,7 @@ fn merge_nearby_sections(
     }
 
     // Sort by visit count descending - keep the most visited version
-    sections.sort_by(|a, b| b.visit_count.cmp(&a.visit_count));
+    sections.sort_by_key(|s| std::cmp::Reverse(s.visit_count));
 
     let mut keep: Vec<bool> = vec![true; sections.len()];
 
@@ -1605,7 +2523,7 @@ fn merge_nearby_sections(
 
             // Skip if sections are very different lengths (>3x difference)
             let length_ratio = section_i.distance_meters / section_j.distance_meters.max(1.0);
-            if length_ratio > 3.0 || length_ratio < 0.33 {
+            if !(0.33..=3.0).contains(&length_ratio) {
                 continue;
             }
 
@@ -1734,22 +2652,20 @@ fn remove_overlapping_sections(
 /// Compute what fraction of polyline A is contained within polyline B
 fn compute_containment(
     poly_a: &[GpsPoint],
-    tree_b: &RTree<IndexedPoint>,
+    tree_b: &PointIndex,
     threshold: f64,
 ) -> f64 {
     if poly_a.is_empty() {
         return 0.0;
     }
 
-    let threshold_deg = threshold / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    let threshold_sq = threshold * threshold;
 
     let mut contained_points = 0;
 
     for point in poly_a {
-        let query = [point.latitude, point.longitude];
-        if let Some(nearest) = tree_b.nearest_neighbor(&query) {
-            if nearest.distance_2(&query) <= threshold_deg_sq {
+        if let Some((_, dist_sq)) = tree_b.nearest(point) {
+            if dist_sq <= threshold_sq {
                 contained_points += 1;
             }
         }
@@ -1769,41 +2685,38 @@ const MIN_TRACE_POINTS: usize = 3;
 /// This handles out-and-back routes where the activity crosses the section twice.
 /// Uses R-tree for efficient O(log n) proximity lookups.
 /// Tolerates small gaps (up to 3 points) due to GPS noise.
-fn extract_activity_trace(track: &[GpsPoint], section_polyline: &[GpsPoint], polyline_tree: &RTree<IndexedPoint>) -> Vec<GpsPoint> {
+/// Returns indices into `track`, so per-point data (e.g. horizontal accuracy)
+/// can follow the trace.
+fn extract_activity_trace_indices(track: &[GpsPoint], section_polyline: &[GpsPoint], polyline_tree: &PointIndex) -> Vec<usize> {
     if track.len() < MIN_TRACE_POINTS || section_polyline.len() < 2 {
         return Vec::new();
     }
 
-    // Convert threshold from meters to approximate degrees for R-tree comparison
     // Use a slightly larger threshold to catch GPS variations
-    let threshold_deg = (TRACE_PROXIMITY_THRESHOLD * 1.2) / 111_000.0;
-    let threshold_deg_sq = threshold_deg * threshold_deg;
+    let threshold = TRACE_PROXIMITY_THRESHOLD * 1.2;
+    let threshold_sq = threshold * threshold;
 
     // Find ALL contiguous sequences of points near the section
-    let mut sequences: Vec<Vec<GpsPoint>> = Vec::new();
-    let mut current_sequence: Vec<GpsPoint> = Vec::new();
+    let mut sequences: Vec<Vec<usize>> = Vec::new();
+    let mut current_sequence: Vec<usize> = Vec::new();
     let mut gap_count = 0;
     const MAX_GAP: usize = 3; // Allow small gaps due to GPS noise
 
-    for point in track {
-        let query = [point.latitude, point.longitude];
-
+    for (i, point) in track.iter().enumerate() {
         // Use R-tree for O(log n) nearest neighbor lookup
-        let is_near = if let Some(nearest) = polyline_tree.nearest_neighbor(&query) {
-            nearest.distance_2(&query) <= threshold_deg_sq
-        } else {
-            false
-        };
+        let is_near = polyline_tree
+            .nearest(point)
+            .is_some_and(|(_, dist_sq)| dist_sq <= threshold_sq);
 
         if is_near {
             // Point is near section - reset gap counter
             gap_count = 0;
-            current_sequence.push(point.clone());
+            current_sequence.push(i);
         } else {
             gap_count += 1;
             // Allow small gaps but still add the point if we're in a sequence
             if gap_count <= MAX_GAP && !current_sequence.is_empty() {
-                current_sequence.push(point.clone());
+                current_sequence.push(i);
             } else if gap_count > MAX_GAP {
                 // End current sequence if valid
                 if current_sequence.len() >= MIN_TRACE_POINTS {
@@ -1838,13 +2751,12 @@ fn extract_activity_trace(track: &[GpsPoint], section_polyline: &[GpsPoint], pol
     let section_tree = build_rtree(section_polyline);
 
     // For each sequence, find where it starts on the section
-    let mut sequence_with_position: Vec<(usize, Vec<GpsPoint>)> = sequences
+    let mut sequence_with_position: Vec<(usize, Vec<usize>)> = sequences
         .into_iter()
         .map(|seq| {
-            let start_pos = if let Some(first) = seq.first() {
-                let query = [first.latitude, first.longitude];
-                section_tree.nearest_neighbor(&query)
-                    .map(|n| n.idx)
+            let start_pos = if let Some(&first) = seq.first() {
+                section_tree.nearest(&track[first])
+                    .map(|(idx, _)| idx)
                     .unwrap_or(0)
             } else {
                 0
@@ -1857,7 +2769,7 @@ fn extract_activity_trace(track: &[GpsPoint], section_polyline: &[GpsPoint], pol
     sequence_with_position.sort_by_key(|(pos, _)| *pos);
 
     // Concatenate all sequences
-    let mut merged: Vec<GpsPoint> = Vec::new();
+    let mut merged: Vec<usize> = Vec::new();
     for (_, seq) in sequence_with_position {
         merged.extend(seq);
     }
@@ -1872,6 +2784,22 @@ fn extract_all_activity_traces(
     section_polyline: &[GpsPoint],
     track_map: &HashMap<String, Vec<GpsPoint>>,
 ) -> HashMap<String, Vec<GpsPoint>> {
+    extract_all_activity_trace_indices(activity_ids, section_polyline, track_map)
+        .into_iter()
+        .map(|(activity_id, indices)| {
+            let track = &track_map[&activity_id];
+            let trace = indices.into_iter().map(|i| track[i]).collect();
+            (activity_id, trace)
+        })
+        .collect()
+}
+
+/// [`extract_all_activity_traces`] as indices into each activity's track.
+fn extract_all_activity_trace_indices(
+    activity_ids: &[String],
+    section_polyline: &[GpsPoint],
+    track_map: &HashMap<String, Vec<GpsPoint>>,
+) -> HashMap<String, Vec<usize>> {
     let mut traces = HashMap::new();
 
     // Build R-tree once for the section polyline (O(n log n))
@@ -1879,7 +2807,7 @@ fn extract_all_activity_traces(
 
     for activity_id in activity_ids {
         if let Some(track) = track_map.get(activity_id) {
-            let trace = extract_activity_trace(track, section_polyline, &polyline_tree);
+            let trace = extract_activity_trace_indices(track, section_polyline, &polyline_tree);
             if !trace.is_empty() {
                 traces.insert(activity_id.clone(), trace);
             }
@@ -1892,6 +2820,7 @@ fn extract_all_activity_traces(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::geo_utils::polyline_length;
 
     fn make_point(lat: f64, lng: f64) -> GpsPoint {
         GpsPoint::new(lat, lng)
@@ -1918,13 +2847,261 @@ mod tests {
     }
 
     #[test]
-    fn test_resample_by_distance() {
-        let points = vec![
-            make_point(0.0, 0.0),
-            make_point(0.001, 0.0),
-            make_point(0.002, 0.0),
+    fn test_medoid_avoids_traces_with_gaps() {
+        // The central trace is the natural medoid, but lost signal for ~450m
+        let trace = |offset: f64, gap: bool| -> Vec<GpsPoint> {
+            (0..40)
+                .filter(|i| !gap || !(10..30).contains(i))
+                .map(|i| make_point(51.5 + i as f64 * 0.0002, -0.1 + offset))
+                .collect()
+        };
+        let overlap = |b: &str, offset: f64| FullTrackOverlap {
+            activity_a: "gap".to_string(),
+            activity_b: b.to_string(),
+            points_a: trace(0.0, true),
+            points_b: trace(offset, false),
+            center: make_point(51.504, -0.1),
+        };
+        let cluster = OverlapCluster {
+            overlaps: vec![overlap("east", 0.00005), overlap("west", -0.00005)],
+            activity_ids: ["gap", "east", "west"].iter().map(|id| id.to_string()).collect(),
+        };
+
+        assert_eq!(select_medoid(&cluster, &TrackAccuracies::new(&HashMap::new()), &SectionConfig::default()).0, "gap");
+        let config = SectionConfig { max_gap_meters: Some(200.0), ..SectionConfig::default() };
+        assert_ne!(select_medoid(&cluster, &TrackAccuracies::new(&HashMap::new()), &config).0, "gap");
+    }
+
+    #[test]
+    fn test_boundaries_grow_and_contract_with_quorum() {
+        // ~11m per step along a meridian
+        let line = |from: usize, to: usize| -> Vec<GpsPoint> {
+            (from..to).map(|i| make_point(51.5 + i as f64 * 0.0001, -0.1)).collect()
+        };
+        let config = SectionConfig::default();
+        let polyline = line(0, 50);
+        let density = vec![4; polyline.len()];
+        let adapt = |tracks: Vec<Vec<GpsPoint>>| {
+            let track_map: HashMap<String, Vec<GpsPoint>> =
+                tracks.into_iter().enumerate().map(|(i, t)| (format!("a{}", i), t)).collect();
+            let mut ids: Vec<String> = track_map.keys().cloned().collect();
+            ids.sort();
+            let portions = compute_activity_portions(&ids, &polyline, &track_map, &config);
+            adapt_section_boundaries(&polyline, &density, &portions, &track_map, &config)
+        };
+
+        // Every traversal carries on ~330m past the end together
+        let (grown, grown_density) = adapt(vec![line(0, 80); 4]).unwrap();
+        assert_eq!(grown[0], polyline[0]);
+        assert_eq!(grown.len(), grown_density.len());
+        let extra = polyline_length(&grown) - polyline_length(&polyline);
+        assert!(extra > 200.0 && extra < 400.0, "grew {}m", extra);
+
+        // Three of four only join ~220m in
+        let (contracted, _) = adapt(vec![line(0, 50), line(20, 50), line(20, 50), line(20, 50)]).unwrap();
+        let removed = polyline_length(&polyline) - polyline_length(&contracted);
+        assert!(removed > 150.0 && removed < 250.0, "contracted {}m", removed);
+
+        // Half continue, half stop: no quorum either way
+        assert!(adapt(vec![line(0, 80), line(0, 80), line(0, 50), line(0, 50)]).is_none());
+    }
+
+    #[test]
+    fn test_adaptive_threshold_tightens_with_confidence() {
+        let config = SectionConfig::default();
+        assert_eq!(adaptive_threshold(0.0, 5.0, &config), config.proximity_threshold);
+        // Full confidence with a tight spread bottoms out at the floor
+        assert_eq!(adaptive_threshold(1.0, 2.0, &config), MIN_ADAPTIVE_THRESHOLD);
+        assert_eq!(adaptive_threshold(1.0, 10.0, &config), 30.0);
+        assert!(adaptive_threshold(0.8, 5.0, &config) < adaptive_threshold(0.4, 5.0, &config));
+        // Never looser than the configured threshold
+        assert_eq!(adaptive_threshold(1.0, 40.0, &config), config.proximity_threshold);
+    }
+
+    #[test]
+    fn test_consensus_weights_by_accuracy() {
+        let line = |offset: f64| -> Vec<GpsPoint> {
+            (0..50).map(|i| make_point(51.5 + i as f64 * 0.0001, -0.1 + offset)).collect()
+        };
+        let reference = line(0.0);
+        // A precise track ~3.5m east and a noisy one ~28m west
+        let precise = line(0.00005);
+        let noisy = line(-0.0004);
+        let traces = vec![precise.clone(), noisy];
+        let offset_from_precise = |consensus: &ConsensusResult| haversine_distance(&consensus.polyline[25], &precise[25]);
+
+        // Inverse distance alone balances the two tracks around the reference
+        let unweighted = compute_consensus_polyline(&reference, &traces, &[], 50.0);
+        assert!(offset_from_precise(&unweighted) > 3.0);
+
+        let accuracies = vec![vec![3.0; 50], vec![40.0; 50]];
+        let weighted = compute_consensus_polyline(&reference, &traces, &accuracies, 50.0);
+        assert!(offset_from_precise(&weighted) < 1.0);
+    }
+
+    fn section(id: &str, sport_type: &str, polyline: Vec<GpsPoint>, visit_count: u32) -> FrequentSection {
+        FrequentSection {
+            id: id.to_string(),
+            sport_type: sport_type.to_string(),
+            distance_meters: polyline_length(&polyline),
+            polyline,
+            representative_activity_id: "a".to_string(),
+            activity_ids: vec!["a".to_string()],
+            activity_portions: Vec::new(),
+            route_ids: Vec::new(),
+            visit_count,
+            activity_traces: HashMap::new(),
+            confidence: 0.5,
+            observation_count: visit_count,
+            average_spread: 5.0,
+            point_density: Vec::new(),
+            same_direction: None,
+            reverse_direction: None,
+            suggested_name: None,
+            matching_threshold: None,
+        }
+    }
+
+    fn line(lat: f64, lng: f64) -> Vec<GpsPoint> {
+        (0..20).map(|i| make_point(lat + i as f64 * 0.0005, lng)).collect()
+    }
+
+    #[test]
+    fn test_split_directions() {
+        // 2km climb ridden up three times and down twice, with a few meters of jitter
+        let climb = |offset: f64| -> Vec<GpsPoint> {
+            (0..200).map(|i| make_point(46.50 + i as f64 * 0.0001, 7.60 + offset)).collect()
+        };
+        let mut tracks: Vec<(String, Vec<GpsPoint>)> = Vec::new();
+        for (i, offset) in [0.0, 0.00003, -0.00003].iter().enumerate() {
+            tracks.push((format!("up{}", i), climb(*offset)));
+        }
+        for (i, offset) in [0.00002, -0.00002].iter().enumerate() {
+            tracks.push((format!("down{}", i), climb(*offset).into_iter().rev().collect()));
+        }
+        let sport_types: HashMap<String, String> = tracks.iter().map(|(id, _)| (id.clone(), "Ride".to_string())).collect();
+
+        let config = SectionConfig { split_directions: true, ..SectionConfig::default() };
+        let sections = detect_sections_from_tracks(&tracks, &sport_types, &[], &config);
+        assert!(!sections.is_empty());
+
+        let section = &sections[0];
+        let same = section.same_direction.as_ref().unwrap();
+        let reverse = section.reverse_direction.as_ref().unwrap();
+        assert_eq!(same.traversal_count + reverse.traversal_count, section.activity_portions.len() as u32);
+        // The two directions partition the activities
+        let ups = if same.activity_ids.iter().any(|id| id.starts_with("up")) { same } else { reverse };
+        assert!(ups.activity_ids.iter().all(|id| id.starts_with("up")));
+        assert_eq!(ups.traversal_count, 3);
+        assert!(ups.confidence > 0.0 && ups.average_distance_meters > 1000.0);
+
+        // Off by default
+        let sections = detect_sections_from_tracks(&tracks, &sport_types, &[], &SectionConfig::default());
+        assert!(sections.iter().all(|s| s.same_direction.is_none() && s.reverse_direction.is_none()));
+    }
+
+    #[test]
+    fn test_progress_reports_every_phase() {
+        use std::sync::Mutex;
+
+        let track: Vec<GpsPoint> = (0..100).map(|i| make_point(51.5 + i as f64 * 0.0001, -0.1)).collect();
+        let tracks: Vec<(String, Vec<GpsPoint>)> = (0..4).map(|i| (format!("a{}", i), track.clone())).collect();
+        let sport_types: HashMap<String, String> = tracks.iter().map(|(id, _)| (id.clone(), "Run".to_string())).collect();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let on_progress: SectionProgress = Arc::new(move |phase, percent| seen.lock().unwrap().push((phase, percent)));
+        let sections = detect_sections_from_tracks_with_progress(
+            &tracks, &sport_types, &[], &SectionConfig::default(), Some(on_progress),
+        );
+        assert!(!sections.is_empty());
+
+        let events = events.lock().unwrap();
+        for phase in [SectionPhase::Overlaps, SectionPhase::Clustering, SectionPhase::Consensus, SectionPhase::PostProcessing] {
+            let percents: Vec<u32> = events.iter().filter(|(p, _)| *p == phase).map(|(_, pct)| *pct).collect();
+            // Worker threads may deliver out of order, but each percentage is reported once
+            let mut unique = percents.clone();
+            unique.sort_unstable();
+            unique.dedup();
+            assert_eq!(unique.len(), percents.len());
+            assert_eq!(unique.first(), Some(&0));
+            assert_eq!(unique.last(), Some(&100));
+        }
+    }
+
+    #[test]
+    fn test_fold_split_recomputes_activities() {
+        // 1km out-and-back: return leg runs ~5m beside the outbound leg
+        let out_and_back = |offset: f64| -> Vec<GpsPoint> {
+            let out = (0..100).map(|i| make_point(47.0 + i as f64 * 0.0001, 8.0 + offset));
+            let back = (0..100).rev().map(|i| make_point(47.0 + i as f64 * 0.0001, 8.00005 + offset));
+            out.chain(back).collect()
+        };
+        let track_map: HashMap<String, Vec<GpsPoint>> = vec![
+            ("a".to_string(), out_and_back(0.0)),
+            ("b".to_string(), out_and_back(0.00002)),
+            ("elsewhere".to_string(), line(48.0, 9.0)),
+        ]
+        .into_iter()
+        .collect();
+        let activity_to_route: HashMap<&str, &str> = vec![("a", "route1"), ("elsewhere", "route2")].into_iter().collect();
+
+        let mut folded = section("sec_run_0", "Run", out_and_back(0.0), 3);
+        folded.activity_ids = vec!["a".to_string(), "b".to_string(), "elsewhere".to_string()];
+
+        let config = SectionConfig { split_directions: true, ..SectionConfig::default() };
+        let split = split_folding_sections(vec![folded], &track_map, &activity_to_route, &config);
+        assert_eq!(split.len(), 2);
+
+        for part in &split {
+            assert_eq!(part.activity_ids.len(), 2);
+            assert!(part.activity_traces.contains_key("a") && part.activity_traces.contains_key("b"));
+            assert_eq!(part.activity_portions.len(), 2);
+            assert_eq!(part.route_ids, vec!["route1".to_string()]);
+            assert!(part.same_direction.is_some());
+        }
+    }
+
+    #[test]
+    fn test_reconcile_section_ids() {
+        let previous = vec![
+            section("starred", "Run", line(51.50, -0.10), 5),
+            section("other", "Run", line(51.60, -0.10), 3),
+        ];
+        // Consensus drifted ~10m east, plus a brand new section
+        let mut current = vec![
+            section(&stable_section_id("Run", &line(51.50, -0.09985)), "Run", line(51.50, -0.09985), 6),
+            section("sec_run_new", "Run", line(51.70, -0.10), 3),
+            section("sec_ride_x", "Ride", line(51.60, -0.10), 3),
         ];
-        let resampled = resample_by_distance(&points, 5);
-        assert_eq!(resampled.len(), 5);
+
+        reconcile_section_ids(&previous, &mut current, &SectionConfig::default());
+        assert_eq!(current[0].id, "starred");
+        assert_eq!(current[1].id, "sec_run_new");
+        // Different sport never inherits an ID
+        assert_eq!(current[2].id, "sec_ride_x");
+    }
+
+    #[cfg(feature = "persist")]
+    #[test]
+    fn test_save_and_load_sections() {
+        let mut original = section("sec_run_1", "Run", line(51.50, -0.10), 4);
+        original.activity_portions.push(SectionPortion {
+            activity_id: "a".to_string(),
+            start_index: 3,
+            end_index: 40,
+            distance_meters: 1000.0,
+            direction: "same".to_string(),
+        });
+
+        let mut buffer = Vec::new();
+        save_sections(&[original.clone()], &mut buffer).unwrap();
+        let restored = load_sections(buffer.as_slice()).unwrap();
+
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, original.id);
+        assert_eq!(restored[0].polyline, original.polyline);
+        assert_eq!(restored[0].activity_portions[0].end_index, 40);
+        assert!(load_sections(&b"{\"version\":99,\"sections\":[]}"[..]).is_err());
     }
 }
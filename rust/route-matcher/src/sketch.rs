@@ -0,0 +1,165 @@
+//! # Sketch Search
+//!
+//! Finds activities that follow a polyline the user drew on the map ("find my
+//! activities along this road"), rather than one recorded as an activity.
+//!
+//! ## Algorithm
+//!
+//! A sketch usually covers only part of an activity, so the comparison is
+//! one-sided: the sketch is resampled to [`MatchConfig::resample_count`]
+//! points and each is measured against the nearest line of the signature
+//! (AMD to the polyline). An activity that rides the whole sketch scores
+//! close to 100% however far it continues beyond it; one that only touches
+//! part of the sketch scores low. Scores use the same thresholds as
+//! [`crate::compare_routes`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{GpsPoint, MatchConfig, RouteSignature};
+//! use route_matcher::sketch::find_routes_similar_to;
+//!
+//! let config = MatchConfig::default();
+//! let road = |from: usize, to: usize| -> Vec<GpsPoint> {
+//!     (from..=to).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.10)).collect()
+//! };
+//! let signatures = vec![
+//!     RouteSignature::from_points("along", &road(0, 30), &config).unwrap(),
+//!     RouteSignature::from_points("elsewhere", &road(40, 70), &config).unwrap(),
+//! ];
+//!
+//! // Two taps on the map, along the first half of the road
+//! let sketch = vec![GpsPoint::new(51.502, -0.10), GpsPoint::new(51.515, -0.10)];
+//! let matches = find_routes_similar_to(&sketch, &signatures, &config);
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].activity_id, "along");
+//! ```
+
+use crate::geo_utils::{bounds_overlap, project_point_onto_route, resample_polyline};
+use crate::projection::LocalProjection;
+use crate::metrics::{amd_to_percentage, average_min_distance_to_polyline};
+use crate::{Bounds, GpsPoint, MatchConfig, RouteSignature};
+
+/// An activity following a drawn polyline
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SketchMatch {
+    pub activity_id: String,
+    /// How closely the activity follows the sketch (0-100)
+    pub match_percentage: f64,
+    /// Average distance in meters from the sketch to the activity
+    pub amd: f64,
+    /// "same" if the activity travels the sketch in its drawn direction,
+    /// otherwise "reverse"
+    pub direction: String,
+}
+
+/// Find signatures that follow `polyline`, best match first.
+///
+/// Matches below [`MatchConfig::min_match_percentage`] are dropped. Returns an
+/// empty list for sketches with fewer than 2 valid points.
+pub fn find_routes_similar_to(
+    polyline: &[GpsPoint],
+    signatures: &[RouteSignature],
+    config: &MatchConfig,
+) -> Vec<SketchMatch> {
+    let sketch: Vec<GpsPoint> = polyline.iter().copied().filter(|p| p.is_valid()).collect();
+    let Some(bounds) = Bounds::from_points(&sketch) else {
+        return Vec::new();
+    };
+    if sketch.len() < 2 {
+        return Vec::new();
+    }
+
+    let resampled = resample_polyline(&sketch, config.resample_count.max(2) as usize);
+    let projection = LocalProjection::for_points(&sketch);
+    let planar_sketch = projection.project_all(&resampled);
+    let reference_lat = bounds.center().latitude;
+
+    let mut matches: Vec<SketchMatch> = signatures
+        .iter()
+        .filter(|sig| sig.points.len() >= 2)
+        .filter(|sig| bounds_overlap(&bounds, &sig.bounds, config.zero_threshold, reference_lat))
+        .filter_map(|sig| {
+            let route = projection.project_all(&sig.points);
+            let gaps: Vec<bool> = (0..route.len() - 1).map(|j| sig.gap_legs.contains(&(j as u32))).collect();
+            let amd = average_min_distance_to_polyline(&planar_sketch, &route, &[], &gaps);
+            if !amd.is_finite() {
+                return None;
+            }
+            let match_percentage = amd_to_percentage(amd, config.perfect_threshold, config.zero_threshold);
+            if match_percentage < config.min_match_percentage {
+                return None;
+            }
+            Some(SketchMatch {
+                activity_id: sig.activity_id.clone(),
+                match_percentage,
+                amd,
+                direction: sketch_direction(&sketch, &sig.points).to_string(),
+            })
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.match_percentage
+            .total_cmp(&a.match_percentage)
+            .then_with(|| a.amd.total_cmp(&b.amd))
+            .then_with(|| a.activity_id.cmp(&b.activity_id))
+    });
+    matches
+}
+
+/// Whether the route passes the sketch's start before its end.
+fn sketch_direction(sketch: &[GpsPoint], route: &[GpsPoint]) -> &'static str {
+    let along = |p: &GpsPoint| project_point_onto_route(p, route).map_or(0.0, |proj| proj.distance_along);
+    if along(&sketch[0]) <= along(&sketch[sketch.len() - 1]) {
+        "same"
+    } else {
+        "reverse"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn road(from: usize, to: usize) -> Vec<GpsPoint> {
+        (from..=to).map(|i| GpsPoint::new(51.50 + i as f64 * 0.001, -0.10)).collect()
+    }
+
+    fn signature(id: &str, points: &[GpsPoint]) -> RouteSignature {
+        RouteSignature::from_points(id, points, &MatchConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_ranks_closer_routes_first_with_direction() {
+        let config = MatchConfig::default();
+        // ~70m east of the road
+        let offset: Vec<GpsPoint> = road(0, 30).iter().map(|p| GpsPoint::new(p.latitude, p.longitude + 0.001)).collect();
+        let mut reversed = road(0, 30);
+        reversed.reverse();
+        let signatures = vec![signature("offset", &offset), signature("reversed", &reversed), signature("exact", &road(0, 30))];
+
+        let matches = find_routes_similar_to(&road(5, 20), &signatures, &config);
+        let ids: Vec<&str> = matches.iter().map(|m| m.activity_id.as_str()).collect();
+        assert_eq!(ids, vec!["exact", "reversed", "offset"]);
+        assert_eq!(matches[0].direction, "same");
+        assert_eq!(matches[1].direction, "reverse");
+        assert!(matches[2].amd > 60.0);
+    }
+
+    #[test]
+    fn test_partial_coverage_of_sketch_does_not_match() {
+        // The activity only covers the first third of the drawn road
+        let signatures = vec![signature("short", &road(0, 10))];
+        assert!(find_routes_similar_to(&road(0, 30), &signatures, &MatchConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_degenerate_sketch() {
+        let signatures = vec![signature("a", &road(0, 10))];
+        let config = MatchConfig::default();
+        assert!(find_routes_similar_to(&[], &signatures, &config).is_empty());
+        assert!(find_routes_similar_to(&[GpsPoint::new(51.5, -0.1)], &signatures, &config).is_empty());
+    }
+}
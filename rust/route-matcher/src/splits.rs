@@ -0,0 +1,273 @@
+//! # Section Splits
+//!
+//! Splits an activity into its traversals of known sections, with timing, so
+//! apps can show interval-style splits ("Hill repeat 3: 2:41") around sections
+//! the user cares about.
+//!
+//! Unlike [`crate::SectionPortion`], which records one overlapping range per
+//! activity, every complete pass is reported: hill repeats, laps of a circuit
+//! and out-and-back rides all produce one [`SectionTraversal`] per pass.
+//!
+//! ## Algorithm
+//! Each track point near the section is projected onto the section polyline.
+//! A traversal runs from the last point at one end of the section to the first
+//! point at the other end, as long as the track doesn't leave the section in
+//! between (a few off-section points are tolerated as GPS noise).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use route_matcher::{FrequentSection, GpsPoint, SectionConfig};
+//! use route_matcher::splits::split_by_sections;
+//!
+//! let climb: Vec<GpsPoint> = (0..=10).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0005, -0.1)).collect();
+//! let section = FrequentSection {
+//!     id: "climb".to_string(),
+//!     polyline: climb.clone(),
+//!     distance_meters: 556.0,
+//!     ..Default::default()
+//! };
+//!
+//! // Up, down and up again, one point every 10 seconds
+//! let mut track = climb.clone();
+//! track.extend(climb.iter().rev().skip(1));
+//! track.extend(climb.iter().skip(1));
+//! let times: Vec<i64> = (0..track.len() as i64).map(|i| i * 10).collect();
+//!
+//! let splits = split_by_sections(&[section], &track, &times, &SectionConfig::default()).unwrap();
+//! assert_eq!(splits.len(), 3);
+//! assert_eq!(splits[1].direction, "reverse");
+//! assert_eq!(splits[2].elapsed_seconds, 100);
+//! ```
+
+use crate::geo_utils::{compute_bounds, meters_to_degrees, polyline_length, project_point_onto_route};
+use crate::{FrequentSection, GpsPoint, SectionConfig};
+
+/// Consecutive off-section points tolerated within a traversal
+const MAX_GAP: usize = 3;
+
+/// One pass of an activity over a section
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "ffi", derive(uniffi::Record))]
+pub struct SectionTraversal {
+    /// Section ID
+    pub section_id: String,
+    /// Start index into the activity's full track
+    pub start_index: u32,
+    /// End index into the activity's full track (exclusive)
+    pub end_index: u32,
+    /// Direction relative to the section polyline: "same" or "reverse"
+    pub direction: String,
+    /// Timestamp of the first point (same unit and epoch as the input, seconds)
+    pub start_time: i64,
+    /// Timestamp of the last point
+    pub end_time: i64,
+    /// Time spent on the section in seconds
+    pub elapsed_seconds: i64,
+    /// Distance covered by the sub-track in meters
+    pub distance_meters: f64,
+    /// Average speed in m/s (0 if no time elapsed)
+    pub average_speed: f64,
+    /// The activity's points for this traversal
+    pub points: Vec<GpsPoint>,
+}
+
+/// Split an activity track into its traversals of `sections`, ordered by
+/// position in the track.
+///
+/// `times` holds one timestamp (seconds) per track point; returns an error if
+/// the lengths differ. Uses each section's adaptive threshold
+/// ([`FrequentSection::proximity_threshold`], falling back to
+/// [`SectionConfig::proximity_threshold`]) both for how far the track may
+/// stray from the section and how close it must get to each end.
+pub fn split_by_sections(
+    sections: &[FrequentSection],
+    track: &[GpsPoint],
+    times: &[i64],
+    config: &SectionConfig,
+) -> Result<Vec<SectionTraversal>, String> {
+    if track.len() != times.len() {
+        return Err(format!(
+            "Track has {} points but {} timestamps",
+            track.len(),
+            times.len()
+        ));
+    }
+
+    let mut traversals: Vec<SectionTraversal> = sections
+        .iter()
+        .flat_map(|section| find_traversals(section, track, section.proximity_threshold(config)))
+        .map(|(section, start, end, direction)| {
+            let points = track[start..end].to_vec();
+            let distance_meters = polyline_length(&points);
+            let elapsed_seconds = times[end - 1] - times[start];
+            SectionTraversal {
+                section_id: section.id.clone(),
+                start_index: start as u32,
+                end_index: end as u32,
+                direction: direction.to_string(),
+                start_time: times[start],
+                end_time: times[end - 1],
+                elapsed_seconds,
+                distance_meters,
+                average_speed: if elapsed_seconds > 0 { distance_meters / elapsed_seconds as f64 } else { 0.0 },
+                points,
+            }
+        })
+        .collect();
+
+    traversals.sort_by_key(|t| (t.start_index, t.end_index));
+    Ok(traversals)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SectionEnd {
+    Start,
+    End,
+}
+
+/// Find (start, exclusive end, direction) of every complete pass over a section.
+fn find_traversals<'a>(
+    section: &'a FrequentSection,
+    track: &[GpsPoint],
+    threshold: f64,
+) -> Vec<(&'a FrequentSection, usize, usize, &'static str)> {
+    let polyline = &section.polyline;
+    if polyline.len() < 2 || track.len() < 2 {
+        return Vec::new();
+    }
+
+    let length = polyline_length(polyline);
+    let end_tolerance = threshold.min(length / 4.0);
+    let Some(bounds) = compute_bounds(polyline) else {
+        return Vec::new();
+    };
+    let margin = meters_to_degrees(threshold, bounds.min_lat.abs().max(bounds.max_lat.abs()));
+
+    let mut traversals = Vec::new();
+    let mut anchor: Option<(usize, SectionEnd)> = None;
+    let mut gap = 0;
+
+    for (i, point) in track.iter().enumerate() {
+        // Tracks crossing the antimeridian skip the prefilter
+        let in_bounds = bounds.min_lng > bounds.max_lng
+            || (point.latitude >= bounds.min_lat - margin
+                && point.latitude <= bounds.max_lat + margin
+                && point.longitude >= bounds.min_lng - margin
+                && point.longitude <= bounds.max_lng + margin);
+        let projection = if in_bounds {
+            project_point_onto_route(point, polyline).filter(|p| p.offset <= threshold)
+        } else {
+            None
+        };
+
+        let Some(projection) = projection else {
+            gap += 1;
+            if gap > MAX_GAP {
+                anchor = None;
+            }
+            continue;
+        };
+        gap = 0;
+
+        let at_start = projection.distance_along <= end_tolerance;
+        let at_end = projection.distance_along >= length - end_tolerance;
+        let reached = match (at_start, at_end) {
+            (true, false) => SectionEnd::Start,
+            (false, true) => SectionEnd::End,
+            _ => continue,
+        };
+
+        if let Some((start, from)) = anchor {
+            if from != reached {
+                let direction = if from == SectionEnd::Start { "same" } else { "reverse" };
+                traversals.push((section, start, i + 1, direction));
+            }
+        }
+        anchor = Some((i, reached));
+    }
+
+    traversals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: usize, to: usize) -> Vec<GpsPoint> {
+        (from..=to).map(|i| GpsPoint::new(51.5 + i as f64 * 0.0005, -0.1)).collect()
+    }
+
+    fn section(id: &str, polyline: Vec<GpsPoint>) -> FrequentSection {
+        FrequentSection {
+            id: id.to_string(),
+            distance_meters: polyline_length(&polyline),
+            polyline,
+            ..Default::default()
+        }
+    }
+
+    fn seconds(track: &[GpsPoint]) -> Vec<i64> {
+        (0..track.len() as i64).collect()
+    }
+
+    #[test]
+    fn test_laps_with_detour_between_passes() {
+        // Section covers points 2..=8 of the line; the track rides the line,
+        // detours far away, comes back to the start and rides it again
+        let sec = section("s", line(2, 8));
+        let mut track = line(0, 10);
+        track.extend((0..10).map(|i| GpsPoint::new(51.51, -0.09 + i as f64 * 0.001)));
+        track.extend(line(0, 10));
+
+        let splits = split_by_sections(&[sec], &track, &seconds(&track), &SectionConfig::default()).unwrap();
+        assert_eq!(splits.len(), 2);
+        for split in &splits {
+            assert_eq!(split.direction, "same");
+            assert_eq!(split.section_id, "s");
+            assert!((split.distance_meters - 333.0).abs() < 60.0);
+            assert_eq!(split.points.len(), (split.end_index - split.start_index) as usize);
+            assert!(split.average_speed > 0.0);
+        }
+        assert!(splits[1].start_index > 20);
+    }
+
+    #[test]
+    fn test_partial_pass_is_not_a_traversal() {
+        let sec = section("s", line(0, 10));
+        // Turns around halfway
+        let mut track = line(0, 5);
+        track.extend(line(0, 4).into_iter().rev());
+
+        let splits = split_by_sections(&[sec], &track, &seconds(&track), &SectionConfig::default()).unwrap();
+        assert!(splits.is_empty());
+    }
+
+    #[test]
+    fn test_multiple_sections_and_length_mismatch() {
+        let first = section("first", line(0, 4));
+        let second = section("second", line(6, 10));
+        let track = line(0, 10);
+        let config = SectionConfig::default();
+
+        let splits = split_by_sections(&[second.clone(), first], &track, &seconds(&track), &config).unwrap();
+        let ids: Vec<&str> = splits.iter().map(|s| s.section_id.as_str()).collect();
+        assert_eq!(ids, vec!["first", "second"]);
+        assert_eq!(splits[1].start_time, 6);
+
+        assert!(split_by_sections(&[second], &track, &[0, 1], &config).is_err());
+    }
+
+    #[test]
+    fn test_uses_section_matching_threshold() {
+        // Ridden ~35m east of the section line
+        let track: Vec<GpsPoint> = line(0, 10).iter().map(|p| GpsPoint::new(p.latitude, p.longitude + 0.0005)).collect();
+        let mut sec = section("s", line(0, 10));
+        let config = SectionConfig::default();
+        assert_eq!(split_by_sections(&[sec.clone()], &track, &seconds(&track), &config).unwrap().len(), 1);
+
+        // A well-observed section with a tight consensus no longer matches
+        sec.matching_threshold = Some(20.0);
+        assert!(split_by_sections(&[sec], &track, &seconds(&track), &config).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! # Telemetry
+//!
+//! Spans around the main pipelines: `signature_create`, `group`,
+//! `detect_sections` and `fetch`. Each carries its input and result counts and
+//! an `elapsed_ms` duration.
+//!
+//! With the `tracing` feature they are `tracing` spans, so desktop and server
+//! users can profile with their existing subscribers. If no subscriber is
+//! installed, `tracing` forwards to `log`, which keeps android_logger working.
+//! Without the feature, each span logs a single summary line through `log`
+//! when it finishes.
+
+use std::time::{Duration, Instant};
+
+/// Open a [`PipelineSpan`]: `pipeline_span!("group", signatures = n; groups)`.
+///
+/// Fields before `;` are recorded up front; those after are declared for
+/// [`PipelineSpan::record`] once the results are known.
+#[cfg(feature = "tracing")]
+macro_rules! pipeline_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(; $($result:ident),*)?) => {
+        $crate::telemetry::PipelineSpan::new(
+            $name,
+            vec![$((stringify!($field), $value as u64)),*],
+            tracing::info_span!(
+                $name,
+                $($field = $value as u64,)*
+                $($($result = tracing::field::Empty,)*)?
+                elapsed_ms = tracing::field::Empty
+            ),
+        )
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! pipeline_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(; $($result:ident),*)?) => {
+        $crate::telemetry::PipelineSpan::new($name, vec![$((stringify!($field), $value as u64)),*])
+    };
+}
+
+pub(crate) use pipeline_span;
+
+/// A timed pipeline stage with count fields.
+pub(crate) struct PipelineSpan {
+    name: &'static str,
+    // Only read by the `log` summary
+    #[cfg_attr(feature = "tracing", allow(dead_code))]
+    fields: Vec<(&'static str, u64)>,
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl PipelineSpan {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn new(name: &'static str, fields: Vec<(&'static str, u64)>, span: tracing::Span) -> Self {
+        Self { name, fields, start: Instant::now(), span }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn new(name: &'static str, fields: Vec<(&'static str, u64)>) -> Self {
+        Self { name, fields, start: Instant::now() }
+    }
+
+    /// Run `op` inside the span.
+    pub(crate) fn in_scope<R>(&self, op: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "tracing")]
+        return self.span.in_scope(op);
+        #[cfg(not(feature = "tracing"))]
+        op()
+    }
+
+    /// Run `future` inside the span.
+    #[cfg(feature = "http")]
+    pub(crate) async fn instrument<F: std::future::Future>(&self, future: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        return tracing::Instrument::instrument(future, self.span.clone()).await;
+        #[cfg(not(feature = "tracing"))]
+        future.await
+    }
+
+    /// Record a result count declared when the span was opened.
+    pub(crate) fn record(&mut self, field: &'static str, value: usize) {
+        #[cfg(feature = "tracing")]
+        self.span.record(field, value as u64);
+        self.fields.push((field, value as u64));
+    }
+
+    /// Close the span, recording and returning its duration.
+    pub(crate) fn finish(self) -> Duration {
+        let elapsed = self.start.elapsed();
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("elapsed_ms", elapsed.as_millis() as u64);
+            self.span.in_scope(|| tracing::info!(elapsed_ms = elapsed.as_millis() as u64, "{} finished", self.name));
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        {
+            let fields: Vec<String> = self.fields.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            log::info!("[RouteMatcherRust] {} {} in {:?}", self.name, fields.join(" "), elapsed);
+        }
+
+        elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_span_records_results() {
+        let mut span = pipeline_span!("group", signatures = 3usize; groups);
+        let doubled = span.in_scope(|| 21 * 2);
+        span.record("groups", 2);
+
+        assert_eq!(doubled, 42);
+        assert_eq!(span.fields, vec![("signatures", 3), ("groups", 2)]);
+        span.finish();
+    }
+}
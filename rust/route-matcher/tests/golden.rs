@@ -0,0 +1,144 @@
+//! Golden-file regression tests over the GPX fixtures in `tests/fixtures/golden`.
+//!
+//! Route groups and frequent sections for the whole fixture set are compared
+//! against `expected.json`. After an intentional algorithm change, review the
+//! difference and re-baseline with:
+//!
+//! ```bash
+//! UPDATE_GOLDEN=1 cargo test --test golden
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use route_matcher::import::import_file;
+use route_matcher::{
+    detect_sections_from_tracks, group_signatures, GpsPoint, MatchConfig, RouteSignature, SectionConfig,
+};
+use serde_json::{json, Value};
+
+/// Relative tolerance for section lengths, which shift slightly with any
+/// change to consensus refinement.
+const DISTANCE_TOLERANCE: f64 = 0.1;
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden")
+}
+
+/// (activity ID, full GPS track)
+type Track = (String, Vec<GpsPoint>);
+
+/// Fixture tracks keyed by file stem, with their sport types.
+fn load_fixtures() -> (Vec<Track>, HashMap<String, String>) {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(fixture_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "gpx"))
+        .collect();
+    paths.sort();
+
+    let mut tracks = Vec::new();
+    let mut sport_types = HashMap::new();
+    for path in paths {
+        let activity = import_file(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let id = path.file_stem().unwrap().to_string_lossy().into_owned();
+        sport_types.insert(id.clone(), activity.sport_type);
+        tracks.push((id, activity.points));
+    }
+    (tracks, sport_types)
+}
+
+/// Groups and sections in a stable, comparable form.
+fn compute_results() -> Value {
+    let (tracks, sport_types) = load_fixtures();
+    let config = MatchConfig::default();
+
+    let signatures: Vec<RouteSignature> = tracks
+        .iter()
+        .filter_map(|(id, points)| RouteSignature::from_points(id, points, &config))
+        .collect();
+    assert_eq!(signatures.len(), tracks.len(), "every fixture should produce a signature");
+
+    let groups = group_signatures(&signatures, &config);
+    let mut group_ids: Vec<Vec<String>> = groups
+        .iter()
+        .map(|g| {
+            let mut ids = g.activity_ids.clone();
+            ids.sort();
+            ids
+        })
+        .collect();
+    group_ids.sort();
+
+    let sections = detect_sections_from_tracks(&tracks, &sport_types, &groups, &SectionConfig::default());
+    let mut section_values: Vec<Value> = sections
+        .iter()
+        .map(|s| {
+            let mut ids = s.activity_ids.clone();
+            ids.sort();
+            json!({
+                "sport_type": s.sport_type,
+                "activity_ids": ids,
+                "distance_meters": s.distance_meters.round(),
+            })
+        })
+        .collect();
+    section_values.sort_by_key(|s| s.to_string());
+
+    json!({ "groups": group_ids, "sections": section_values })
+}
+
+fn sections_match(actual: &[Value], expected: &[Value]) -> bool {
+    actual.len() == expected.len()
+        && actual.iter().zip(expected).all(|(a, e)| {
+            let (da, de) = (a["distance_meters"].as_f64().unwrap(), e["distance_meters"].as_f64().unwrap());
+            a["sport_type"] == e["sport_type"]
+                && a["activity_ids"] == e["activity_ids"]
+                && (da - de).abs() <= de * DISTANCE_TOLERANCE
+        })
+}
+
+#[test]
+fn test_golden_groups_and_sections() {
+    let actual = compute_results();
+    let expected_path = fixture_dir().join("expected.json");
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&expected_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(&std::fs::read_to_string(&expected_path).unwrap()).unwrap();
+    assert_eq!(
+        actual["groups"], expected["groups"],
+        "route groups changed; re-baseline with UPDATE_GOLDEN=1 if intended"
+    );
+    assert!(
+        sections_match(actual["sections"].as_array().unwrap(), expected["sections"].as_array().unwrap()),
+        "sections changed; re-baseline with UPDATE_GOLDEN=1 if intended\nactual: {:#}\nexpected: {:#}",
+        actual["sections"],
+        expected["sections"]
+    );
+}
+
+#[test]
+fn test_golden_scenarios() {
+    // Properties the baseline must keep regardless of tuning
+    let actual = compute_results();
+    let groups: Vec<Vec<String>> = serde_json::from_value(actual["groups"].clone()).unwrap();
+    let group_of = |id: &str| groups.iter().position(|g| g.iter().any(|a| a == id)).unwrap();
+
+    // Repeated commutes are one route, in either direction
+    assert_eq!(group_of("commute_1"), group_of("commute_3"));
+    assert_eq!(group_of("commute_1"), group_of("commute_home"));
+    // Unrelated activities stay apart
+    assert_ne!(group_of("commute_1"), group_of("run_out_and_back_1"));
+    assert_ne!(group_of("commute_1"), group_of("mtb_laps_3"));
+
+    // The noisy city ride shares road with the commute
+    let sections = actual["sections"].as_array().unwrap();
+    assert!(sections.iter().any(|s| {
+        let ids = s["activity_ids"].as_array().unwrap();
+        ids.iter().any(|id| id == "city_ride_noisy") && ids.iter().any(|id| id == "commute_1")
+    }));
+}